@@ -0,0 +1,96 @@
+//! # tylax-api
+//!
+//! The supported, semver-stable surface of Tylax. The `tylax` crate and the
+//! `tylax-*` pipeline crates behind it expose their internals so they can be
+//! shared across the workspace; depend on this crate instead when building a
+//! tool on top of the converter.
+//!
+//! ```rust,no_run
+//! use tylax_api::{render_document, typst_to_ir, LatexRenderOptions};
+//!
+//! let doc = typst_to_ir("= Introduction\n\nHello, *world*!");
+//! for loss in &doc.losses {
+//!     eprintln!("{}: {}", loss.kind, loss.message);
+//! }
+//! let latex = render_document(
+//!     &doc,
+//!     LatexRenderOptions {
+//!         full_document: true,
+//!         ..LatexRenderOptions::default()
+//!     },
+//! );
+//! assert!(latex.contains("\\section{Introduction}"));
+//! ```
+//!
+//! ## Stability
+//!
+//! Versions of this crate follow semver. Within a major version:
+//!
+//! - Every item exported here keeps its name, path and signature. The
+//!   underlying crates may rename or move things between their own
+//!   releases; this crate absorbs that.
+//! - Options structs may gain fields. Build them from `Default` with
+//!   `..Default::default()` rather than listing every field.
+//! - The IR enums ([`ir::Block`], [`ir::Inline`]), option enums and error
+//!   enums may gain variants; they are `#[non_exhaustive]`, so matches over
+//!   them need a wildcard arm.
+//! - Only the items listed here are supported. [`ir`] names the document
+//!   model one by one rather than re-exporting all of `tylax-ir`.
+//! - Conversion output is not part of the contract: a minor release may
+//!   produce different (better) LaTeX or Typst for the same input, and may
+//!   report different losses.
+//! - The JSON form of the IR ([`ir_to_json`]) only gains optional fields, so
+//!   documents serialized by an older release still load.
+
+// Math and document conversion without the IR.
+pub use tylax::{
+    latex_document_to_typst, latex_document_to_typst_with_options, latex_to_typst,
+    latex_to_typst_with_options, typst_document_to_latex, typst_to_latex,
+    typst_to_latex_with_options, L2TOptions, T2LOptions,
+};
+
+// The IR pipeline: parse into a `Document`, inspect or edit it, render it.
+pub use tylax::{
+    ir_from_json, ir_to_json, ir_to_latex, ir_to_typst, latex_to_ir, latex_to_ir_strict,
+    latex_to_ir_with_limits, latex_to_ir_with_report, latex_to_typst_ir,
+    latex_to_typst_ir_with_report, typst_to_ir, typst_to_latex_ir, typst_to_latex_ir_strict,
+    typst_to_latex_ir_with_limits, typst_to_latex_ir_with_report,
+};
+
+// Counts of what a document holds, to compare a source with its conversion.
+pub use tylax_ir::{stats, DocStats, HeadingNode};
+
+// Bounds for converting untrusted or very large input.
+pub use tylax_ir::{CancellationToken, ConversionLimits};
+
+// LaTeX rendering of IR documents and its options.
+pub use tylax_latex_backend::{
+    format_latex, render_document, CodeBackend, EncodingMode, FormatOptions, LatexRenderOptions,
+    MathDelimiters, QuoteStyle, TableCaptionPosition, TableStyle, TheoremNumbering,
+};
+
+// Reports of what a conversion could not carry over.
+pub use tylax::utils::loss::SourceSpan;
+pub use tylax::{
+    ConversionError, ConversionReport, ConversionResult, DiagnosticSeverity, FidelityScore,
+    LossKind, LossRecord, LossReport, TylaxError, TylaxResult,
+};
+
+pub use tylax_ir::{Document, Loss, Severity};
+
+/// The document IR shared by the frontends and backends: the document
+/// model, the queries over it and the visitor that walks it. The
+/// renderers' own helpers, such as the conversion budget and the table
+/// grid, are not part of it.
+pub mod ir {
+    pub use tylax_ir::{
+        partition, tables, visit_mut, walk_block, walk_blocks, walk_document, walk_figure,
+        walk_inline, walk_inlines, walk_table, Algorithm, AlgorithmKeyword, AlgorithmStep,
+        Alignment, Author, Block, BlockBlock, BlockHints, BoxBlock, CaptionPosition, Citation,
+        CiteForm, ColumnWidth, Columns, Document, DocumentMeta, EnvironmentBlock, Figure,
+        FigureContent, Grid, Image, Inline, LabeledTable, LinkTarget, ListKind, Loss, MathBlock,
+        OutlineTarget, PageDecoration, PageMargin, Part, ReferenceIssue, ReferenceIssueKind,
+        RuleAxis, Severity, Side, SideBySide, Slide, Span, Table, TableCell, TableRule, TermItem,
+        VerticalAlignment, Visitor, VisitorMut,
+    };
+}
@@ -0,0 +1,628 @@
+//! Colors as Typst and LaTeX write them. A [`Color`] is read from the
+//! syntax of either and written in the syntax of either, so both directions
+//! of the conversion agree on what a name stands for and on how mixes and
+//! values are written.
+//!
+//! Typst's named colors have a counterpart in xcolor: the color of the same
+//! name where xcolor has one, `cyan` for `aqua`, a mix such as
+//! `blue!50!black` for `navy`. The shades differ; the names are kept, since
+//! `red` is expected to stay `red`.
+
+/// A color Typst or xcolor knows by name.
+#[derive(Debug, PartialEq, Eq)]
+pub struct NamedColor {
+    typst: Option<&'static str>,
+    /// Its name in xcolor without package options, or the expression that
+    /// stands for it.
+    latex: Option<&'static str>,
+    /// Its value, Typst's where Typst names it and xcolor's otherwise.
+    rgb: [u8; 3],
+}
+
+const fn both(typst: &'static str, latex: &'static str, rgb: u32) -> NamedColor {
+    NamedColor {
+        typst: Some(typst),
+        latex: Some(latex),
+        rgb: split_rgb(rgb),
+    }
+}
+
+const fn latex_only(latex: &'static str, rgb: u32) -> NamedColor {
+    NamedColor {
+        typst: None,
+        latex: Some(latex),
+        rgb: split_rgb(rgb),
+    }
+}
+
+const fn split_rgb(rgb: u32) -> [u8; 3] {
+    [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]
+}
+
+const BLACK: NamedColor = both("black", "black", 0x000000);
+const WHITE: NamedColor = both("white", "white", 0xFFFFFF);
+
+const NAMED: &[NamedColor] = &[
+    BLACK,
+    both("gray", "gray", 0xAAAAAA),
+    both("silver", "gray!50", 0xDDDDDD),
+    WHITE,
+    both("navy", "blue!50!black", 0x001F3F),
+    both("blue", "blue", 0x0074D9),
+    both("aqua", "cyan", 0x7FDBFF),
+    both("teal", "teal", 0x39CCCC),
+    NamedColor {
+        typst: Some("eastern"),
+        latex: None,
+        rgb: split_rgb(0x239DAD),
+    },
+    both("purple", "purple", 0xB10DC9),
+    both("fuchsia", "magenta", 0xF012BE),
+    both("maroon", "red!50!black", 0x85144B),
+    both("red", "red", 0xFF4136),
+    both("orange", "orange", 0xFF851B),
+    both("yellow", "yellow", 0xFFDC00),
+    both("olive", "olive", 0x3D9970),
+    both("green", "green", 0x2ECC40),
+    both("lime", "lime", 0x01FF70),
+    latex_only("pink", 0xFFBFBF),
+    latex_only("brown", 0xBF8040),
+    latex_only("darkgray", 0x404040),
+    latex_only("lightgray", 0xBFBFBF),
+    latex_only("violet", 0x800080),
+];
+
+/// xcolor's `dvipsnames`, which are given by value: the option that loads
+/// them may not be set where the color ends up. Those named like a base
+/// color, such as `Red`, are found as that color.
+const DVIPS_NAMES: &[(&str, u32)] = &[
+    ("Apricot", 0xFBB982),
+    ("Aquamarine", 0x00B5BE),
+    ("Bittersweet", 0xC04F17),
+    ("BlueGreen", 0x00B5BE),
+    ("BlueViolet", 0x473992),
+    ("BrickRed", 0xB6321C),
+    ("Brown", 0x792500),
+    ("BurntOrange", 0xF7921D),
+    ("CadetBlue", 0x74729A),
+    ("CarnationPink", 0xF282B4),
+    ("Cerulean", 0x00A2E3),
+    ("CornflowerBlue", 0x41B0E4),
+    ("Dandelion", 0xFDBC42),
+    ("DarkOrchid", 0xA4538A),
+    ("Emerald", 0x00A99D),
+    ("ForestGreen", 0x009B55),
+    ("Fuchsia", 0x8C368C),
+    ("Goldenrod", 0xFFDF42),
+    ("GreenYellow", 0xDFE674),
+    ("JungleGreen", 0x00A99A),
+    ("Lavender", 0xF49EC4),
+    ("LimeGreen", 0x8DC73E),
+    ("Mahogany", 0xA9341F),
+    ("Maroon", 0xAF3235),
+    ("Melon", 0xF89E7B),
+    ("MidnightBlue", 0x006795),
+    ("Mulberry", 0xA93C93),
+    ("NavyBlue", 0x006EB8),
+    ("OliveGreen", 0x3C8031),
+    ("OrangeRed", 0xED135A),
+    ("Orchid", 0xAF72B0),
+    ("Peach", 0xF7965A),
+    ("Periwinkle", 0x7977B8),
+    ("PineGreen", 0x008B72),
+    ("Plum", 0x92268F),
+    ("ProcessBlue", 0x00B0F0),
+    ("RawSienna", 0x974006),
+    ("RedOrange", 0xF26035),
+    ("RedViolet", 0xA1246B),
+    ("Rhodamine", 0xEF559F),
+    ("RoyalBlue", 0x0071BC),
+    ("RoyalPurple", 0x613F99),
+    ("RubineRed", 0xED017D),
+    ("Salmon", 0xF69289),
+    ("SeaGreen", 0x3FBC9D),
+    ("Sepia", 0x671800),
+    ("SkyBlue", 0x46C5DD),
+    ("SpringGreen", 0xC6DC67),
+    ("Tan", 0xDA9D76),
+    ("TealBlue", 0x00AEB3),
+    ("Thistle", 0xD883B7),
+    ("Turquoise", 0x00B4CE),
+    ("Violet", 0x58429B),
+    ("VioletRed", 0xEF58A0),
+    ("WildStrawberry", 0xEE2967),
+    ("YellowGreen", 0x98CC70),
+    ("YellowOrange", 0xFAA21A),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Color {
+    Named(&'static NamedColor),
+    Rgb([u8; 3]),
+    /// A gray, from black at 0 to white at 255.
+    Luma(u8),
+    /// `percent` of `first` mixed with the rest of `second`, as xcolor's
+    /// `red!30!white`.
+    Mix {
+        first: Box<Color>,
+        percent: f64,
+        second: Box<Color>,
+    },
+    /// A color the document names itself, with `\definecolor` or a Typst
+    /// variable.
+    Custom(String),
+}
+
+impl Color {
+    /// A color Typst or xcolor knows by `name`, in either's spelling: `red`,
+    /// `aqua`, `cyan`, `ForestGreen`. Case and `grey` for `gray` are
+    /// forgiven where nothing matches as written.
+    pub fn from_name(name: &str) -> Option<Color> {
+        let name = name.trim();
+        lookup_name(name).or_else(|| lookup_name(&name.to_lowercase().replace("grey", "gray")))
+    }
+
+    /// A color as Typst writes it: `red`, `rgb("#ff4136")`, `rgb(255, 0,
+    /// 0)`, `luma(50%)`, `cmyk(..)`, `red.lighten(20%)`, `color.mix(..)`
+    /// or a bare `"#ff4136"`. Other identifiers are taken as the
+    /// document's own colors.
+    pub fn parse_typst(raw: &str) -> Option<Color> {
+        let raw = raw.trim();
+        if let Some(hex) = raw.trim_matches('"').strip_prefix('#') {
+            return parse_hex(hex).map(Color::Rgb);
+        }
+        if let Some((callee, args)) = split_call(raw) {
+            let args = positional_args(args);
+            return match callee.rsplit_once('.') {
+                Some(("color", "mix")) => parse_typst_mix(&args),
+                Some(("color", function)) => parse_typst_function(function, &args),
+                Some((base, method)) => {
+                    let base = Color::parse_typst(base)?;
+                    let amount = args.first().and_then(|arg| parse_percent(arg));
+                    Some(match (method, amount) {
+                        ("lighten", Some(amount)) => base.mix(100.0 - amount, Color::Named(&WHITE)),
+                        ("darken", Some(amount)) => base.mix(100.0 - amount, Color::Named(&BLACK)),
+                        // Transparency and saturation have no counterpart;
+                        // the color is kept as it is.
+                        _ => base,
+                    })
+                }
+                None => parse_typst_function(callee, &args),
+            };
+        }
+        if !is_identifier(raw) {
+            return None;
+        }
+        Some(Color::from_name(raw).unwrap_or_else(|| Color::Custom(raw.to_string())))
+    }
+
+    /// A color as xcolor writes it: a name, `#RRGGBB`, or a mix such as
+    /// `blue!30`, `red!50!black` or `red!50!black!20!white`. Braces around
+    /// it are dropped. Other names are taken as the document's own colors.
+    pub fn parse_latex(raw: &str) -> Option<Color> {
+        let raw = raw.trim();
+        let raw = raw
+            .strip_prefix('{')
+            .and_then(|raw| raw.strip_suffix('}'))
+            .unwrap_or(raw);
+        let mut parts = raw.split('!');
+        let mut color = parse_latex_name(parts.next()?)?;
+        while let Some(percent) = parts.next() {
+            let percent = percent.trim().parse::<f64>().ok()?;
+            let other = match parts.next() {
+                Some(name) => parse_latex_name(name)?,
+                None => Color::Named(&WHITE),
+            };
+            color = color.mix(percent, other);
+        }
+        Some(color)
+    }
+
+    /// A color given in one of xcolor's models, as in
+    /// `\definecolor{name}{model}{spec}` or `\textcolor[model]{spec}`:
+    /// `RGB`, `rgb`, `HTML`, `gray` or `cmyk`. Any other model reads `spec`
+    /// as [`Color::parse_latex`] does.
+    pub fn parse_latex_model(model: &str, spec: &str) -> Option<Color> {
+        let spec = spec.trim();
+        let numbers = || {
+            spec.split(',')
+                .map(|value| value.trim().parse::<f64>().ok())
+                .collect::<Option<Vec<f64>>>()
+        };
+        match model.trim() {
+            "RGB" => match numbers()?[..] {
+                [r, g, b] => Some(Color::Rgb([byte(r), byte(g), byte(b)])),
+                _ => None,
+            },
+            "rgb" => match numbers()?[..] {
+                [r, g, b] => Some(Color::Rgb([
+                    byte(r * 255.0),
+                    byte(g * 255.0),
+                    byte(b * 255.0),
+                ])),
+                _ => None,
+            },
+            "HTML" => parse_hex(spec).map(Color::Rgb),
+            "gray" => match numbers()?[..] {
+                [value] => Some(Color::Luma(byte(value * 255.0))),
+                _ => None,
+            },
+            "cmyk" => match numbers()?[..] {
+                [c, m, y, k] => Some(Color::Rgb(cmyk_to_rgb(c, m, y, k))),
+                _ => None,
+            },
+            _ => Color::parse_latex(spec),
+        }
+    }
+
+    /// `percent` of the color mixed with the rest of `other`.
+    pub fn mix(self, percent: f64, other: Color) -> Color {
+        Color::Mix {
+            first: Box::new(self),
+            percent,
+            second: Box::new(other),
+        }
+    }
+
+    /// The color's value; `None` where it depends on a color of the
+    /// document's own.
+    pub fn to_rgb(&self) -> Option<[u8; 3]> {
+        match self {
+            Color::Named(named) => Some(named.rgb),
+            Color::Rgb(rgb) => Some(*rgb),
+            Color::Luma(value) => Some([*value; 3]),
+            Color::Mix {
+                first,
+                percent,
+                second,
+            } => {
+                let (first, second) = (first.to_rgb()?, second.to_rgb()?);
+                let share = (percent / 100.0).clamp(0.0, 1.0);
+                let channel = |i: usize| {
+                    byte(f64::from(first[i]) * share + f64::from(second[i]) * (1.0 - share))
+                };
+                Some([channel(0), channel(1), channel(2)])
+            }
+            Color::Custom(_) => None,
+        }
+    }
+
+    /// The color in Typst syntax. A color Typst has no name for is written
+    /// by its value, a mix with `color.mix`.
+    pub fn to_typst(&self) -> String {
+        match self {
+            Color::Named(NamedColor {
+                typst: Some(name), ..
+            }) => name.to_string(),
+            Color::Named(NamedColor { rgb, .. }) | Color::Rgb(rgb) => {
+                format!("rgb(\"#{}\")", hex(*rgb))
+            }
+            Color::Luma(value) => format!("luma({})", value),
+            Color::Mix {
+                first,
+                percent,
+                second,
+            } => format!(
+                "color.mix(({}, {}%), ({}, {}%))",
+                first.to_typst(),
+                format_percent(*percent),
+                second.to_typst(),
+                format_percent(100.0 - percent)
+            ),
+            Color::Custom(name) => name.clone(),
+        }
+    }
+
+    /// The color as an xcolor expression, as `\color{..}` takes it
+    /// without a model: a name, or a mix of names such as `red!50!black`.
+    /// `None` for a color given by its value.
+    pub fn latex_expression(&self) -> Option<String> {
+        match self {
+            Color::Named(named) => named.latex.map(str::to_string),
+            Color::Custom(name) => Some(name.clone()),
+            Color::Mix {
+                first,
+                percent,
+                second,
+            } => {
+                let first = first.latex_expression()?;
+                // xcolor mixes into a name, not into another mix.
+                let second = second
+                    .latex_expression()
+                    .filter(|second| !second.contains('!'))?;
+                Some(format!("{}!{}!{}", first, format_percent(*percent), second))
+            }
+            Color::Rgb(_) | Color::Luma(_) => None,
+        }
+    }
+
+    /// The color in LaTeX syntax, with the xcolor model it is given in,
+    /// as in `\textcolor[HTML]{FF4136}`: an expression without a model
+    /// where there is one, its value in `HTML` otherwise.
+    pub fn to_latex(&self) -> (Option<&'static str>, String) {
+        if let Some(expression) = self.latex_expression() {
+            return (None, expression);
+        }
+        match (self.to_rgb(), self) {
+            (Some(rgb), _) => (Some("HTML"), hex(rgb)),
+            // A mix of one of the document's colors with a color xcolor
+            // cannot name: the first of the two stands for it.
+            (None, Color::Mix { first, .. }) => first.to_latex(),
+            (None, _) => (None, "black".to_string()),
+        }
+    }
+}
+
+fn lookup_name(name: &str) -> Option<Color> {
+    if let Some(named) = NAMED
+        .iter()
+        .find(|named| named.typst == Some(name) || named.latex == Some(name))
+    {
+        return Some(Color::Named(named));
+    }
+    DVIPS_NAMES
+        .iter()
+        .find(|(dvips, _)| *dvips == name)
+        .map(|(_, rgb)| Color::Rgb(split_rgb(*rgb)))
+}
+
+fn parse_latex_name(name: &str) -> Option<Color> {
+    let name = name.trim();
+    if let Some(hex) = name.strip_prefix('#') {
+        return parse_hex(hex).map(Color::Rgb);
+    }
+    if !is_identifier(name) {
+        return None;
+    }
+    Some(Color::from_name(name).unwrap_or_else(|| Color::Custom(name.to_string())))
+}
+
+fn parse_typst_function(function: &str, args: &[&str]) -> Option<Color> {
+    match (function, args) {
+        ("rgb", [hex]) => parse_hex(hex.trim_matches('"').trim_start_matches('#')).map(Color::Rgb),
+        ("rgb", [r, g, b, ..]) => Some(Color::Rgb([
+            parse_component(r)?,
+            parse_component(g)?,
+            parse_component(b)?,
+        ])),
+        ("luma", [value, ..]) => parse_component(value).map(Color::Luma),
+        ("cmyk", [c, m, y, k]) => {
+            let ratio = |arg: &str| parse_percent(arg).map(|percent| percent / 100.0);
+            Some(Color::Rgb(cmyk_to_rgb(
+                ratio(c)?,
+                ratio(m)?,
+                ratio(y)?,
+                ratio(k)?,
+            )))
+        }
+        _ => None,
+    }
+}
+
+/// `color.mix((a, 30%), (b, 70%))`, or of colors without weights, which
+/// count alike.
+fn parse_typst_mix(args: &[&str]) -> Option<Color> {
+    let [first, second] = args else {
+        return None;
+    };
+    let (first, a) = parse_weighted(first)?;
+    let (second, b) = parse_weighted(second)?;
+    if a + b <= 0.0 {
+        return None;
+    }
+    Some(first.mix(100.0 * a / (a + b), second))
+}
+
+fn parse_weighted(arg: &str) -> Option<(Color, f64)> {
+    let pair = arg
+        .strip_prefix('(')
+        .and_then(|arg| arg.strip_suffix(')'))
+        .map(split_args);
+    if let Some([color, weight]) = pair.as_deref() {
+        return Some((Color::parse_typst(color)?, parse_percent(weight)?));
+    }
+    Some((Color::parse_typst(arg)?, 50.0))
+}
+
+/// A channel of `rgb(..)` or `luma(..)`: a number up to 255 or a percentage.
+fn parse_component(arg: &str) -> Option<u8> {
+    match arg.trim().strip_suffix('%') {
+        Some(percent) => Some(byte(percent.trim().parse::<f64>().ok()? * 255.0 / 100.0)),
+        None => Some(byte(arg.trim().parse().ok()?)),
+    }
+}
+
+fn parse_percent(arg: &str) -> Option<f64> {
+    arg.trim().trim_end_matches('%').trim().parse().ok()
+}
+
+/// `RGB`, `RRGGBB` or, with the alpha dropped, `RRGGBBAA`.
+fn parse_hex(hex: &str) -> Option<[u8; 3]> {
+    let hex = hex.trim();
+    if !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return None;
+    }
+    let expanded: String = match hex.len() {
+        3 | 4 => hex.chars().take(3).flat_map(|ch| [ch, ch]).collect(),
+        6 | 8 => hex[..6].to_string(),
+        _ => return None,
+    };
+    let channel = |i: usize| u8::from_str_radix(&expanded[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+fn cmyk_to_rgb(c: f64, m: f64, y: f64, k: f64) -> [u8; 3] {
+    let channel = |value: f64| byte((1.0 - value) * (1.0 - k) * 255.0);
+    [channel(c), channel(m), channel(y)]
+}
+
+fn byte(value: f64) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+fn hex(rgb: [u8; 3]) -> String {
+    format!("{:02X}{:02X}{:02X}", rgb[0], rgb[1], rgb[2])
+}
+
+/// A percentage to at most two places, without trailing zeros.
+fn format_percent(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-')
+}
+
+/// The callee and the arguments of a call such as `rgb(..)` or
+/// `red.lighten(..)`.
+fn split_call(raw: &str) -> Option<(&str, &str)> {
+    let body = raw.strip_suffix(')')?;
+    let mut depth = 0i32;
+    let mut open = None;
+    let mut in_string = false;
+    for (idx, ch) in raw.char_indices() {
+        match ch {
+            '"' => in_string = !in_string,
+            '(' if !in_string => {
+                if depth == 0 {
+                    open = Some(idx);
+                }
+                depth += 1;
+            }
+            ')' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    let open = open?;
+    let callee = raw[..open].trim();
+    (depth == 0 && !callee.is_empty()).then(|| (callee, &body[open + 1..]))
+}
+
+/// Arguments split at the commas outside parentheses and strings.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut in_string = false;
+    let mut start = 0;
+    for (idx, ch) in args.char_indices() {
+        match ch {
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                parts.push(args[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// The arguments without named ones such as `space: oklab`.
+fn positional_args(args: &str) -> Vec<&str> {
+    let mut args = split_args(args);
+    args.retain(|arg| {
+        !arg.split_once(':')
+            .is_some_and(|(key, _)| is_identifier(key.trim()))
+    });
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typst(raw: &str) -> Option<(Option<&'static str>, String)> {
+        Color::parse_typst(raw).map(|color| color.to_latex())
+    }
+
+    fn latex(raw: &str) -> Option<String> {
+        Color::parse_latex(raw).map(|color| color.to_typst())
+    }
+
+    #[test]
+    fn typst_colors_in_latex() {
+        assert_eq!(typst("red"), Some((None, "red".to_string())));
+        assert_eq!(typst("aqua"), Some((None, "cyan".to_string())));
+        assert_eq!(typst("navy"), Some((None, "blue!50!black".to_string())));
+        assert_eq!(
+            typst("rgb(\"#ff8000\")"),
+            Some((Some("HTML"), "FF8000".to_string()))
+        );
+        assert_eq!(
+            typst("\"#abc\""),
+            Some((Some("HTML"), "AABBCC".to_string()))
+        );
+        assert_eq!(
+            typst("rgb(255, 128, 0)"),
+            Some((Some("HTML"), "FF8000".to_string()))
+        );
+        assert_eq!(
+            typst("luma(50%)"),
+            Some((Some("HTML"), "808080".to_string()))
+        );
+        assert_eq!(
+            typst("red.lighten(20%)"),
+            Some((None, "red!80!white".to_string()))
+        );
+        assert_eq!(
+            typst("navy.darken(10%)"),
+            Some((None, "blue!50!black!90!black".to_string()))
+        );
+        assert_eq!(
+            typst("color.mix((blue, 30%), (white, 70%))"),
+            Some((None, "blue!30!white".to_string()))
+        );
+        assert_eq!(typst("brand"), Some((None, "brand".to_string())));
+        assert_eq!(typst("1 + 2"), None);
+    }
+
+    #[test]
+    fn latex_colors_in_typst() {
+        assert_eq!(latex("red").as_deref(), Some("red"));
+        assert_eq!(latex("cyan").as_deref(), Some("aqua"));
+        assert_eq!(latex("ForestGreen").as_deref(), Some("rgb(\"#009B55\")"));
+        assert_eq!(latex("Red").as_deref(), Some("red"));
+        assert_eq!(latex("lightgrey").as_deref(), Some("rgb(\"#BFBFBF\")"));
+        assert_eq!(latex("darkgray").as_deref(), Some("rgb(\"#404040\")"));
+        assert_eq!(
+            latex("{blue!80!black}").as_deref(),
+            Some("color.mix((blue, 80%), (black, 20%))")
+        );
+        assert_eq!(
+            latex("red!30").as_deref(),
+            Some("color.mix((red, 30%), (white, 70%))")
+        );
+        assert_eq!(latex("red!x"), None);
+        let model = |model, spec| Color::parse_latex_model(model, spec).map(|c| c.to_typst());
+        assert_eq!(
+            model("RGB", "255, 128, 0").as_deref(),
+            Some("rgb(\"#FF8000\")")
+        );
+        assert_eq!(model("rgb", "1,0.5,0").as_deref(), Some("rgb(\"#FF8000\")"));
+        assert_eq!(model("HTML", "ff5733").as_deref(), Some("rgb(\"#FF5733\")"));
+        assert_eq!(model("gray", "0.5").as_deref(), Some("luma(128)"));
+        assert_eq!(
+            model("cmyk", "0,0.5,1,0").as_deref(),
+            Some("rgb(\"#FF8000\")")
+        );
+    }
+
+    #[test]
+    fn mixes_xcolor_cannot_write_are_set_by_value() {
+        let mix = Color::parse_typst("color.mix(rgb(\"#ff0000\"), blue)").unwrap();
+        assert_eq!(mix.latex_expression(), None);
+        assert_eq!(mix.to_latex(), (Some("HTML"), "803A6D".to_string()));
+        let mix = Color::parse_latex("red!50!navy").unwrap();
+        assert_eq!(mix.latex_expression(), None);
+        assert_eq!(mix.to_latex(), (Some("HTML"), "80303B".to_string()));
+    }
+}
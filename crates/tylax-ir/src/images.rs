@@ -0,0 +1,23 @@
+//! The images of a document, for passes that move or convert the files
+//! they point to.
+
+use crate::visit_mut::walk_blocks;
+use crate::{Document, Image, Inline, VisitorMut};
+
+/// Every image of `doc`, in document order, wherever it is nested.
+pub fn images_mut(doc: &mut Document) -> Vec<&mut Image> {
+    let mut images = Images::default();
+    walk_blocks(&mut images, &mut doc.blocks);
+    images.0
+}
+
+#[derive(Default)]
+struct Images<'a>(Vec<&'a mut Image>);
+
+impl<'a> VisitorMut<'a> for Images<'a> {
+    fn visit_image(&mut self, image: &'a mut Image) {
+        self.0.push(image);
+    }
+
+    fn visit_inlines(&mut self, _inlines: &'a mut [Inline]) {}
+}
@@ -1,9 +1,46 @@
 //! Semantic intermediate representation for document conversion.
 
+mod images;
+mod limits;
+mod partition;
+mod references;
+mod stats;
+mod tables;
+mod visit;
+
+pub use images::images_mut;
+pub use limits::{Budget, CancellationToken, ConversionLimits};
+pub use partition::{partition, Part};
+pub use references::{ReferenceIssue, ReferenceIssueKind};
+pub use stats::{stats, DocStats, HeadingNode};
+pub use tables::{tables, LabeledTable, TableGrid};
+pub use visit::{
+    visit_mut, walk_block, walk_blocks, walk_document, walk_figure, walk_inline, walk_inlines,
+    walk_table, Visitor, VisitorMut,
+};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Document {
     pub blocks: Vec<Block>,
+    #[cfg_attr(feature = "serde", serde(default))]
     pub losses: Vec<Loss>,
+    /// Source spans of the top-level `blocks`, index for index. Empty when
+    /// the producing frontend does not track positions.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub spans: Vec<Option<Span>>,
+    /// Source spans of the inlines of the top-level paragraphs, block for
+    /// block and inline for inline. Other blocks have none. Empty when the
+    /// producing frontend does not track positions.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub inline_spans: Vec<Vec<Span>>,
+    /// Conversion hints for the top-level `blocks`, index for index. Empty
+    /// when the source carries none.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hints: Vec<Option<BlockHints>>,
+    /// Page and text settings that apply to the whole document.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub meta: DocumentMeta,
 }
 
 impl Document {
@@ -11,15 +48,178 @@ impl Document {
         Self {
             blocks,
             losses: Vec::new(),
+            spans: Vec::new(),
+            inline_spans: Vec::new(),
+            hints: Vec::new(),
+            meta: DocumentMeta::default(),
         }
     }
 
     pub fn with_losses(blocks: Vec<Block>, losses: Vec<Loss>) -> Self {
-        Self { blocks, losses }
+        Self {
+            blocks,
+            losses,
+            spans: Vec::new(),
+            inline_spans: Vec::new(),
+            hints: Vec::new(),
+            meta: DocumentMeta::default(),
+        }
+    }
+
+    pub fn with_spans(mut self, spans: Vec<Option<Span>>) -> Self {
+        self.spans = spans;
+        self
+    }
+
+    pub fn block_span(&self, index: usize) -> Option<Span> {
+        self.spans.get(index).copied().flatten()
+    }
+
+    pub fn with_inline_spans(mut self, inline_spans: Vec<Vec<Span>>) -> Self {
+        self.inline_spans = inline_spans;
+        self
+    }
+
+    /// The span of inline `inline` of top-level block `block`.
+    pub fn inline_span(&self, block: usize, inline: usize) -> Option<Span> {
+        self.inline_spans.get(block)?.get(inline).copied()
+    }
+
+    pub fn with_hints(mut self, hints: Vec<Option<BlockHints>>) -> Self {
+        self.hints = hints;
+        self
+    }
+
+    pub fn block_hints(&self, index: usize) -> Option<&BlockHints> {
+        self.hints.get(index).and_then(Option::as_ref)
+    }
+
+    pub fn with_meta(mut self, meta: DocumentMeta) -> Self {
+        self.meta = meta;
+        self
+    }
+}
+
+/// Settings of the whole document, from Typst's top-level `#set page(..)`,
+/// `#set text(..)`, `#set par(..)` and `#set heading(..)`, and its front
+/// matter, from a template applied with `#show: template.with(..)`. Lengths
+/// are kept as written, e.g. "2.5cm". Unset fields keep the output's
+/// defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DocumentMeta {
+    /// Paper name as Typst writes it, e.g. "a4" or "us-letter".
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub paper: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub margin: PageMargin,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub font_size: Option<String>,
+    /// Language code, e.g. "de".
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub lang: Option<String>,
+    /// Numbering pattern of headings, e.g. "1.a". Empty when headings are
+    /// not numbered.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub heading_numbering: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub justify: Option<bool>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub title: Option<Vec<Inline>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub authors: Vec<Author>,
+    /// Date as the template gives it, e.g. "March 2024".
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub date: Option<String>,
+    /// Empty when the document has no abstract.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub abstract_content: Vec<Block>,
+    /// Numbering pattern of pages, e.g. "1" or "1 of 1". Empty when pages
+    /// are not numbered.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub page_numbering: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub header: Option<PageDecoration>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub footer: Option<PageDecoration>,
+    /// Columns the whole text is set in, as with `set page(columns: 2)` or
+    /// a show rule that sets the document in `columns(..)`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub columns: Option<usize>,
+}
+
+/// A header or footer of every page, its content set left, in the center
+/// and right.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PageDecoration {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub left: Vec<Inline>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub center: Vec<Inline>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub right: Vec<Inline>,
+    /// What even pages show instead, when the header or footer alternates
+    /// on the page parity, as `if calc.odd(here().page()) [..] else [..]`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub even: Option<Box<PageDecoration>>,
+}
+
+/// An author named in the front matter.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Author {
+    pub name: String,
+    /// Department, institution and place in one line, e.g.
+    /// "Typst GmbH, Berlin".
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub affiliation: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub email: Option<String>,
+}
+
+/// Page margins, side by side.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PageMargin {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub top: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bottom: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub left: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub right: Option<String>,
+}
+
+/// How the author asked for a block to be converted, e.g. through
+/// `#metadata((tylax: (placement: "tb", wide: true))) <tylax:fig:a>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockHints {
+    /// Float placement to use instead of the default, e.g. `tb`.
+    pub placement: Option<String>,
+    /// Set the block as a float spanning all columns.
+    pub wide: bool,
+}
+
+/// Byte range `start..end` in the original source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum Block {
     Paragraph(Vec<Inline>),
     VSpace(String),
@@ -27,132 +227,451 @@ pub enum Block {
         level: u8,
         content: Vec<Inline>,
         numbered: bool,
+        label: Option<String>,
+        /// Whether the heading appears in the table of contents.
+        outlined: bool,
+        /// Whether the heading gets a PDF bookmark; `None` follows `outlined`.
+        bookmarked: Option<bool>,
     },
     List { kind: ListKind, items: Vec<Vec<Block>> },
+    TermList(Vec<TermItem>),
     MathBlock(MathBlock),
-    CodeBlock(String),
+    CodeBlock {
+        /// Language tag for syntax highlighting, as written in the source.
+        lang: Option<String>,
+        content: String,
+    },
     Quote(Vec<Block>),
-    Align { alignment: Alignment, blocks: Vec<Block> },
+    Align {
+        /// `None` when the source only aligns vertically, as in
+        /// `align(horizon)[..]`.
+        alignment: Option<Alignment>,
+        #[cfg_attr(feature = "serde", serde(default))]
+        vertical: Option<VerticalAlignment>,
+        blocks: Vec<Block>,
+    },
     Table(Table),
     Figure(Figure),
     Environment(EnvironmentBlock),
-    Bibliography { file: String, style: Option<String> },
-    Outline { title: Option<Vec<Inline>> },
+    Bibliography {
+        file: String,
+        style: Option<String>,
+        /// List every entry of the files, cited or not.
+        #[cfg_attr(feature = "serde", serde(default))]
+        full: bool,
+        /// Heading over the list: `None` keeps the default one and an empty
+        /// title sets no heading at all.
+        #[cfg_attr(feature = "serde", serde(default))]
+        title: Option<Vec<Inline>>,
+    },
+    Outline {
+        title: Option<Vec<Inline>>,
+        /// What the outline lists; headings make a table of contents.
+        #[cfg_attr(feature = "serde", serde(default))]
+        target: OutlineTarget,
+    },
     Box(BoxBlock),
     Block(BlockBlock),
     Columns(Columns),
     Grid(Grid),
+    Algorithm(Algorithm),
+    /// A presentation slide, as polylux's and touying's `#slide[..]`.
+    Slide(Slide),
+    /// Columns set next to each other, each with its own content.
+    SideBySide(SideBySide),
+    /// Source the frontend could not lower, kept verbatim so a backend can
+    /// pass it through as a marked comment instead of guessing at it.
+    Unsupported { source: String, reason: String },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Table {
     pub columns: usize,
     pub cells: Vec<TableCell>,
     pub align: Option<Vec<Alignment>>,
+    /// Vertical alignment of every cell, from the vertical part of the
+    /// table's `align`, as in `align: left + horizon`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub valign: Option<VerticalAlignment>,
     pub caption: Option<Vec<Inline>>,
     pub stroke: Option<String>,
     pub fill: Option<String>,
     pub inset: Option<String>,
+    pub label: Option<String>,
+    /// Whether the header rows repeat on every page a long table spans.
+    pub repeat_header: bool,
+    /// Width of each column when the source gives them; empty otherwise.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub widths: Vec<ColumnWidth>,
+    /// Space between the cells, on top of their inset, as in Typst's
+    /// `gutter`; `row_gutter` and `column_gutter` take precedence.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub gutter: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub row_gutter: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub column_gutter: Option<String>,
+    /// Rules the source draws on particular grid lines, beyond what
+    /// `stroke` says for the whole table.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rules: Vec<TableRule>,
+}
+
+/// Width of one table column, as in Typst's `columns: (auto, 1fr, 3cm)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum ColumnWidth {
+    /// Sized to the column's content.
+    Auto,
+    /// A share of the width the other columns leave, as the `fr` count
+    /// written in the source (`"1.5"` for `1.5fr`).
+    Fraction(String),
+    /// A fixed length or a ratio of the line, e.g. `3cm` or `30%`.
+    Length(String),
+}
+
+/// A rule along one grid line of a table, as Typst's `table.hline()` and
+/// `table.vline()` draw.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableRule {
+    pub axis: RuleAxis,
+    /// The grid line: `0` runs above the first row (or left of the first
+    /// column) and `n` below row `n - 1`.
+    pub position: usize,
+    /// First column (or row, for a vertical rule) the rule covers.
+    pub start: usize,
+    /// Column (or row) the rule stops before; `None` runs to the edge.
+    pub end: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum RuleAxis {
+    Horizontal,
+    Vertical,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TableCell {
     pub content: Vec<Inline>,
+    pub blocks: Option<Vec<Block>>,
     pub colspan: usize,
     pub rowspan: usize,
     pub align: Option<Alignment>,
+    /// Overrides the table's vertical alignment for this cell.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub valign: Option<VerticalAlignment>,
     pub is_header: bool,
+    /// Whether the cell belongs to the table's footer rows.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_footer: bool,
     pub fill: Option<String>,
     pub stroke: Option<String>,
     pub inset: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MathBlock {
     pub content: String,
     pub label: Option<String>,
+    /// Whether `content` is LaTeX rather than Typst math, as the `mitex`
+    /// package embeds it; it is set as written.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub latex: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Figure {
     pub content: FigureContent,
     pub caption: Option<Vec<Inline>>,
     pub label: Option<String>,
     pub placement: Option<String>,
+    /// Whether the figure is left out of the numbering, as with
+    /// `numbering: none`; its caption is set without a number.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unnumbered: bool,
+    /// Text between the figure's number and its caption, when the source
+    /// sets one; `None` keeps the default separator.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub caption_separator: Option<Vec<Inline>>,
+    /// Where the caption is set, when the source says; `None` leaves it to
+    /// the kind of figure.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub caption_position: Option<CaptionPosition>,
+    /// A shorter caption for the list of figures.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub short_caption: Option<Vec<Inline>>,
+    /// The numbering pattern of the figure, such as `I` or `(a)`; `None`
+    /// keeps the default `1`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub numbering: Option<String>,
+    /// The side of the text the figure is set on, with the text flowing
+    /// around it; `None` for a figure across the text.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub wrap: Option<Side>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EnvironmentBlock {
     pub name: String,
     pub title: Option<Vec<Inline>>,
     pub blocks: Vec<Block>,
+    pub label: Option<String>,
+    /// Arguments of `\begin{name}` after the title, as LaTeX source with
+    /// their delimiters: `[..]` for an optional one, `{..}` for a required
+    /// one.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub args: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum FigureContent {
-    Table(Table),
+    /// Boxed, as a table is by far the largest content; a figure otherwise
+    /// takes its size, and every block with it.
+    Table(Box<Table>),
     Image(Image),
     Raw(Vec<Block>),
+    /// Figures set side by side within the figure, each with its own
+    /// caption and label, `columns` to a row.
+    SubFigures {
+        figures: Vec<Figure>,
+        columns: usize,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Image {
     pub path: String,
     pub width: Option<String>,
     pub height: Option<String>,
     pub fit: Option<String>,
+    /// Text describing the image for readers who cannot see it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub alt: Option<String>,
+}
+
+/// One `term: description` entry of a term (description) list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TermItem {
+    pub term: Vec<Inline>,
+    pub description: Vec<Block>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum ListKind {
     Unordered,
     Ordered,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum Alignment {
     Left,
     Center,
     Right,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum CaptionPosition {
+    Top,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Typst's `top`, `horizon` and `bottom`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum VerticalAlignment {
+    Top,
+    Horizon,
+    Bottom,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum Inline {
     Text(String),
     Size { size: String, content: Vec<Inline> },
     Strong(Vec<Inline>),
     Emph(Vec<Inline>),
-    Code(String),
+    Code {
+        /// Language tag for syntax highlighting, as written in the source.
+        lang: Option<String>,
+        content: String,
+    },
     Math(String),
-    Link { text: Vec<Inline>, url: String },
+    /// Inline math written in LaTeX, as the `mitex` package's `mi` embeds
+    /// it.
+    LatexMath(String),
+    Link {
+        text: Vec<Inline>,
+        url: String,
+        /// What `url` names: a web address, a label or an email address,
+        /// the latter without its `mailto:`.
+        #[cfg_attr(feature = "serde", serde(default))]
+        target: LinkTarget,
+    },
     Ref(String),
+    /// Reference to a consecutive run of labels, e.g. figures 2 through 4.
+    RefRange { from: String, to: String },
+    /// A reference set after its own supplement, the word before the
+    /// number, as Typst's `@fig:plot[Plot]`. An empty supplement leaves the
+    /// number alone.
+    SupplementedRef { label: String, supplement: Vec<Inline> },
     Label(String),
     Cite(String),
+    /// A citation with notes, or set in another form than the default one.
+    Citation(Citation),
     Footnote(Vec<Inline>),
     Color { color: String, content: Vec<Inline> },
     RawLatex(String),
     Superscript(Vec<Inline>),
     Subscript(Vec<Inline>),
+    Underline(Vec<Inline>),
+    Overline(Vec<Inline>),
+    /// Struck-through text.
+    Strike(Vec<Inline>),
+    SmallCaps(Vec<Inline>),
+    /// Text set in upper case whatever case it is written in.
+    Uppercase(Vec<Inline>),
+    /// Text set in lower case whatever case it is written in.
+    Lowercase(Vec<Inline>),
     LineBreak,
+    /// A quotation mark set as an opening or closing one after the text
+    /// around it, as Typst's `"` and `'`; a single one may be an
+    /// apostrophe.
+    SmartQuote { double: bool },
+    /// A number, a unit, or a number with a unit. Units are kept as written,
+    /// e.g. `m/s^2` or `meter per second`.
+    Quantity {
+        value: Option<String>,
+        unit: Option<String>,
+    },
+    /// A chemical formula in mhchem notation, e.g. `2H2 + O2 -> 2H2O`.
+    Chemical(String),
+    /// The end of one step of a slide, revealing what follows on the next,
+    /// as `#pause`.
+    Pause,
+    /// The number of the current page, in a numbering pattern such as "1"
+    /// or "1 of 1", whose second counting symbol is the number of pages.
+    PageNumber(String),
+    /// The title of the current heading of a level, as a running head in a
+    /// page header shows it with `hydra(2)`.
+    RunningHead(u8),
+}
+
+/// A citation of comma-separated keys, as in [`Inline::Cite`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Citation {
+    pub keys: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub form: CiteForm,
+    /// Text set before the reference, e.g. "see".
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub prenote: Option<String>,
+    /// Text set after the reference, e.g. "p.~12".
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub postnote: Option<String>,
+}
+
+/// Where a link leads, after the destination of Typst's `link`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum LinkTarget {
+    /// A web address, as `link("https://..")` or a bare URL.
+    #[default]
+    Url,
+    /// A label of the document, as `link(<intro>)`.
+    Label,
+    /// An email address, as `link("mailto:..")`.
+    Email,
+}
+
+/// The elements an outline lists, after Typst's `outline(target: ..)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum OutlineTarget {
+    /// Headings, as a table of contents.
+    #[default]
+    Headings,
+    /// Figures, as `figure.where(kind: image)` or all figures.
+    Figures,
+    /// Tables, as `figure.where(kind: table)`.
+    Tables,
+    /// Code listings, as `figure.where(kind: raw)`.
+    Listings,
+}
+
+/// How a citation reads in the text, after Typst's `cite(form: ..)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum CiteForm {
+    /// The style's usual citation, e.g. "[1]" or "(Knuth, 1984)".
+    #[default]
+    Normal,
+    /// A citation that is part of the sentence: "Knuth (1984)".
+    Prose,
+    /// The whole reference, as the bibliography lists it.
+    Full,
+    /// Only the authors.
+    Author,
+    /// Only the year.
+    Year,
+    /// Nothing in the text; the entry is only added to the bibliography.
+    Hidden,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoxBlock {
     pub blocks: Vec<Block>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlockBlock {
     pub blocks: Vec<Block>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Columns {
     pub columns: usize,
     pub blocks: Vec<Block>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Grid {
     pub columns: usize,
     pub cells: Vec<Vec<Block>>,
@@ -161,6 +680,67 @@ pub struct Grid {
     pub column_gutter: Option<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Slide {
+    pub title: Option<Vec<Inline>>,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SideBySide {
+    /// Width of each column when the source gives them; empty otherwise.
+    pub widths: Vec<ColumnWidth>,
+    pub columns: Vec<Vec<Block>>,
+}
+
+/// Pseudocode, as written with packages such as lovelace or algo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Algorithm {
+    pub caption: Option<Vec<Inline>>,
+    pub label: Option<String>,
+    pub line_numbers: bool,
+    pub steps: Vec<AlgorithmStep>,
+}
+
+/// One line of pseudocode and the lines indented under it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AlgorithmStep {
+    pub keyword: AlgorithmKeyword,
+    /// The line without its keyword, e.g. the condition of an `if`.
+    pub content: Vec<Inline>,
+    /// A comment set at the end of the line.
+    pub comment: Option<Vec<Inline>>,
+    pub body: Vec<AlgorithmStep>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum AlgorithmKeyword {
+    Statement,
+    Require,
+    Ensure,
+    /// An `Input:` line, which algpseudocode would otherwise set as `Require:`.
+    Input,
+    /// An `Output:` line, which algpseudocode would otherwise set as `Ensure:`.
+    Output,
+    If,
+    ElseIf,
+    Else,
+    For,
+    ForAll,
+    While,
+    Repeat,
+    Until,
+    Return,
+    Function,
+    Procedure,
+}
+
 impl Inline {
     pub fn text(s: impl Into<String>) -> Self {
         Inline::Text(s.into())
@@ -168,9 +748,32 @@ impl Inline {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Loss {
     pub kind: String,
     pub message: String,
+    pub span: Option<Span>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub severity: Severity,
+    /// How the source could be written so nothing is lost, when there is a
+    /// known way.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub suggestion: Option<String>,
+}
+
+/// How much of the document a loss costs, from a note to dropped content.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[non_exhaustive]
+pub enum Severity {
+    /// Nothing of the document is lost, e.g. an ignored conversion hint.
+    Info,
+    /// The content is kept but only approximated in the output.
+    #[default]
+    Warning,
+    /// Content was dropped from the output.
+    Error,
 }
 
 impl Loss {
@@ -178,6 +781,24 @@ impl Loss {
         Self {
             kind: kind.into(),
             message: message.into(),
+            span: None,
+            severity: Severity::Warning,
+            suggestion: None,
         }
     }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
 }
@@ -0,0 +1,157 @@
+//! Bounds on a single conversion, for embedders that convert untrusted or
+//! very large input. Frontends check them as they walk the source and stop
+//! early, keeping what they have converted and recording a `limit` loss.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{Loss, Severity, Span};
+
+/// Limits of one conversion. Unset fields do not limit anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConversionLimits {
+    /// Input beyond this many bytes is cut off at the line break before it.
+    pub max_input_bytes: Option<usize>,
+    /// The conversion stops once it has produced more blocks than this,
+    /// counted at every nesting level.
+    pub max_blocks: Option<usize>,
+    /// Wall-clock time the frontend may spend. Not available on
+    /// `wasm32-unknown-unknown`, which has no clock.
+    pub timeout: Option<Duration>,
+}
+
+/// Stops a running conversion from another thread. Clones share one flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl PartialEq for CancellationToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CancellationToken {}
+
+/// The current time, where the target has a clock. `Instant::now` panics
+/// on `wasm32-unknown-unknown`, where timeouts are then not enforced.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn now() -> Option<Instant> {
+    Some(Instant::now())
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn now() -> Option<Instant> {
+    None
+}
+
+/// One conversion's progress against its limits.
+#[derive(Debug)]
+pub struct Budget {
+    limits: ConversionLimits,
+    cancel: Option<CancellationToken>,
+    started: Option<Instant>,
+    blocks: usize,
+    stopped: bool,
+}
+
+impl Budget {
+    pub fn new(limits: &ConversionLimits, cancel: Option<&CancellationToken>) -> Self {
+        Self {
+            limits: limits.clone(),
+            cancel: cancel.cloned(),
+            started: limits.timeout.and_then(|_| now()),
+            blocks: 0,
+            stopped: false,
+        }
+    }
+
+    /// A budget that never runs out.
+    pub fn unlimited() -> Self {
+        Self::new(&ConversionLimits::default(), None)
+    }
+
+    /// The part of `input` within `max_input_bytes`, ending at a line break
+    /// so no line is cut in half. The loss covers what was cut.
+    pub fn truncate_input<'a>(&self, input: &'a str) -> (&'a str, Option<Loss>) {
+        let Some(max) = self.limits.max_input_bytes else {
+            return (input, None);
+        };
+        if input.len() <= max {
+            return (input, None);
+        }
+        let mut end = max;
+        while !input.is_char_boundary(end) {
+            end -= 1;
+        }
+        let end = input[..end].rfind('\n').map_or(end, |pos| pos + 1);
+        let loss = Loss::new(
+            "limit",
+            format!(
+                "input longer than {} bytes; the {} bytes after it were dropped",
+                max,
+                input.len() - end
+            ),
+        )
+        .with_span(Span::new(end, input.len()))
+        .with_severity(Severity::Error);
+        (&input[..end], Some(loss))
+    }
+
+    /// Count blocks the frontend produced.
+    pub fn add_blocks(&mut self, count: usize) {
+        self.blocks += count;
+    }
+
+    /// Whether the conversion must stop. The first time a limit is hit a
+    /// loss is pushed to `losses`; every later call returns `true` quietly.
+    pub fn exhausted(&mut self, losses: &mut Vec<Loss>) -> bool {
+        if self.stopped {
+            return true;
+        }
+        let Some(reason) = self.reason() else {
+            return false;
+        };
+        self.stopped = true;
+        losses.push(
+            Loss::new(
+                "limit",
+                format!("{}; the content after it was dropped", reason),
+            )
+            .with_severity(Severity::Error),
+        );
+        true
+    }
+    /// Whether a limit is hit, without recording it, for code that checks
+    /// the budget where it has no losses to add to.
+    pub fn is_exhausted(&self) -> bool {
+        self.stopped || self.reason().is_some()
+    }
+
+    fn reason(&self) -> Option<String> {
+        if self.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+            Some("the conversion was cancelled".to_string())
+        } else if let Some(max) = self.limits.max_blocks.filter(|max| self.blocks > *max) {
+            Some(format!("the document has more than {} blocks", max))
+        } else if let (Some(started), Some(timeout)) = (self.started, self.limits.timeout) {
+            (started.elapsed() >= timeout)
+                .then(|| format!("the conversion took longer than {:?}", timeout))
+        } else {
+            None
+        }
+    }
+}
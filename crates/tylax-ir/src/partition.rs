@@ -0,0 +1,68 @@
+//! Partition a document into parts at its headings, so the output of a
+//! long document can be split into one file per chapter or section.
+
+use std::ops::Range;
+
+use crate::stats::plain_text;
+use crate::{Block, Document};
+
+/// A run of top-level blocks starting at a heading, up to the next heading
+/// of its level or above.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part {
+    /// A file name for the part, unique within the document, e.g.
+    /// `02-related-work`.
+    pub name: String,
+    /// The top-level blocks of the part, its heading first.
+    pub blocks: Range<usize>,
+}
+
+/// The parts of `doc` that start at a heading of `level` or above. The
+/// blocks before the first such heading belong to no part.
+pub fn partition(doc: &Document, level: u8) -> Vec<Part> {
+    let starts: Vec<(usize, String)> = doc
+        .blocks
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, block)| match block {
+            Block::Heading {
+                level: heading_level,
+                content,
+                ..
+            } if *heading_level <= level => Some((idx, plain_text(content))),
+            _ => None,
+        })
+        .collect();
+    let mut parts = Vec::with_capacity(starts.len());
+    for (number, (start, title)) in starts.iter().enumerate() {
+        let end = starts
+            .get(number + 1)
+            .map_or(doc.blocks.len(), |(next, _)| *next);
+        parts.push(Part {
+            name: format!("{:02}-{}", number + 1, slug(title)),
+            blocks: *start..end,
+        });
+    }
+    parts
+}
+
+/// Lowercase ASCII letters and digits of `title`, words joined by `-`.
+fn slug(title: &str) -> String {
+    let mut slug = String::new();
+    for word in title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        if slug.len() + word.len() >= 40 {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word.to_ascii_lowercase());
+    }
+    if slug.is_empty() {
+        slug.push_str("part");
+    }
+    slug
+}
@@ -0,0 +1,265 @@
+//! Labels a document defines against the labels its references name, to
+//! report before rendering what a backend would set as `??` or what would
+//! make the output fail to compile.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::{
+    walk_block, walk_blocks, walk_figure, walk_inline, walk_table, Block, Document, Figure,
+    FigureContent, Inline, LinkTarget, Loss, Span, Table, Visitor,
+};
+
+/// A label referred to but defined nowhere, or defined more than once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReferenceIssue {
+    pub kind: ReferenceIssueKind,
+    pub label: String,
+    /// The source span of the top-level block of the first reference to an
+    /// undefined label, or of the second definition of a duplicate one.
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[non_exhaustive]
+pub enum ReferenceIssueKind {
+    /// A reference names a label no element of the document carries.
+    Undefined,
+    /// Two elements carry the same label.
+    Duplicate,
+}
+
+impl ReferenceIssue {
+    /// The issue as a loss of the conversion, for reports that list both.
+    pub fn to_loss(&self) -> Loss {
+        let loss = Loss::new("reference", self.to_string());
+        match self.span {
+            Some(span) => loss.with_span(span),
+            None => loss,
+        }
+    }
+}
+
+impl fmt::Display for ReferenceIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ReferenceIssueKind::Undefined => {
+                write!(f, "reference to undefined label `{}`", self.label)
+            }
+            ReferenceIssueKind::Duplicate => {
+                write!(f, "label `{}` is defined more than once", self.label)
+            }
+        }
+    }
+}
+
+impl Document {
+    /// The labels referred to but never defined and those defined more than
+    /// once, each reported once, in the order they first go wrong.
+    /// Definitions are the labels of headings, equations, tables, figures,
+    /// environments and algorithms and labels set in the text; references
+    /// are `Inline::Ref` and its variants and links to a label.
+    pub fn check_references(&self) -> Vec<ReferenceIssue> {
+        let mut collector = Collector::default();
+        walk_blocks(&mut collector, &self.meta.abstract_content);
+        for (index, block) in self.blocks.iter().enumerate() {
+            collector.span = self.block_span(index);
+            collector.visit_block(block);
+        }
+
+        let mut issues = Vec::new();
+        let mut defined = HashSet::new();
+        let mut duplicated = HashSet::new();
+        for (label, span) in &collector.definitions {
+            if !defined.insert(label.as_str()) && duplicated.insert(label.as_str()) {
+                issues.push(ReferenceIssue {
+                    kind: ReferenceIssueKind::Duplicate,
+                    label: label.clone(),
+                    span: *span,
+                });
+            }
+        }
+        let mut reported = HashSet::new();
+        for (label, span) in &collector.uses {
+            if !defined.contains(label.as_str()) && reported.insert(label.as_str()) {
+                issues.push(ReferenceIssue {
+                    kind: ReferenceIssueKind::Undefined,
+                    label: label.clone(),
+                    span: *span,
+                });
+            }
+        }
+        issues
+    }
+}
+
+/// Every label defined and referred to, with the span of the top-level
+/// block it is found in.
+#[derive(Default)]
+struct Collector {
+    span: Option<Span>,
+    definitions: Vec<(String, Option<Span>)>,
+    uses: Vec<(String, Option<Span>)>,
+}
+
+impl Collector {
+    fn define(&mut self, label: &Option<String>) {
+        if let Some(label) = label {
+            self.definitions.push((label.clone(), self.span));
+        }
+    }
+
+    fn refer(&mut self, label: &str) {
+        self.uses.push((label.to_string(), self.span));
+    }
+}
+
+impl Visitor<'_> for Collector {
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block);
+        match block {
+            Block::Heading { label, .. } => self.define(label),
+            Block::MathBlock(math) => self.define(&math.label),
+            Block::Environment(environment) => self.define(&environment.label),
+            Block::Algorithm(algorithm) => self.define(&algorithm.label),
+            _ => {}
+        }
+    }
+
+    fn visit_figure(&mut self, figure: &Figure) {
+        walk_figure(self, figure);
+        // A table set in a figure may carry the figure's label as well.
+        if !matches!(&figure.content, FigureContent::Table(table) if table.label == figure.label) {
+            self.define(&figure.label);
+        }
+    }
+
+    fn visit_table(&mut self, table: &Table) {
+        walk_table(self, table);
+        self.define(&table.label);
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        match inline {
+            Inline::Label(label) => self.definitions.push((label.clone(), self.span)),
+            Inline::Ref(label) => self.refer(label),
+            Inline::RefRange { from, to } => {
+                self.refer(from);
+                self.refer(to);
+            }
+            Inline::SupplementedRef { label, .. } => self.refer(label),
+            Inline::Link {
+                url,
+                target: LinkTarget::Label,
+                ..
+            } => self.refer(url),
+            _ => {}
+        }
+        walk_inline(self, inline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MathBlock;
+
+    fn heading(label: &str) -> Block {
+        Block::Heading {
+            level: 1,
+            content: vec![Inline::text("Intro")],
+            numbered: true,
+            label: Some(label.to_string()),
+            outlined: true,
+            bookmarked: None,
+        }
+    }
+
+    fn equation(label: &str) -> Block {
+        Block::MathBlock(MathBlock {
+            content: "x = 1".to_string(),
+            label: Some(label.to_string()),
+            latex: false,
+        })
+    }
+
+    #[test]
+    fn undefined_and_duplicate_labels_are_reported_once() {
+        let doc = Document::new(vec![
+            heading("sec:intro"),
+            Block::Paragraph(vec![
+                Inline::Ref("sec:intro".to_string()),
+                Inline::Ref("eq:missing".to_string()),
+                Inline::Emph(vec![Inline::Ref("eq:missing".to_string())]),
+                Inline::Label("eq:one".to_string()),
+            ]),
+            equation("eq:one"),
+            equation("eq:one"),
+            Block::Quote(vec![Block::Paragraph(vec![Inline::RefRange {
+                from: "eq:one".to_string(),
+                to: "eq:two".to_string(),
+            }])]),
+        ])
+        .with_spans(vec![
+            None,
+            Some(Span::new(10, 20)),
+            None,
+            Some(Span::new(30, 40)),
+        ]);
+        let issues = doc.check_references();
+        assert_eq!(
+            issues,
+            [
+                ReferenceIssue {
+                    kind: ReferenceIssueKind::Duplicate,
+                    label: "eq:one".to_string(),
+                    span: None,
+                },
+                ReferenceIssue {
+                    kind: ReferenceIssueKind::Undefined,
+                    label: "eq:missing".to_string(),
+                    span: Some(Span::new(10, 20)),
+                },
+                ReferenceIssue {
+                    kind: ReferenceIssueKind::Undefined,
+                    label: "eq:two".to_string(),
+                    span: None,
+                },
+            ]
+        );
+        assert_eq!(
+            issues[1].to_loss().message,
+            "reference to undefined label `eq:missing`"
+        );
+    }
+
+    #[test]
+    fn labels_of_figures_and_links_to_them_agree() {
+        let doc = Document::new(vec![
+            Block::Figure(Figure {
+                content: FigureContent::Raw(vec![equation("eq:inner")]),
+                caption: Some(vec![Inline::SupplementedRef {
+                    label: "eq:inner".to_string(),
+                    supplement: vec![Inline::text("Equation")],
+                }]),
+                label: Some("fig:plot".to_string()),
+                placement: None,
+                unnumbered: false,
+                caption_separator: None,
+                caption_position: None,
+                short_caption: None,
+                numbering: None,
+                wrap: None,
+            }),
+            Block::Paragraph(vec![Inline::Link {
+                text: vec![Inline::text("the plot")],
+                url: "fig:plot".to_string(),
+                target: LinkTarget::Label,
+            }]),
+        ]);
+        assert_eq!(doc.check_references(), []);
+    }
+}
@@ -0,0 +1,176 @@
+//! Counts of what a document holds, to compare a source with what it was
+//! converted to and spot content a conversion dropped.
+
+use std::collections::BTreeSet;
+
+use crate::visit::children;
+use crate::{
+    walk_block, walk_document, walk_figure, walk_inline, walk_inlines, walk_table, Block, Document,
+    Figure, FigureContent, Inline, Table, Visitor,
+};
+
+/// What a document holds, as [`stats`] counts it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DocStats {
+    /// Words of text, the title and abstract included. Math, code,
+    /// references and citations are not words.
+    pub words: usize,
+    /// The headings, each with the headings of lower level under it.
+    pub headings: Vec<HeadingNode>,
+    /// Display equations.
+    pub equations: usize,
+    /// Tables, in a figure or not.
+    pub tables: usize,
+    /// Figures of anything but a table.
+    pub figures: usize,
+    /// Keys cited anywhere in the document, sorted and each once.
+    pub citations: Vec<String>,
+    /// Blocks at every nesting level, as paragraphs, headings, lists,
+    /// equations, tables, figures and the blocks inside them. Empty
+    /// paragraphs are not counted.
+    pub blocks: usize,
+}
+
+/// A heading and the headings of its section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeadingNode {
+    pub level: u8,
+    /// The heading's text, without its formatting.
+    pub title: String,
+    pub children: Vec<HeadingNode>,
+}
+
+/// Count the words, headings, equations, tables, figures and citations of
+/// `doc`, at every nesting level.
+pub fn stats(doc: &Document) -> DocStats {
+    let mut counter = Counter::default();
+    walk_document(&mut counter, doc);
+    let mut headings = counter.headings.into_iter().peekable();
+    DocStats {
+        words: counter.words,
+        headings: heading_tree(&mut headings, None),
+        equations: counter.equations,
+        tables: counter.tables,
+        figures: counter.figures,
+        citations: counter.citations.into_iter().collect(),
+        blocks: counter.block_count,
+    }
+}
+
+/// The headings of a section of `parent` level, taken from the flat list
+/// until one of its own level or above.
+fn heading_tree(
+    headings: &mut std::iter::Peekable<std::vec::IntoIter<HeadingNode>>,
+    parent: Option<u8>,
+) -> Vec<HeadingNode> {
+    let mut nodes = Vec::new();
+    while let Some(mut heading) =
+        headings.next_if(|heading| parent.is_none_or(|level| heading.level > level))
+    {
+        heading.children = heading_tree(headings, Some(heading.level));
+        nodes.push(heading);
+    }
+    nodes
+}
+
+#[derive(Default)]
+struct Counter {
+    words: usize,
+    headings: Vec<HeadingNode>,
+    equations: usize,
+    tables: usize,
+    figures: usize,
+    citations: BTreeSet<String>,
+    block_count: usize,
+}
+
+impl<'a> Visitor<'a> for Counter {
+    fn visit_block(&mut self, block: &'a Block) {
+        if !matches!(block, Block::Paragraph(inlines) if inlines.is_empty()) {
+            self.block_count += 1;
+        }
+        match block {
+            Block::Heading { level, content, .. } => self.headings.push(HeadingNode {
+                level: *level,
+                title: plain_text(content)
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                children: Vec::new(),
+            }),
+            Block::MathBlock(_) => self.equations += 1,
+            _ => {}
+        }
+        walk_block(self, block);
+    }
+
+    fn visit_figure(&mut self, figure: &'a Figure) {
+        if matches!(
+            figure.content,
+            FigureContent::Image(_) | FigureContent::Raw(_)
+        ) {
+            self.figures += 1;
+        }
+        walk_figure(self, figure);
+    }
+
+    fn visit_table(&mut self, table: &'a Table) {
+        self.tables += 1;
+        walk_table(self, table);
+    }
+
+    fn visit_inlines(&mut self, inlines: &'a [Inline]) {
+        // Punctuation left alone by a reference, as in "see @fig.", is no
+        // word.
+        self.words += plain_text(inlines)
+            .split_whitespace()
+            .filter(|word| word.chars().any(char::is_alphanumeric))
+            .count();
+        walk_inlines(self, inlines);
+    }
+
+    fn visit_inline(&mut self, inline: &'a Inline) {
+        match inline {
+            Inline::Cite(keys) => self.cite_keys(keys),
+            Inline::Citation(citation) => self.cite_keys(&citation.keys),
+            _ => walk_inline(self, inline),
+        }
+    }
+}
+
+impl Counter {
+    fn cite_keys(&mut self, keys: &str) {
+        self.citations.extend(
+            keys.split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(str::to_string),
+        );
+    }
+}
+
+/// The text of `inlines` without their formatting. What is not text, as
+/// math or a reference, leaves a space so it does not join two words.
+pub(crate) fn plain_text(inlines: &[Inline]) -> String {
+    let mut out = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) => out.push_str(text),
+            // Quotes and apostrophes are part of the word they touch.
+            Inline::SmartQuote { .. } => {}
+            // A footnote's words are not part of the word it follows.
+            Inline::Footnote(content) => {
+                out.push(' ');
+                out.push_str(&plain_text(content));
+                out.push(' ');
+            }
+            _ => match children(inline) {
+                Some(children) => out.push_str(&plain_text(children)),
+                None => out.push(' '),
+            },
+        }
+    }
+    out
+}
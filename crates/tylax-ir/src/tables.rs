@@ -0,0 +1,298 @@
+//! The tables of a document as rows of text, for passes that export their
+//! data rather than set them.
+
+use crate::visit::children;
+use crate::{
+    walk_blocks, walk_figure, walk_table, Block, Document, Figure, FigureContent, Inline, Table,
+    TableCell, Visitor,
+};
+
+/// A table of a document and the label it is referred to by: its own, or
+/// that of the figure it is set in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LabeledTable<'a> {
+    pub table: &'a Table,
+    pub label: Option<&'a str>,
+}
+
+/// Every table of `doc`, in document order, wherever it is nested.
+pub fn tables(doc: &Document) -> Vec<LabeledTable<'_>> {
+    let mut tables = Tables::default();
+    walk_blocks(&mut tables, &doc.blocks);
+    tables.0
+}
+
+/// The cells of a table placed on its grid. Rows fill left to right; a
+/// cell moves on past the slots a spanning cell above or before covers,
+/// and to the next row when its columns do not fit in the one it is on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableGrid {
+    /// The index of the cell covering each slot, row by row. A rowspan past
+    /// the last cell adds its rows.
+    pub slots: Vec<Vec<Option<usize>>>,
+    /// Row and column of the top-left slot of each cell.
+    pub origins: Vec<(usize, usize)>,
+}
+
+impl TableGrid {
+    pub fn new(cells: &[TableCell], columns: usize) -> Self {
+        let columns = columns.max(1);
+        let mut slots: Vec<Vec<Option<usize>>> = Vec::new();
+        let mut origins = Vec::with_capacity(cells.len());
+        let (mut row, mut col) = (0usize, 0usize);
+        for (idx, cell) in cells.iter().enumerate() {
+            let colspan = cell.colspan.clamp(1, columns);
+            loop {
+                if col + colspan > columns {
+                    row += 1;
+                    col = 0;
+                }
+                let taken = slots
+                    .get(row)
+                    .is_some_and(|cells| cells[col..col + colspan].iter().any(Option::is_some));
+                if !taken {
+                    break;
+                }
+                col += 1;
+            }
+            let rows_end = row + cell.rowspan.max(1);
+            if slots.len() < rows_end {
+                slots.resize(rows_end, vec![None; columns]);
+            }
+            for cells in &mut slots[row..rows_end] {
+                for slot in &mut cells[col..col + colspan] {
+                    slot.get_or_insert(idx);
+                }
+            }
+            origins.push((row, col));
+            col += colspan;
+        }
+        TableGrid { slots, origins }
+    }
+
+    /// Slots of `row` the cell at `col` covers from that column on.
+    pub fn width(&self, row: usize, col: usize) -> usize {
+        let owner = self.slots[row][col];
+        self.slots[row][col..]
+            .iter()
+            .take_while(|slot| **slot == owner)
+            .count()
+    }
+
+    /// Whether the cell at `row` and `col` carries on into the row below.
+    pub fn continues(&self, row: usize, col: usize) -> bool {
+        let owner = self.slots[row][col];
+        owner.is_some()
+            && self
+                .slots
+                .get(row + 1)
+                .is_some_and(|below| below[col] == owner)
+    }
+}
+
+impl Table {
+    /// The cells placed on the grid of the table.
+    pub fn grid(&self) -> TableGrid {
+        TableGrid::new(&self.cells, self.columns)
+    }
+
+    /// The text of the cells, row by row as they are laid out on the grid.
+    /// A cell spanning several columns or rows gives its text to the first
+    /// of them and leaves the others empty. The text keeps no formatting;
+    /// math and code keep their source.
+    pub fn text_rows(&self) -> Vec<Vec<String>> {
+        let grid = self.grid();
+        // Rows a cell only spans into, past the last row one starts in, are
+        // not rows of the table.
+        let rows = grid.origins.iter().map(|&(row, _)| row + 1).max();
+        grid.slots
+            .iter()
+            .take(rows.unwrap_or(0))
+            .enumerate()
+            .map(|(row, slots)| {
+                slots
+                    .iter()
+                    .enumerate()
+                    .map(|(column, slot)| match *slot {
+                        Some(idx) if grid.origins[idx] == (row, column) => {
+                            let cell = &self.cells[idx];
+                            cell_text(&cell.content, cell.blocks.as_deref())
+                        }
+                        _ => String::new(),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// The tables met on a walk, each with its label.
+#[derive(Default)]
+struct Tables<'a>(Vec<LabeledTable<'a>>);
+
+impl<'a> Visitor<'a> for Tables<'a> {
+    fn visit_figure(&mut self, figure: &'a Figure) {
+        match &figure.content {
+            FigureContent::Table(table) => {
+                self.0.push(LabeledTable {
+                    table,
+                    label: table.label.as_deref().or(figure.label.as_deref()),
+                });
+                walk_table(self, table);
+            }
+            _ => walk_figure(self, figure),
+        }
+    }
+
+    fn visit_table(&mut self, table: &'a Table) {
+        self.0.push(LabeledTable {
+            table,
+            label: table.label.as_deref(),
+        });
+        walk_table(self, table);
+    }
+
+    fn visit_inlines(&mut self, _inlines: &'a [Inline]) {}
+}
+
+/// The text of a cell on one line, from its block content when its
+/// inline content has none.
+fn cell_text(content: &[Inline], cell_blocks: Option<&[Block]>) -> String {
+    let mut text = String::new();
+    inline_text(content, &mut text);
+    if text.trim().is_empty() {
+        for block in cell_blocks.unwrap_or_default() {
+            block_text(block, &mut text);
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn block_text(block: &Block, out: &mut String) {
+    match block {
+        Block::Paragraph(inlines) => inline_text(inlines, out),
+        Block::Heading { content, .. } => inline_text(content, out),
+        Block::List { items, .. } => {
+            for block in items.iter().flatten() {
+                block_text(block, out);
+            }
+        }
+        _ => {}
+    }
+    out.push(' ');
+}
+
+fn inline_text(inlines: &[Inline], out: &mut String) {
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) => out.push_str(text),
+            Inline::SmartQuote { double: true } => out.push('"'),
+            Inline::SmartQuote { double: false } => out.push('\''),
+            Inline::Math(source)
+            | Inline::LatexMath(source)
+            | Inline::Chemical(source)
+            | Inline::Code {
+                content: source, ..
+            } => out.push_str(source.trim()),
+            Inline::Quantity { value, unit } => {
+                let parts = [value.as_deref(), unit.as_deref()];
+                out.push_str(&parts.into_iter().flatten().collect::<Vec<_>>().join(" "));
+            }
+            // A note is no part of the data of its cell.
+            Inline::Footnote(_) => {}
+            Inline::LineBreak => out.push(' '),
+            _ => {
+                if let Some(children) = children(inline) {
+                    inline_text(children, out);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(text: &str, colspan: usize, rowspan: usize) -> TableCell {
+        TableCell {
+            content: vec![Inline::Text(text.to_string())],
+            blocks: None,
+            colspan,
+            rowspan,
+            align: None,
+            valign: None,
+            is_header: false,
+            is_footer: false,
+            fill: None,
+            stroke: None,
+            inset: None,
+        }
+    }
+
+    fn table(columns: usize, cells: Vec<TableCell>) -> Table {
+        Table {
+            columns,
+            cells,
+            align: None,
+            valign: None,
+            caption: None,
+            stroke: None,
+            fill: None,
+            inset: None,
+            label: None,
+            repeat_header: false,
+            widths: Vec::new(),
+            gutter: None,
+            row_gutter: None,
+            column_gutter: None,
+            rules: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn spanning_cells_leave_the_cells_they_cover_empty() {
+        let cells = vec![
+            cell("Name", 1, 2),
+            cell("Score", 2, 1),
+            cell("a", 1, 1),
+            cell("b", 1, 1),
+            cell("x", 1, 1),
+            cell("1", 1, 1),
+            cell("2", 1, 1),
+        ];
+        assert_eq!(
+            table(3, cells).text_rows(),
+            [["Name", "Score", ""], ["", "a", "b"], ["x", "1", "2"]]
+        );
+        let mut quoted = cell("", 1, 1);
+        quoted.content = vec![
+            Inline::Strong(vec![Inline::Text("p ".to_string())]),
+            Inline::Math("< 0.05".to_string()),
+            Inline::Footnote(vec![Inline::Text("two-sided".to_string())]),
+        ];
+        assert_eq!(table(2, vec![quoted]).text_rows(), [["p < 0.05", ""]]);
+    }
+
+    #[test]
+    fn tables_take_the_label_of_their_figure() {
+        let table = table(1, vec![cell("1", 1, 1)]);
+        let doc = Document::new(vec![
+            Block::Figure(Figure {
+                content: FigureContent::Table(Box::new(table.clone())),
+                caption: None,
+                label: Some("tab:one".to_string()),
+                placement: None,
+                unnumbered: false,
+                caption_separator: None,
+                caption_position: None,
+                short_caption: None,
+                numbering: None,
+                wrap: None,
+            }),
+            Block::Quote(vec![Block::Table(table)]),
+        ]);
+        let labels: Vec<_> = tables(&doc).iter().map(|table| table.label).collect();
+        assert_eq!(labels, [Some("tab:one"), None]);
+    }
+}
@@ -0,0 +1,234 @@
+//! Walks over every block and inline of a document, for passes that look at
+//! or change one kind of node wherever it is nested.
+//!
+//! A pass implements [`Visitor`], or [`VisitorMut`] to change what it
+//! visits, and overrides the methods of the nodes it acts on. Each method
+//! walks into the children of its node by default; an override calls the
+//! matching `walk_` function to keep descending, or leaves it out to skip
+//! what is inside.
+
+use crate::{AlgorithmStep, Block, Document, Figure, FigureContent, Image, Inline, Table};
+
+macro_rules! visitor {
+    ($visitor:ident, $($mut:tt)?) => {
+        pub trait $visitor<'a> {
+            fn visit_block(&mut self, block: &'a $($mut)? Block) {
+                walk_block(self, block);
+            }
+
+            fn visit_figure(&mut self, figure: &'a $($mut)? Figure) {
+                walk_figure(self, figure);
+            }
+
+            fn visit_table(&mut self, table: &'a $($mut)? Table) {
+                walk_table(self, table);
+            }
+
+            fn visit_image(&mut self, _image: &'a $($mut)? Image) {}
+
+            /// A run of inlines set together: a paragraph, a heading, a
+            /// caption, a title or the content of a cell. The inlines
+            /// nested in it are walked as part of it.
+            fn visit_inlines(&mut self, inlines: &'a $($mut)? [Inline]) {
+                walk_inlines(self, inlines);
+            }
+
+            fn visit_inline(&mut self, inline: &'a $($mut)? Inline) {
+                walk_inline(self, inline);
+            }
+        }
+
+        /// The title, the abstract and the blocks of `doc`, in that order.
+        /// Page headers and footers are not part of the content.
+        pub fn walk_document<'a, V: $visitor<'a> + ?Sized>(
+            visitor: &mut V,
+            doc: &'a $($mut)? Document,
+        ) {
+            if let Some(title) = & $($mut)? doc.meta.title {
+                visitor.visit_inlines(title);
+            }
+            walk_blocks(visitor, & $($mut)? doc.meta.abstract_content);
+            walk_blocks(visitor, & $($mut)? doc.blocks);
+        }
+
+        pub fn walk_blocks<'a, V: $visitor<'a> + ?Sized>(
+            visitor: &mut V,
+            blocks: &'a $($mut)? [Block],
+        ) {
+            for block in blocks {
+                visitor.visit_block(block);
+            }
+        }
+
+        pub fn walk_block<'a, V: $visitor<'a> + ?Sized>(
+            visitor: &mut V,
+            block: &'a $($mut)? Block,
+        ) {
+            match block {
+                Block::Paragraph(inlines) => visitor.visit_inlines(inlines),
+                Block::Heading { content, .. } => visitor.visit_inlines(content),
+                Block::List { items, .. } => {
+                    for item in items {
+                        walk_blocks(visitor, item);
+                    }
+                }
+                Block::TermList(items) => {
+                    for item in items {
+                        visitor.visit_inlines(& $($mut)? item.term);
+                        walk_blocks(visitor, & $($mut)? item.description);
+                    }
+                }
+                Block::Table(table) => visitor.visit_table(table),
+                Block::Figure(figure) => visitor.visit_figure(figure),
+                Block::Environment(environment) => {
+                    if let Some(title) = & $($mut)? environment.title {
+                        visitor.visit_inlines(title);
+                    }
+                    walk_blocks(visitor, & $($mut)? environment.blocks);
+                }
+                Block::Bibliography { title, .. } | Block::Outline { title, .. } => {
+                    if let Some(title) = title {
+                        visitor.visit_inlines(title);
+                    }
+                }
+                Block::Quote(blocks) | Block::Align { blocks, .. } => walk_blocks(visitor, blocks),
+                Block::Box(inner) => walk_blocks(visitor, & $($mut)? inner.blocks),
+                Block::Block(inner) => walk_blocks(visitor, & $($mut)? inner.blocks),
+                Block::Columns(columns) => walk_blocks(visitor, & $($mut)? columns.blocks),
+                Block::Grid(grid) => {
+                    for cell in & $($mut)? grid.cells {
+                        walk_blocks(visitor, cell);
+                    }
+                }
+                Block::Algorithm(algorithm) => {
+                    if let Some(caption) = & $($mut)? algorithm.caption {
+                        visitor.visit_inlines(caption);
+                    }
+                    walk_steps(visitor, & $($mut)? algorithm.steps);
+                }
+                Block::Slide(slide) => {
+                    if let Some(title) = & $($mut)? slide.title {
+                        visitor.visit_inlines(title);
+                    }
+                    walk_blocks(visitor, & $($mut)? slide.blocks);
+                }
+                Block::SideBySide(side_by_side) => {
+                    for column in & $($mut)? side_by_side.columns {
+                        walk_blocks(visitor, column);
+                    }
+                }
+                Block::MathBlock(_)
+                | Block::VSpace(_)
+                | Block::CodeBlock { .. }
+                | Block::Unsupported { .. } => {}
+            }
+        }
+
+        /// The content of `figure`, then its caption.
+        pub fn walk_figure<'a, V: $visitor<'a> + ?Sized>(
+            visitor: &mut V,
+            figure: &'a $($mut)? Figure,
+        ) {
+            match & $($mut)? figure.content {
+                FigureContent::Table(table) => visitor.visit_table(table),
+                FigureContent::Image(image) => visitor.visit_image(image),
+                FigureContent::Raw(blocks) => walk_blocks(visitor, blocks),
+                FigureContent::SubFigures { figures, .. } => {
+                    for figure in figures {
+                        visitor.visit_figure(figure);
+                    }
+                }
+            }
+            if let Some(caption) = & $($mut)? figure.caption {
+                visitor.visit_inlines(caption);
+            }
+        }
+
+        /// The cells of `table` in order, then its caption.
+        pub fn walk_table<'a, V: $visitor<'a> + ?Sized>(
+            visitor: &mut V,
+            table: &'a $($mut)? Table,
+        ) {
+            for cell in & $($mut)? table.cells {
+                visitor.visit_inlines(& $($mut)? cell.content);
+                if let Some(blocks) = & $($mut)? cell.blocks {
+                    walk_blocks(visitor, blocks);
+                }
+            }
+            if let Some(caption) = & $($mut)? table.caption {
+                visitor.visit_inlines(caption);
+            }
+        }
+
+        fn walk_steps<'a, V: $visitor<'a> + ?Sized>(
+            visitor: &mut V,
+            steps: &'a $($mut)? [AlgorithmStep],
+        ) {
+            for step in steps {
+                visitor.visit_inlines(& $($mut)? step.content);
+                if let Some(comment) = & $($mut)? step.comment {
+                    visitor.visit_inlines(comment);
+                }
+                walk_steps(visitor, & $($mut)? step.body);
+            }
+        }
+
+        pub fn walk_inlines<'a, V: $visitor<'a> + ?Sized>(
+            visitor: &mut V,
+            inlines: &'a $($mut)? [Inline],
+        ) {
+            for inline in inlines {
+                visitor.visit_inline(inline);
+            }
+        }
+
+        /// The inlines `inline` wraps, and the supplement of a reference.
+        /// They are part of the run `inline` is in, so they are not a run of
+        /// their own for `visit_inlines`.
+        pub fn walk_inline<'a, V: $visitor<'a> + ?Sized>(
+            visitor: &mut V,
+            inline: &'a $($mut)? Inline,
+        ) {
+            match inline {
+                Inline::SupplementedRef { supplement, .. } => walk_inlines(visitor, supplement),
+                _ => {
+                    if let Some(children) = children(inline) {
+                        walk_inlines(visitor, children);
+                    }
+                }
+            }
+        }
+
+        /// The inlines a formatting inline wraps.
+        pub(crate) fn children(inline: & $($mut)? Inline) -> Option<& $($mut)? [Inline]> {
+            match inline {
+                Inline::Size { content, .. }
+                | Inline::Color { content, .. }
+                | Inline::Link { text: content, .. }
+                | Inline::Strong(content)
+                | Inline::Emph(content)
+                | Inline::Footnote(content)
+                | Inline::Superscript(content)
+                | Inline::Subscript(content)
+                | Inline::Underline(content)
+                | Inline::Overline(content)
+                | Inline::Strike(content)
+                | Inline::SmallCaps(content)
+                | Inline::Uppercase(content)
+                | Inline::Lowercase(content) => Some(content),
+                _ => None,
+            }
+        }
+    };
+}
+
+visitor!(Visitor,);
+
+/// The walks of [`VisitorMut`], which hand out the nodes to change them.
+pub mod visit_mut {
+    use super::*;
+
+    visitor!(VisitorMut, mut);
+}
+
+pub use visit_mut::VisitorMut;
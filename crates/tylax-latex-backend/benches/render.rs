@@ -0,0 +1,121 @@
+//! Rendering throughput on a large synthetic document.
+//!
+//! Run with `cargo bench -p tylax-latex-backend`.
+
+use std::time::{Duration, Instant};
+
+use tylax_ir::{Block, Document, Inline, ListKind, MathBlock, Span, Table, TableCell};
+use tylax_latex_backend::{render_document, LatexRenderOptions};
+
+const SECTIONS: usize = 2_000;
+const ITERATIONS: usize = 10;
+
+fn cell(text: String) -> TableCell {
+    TableCell {
+        content: vec![Inline::Text(text)],
+        blocks: None,
+        colspan: 1,
+        rowspan: 1,
+        align: None,
+        valign: None,
+        is_header: false,
+        is_footer: false,
+        fill: None,
+        stroke: None,
+        inset: None,
+    }
+}
+
+fn paragraph(idx: usize) -> Block {
+    Block::Paragraph(vec![
+        Inline::text("Paragraph "),
+        Inline::Text(idx.to_string()),
+        Inline::text(" has "),
+        Inline::Strong(vec![Inline::text("bold")]),
+        Inline::text(", "),
+        Inline::Emph(vec![Inline::text("emphasis")]),
+        Inline::text(", math "),
+        Inline::Math("x^2 + y_i".to_string()),
+        Inline::text(" and a citation "),
+        Inline::Cite(format!("key{}", idx)),
+        Inline::text(". Some filler text keeps the paragraph at a realistic length."),
+    ])
+}
+
+fn build_document() -> Document {
+    let mut blocks = Vec::new();
+    for idx in 0..SECTIONS {
+        blocks.push(Block::Heading {
+            level: 1 + (idx % 3) as u8,
+            content: vec![Inline::Text(format!("Section {}", idx))],
+            numbered: true,
+            label: Some(format!("sec:{}", idx)),
+            outlined: true,
+            bookmarked: None,
+        });
+        blocks.push(paragraph(idx));
+        blocks.push(paragraph(idx + 1));
+        blocks.push(Block::List {
+            kind: if idx % 2 == 0 {
+                ListKind::Unordered
+            } else {
+                ListKind::Ordered
+            },
+            items: (0..4).map(|item| vec![paragraph(item)]).collect(),
+        });
+        blocks.push(Block::MathBlock(MathBlock {
+            content: "sum_(i=1)^n x_i = integral_0^1 f(t) dif t".to_string(),
+            label: Some(format!("eq:{}", idx)),
+            latex: false,
+        }));
+        if idx % 10 == 0 {
+            let cells = (0..40)
+                .map(|n| cell(format!("cell {}", n)))
+                .collect::<Vec<_>>();
+            blocks.push(Block::Table(Table {
+                columns: 4,
+                cells,
+                align: None,
+                valign: None,
+                caption: None,
+                stroke: None,
+                fill: None,
+                inset: None,
+                label: None,
+                repeat_header: true,
+                widths: Vec::new(),
+                gutter: None,
+                row_gutter: None,
+                column_gutter: None,
+                rules: Vec::new(),
+            }));
+        }
+    }
+    // Spans let the renderer size its buffer from the source length.
+    let spans = (0..blocks.len())
+        .map(|idx| Some(Span::new(idx * 200, idx * 200 + 180)))
+        .collect();
+    Document::new(blocks).with_spans(spans)
+}
+
+fn main() {
+    let doc = build_document();
+    let mut timings: Vec<Duration> = Vec::with_capacity(ITERATIONS);
+    let mut output_len = 0;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        let out = render_document(&doc, LatexRenderOptions::default());
+        timings.push(start.elapsed());
+        output_len = out.len();
+    }
+    timings.sort();
+    let median = timings[timings.len() / 2];
+    println!(
+        "render_document: {} blocks, {} KiB output, median {:.2?} (min {:.2?}, max {:.2?})",
+        doc.blocks.len(),
+        output_len / 1024,
+        median,
+        timings[0],
+        timings[timings.len() - 1]
+    );
+}
@@ -0,0 +1,190 @@
+//! Pseudocode rendered as an `algorithm` float around `algorithmic`
+//! (algpseudocode).
+
+use tylax_ir::{Algorithm, AlgorithmKeyword, AlgorithmStep};
+
+use crate::{
+    apply_float_hints, normalize_inline_whitespace, output_label, render_inlines,
+    render_moving_argument, LatexRenderOptions,
+};
+
+pub(crate) fn render_algorithm(algorithm: &Algorithm, options: &LatexRenderOptions) -> String {
+    let mut env = "algorithm".to_string();
+    let mut placement = String::new();
+    apply_float_hints(&mut env, &mut placement);
+    let mut out = format!("\\begin{{{}}}", env);
+    if !placement.is_empty() {
+        out.push_str(&format!("[{}]", placement));
+    }
+    out.push('\n');
+    if let Some(caption) = &algorithm.caption {
+        out.push_str("\\caption{");
+        out.push_str(&render_moving_argument(caption, options));
+        out.push_str("}\n");
+    }
+    if let Some(label) = &algorithm.label {
+        out.push_str("\\label{");
+        out.push_str(&output_label(label, options));
+        out.push_str("}\n");
+    }
+    out.push_str("\\begin{algorithmic}");
+    if algorithm.line_numbers {
+        out.push_str("[1]");
+    }
+    out.push('\n');
+    render_steps(&mut out, &algorithm.steps, options, 1);
+    out.push_str("\\end{algorithmic}\n");
+    out.push_str(&format!("\\end{{{}}}", env));
+    out
+}
+
+/// Render sibling steps. An `if` absorbs the `else if`/`else` steps that
+/// follow it and a `repeat` the `until`, since algpseudocode closes those
+/// blocks as one.
+fn render_steps(
+    out: &mut String,
+    steps: &[AlgorithmStep],
+    options: &LatexRenderOptions,
+    depth: usize,
+) {
+    let mut i = 0;
+    while i < steps.len() {
+        let step = &steps[i];
+        let content = normalize_inline_whitespace(&render_inlines(&step.content, options));
+        let comment = line_comment(step, options);
+        match step.keyword {
+            AlgorithmKeyword::If => {
+                push_line(out, depth, &format!("\\If{{{}}}{}", content, comment));
+                render_steps(out, &step.body, options, depth + 1);
+                while let Some(next) = steps.get(i + 1) {
+                    let line = match next.keyword {
+                        AlgorithmKeyword::ElseIf => format!(
+                            "\\ElsIf{{{}}}",
+                            normalize_inline_whitespace(&render_inlines(&next.content, options))
+                        ),
+                        AlgorithmKeyword::Else => "\\Else".to_string(),
+                        _ => break,
+                    };
+                    push_line(out, depth, &(line + &line_comment(next, options)));
+                    render_steps(out, &next.body, options, depth + 1);
+                    i += 1;
+                }
+                push_line(out, depth, "\\EndIf");
+            }
+            AlgorithmKeyword::For | AlgorithmKeyword::ForAll | AlgorithmKeyword::While => {
+                let (open, close) = match step.keyword {
+                    AlgorithmKeyword::For => ("For", "EndFor"),
+                    AlgorithmKeyword::ForAll => ("ForAll", "EndFor"),
+                    _ => ("While", "EndWhile"),
+                };
+                push_line(out, depth, &format!("\\{}{{{}}}{}", open, content, comment));
+                render_steps(out, &step.body, options, depth + 1);
+                push_line(out, depth, &format!("\\{}", close));
+            }
+            AlgorithmKeyword::Repeat => {
+                push_line(out, depth, &format!("\\Repeat{}", comment));
+                render_steps(out, &step.body, options, depth + 1);
+                let (condition, until_comment) = match steps.get(i + 1) {
+                    Some(next) if next.keyword == AlgorithmKeyword::Until => {
+                        i += 1;
+                        (
+                            normalize_inline_whitespace(&render_inlines(&next.content, options)),
+                            line_comment(next, options),
+                        )
+                    }
+                    _ => (String::new(), String::new()),
+                };
+                push_line(
+                    out,
+                    depth,
+                    &format!("\\Until{{{}}}{}", condition, until_comment),
+                );
+            }
+            AlgorithmKeyword::Function | AlgorithmKeyword::Procedure => {
+                let (open, close) = if step.keyword == AlgorithmKeyword::Function {
+                    ("Function", "EndFunction")
+                } else {
+                    ("Procedure", "EndProcedure")
+                };
+                let (name, params) = split_signature(&content);
+                let line = format!("\\{}{{{}}}{{{}}}{}", open, name, params, comment);
+                push_line(out, depth, &line);
+                render_steps(out, &step.body, options, depth + 1);
+                push_line(out, depth, &format!("\\{}", close));
+            }
+            AlgorithmKeyword::Require | AlgorithmKeyword::Ensure => {
+                let command = if step.keyword == AlgorithmKeyword::Require {
+                    "Require"
+                } else {
+                    "Ensure"
+                };
+                push_line(out, depth, &format!("\\{} {}{}", command, content, comment));
+            }
+            // algpseudocode has no input and output lines of its own; they
+            // are set unnumbered, as `\Require` and `\Ensure` are.
+            AlgorithmKeyword::Input | AlgorithmKeyword::Output => {
+                let word = if step.keyword == AlgorithmKeyword::Input {
+                    "Input"
+                } else {
+                    "Output"
+                };
+                let line = format!("\\Statex \\textbf{{{}:}} {}{}", word, content, comment);
+                push_line(out, depth, &line);
+            }
+            AlgorithmKeyword::Return => {
+                let line = format!("\\State \\Return {}{}", content, comment);
+                push_line(out, depth, &line);
+            }
+            // An `else` or `until` without its opening block has nothing to
+            // close, so it is kept as a plain line with its keyword.
+            AlgorithmKeyword::ElseIf | AlgorithmKeyword::Else | AlgorithmKeyword::Until => {
+                let word = match step.keyword {
+                    AlgorithmKeyword::ElseIf => "else if",
+                    AlgorithmKeyword::Else => "else",
+                    _ => "until",
+                };
+                push_line(
+                    out,
+                    depth,
+                    &format!("\\State \\textbf{{{}}} {}{}", word, content, comment),
+                );
+                render_steps(out, &step.body, options, depth + 1);
+            }
+            // Statements, and keywords this renderer does not know, are set
+            // as plain lines.
+            _ => {
+                push_line(out, depth, &format!("\\State {}{}", content, comment));
+                render_steps(out, &step.body, options, depth + 1);
+            }
+        }
+        i += 1;
+    }
+}
+
+/// The `\Comment` ending the line of `step`, if it has a comment.
+fn line_comment(step: &AlgorithmStep, options: &LatexRenderOptions) -> String {
+    match &step.comment {
+        Some(comment) => format!(
+            " \\Comment{{{}}}",
+            normalize_inline_whitespace(&render_inlines(comment, options))
+        ),
+        None => String::new(),
+    }
+}
+
+fn push_line(out: &mut String, depth: usize, line: &str) {
+    out.push_str(&"  ".repeat(depth));
+    out.push_str(line.trim_end());
+    out.push('\n');
+}
+
+/// Split `name(params)` into the two arguments of `\Procedure`.
+fn split_signature(signature: &str) -> (&str, &str) {
+    match signature.split_once('(') {
+        Some((name, rest)) => (
+            name.trim(),
+            rest.trim_end().strip_suffix(')').unwrap_or(rest),
+        ),
+        None => (signature.trim(), ""),
+    }
+}
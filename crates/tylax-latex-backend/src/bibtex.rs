@@ -0,0 +1,1226 @@
+//! BibTeX databases and inline `thebibliography` rendering.
+//!
+//! [`BibDatabase::parse`] reads `.bib` sources, including `@string` macros,
+//! `#` concatenation and `crossref` inheritance. [`render_thebibliography`]
+//! formats the entries the way the matching BibTeX style would, so a document
+//! can be typeset without an external `.bib` file.
+
+use std::collections::HashMap;
+
+/// Entries of one or more `.bib` files, in source order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BibDatabase {
+    entries: Vec<BibEntry>,
+}
+
+/// A single `@type{key, ...}` entry. Field names are lowercased and values
+/// have macros expanded and whitespace collapsed; braces are kept as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BibEntry {
+    pub kind: String,
+    pub key: String,
+    fields: Vec<(String, String)>,
+}
+
+/// A personal name split into BibTeX's four parts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BibName {
+    pub first: String,
+    pub von: String,
+    pub last: String,
+    pub jr: String,
+}
+
+/// Reference list layouts supported for inline rendering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BibStyle {
+    /// Numeric labels, sorted by author (`plain`).
+    Plain,
+    /// Numeric labels in citation order (`unsrt`).
+    Unsrt,
+    /// Labels built from author names and year, e.g. `[Ein05]` (`alpha`).
+    Alpha,
+    /// Numeric labels in citation order with IEEE punctuation.
+    Ieee,
+    /// Author-year references in APA layout (`apalike`).
+    Apa,
+}
+
+impl BibDatabase {
+    /// Parse BibTeX source. Malformed entries are skipped.
+    pub fn parse(input: &str) -> Self {
+        let mut parser = Parser::new(input);
+        let mut entries = Vec::new();
+        while let Some(entry) = parser.next_entry() {
+            entries.push(entry);
+        }
+        let mut db = Self { entries };
+        db.resolve_crossrefs();
+        db
+    }
+
+    /// Append the entries of `other`, keeping the first entry for each key.
+    pub fn extend(&mut self, other: BibDatabase) {
+        for entry in other.entries {
+            if self.get(&entry.key).is_none() {
+                self.entries.push(entry);
+            }
+        }
+    }
+
+    /// Serialize the entries as `.bib` source.
+    pub fn to_bibtex(&self) -> String {
+        let mut out = String::new();
+        for entry in &self.entries {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!("@{}{{{},\n", entry.kind, entry.key));
+            for (name, value) in &entry.fields {
+                out.push_str(&format!("  {} = {{{}}},\n", name, value));
+            }
+            out.push_str("}\n");
+        }
+        out
+    }
+
+    pub(crate) fn push(&mut self, entry: BibEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> &[BibEntry] {
+        &self.entries
+    }
+
+    /// Look up an entry by citation key. Keys compare case-insensitively, as
+    /// in BibTeX.
+    pub fn get(&self, key: &str) -> Option<&BibEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key.eq_ignore_ascii_case(key))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Copy fields an entry lacks from the entry its `crossref` names.
+    fn resolve_crossrefs(&mut self) {
+        for idx in 0..self.entries.len() {
+            let Some(parent_key) = self.entries[idx].field("crossref").map(str::to_string) else {
+                continue;
+            };
+            let Some(parent) = self.get(&parent_key).cloned() else {
+                continue;
+            };
+            let entry = &mut self.entries[idx];
+            if entry.field("booktitle").is_none() {
+                if let Some(title) = parent.field("title") {
+                    entry.set("booktitle", title);
+                }
+            }
+            for (name, value) in &parent.fields {
+                if entry.field(name).is_none() {
+                    entry.set(name, value);
+                }
+            }
+        }
+    }
+}
+
+impl BibEntry {
+    pub fn new(kind: &str, key: &str) -> Self {
+        Self {
+            kind: kind.to_lowercase(),
+            key: key.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Set a field, replacing an earlier value of the same name.
+    pub fn set(&mut self, name: &str, value: &str) {
+        let name = name.to_lowercase();
+        match self.fields.iter_mut().find(|(field, _)| *field == name) {
+            Some(slot) => slot.1 = value.to_string(),
+            None => self.fields.push((name, value.to_string())),
+        }
+    }
+
+    pub fn authors(&self) -> Vec<BibName> {
+        self.field("author").map(parse_names).unwrap_or_default()
+    }
+
+    pub fn editors(&self) -> Vec<BibName> {
+        self.field("editor").map(parse_names).unwrap_or_default()
+    }
+
+    pub fn year(&self) -> Option<&str> {
+        self.field("year")
+    }
+}
+
+impl BibName {
+    /// `others` in a name list stands for the authors left out.
+    pub fn is_others(&self) -> bool {
+        self.first.is_empty() && self.von.is_empty() && self.last == "others"
+    }
+
+    /// Surname with its von part, e.g. `van Beethoven`.
+    pub fn surname(&self) -> String {
+        join_nonempty(&[self.von.as_str(), self.last.as_str()], " ")
+    }
+
+    /// `First von Last, Jr.`
+    pub fn full(&self) -> String {
+        let name = join_nonempty(&[self.first.as_str(), &self.surname()], " ");
+        join_nonempty(&[name.as_str(), self.jr.as_str()], ", ")
+    }
+
+    /// `F. von Last, Jr.`
+    pub fn initials_first(&self) -> String {
+        let name = join_nonempty(&[initials(&self.first).as_str(), &self.surname()], " ");
+        join_nonempty(&[name.as_str(), self.jr.as_str()], ", ")
+    }
+
+    /// `von Last, F.`
+    pub fn last_first(&self) -> String {
+        let surname = join_nonempty(&[self.surname().as_str(), self.jr.as_str()], ", ");
+        join_nonempty(&[surname.as_str(), &initials(&self.first)], ", ")
+    }
+}
+
+impl BibStyle {
+    /// Pick the layout closest to a Typst or BibTeX style name. Unknown
+    /// names fall back to [`BibStyle::Plain`].
+    pub fn from_name(name: &str) -> Self {
+        let lowered = name.trim().to_lowercase();
+        if lowered.contains("ieee") {
+            BibStyle::Ieee
+        } else if ["apa", "author-date", "harvard", "chicago", "natbib"]
+            .iter()
+            .any(|needle| lowered.contains(needle))
+        {
+            BibStyle::Apa
+        } else if lowered.contains("alpha") {
+            BibStyle::Alpha
+        } else if ["unsrt", "vancouver", "nature"]
+            .iter()
+            .any(|needle| lowered.contains(needle))
+        {
+            BibStyle::Unsrt
+        } else {
+            BibStyle::Plain
+        }
+    }
+
+    fn sorted(self) -> bool {
+        matches!(self, BibStyle::Plain | BibStyle::Alpha | BibStyle::Apa)
+    }
+}
+
+/// Render a `thebibliography` environment for the `cited` keys, in first
+/// citation order. With no citations every entry of `db` is listed, as with
+/// `\nocite{*}`. Keys missing from the database are skipped.
+pub fn render_thebibliography(db: &BibDatabase, cited: &[String], style: BibStyle) -> String {
+    let mut entries: Vec<&BibEntry> = Vec::new();
+    if cited.is_empty() {
+        entries.extend(db.entries());
+    } else {
+        for key in cited {
+            if let Some(entry) = db.get(key) {
+                if !entries.iter().any(|seen| seen.key == entry.key) {
+                    entries.push(entry);
+                }
+            }
+        }
+    }
+    if style.sorted() {
+        entries.sort_by_cached_key(|entry| sort_key(entry));
+    }
+
+    let labels: Vec<Option<String>> = match style {
+        BibStyle::Alpha => alpha_labels(&entries).into_iter().map(Some).collect(),
+        BibStyle::Apa => entries
+            .iter()
+            .map(|entry| Some(author_year_label(entry)))
+            .collect(),
+        _ => vec![None; entries.len()],
+    };
+    let widest = match style {
+        BibStyle::Alpha => labels
+            .iter()
+            .flatten()
+            .max_by_key(|label| label.chars().count())
+            .cloned()
+            .unwrap_or_default(),
+        BibStyle::Apa => String::new(),
+        _ => entries.len().max(1).to_string(),
+    };
+
+    let mut out = String::new();
+    out.push_str("\\begin{thebibliography}{");
+    out.push_str(&widest);
+    out.push_str("}\n");
+    for (entry, label) in entries.iter().zip(&labels) {
+        out.push_str("\n\\bibitem");
+        if let Some(label) = label {
+            out.push('[');
+            out.push_str(label);
+            out.push(']');
+        }
+        out.push('{');
+        out.push_str(&entry.key);
+        out.push_str("}\n");
+        let blocks = match style {
+            BibStyle::Plain | BibStyle::Unsrt | BibStyle::Alpha => plain_blocks(entry),
+            BibStyle::Ieee => ieee_blocks(entry),
+            BibStyle::Apa => apa_blocks(entry),
+        };
+        out.push_str(&blocks.join("\n\\newblock "));
+        out.push('\n');
+    }
+    out.push_str("\n\\end{thebibliography}");
+    out
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    macros: HashMap<String, String>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            pos: 0,
+            macros: HashMap::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn identifier(&mut self) -> &'a str {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|ch| ch.is_alphanumeric() || "_-:.+/'".contains(ch))
+        {
+            self.bump();
+        }
+        &self.src[start..self.pos]
+    }
+
+    /// Read entries until the next regular one; `@string`, `@preamble` and
+    /// `@comment` are consumed on the way.
+    fn next_entry(&mut self) -> Option<BibEntry> {
+        loop {
+            let at = self.src[self.pos..].find('@')?;
+            self.pos += at + 1;
+            self.skip_whitespace();
+            let kind = self.identifier().to_lowercase();
+            self.skip_whitespace();
+            let close = match self.peek() {
+                Some('{') => '}',
+                Some('(') => ')',
+                _ => continue,
+            };
+            self.bump();
+            match kind.as_str() {
+                "comment" => {
+                    self.skip_to_close(close);
+                }
+                "preamble" => {
+                    self.value(close);
+                    self.skip_to_close(close);
+                }
+                "string" => {
+                    self.skip_whitespace();
+                    let name = self.identifier().to_lowercase();
+                    self.skip_whitespace();
+                    if self.peek() == Some('=') {
+                        self.bump();
+                        let value = self.value(close);
+                        if !name.is_empty() {
+                            self.macros.insert(name, value);
+                        }
+                    }
+                    self.skip_to_close(close);
+                }
+                "" => {}
+                _ => {
+                    if let Some(entry) = self.entry_body(&kind, close) {
+                        return Some(entry);
+                    }
+                }
+            }
+        }
+    }
+
+    fn entry_body(&mut self, kind: &str, close: char) -> Option<BibEntry> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.peek().is_some_and(|ch| ch != ',' && ch != close) {
+            self.bump();
+        }
+        let key = self.src[start..self.pos].trim();
+        if key.is_empty() {
+            self.skip_to_close(close);
+            return None;
+        }
+        let mut entry = BibEntry::new(kind, key);
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => break,
+                Some(ch) if ch == close => {
+                    self.bump();
+                    break;
+                }
+                Some(',') => {
+                    self.bump();
+                    continue;
+                }
+                _ => {}
+            }
+            let name = self.identifier().to_lowercase();
+            self.skip_whitespace();
+            if name.is_empty() || self.peek() != Some('=') {
+                self.skip_to_close(close);
+                break;
+            }
+            self.bump();
+            let value = self.value(close);
+            entry.set(&name, &value);
+        }
+        Some(entry)
+    }
+
+    /// A field value: braced or quoted strings, numbers and macro names,
+    /// joined with `#`.
+    fn value(&mut self, close: char) -> String {
+        let mut out = String::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('{') => {
+                    self.bump();
+                    out.push_str(&self.delimited('}'));
+                }
+                Some('"') => {
+                    self.bump();
+                    out.push_str(&self.delimited('"'));
+                }
+                Some(ch) if ch == close || ch == ',' => break,
+                Some(_) => {
+                    let word = self.identifier();
+                    if word.is_empty() {
+                        self.bump();
+                        continue;
+                    }
+                    if word.chars().all(|ch| ch.is_ascii_digit()) {
+                        out.push_str(word);
+                    } else {
+                        let name = word.to_lowercase();
+                        match self.macros.get(&name) {
+                            Some(value) => out.push_str(value),
+                            None => out.push_str(month_name(&name).unwrap_or(word)),
+                        }
+                    }
+                }
+                None => break,
+            }
+            self.skip_whitespace();
+            if self.peek() == Some('#') {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        collapse_whitespace(&out)
+    }
+
+    /// Text up to `end` at brace depth zero; nested braces are kept.
+    fn delimited(&mut self, end: char) -> String {
+        let start = self.pos;
+        let mut depth = 0usize;
+        while let Some(ch) = self.peek() {
+            match ch {
+                '\\' => {
+                    self.bump();
+                }
+                '{' => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                _ if ch == end && depth == 0 => {
+                    let text = self.src[start..self.pos].to_string();
+                    self.bump();
+                    return text;
+                }
+                _ => {}
+            }
+            self.bump();
+        }
+        self.src[start..].to_string()
+    }
+
+    fn skip_to_close(&mut self, close: char) {
+        let mut depth = 0usize;
+        while let Some(ch) = self.bump() {
+            match ch {
+                '{' => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                _ if ch == close && depth == 0 => return,
+                _ => {}
+            }
+        }
+    }
+}
+
+fn month_name(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "jan" => "January",
+        "feb" => "February",
+        "mar" => "March",
+        "apr" => "April",
+        "may" => "May",
+        "jun" => "June",
+        "jul" => "July",
+        "aug" => "August",
+        "sep" => "September",
+        "oct" => "October",
+        "nov" => "November",
+        "dec" => "December",
+        _ => return None,
+    })
+}
+
+fn collapse_whitespace(input: &str) -> String {
+    input.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn join_nonempty(parts: &[&str], sep: &str) -> String {
+    parts
+        .iter()
+        .filter(|part| !part.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+/// Split `input` at `sep` characters outside braces.
+fn split_top_level(input: &str, sep: impl Fn(char) -> bool) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (idx, ch) in input.char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            _ if depth == 0 && sep(ch) => {
+                parts.push(&input[start..idx]);
+                start = idx + ch.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&input[start..]);
+    parts
+}
+
+/// Parse an `author`/`editor` field: names separated by `and`.
+fn parse_names(field: &str) -> Vec<BibName> {
+    let words: Vec<&str> = split_top_level(field, char::is_whitespace)
+        .into_iter()
+        .filter(|word| !word.is_empty())
+        .collect();
+    words
+        .split(|word| word.eq_ignore_ascii_case("and"))
+        .filter(|name| !name.is_empty())
+        .map(|name| parse_name(&name.join(" ")))
+        .collect()
+}
+
+fn parse_name(name: &str) -> BibName {
+    let parts: Vec<&str> = split_top_level(name, |ch| ch == ',')
+        .into_iter()
+        .map(str::trim)
+        .collect();
+    let words = |part: &str| -> Vec<String> {
+        split_top_level(part, char::is_whitespace)
+            .into_iter()
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    match parts.as_slice() {
+        [single] => {
+            let words = words(single);
+            let Some((last, rest)) = words.split_last() else {
+                return BibName::default();
+            };
+            let von_start = rest.iter().position(|word| starts_lowercase(word));
+            let von_end = rest.iter().rposition(|word| starts_lowercase(word));
+            match (von_start, von_end) {
+                (Some(start), Some(end)) => BibName {
+                    first: rest[..start].join(" "),
+                    von: rest[start..=end].join(" "),
+                    last: rest[end + 1..]
+                        .iter()
+                        .chain(std::iter::once(last))
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    jr: String::new(),
+                },
+                _ => BibName {
+                    first: rest.join(" "),
+                    last: last.clone(),
+                    ..BibName::default()
+                },
+            }
+        }
+        [surname, rest @ ..] => {
+            let (jr, first) = match rest {
+                [first] => ("", *first),
+                [jr, first, ..] => (*jr, *first),
+                [] => ("", ""),
+            };
+            let words = words(surname);
+            let von_len = match words.iter().rposition(|word| starts_lowercase(word)) {
+                Some(end) if end + 1 < words.len() => end + 1,
+                _ => 0,
+            };
+            BibName {
+                first: first.to_string(),
+                von: words[..von_len].join(" "),
+                last: words[von_len..].join(" "),
+                jr: jr.to_string(),
+            }
+        }
+        [] => BibName::default(),
+    }
+}
+
+fn starts_lowercase(word: &str) -> bool {
+    word.chars().next().is_some_and(char::is_lowercase)
+}
+
+/// `Jean-Paul {\'E}mile` becomes `J.-P. {\'E}.`
+fn initials(first: &str) -> String {
+    split_top_level(first, char::is_whitespace)
+        .into_iter()
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            split_top_level(word, |ch| ch == '-')
+                .into_iter()
+                .filter_map(initial)
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn initial(word: &str) -> Option<String> {
+    if word.ends_with('.') && word.chars().count() <= 3 {
+        return Some(word.to_string());
+    }
+    if word.starts_with('{') {
+        let mut depth = 0usize;
+        for (idx, ch) in word.char_indices() {
+            match ch {
+                '{' => depth += 1,
+                '}' => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        return Some(format!("{}.", &word[..=idx]));
+                    }
+                }
+                _ => {}
+            }
+        }
+        return Some(format!("{}.", word));
+    }
+    word.chars().next().map(|ch| format!("{}.", ch))
+}
+
+/// Letters and digits of a field with TeX commands and braces removed, for
+/// sorting and label building.
+fn purify(input: &str) -> String {
+    let mut out = String::new();
+    let mut chars = input.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            while chars.peek().is_some_and(|next| next.is_ascii_alphabetic()) {
+                chars.next();
+            }
+        } else if ch.is_alphanumeric() || ch == ' ' {
+            out.push(ch);
+        }
+    }
+    collapse_whitespace(&out)
+}
+
+fn sort_key(entry: &BibEntry) -> String {
+    let names = primary_names(entry)
+        .iter()
+        .map(|name| format!("{} {}", purify(&name.surname()), purify(&name.first)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "{}    {}    {}",
+        if names.is_empty() {
+            purify(&entry.key)
+        } else {
+            names
+        },
+        entry.year().unwrap_or_default(),
+        purify(entry.field("title").unwrap_or_default())
+    )
+    .to_lowercase()
+}
+
+fn primary_names(entry: &BibEntry) -> Vec<BibName> {
+    let authors = entry.authors();
+    if authors.is_empty() {
+        entry.editors()
+    } else {
+        authors
+    }
+}
+
+fn alpha_labels(entries: &[&BibEntry]) -> Vec<String> {
+    let mut labels: Vec<String> = entries.iter().map(|entry| alpha_label(entry)).collect();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for label in &labels {
+        *counts.entry(label.clone()).or_default() += 1;
+    }
+    let mut seen: HashMap<String, u8> = HashMap::new();
+    for label in labels.iter_mut() {
+        if counts[label.as_str()] > 1 {
+            let next = seen.entry(label.clone()).or_insert(b'a');
+            label.push(*next as char);
+            *next = next.saturating_add(1);
+        }
+    }
+    labels
+}
+
+fn alpha_label(entry: &BibEntry) -> String {
+    let names: Vec<BibName> = primary_names(entry)
+        .into_iter()
+        .filter(|name| !name.is_others())
+        .collect();
+    let surname = |name: &BibName| purify(&name.surname()).replace(' ', "");
+    let mut label = match names.as_slice() {
+        [] => purify(&entry.key).chars().take(3).collect(),
+        [single] => surname(single).chars().take(3).collect(),
+        several => {
+            let mut label: String = several
+                .iter()
+                .take(if several.len() > 4 { 3 } else { 4 })
+                .flat_map(|name| {
+                    purify(&name.surname())
+                        .split(' ')
+                        .filter_map(|word| word.chars().next())
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            if several.len() > 4 {
+                label.push('+');
+            }
+            label
+        }
+    };
+    if let Some(year) = entry.year() {
+        let digits: String = year.chars().filter(char::is_ascii_digit).collect();
+        label.push_str(&digits[digits.len().saturating_sub(2)..]);
+    }
+    label
+}
+
+/// natbib-style `Author(Year)` label for author-year layouts.
+fn author_year_label(entry: &BibEntry) -> String {
+    let names = primary_names(entry);
+    let authors = match names.as_slice() {
+        [] => entry.key.clone(),
+        [single] => single.surname(),
+        [first, second] if !second.is_others() => {
+            format!("{} and {}", first.surname(), second.surname())
+        }
+        [first, ..] => format!("{} et~al.", first.surname()),
+    };
+    format!("{}({})", authors, entry.year().unwrap_or("n.d."))
+}
+
+/// `A, B, and C` with `et~al.` for a trailing `others`.
+fn join_names(names: &[BibName], format: impl Fn(&BibName) -> String) -> String {
+    let others = names.last().is_some_and(BibName::is_others);
+    let named: Vec<String> = names
+        .iter()
+        .filter(|name| !name.is_others())
+        .map(format)
+        .collect();
+    if others {
+        return match named.first() {
+            Some(first) => format!("{} et~al.", first),
+            None => String::new(),
+        };
+    }
+    match named.as_slice() {
+        [] => String::new(),
+        [single] => single.clone(),
+        [first, second] => format!("{} and {}", first, second),
+        [init @ .., last] => format!("{}, and {}", init.join(", "), last),
+    }
+}
+
+fn pages(entry: &BibEntry) -> Option<String> {
+    let pages = entry.field("pages")?;
+    if pages.contains("--") {
+        return Some(pages.to_string());
+    }
+    Some(pages.replace('-', "--"))
+}
+
+fn has_multiple_pages(pages: &str) -> bool {
+    pages.contains("--") || pages.contains(',') || pages.contains('+')
+}
+
+/// End a block with a period unless it already ends in punctuation.
+fn add_period(text: &str) -> String {
+    let trimmed = text.trim_end_matches('}');
+    if trimmed.ends_with(['.', '?', '!']) {
+        text.to_string()
+    } else {
+        format!("{}.", text)
+    }
+}
+
+fn emph(text: &str) -> String {
+    format!("\\emph{{{}}}", text)
+}
+
+fn url_block(entry: &BibEntry) -> Option<String> {
+    entry.field("url").map(|url| format!("\\url{{{}}}", url))
+}
+
+fn kind_is(entry: &BibEntry, kinds: &[&str]) -> bool {
+    kinds.contains(&entry.kind.as_str())
+}
+
+fn editors_suffix(names: &[BibName]) -> &'static str {
+    if names.len() > 1 {
+        ", editors"
+    } else {
+        ", editor"
+    }
+}
+
+/// Blocks of a `plain`/`unsrt`/`alpha` item, separated by `\newblock`.
+fn plain_blocks(entry: &BibEntry) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let authors = entry.authors();
+    if !authors.is_empty() {
+        blocks.push(join_names(&authors, BibName::full));
+    } else {
+        let editors = entry.editors();
+        if !editors.is_empty() {
+            blocks.push(join_names(&editors, BibName::full) + editors_suffix(&editors));
+        }
+    }
+    let whole_work = kind_is(entry, &["book", "booklet", "manual", "proceedings"]);
+    if let Some(title) = entry.field("title") {
+        blocks.push(if whole_work {
+            emph(title)
+        } else {
+            title.to_string()
+        });
+    }
+
+    let year = entry.year().map(str::to_string);
+    let mut details = Vec::new();
+    match entry.kind.as_str() {
+        "article" => {
+            if let Some(journal) = entry.field("journal") {
+                details.push(emph(journal));
+            }
+            let mut volume = entry.field("volume").unwrap_or_default().to_string();
+            if let Some(number) = entry.field("number") {
+                volume.push_str(&format!("({})", number));
+            }
+            match pages(entry) {
+                Some(pages) if volume.is_empty() => details.push(format!("pages {}", pages)),
+                Some(pages) => details.push(format!("{}:{}", volume, pages)),
+                None if !volume.is_empty() => details.push(volume),
+                None => {}
+            }
+        }
+        "inproceedings" | "conference" | "incollection" | "inbook" => {
+            let mut container = String::new();
+            let editors = if entry.field("author").is_some() {
+                entry.editors()
+            } else {
+                Vec::new()
+            };
+            if let Some(booktitle) = entry.field("booktitle") {
+                container.push_str("In ");
+                if !editors.is_empty() {
+                    container.push_str(&join_names(&editors, BibName::full));
+                    container.push_str(editors_suffix(&editors));
+                    container.push_str(", ");
+                }
+                container.push_str(&emph(booktitle));
+                details.push(container);
+            }
+            if let Some(volume) = entry.field("volume") {
+                details.push(format!("volume {}", volume));
+            }
+            if let Some(pages) = pages(entry) {
+                let prefix = if has_multiple_pages(&pages) {
+                    "pages"
+                } else {
+                    "page"
+                };
+                details.push(format!("{} {}", prefix, pages));
+            }
+            details.extend(entry.field("address").map(str::to_string));
+            details.extend(entry.field("organization").map(str::to_string));
+        }
+        "phdthesis" | "mastersthesis" | "thesis" => {
+            let default = if entry.kind == "mastersthesis" {
+                "Master's thesis"
+            } else {
+                "PhD thesis"
+            };
+            details.push(entry.field("type").unwrap_or(default).to_string());
+            details.extend(entry.field("school").map(str::to_string));
+            details.extend(entry.field("address").map(str::to_string));
+        }
+        "techreport" | "report" => {
+            let kind = entry.field("type").unwrap_or("Technical Report");
+            details.push(match entry.field("number") {
+                Some(number) => format!("{} {}", kind, number),
+                None => kind.to_string(),
+            });
+            details.extend(entry.field("institution").map(str::to_string));
+            details.extend(entry.field("address").map(str::to_string));
+        }
+        "book" | "booklet" | "manual" | "proceedings" => {
+            if let Some(volume) = entry.field("volume") {
+                let series = entry
+                    .field("series")
+                    .map(|series| format!(" of {}", emph(series)))
+                    .unwrap_or_default();
+                blocks.push(add_period(&format!("Volume {}{}", volume, series)));
+            }
+            details.extend(entry.field("publisher").map(str::to_string));
+            details.extend(entry.field("organization").map(str::to_string));
+            details.extend(entry.field("address").map(str::to_string));
+            details.extend(
+                entry
+                    .field("edition")
+                    .map(|edition| format!("{} edition", edition)),
+            );
+        }
+        _ => {
+            details.extend(entry.field("howpublished").map(str::to_string));
+            details.extend(entry.field("publisher").map(str::to_string));
+        }
+    }
+    let date = match (entry.field("month"), year) {
+        (Some(month), Some(year)) => Some(format!("{} {}", month, year)),
+        (None, year) => year,
+        (Some(_), None) => None,
+    };
+    details.extend(date);
+    // Proceedings name their publisher after the date, as in plain.bst.
+    if kind_is(
+        entry,
+        &["inproceedings", "conference", "incollection", "inbook"],
+    ) {
+        if let Some(publisher) = entry.field("publisher") {
+            blocks.push(details.join(", "));
+            details = vec![publisher.to_string()];
+        }
+    }
+    if !details.is_empty() {
+        blocks.push(details.join(", "));
+    }
+    blocks.extend(url_block(entry));
+    blocks.extend(entry.field("note").map(str::to_string));
+    blocks.iter().map(|block| add_period(block)).collect()
+}
+
+/// A single IEEE-style block, with an optional URL block after it.
+fn ieee_blocks(entry: &BibEntry) -> Vec<String> {
+    let mut parts = Vec::new();
+    let authors = entry.authors();
+    if !authors.is_empty() {
+        parts.push(join_names(&authors, BibName::initials_first));
+    } else {
+        let editors = entry.editors();
+        if !editors.is_empty() {
+            let suffix = if editors.len() > 1 { ", Eds." } else { ", Ed." };
+            parts.push(join_names(&editors, BibName::initials_first) + suffix);
+        }
+    }
+    let whole_work = kind_is(entry, &["book", "booklet", "manual", "proceedings"]);
+    let title = entry.field("title");
+    let year = entry.year().map(str::to_string);
+
+    let mut details: Vec<String> = Vec::new();
+    match entry.kind.as_str() {
+        "article" => {
+            details.extend(entry.field("journal").map(emph));
+            details.extend(
+                entry
+                    .field("volume")
+                    .map(|volume| format!("vol.~{}", volume)),
+            );
+            details.extend(
+                entry
+                    .field("number")
+                    .map(|number| format!("no.~{}", number)),
+            );
+            details.extend(pages(entry).map(|pages| ieee_pages(&pages)));
+            details.extend(year);
+        }
+        "inproceedings" | "conference" | "incollection" | "inbook" => {
+            details.extend(
+                entry
+                    .field("booktitle")
+                    .map(|booktitle| format!("in {}", emph(booktitle))),
+            );
+            details.extend(entry.field("address").map(str::to_string));
+            details.extend(entry.field("publisher").map(str::to_string));
+            details.extend(year);
+            details.extend(pages(entry).map(|pages| ieee_pages(&pages)));
+        }
+        "phdthesis" | "thesis" | "mastersthesis" => {
+            let default = if entry.kind == "mastersthesis" {
+                "M.S. thesis"
+            } else {
+                "Ph.D. dissertation"
+            };
+            details.push(entry.field("type").unwrap_or(default).to_string());
+            details.extend(entry.field("school").map(str::to_string));
+            details.extend(entry.field("address").map(str::to_string));
+            details.extend(year);
+        }
+        "techreport" | "report" => {
+            details.extend(entry.field("institution").map(str::to_string));
+            details.extend(entry.field("address").map(str::to_string));
+            details.push(match entry.field("number") {
+                Some(number) => format!("Tech. Rep.~{}", number),
+                None => "Tech. Rep.".to_string(),
+            });
+            details.extend(year);
+        }
+        "book" | "booklet" | "manual" | "proceedings" => {
+            details.extend(
+                entry
+                    .field("edition")
+                    .map(|edition| format!("{} ed.", edition)),
+            );
+            let publisher = match (entry.field("address"), entry.field("publisher")) {
+                (Some(address), Some(publisher)) => Some(format!("{}: {}", address, publisher)),
+                (address, publisher) => address.or(publisher).map(str::to_string),
+            };
+            details.extend(publisher);
+            details.extend(year);
+        }
+        _ => {
+            details.extend(entry.field("howpublished").map(str::to_string));
+            details.extend(year);
+        }
+    }
+
+    let mut out = parts.join(", ");
+    if let Some(title) = title {
+        if !out.is_empty() {
+            out.push_str(", ");
+        }
+        if whole_work {
+            out.push_str(&emph(title));
+            if !details.is_empty() {
+                out = add_period(&out);
+                out.push(' ');
+            }
+        } else {
+            let punct = if details.is_empty() { "." } else { "," };
+            out.push_str(&format!("``{}{}''", title, punct));
+            if !details.is_empty() {
+                out.push(' ');
+            }
+        }
+    } else if !out.is_empty() && !details.is_empty() {
+        out.push_str(", ");
+    }
+    out.push_str(&details.join(", "));
+
+    let mut blocks = vec![add_period(&out)];
+    blocks.extend(url_block(entry));
+    blocks.extend(entry.field("note").map(add_period));
+    blocks
+}
+
+fn ieee_pages(pages: &str) -> String {
+    if has_multiple_pages(pages) {
+        format!("pp.~{}", pages)
+    } else {
+        format!("p.~{}", pages)
+    }
+}
+
+/// Blocks of an APA-style item: author and year, title, source.
+fn apa_blocks(entry: &BibEntry) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let names = primary_names(entry);
+    let mut head = String::new();
+    if !names.is_empty() {
+        let others = names.last().is_some_and(BibName::is_others);
+        let named: Vec<String> = names
+            .iter()
+            .filter(|name| !name.is_others())
+            .map(BibName::last_first)
+            .collect();
+        head = match named.as_slice() {
+            [single] => single.clone(),
+            [init @ .., last] if !others => format!("{}, \\& {}", init.join(", "), last),
+            _ => named.join(", "),
+        };
+        if others {
+            head.push_str(", et~al.");
+        }
+        if entry.field("author").is_none() {
+            head.push_str(if names.len() > 1 { " (Eds.)" } else { " (Ed.)" });
+        }
+        head.push(' ');
+    }
+    head.push_str(&format!("({})", entry.year().unwrap_or("n.d.")));
+    blocks.push(head);
+
+    let whole_work = !kind_is(
+        entry,
+        &[
+            "article",
+            "inproceedings",
+            "conference",
+            "incollection",
+            "inbook",
+        ],
+    );
+    if let Some(title) = entry.field("title") {
+        let mut title = if whole_work {
+            emph(title)
+        } else {
+            title.to_string()
+        };
+        match entry.kind.as_str() {
+            "phdthesis" | "thesis" => {
+                let school = entry
+                    .field("school")
+                    .map(|school| format!(", {}", school))
+                    .unwrap_or_default();
+                title.push_str(&format!(" [Doctoral dissertation{}]", school));
+            }
+            "mastersthesis" => {
+                let school = entry
+                    .field("school")
+                    .map(|school| format!(", {}", school))
+                    .unwrap_or_default();
+                title.push_str(&format!(" [Master's thesis{}]", school));
+            }
+            "techreport" | "report" => {
+                if let Some(number) = entry.field("number") {
+                    title.push_str(&format!(" (Technical Report No.~{})", number));
+                }
+            }
+            _ => {}
+        }
+        blocks.push(title);
+    }
+
+    let mut source = String::new();
+    match entry.kind.as_str() {
+        "article" => {
+            if let Some(journal) = entry.field("journal") {
+                source.push_str(&emph(journal));
+                if let Some(volume) = entry.field("volume") {
+                    source.push_str(&format!(", {}", emph(volume)));
+                }
+                if let Some(number) = entry.field("number") {
+                    source.push_str(&format!("({})", number));
+                }
+                if let Some(pages) = pages(entry) {
+                    source.push_str(&format!(", {}", pages));
+                }
+            }
+        }
+        "inproceedings" | "conference" | "incollection" | "inbook" => {
+            if let Some(booktitle) = entry.field("booktitle") {
+                source.push_str("In ");
+                source.push_str(&emph(booktitle));
+                if let Some(pages) = pages(entry) {
+                    let prefix = if has_multiple_pages(&pages) {
+                        "pp."
+                    } else {
+                        "p."
+                    };
+                    source.push_str(&format!(" ({}~{})", prefix, pages));
+                }
+                if let Some(publisher) = entry.field("publisher") {
+                    source = add_period(&source);
+                    source.push(' ');
+                    source.push_str(publisher);
+                }
+            }
+        }
+        "techreport" | "report" => {
+            source.extend(entry.field("institution"));
+        }
+        "phdthesis" | "thesis" | "mastersthesis" => {}
+        _ => {
+            let publisher = entry
+                .field("publisher")
+                .or_else(|| entry.field("howpublished"));
+            source.extend(publisher);
+        }
+    }
+    if !source.is_empty() {
+        blocks.push(source);
+    }
+    let mut blocks: Vec<String> = blocks.iter().map(|block| add_period(block)).collect();
+    blocks.extend(url_block(entry));
+    blocks.extend(entry.field("note").map(add_period));
+    blocks
+}
@@ -0,0 +1,220 @@
+//! Post-pass that indents environment bodies and soft-wraps long lines of
+//! generated LaTeX. Verbatim-like environments are copied untouched.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Spaces added per nested environment.
+    pub indent_width: usize,
+    /// Wrap prose lines longer than this at word boundaries. `None` disables
+    /// wrapping.
+    pub max_line_length: Option<usize>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: 2,
+            max_line_length: Some(100),
+        }
+    }
+}
+
+const VERBATIM_ENVIRONMENTS: &[&str] = &[
+    "verbatim",
+    "verbatim*",
+    "Verbatim",
+    "lstlisting",
+    "minted",
+    "alltt",
+    "comment",
+    "filecontents",
+    "filecontents*",
+];
+
+/// Environments whose body is left at the enclosing indentation.
+const FLAT_ENVIRONMENTS: &[&str] = &["document"];
+
+pub fn format_latex(input: &str, options: &FormatOptions) -> String {
+    let mut out = String::with_capacity(input.len() + input.len() / 8);
+    let mut depth = 0usize;
+    let mut verbatim: Option<String> = None;
+
+    for line in input.lines() {
+        if let Some(env) = verbatim.as_deref() {
+            if line.trim_start().starts_with(&format!("\\end{{{}}}", env)) {
+                depth = depth.saturating_sub(1);
+                push_indent(&mut out, depth * options.indent_width);
+                out.push_str(line.trim());
+                out.push('\n');
+                verbatim = None;
+            } else {
+                out.push_str(line);
+                out.push('\n');
+            }
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            out.push('\n');
+            continue;
+        }
+
+        let (opens, closes) = count_environments(trimmed);
+        let leading_closes = leading_end_count(trimmed);
+        depth = depth.saturating_sub(leading_closes);
+        let indent = depth * options.indent_width;
+        depth = (depth + opens).saturating_sub(closes - leading_closes.min(closes));
+
+        match options.max_line_length {
+            Some(max) if can_wrap(trimmed) => push_wrapped(&mut out, trimmed, indent, max),
+            _ => {
+                push_indent(&mut out, indent);
+                out.push_str(trimmed);
+                out.push('\n');
+            }
+        }
+
+        if let Some(env) = opened_verbatim(trimmed) {
+            verbatim = Some(env);
+        }
+    }
+
+    if !input.ends_with('\n') && out.ends_with('\n') {
+        out.pop();
+    }
+    out
+}
+
+fn push_indent(out: &mut String, width: usize) {
+    for _ in 0..width {
+        out.push(' ');
+    }
+}
+
+/// Environment names of every `\begin{..}` / `\end{..}` on the line, in order.
+fn environment_markers(line: &str) -> Vec<(bool, &str)> {
+    let mut markers = Vec::new();
+    let mut rest = line;
+    while let Some(pos) = rest.find('\\') {
+        let after = &rest[pos + 1..];
+        let (is_begin, tail) = if let Some(tail) = after.strip_prefix("begin{") {
+            (true, tail)
+        } else if let Some(tail) = after.strip_prefix("end{") {
+            (false, tail)
+        } else {
+            // Skip the escaped character so `\\begin` is not misread.
+            let skip = after.chars().next().map_or(0, char::len_utf8);
+            rest = &after[skip..];
+            continue;
+        };
+        let Some(close) = tail.find('}') else {
+            break;
+        };
+        markers.push((is_begin, &tail[..close]));
+        rest = &tail[close + 1..];
+    }
+    markers
+}
+
+fn count_environments(line: &str) -> (usize, usize) {
+    let mut opens = 0;
+    let mut closes = 0;
+    for (is_begin, name) in environment_markers(line) {
+        if FLAT_ENVIRONMENTS.contains(&name) {
+            continue;
+        }
+        if is_begin {
+            opens += 1;
+        } else {
+            closes += 1;
+        }
+    }
+    (opens, closes)
+}
+
+/// Number of `\end{..}` markers before the first `\begin{..}`; these dedent
+/// the line itself rather than the lines after it.
+fn leading_end_count(line: &str) -> usize {
+    if !line.starts_with("\\end{") {
+        return 0;
+    }
+    environment_markers(line)
+        .into_iter()
+        .filter(|(_, name)| !FLAT_ENVIRONMENTS.contains(name))
+        .take_while(|(is_begin, _)| !is_begin)
+        .count()
+}
+
+/// A verbatim environment opened on this line and not closed on it.
+fn opened_verbatim(line: &str) -> Option<String> {
+    let mut open: Option<&str> = None;
+    for (is_begin, name) in environment_markers(line) {
+        if !VERBATIM_ENVIRONMENTS.contains(&name) {
+            continue;
+        }
+        if is_begin {
+            open = Some(name);
+        } else if open == Some(name) {
+            open = None;
+        }
+    }
+    open.map(str::to_string)
+}
+
+/// Comments, inline verbatim, and verbatim openers must keep their line
+/// structure.
+fn can_wrap(line: &str) -> bool {
+    if line.contains("\\verb") || line.contains("\\lstinline") || line.contains("\\mintinline") {
+        return false;
+    }
+    if opened_verbatim(line).is_some() {
+        return false;
+    }
+    let mut escaped = false;
+    for ch in line.chars() {
+        match ch {
+            '\\' => escaped = !escaped,
+            '%' if !escaped => return false,
+            _ => escaped = false,
+        }
+    }
+    true
+}
+
+fn push_wrapped(out: &mut String, line: &str, indent: usize, max: usize) {
+    let width = max.saturating_sub(indent).max(20);
+    let mut rest = line;
+    while rest.chars().count() > width {
+        let Some(split) = wrap_point(rest, width) else {
+            break;
+        };
+        push_indent(out, indent);
+        out.push_str(rest[..split].trim_end());
+        out.push('\n');
+        rest = rest[split..].trim_start();
+    }
+    push_indent(out, indent);
+    out.push_str(rest);
+    out.push('\n');
+}
+
+/// Byte offset of the last space at or before `width` characters that is
+/// safe to turn into a newline, falling back to the first one after it.
+fn wrap_point(line: &str, width: usize) -> Option<usize> {
+    let mut best = None;
+    let mut prev: Option<char> = None;
+    for (count, (idx, ch)) in line.char_indices().enumerate() {
+        if ch == ' ' && idx > 0 && prev != Some('\\') && prev != Some(' ') {
+            if count <= width {
+                best = Some(idx);
+            } else if best.is_none() {
+                return Some(idx);
+            } else {
+                break;
+            }
+        }
+        prev = Some(ch);
+    }
+    best
+}
@@ -0,0 +1,324 @@
+//! Hayagriva YAML bibliographies, converted to BibTeX entries.
+
+use serde_yaml::Value;
+
+use crate::bibtex::{BibDatabase, BibEntry};
+
+impl BibDatabase {
+    /// Read a Hayagriva `.yml` bibliography. Entries whose body is not a
+    /// mapping are skipped.
+    pub fn from_hayagriva(input: &str) -> Self {
+        let mut db = BibDatabase::default();
+        let Yaml::Map(entries) = parse_yaml(input) else {
+            return db;
+        };
+        for (key, body) in &entries {
+            if let Yaml::Map(fields) = body {
+                db.push(convert_entry(key, fields));
+            }
+        }
+        db
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Yaml {
+    Str(String),
+    List(Vec<Yaml>),
+    Map(Vec<(String, Yaml)>),
+}
+
+impl Yaml {
+    fn get(&self, key: &str) -> Option<&Yaml> {
+        match self {
+            Yaml::Map(fields) => fields
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Scalar text without the line break block scalars end in;
+    /// formattable strings written as `{value: ...}` count too.
+    fn text(&self) -> Option<&str> {
+        match self {
+            Yaml::Str(text) => Some(text.trim()).filter(|text| !text.is_empty()),
+            Yaml::Map(_) => self.get("value").and_then(Yaml::text),
+            Yaml::List(_) => None,
+        }
+    }
+
+    fn items(&self) -> Vec<&Yaml> {
+        match self {
+            Yaml::List(items) => items.iter().collect(),
+            other => vec![other],
+        }
+    }
+}
+
+/// The YAML as the entry conversion reads it: numbers, booleans and
+/// dates as their text, nulls and unreadable input as empty.
+fn parse_yaml(input: &str) -> Yaml {
+    serde_yaml::from_str(input)
+        .map(|value| from_value(&value))
+        .unwrap_or(Yaml::Map(Vec::new()))
+}
+
+fn from_value(value: &Value) -> Yaml {
+    match value {
+        Value::Null => Yaml::Str(String::new()),
+        Value::Bool(flag) => Yaml::Str(flag.to_string()),
+        Value::Number(number) => Yaml::Str(number.to_string()),
+        Value::String(text) => Yaml::Str(text.clone()),
+        Value::Sequence(items) => Yaml::List(items.iter().map(from_value).collect()),
+        Value::Mapping(fields) => Yaml::Map(
+            fields
+                .iter()
+                .filter_map(|(key, value)| Some((key_text(key)?, from_value(value))))
+                .collect(),
+        ),
+        Value::Tagged(tagged) => from_value(&tagged.value),
+    }
+}
+
+fn key_text(key: &Value) -> Option<String> {
+    match from_value(key) {
+        Yaml::Str(text) => Some(text),
+        _ => None,
+    }
+}
+
+/// Map one Hayagriva entry onto the closest BibTeX entry type.
+fn convert_entry(key: &str, fields: &[(String, Yaml)]) -> BibEntry {
+    let entry = Yaml::Map(fields.to_vec());
+    let kind = entry_type(&entry);
+    let parent = entry
+        .get("parent")
+        .and_then(|parent| parent.items().first().map(|first| (*first).clone()));
+    let parent_type = parent
+        .as_ref()
+        .and_then(entry_type_name)
+        .unwrap_or_default();
+    let bib_kind = match kind.as_str() {
+        "article" if matches!(parent_type.as_str(), "proceedings" | "conference") => {
+            "inproceedings"
+        }
+        "article" => "article",
+        "chapter" | "entry" if parent_type == "proceedings" => "inproceedings",
+        "chapter" | "entry" => "incollection",
+        "book" | "anthology" => "book",
+        "proceedings" => "proceedings",
+        "thesis" => "phdthesis",
+        "report" => "techreport",
+        "manuscript" => "unpublished",
+        _ => "misc",
+    };
+    let mut out = BibEntry::new(bib_kind, key);
+
+    out.set_text("author", &names(entry.get("author")));
+    out.set_text("editor", &names(entry.get("editor")));
+    out.set_text("title", &escape(entry.get("title").and_then(Yaml::text)));
+
+    let container = parent
+        .as_ref()
+        .and_then(|parent| parent.get("title")?.text());
+    match bib_kind {
+        "article" => out.set_text("journal", &escape(container)),
+        "inproceedings" | "incollection" => out.set_text("booktitle", &escape(container)),
+        _ => {}
+    }
+    if bib_kind == "incollection" || bib_kind == "inproceedings" {
+        if let Some(parent) = &parent {
+            if entry.get("editor").is_none() {
+                out.set_text("editor", &names(parent.get("editor")));
+            }
+        }
+    }
+
+    // Volume, issue, publisher and date often live on the parent.
+    let inherited = |name: &str| -> Option<&Yaml> {
+        entry
+            .get(name)
+            .or_else(|| parent.as_ref().and_then(|parent| parent.get(name)))
+    };
+    out.set_text("volume", &escape(inherited("volume").and_then(Yaml::text)));
+    out.set_text("number", &escape(inherited("issue").and_then(Yaml::text)));
+    out.set_text(
+        "edition",
+        &escape(entry.get("edition").and_then(Yaml::text)),
+    );
+    if let Some(pages) = entry.get("page-range").and_then(Yaml::text) {
+        out.set_text("pages", &pages.replace("--", "-").replace('-', "--"));
+    }
+
+    let publisher = inherited("publisher");
+    let publisher_name = publisher.and_then(|publisher| match publisher {
+        Yaml::Map(_) => publisher.get("name").and_then(Yaml::text),
+        _ => publisher.text(),
+    });
+    let location = publisher
+        .and_then(|publisher| publisher.get("location"))
+        .or_else(|| inherited("location"))
+        .and_then(Yaml::text);
+    let organization = inherited("organization").and_then(Yaml::text);
+    match bib_kind {
+        "phdthesis" => out.set_text("school", &escape(organization)),
+        "techreport" => out.set_text("institution", &escape(organization.or(publisher_name))),
+        _ => {
+            out.set_text("publisher", &escape(publisher_name));
+            out.set_text("organization", &escape(organization));
+        }
+    }
+    out.set_text("address", &escape(location));
+
+    if let Some(date) = inherited("date").and_then(Yaml::text) {
+        let mut parts = date.split('-');
+        out.set_text("year", parts.next().unwrap_or_default().trim());
+        let month = parts
+            .next()
+            .and_then(|month| month.trim().parse::<usize>().ok());
+        if let Some(month) = month.filter(|month| (1..=12).contains(month)) {
+            out.set_text("month", MONTHS[month - 1]);
+        }
+    }
+
+    let serial = entry.get("serial-number");
+    match serial {
+        Some(Yaml::Map(numbers)) => {
+            for (name, value) in numbers {
+                let field = match name.as_str() {
+                    "doi" | "isbn" | "issn" => name.as_str(),
+                    "arxiv" => "eprint",
+                    _ => "number",
+                };
+                out.set_text(field, &escape(value.text()));
+            }
+        }
+        Some(serial) if bib_kind == "techreport" => {
+            out.set_text("number", &escape(serial.text()));
+        }
+        _ => {}
+    }
+    if let Some(url) = entry.get("url") {
+        let url = match url {
+            Yaml::Map(_) => url.get("value").and_then(Yaml::text),
+            _ => url.text(),
+        };
+        out.set_text("url", url.unwrap_or_default());
+    }
+    if kind == "web" {
+        if let Some(container) = container {
+            out.set_text("howpublished", &escape(Some(container)));
+        }
+    }
+    out.set_text("type", &escape(entry.get("genre").and_then(Yaml::text)));
+    out.set_text("note", &escape(entry.get("note").and_then(Yaml::text)));
+    out.set_text(
+        "language",
+        &escape(entry.get("language").and_then(Yaml::text)),
+    );
+    out
+}
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+fn entry_type(entry: &Yaml) -> String {
+    entry_type_name(entry).unwrap_or_else(|| "misc".to_string())
+}
+
+fn entry_type_name(entry: &Yaml) -> Option<String> {
+    entry
+        .get("type")
+        .and_then(Yaml::text)
+        .map(|kind| kind.trim().to_lowercase())
+}
+
+/// Hayagriva names are `Last, First` strings, one per list item.
+fn names(value: Option<&Yaml>) -> String {
+    let Some(value) = value else {
+        return String::new();
+    };
+    value
+        .items()
+        .into_iter()
+        .filter_map(|name| match name {
+            Yaml::Map(_) => {
+                let last = name.get("name").and_then(Yaml::text)?;
+                match name.get("given-name").and_then(Yaml::text) {
+                    Some(given) => Some(format!("{}, {}", last, given)),
+                    None => Some(last.to_string()),
+                }
+            }
+            _ => name.text().map(str::to_string),
+        })
+        .map(|name| escape(Some(&name)))
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// Escape LaTeX specials in plain Hayagriva text.
+fn escape(text: Option<&str>) -> String {
+    let mut out = String::new();
+    for ch in text.unwrap_or_default().chars() {
+        match ch {
+            '&' | '%' | '$' | '#' | '_' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+impl BibEntry {
+    /// Set a field unless `value` is empty.
+    fn set_text(&mut self, name: &str, value: &str) {
+        if !value.trim().is_empty() {
+            self.set(name, value.trim());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_hayagriva_yaml() {
+        let yaml = parse_yaml(
+            "# refs\nkey:\n  type: Article\n  volume: 17\n  author: [\"Doan, T. D.\", 'O''Neil, A']\n  note: >\n    folded\n    text\n  parent:\n    - type: Periodical\n      title: \"Phys: Rev\" # comment\n  tags:\n  - a\n  - b\n",
+        );
+        let entry = yaml.get("key").unwrap();
+        assert_eq!(entry.get("type"), Some(&Yaml::Str("Article".to_string())));
+        assert_eq!(
+            entry.get("author"),
+            Some(&Yaml::List(vec![
+                Yaml::Str("Doan, T. D.".to_string()),
+                Yaml::Str("O'Neil, A".to_string()),
+            ]))
+        );
+        assert_eq!(entry.get("volume").and_then(Yaml::text), Some("17"));
+        assert_eq!(entry.get("note").and_then(Yaml::text), Some("folded text"));
+        let parent = entry.get("parent").unwrap().items()[0];
+        assert_eq!(parent.get("title").and_then(Yaml::text), Some("Phys: Rev"));
+        assert_eq!(entry.get("tags").unwrap().items().len(), 2);
+    }
+}
@@ -0,0 +1,26 @@
+//! The leading length of a longer Typst value, such as an inset.
+
+pub(crate) fn extract_length_token(raw: &str) -> Option<String> {
+    let mut token = String::new();
+    let mut started = false;
+    for ch in raw.chars() {
+        if !started {
+            if ch.is_ascii_digit() || ch == '.' || ch == '-' {
+                token.push(ch);
+                started = true;
+            }
+            continue;
+        }
+        if ch.is_ascii_digit() || ch == '.' || ch == '-' || ch.is_ascii_alphabetic() {
+            token.push(ch);
+            started = true;
+        } else if started {
+            break;
+        }
+    }
+    if token.is_empty() {
+        None
+    } else {
+        Some(token)
+    }
+}
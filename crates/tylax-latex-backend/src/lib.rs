@@ -1,9 +1,37 @@
 //! IR to LaTeX backend.
 
+use std::cell::{Cell, RefCell};
+use std::io;
+
+use tylax_color::Color;
 use tylax_ir::{
-    Alignment, Block, Document, EnvironmentBlock, Figure, FigureContent, Grid, Image, Inline,
-    ListKind, MathBlock, Table, TableCell,
+    partition, AlgorithmStep, Alignment, Block, BlockHints, CaptionPosition, Citation, CiteForm,
+    ColumnWidth, Document, EnvironmentBlock, Figure, FigureContent, Grid, Image, Inline,
+    LinkTarget, ListKind, MathBlock, OutlineTarget, Part, RuleAxis, Side, SideBySide, Slide, Table,
+    TableCell, TermItem, VerticalAlignment,
 };
+use tylax_units::{Length, Unit};
+
+mod algorithm;
+mod bibtex;
+mod format;
+mod hayagriva;
+mod length;
+mod math;
+mod meta;
+mod theorems;
+mod unicode;
+
+pub use bibtex::{render_thebibliography, BibDatabase, BibEntry, BibName, BibStyle};
+pub use format::{format_latex, FormatOptions};
+pub use meta::{class_options, push_meta_preamble, push_page_style};
+pub use theorems::{render_theorem_definitions, TheoremCounter, TheoremNumbering};
+pub use unicode::unicode_only_char;
+
+use algorithm::render_algorithm;
+use length::extract_length_token;
+use math::{convert_math_content_inline, convert_math_inline_context, convert_math_rows, MathRows};
+use unicode::transliterate;
 
 #[derive(Debug, Clone)]
 pub struct LatexRenderOptions {
@@ -19,16 +47,243 @@ pub struct LatexRenderOptions {
     pub cite_command: Option<String>,
     pub base_font_size_pt: Option<f64>,
     pub heading_numbering_none: bool,
+    /// Cell length (in characters) above which a table column is set as a
+    /// wrapping `p{}` column. `None` keeps every column at natural width.
+    pub table_wrap_threshold: Option<usize>,
+    pub code_backend: CodeBackend,
+    /// Render strong/emph spans that hold only math as `\boldsymbol` /
+    /// `\mathit` inside the formula instead of wrapping it in `\textbf`.
+    pub math_emphasis: bool,
+    /// Counter layout of the `\newtheorem` lines emitted in full-document mode.
+    pub theorem_numbering: TheoremNumbering,
+    /// Emit cleveref's `\cref` / `\crefrange` for cross-references and load
+    /// the package in full-document mode.
+    pub cleveref: bool,
+    /// In full-document mode, render a leading Abstract section as an
+    /// `abstract` environment instead of a numbered section.
+    pub detect_abstract: bool,
+    /// Entries to typeset as a `thebibliography` list in place of
+    /// `\bibliography`, for output that needs no external `.bib` file.
+    pub inline_bibliography: Option<BibDatabase>,
+    /// Delimiters around inline math and unnumbered display math.
+    pub math_delimiters: MathDelimiters,
+    /// Set tables as `longtable` so they break across pages. Captions and
+    /// labels move into the table, and header rows repeat on each page
+    /// unless the table says otherwise.
+    pub long_tables: bool,
+    /// Also set tables with more rows than this as `longtable`. Ignored in
+    /// two-column layouts, where longtable cannot be used.
+    pub long_table_rows: Option<usize>,
+    /// Set tables with `fr` columns as `tabularx` with `X` columns scaled to
+    /// their share, instead of `p{}` columns of a fixed share of the line.
+    pub tabularx: bool,
+    /// Package whose commands set citations and the bibliography. An inline
+    /// bibliography is always cited with natbib commands.
+    pub citation_package: CitationPackage,
+    /// Document class of full-document output, which also decides the
+    /// command each heading level becomes.
+    pub template: DocumentTemplate,
+    /// Label prefixes to replace, as `("fig:", "fig-")`, for a house scheme
+    /// of labels. The first prefix a label starts with applies, to the
+    /// label and the references to it alike. Citation keys are kept.
+    pub label_rewrites: Vec<(String, String)>,
+    /// How text outside ASCII reaches the output: as commands pdflatex can
+    /// set, or as written for a Unicode engine.
+    pub encoding_mode: EncodingMode,
+    /// How smart quotes are set.
+    pub quote_style: QuoteStyle,
+    /// Deepest heading level the table of contents lists, set as LaTeX's
+    /// `tocdepth`. `None` keeps the class's own.
+    pub toc_depth: Option<u8>,
 }
 
+pub const DEFAULT_TABLE_WRAP_THRESHOLD: usize = 60;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum TableStyle {
     Plain,
     Grid,
     Booktabs,
 }
 
+/// Environment used for raw code blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CodeBackend {
+    Verbatim,
+    Listings,
+    Minted,
+}
+
+/// How unnumbered math is delimited. Numbered and multi-line formulas always
+/// use their `equation` / `align` / `gather` environments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum MathDelimiters {
+    /// `$...$` inline and `\[...\]` for display math.
+    #[default]
+    Dollar,
+    /// `\(...\)` inline and `\[...\]` for display math.
+    Paren,
+    /// `\(...\)` inline and an `equation*` environment for display math.
+    Equation,
+}
+
+impl MathDelimiters {
+    fn inline(self) -> (&'static str, &'static str) {
+        match self {
+            MathDelimiters::Dollar => ("$", "$"),
+            MathDelimiters::Paren | MathDelimiters::Equation => ("\\(", "\\)"),
+        }
+    }
+}
+
+/// How smart quotes are set, each as an opening or closing mark after the
+/// text around it, as Typst does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum QuoteStyle {
+    /// `"` and `'` as written, which LaTeX sets as straight quotes.
+    #[default]
+    Straight,
+    /// TeX's ``` `` ``` and `''` pairs, and `` ` `` and `'`.
+    Tex,
+    /// csquotes' `\enquote{..}`, which follows the document's language.
+    Csquotes,
+}
+
+/// How characters outside ASCII are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum EncodingMode {
+    /// Accented letters, Greek, dashes, quotes and special spaces become
+    /// the commands that set them under pdflatex, with `fontenc`'s T1
+    /// encoding loaded when the text needs it. Scripts without such
+    /// commands, as CJK, are kept as written.
+    #[default]
+    Transliterate,
+    /// Text is kept as written and `fontspec` is loaded, for XeLaTeX or
+    /// LuaLaTeX.
+    Unicode,
+}
+
+/// Where citation commands come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum CitationPackage {
+    /// Plain `\cite`, and natbib's `\citep`, `\citet`, ... for citations
+    /// with notes or another form. natbib is loaded only when they occur.
+    #[default]
+    Natbib,
+    /// biblatex's `\autocite`, `\textcite`, ... with `\printbibliography`.
+    Biblatex,
+}
+
+impl CitationPackage {
+    /// The command that sets a citation in `form`. natbib has no full
+    /// citation; `\citet*`, with the whole author list, comes closest.
+    fn command(self, form: CiteForm) -> &'static str {
+        match (self, form) {
+            (_, CiteForm::Hidden) => "nocite",
+            (CitationPackage::Natbib, CiteForm::Normal) => "citep",
+            (CitationPackage::Natbib, CiteForm::Prose) => "citet",
+            (CitationPackage::Natbib, CiteForm::Full) => "citet*",
+            (CitationPackage::Natbib, CiteForm::Author) => "citeauthor",
+            (CitationPackage::Natbib, CiteForm::Year) => "citeyearpar",
+            (CitationPackage::Biblatex, CiteForm::Normal) => "autocite",
+            (CitationPackage::Biblatex, CiteForm::Prose) => "textcite",
+            (CitationPackage::Biblatex, CiteForm::Full) => "fullcite",
+            (CitationPackage::Biblatex, CiteForm::Author) => "citeauthor",
+            (CitationPackage::Biblatex, CiteForm::Year) => "parencite*",
+            _ => "cite",
+        }
+    }
+}
+
+/// The document class a full document is set in, with the preamble and
+/// heading commands that go with it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum DocumentTemplate {
+    #[default]
+    Article,
+    /// `report`: top-level headings become chapters.
+    Report,
+    /// `book`: top-level headings become chapters.
+    Book,
+    /// `IEEEtran` in its conference layout.
+    IeeeTran,
+    /// `acmart` in its `sigconf` layout.
+    Acm,
+    /// `beamer` slides: a top-level heading starts a section and a
+    /// second-level one a frame with that title.
+    Beamer,
+    /// A preamble of one's own, from `\documentclass` up to but not
+    /// including `\begin{document}`. Nothing is added to it, so it has to
+    /// load the packages the body uses.
+    Custom { preamble: String },
+}
+
+impl DocumentTemplate {
+    /// Sectioning commands of heading levels 1, 2, ..., with the depth
+    /// LaTeX gives each.
+    fn sections(&self) -> &'static [(&'static str, i32)] {
+        match self {
+            DocumentTemplate::Report | DocumentTemplate::Book => &[
+                ("chapter", 0),
+                ("section", 1),
+                ("subsection", 2),
+                ("subsubsection", 3),
+                ("paragraph", 4),
+            ],
+            _ => &[
+                ("section", 1),
+                ("subsection", 2),
+                ("subsubsection", 3),
+                ("paragraph", 4),
+            ],
+        }
+    }
+
+    /// The `tocdepth` the class sets: report and book list down to
+    /// subsections, the other classes down to subsubsections.
+    fn toc_depth(&self) -> i32 {
+        match self {
+            DocumentTemplate::Report | DocumentTemplate::Book => 2,
+            _ => 3,
+        }
+    }
+
+    /// Whether the class has an `abstract` environment.
+    fn has_abstract(&self) -> bool {
+        !matches!(self, DocumentTemplate::Book | DocumentTemplate::Beamer)
+    }
+}
+
+/// The `tocdepth` of the table of contents: the depth of the sectioning
+/// command of the deepest heading level it lists.
+fn toc_depth(options: &LatexRenderOptions) -> i32 {
+    let Some(level) = options.toc_depth else {
+        return options.template.toc_depth();
+    };
+    let sections = options.template.sections();
+    sections
+        .get(usize::from(level).saturating_sub(1))
+        .or(sections.last())
+        .map_or(3, |&(_, depth)| depth)
+}
+
+fn citation_package(options: &LatexRenderOptions) -> CitationPackage {
+    if options.inline_bibliography.is_some() {
+        CitationPackage::Natbib
+    } else {
+        options.citation_package
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum TableCaptionPosition {
     Top,
     Bottom,
@@ -49,158 +304,760 @@ impl Default for LatexRenderOptions {
             cite_command: None,
             base_font_size_pt: None,
             heading_numbering_none: false,
+            table_wrap_threshold: Some(DEFAULT_TABLE_WRAP_THRESHOLD),
+            code_backend: CodeBackend::Verbatim,
+            math_emphasis: false,
+            theorem_numbering: TheoremNumbering::default(),
+            cleveref: false,
+            detect_abstract: true,
+            inline_bibliography: None,
+            math_delimiters: MathDelimiters::Dollar,
+            long_tables: false,
+            long_table_rows: None,
+            tabularx: false,
+            citation_package: CitationPackage::Natbib,
+            template: DocumentTemplate::Article,
+            label_rewrites: Vec::new(),
+            encoding_mode: EncodingMode::Transliterate,
+            quote_style: QuoteStyle::Straight,
+            toc_depth: None,
         }
     }
 }
 
+/// Byte range of the output produced for the top-level block at `block`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderedBlock {
+    pub block: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
 pub fn render_document(doc: &Document, options: LatexRenderOptions) -> String {
-    let mut out = String::new();
-    if options.full_document {
-        out.push_str("\\documentclass{article}\n");
-        out.push_str("\\usepackage{amsmath,amssymb}\n");
-        out.push_str("\\usepackage{graphicx}\n");
-        out.push_str("\\usepackage{hyperref}\n");
-        out.push_str("\\hypersetup{hidelinks}\n");
-        out.push_str("\\usepackage[table]{xcolor}\n");
-        out.push_str("\\usepackage{booktabs}\n");
-        out.push_str("\\usepackage{enumitem}\n");
-        out.push_str("\\usepackage{multirow}\n");
-        out.push_str("\\usepackage{multicol}\n");
-        out.push_str("\\usepackage{array}\n");
-        if options.inline_wide_tables {
-            out.push_str("\\usepackage{caption}\n");
+    render_document_with_map(doc, options).0
+}
+
+/// Render the document and report where each top-level block landed in
+/// the output, so positions in the LaTeX can be traced back to the source.
+pub fn render_document_with_map(
+    doc: &Document,
+    options: LatexRenderOptions,
+) -> (String, Vec<RenderedBlock>) {
+    let mut out = String::with_capacity(estimated_output_len(doc));
+    let mut map = Vec::with_capacity(doc.blocks.len());
+    let mut renderer = DocumentRenderer::new(doc, options);
+    if renderer.options.full_document {
+        push_preamble(&mut out, doc, &renderer.options);
+    }
+    while let Some((block, chunk)) = renderer.next_block() {
+        if !map.is_empty() {
+            out.push_str(BLOCK_SEPARATOR);
+        }
+        let start = out.len();
+        out.push_str(&chunk);
+        map.push(RenderedBlock {
+            block,
+            start,
+            end: out.len(),
+        });
+    }
+    if renderer.open_frame {
+        out.push_str(BLOCK_SEPARATOR);
+        out.push_str(renderer.close_frame().trim_end());
+    }
+    if renderer.open_columns {
+        out.push('\n');
+        out.push_str(renderer.close_columns().trim_end());
+    }
+    if renderer.options.full_document {
+        out.push_str(DOCUMENT_END);
+    }
+    (out, map)
+}
+
+/// Render the document straight into `writer`, one top-level block at a
+/// time, without holding the whole output in memory.
+pub fn render_document_to<W: io::Write>(
+    doc: &Document,
+    options: LatexRenderOptions,
+    writer: &mut W,
+) -> io::Result<()> {
+    for chunk in DocumentRenderer::new(doc, options) {
+        writer.write_all(chunk.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// LaTeX split into a main file and a file for each part of the document,
+/// which the main file `\input`s.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SplitDocument {
+    pub main: String,
+    /// Name, without `.tex`, and content of the file of each part, in order.
+    pub parts: Vec<(String, String)>,
+}
+
+/// Render the document with each part [`partition`] finds at headings of
+/// `level` or above in a file of its own. The document is rendered as a
+/// whole, so labels, citations and counters are those of the single file.
+pub fn render_document_split(
+    doc: &Document,
+    options: LatexRenderOptions,
+    level: u8,
+) -> SplitDocument {
+    let (out, blocks) = render_document_with_map(doc, options);
+    split_rendered(&out, &blocks, &partition(doc, level))
+}
+
+/// Cut `out`, rendered with the block map `blocks`, into the files of
+/// `parts`. What comes before the first part and after the last block
+/// stays in the main file, around the `\input`s of the parts.
+pub fn split_rendered(out: &str, blocks: &[RenderedBlock], parts: &[Part]) -> SplitDocument {
+    // Where each part's output starts; a part that renders nothing has no
+    // file.
+    let cuts: Vec<(&str, usize)> = parts
+        .iter()
+        .filter_map(|part| {
+            let first = blocks
+                .iter()
+                .find(|rendered| part.blocks.contains(&rendered.block))?;
+            Some((part.name.as_str(), first.start))
+        })
+        .collect();
+    let Some(&(_, first_cut)) = cuts.first() else {
+        return SplitDocument {
+            main: out.to_string(),
+            parts: Vec::new(),
+        };
+    };
+    let body_end = blocks.last().map_or(out.len(), |rendered| rendered.end);
+    let mut split = SplitDocument::default();
+    let front = out[..first_cut].trim_end();
+    split.main.push_str(front);
+    if !front.is_empty() {
+        split.main.push_str(BLOCK_SEPARATOR);
+    }
+    for (idx, &(name, start)) in cuts.iter().enumerate() {
+        let end = cuts.get(idx + 1).map_or(body_end, |&(_, next)| next);
+        let mut content = out[start..end].trim_end().to_string();
+        content.push('\n');
+        split.parts.push((name.to_string(), content));
+        if idx > 0 {
+            split.main.push('\n');
         }
-        out.push_str("\\providecommand{\\textsubscript}[1]{$_{\\text{#1}}$}\n");
-        out.push_str("\\begin{document}\n\n");
+        split.main.push_str(&format!("\\input{{{}}}", name));
     }
+    split.main.push_str(&out[body_end..]);
+    split
+}
+
+const BLOCK_SEPARATOR: &str = "\n\n";
+const DOCUMENT_END: &str = "\n\\end{document}\n";
+
+/// Iterator over the rendered output of a document: the preamble, then one
+/// item per top-level block, then the closing lines. Concatenated, the items
+/// equal [`render_document`]'s result.
+///
+/// Citations are tracked per thread while rendering, so drive one renderer
+/// to completion before starting another on the same thread.
+pub struct DocumentRenderer<'a> {
+    doc: &'a Document,
+    options: LatexRenderOptions,
+    idx: usize,
+    stage: RenderStage,
+    first: bool,
+    /// Whether a beamer frame is open and has to be closed.
+    open_frame: bool,
+    /// Whether the `multicols` of a document set in more than two columns
+    /// is open and has to be closed.
+    open_columns: bool,
+}
 
-    let mut first = true;
-    let mut idx = 0usize;
-    while idx < doc.blocks.len() {
-        let mut rendered: Option<String> = None;
-        let mut consumed = 1usize;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RenderStage {
+    Preamble,
+    Body,
+    Done,
+}
 
-        if let Some(rendered_refs) = render_references_block(&doc.blocks, idx, &options) {
-            rendered = Some(rendered_refs);
-            consumed = 2;
-        } else if let Some(rendered_heading) = render_heading_with_label(&doc.blocks, idx, &options)
-        {
-            rendered = Some(rendered_heading);
-            consumed = 2;
-        } else if let Some(rendered_env) = render_environment_with_label(&doc.blocks, idx, &options)
-        {
-            rendered = Some(rendered_env);
-            consumed = 2;
-        } else if let Some(rendered_table) = render_table_with_label(&doc.blocks, idx, &options) {
-            rendered = Some(rendered_table);
-            consumed = 2;
+impl<'a> DocumentRenderer<'a> {
+    pub fn new(doc: &'a Document, mut options: LatexRenderOptions) -> Self {
+        CITED_KEYS.with(|cited| cited.borrow_mut().clear());
+        // A document set in two columns places its floats as one set with
+        // the `two_column` option does.
+        options.two_column |= doc.meta.columns == Some(2);
+        // Code set verbatim has no caption a list of listings could show.
+        if options.code_backend == CodeBackend::Verbatim && lists_code(doc) {
+            options.code_backend = CodeBackend::Listings;
+        }
+        Self {
+            doc,
+            options,
+            idx: 0,
+            stage: RenderStage::Preamble,
+            first: true,
+            open_frame: false,
+            open_columns: false,
+        }
+    }
+
+    /// Render the next top-level block that produces output, together with
+    /// its index. Special sections may consume several blocks at once.
+    fn next_block(&mut self) -> Option<(usize, String)> {
+        let blocks = &self.doc.blocks;
+        while self.idx < blocks.len() {
+            let idx = self.idx;
+            let _hints = HintsScope::enter(self.doc.block_hints(idx));
+            let (chunk, consumed) = render_special_section(blocks, idx, &self.options)
+                .unwrap_or_else(|| (render_block(&blocks[idx], &self.options), 1));
+            self.idx += consumed;
+            if !chunk.trim().is_empty() {
+                if self.options.template == DocumentTemplate::Beamer {
+                    return Some((idx, self.on_frame(&blocks[idx], consumed, chunk)));
+                }
+                if let Some(count) = self.doc.meta.columns.filter(|&count| count > 2) {
+                    return Some((idx, self.in_columns(&blocks[idx], count, chunk)));
+                }
+                return Some((idx, chunk));
+            }
         }
+        None
+    }
 
-        let chunk = rendered.unwrap_or_else(|| render_block(&doc.blocks[idx], &options));
-        if !chunk.trim().is_empty() {
-            if !first {
-                out.push_str("\n\n");
+    /// Put a slide's content on a frame. A second-level heading renders as
+    /// the start of a titled frame and a top-level one as a section between
+    /// frames; other content opens an untitled frame if none is open.
+    fn on_frame(&mut self, block: &Block, consumed: usize, chunk: String) -> String {
+        let mut out = String::with_capacity(chunk.len() + 32);
+        match block {
+            Block::Heading { level, .. } if consumed == 1 && *level <= 2 => {
+                out.push_str(self.close_frame());
+                self.open_frame = *level == 2;
             }
-            out.push_str(&chunk);
-            first = false;
+            // A slide is a frame of its own.
+            Block::Slide(_) => out.push_str(self.close_frame()),
+            _ if !self.open_frame => {
+                out.push_str("\\begin{frame}[fragile]\n");
+                self.open_frame = true;
+            }
+            _ => {}
         }
-        idx += consumed;
+        out.push_str(&chunk);
+        out
     }
 
-    if options.full_document {
-        out.push_str("\n\\end{document}\n");
+    /// The end of the open frame, if any.
+    fn close_frame(&mut self) -> &'static str {
+        if std::mem::take(&mut self.open_frame) {
+            "\\end{frame}\n\n"
+        } else {
+            ""
+        }
     }
 
-    out
+    /// Set a block of a document of more than two columns in `multicols`,
+    /// which LaTeX's classes have no option for. The environment is closed
+    /// around blocks that span the columns, as [`render_columns`] does.
+    fn in_columns(&mut self, block: &Block, count: usize, chunk: String) -> String {
+        let mut out = String::with_capacity(chunk.len() + 32);
+        if spans_columns(block, &self.options) {
+            out.push_str(self.close_columns());
+        } else if !self.open_columns {
+            out.push_str(&format!("\\begin{{multicols}}{{{}}}\n", count));
+            self.open_columns = true;
+        }
+        out.push_str(&chunk);
+        out
+    }
+
+    /// The end of the open `multicols`, if any.
+    fn close_columns(&mut self) -> &'static str {
+        if std::mem::take(&mut self.open_columns) {
+            "\\end{multicols}\n\n"
+        } else {
+            ""
+        }
+    }
 }
 
-fn render_heading_with_label(
-    blocks: &[Block],
-    idx: usize,
-    options: &LatexRenderOptions,
-) -> Option<String> {
-    let heading = blocks.get(idx)?;
-    let Block::Heading { .. } = heading else {
-        return None;
-    };
-    let label = blocks
-        .get(idx + 1)
-        .and_then(extract_label_from_paragraph)?;
-    let mut out = render_block(heading, options);
-    out.push_str("\n\\label{");
-    out.push_str(&escape_label(&label));
-    out.push('}');
-    Some(out)
+impl Iterator for DocumentRenderer<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if self.stage == RenderStage::Preamble {
+            self.stage = RenderStage::Body;
+            if self.options.full_document {
+                let mut out = String::new();
+                push_preamble(&mut out, self.doc, &self.options);
+                return Some(out);
+            }
+        }
+        if self.stage == RenderStage::Body {
+            if let Some((_, chunk)) = self.next_block() {
+                if std::mem::take(&mut self.first) {
+                    return Some(chunk);
+                }
+                let mut out = String::with_capacity(BLOCK_SEPARATOR.len() + chunk.len());
+                out.push_str(BLOCK_SEPARATOR);
+                out.push_str(&chunk);
+                return Some(out);
+            }
+            self.stage = RenderStage::Done;
+            let mut out = String::new();
+            if self.open_frame {
+                out.push_str(BLOCK_SEPARATOR);
+                out.push_str(self.close_frame().trim_end());
+            }
+            if self.open_columns {
+                out.push('\n');
+                out.push_str(self.close_columns().trim_end());
+            }
+            if self.options.full_document {
+                out.push_str(DOCUMENT_END);
+            }
+            if !out.is_empty() {
+                return Some(out);
+            }
+        }
+        None
+    }
 }
 
-fn render_environment_with_label(
-    blocks: &[Block],
-    idx: usize,
-    options: &LatexRenderOptions,
-) -> Option<String> {
-    let env_block = blocks.get(idx)?;
-    let Block::Environment(env) = env_block else {
-        return None;
+fn push_preamble(out: &mut String, doc: &Document, options: &LatexRenderOptions) {
+    let template = &options.template;
+    if let DocumentTemplate::Custom { preamble } = template {
+        out.push_str(preamble);
+        if !preamble.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str("\\begin{document}\n\n");
+        meta::push_front_matter(out, &doc.meta, options);
+        return;
+    }
+    // acmart and beamer load xcolor and hyperref themselves.
+    let class_loads_xcolor = matches!(template, DocumentTemplate::Acm | DocumentTemplate::Beamer);
+    if class_loads_xcolor {
+        out.push_str("\\PassOptionsToPackage{table}{xcolor}\n");
+    }
+    out.push_str(&meta::document_class(&doc.meta, template));
+    let (blocks, inlines) = document_nodes(doc);
+    match options.encoding_mode {
+        EncodingMode::Unicode => out.push_str("\\usepackage{fontspec}\n"),
+        EncodingMode::Transliterate => {
+            let non_ascii = inlines
+                .iter()
+                .any(|inline| matches!(inline, Inline::Text(text) if !text.is_ascii()))
+                || doc.meta.authors.iter().any(|author| {
+                    !author.name.is_ascii()
+                        || author.affiliation.as_deref().is_some_and(|a| !a.is_ascii())
+                });
+            if non_ascii {
+                out.push_str("\\usepackage[T1]{fontenc}\n");
+            }
+        }
+    }
+    meta::push_meta_preamble(out, &doc.meta, template);
+    if *template == DocumentTemplate::Acm {
+        // acmart's fonts bring their own symbols, which amssymb clashes with.
+        out.push_str("\\usepackage{amsmath}\n");
+    } else {
+        out.push_str("\\usepackage{amsmath,amssymb}\n");
+    }
+    out.push_str("\\usepackage{graphicx}\n");
+    if !class_loads_xcolor {
+        out.push_str("\\usepackage{hyperref}\n");
+    }
+    out.push_str("\\hypersetup{hidelinks}\n");
+    if !class_loads_xcolor {
+        out.push_str("\\usepackage[table]{xcolor}\n");
+    }
+    out.push_str("\\usepackage{booktabs}\n");
+    // enumitem breaks beamer's lists.
+    if *template != DocumentTemplate::Beamer {
+        out.push_str("\\usepackage{enumitem}\n");
+    }
+    out.push_str("\\usepackage{multirow}\n");
+    out.push_str("\\usepackage{multicol}\n");
+    out.push_str("\\usepackage{array}\n");
+    push_content_packages(out, doc, options);
+    if citation_package(options) == CitationPackage::Natbib {
+        let custom_cite = options
+            .cite_command
+            .as_deref()
+            .is_some_and(|cmd| cmd != "cite");
+        let natbib_cites = inlines.iter().any(|inline| match inline {
+            Inline::Cite(_) => custom_cite,
+            Inline::Citation(citation) => citation.form != CiteForm::Hidden,
+            _ => false,
+        });
+        if natbib_cites {
+            out.push_str("\\usepackage{natbib}\n");
+        }
+    }
+    // A list of listings comes from the package that sets the code.
+    let highlighted_code = lists_code(doc)
+        || blocks
+            .iter()
+            .any(|block| matches!(block, Block::CodeBlock { .. }))
+        || inlines
+            .iter()
+            .any(|inline| matches!(inline, Inline::Code { lang: Some(_), .. }));
+    if highlighted_code {
+        match options.code_backend {
+            CodeBackend::Verbatim => {}
+            CodeBackend::Listings => out.push_str(LISTINGS_SETUP),
+            CodeBackend::Minted => out.push_str("\\usepackage{minted}\n"),
+        }
+    }
+    meta::push_page_style(out, &doc.meta, options);
+    out.push_str("\\providecommand{\\textsubscript}[1]{$_{\\text{#1}}$}\n");
+    out.push_str("\\begin{document}\n\n");
+    meta::push_front_matter(out, &doc.meta, options);
+}
+
+/// Load the packages the content of `doc` needs whichever preamble it is
+/// set under: this document's own or one read from the Typst source. The
+/// theorem-like environments the document uses are declared here too, and
+/// nowhere else.
+pub fn push_content_packages(out: &mut String, doc: &Document, options: &LatexRenderOptions) {
+    let (blocks, inlines) = document_nodes(doc);
+    // amsthm goes before cleveref, and the declarations after it.
+    let theorems = render_theorem_definitions(doc, &options.theorem_numbering);
+    if !theorems.is_empty() {
+        out.push_str("\\usepackage{amsthm}\n");
+    }
+    let tables = || {
+        blocks.iter().filter_map(|block| match block {
+            Block::Table(table) => Some(table),
+            Block::Figure(Figure {
+                content: FigureContent::Table(table),
+                ..
+            }) => Some(&**table),
+            _ => None,
+        })
     };
-    let label = blocks
-        .get(idx + 1)
-        .and_then(extract_label_from_paragraph)?;
-    Some(render_environment(env, options, Some(&label)))
+    if tables().any(|table| is_long_table(table, options)) {
+        out.push_str("\\usepackage{longtable}\n");
+    }
+    let has_fraction_columns = tables().any(|table| {
+        table
+            .widths
+            .iter()
+            .any(|width| matches!(width, ColumnWidth::Fraction(_)))
+    });
+    if options.tabularx && has_fraction_columns {
+        out.push_str("\\usepackage{tabularx}\n");
+    }
+    // `\caption*` and `\captionsetup` come from the caption package.
+    let styled_captions = blocks.iter().any(|block| {
+        matches!(block, Block::Figure(figure) if figure.caption.is_some()
+            && (figure.unnumbered
+                || figure.caption_separator.is_some()
+                || figure.caption_position.is_some()))
+    });
+    if options.inline_wide_tables || styled_captions {
+        out.push_str("\\usepackage{caption}\n");
+    }
+    let has_subfigures = blocks.iter().any(|block| {
+        matches!(
+            block,
+            Block::Figure(Figure {
+                content: FigureContent::SubFigures { .. },
+                ..
+            })
+        )
+    });
+    if has_subfigures {
+        out.push_str("\\usepackage{subcaption}\n");
+    }
+    if blocks
+        .iter()
+        .any(|block| matches!(block, Block::Figure(figure) if figure.wrap.is_some()))
+    {
+        out.push_str("\\usepackage{wrapfig}\n");
+    }
+    if inlines.iter().any(|inline| matches!(inline, Inline::Quantity { .. })) {
+        out.push_str("\\usepackage{siunitx}\n");
+    }
+    if meta::shows_page_count(&doc.meta, &inlines, &options.template) {
+        out.push_str("\\usepackage{lastpage}\n");
+    }
+    if inlines.iter().any(|inline| matches!(inline, Inline::Chemical(_))) {
+        out.push_str("\\usepackage[version=4]{mhchem}\n");
+    }
+    if inlines
+        .iter()
+        .any(|inline| matches!(inline, Inline::Underline(_) | Inline::Strike(_)))
+    {
+        out.push_str("\\usepackage[normalem]{ulem}\n");
+    }
+    // A document listing its code has the code set with listings rather
+    // than verbatim; see `DocumentRenderer::new`.
+    if options.code_backend == CodeBackend::Verbatim && lists_code(doc) {
+        out.push_str(LISTINGS_SETUP);
+    }
+    if blocks.iter().any(|block| matches!(block, Block::Algorithm(_))) {
+        out.push_str("\\usepackage{algorithm}\n");
+        out.push_str("\\usepackage{algpseudocode}\n");
+    }
+    if options.quote_style == QuoteStyle::Csquotes
+        && inlines
+            .iter()
+            .any(|inline| matches!(inline, Inline::SmartQuote { .. }))
+    {
+        out.push_str("\\usepackage{csquotes}\n");
+    }
+    // A preamble read from the source loads natbib itself where it is
+    // needed; biblatex comes from here under any preamble.
+    if citation_package(options) == CitationPackage::Biblatex {
+        push_biblatex(out, &blocks, &inlines, options);
+    } else if blocks.iter().any(|block| is_untitled_bibliography(block)) {
+        // See `render_bibliography_block`.
+        out.push_str("\\usepackage{titlesec}\n");
+    }
+    // cleveref goes after hyperref and the other packages it patches.
+    if options.cleveref {
+        out.push_str("\\usepackage{cleveref}\n");
+    }
+    out.push_str(&theorems);
 }
 
-fn render_table_with_label(
-    blocks: &[Block],
-    idx: usize,
+fn is_untitled_bibliography(block: &Block) -> bool {
+    matches!(block, Block::Bibliography { title: Some(title), .. } if title.is_empty())
+}
+
+const LISTINGS_SETUP: &str = "\\usepackage{listings}\n\\lstset{basicstyle=\\ttfamily\\small, \
+                              columns=fullflexible, keepspaces=true}\n";
+
+/// Whether the document has a list of its listings.
+fn lists_code(doc: &Document) -> bool {
+    document_nodes(doc).0.iter().any(|block| {
+        matches!(
+            block,
+            Block::Outline {
+                target: OutlineTarget::Listings,
+                ..
+            }
+        )
+    })
+}
+
+/// Load biblatex in the style of the document's bibliography, with its files
+/// as resources. A Hayagriva `.yml` file is read from the `.bib` converted
+/// from it.
+fn push_biblatex(
+    out: &mut String,
+    blocks: &[&Block],
+    inlines: &[&Inline],
     options: &LatexRenderOptions,
-) -> Option<String> {
-    let table_block = blocks.get(idx)?;
-    let Block::Table(table) = table_block else {
-        return None;
+) {
+    let bibliographies: Vec<(&str, Option<&str>)> = blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Bibliography { file, style, .. } => Some((file.as_str(), style.as_deref())),
+            _ => None,
+        })
+        .collect();
+    let cites = inlines
+        .iter()
+        .any(|inline| matches!(inline, Inline::Cite(_) | Inline::Citation(_)));
+    if bibliographies.is_empty() && !cites {
+        return;
+    }
+    let style = bibliographies
+        .iter()
+        .find_map(|(_, style)| *style)
+        .or(options.bibliography_style_default.as_deref());
+    let style = match BibStyle::from_name(style.unwrap_or("plain")) {
+        BibStyle::Plain => "style=numeric",
+        BibStyle::Unsrt => "style=numeric,sorting=none",
+        BibStyle::Alpha => "style=alphabetic",
+        BibStyle::Ieee => "style=ieee",
+        BibStyle::Apa => "style=apa",
     };
-    let label = blocks
-        .get(idx + 1)
-        .and_then(extract_label_from_paragraph)?;
-    Some(render_table_block(table, options, Some(&label)))
+    out.push_str(&format!("\\usepackage[{}]{{biblatex}}\n", style));
+    for (file, _) in bibliographies {
+        for name in file.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let stem = name
+                .trim_end_matches(".bib")
+                .trim_end_matches(".yml")
+                .trim_end_matches(".yaml");
+            out.push_str(&format!("\\addbibresource{{{}.bib}}\n", stem));
+        }
+    }
 }
 
-fn extract_label_from_paragraph(block: &Block) -> Option<String> {
-    let Block::Paragraph(inlines) = block else {
-        return None;
-    };
-    let mut label: Option<String> = None;
-    for inline in inlines {
-        match inline {
-            Inline::Label(value) => {
-                if label.is_some() {
-                    return None;
+/// Every block and inline in the document, however deeply nested. Used to
+/// load packages only when their commands are emitted.
+fn document_nodes(doc: &Document) -> (Vec<&Block>, Vec<&Inline>) {
+    fn push_table<'a>(table: &'a Table, pending: &mut Vec<&'a Block>, inlines: &mut Vec<&'a Inline>) {
+        inlines.extend(table.caption.iter().flatten());
+        for cell in &table.cells {
+            inlines.extend(&cell.content);
+            pending.extend(cell.blocks.iter().flatten());
+        }
+    }
+
+    fn push_figure<'a>(
+        figure: &'a Figure,
+        pending: &mut Vec<&'a Block>,
+        inlines: &mut Vec<&'a Inline>,
+    ) {
+        inlines.extend(figure.caption.iter().flatten());
+        match &figure.content {
+            FigureContent::Table(table) => push_table(table, pending, inlines),
+            FigureContent::Raw(inner) => pending.extend(inner),
+            FigureContent::Image(_) => {}
+            FigureContent::SubFigures { figures, .. } => {
+                for figure in figures {
+                    push_figure(figure, pending, inlines);
                 }
-                label = Some(value.clone());
             }
-            Inline::Text(text) => {
-                if !text.trim().is_empty() {
-                    return None;
+            _ => {}
+        }
+    }
+
+    let mut pending: Vec<&Block> = doc.blocks.iter().collect();
+    pending.extend(&doc.meta.abstract_content);
+    let mut blocks: Vec<&Block> = Vec::new();
+    let mut inlines: Vec<&Inline> = doc.meta.title.iter().flatten().collect();
+    for decoration in doc.meta.header.iter().chain(&doc.meta.footer) {
+        inlines.extend(decoration.left.iter().chain(&decoration.center).chain(&decoration.right));
+    }
+    while let Some(block) = pending.pop() {
+        blocks.push(block);
+        match block {
+            Block::Paragraph(content) => inlines.extend(content),
+            Block::Heading { content, .. } => inlines.extend(content),
+            Block::List { items, .. } => pending.extend(items.iter().flatten()),
+            Block::TermList(items) => {
+                for item in items {
+                    inlines.extend(&item.term);
+                    pending.extend(&item.description);
                 }
             }
-            Inline::LineBreak => {}
-            _ => return None,
+            Block::Quote(inner) | Block::Align { blocks: inner, .. } => pending.extend(inner),
+            Block::Environment(env) => {
+                inlines.extend(env.title.iter().flatten());
+                pending.extend(&env.blocks);
+            }
+            Block::Box(inner) => pending.extend(&inner.blocks),
+            Block::Block(inner) => pending.extend(&inner.blocks),
+            Block::Columns(columns) => pending.extend(&columns.blocks),
+            Block::Grid(grid) => pending.extend(grid.cells.iter().flatten()),
+            Block::Slide(slide) => {
+                inlines.extend(slide.title.iter().flatten());
+                pending.extend(&slide.blocks);
+            }
+            Block::SideBySide(side) => pending.extend(side.columns.iter().flatten()),
+            Block::Outline { title, .. } | Block::Bibliography { title, .. } => {
+                inlines.extend(title.iter().flatten())
+            }
+            Block::Table(table) => push_table(table, &mut pending, &mut inlines),
+            Block::Figure(figure) => push_figure(figure, &mut pending, &mut inlines),
+            Block::Algorithm(algorithm) => {
+                inlines.extend(algorithm.caption.iter().flatten());
+                let mut steps: Vec<&AlgorithmStep> = algorithm.steps.iter().collect();
+                while let Some(step) = steps.pop() {
+                    inlines.extend(&step.content);
+                    inlines.extend(step.comment.iter().flatten());
+                    steps.extend(&step.body);
+                }
+            }
+            Block::VSpace(_)
+            | Block::MathBlock(_)
+            | Block::CodeBlock { .. }
+            | Block::Unsupported { .. } => {}
+            _ => {}
+        }
+    }
+    let mut idx = 0;
+    while let Some(inline) = inlines.get(idx).copied() {
+        idx += 1;
+        match inline {
+            Inline::Size { content, .. }
+            | Inline::Color { content, .. }
+            | Inline::Strong(content)
+            | Inline::Emph(content)
+            | Inline::Footnote(content)
+            | Inline::Superscript(content)
+            | Inline::Subscript(content)
+            | Inline::Underline(content)
+            | Inline::Overline(content)
+            | Inline::Strike(content)
+            | Inline::SmallCaps(content)
+            | Inline::Uppercase(content)
+            | Inline::Lowercase(content)
+            | Inline::Link { text: content, .. } => inlines.extend(content),
+            _ => {}
         }
     }
-    label
+    (blocks, inlines)
 }
 
-fn render_references_block(
+/// Rough size of the rendered output. LaTeX markup is somewhat longer than
+/// the Typst it came from, so size from the source extent when the frontend
+/// recorded spans and from the block count otherwise.
+fn estimated_output_len(doc: &Document) -> usize {
+    const PREAMBLE_LEN: usize = 512;
+    const BYTES_PER_BLOCK: usize = 96;
+    let source_len = doc
+        .spans
+        .iter()
+        .flatten()
+        .map(|span| span.end)
+        .max()
+        .unwrap_or(0);
+    let estimate = if source_len > 0 {
+        source_len + source_len / 4
+    } else {
+        doc.blocks.len() * BYTES_PER_BLOCK
+    };
+    estimate + PREAMBLE_LEN
+}
+
+fn push_begin(out: &mut String, env: &str) {
+    out.push_str("\\begin{");
+    out.push_str(env);
+    out.push_str("}\n");
+}
+
+fn push_end(out: &mut String, env: &str) {
+    out.push_str("\\end{");
+    out.push_str(env);
+    out.push('}');
+}
+
+/// Render a heading and the blocks under it as the LaTeX construct the
+/// section stands for, such as `thebibliography` for a References heading.
+/// Returns the output and the number of blocks it consumed.
+fn render_special_section(
     blocks: &[Block],
     idx: usize,
     options: &LatexRenderOptions,
-) -> Option<String> {
-    let heading = blocks.get(idx)?;
-    let Block::Heading { content, .. } = heading else {
+) -> Option<(String, usize)> {
+    let title = section_title(blocks.get(idx)?)?;
+    if is_references_title(&title) {
+        return render_references_block(blocks, idx, options).map(|out| (out, 2));
+    }
+    if is_abstract_title(&title) {
+        return render_abstract_block(blocks, idx, options);
+    }
+    None
+}
+
+fn section_title(block: &Block) -> Option<String> {
+    let Block::Heading { content, .. } = block else {
         return None;
     };
-    let title_plain = normalize_inline_whitespace(&plain_inline_text(content));
-    if !is_references_title(&title_plain) {
-        return None;
-    }
+    Some(normalize_inline_whitespace(&plain_inline_text(content)))
+}
+
+fn render_references_block(
+    blocks: &[Block],
+    idx: usize,
+    options: &LatexRenderOptions,
+) -> Option<String> {
     let next = blocks.get(idx + 1)?;
     match next {
         Block::Paragraph(inlines) => {
@@ -232,14 +1089,20 @@ fn render_references_block(
             out.push_str("\\end{thebibliography}");
             Some(out)
         }
-        Block::Bibliography { file, style } => {
-            let style = style
-                .as_deref()
-                .or_else(|| options.bibliography_style_default.as_deref());
-            Some(render_bibliography(file, style))
-        }
+        Block::Bibliography {
+            file,
+            style,
+            full,
+            title,
+        } => Some(render_bibliography_block(
+            file,
+            style.as_deref(),
+            *full,
+            title.as_deref(),
+            options,
+        )),
         Block::Environment(env) if env.name == "thebibliography" => {
-            Some(render_environment(env, options, None))
+            Some(render_environment(env, options))
         }
         _ => None,
     }
@@ -250,6 +1113,56 @@ fn is_references_title(title: &str) -> bool {
     lowered == "references" || lowered == "bibliography"
 }
 
+/// Render the document's first heading as an `abstract` environment when it
+/// is titled Abstract. The abstract runs up to the next heading; a short note
+/// without further headings only gives up its first paragraph.
+fn render_abstract_block(
+    blocks: &[Block],
+    idx: usize,
+    options: &LatexRenderOptions,
+) -> Option<(String, usize)> {
+    if !options.full_document || !options.detect_abstract || !options.template.has_abstract() {
+        return None;
+    }
+    if blocks[..idx]
+        .iter()
+        .any(|block| matches!(block, Block::Heading { .. }))
+    {
+        return None;
+    }
+    let rest = &blocks[idx + 1..];
+    let mut len = rest
+        .iter()
+        .take_while(|block| !matches!(block, Block::Heading { .. }))
+        .count();
+    if len == rest.len() {
+        len = rest
+            .iter()
+            .position(|block| matches!(block, Block::Paragraph(_)))
+            .map_or(0, |pos| pos + 1);
+    }
+    let body = rest[..len]
+        .iter()
+        .map(|block| render_block(block, options))
+        .filter(|chunk| !chunk.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    if body.is_empty() {
+        return None;
+    }
+    let mut out = String::with_capacity(body.len() + 32);
+    push_begin(&mut out, "abstract");
+    out.push_str(&body);
+    out.push('\n');
+    push_end(&mut out, "abstract");
+    Some((out, len + 1))
+}
+
+fn is_abstract_title(title: &str) -> bool {
+    let lowered = title.trim().trim_end_matches(['.', ':']).to_lowercase();
+    lowered == "abstract"
+}
+
 fn split_reference_entries(inlines: &[Inline]) -> Vec<Vec<Inline>> {
     let mut entries = split_inlines_on_linebreak(inlines);
     entries.retain(|entry| !entry.is_empty());
@@ -283,12 +1196,13 @@ fn split_inlines_on_linebreak(inlines: &[Inline]) -> Vec<Vec<Inline>> {
                     Inline::Emph(part)
                 });
             }
-            Inline::Link { text, url } => {
+            Inline::Link { text, url, target } => {
                 let url = url.clone();
                 split_wrapped_inlines(&mut entries, text, |part| {
                     Inline::Link {
                         text: part,
                         url: url.clone(),
+                        target: *target,
                     }
                 });
             }
@@ -474,12 +1388,13 @@ fn strip_reference_prefix_inlines_inner(inlines: &[Inline]) -> (Option<String>,
                 }
                 return (None, inlines.to_vec());
             }
-            Inline::Link { text, url } => {
+            Inline::Link { text, url, target } => {
                 let url = url.clone();
                 if let Some((label, inner)) =
                     strip_wrapped_reference_prefix(text, |part| Inline::Link {
                         text: part,
                         url: url.clone(),
+                        target: *target,
                     })
                 {
                     let mut out = Vec::new();
@@ -649,89 +1564,656 @@ fn skip_brace_group(s: &str, start: usize) -> Option<usize> {
     None
 }
 
-fn render_block(block: &Block, options: &LatexRenderOptions) -> String {
-    match block {
-        Block::Paragraph(inlines) => normalize_inline_whitespace(&render_inlines(inlines, options)),
-        Block::VSpace(size) => render_vspace(size),
-        Block::Heading {
-            level,
-            content,
-            numbered,
-        } => {
-            let numbered = *numbered && !options.heading_numbering_none;
-            let cmd = match *level {
-                1 => "\\section",
-                2 => "\\subsection",
-                3 => "\\subsubsection",
-                4 => "\\paragraph",
-                _ => "\\section",
-            };
-            let cmd = if numbered {
-                cmd.to_string()
-            } else {
-                format!("{}*", cmd)
-            };
-            format!(
-                "{}{{{}}}",
-                cmd,
-                normalize_inline_whitespace(&render_inlines(content, options))
-            )
-        }
-        Block::List { kind, items } => {
-            let env = match kind {
-                ListKind::Unordered => "itemize",
-                ListKind::Ordered => "enumerate",
-            };
-            let mut out = String::new();
-            out.push_str(&format!("\\begin{{{}}}\n", env));
-            for item in items {
-                out.push_str("  \\item ");
-                out.push_str(&render_blocks_inline(item, options));
-                out.push('\n');
-            }
-            out.push_str(&format!("\\end{{{}}}", env));
-            out
-        }
-        Block::MathBlock(math) => render_math_block(math, options),
-        Block::CodeBlock(content) => format!("\\begin{{verbatim}}\n{}\n\\end{{verbatim}}", content),
-        Block::Quote(blocks) => {
+/// How deeply blocks and inline spans may nest before the renderer stops
+/// descending. IR built by hand or by another frontend is not bounded by
+/// the Typst frontend's limit, and every level costs several stack frames.
+const MAX_RENDER_DEPTH: usize = 64;
+
+thread_local! {
+    static RENDER_DEPTH: Cell<usize> = const { Cell::new(0) };
+    /// Keys cited so far in the current document, in first-use order. An
+    /// inline bibliography lists these entries only.
+    static CITED_KEYS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    /// Hints of the top-level block being rendered.
+    static BLOCK_HINTS: RefCell<Option<BlockHints>> = const { RefCell::new(None) };
+    /// Footnotes met inside the table being rendered, in mark order.
+    static TABLE_NOTES: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+    /// Whether the inlines being rendered are the text of a footnote.
+    static IN_FOOTNOTE: Cell<bool> = const { Cell::new(false) };
+    /// Whether the content being rendered is that of a table cell.
+    static IN_CELL: Cell<bool> = const { Cell::new(false) };
+    /// Whether the inlines being rendered are a caption or a heading, which
+    /// LaTeX writes to the auxiliary files and reads again.
+    static IN_MOVING_ARGUMENT: Cell<bool> = const { Cell::new(false) };
+    /// Smart quotes opened in the inline run being rendered and the runs
+    /// around it, innermost last. `None` outside of any run.
+    static OPEN_QUOTES: RefCell<Option<Vec<OpenQuote>>> = const { RefCell::new(None) };
+}
+
+/// Makes a top-level block's hints visible while it renders.
+struct HintsScope;
+
+impl HintsScope {
+    fn enter(hints: Option<&BlockHints>) -> Self {
+        BLOCK_HINTS.with(|cell| *cell.borrow_mut() = hints.cloned());
+        HintsScope
+    }
+}
+
+impl Drop for HintsScope {
+    fn drop(&mut self) {
+        BLOCK_HINTS.with(|cell| *cell.borrow_mut() = None);
+    }
+}
+
+/// Hints for the float being rendered, if it is the hinted top-level block
+/// itself rather than something nested inside it.
+fn float_hints() -> Option<BlockHints> {
+    if RENDER_DEPTH.with(Cell::get) != 1 {
+        return None;
+    }
+    BLOCK_HINTS.with(|cell| cell.borrow().clone())
+}
+
+/// Apply hinted placement and width to a float's environment name and
+/// placement specifier.
+fn apply_float_hints(env: &mut String, placement: &mut String) {
+    let Some(hints) = float_hints() else {
+        return;
+    };
+    if let Some(hinted) = hints.placement.as_deref().map(str::trim) {
+        if !hinted.is_empty() && hinted.chars().all(|c| "htbpH!".contains(c)) {
+            *placement = hinted.to_string();
+        } else if let Some(mapped) = map_placement(hinted) {
+            *placement = mapped.to_string();
+        }
+    }
+    if hints.wide && !env.ends_with('*') {
+        env.push('*');
+        if placement == "h" || placement == "H" {
+            *placement = "t".to_string();
+        }
+    }
+}
+
+/// `\footnote` is lost inside a tabular, so while a table renders its notes
+/// are collected and only a `\footnotemark` is left in the cell. The texts
+/// follow the table as `\footnotetext`, numbered to match the marks.
+fn with_table_notes(long: bool, render: impl FnOnce() -> String) -> String {
+    // longtable handles `\footnote` itself, and an enclosing table collects
+    // the notes of tables nested in its cells.
+    if long || TABLE_NOTES.with(|notes| notes.borrow().is_some()) {
+        return render();
+    }
+    TABLE_NOTES.with(|notes| *notes.borrow_mut() = Some(Vec::new()));
+    let mut out = render();
+    let notes = TABLE_NOTES
+        .with(|notes| notes.borrow_mut().take())
+        .unwrap_or_default();
+    match notes.as_slice() {
+        [] => {}
+        [note] => out.push_str(&format!("\n\\footnotetext{{{}}}", note)),
+        _ => {
+            out.push_str(&format!("\n\\addtocounter{{footnote}}{{-{}}}", notes.len()));
+            for note in &notes {
+                out.push_str(&format!("\n\\stepcounter{{footnote}}\\footnotetext{{{}}}", note));
+            }
+        }
+    }
+    out
+}
+
+/// The mark for a footnote inside a table, or `None` outside one. A note
+/// repeated in the same table points back at its first mark.
+fn table_note_mark(note: String) -> Option<String> {
+    TABLE_NOTES.with(|notes| {
+        let mut notes = notes.borrow_mut();
+        let notes = notes.as_mut()?;
+        match notes.iter().position(|seen| *seen == note) {
+            Some(idx) => {
+                let back = notes.len() - idx - 1;
+                if back == 0 {
+                    Some("\\footnotemark[\\value{footnote}]".to_string())
+                } else {
+                    Some(format!("\\footnotemark[\\numexpr\\value{{footnote}}-{}\\relax]", back))
+                }
+            }
+            None => {
+                notes.push(note);
+                Some("\\footnotemark".to_string())
+            }
+        }
+    })
+}
+
+/// The text of a footnote. A footnote may be moved out of where it is
+/// written, e.g. from a float, so citations in it are protected, and it is
+/// one paragraph, so its equations are set inline.
+fn render_footnote(content: &[Inline], options: &LatexRenderOptions) -> String {
+    let outer = IN_FOOTNOTE.with(|flag| flag.replace(true));
+    let note = render_inlines(content, options);
+    IN_FOOTNOTE.with(|flag| flag.set(outer));
+    note
+}
+
+/// `\protect` before a fragile command inside a footnote, or nothing.
+fn footnote_protect() -> &'static str {
+    if IN_FOOTNOTE.with(Cell::get) {
+        "\\protect"
+    } else {
+        ""
+    }
+}
+
+/// Inlines set as a caption, a heading or a title. Verbatim commands such
+/// as `\lstinline` break in these moving arguments, so code in them stays
+/// `\texttt`.
+pub(crate) fn render_moving_argument(inlines: &[Inline], options: &LatexRenderOptions) -> String {
+    let outer = IN_MOVING_ARGUMENT.with(|flag| flag.replace(true));
+    let rendered = normalize_inline_whitespace(&render_inlines(inlines, options));
+    IN_MOVING_ARGUMENT.with(|flag| flag.set(outer));
+    rendered
+}
+
+/// Whether display environments such as `align` cannot be set here: in a
+/// footnote, which is one paragraph, or in a table cell.
+fn in_inline_context() -> bool {
+    IN_FOOTNOTE.with(Cell::get) || IN_CELL.with(Cell::get)
+}
+
+/// Math set inline where display environments cannot go, laid out in
+/// `rows`. Rows stay apart in an `aligned` or `gathered` block; a single row
+/// is set as it is, without the alignment points that would end a table
+/// cell.
+fn inline_context_math(math: &str, rows: MathRows, options: &LatexRenderOptions) -> String {
+    let (open, close) = options.math_delimiters.inline();
+    if !rows.line_breaks {
+        return format!("{}{}{}", open, math, close);
+    }
+    let env = if rows.align_points {
+        "aligned"
+    } else {
+        "gathered"
+    };
+    format!("{}\\begin{{{}}}{}\\end{{{}}}{}", open, env, math, env, close)
+}
+
+/// LaTeX math to set where display environments cannot go, and how its rows
+/// are laid out. A single row loses its alignment points; those of
+/// environments nested in the equation, such as a `pmatrix` or `cases`, are
+/// kept.
+fn latex_inline_context_math(math: &str) -> (String, MathRows) {
+    let (align_points, row_breaks) = top_level_marks(math);
+    let rows = MathRows {
+        align_points: !align_points.is_empty(),
+        line_breaks: row_breaks > 0,
+    };
+    let mut math = math.to_string();
+    if !rows.line_breaks {
+        for &offset in align_points.iter().rev() {
+            math.remove(offset);
+        }
+    }
+    (math, rows)
+}
+
+/// The byte offsets of the alignment points `&` of an equation's own rows
+/// and the number of its row breaks `\\`, leaving out escaped `\&` and those
+/// of the environments nested in it.
+fn top_level_marks(math: &str) -> (Vec<usize>, usize) {
+    let mut align_points = Vec::new();
+    let mut row_breaks = 0;
+    let mut depth = 0usize;
+    let mut chars = math.char_indices().peekable();
+    while let Some((offset, ch)) = chars.next() {
+        match ch {
+            '&' if depth == 0 => align_points.push(offset),
+            '\\' => match chars.peek() {
+                Some(&(_, '\\')) => {
+                    chars.next();
+                    if depth == 0 {
+                        row_breaks += 1;
+                    }
+                }
+                Some(&(start, next)) if next.is_ascii_alphabetic() => {
+                    let mut end = start;
+                    while let Some(&(idx, letter)) = chars.peek() {
+                        if !letter.is_ascii_alphabetic() {
+                            break;
+                        }
+                        end = idx + letter.len_utf8();
+                        chars.next();
+                    }
+                    match &math[start..end] {
+                        "begin" => depth += 1,
+                        "end" => depth = depth.saturating_sub(1),
+                        _ => {}
+                    }
+                }
+                // An escaped character, as `\&`.
+                Some(_) => {
+                    chars.next();
+                }
+                None => {}
+            },
+            _ => {}
+        }
+    }
+    (align_points, row_breaks)
+}
+
+fn record_citation(keys: &str) {
+    CITED_KEYS.with(|cited| {
+        let mut cited = cited.borrow_mut();
+        for key in keys.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+            if !cited.iter().any(|seen| seen == key) {
+                cited.push(key.to_string());
+            }
+        }
+    });
+}
+
+/// Marks one level of render recursion for as long as it is alive.
+struct RenderDepthGuard;
+
+impl RenderDepthGuard {
+    fn enter() -> Option<Self> {
+        let depth = RENDER_DEPTH.with(Cell::get);
+        if depth >= MAX_RENDER_DEPTH {
+            return None;
+        }
+        RENDER_DEPTH.with(|cell| cell.set(depth + 1));
+        Some(RenderDepthGuard)
+    }
+}
+
+impl Drop for RenderDepthGuard {
+    fn drop(&mut self) {
+        RENDER_DEPTH.with(|cell| cell.set(cell.get() - 1));
+    }
+}
+
+fn render_block(block: &Block, options: &LatexRenderOptions) -> String {
+    let Some(_guard) = RenderDepthGuard::enter() else {
+        return String::new();
+    };
+    match block {
+        Block::Paragraph(inlines) => normalize_inline_whitespace(&render_inlines(inlines, options)),
+        Block::VSpace(size) => render_vspace(size),
+        Block::Heading { .. } => render_heading(block, options),
+        Block::List { kind, items } => {
+            let mut out = String::new();
+            render_list(&mut out, *kind, items, options, 0);
+            out
+        }
+        Block::TermList(items) => {
+            let mut out = String::new();
+            render_term_list(&mut out, items, options, 0);
+            out
+        }
+        Block::MathBlock(math) => render_math_block(math, options),
+        Block::CodeBlock { lang, content } => {
+            render_code_block(lang.as_deref(), content, options)
+        }
+        Block::Quote(blocks) => {
             let mut out = String::new();
             out.push_str("\\begin{quote}\n");
-            out.push_str(&render_blocks_inline(blocks, options));
+            out.push_str(&render_blocks_separated(blocks, options));
             out.push_str("\n\\end{quote}");
             out
         }
-        Block::Align { alignment, blocks } => {
-            let env = match alignment {
-                Alignment::Left => "flushleft",
-                Alignment::Right => "flushright",
-                Alignment::Center => "center",
+        Block::Align {
+            alignment,
+            vertical,
+            blocks,
+        } => {
+            // Vertically the content is pushed by fill above and below it.
+            let (fill_above, fill_below) = match vertical {
+                Some(VerticalAlignment::Horizon) => (true, true),
+                Some(VerticalAlignment::Bottom) => (true, false),
+                Some(VerticalAlignment::Top) | None => (false, false),
+                _ => (false, false),
             };
             let mut out = String::new();
-            out.push_str(&format!("\\begin{{{}}}\n", env));
-            out.push_str(&render_blocks_inline(blocks, options));
-            out.push_str(&format!("\n\\end{{{}}}", env));
+            if fill_above {
+                out.push_str("\\vspace*{\\fill}\n");
+            }
+            let body = render_blocks_separated(blocks, options);
+            match alignment {
+                Some(alignment) => {
+                    let env = match alignment {
+                        Alignment::Left => "flushleft",
+                        Alignment::Right => "flushright",
+                        Alignment::Center => "center",
+                        _ => "center",
+                    };
+                    push_begin(&mut out, env);
+                    out.push_str(&body);
+                    out.push('\n');
+                    push_end(&mut out, env);
+                }
+                None => out.push_str(&body),
+            }
+            if fill_below {
+                out.push_str("\n\\vspace*{\\fill}");
+            }
             out
         }
-        Block::Table(table) => render_table_block(table, options, None),
+        Block::Table(table) => with_table_notes(is_long_table(table, options), || {
+            render_table_block(table, options)
+        }),
+        Block::Figure(
+            figure @ Figure {
+                content: FigureContent::Table(table),
+                ..
+            },
+        ) => with_table_notes(is_long_table(table, options), || {
+            render_figure(figure, options)
+        }),
         Block::Figure(figure) => render_figure(figure, options),
-        Block::Environment(env) => render_environment(env, options, None),
-        Block::Bibliography { file, style } => {
-            let style = style
-                .as_deref()
-                .or_else(|| options.bibliography_style_default.as_deref());
-            render_bibliography(file, style)
-        }
-        Block::Outline { title } => render_outline(title.as_deref(), options),
+        Block::Environment(env) => render_environment(env, options),
+        Block::Bibliography {
+            file,
+            style,
+            full,
+            title,
+        } => render_bibliography_block(file, style.as_deref(), *full, title.as_deref(), options),
+        Block::Outline { title, target } => render_outline(title.as_deref(), *target, options),
         Block::Box(b) => render_box(&b.blocks, options),
         Block::Block(b) => render_block_wrapper(&b.blocks, options),
         Block::Columns(columns) => render_columns(columns, options),
         Block::Grid(grid) => render_grid(grid, options),
+        Block::Algorithm(algorithm) => render_algorithm(algorithm, options),
+        Block::Slide(slide) => render_slide(slide, options),
+        Block::SideBySide(side) => render_side_by_side(side, options),
+        Block::Unsupported { source, reason } => render_unsupported(source, reason),
+        _ => String::new(),
     }
 }
 
-fn render_blocks_inline(blocks: &[Block], options: &LatexRenderOptions) -> String {
+/// Render a heading. Titles with markup get a `\texorpdfstring` so hyperref
+/// has plain text for the bookmark, and the `outlined` / `bookmarked` flags
+/// pick between the numbered, starred and hand-numbered forms: hyperref
+/// bookmarks exactly the headings that write a contents line.
+fn render_heading(block: &Block, options: &LatexRenderOptions) -> String {
+    let Block::Heading {
+        level,
+        content,
+        numbered,
+        label,
+        outlined,
+        bookmarked,
+    } = block
+    else {
+        return String::new();
+    };
+    let numbered = *numbered && !options.heading_numbering_none;
+    let sections = options.template.sections();
+    let (counter, depth) = match sections.get(usize::from(*level).wrapping_sub(1)) {
+        Some(&section) => section,
+        None => sections[0],
+    };
+    let cmd = format!("\\{}", counter);
+    let title = render_moving_argument(content, options);
+    if options.template == DocumentTemplate::Beamer && *level >= 2 {
+        // The renderer closes the frame before the next one.
+        let mut out = if *level == 2 {
+            format!("\\begin{{frame}}[fragile]{{{}}}", title)
+        } else {
+            format!("\\textbf{{{}}}", title)
+        };
+        if let Some(label) = label {
+            out.push_str("\n\\label{");
+            out.push_str(&output_label(label, options));
+            out.push('}');
+        }
+        return out;
+    }
+    let pdf_title = pdf_string(content);
+    let title = if title != pdf_title && !pdf_title.is_empty() {
+        format!("\\texorpdfstring{{{}}}{{{}}}", title, pdf_title)
+    } else {
+        title
+    };
+    let in_bookmarks = bookmarked.unwrap_or(*outlined);
+
+    let mut out = String::new();
+    if numbered && !*outlined && !in_bookmarks {
+        // Neither in the contents nor the bookmarks: step the counter by
+        // hand and set the number in a starred heading.
+        out.push_str(&format!("\\refstepcounter{{{}}}\n", counter));
+        out.push_str(&format!("{}*{{\\the{}\\quad {}}}", cmd, counter, title));
+    } else if numbered && !*outlined {
+        // Keep the bookmark but hide the contents line.
+        out.push_str(&format!(
+            "\\addtocontents{{toc}}{{\\protect\\setcounter{{tocdepth}}{{{}}}}}\n",
+            depth - 1
+        ));
+        out.push_str(&format!("{}{{{}}}\n", cmd, title));
+        out.push_str(&format!(
+            "\\addtocontents{{toc}}{{\\protect\\setcounter{{tocdepth}}{{{}}}}}",
+            toc_depth(options)
+        ));
+    } else if numbered {
+        out.push_str(&format!("{}{{{}}}", cmd, title));
+    } else {
+        if *bookmarked == Some(true) {
+            let anchor = match label {
+                Some(label) => output_label(label, options),
+                None => format!(
+                    "heading.{}",
+                    escape_label(&plain_inline_text(content).to_lowercase())
+                ),
+            };
+            out.push_str(&format!(
+                "\\pdfbookmark[{}]{{{}}}{{{}}}\n",
+                depth, pdf_title, anchor
+            ));
+        }
+        out.push_str(&format!("{}*{{{}}}", cmd, title));
+    }
+    if let Some(label) = label {
+        out.push_str("\n\\label{");
+        out.push_str(&output_label(label, options));
+        out.push('}');
+    }
+    out
+}
+
+/// Heading text as hyperref wants it in a PDF string: no formatting
+/// commands, math reduced to its symbols, special characters escaped.
+fn pdf_string(inlines: &[Inline]) -> String {
     let mut out = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) | Inline::Code { content: text, .. } => {
+                out.push_str(&escape_latex(text))
+            }
+            Inline::Math(content) | Inline::LatexMath(content) => {
+                let symbols: String = content
+                    .chars()
+                    .filter(|ch| !matches!(ch, '$' | '\\' | '{' | '}' | '^' | '_' | '"'))
+                    .collect();
+                out.push_str(&escape_latex(&symbols));
+            }
+            Inline::Size { content, .. } | Inline::Color { content, .. } => {
+                out.push_str(&pdf_string(content))
+            }
+            Inline::Strong(inner)
+            | Inline::Emph(inner)
+            | Inline::Superscript(inner)
+            | Inline::Subscript(inner)
+            | Inline::Underline(inner)
+            | Inline::Overline(inner)
+            | Inline::Strike(inner)
+            | Inline::SmallCaps(inner)
+            | Inline::Uppercase(inner)
+            | Inline::Lowercase(inner) => out.push_str(&pdf_string(inner)),
+            Inline::Link { text, .. } => out.push_str(&pdf_string(text)),
+            Inline::SmartQuote { double: true } => out.push('"'),
+            Inline::SmartQuote { double: false } => out.push('\''),
+            Inline::LineBreak => out.push(' '),
+            Inline::Quantity { .. } | Inline::Chemical(_) => {
+                out.push_str(&escape_latex(&plain_inline_text(std::slice::from_ref(inline))))
+            }
+            Inline::Footnote(_)
+            | Inline::RawLatex(_)
+            | Inline::Ref(_)
+            | Inline::RefRange { .. }
+            | Inline::SupplementedRef { .. }
+            | Inline::Label(_)
+            | Inline::Cite(_)
+            | Inline::Citation(_)
+            | Inline::Pause
+            | Inline::PageNumber(_)
+            | Inline::RunningHead(_) => {}
+            _ => {}
+        }
+    }
+    normalize_inline_whitespace(&out)
+}
+
+/// Render a list whose `\begin`/`\end` lines sit `depth` levels deep.
+fn render_list(
+    out: &mut String,
+    kind: ListKind,
+    items: &[Vec<Block>],
+    options: &LatexRenderOptions,
+    depth: usize,
+) {
+    let env = match kind {
+        ListKind::Unordered => "itemize",
+        ListKind::Ordered => "enumerate",
+        _ => "itemize",
+    };
+    // Items left empty at the end, as a dangling marker leaves, would each
+    // set a bare bullet; a list keeps its first item so it is never empty.
+    let mut items = items;
+    while let [rest @ .., last] = items {
+        if rest.is_empty() || !is_blank_item(last) {
+            break;
+        }
+        items = rest;
+    }
+    let indent = "  ".repeat(depth);
+    out.push_str(&indent);
+    push_begin(out, env);
+    for item in items {
+        out.push_str(&indent);
+        out.push_str("  \\item");
+        push_list_item_body(out, item, options, depth);
+    }
+    out.push_str(&indent);
+    push_end(out, env);
+}
+
+/// Whether a list item holds nothing but whitespace.
+fn is_blank_item(blocks: &[Block]) -> bool {
+    blocks.iter().all(|block| {
+        matches!(block, Block::Paragraph(inlines) if inlines.iter().all(|inline| match inline {
+            Inline::Text(text) => text.trim().is_empty(),
+            Inline::LineBreak => true,
+            _ => false,
+        }))
+    })
+}
+
+fn render_term_list(
+    out: &mut String,
+    items: &[TermItem],
+    options: &LatexRenderOptions,
+    depth: usize,
+) {
+    let indent = "  ".repeat(depth);
+    out.push_str(&indent);
+    push_begin(out, "description");
+    for item in items {
+        let term = normalize_inline_whitespace(&render_inlines(&item.term, options));
+        out.push_str(&indent);
+        out.push_str("  \\item[");
+        // A bare `]` would end the optional argument early.
+        if term.contains(']') {
+            out.push('{');
+            out.push_str(&term);
+            out.push('}');
+        } else {
+            out.push_str(&term);
+        }
+        out.push(']');
+        push_list_item_body(out, &item.description, options, depth);
+    }
+    out.push_str(&indent);
+    push_end(out, "description");
+}
+
+/// Lists nested in an item start on their own line one level further in,
+/// and any blocks after them continue the item on a new line.
+fn push_list_item_body(
+    out: &mut String,
+    blocks: &[Block],
+    options: &LatexRenderOptions,
+    depth: usize,
+) {
+    let Some(_guard) = RenderDepthGuard::enter() else {
+        out.push('\n');
+        return;
+    };
+    let indent = "  ".repeat(depth);
+    let mut start = 0;
+    for (idx, block) in blocks.iter().enumerate() {
+        if !matches!(block, Block::List { .. } | Block::TermList(_)) {
+            continue;
+        }
+        push_list_item_text(out, &blocks[start..idx], options, &indent, start > 0);
+        out.push('\n');
+        match block {
+            Block::List { kind, items } => render_list(out, *kind, items, options, depth + 1),
+            Block::TermList(items) => render_term_list(out, items, options, depth + 1),
+            _ => {}
+        }
+        start = idx + 1;
+    }
+    push_list_item_text(out, &blocks[start..], options, &indent, start > 0);
+    if out.ends_with("\\item") {
+        out.push(' ');
+    }
+    out.push('\n');
+}
+
+fn push_list_item_text(
+    out: &mut String,
+    blocks: &[Block],
+    options: &LatexRenderOptions,
+    indent: &str,
+    after_list: bool,
+) {
+    let text = render_blocks_separated(blocks, options);
+    if text.trim().is_empty() {
+        return;
+    }
+    if after_list {
+        out.push('\n');
+        out.push_str(indent);
+        out.push_str("  ");
+    } else {
+        out.push(' ');
+    }
+    out.push_str(&text);
+}
+
+/// Render blocks as paragraphs of their own, separated by blank lines as at
+/// the top level, so display math and lists keep their own lines.
+fn render_blocks_separated(blocks: &[Block], options: &LatexRenderOptions) -> String {
+    blocks
+        .iter()
+        .map(|block| render_block(block, options))
+        .filter(|chunk| !chunk.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join(BLOCK_SEPARATOR)
+}
+
+fn render_blocks_inline(blocks: &[Block], options: &LatexRenderOptions) -> String {
+    let mut out = String::with_capacity(blocks.len() * 64);
     for (i, block) in blocks.iter().enumerate() {
         if i > 0 {
             let prev = &blocks[i - 1];
@@ -746,15 +2228,169 @@ fn render_blocks_inline(blocks: &[Block], options: &LatexRenderOptions) -> Strin
     out
 }
 
+/// Comment out source the frontend could not lower, fenced so it is easy to
+/// find and port by hand.
+fn render_unsupported(source: &str, reason: &str) -> String {
+    let mut out = format!("% BEGIN unsupported: {}", reason.replace('\n', " "));
+    for line in source.trim_end().lines() {
+        out.push_str("\n% ");
+        out.push_str(line);
+    }
+    out.push_str("\n% END unsupported");
+    out
+}
+
+/// Languages listings ships a driver for, keyed by the Typst tag. Other tags
+/// are left off, since listings stops on a language it cannot load.
+const LISTINGS_LANGUAGES: &[(&str, &str)] = &[
+    ("bash", "bash"),
+    ("c", "C"),
+    ("c++", "C++"),
+    ("cpp", "C++"),
+    ("cs", "[Sharp]C"),
+    ("csharp", "[Sharp]C"),
+    ("erlang", "erlang"),
+    ("fortran", "Fortran"),
+    ("haskell", "Haskell"),
+    ("hs", "Haskell"),
+    ("html", "HTML"),
+    ("java", "Java"),
+    ("latex", "TeX"),
+    ("lisp", "Lisp"),
+    ("make", "make"),
+    ("makefile", "make"),
+    ("matlab", "Matlab"),
+    ("ocaml", "[Objective]Caml"),
+    ("pascal", "Pascal"),
+    ("perl", "Perl"),
+    ("php", "PHP"),
+    ("prolog", "Prolog"),
+    ("py", "Python"),
+    ("python", "Python"),
+    ("r", "R"),
+    ("rb", "Ruby"),
+    ("ruby", "Ruby"),
+    ("scala", "Scala"),
+    ("sh", "sh"),
+    ("shell", "sh"),
+    ("sql", "SQL"),
+    ("tcl", "tcl"),
+    ("tex", "TeX"),
+    ("verilog", "Verilog"),
+    ("vhdl", "VHDL"),
+    ("xml", "XML"),
+];
+
+fn render_code_block(lang: Option<&str>, content: &str, options: &LatexRenderOptions) -> String {
+    match options.code_backend {
+        CodeBackend::Verbatim => format!("\\begin{{verbatim}}\n{}\n\\end{{verbatim}}", content),
+        CodeBackend::Listings => match lang.and_then(listings_language) {
+            Some(language) => format!(
+                "\\begin{{lstlisting}}[language={{{}}}]\n{}\n\\end{{lstlisting}}",
+                language, content
+            ),
+            None => format!("\\begin{{lstlisting}}\n{}\n\\end{{lstlisting}}", content),
+        },
+        CodeBackend::Minted => format!(
+            "\\begin{{minted}}{{{}}}\n{}\n\\end{{minted}}",
+            minted_lexer(lang),
+            content
+        ),
+    }
+}
+
+/// Inline code tagged with a language goes through `\lstinline` or
+/// `\mintinline` when a highlighting backend is selected; untagged code,
+/// code the delimiters cannot enclose and code in a moving argument stay
+/// `\texttt`.
+fn push_inline_code(
+    out: &mut String,
+    lang: Option<&str>,
+    content: &str,
+    options: &LatexRenderOptions,
+) {
+    let delimiter = ['|', '!', '+', '@', '#']
+        .into_iter()
+        .find(|delim| !content.contains(*delim));
+    let delimiter = delimiter.filter(|_| !IN_MOVING_ARGUMENT.with(Cell::get));
+    match (lang, delimiter, options.code_backend) {
+        (Some(lang), Some(delim), CodeBackend::Listings) if !content.contains('\n') => {
+            out.push_str("\\lstinline");
+            if let Some(language) = listings_language(lang) {
+                out.push_str(&format!("[language={{{}}}]", language));
+            }
+            out.push(delim);
+            out.push_str(content);
+            out.push(delim);
+        }
+        (Some(lang), Some(delim), CodeBackend::Minted) if !content.contains('\n') => {
+            out.push_str(&format!("\\mintinline{{{}}}", minted_lexer(Some(lang))));
+            out.push(delim);
+            out.push_str(content);
+            out.push(delim);
+        }
+        _ => {
+            out.push_str("\\texttt{");
+            out.push_str(&escape_text(content, options));
+            out.push('}');
+        }
+    }
+}
+
+fn listings_language(lang: &str) -> Option<&'static str> {
+    let lang = lang.to_ascii_lowercase();
+    LISTINGS_LANGUAGES
+        .iter()
+        .find(|(tag, _)| *tag == lang)
+        .map(|(_, name)| *name)
+}
+
+/// Pygments knows most tags by their Typst name; anything that is not a
+/// plain lexer name falls back to unhighlighted text.
+fn minted_lexer(lang: Option<&str>) -> String {
+    lang.map(str::to_ascii_lowercase)
+        .filter(|lang| {
+            !lang.is_empty()
+                && lang
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '#'))
+        })
+        .unwrap_or_else(|| "text".to_string())
+}
+
 fn render_math_block(math: &MathBlock, options: &LatexRenderOptions) -> String {
     let raw = math.content.trim();
-    let content = convert_math_content(raw);
+    if in_inline_context() {
+        let (content, rows) = if math.latex {
+            latex_inline_context_math(raw)
+        } else {
+            convert_math_inline_context(raw)
+        };
+        let mut out = inline_context_math(content.trim(), rows, options);
+        // The equation keeps its number, set after it, so that references
+        // to its label resolve.
+        if let Some(label) = &math.label {
+            out.push_str(&format!(
+                "~\\refstepcounter{{equation}}(\\theequation)\\label{{{}}}",
+                output_label(label, options)
+            ));
+        }
+        return out;
+    }
+    let (content, rows) = if math.latex {
+        let (align_points, row_breaks) = top_level_marks(raw);
+        let rows = MathRows {
+            align_points: !align_points.is_empty(),
+            line_breaks: row_breaks > 0,
+        };
+        (raw.to_string(), rows)
+    } else {
+        convert_math_rows(raw)
+    };
     let content = content.trim();
-    let has_alignment = raw.contains('&');
-    let has_line_break = raw.contains("\\\\");
     let mut out = String::new();
-    if has_alignment || has_line_break {
-        let env = if has_alignment {
+    if rows.align_points || rows.line_breaks {
+        let env = if rows.align_points {
             if options.number_equations {
                 "align"
             } else {
@@ -765,14 +2401,15 @@ fn render_math_block(math: &MathBlock, options: &LatexRenderOptions) -> String {
         } else {
             "gather*"
         };
-        out.push_str(&format!("\\begin{{{}}}\n", env));
+        push_begin(&mut out, env);
         out.push_str(content);
         if let Some(label) = &math.label {
             out.push_str("\n\\label{");
-            out.push_str(&escape_label(label));
+            out.push_str(&output_label(label, options));
             out.push('}');
         }
-        out.push_str(&format!("\n\\end{{{}}}", env));
+        out.push('\n');
+        push_end(&mut out, env);
         return out;
     }
     if options.number_equations {
@@ -780,10 +2417,14 @@ fn render_math_block(math: &MathBlock, options: &LatexRenderOptions) -> String {
         out.push_str(content);
         if let Some(label) = &math.label {
             out.push_str("\n\\label{");
-            out.push_str(&escape_label(label));
+            out.push_str(&output_label(label, options));
             out.push('}');
         }
         out.push_str("\n\\end{equation}");
+    } else if options.math_delimiters == MathDelimiters::Equation {
+        out.push_str("\\begin{equation*}\n");
+        out.push_str(content);
+        out.push_str("\n\\end{equation*}");
     } else {
         out.push_str("\\[\n");
         out.push_str(content);
@@ -792,1490 +2433,2668 @@ fn render_math_block(math: &MathBlock, options: &LatexRenderOptions) -> String {
     out
 }
 
-fn convert_math_content(input: &str) -> String {
+fn render_environment(env: &EnvironmentBlock, options: &LatexRenderOptions) -> String {
+    let name = sanitize_env_name(&env.name);
     let mut out = String::new();
-    let mut i = 0usize;
-    while i < input.len() {
-        if let Some(paren_idx) = match_call_at(input, i, "text") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_text_call(&args));
-                i = end_idx;
-                continue;
+    out.push_str("\\begin{");
+    out.push_str(&name);
+    out.push('}');
+    if let Some(title) = env.title.as_ref().filter(|_| name != "proof") {
+        out.push('[');
+        out.push_str(&render_moving_argument(title, options));
+        out.push(']');
+    }
+    for arg in &env.args {
+        out.push_str(arg);
+    }
+    out.push('\n');
+    out.push_str(&render_blocks_separated(&env.blocks, options));
+    if let Some(label) = &env.label {
+        out.push_str("\n\\label{");
+        out.push_str(&output_label(label, options));
+        out.push('}');
+    }
+    out.push('\n');
+    push_end(&mut out, &name);
+    out
+}
+
+fn sanitize_env_name(name: &str) -> String {
+    let lowered = name.trim().to_lowercase();
+    if lowered.is_empty() {
+        return "theorem".to_string();
+    }
+    lowered
+        .chars()
+        .filter(|ch| ch.is_ascii_alphanumeric())
+        .collect::<String>()
+}
+
+fn render_inline_size(size: &str, content: String, options: &LatexRenderOptions) -> String {
+    if content.is_empty() {
+        return String::new();
+    }
+    let base_pt = options.base_font_size_pt.unwrap_or(10.0);
+    let size_pt = Length::parse_typst(size).and_then(|size| size.to_pt(base_pt));
+    if let Some(size_pt) = size_pt {
+        if base_pt > 0.0 {
+            let scale = size_pt / base_pt;
+            if (scale - 1.0).abs() < 0.05 {
+                return content;
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "upright") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_upright_call(&args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "cases") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_cases(&args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "mat") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_mat(&args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "frac") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_frac(&args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "sqrt") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_sqrt(&args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "root") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_root(&args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "binom") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_binom(&args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "sin") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_operator_call("sin", &args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "cos") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_operator_call("cos", &args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "tan") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_operator_call("tan", &args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "log") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_operator_call("log", &args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "ln") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_operator_call("ln", &args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "exp") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_operator_call("exp", &args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "lim") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_operator_call("lim", &args));
-                i = end_idx;
-                continue;
-            }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "max") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_operator_call_multi("max", &args));
-                i = end_idx;
-                continue;
+            if let Some(cmd) = map_size_command(scale) {
+                return format!("{{{} {}}}", cmd, content);
             }
         }
-        if let Some(paren_idx) = match_call_at(input, i, "min") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_operator_call_multi("min", &args));
-                i = end_idx;
-                continue;
-            }
+        let baseline = (size_pt * 1.2).max(size_pt + 1.0);
+        return format!(
+            "{{\\fontsize{{{:.1}pt}}{{{:.1}pt}}\\selectfont {}}}",
+            size_pt, baseline, content
+        );
+    }
+    content
+}
+
+fn map_size_command(scale: f64) -> Option<&'static str> {
+    const SIZE_MAP: [(&str, f64); 10] = [
+        ("\\tiny", 0.5),
+        ("\\scriptsize", 0.7),
+        ("\\footnotesize", 0.8),
+        ("\\small", 0.9),
+        ("\\normalsize", 1.0),
+        ("\\large", 1.2),
+        ("\\Large", 1.44),
+        ("\\LARGE", 1.728),
+        ("\\huge", 2.074),
+        ("\\Huge", 2.488),
+    ];
+    let mut best_cmd: Option<&'static str> = None;
+    let mut best_diff = f64::MAX;
+    for (cmd, target) in SIZE_MAP {
+        let diff = (scale - target).abs();
+        if diff < best_diff {
+            best_diff = diff;
+            best_cmd = Some(cmd);
         }
-        if let Some(paren_idx) = match_call_at(input, i, "sup") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_operator_call_multi("sup", &args));
-                i = end_idx;
-                continue;
+    }
+    if best_diff <= 0.15 {
+        best_cmd
+    } else {
+        None
+    }
+}
+
+fn render_inlines(inlines: &[Inline], options: &LatexRenderOptions) -> String {
+    render_inline_run(inlines, options, true)
+}
+
+/// Render a run of inlines; `sentence_start` says whether the text before
+/// the run ends a sentence, for a run nested in another.
+fn render_inline_run(
+    inlines: &[Inline],
+    options: &LatexRenderOptions,
+    sentence_start: bool,
+) -> String {
+    let Some(_guard) = RenderDepthGuard::enter() else {
+        return String::new();
+    };
+    let outermost = OPEN_QUOTES.with(|quotes| {
+        let mut quotes = quotes.borrow_mut();
+        let outermost = quotes.is_none();
+        quotes.get_or_insert_with(Vec::new);
+        outermost
+    });
+    let mut out = String::new();
+    let mut last_was_linebreak = false;
+    for inline in inlines {
+        // Whether a run nested in this inline starts a sentence, taken
+        // before the inline's own opening is written.
+        let nested_start = starts_sentence(&out, sentence_start);
+        match inline {
+            Inline::Text(text) => out.push_str(&escape_text(text, options)),
+            Inline::SmartQuote { double } => push_smart_quote(&mut out, *double, options),
+            Inline::Size { size, content } => {
+                let content = render_inline_run(content, options, nested_start);
+                out.push_str(&render_inline_size(size, content, options));
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "inf") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_operator_call_multi("inf", &args));
-                i = end_idx;
-                continue;
+            Inline::Strong(inner) => {
+                if let Some(math) = render_emphasized_math(inner, "\\boldsymbol", options) {
+                    out.push_str(&math);
+                } else {
+                    out.push_str("\\textbf{");
+                    out.push_str(&render_inline_run(inner, options, nested_start));
+                    out.push('}');
+                }
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "argmax") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_operator_star_call("argmax", &args));
-                i = end_idx;
-                continue;
+            Inline::Emph(inner) => {
+                if let Some(math) = render_emphasized_math(inner, "\\mathit", options) {
+                    out.push_str(&math);
+                } else {
+                    out.push_str("\\textit{");
+                    out.push_str(&render_inline_run(inner, options, nested_start));
+                    out.push('}');
+                }
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "argmin") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_operator_star_call("argmin", &args));
-                i = end_idx;
-                continue;
+            Inline::Code { lang, content } => {
+                push_inline_code(&mut out, lang.as_deref(), content, options)
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "abs") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_wrapped(
-                    &args,
-                    "\\left\\lvert ",
-                    " \\right\\rvert",
-                ));
-                i = end_idx;
-                continue;
+            Inline::Math(content) | Inline::LatexMath(content) => {
+                if in_inline_context() {
+                    let (math, rows) = match inline {
+                        Inline::LatexMath(_) => latex_inline_context_math(content.trim()),
+                        _ => convert_math_inline_context(content),
+                    };
+                    out.push_str(&inline_context_math(&math, rows, options));
+                } else {
+                    let math = match inline {
+                        Inline::LatexMath(_) => content.trim().to_string(),
+                        _ => convert_math_content_inline(content),
+                    };
+                    let (open, close) = options.math_delimiters.inline();
+                    out.push_str(open);
+                    out.push_str(&math);
+                    out.push_str(close);
+                }
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "norm") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_wrapped(
-                    &args,
-                    "\\left\\lVert ",
-                    " \\right\\rVert",
-                ));
-                i = end_idx;
-                continue;
+            Inline::Link { text, url, target } => push_link(&mut out, text, url, *target, options),
+            Inline::Ref(label) => push_ref(&mut out, label, options, sentence_start),
+            Inline::RefRange { from, to } => {
+                if options.cleveref {
+                    let command = if nested_start {
+                        "\\Crefrange{"
+                    } else {
+                        "\\crefrange{"
+                    };
+                    out.push_str(command);
+                    out.push_str(&output_label(from, options));
+                    out.push_str("}{");
+                    out.push_str(&output_label(to, options));
+                    out.push('}');
+                } else {
+                    push_ref(&mut out, from, options, sentence_start);
+                    out.push_str("--");
+                    push_ref_number(&mut out, to, options);
+                }
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "ceil") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_wrapped(
-                    &args,
-                    "\\left\\lceil ",
-                    " \\right\\rceil",
-                ));
-                i = end_idx;
-                continue;
+            Inline::SupplementedRef { label, supplement } => {
+                let supplement = render_inline_run(supplement, options, nested_start);
+                if !supplement.trim().is_empty() {
+                    out.push_str(supplement.trim_end());
+                    out.push('~');
+                }
+                push_ref_number(&mut out, label, options);
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "floor") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_wrapped(
-                    &args,
-                    "\\left\\lfloor ",
-                    " \\right\\rfloor",
-                ));
-                i = end_idx;
-                continue;
+            Inline::Label(label) => {
+                out.push_str("\\label{");
+                out.push_str(&output_label(label, options));
+                out.push('}');
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "vec") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_unary_command("vec", &args));
-                i = end_idx;
-                continue;
+            Inline::Cite(key) => {
+                if options.inline_bibliography.is_some() {
+                    record_citation(key);
+                }
+                let default = match citation_package(options) {
+                    CitationPackage::Natbib => "cite",
+                    CitationPackage::Biblatex => "autocite",
+                };
+                let cmd = options.cite_command.as_deref().unwrap_or(default);
+                out.push_str(footnote_protect());
+                out.push_str("\\");
+                out.push_str(cmd);
+                out.push_str("{");
+                out.push_str(&escape_cite_keys(key));
+                out.push('}');
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "hat") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_unary_command("hat", &args));
-                i = end_idx;
-                continue;
+            Inline::Citation(citation) => {
+                if options.inline_bibliography.is_some() {
+                    record_citation(&citation.keys);
+                }
+                out.push_str(footnote_protect());
+                out.push_str(&render_citation(citation, options));
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "tilde") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_unary_command("tilde", &args));
-                i = end_idx;
-                continue;
+            Inline::Footnote(content) => {
+                let note = render_footnote(content, options);
+                match table_note_mark(note.clone()) {
+                    Some(mark) => out.push_str(&mark),
+                    None => {
+                        out.push_str("\\footnote{");
+                        out.push_str(&note);
+                        out.push('}');
+                    }
+                }
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "bar") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_unary_command("bar", &args));
-                i = end_idx;
-                continue;
+            Inline::Color { color, content } => {
+                let (model, value) = color_to_latex(color);
+                out.push_str("\\textcolor");
+                if let Some(model) = model {
+                    out.push_str(&format!("[{}]", model));
+                }
+                out.push_str("{");
+                out.push_str(&value);
+                out.push_str("}{");
+                out.push_str(&render_inline_run(content, options, nested_start));
+                out.push('}');
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "dot") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_unary_command("dot", &args));
-                i = end_idx;
-                continue;
+            Inline::RawLatex(raw) => out.push_str(raw),
+            Inline::Quantity { value, unit } => push_quantity(&mut out, value, unit),
+            Inline::Chemical(formula) => {
+                out.push_str("\\ce{");
+                out.push_str(&formula.replace('%', "\\%"));
+                out.push('}');
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "ddot") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_unary_command("ddot", &args));
-                i = end_idx;
-                continue;
+            // Outside slides every step shows at once.
+            Inline::Pause if options.template == DocumentTemplate::Beamer => {
+                out.push_str("\\pause ");
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "overline") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_unary_command("overline", &args));
-                i = end_idx;
-                continue;
+            Inline::Pause => {}
+            Inline::PageNumber(pattern) => out.push_str(&meta::page_number(pattern)),
+            Inline::RunningHead(level) => out.push_str(meta::running_head(*level)),
+            Inline::Superscript(content) => {
+                out.push_str("\\textsuperscript{");
+                out.push_str(&render_inline_run(content, options, nested_start));
+                out.push('}');
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "underline") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_unary_command("underline", &args));
-                i = end_idx;
-                continue;
+            Inline::Subscript(content) => {
+                out.push_str("\\textsubscript{");
+                out.push_str(&render_inline_run(content, options, nested_start));
+                out.push('}');
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "bb") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_font_command("mathbb", &args));
-                i = end_idx;
-                continue;
+            Inline::Underline(content) => {
+                out.push_str("\\uline{");
+                out.push_str(&render_inline_run(content, options, nested_start));
+                out.push('}');
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "cal") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_font_command("mathcal", &args));
-                i = end_idx;
-                continue;
+            Inline::Overline(content) => {
+                // `\overline` is a math command; the box keeps the text upright.
+                out.push_str("\\ensuremath{\\overline{\\mbox{");
+                out.push_str(&render_inline_run(content, options, nested_start));
+                out.push_str("}}}");
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "frak") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_font_command("mathfrak", &args));
-                i = end_idx;
-                continue;
+            Inline::Strike(content) => {
+                out.push_str("\\sout{");
+                out.push_str(&render_inline_run(content, options, nested_start));
+                out.push('}');
             }
-        }
-        if let Some(paren_idx) = match_call_at(input, i, "bold") {
-            if let Some((args, end_idx)) = extract_paren_content(input, paren_idx) {
-                out.push_str(&convert_font_command("mathbf", &args));
-                i = end_idx;
-                continue;
+            Inline::SmallCaps(content) => {
+                out.push_str("\\textsc{");
+                out.push_str(&render_inline_run(content, options, nested_start));
+                out.push('}');
             }
-        }
-        // Handle quoted strings like "softmax" → \text{softmax}
-        if input[i..].starts_with('"') {
-            if let Some(end_quote) = input[i + 1..].find('"') {
-                let text_content = &input[i + 1..i + 1 + end_quote];
-                // Unescape Typst string escapes (e.g., \@ → @, \_ → _)
-                let unescaped = unescape_typst_string(text_content);
-                out.push_str(&format!("\\text{{{}}}", unescaped));
-                i = i + 1 + end_quote + 1;
-                continue;
+            Inline::Uppercase(content) => {
+                out.push_str("\\MakeUppercase{");
+                out.push_str(&render_inline_run(content, options, nested_start));
+                out.push('}');
             }
-        }
-        // Handle Typst subscript/superscript with parentheses: _(x) → _{x}, ^(x) → ^{x}
-        if input[i..].starts_with("_(") || input[i..].starts_with("^(") {
-            let op = &input[i..i + 1]; // "_" or "^"
-            if let Some((content, end_idx)) = extract_paren_content(input, i + 1) {
-                let converted_content = convert_math_content(&content);
-                out.push_str(op);
-                out.push('{');
-                out.push_str(&converted_content);
+            Inline::Lowercase(content) => {
+                out.push_str("\\MakeLowercase{");
+                out.push_str(&render_inline_run(content, options, nested_start));
                 out.push('}');
-                i = end_idx;
+            }
+            Inline::LineBreak => {
+                if !last_was_linebreak {
+                    if !out.trim().is_empty() {
+                        // Add a trailing space to avoid "\\[...]" being parsed as an optional arg.
+                        out.push_str("\\\\ ");
+                        last_was_linebreak = true;
+                    }
+                }
                 continue;
             }
+            _ => {}
         }
-        if let Some((latex, len)) = match_sequence_at(input, i) {
-            out.push_str(latex);
-            i += len;
-            continue;
-        }
-        if let Some((latex, len)) = match_dotted_symbol_at(input, i) {
-            out.push_str(latex);
-            i += len;
-            continue;
-        }
-        if let Some((latex, len)) = match_symbol_at(input, i) {
-            out.push_str(latex);
-            i += len;
-            continue;
-        }
-        let ch = input[i..].chars().next().unwrap();
-        // Escape special LaTeX characters in math mode
-        match ch {
-            '%' => out.push_str("\\%"),
-            _ => out.push(ch),
-        }
-        i += ch.len_utf8();
-    }
-    // Post-process: join spaced letters like "m a x" → "max"
-    // This handles Typst's letter-spacing in math mode for multi-letter identifiers
-    join_spaced_letters(&out)
-}
-
-/// Join specific spaced letter patterns that are known math identifiers
-/// Only joins patterns that match known words to avoid breaking valid spacing
-fn join_spaced_letters(input: &str) -> String {
-    // Known multi-letter identifiers that get spaced in Typst math mode
-    const KNOWN_WORDS: &[(&str, &str)] = &[
-        ("m a x", "max"),
-        ("m i n", "min"),
-        ("l e n", "len"),
-        ("l o g", "log"),
-        ("e x p", "exp"),
-        ("s i n", "sin"),
-        ("c o s", "cos"),
-        ("t a n", "tan"),
-        ("l i m", "lim"),
-        ("s u p", "sup"),
-        ("i n f", "inf"),
-        ("d e t", "det"),
-        ("d i m", "dim"),
-        ("k e r", "ker"),
-        ("g c d", "gcd"),
-        ("l c m", "lcm"),
-        ("a r g", "arg"),
-        ("d e g", "deg"),
-        ("h o m", "hom"),
-        ("m o d", "mod"),
-        ("s e c", "sec"),
-        ("c s c", "csc"),
-        ("c o t", "cot"),
-    ];
-
-    let mut result = input.to_string();
-    for (spaced, joined) in KNOWN_WORDS {
-        result = result.replace(spaced, joined);
+        last_was_linebreak = false;
     }
-    result
+    close_quotes(&mut out, outermost, options);
+    out
 }
 
-fn convert_math_content_inline(input: &str) -> String {
-    let converted = convert_math_content(input);
-    replace_inline_setminus(&converted)
+/// A smart quote opened and not yet closed.
+struct OpenQuote {
+    double: bool,
+    /// Render depth of the inline run it was opened in.
+    depth: usize,
+    /// Closed in a nested run, where csquotes' group could not end; the
+    /// group ends with the run the quote was opened in.
+    closed: bool,
 }
 
-fn replace_inline_setminus(input: &str) -> String {
-    let bytes = input.as_bytes();
-    let mut out = String::new();
-    let mut i = 0usize;
-    while i < bytes.len() {
-        if i + 1 < bytes.len() && bytes[i] == b'\\' && bytes[i + 1] == b'\\' {
-            let prev = input[..i].chars().rev().find(|c| !c.is_whitespace());
-            let next = input[i + 2..].chars().find(|c| !c.is_whitespace());
-            let prev_is_word = prev.map_or(false, |c| c.is_alphanumeric() || c == '}' || c == ')');
-            let next_is_word = next.map_or(false, |c| c.is_alphanumeric() || c == '{' || c == '(');
-            if prev_is_word && next_is_word {
-                out.push_str("\\setminus");
-                i += 2;
-                continue;
+/// A smart quote as Typst sets it: an apostrophe after a letter, a prime
+/// after a digit, the close of the innermost open quote of its kind, or
+/// else a new quote.
+fn push_smart_quote(out: &mut String, double: bool, options: &LatexRenderOptions) {
+    let before = out.trim_end_matches('}').chars().last().unwrap_or(' ');
+    let depth = RENDER_DEPTH.with(Cell::get);
+    let close_mark = match (options.quote_style, double) {
+        (QuoteStyle::Straight, true) => "\"",
+        (_, true) => "''",
+        (_, false) => "'",
+    };
+    OPEN_QUOTES.with(|quotes| {
+        let mut quotes = quotes.borrow_mut();
+        let quotes = quotes.get_or_insert_with(Vec::new);
+        let open = quotes.iter().rposition(|quote| !quote.closed);
+        let opened = open.map(|index| quotes[index].double);
+        if before.is_numeric() && opened != Some(double) {
+            out.push_str(close_mark);
+            return;
+        }
+        // Inline math counts as a letter, as in `$n$'s`.
+        if !double && opened != Some(false) && (before.is_alphabetic() || before == '$') {
+            out.push('\'');
+            return;
+        }
+        if let Some(index) = open.filter(|_| {
+            opened == Some(double) && !before.is_whitespace() && !matches!(before, '(' | '[' | '{')
+        }) {
+            match options.quote_style {
+                QuoteStyle::Csquotes if quotes[index].depth == depth => {
+                    quotes.remove(index);
+                    out.push('}');
+                }
+                QuoteStyle::Csquotes => quotes[index].closed = true,
+                _ => {
+                    quotes.remove(index);
+                    out.push_str(close_mark);
+                }
             }
+            return;
         }
-        out.push(bytes[i] as char);
-        i += 1;
-    }
-    out
+        quotes.push(OpenQuote {
+            double,
+            depth,
+            closed: false,
+        });
+        out.push_str(match (options.quote_style, double) {
+            (QuoteStyle::Straight, true) => "\"",
+            (QuoteStyle::Straight, false) => "'",
+            (QuoteStyle::Tex, true) => "``",
+            (QuoteStyle::Tex, false) => "`",
+            (QuoteStyle::Csquotes, _) => "\\enquote{",
+        });
+    });
 }
 
-fn match_call_at(input: &str, idx: usize, name: &str) -> Option<usize> {
-    if !input[idx..].starts_with(name) {
-        return None;
-    }
-    if idx > 0 {
-        // Check the immediate previous character
-        if let Some(prev_char) = input[..idx].chars().last() {
-            // If there's whitespace immediately before, allow the match (tokens are separated)
-            if !prev_char.is_whitespace() {
-                // No whitespace, check if we're part of a larger identifier
-                if prev_char == '\\' || prev_char.is_ascii_alphanumeric() || prev_char == '_' {
-                    return None;
+/// End the csquotes groups of the quotes opened in the run at the current
+/// depth, so its braces balance. Quotes left open in other styles stay
+/// open to the end of the outermost run.
+fn close_quotes(out: &mut String, outermost: bool, options: &LatexRenderOptions) {
+    let depth = RENDER_DEPTH.with(Cell::get);
+    OPEN_QUOTES.with(|quotes| {
+        let mut quotes = quotes.borrow_mut();
+        if options.quote_style == QuoteStyle::Csquotes {
+            if let Some(quotes) = quotes.as_mut() {
+                while quotes.last().is_some_and(|quote| quote.depth >= depth) {
+                    quotes.pop();
+                    out.push('}');
                 }
             }
         }
+        if outermost {
+            *quotes = None;
+        }
+    });
+}
+
+/// Render a strong/emph span whose only content is inline math by applying
+/// `command` inside the formula, e.g. `*$x$*` becomes `$\boldsymbol{x}$`.
+fn render_emphasized_math(
+    inner: &[Inline],
+    command: &str,
+    options: &LatexRenderOptions,
+) -> Option<String> {
+    if !options.math_emphasis {
+        return None;
     }
-    let mut j = idx + name.len();
-    while j < input.len() {
-        let ch = input[j..].chars().next().unwrap();
-        if ch.is_whitespace() {
-            j += ch.len_utf8();
-        } else {
-            break;
+    let mut formulas = Vec::new();
+    for inline in inner {
+        match inline {
+            Inline::Math(content) => formulas.push(content),
+            Inline::Text(text) if text.trim().is_empty() => {}
+            _ => return None,
         }
     }
-    if j < input.len() && input[j..].starts_with('(') {
-        Some(j)
-    } else {
-        None
+    if formulas.len() != 1 {
+        return None;
     }
+    let (open, close) = options.math_delimiters.inline();
+    Some(format!(
+        "{}{}{{{}}}{}",
+        open,
+        command,
+        convert_math_content_inline(formulas[0]),
+        close
+    ))
 }
 
-fn extract_paren_content(input: &str, start_paren: usize) -> Option<(String, usize)> {
-    let mut depth = 0usize;
-    let mut in_string = false;
-    let mut escaped = false;
-    let mut i = start_paren;
-    let mut content_start = None;
-    while i < input.len() {
-        let ch = input[i..].chars().next().unwrap();
-        if content_start.is_none() {
-            if ch != '(' {
-                return None;
-            }
-            depth = 1;
-            i += ch.len_utf8();
-            content_start = Some(i);
-            continue;
-        }
-        if in_string {
-            if escaped {
-                escaped = false;
-            } else if ch == '\\' {
-                escaped = true;
-            } else if ch == '"' {
-                in_string = false;
-            }
-            i += ch.len_utf8();
-            continue;
-        }
-        match ch {
-            '"' => in_string = true,
-            '(' => depth += 1,
-            ')' => {
-                depth = depth.saturating_sub(1);
-                if depth == 0 {
-                    let start = content_start.unwrap_or(i);
-                    let content = input[start..i].to_string();
-                    i += ch.len_utf8();
-                    return Some((content, i));
-                }
-            }
-            _ => {}
-        }
-        i += ch.len_utf8();
+fn push_ref(out: &mut String, label: &str, options: &LatexRenderOptions, sentence_start: bool) {
+    if is_equation_label(label) {
+        push_ref_number(out, label, options);
+        return;
     }
-    None
+    if options.cleveref {
+        // cleveref names the target itself, capitalized where a sentence
+        // starts.
+        out.push_str(if starts_sentence(out, sentence_start) {
+            "\\Cref{"
+        } else {
+            "\\cref{"
+        });
+        out.push_str(&output_label(label, options));
+        out.push('}');
+        return;
+    }
+    if let Some(prefix) = reference_prefix(label) {
+        out.push_str(prefix);
+        out.push('~');
+    }
+    push_ref_number(out, label, options);
 }
 
-fn split_top_level(input: &str, delim: char) -> Vec<String> {
-    let mut parts = Vec::new();
-    let mut depth_paren = 0usize;
-    let mut depth_brack = 0usize;
-    let mut depth_brace = 0usize;
-    let mut in_string = false;
-    let mut escaped = false;
-    let mut start = 0usize;
-    let mut i = 0usize;
-    while i < input.len() {
-        let ch = input[i..].chars().next().unwrap();
-        if in_string {
-            if escaped {
-                escaped = false;
-            } else if ch == '\\' {
-                escaped = true;
-            } else if ch == '"' {
-                in_string = false;
-            }
-            i += ch.len_utf8();
-            continue;
-        }
-        match ch {
-            '"' => in_string = true,
-            '(' => depth_paren += 1,
-            ')' => depth_paren = depth_paren.saturating_sub(1),
-            '[' => depth_brack += 1,
-            ']' => depth_brack = depth_brack.saturating_sub(1),
-            '{' => depth_brace += 1,
-            '}' => depth_brace = depth_brace.saturating_sub(1),
-            _ => {}
-        }
-        if ch == delim
-            && depth_paren == 0
-            && depth_brack == 0
-            && depth_brace == 0
-        {
-            parts.push(input[start..i].to_string());
-            i += ch.len_utf8();
-            start = i;
-            continue;
-        }
-        i += ch.len_utf8();
+/// The number of a reference alone: `\ref`, or `\eqref` for an equation.
+fn push_ref_number(out: &mut String, label: &str, options: &LatexRenderOptions) {
+    out.push_str(if is_equation_label(label) {
+        "\\eqref{"
+    } else {
+        "\\ref{"
+    });
+    out.push_str(&output_label(label, options));
+    out.push('}');
+}
+
+/// Whether text set after `out` starts a sentence: the end of one comes
+/// before it, or, when `out` is empty, the run it opens starts one.
+fn starts_sentence(out: &str, run_starts_sentence: bool) -> bool {
+    let before = out.trim_end();
+    if before.is_empty() {
+        return run_starts_sentence;
     }
-    if start <= input.len() {
-        parts.push(input[start..].to_string());
+    if !before.ends_with(['.', '!', '?']) {
+        return false;
     }
-    parts
+    // An abbreviation ends in a period without ending the sentence.
+    let last = before
+        .rsplit(|c: char| c.is_whitespace() || c == '~')
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches(['(', '['])
+        .to_lowercase();
+    !ABBREVIATIONS.contains(&last.as_str())
 }
 
-fn convert_cases(args: &str) -> String {
-    let entries = split_top_level(args, ',');
-    let mut rows = Vec::new();
-    let mut pending_expr: Option<String> = None;
+/// Abbreviations a reference commonly follows.
+const ABBREVIATIONS: &[&str] = &[
+    "cf.", "e.g.", "i.e.", "vs.", "viz.", "resp.", "al.", "fig.", "eq.",
+];
 
-    for entry in entries {
-        let trimmed = entry.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-        if is_cases_named_arg(trimmed) {
-            continue;
-        }
+fn is_equation_label(label: &str) -> bool {
+    let lowered = label.trim().to_lowercase();
+    lowered.starts_with("eq:")
+}
 
-        // Check if this entry starts with & (condition part in Typst cases format)
-        if trimmed.starts_with('&') {
-            let cond_part = trimmed[1..].trim();
-            let converted_cond = convert_math_content(cond_part);
-            if let Some(expr) = pending_expr.take() {
-                // Pair with pending expression
-                rows.push(format!("{} & {}", expr, converted_cond));
-            } else {
-                // Standalone condition (shouldn't happen but handle it)
-                rows.push(format!("& {}", converted_cond));
-            }
-        } else {
-            // This is an expression
-            // First, flush any pending expression without condition
-            if let Some(expr) = pending_expr.take() {
-                rows.push(expr);
-            }
-            // Split BEFORE convert_math_content so we can find "if"/"else" patterns
-            let (expr_raw, cond_raw) = split_case_condition(trimmed);
-            let expr = convert_math_content(&expr_raw);
-            if let Some(cond) = cond_raw {
-                let converted_cond = convert_math_content(&cond);
-                rows.push(format!("{} & {}", expr, converted_cond));
-            } else {
-                // Store as pending, waiting for possible & condition
-                pending_expr = Some(expr);
-            }
-        }
+fn reference_prefix(label: &str) -> Option<&'static str> {
+    let lowered = label.trim().to_lowercase();
+    if lowered.starts_with("fig:") {
+        return Some("Fig.");
     }
-
-    // Flush any remaining pending expression
-    if let Some(expr) = pending_expr {
-        rows.push(expr);
+    if lowered.starts_with("tab:") {
+        return Some("Table");
     }
-
-    let mut out = String::new();
-    out.push_str("\\begin{cases}");
-    if !rows.is_empty() {
-        out.push_str(&rows.join(" \\\\ "));
+    if lowered.starts_with("sec:") {
+        return Some("Section");
     }
-    out.push_str("\\end{cases}");
-    out
-}
-
-fn is_cases_named_arg(entry: &str) -> bool {
-    let lower = entry.trim_start().to_lowercase();
-    lower.starts_with("delim:")
-        || lower.starts_with("reverse:")
-        || lower.starts_with("gap:")
-        || lower.starts_with("row-gap:")
-        || lower.starts_with("column-gap:")
-}
-
-fn split_case_condition(entry: &str) -> (String, Option<String>) {
-    // Look for "if" or "else" patterns to split expression from condition
-    // Returns (expression, optional_condition) with raw strings (not converted)
-    if let Some(idx) = entry.find("\"if\"") {
-        let left = entry[..idx].trim().to_string();
-        let right = entry[idx..].trim().to_string(); // Keep the whole condition part
-        return (left, Some(right));
-    }
-    if let Some(idx) = entry.find("\"else\"") {
-        let left = entry[..idx].trim().to_string();
-        let right = entry[idx..].trim().to_string(); // Keep the whole condition part
-        return (left, Some(right));
-    }
-    if let Some(idx) = entry.find("\"otherwise\"") {
-        let left = entry[..idx].trim().to_string();
-        let right = entry[idx..].trim().to_string(); // Keep the whole condition part
-        return (left, Some(right));
-    }
-    (entry.trim().to_string(), None)
-}
-
-fn convert_mat(args: &str) -> String {
-    let rows_raw = split_top_level(args, ';');
-    let mut rows = Vec::new();
-    let mut delim: Option<String> = None;
-    for row in rows_raw {
-        let cols_raw = split_top_level(&row, ',');
-        let mut cols = Vec::new();
-        for col in cols_raw {
-            let trimmed = col.trim();
-            if trimmed.is_empty() {
-                continue;
-            }
-            if let Some(value) = parse_named_arg(trimmed, "delim") {
-                if delim.is_none() {
-                    delim = Some(value);
-                }
-                continue;
-            }
-            if is_mat_named_arg(trimmed) {
-                continue;
-            }
-            cols.push(convert_math_content(trimmed));
-        }
-        if !cols.is_empty() {
-            rows.push(cols.join(" & "));
-        }
+    if lowered.starts_with("alg:") {
+        return Some("Algorithm");
     }
-    let env = mat_env_from_delim(delim.as_deref());
-    let mut out = String::new();
-    out.push_str(&format!("\\begin{{{}}}", env));
-    if !rows.is_empty() {
-        out.push_str(&rows.join(" \\\\ "));
+    if lowered.starts_with("lst:") {
+        return Some("Listing");
     }
-    out.push_str(&format!("\\end{{{}}}", env));
-    out
-}
-
-fn convert_frac(args: &str) -> String {
-    let parts = positional_args(args);
-    if parts.len() >= 2 {
-        return format!(
-            "\\frac{{{}}}{{{}}}",
-            convert_math_content(&parts[0]),
-            convert_math_content(&parts[1])
-        );
+    if lowered.starts_with("thm:") {
+        return Some("Theorem");
     }
-    format!(
-        "\\operatorname{{frac}}\\left({}\\right)",
-        convert_math_content(args)
-    )
-}
-
-fn convert_sqrt(args: &str) -> String {
-    if let Some(arg) = first_positional_arg(args) {
-        return format!("\\sqrt{{{}}}", convert_math_content(&arg));
+    if lowered.starts_with("lemma:") || lowered.starts_with("lem:") {
+        return Some("Lemma");
     }
-    "\\sqrt{}".to_string()
-}
-
-fn convert_root(args: &str) -> String {
-    let parts = positional_args(args);
-    if parts.len() >= 2 {
-        return format!(
-            "\\sqrt[{}]{{{}}}",
-            convert_math_content(&parts[0]),
-            convert_math_content(&parts[1])
-        );
+    if lowered.starts_with("prop:") {
+        return Some("Proposition");
     }
-    if let Some(arg) = parts.get(0) {
-        return format!("\\sqrt{{{}}}", convert_math_content(arg));
+    if lowered.starts_with("def:") {
+        return Some("Definition");
     }
-    "\\sqrt{}".to_string()
-}
-
-fn convert_binom(args: &str) -> String {
-    let parts = positional_args(args);
-    if parts.len() >= 2 {
-        return format!(
-            "\\binom{{{}}}{{{}}}",
-            convert_math_content(&parts[0]),
-            convert_math_content(&parts[1])
-        );
+    if lowered.starts_with("cor:") {
+        return Some("Corollary");
     }
-    format!(
-        "\\operatorname{{binom}}\\left({}\\right)",
-        convert_math_content(args)
-    )
+    if lowered.starts_with("ex:") {
+        return Some("Example");
+    }
+    if lowered.starts_with("remark:") || lowered.starts_with("rem:") {
+        return Some("Remark");
+    }
+    if lowered.starts_with("app:") || lowered.starts_with("appendix:") {
+        return Some("Appendix");
+    }
+    None
 }
 
-fn convert_operator_call(op: &str, args: &str) -> String {
-    let arg = first_positional_arg(args).unwrap_or_default();
-    if arg.is_empty() {
-        return format!("\\{}", op);
+/// Text escaped for LaTeX, with the characters pdflatex cannot read set
+/// as commands unless the output is for a Unicode engine.
+fn escape_text(input: &str, options: &LatexRenderOptions) -> String {
+    match options.encoding_mode {
+        EncodingMode::Transliterate => transliterate(&escape_latex(input)),
+        EncodingMode::Unicode => escape_latex(input),
     }
-    format!("\\{}\\left({}\\right)", op, convert_math_content(&arg))
 }
 
-fn convert_operator_call_multi(op: &str, args: &str) -> String {
-    let parts = positional_args(args);
-    if parts.is_empty() {
-        return format!("\\{}", op);
+fn escape_latex(input: &str) -> String {
+    let mut out = String::new();
+    for ch in input.chars() {
+        match ch {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '{' => out.push_str("\\{") ,
+            '}' => out.push_str("\\}"),
+            '$' => out.push_str("\\$") ,
+            '&' => out.push_str("\\&") ,
+            '%' => out.push_str("\\%") ,
+            '#' => out.push_str("\\#") ,
+            '_' => out.push_str("\\_") ,
+            '^' => out.push_str("\\textasciicircum{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            _ => out.push(ch),
+        }
     }
-    let rendered = parts
-        .into_iter()
-        .map(|part| convert_math_content(&part))
-        .collect::<Vec<_>>()
-        .join(", ");
-    format!("\\{}\\left({}\\right)", op, rendered)
+    out
 }
 
-fn convert_operator_star_call(op: &str, args: &str) -> String {
-    let arg = first_positional_arg(args).unwrap_or_default();
-    if arg.is_empty() {
-        return format!("\\operatorname*{{{}}}", op);
+/// A link in the command its target calls for: `\url` for a bare address,
+/// `\href` for linked text or mail and `\hyperref` for a label.
+fn push_link(
+    out: &mut String,
+    text: &[Inline],
+    url: &str,
+    target: LinkTarget,
+    options: &LatexRenderOptions,
+) {
+    match target {
+        LinkTarget::Url if matches!(text, [Inline::Text(shown)] if shown == url) => {
+            out.push_str("\\url{");
+            out.push_str(&escape_url(url));
+            out.push('}');
+            return;
+        }
+        LinkTarget::Email => {
+            out.push_str("\\href{mailto:");
+            out.push_str(&escape_url(url));
+            out.push('}');
+        }
+        LinkTarget::Label => {
+            out.push_str("\\hyperref[");
+            out.push_str(&output_label(url, options));
+            out.push(']');
+        }
+        _ => {
+            out.push_str("\\href{");
+            out.push_str(&escape_url(url));
+            out.push('}');
+        }
     }
-    format!(
-        "\\operatorname*{{{}}}\\left({}\\right)",
-        op,
-        convert_math_content(&arg)
-    )
+    out.push('{');
+    out.push_str(&render_inlines(text, options));
+    out.push('}');
 }
 
-fn convert_wrapped(args: &str, left: &str, right: &str) -> String {
-    if let Some(arg) = first_positional_arg(args) {
-        return format!("{}{}{}", left, convert_math_content(&arg), right);
+/// An address as `\url` and `\href` read it: their argument is verbatim
+/// but for `%` and `#`, and braces must balance.
+fn escape_url(url: &str) -> String {
+    let mut out = String::new();
+    for ch in url.chars() {
+        match ch {
+            '%' => out.push_str("\\%"),
+            '#' => out.push_str("\\#"),
+            '\\' => out.push_str("%5C"),
+            '{' => out.push_str("%7B"),
+            '}' => out.push_str("%7D"),
+            _ => out.push(ch),
+        }
     }
-    format!("{}{}", left, right)
+    out
 }
 
-fn convert_unary_command(cmd: &str, args: &str) -> String {
-    let arg = first_positional_arg(args).unwrap_or_default();
-    format!("\\{}{{{}}}", cmd, convert_math_content(&arg))
+fn escape_label(input: &str) -> String {
+    let mut out = String::new();
+    for ch in input.chars() {
+        if ch.is_ascii_alphanumeric() || matches!(ch, ':' | '-' | '_' | '.') {
+            out.push(ch);
+        } else if ch.is_whitespace() {
+            out.push('-');
+        }
+    }
+    if out.is_empty() {
+        "label".to_string()
+    } else {
+        out
+    }
 }
 
-fn convert_text_call(args: &str) -> String {
-    let arg = first_positional_arg(args).unwrap_or_default();
-    let text = strip_quotes(&arg);
-    format!("\\text{{{}}}", escape_latex(&text))
+/// A label as the output sets it, after the first of the label rewrites
+/// its prefix matches.
+fn output_label(label: &str, options: &LatexRenderOptions) -> String {
+    let rewritten = options
+        .label_rewrites
+        .iter()
+        .find_map(|(from, to)| {
+            label
+                .strip_prefix(from.as_str())
+                .map(|rest| format!("{}{}", to, rest))
+        });
+    escape_label(rewritten.as_deref().unwrap_or(label))
 }
 
-fn convert_upright_call(args: &str) -> String {
-    let arg = first_positional_arg(args).unwrap_or_default();
-    let text = strip_quotes(&arg);
-    // Recursively convert content to handle nested calls like upright(bold(s))
-    format!("\\mathrm{{{}}}", convert_math_content(&text))
+/// Each label the document sets, with the label the output sets in its
+/// place, sorted by label. For tools that follow labels from the source
+/// into the LaTeX.
+pub fn label_map(doc: &Document, options: &LatexRenderOptions) -> Vec<(String, String)> {
+    let (blocks, inlines) = document_nodes(doc);
+    let mut labels: Vec<&str> = Vec::new();
+    for block in blocks {
+        match block {
+            Block::Heading { label, .. } => labels.extend(label.as_deref()),
+            Block::Table(table) => labels.extend(table.label.as_deref()),
+            Block::Figure(figure) => {
+                labels.extend(figure.label.as_deref());
+                match &figure.content {
+                    FigureContent::Table(table) => labels.extend(table.label.as_deref()),
+                    FigureContent::SubFigures { figures, .. } => {
+                        labels.extend(figures.iter().filter_map(|sub| sub.label.as_deref()))
+                    }
+                    _ => {}
+                }
+            }
+            Block::MathBlock(math) => labels.extend(math.label.as_deref()),
+            Block::Environment(env) => labels.extend(env.label.as_deref()),
+            Block::Algorithm(algorithm) => labels.extend(algorithm.label.as_deref()),
+            _ => {}
+        }
+    }
+    for inline in inlines {
+        if let Inline::Label(label) = inline {
+            labels.push(label);
+        }
+    }
+    labels.sort_unstable();
+    labels.dedup();
+    labels
+        .into_iter()
+        .map(|label| (label.to_string(), output_label(label, options)))
+        .collect()
 }
 
-fn convert_font_command(cmd: &str, args: &str) -> String {
-    let arg = first_positional_arg(args).unwrap_or_default();
-    let arg = strip_quotes(&arg);
-    format!("\\{}{{{}}}", cmd, convert_math_content(&arg))
+/// A citation with its notes, which `\nocite` does not take. A lone
+/// prenote keeps an empty postnote so natbib does not read it as one.
+fn render_citation(citation: &Citation, options: &LatexRenderOptions) -> String {
+    let command = match citation.form {
+        CiteForm::Normal => options.cite_command.as_deref(),
+        _ => None,
+    }
+    .unwrap_or_else(|| citation_package(options).command(citation.form));
+    let mut out = format!("\\{}", command);
+    if citation.form != CiteForm::Hidden {
+        match (&citation.prenote, &citation.postnote) {
+            (Some(pre), Some(post)) => out.push_str(&format!("[{}][{}]", pre, post)),
+            (Some(pre), None) => out.push_str(&format!("[{}][]", pre)),
+            (None, Some(post)) => out.push_str(&format!("[{}]", post)),
+            (None, None) => {}
+        }
+    }
+    out.push('{');
+    out.push_str(&escape_cite_keys(&citation.keys));
+    out.push('}');
+    out
 }
 
-fn positional_args(args: &str) -> Vec<String> {
-    let mut out = Vec::new();
-    for part in split_top_level(args, ',') {
+fn escape_cite_keys(input: &str) -> String {
+    let mut keys = Vec::new();
+    for part in input.split(',') {
         let trimmed = part.trim();
         if trimmed.is_empty() {
             continue;
         }
-        if is_named_arg_like(trimmed) {
-            continue;
-        }
-        out.push(trimmed.to_string());
+        keys.push(escape_label(trimmed));
+    }
+    if keys.is_empty() {
+        escape_label(input)
+    } else {
+        keys.join(",")
     }
-    out
-}
-
-fn first_positional_arg(args: &str) -> Option<String> {
-    positional_args(args).into_iter().next()
 }
 
-fn is_named_arg_like(input: &str) -> bool {
-    let mut parts = input.splitn(2, ':');
-    let Some(head) = parts.next() else {
-        return false;
-    };
-    let Some(_) = parts.next() else {
-        return false;
-    };
-    let head = head.trim();
-    if head.is_empty() {
-        return false;
+fn color_to_latex(input: &str) -> (Option<&'static str>, String) {
+    match parse_color(input).map(|color| color.to_latex()) {
+        Some((model, value)) => (model, escape_latex(&value)),
+        None => (None, "black".to_string()),
     }
-    head.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
-fn strip_quotes(input: &str) -> String {
-    let trimmed = input.trim();
-    if trimmed.len() >= 2 && trimmed.starts_with('"') && trimmed.ends_with('"') {
-        let inner = &trimmed[1..trimmed.len() - 1];
-        return unescape_typst_string(inner);
-    }
-    trimmed.to_string()
+/// A color in the syntax of either frontend: Typst's, or xcolor's for a
+/// document read from LaTeX.
+fn parse_color(raw: &str) -> Option<Color> {
+    Color::parse_typst(raw).or_else(|| Color::parse_latex(raw))
 }
 
-/// Unescape Typst string escape sequences back to their original characters.
-/// Handles: \@ → @, \_ → _, \# → #, \$ → $, \* → *, \` → `, \< → <, \> → >,
-///          \\ → \, \" → ", \n → newline, \t → tab
-fn unescape_typst_string(input: &str) -> String {
-    let mut out = String::with_capacity(input.len());
-    let mut chars = input.chars().peekable();
-    while let Some(ch) = chars.next() {
-        if ch == '\\' {
-            match chars.peek() {
-                Some('@') => {
-                    chars.next();
-                    out.push('@');
-                }
-                Some('_') => {
-                    chars.next();
-                    out.push('_');
-                }
-                Some('#') => {
-                    chars.next();
-                    out.push('#');
-                }
-                Some('$') => {
-                    chars.next();
-                    out.push('$');
-                }
-                Some('*') => {
-                    chars.next();
-                    out.push('*');
-                }
-                Some('`') => {
-                    chars.next();
-                    out.push('`');
-                }
-                Some('<') => {
-                    chars.next();
-                    out.push('<');
-                }
-                Some('>') => {
-                    chars.next();
-                    out.push('>');
-                }
-                Some('\\') => {
-                    chars.next();
-                    out.push('\\');
-                }
-                Some('"') => {
-                    chars.next();
-                    out.push('"');
-                }
-                Some('n') => {
-                    chars.next();
-                    out.push('\n');
-                }
-                Some('t') => {
-                    chars.next();
-                    out.push('\t');
-                }
-                _ => out.push(ch),
+fn normalize_inline_whitespace(input: &str) -> String {
+    let mut out = String::new();
+    let mut prev_space = false;
+    for ch in input.chars() {
+        // A no-break space is kept for the output to tie its words.
+        if ch.is_whitespace() && ch != '\u{a0}' {
+            if !prev_space {
+                out.push(' ');
+                prev_space = true;
             }
         } else {
+            prev_space = false;
             out.push(ch);
         }
     }
-    out
-}
-
-fn match_token_at(input: &str, idx: usize, token: &str) -> bool {
-    if !input[idx..].starts_with(token) {
-        return false;
-    }
-    if idx > 0 {
-        if let Some(prev) = input[..idx].chars().rev().next() {
-            if prev == '\\' || prev.is_ascii_alphanumeric() {
-                return false;
-            }
-        }
-    }
-    let end = idx + token.len();
-    if end < input.len() {
-        if let Some(next) = input[end..].chars().next() {
-            if next.is_ascii_alphanumeric() {
-                return false;
-            }
-        }
-    }
-    true
+    out.trim().to_string()
 }
 
-fn match_sequence_at(input: &str, idx: usize) -> Option<(&'static str, usize)> {
-    const MAP: &[(&str, &str)] = &[
-        ("<=>", "\\Leftrightarrow"),
-        ("<->", "\\leftrightarrow"),
-        ("=>", "\\Rightarrow"),
-        ("<-", "\\leftarrow"),
-        ("->", "\\to"),
-        ("<=", "\\le"),
-        (">=", "\\ge"),
-        ("!=", "\\ne"),
-        ("...", "\\ldots"),
-    ];
-    for (token, latex) in MAP {
-        if input[idx..].starts_with(token) {
-            return Some((latex, token.len()));
+/// `\SI{value}{unit}`, or `\num`/`\si` when only one half is given.
+fn push_quantity(out: &mut String, value: &Option<String>, unit: &Option<String>) {
+    match (value, unit) {
+        (Some(value), Some(unit)) => {
+            out.push_str(&format!("\\SI{{{}}}{{{}}}", value.trim(), siunitx_unit(unit)))
         }
+        (Some(value), None) => out.push_str(&format!("\\num{{{}}}", value.trim())),
+        (None, Some(unit)) => out.push_str(&format!("\\si{{{}}}", siunitx_unit(unit))),
+        (None, None) => {}
     }
-    None
 }
 
-fn match_symbol_at(input: &str, idx: usize) -> Option<(&'static str, usize)> {
-    const MAP: &[(&str, &str)] = &[
-        ("infinity", "\\infty"),
-        ("oo", "\\infty"),
-        ("dif", "\\mathrm{d}"),
-        ("varepsilon", "\\varepsilon"),
-        ("vartheta", "\\vartheta"),
-        ("varsigma", "\\varsigma"),
-        ("varphi", "\\varphi"),
-        ("varrho", "\\varrho"),
-        ("varpi", "\\varpi"),
-        ("epsilon", "\\epsilon"),
-        ("theta", "\\theta"),
-        ("sigma", "\\sigma"),
-        ("phi", "\\phi"),
-        ("rho", "\\rho"),
-        ("pi", "\\pi"),
-        ("alpha", "\\alpha"),
-        ("beta", "\\beta"),
-        ("gamma", "\\gamma"),
-        ("delta", "\\delta"),
-        ("zeta", "\\zeta"),
-        ("eta", "\\eta"),
-        ("iota", "\\iota"),
-        ("kappa", "\\kappa"),
-        ("lambda", "\\lambda"),
-        ("mu", "\\mu"),
-        ("nu", "\\nu"),
-        ("xi", "\\xi"),
-        ("tau", "\\tau"),
-        ("upsilon", "\\upsilon"),
-        ("chi", "\\chi"),
-        ("psi", "\\psi"),
-        ("omega", "\\omega"),
-        ("Gamma", "\\Gamma"),
-        ("Delta", "\\Delta"),
-        ("Theta", "\\Theta"),
-        ("Lambda", "\\Lambda"),
-        ("Xi", "\\Xi"),
-        ("Pi", "\\Pi"),
-        ("Sigma", "\\Sigma"),
-        ("Upsilon", "\\Upsilon"),
-        ("Phi", "\\Phi"),
-        ("Psi", "\\Psi"),
-        ("Omega", "\\Omega"),
-        ("argmax", "\\operatorname*{argmax}"),
-        ("argmin", "\\operatorname*{argmin}"),
-        ("supseteq", "\\supseteq"),
-        ("subseteq", "\\subseteq"),
-        ("supset", "\\supset"),
-        ("subset", "\\subset"),
-        ("notin", "\\notin"),
-        ("infty", "\\infty"),
-        ("forall", "\\forall"),
-        ("exists", "\\exists"),
-        ("partial", "\\partial"),
-        ("nabla", "\\nabla"),
-        ("implies", "\\implies"),
-        ("iff", "\\iff"),
-        ("approx", "\\approx"),
-        ("sim", "\\sim"),
-        ("tilde.op", "\\sim"),
-        ("tilde.eq", "\\simeq"),
-        ("tilde.equiv", "\\cong"),
-        ("integral", "\\int"),
-        ("prod", "\\prod"),
-        ("sum", "\\sum"),
-        ("lim", "\\lim"),
-        ("max", "\\max"),
-        ("min", "\\min"),
-        ("sup", "\\sup"),
-        ("inf", "\\inf"),
-        ("int", "\\int"),
-        ("in", "\\in"),
-        ("RR", "\\mathbb{R}"),
-        ("NN", "\\mathbb{N}"),
-        ("ZZ", "\\mathbb{Z}"),
-        ("QQ", "\\mathbb{Q}"),
-        ("CC", "\\mathbb{C}"),
-        ("HH", "\\mathbb{H}"),
-        ("FF", "\\mathbb{F}"),
-        ("EE", "\\mathbb{E}"),
-        ("PP", "\\mathbb{P}"),
-    ];
-    for (token, latex) in MAP {
-        if match_token_at(input, idx, token) {
-            return Some((latex, token.len()));
-        }
+/// Units spelled out in words (`kilo meter per second`, `percent`) become
+/// siunitx macros; symbolic units (`km/s^2`) are passed through literally.
+fn siunitx_unit(unit: &str) -> String {
+    let unit = unit.trim();
+    let words: Vec<&str> = unit.split_whitespace().collect();
+    let spelled_out = match words.as_slice() {
+        [word] => word.len() >= 4 && word.chars().all(|c| c.is_ascii_lowercase()),
+        _ => words.iter().all(|word| word.chars().all(|c| c.is_ascii_alphabetic())),
+    };
+    if spelled_out {
+        return words.iter().map(|word| format!("\\{}", word)).collect();
     }
-    None
+    unit.replace('%', "\\percent")
 }
 
-fn match_dotted_symbol_at(input: &str, idx: usize) -> Option<(&'static str, usize)> {
-    const MAP: &[(&str, &str)] = &[
-        ("square.stroked", "\\square"),
-        ("sym.square.stroked", "\\square"),
-    ];
-    for (token, latex) in MAP {
-        if !input[idx..].starts_with(token) {
-            continue;
-        }
-        if idx > 0 {
-            if let Some(prev) = input[..idx].chars().rev().next() {
-                if prev.is_ascii_alphanumeric() || prev == '_' || prev == '.' {
-                    continue;
-                }
-            }
-        }
-        let end = idx + token.len();
-        if end < input.len() {
-            if let Some(next) = input[end..].chars().next() {
-                if next.is_ascii_alphanumeric() || next == '_' || next == '.' {
-                    continue;
-                }
+fn plain_inline_text(inlines: &[Inline]) -> String {
+    let mut out = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) => out.push_str(text),
+            Inline::Code { content, .. } => out.push_str(content),
+            Inline::Math(content) | Inline::LatexMath(content) => out.push_str(content),
+            Inline::Size { content, .. } => out.push_str(&plain_inline_text(content)),
+            Inline::Strong(inner) => out.push_str(&plain_inline_text(inner)),
+            Inline::Emph(inner) => out.push_str(&plain_inline_text(inner)),
+            Inline::Link { text, .. } => out.push_str(&plain_inline_text(text)),
+            Inline::Footnote(content) => out.push_str(&plain_inline_text(content)),
+            Inline::Color { content, .. } => out.push_str(&plain_inline_text(content)),
+            Inline::Superscript(content) => out.push_str(&plain_inline_text(content)),
+            Inline::Subscript(content) => out.push_str(&plain_inline_text(content)),
+            Inline::Underline(content)
+            | Inline::Overline(content)
+            | Inline::Strike(content)
+            | Inline::SmallCaps(content) => out.push_str(&plain_inline_text(content)),
+            Inline::Uppercase(content) => out.push_str(&plain_inline_text(content).to_uppercase()),
+            Inline::Lowercase(content) => out.push_str(&plain_inline_text(content).to_lowercase()),
+            Inline::SmartQuote { double: true } => out.push('"'),
+            Inline::SmartQuote { double: false } => out.push('\''),
+            Inline::LineBreak => out.push(' '),
+            Inline::Quantity { value, unit } => {
+                let parts: Vec<&str> = value.iter().chain(unit).map(String::as_str).collect();
+                out.push_str(&parts.join(" "));
             }
+            Inline::Chemical(formula) => out.push_str(formula),
+            Inline::RawLatex(_)
+            | Inline::Ref(_)
+            | Inline::RefRange { .. }
+            | Inline::SupplementedRef { .. }
+            | Inline::Label(_)
+            | Inline::Cite(_)
+            | Inline::Citation(_)
+            | Inline::Pause
+            | Inline::PageNumber(_)
+            | Inline::RunningHead(_) => {}
+            _ => {}
         }
-        return Some((latex, token.len()));
     }
-    None
+    out
 }
 
-fn parse_named_arg(input: &str, name: &str) -> Option<String> {
-    let trimmed = input.trim();
-    let prefix = format!("{}:", name);
-    if trimmed.starts_with(&prefix) {
-        let value = trimmed[prefix.len()..].trim();
-        return Some(value.trim_matches('"').to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tylax_ir::{Author, DocumentMeta, PageDecoration, PageMargin, TableRule};
+
+    /// A cell of one row and column holding `text`, for tests to set the
+    /// rest of with `..cell(text)`.
+    fn cell(text: &str) -> TableCell {
+        TableCell {
+            content: vec![Inline::text(text)],
+            blocks: None,
+            colspan: 1,
+            rowspan: 1,
+            align: None,
+            valign: None,
+            is_header: false,
+            is_footer: false,
+            fill: None,
+            stroke: None,
+            inset: None,
+        }
+    }
+
+    /// A table of `columns` holding `cells` and set with no options.
+    fn table(columns: usize, cells: Vec<TableCell>) -> Table {
+        Table {
+            columns,
+            cells,
+            align: None,
+            valign: None,
+            caption: None,
+            stroke: None,
+            fill: None,
+            inset: None,
+            label: None,
+            repeat_header: false,
+            widths: Vec::new(),
+            gutter: None,
+            row_gutter: None,
+            column_gutter: None,
+            rules: Vec::new(),
+        }
+    }
+
+    /// An image of `path` at its natural size.
+    fn image(path: &str) -> Image {
+        Image {
+            path: path.to_string(),
+            width: None,
+            height: None,
+            fit: None,
+            alt: None,
+        }
+    }
+
+    /// A numbered figure of `content` with no caption or label, placed
+    /// where the renderer chooses.
+    fn figure(content: FigureContent) -> Figure {
+        Figure {
+            content,
+            caption: None,
+            label: None,
+            placement: None,
+            unnumbered: false,
+            caption_separator: None,
+            caption_position: None,
+            short_caption: None,
+            numbering: None,
+            wrap: None,
+        }
     }
-    None
-}
 
-fn is_mat_named_arg(entry: &str) -> bool {
-    let lower = entry.trim_start().to_lowercase();
-    lower.starts_with("delim:")
-        || lower.starts_with("augment:")
-        || lower.starts_with("align:")
-        || lower.starts_with("gap:")
-        || lower.starts_with("row-gap:")
-        || lower.starts_with("column-gap:")
-}
-
-fn mat_env_from_delim(delim: Option<&str>) -> &'static str {
-    let Some(raw) = delim else {
-        return "pmatrix";
-    };
-    let lower = raw.trim().trim_matches('"').to_lowercase();
-    if lower.contains("none") {
-        return "matrix";
+    #[test]
+    fn strip_references_prefix_inside_size() {
+        let entry = vec![Inline::Size {
+            size: "9pt".to_string(),
+            content: vec![Inline::Text("[1] A.".to_string())],
+        }];
+        let (label, body) = strip_reference_prefix_inlines(&entry);
+        assert_eq!(label.as_deref(), Some("ref1"));
+        let rendered = render_inlines(&body, &LatexRenderOptions::default());
+        assert_eq!(rendered, "{\\small A.}");
     }
-    if lower.contains('[') || lower.contains("bracket") {
-        return "bmatrix";
+
+    #[test]
+    fn emphasized_math_stays_in_math_mode() {
+        let options = LatexRenderOptions {
+            math_emphasis: true,
+            ..LatexRenderOptions::default()
+        };
+        let strong = vec![Inline::Strong(vec![Inline::Math("x^2".to_string())])];
+        assert_eq!(render_inlines(&strong, &options), "$\\boldsymbol{x^2}$");
+        let emph = vec![Inline::Emph(vec![Inline::Math("v".to_string())])];
+        assert_eq!(render_inlines(&emph, &options), "$\\mathit{v}$");
+        let mixed = vec![Inline::Strong(vec![
+            Inline::Text("important ".to_string()),
+            Inline::Math("x".to_string()),
+        ])];
+        assert_eq!(render_inlines(&mixed, &options), "\\textbf{important $x$}");
+        assert_eq!(
+            render_inlines(&strong, &LatexRenderOptions::default()),
+            "\\textbf{$x^2$}"
+        );
     }
-    if lower.contains('{') || lower.contains("brace") {
-        return "Bmatrix";
+
+    #[test]
+    fn images_pdflatex_cannot_read_are_included_converted() {
+        assert_eq!(image_path("figs/plot.svg"), "figs/plot.pdf");
+        assert_eq!(image_path("Anim.GIF"), "Anim.png");
+        assert_eq!(image_path("photo.jpg"), "photo.jpg");
+        assert_eq!(image_path("figs.v2/plot"), "figs.v2/plot");
+        assert_eq!(included_image_path("photo.jpg"), None);
     }
-    if lower.contains("||") || lower.contains('‖') || lower.contains("double") {
-        return "Vmatrix";
+
+    #[test]
+    fn subfigures_are_set_with_subcaption() {
+        let subfigure = |path: &str, caption: &str, label: Option<&str>| Figure {
+            caption: Some(vec![Inline::text(caption)]),
+            label: label.map(str::to_string),
+            ..figure(FigureContent::Image(image(path)))
+        };
+        let doc = Document::new(vec![Block::Figure(Figure {
+            caption: Some(vec![Inline::text("Both")]),
+            label: Some("fig:both".to_string()),
+            ..figure(FigureContent::SubFigures {
+                figures: vec![
+                    subfigure("a.png", "Left", Some("fig:a")),
+                    subfigure("b.png", "Right", None),
+                ],
+                columns: 2,
+            })
+        })]);
+        let options = LatexRenderOptions {
+            full_document: true,
+            ..Default::default()
+        };
+        let out = render_document(&doc, options.clone());
+        assert!(out.contains("\\usepackage{subcaption}\n"), "{out}");
+        assert!(
+            out.contains(concat!(
+                "\\centering\n",
+                "\\begin{subfigure}[t]{0.47\\linewidth}\n",
+                "\\centering\n",
+                "\\includegraphics[width=\\linewidth]{a.png}\n",
+                "\\subcaption{Left}\n",
+                "\\label{fig:a}\n",
+                "\\end{subfigure}\\hfill\n",
+                "\\begin{subfigure}[t]{0.47\\linewidth}\n",
+                "\\centering\n",
+                "\\includegraphics[width=\\linewidth]{b.png}\n",
+                "\\subcaption{Right}\n",
+                "\\end{subfigure}\n",
+                "\\caption{Both}\n",
+                "\\label{fig:both}",
+            )),
+            "{out}"
+        );
+        let labels: Vec<String> = label_map(&doc, &options)
+            .into_iter()
+            .map(|(label, _)| label)
+            .collect();
+        assert_eq!(labels, ["fig:a", "fig:both"]);
+
+        let rows = render_subfigures(
+            &[
+                subfigure("a.png", "A", None),
+                subfigure("b.png", "B", None),
+                subfigure("c.png", "C", None),
+            ],
+            2,
+            &options,
+        );
+        let breaks: Vec<&str> = rows
+            .lines()
+            .filter(|line| matches!(*line, "\\end{subfigure}\\hfill" | "\\end{subfigure}\\par"))
+            .collect();
+        assert_eq!(
+            breaks,
+            ["\\end{subfigure}\\hfill", "\\end{subfigure}\\par"],
+            "{rows}"
+        );
     }
-    if lower.contains('|') || lower.contains("bar") {
-        return "vmatrix";
+
+    #[test]
+    fn wrapped_figures_are_set_with_wrapfig() {
+        let wrapped = |width: Option<&str>, wrap| Figure {
+            caption: Some(vec![Inline::text("Plot")]),
+            label: Some("fig:plot".to_string()),
+            wrap,
+            ..figure(FigureContent::Image(Image {
+                width: width.map(str::to_string),
+                ..image("plot.png")
+            }))
+        };
+        let doc = Document::new(vec![
+            Block::Figure(wrapped(Some("40%"), Some(Side::Right))),
+            Block::Figure(wrapped(None, Some(Side::Left))),
+        ]);
+        let options = LatexRenderOptions {
+            full_document: true,
+            ..Default::default()
+        };
+        let out = render_document(&doc, options);
+        assert!(out.contains("\\usepackage{wrapfig}\n"), "{out}");
+        assert!(
+            out.contains(concat!(
+                "\\begin{wrapfigure}{r}{0.40\\textwidth}\n",
+                "\\centering\n",
+                "\\includegraphics[width=\\linewidth]{plot.png}\n",
+                "\\caption{Plot}\n",
+                "\\label{fig:plot}\n",
+                "\\end{wrapfigure}",
+            )),
+            "{out}"
+        );
+        assert!(
+            out.contains("\\begin{wrapfigure}{l}{0pt}\n\\centering\n\\includegraphics{plot.png}"),
+            "{out}"
+        );
     }
-    if lower.contains('(') || lower.contains("paren") {
-        return "pmatrix";
+
+    #[test]
+    fn render_map_covers_each_block() {
+        let doc = Document::new(vec![
+            Block::Paragraph(vec![Inline::text("First")]),
+            Block::Paragraph(vec![Inline::text("   ")]),
+            Block::Paragraph(vec![Inline::text("Second")]),
+        ]);
+        let (out, map) = render_document_with_map(&doc, LatexRenderOptions::default());
+        let chunks: Vec<(usize, &str)> = map
+            .iter()
+            .map(|rendered| (rendered.block, &out[rendered.start..rendered.end]))
+            .collect();
+        assert_eq!(chunks, vec![(0, "First"), (2, "Second")]);
     }
-    "pmatrix"
-}
 
-fn render_environment(
-    env: &EnvironmentBlock,
-    options: &LatexRenderOptions,
-    label: Option<&str>,
-) -> String {
-    let name = sanitize_env_name(&env.name);
-    let mut out = String::new();
-    if name == "proof" {
-        out.push_str("\\begin{proof}\n");
-    } else if let Some(title) = &env.title {
-        out.push_str(&format!(
-            "\\begin{{{}}}[{}]\n",
-            name,
-            normalize_inline_whitespace(&render_inlines(title, options))
-        ));
-    } else {
-        out.push_str(&format!("\\begin{{{}}}\n", name));
+    #[test]
+    fn nested_lists_are_indented() {
+        let para = |text: &str| Block::Paragraph(vec![Inline::text(text)]);
+        let inner = Block::List {
+            kind: ListKind::Ordered,
+            items: vec![vec![para("deep")]],
+        };
+        let list = Block::List {
+            kind: ListKind::Unordered,
+            items: vec![
+                vec![para("top"), inner.clone(), para("after")],
+                vec![inner],
+                vec![],
+                vec![Block::Paragraph(vec![Inline::text(" ")])],
+            ],
+        };
+        assert_eq!(
+            render_block(&list, &LatexRenderOptions::default()),
+            "\\begin{itemize}\n  \\item top\n  \\begin{enumerate}\n    \\item deep\n  \\end{enumerate}\n  after\n  \\item\n  \\begin{enumerate}\n    \\item deep\n  \\end{enumerate}\n\\end{itemize}"
+        );
+        let blank = Block::List {
+            kind: ListKind::Unordered,
+            items: vec![vec![], vec![para("kept")], vec![]],
+        };
+        assert_eq!(
+            render_block(&blank, &LatexRenderOptions::default()),
+            "\\begin{itemize}\n  \\item \n  \\item kept\n\\end{itemize}"
+        );
     }
-    out.push_str(&render_blocks_inline(&env.blocks, options));
-    if let Some(label) = label {
-        out.push_str("\n\\label{");
-        out.push_str(&escape_label(label));
-        out.push('}');
+
+    #[test]
+    fn deeply_nested_blocks_are_truncated() {
+        let mut block = Block::Paragraph(vec![Inline::text("core")]);
+        for _ in 0..2_000 {
+            block = Block::Quote(vec![block]);
+        }
+        let mut inline = Inline::text("x");
+        for _ in 0..2_000 {
+            inline = Inline::Strong(vec![inline]);
+        }
+        let doc = Document::new(vec![block, Block::Paragraph(vec![inline])]);
+        let out = render_document(&doc, LatexRenderOptions::default());
+        assert!(!out.contains("core"));
+        assert!(out.starts_with("\\begin{quote}"));
+
+        let shallow = Block::Quote(vec![Block::Quote(vec![Block::Paragraph(vec![
+            Inline::text("kept"),
+        ])])]);
+        assert!(render_block(&shallow, &LatexRenderOptions::default()).contains("kept"));
     }
-    out.push_str(&format!("\n\\end{{{}}}", name));
-    out
-}
 
-fn sanitize_env_name(name: &str) -> String {
-    let lowered = name.trim().to_lowercase();
-    if lowered.is_empty() {
-        return "theorem".to_string();
+    #[test]
+    fn term_list_renders_description() {
+        let list = Block::TermList(vec![
+            TermItem {
+                term: vec![Inline::Strong(vec![Inline::text("Term")])],
+                description: vec![Block::Paragraph(vec![Inline::text("Meaning.")])],
+            },
+            TermItem {
+                term: vec![Inline::text("a[1]")],
+                description: vec![],
+            },
+        ]);
+        assert_eq!(
+            render_block(&list, &LatexRenderOptions::default()),
+            "\\begin{description}\n  \\item[\\textbf{Term}] Meaning.\n  \\item[{a[1]}]\n\\end{description}"
+        );
     }
-    lowered
-        .chars()
-        .filter(|ch| ch.is_ascii_alphanumeric())
-        .collect::<String>()
-}
 
-fn render_inline_size(size: &str, content: &[Inline], options: &LatexRenderOptions) -> String {
-    if content.is_empty() {
-        return String::new();
+    #[test]
+    fn ref_ranges_follow_cleveref_mode() {
+        let range = vec![Inline::RefRange {
+            from: "fig:a".to_string(),
+            to: "fig:c".to_string(),
+        }];
+        assert_eq!(
+            render_inlines(&range, &LatexRenderOptions::default()),
+            "Fig.~\\ref{fig:a}--\\ref{fig:c}"
+        );
+        let cleveref = LatexRenderOptions {
+            cleveref: true,
+            ..LatexRenderOptions::default()
+        };
+        assert_eq!(
+            render_inlines(&range, &cleveref),
+            "\\Crefrange{fig:a}{fig:c}"
+        );
+        let single = vec![Inline::text("see "), Inline::Ref("sec:intro".to_string())];
+        assert_eq!(render_inlines(&single, &cleveref), "see \\cref{sec:intro}");
+        let opening = vec![
+            Inline::text("Done. "),
+            Inline::Ref("sec:intro".to_string()),
+            Inline::text(" shows it."),
+        ];
+        assert_eq!(
+            render_inlines(&opening, &cleveref),
+            "Done. \\Cref{sec:intro} shows it."
+        );
     }
-    let base_pt = options.base_font_size_pt.unwrap_or(10.0);
-    if let Some(size_pt) = parse_size_to_pt(size, base_pt) {
-        if base_pt > 0.0 {
-            let scale = size_pt / base_pt;
-            if (scale - 1.0).abs() < 0.05 {
-                return render_inlines(content, options);
-            }
-            if let Some(cmd) = map_size_command(scale) {
-                return format!("{{{} {}}}", cmd, render_inlines(content, options));
-            }
-        }
-        let baseline = (size_pt * 1.2).max(size_pt + 1.0);
-        return format!(
-            "{{\\fontsize{{{:.1}pt}}{{{:.1}pt}}\\selectfont {}}}",
-            size_pt,
-            baseline,
-            render_inlines(content, options)
+
+    #[test]
+    fn cleveref_capitals_follow_the_sentence_not_the_run() {
+        let cleveref = LatexRenderOptions {
+            cleveref: true,
+            ..LatexRenderOptions::default()
+        };
+        let abbreviated = vec![
+            Inline::text("as shown (cf. "),
+            Inline::Ref("sec:intro".to_string()),
+            Inline::text(", e.g. "),
+            Inline::Ref("fig:plot".to_string()),
+            Inline::text(")"),
+        ];
+        assert_eq!(
+            render_inlines(&abbreviated, &cleveref),
+            "as shown (cf. \\cref{sec:intro}, e.g. \\cref{fig:plot})"
+        );
+        let nested = vec![
+            Inline::text("as in "),
+            Inline::Strong(vec![Inline::Ref("sec:intro".to_string())]),
+            Inline::text(". "),
+            Inline::Emph(vec![Inline::Ref("fig:plot".to_string())]),
+        ];
+        assert_eq!(
+            render_inlines(&nested, &cleveref),
+            "as in \\textbf{\\cref{sec:intro}}. \\textit{\\Cref{fig:plot}}"
         );
     }
-    render_inlines(content, options)
-}
 
-fn parse_size_to_pt(value: &str, base_pt: f64) -> Option<f64> {
-    let (num, unit) = split_number_unit(value)?;
-    match unit {
-        "pt" => Some(num),
-        "em" | "rem" => {
-            if base_pt > 0.0 {
-                Some(num * base_pt)
+    #[test]
+    fn supplemented_refs_set_their_own_supplement() {
+        let supplemented = |label: &str, supplement: &str| Inline::SupplementedRef {
+            label: label.to_string(),
+            supplement: if supplement.is_empty() {
+                Vec::new()
             } else {
-                None
-            }
+                vec![Inline::text(supplement)]
+            },
+        };
+        let cleveref = LatexRenderOptions {
+            cleveref: true,
+            ..LatexRenderOptions::default()
+        };
+        for options in [&LatexRenderOptions::default(), &cleveref] {
+            let inlines = vec![
+                supplemented("fig:plot", "Plot"),
+                Inline::text(", "),
+                supplemented("fig:plot", ""),
+                Inline::text(", "),
+                supplemented("eq:one", "Eq."),
+            ];
+            assert_eq!(
+                render_inlines(&inlines, options),
+                "Plot~\\ref{fig:plot}, \\ref{fig:plot}, Eq.~\\eqref{eq:one}"
+            );
         }
-        "" => Some(num),
-        _ => None,
     }
-}
 
-fn split_number_unit(value: &str) -> Option<(f64, &str)> {
-    let trimmed = value.trim();
-    if trimmed.is_empty() {
-        return None;
-    }
-    let mut split_idx = trimmed.len();
-    for (idx, ch) in trimmed.char_indices() {
-        if !(ch.is_ascii_digit() || ch == '.' || ch == '-') {
-            split_idx = idx;
-            break;
-        }
+    #[test]
+    fn label_rewrites_apply_to_labels_and_references_alike() {
+        let options = LatexRenderOptions {
+            label_rewrites: vec![
+                ("fig:".to_string(), "fig-".to_string()),
+                ("sec:".to_string(), "s:".to_string()),
+            ],
+            ..LatexRenderOptions::default()
+        };
+        let doc = Document::new(vec![
+            Block::Heading {
+                level: 1,
+                content: vec![Inline::text("Intro")],
+                numbered: true,
+                label: Some("sec:intro".to_string()),
+                outlined: true,
+                bookmarked: None,
+            },
+            Block::Paragraph(vec![
+                Inline::Ref("fig:plot".to_string()),
+                Inline::text(" "),
+                Inline::Label("eq:one".to_string()),
+                Inline::text(" "),
+                Inline::Cite("fig:key".to_string()),
+            ]),
+        ]);
+        let out = render_document(&doc, options.clone());
+        assert!(out.contains("\\label{s:intro}"), "{out}");
+        assert!(out.contains("Fig.~\\ref{fig-plot}"), "{out}");
+        assert!(out.contains("\\label{eq:one}"), "{out}");
+        assert!(out.contains("{fig:key}"), "{out}");
+        assert_eq!(
+            label_map(&doc, &options),
+            vec![
+                ("eq:one".to_string(), "eq:one".to_string()),
+                ("sec:intro".to_string(), "s:intro".to_string()),
+            ]
+        );
     }
-    let (num_str, unit) = trimmed.split_at(split_idx);
-    let num = num_str.trim().parse::<f64>().ok()?;
-    Some((num, unit.trim()))
-}
 
-fn map_size_command(scale: f64) -> Option<&'static str> {
-    const SIZE_MAP: [(&str, f64); 10] = [
-        ("\\tiny", 0.5),
-        ("\\scriptsize", 0.7),
-        ("\\footnotesize", 0.8),
-        ("\\small", 0.9),
-        ("\\normalsize", 1.0),
-        ("\\large", 1.2),
-        ("\\Large", 1.44),
-        ("\\LARGE", 1.728),
-        ("\\huge", 2.074),
-        ("\\Huge", 2.488),
-    ];
-    let mut best_cmd: Option<&'static str> = None;
-    let mut best_diff = f64::MAX;
-    for (cmd, target) in SIZE_MAP {
-        let diff = (scale - target).abs();
-        if diff < best_diff {
-            best_diff = diff;
-            best_cmd = Some(cmd);
+    fn heading(title: &str) -> Block {
+        Block::Heading {
+            level: 1,
+            content: vec![Inline::text(title)],
+            numbered: false,
+            label: None,
+            outlined: true,
+            bookmarked: None,
         }
     }
-    if best_diff <= 0.15 {
-        best_cmd
-    } else {
-        None
+
+    fn body(out: &str) -> &str {
+        let start = out.find("\\begin{document}").unwrap();
+        &out[start..]
     }
-}
 
-fn render_inlines(inlines: &[Inline], options: &LatexRenderOptions) -> String {
-    let mut out = String::new();
-    let mut last_was_linebreak = false;
-    for inline in inlines {
-        match inline {
-            Inline::Text(text) => out.push_str(&escape_latex(text)),
-            Inline::Size { size, content } => {
-                out.push_str(&render_inline_size(size, content, options));
-            }
-            Inline::Strong(inner) => {
-                out.push_str("\\textbf{");
-                out.push_str(&render_inlines(inner, options));
-                out.push('}');
-            }
-            Inline::Emph(inner) => {
-                out.push_str("\\textit{");
-                out.push_str(&render_inlines(inner, options));
-                out.push('}');
-            }
-            Inline::Code(code) => {
-                out.push_str("\\texttt{");
-                out.push_str(&escape_latex(code));
-                out.push('}');
-            }
-            Inline::Math(content) => {
-                out.push('$');
-                out.push_str(&convert_math_content_inline(content));
-                out.push('$');
-            }
-            Inline::Link { text, url } => {
-                out.push_str("\\href{");
-                out.push_str(&escape_latex(url));
-                out.push_str("}{");
-                out.push_str(&render_inlines(text, options));
-                out.push('}');
-            }
-            Inline::Ref(label) => {
-                if is_equation_label(label) {
-                    out.push_str("\\eqref{");
-                    out.push_str(&escape_label(label));
-                    out.push('}');
-                } else if let Some(prefix) = reference_prefix(label) {
-                    out.push_str(prefix);
-                    out.push_str("~\\ref{");
-                    out.push_str(&escape_label(label));
-                    out.push('}');
-                } else {
-                    out.push_str("\\ref{");
-                    out.push_str(&escape_label(label));
-                    out.push('}');
-                }
-            }
-            Inline::Label(label) => {
-                out.push_str("\\label{");
-                out.push_str(&escape_label(label));
-                out.push('}');
-            }
-            Inline::Cite(key) => {
-                let cmd = options.cite_command.as_deref().unwrap_or("cite");
-                out.push_str("\\");
-                out.push_str(cmd);
-                out.push_str("{");
-                out.push_str(&escape_cite_keys(key));
-                out.push('}');
-            }
-            Inline::Footnote(content) => {
-                out.push_str("\\footnote{");
-                out.push_str(&render_inlines(content, options));
-                out.push('}');
-            }
-            Inline::Color { color, content } => {
-                let (model, value) = color_to_latex(color);
-                out.push_str("\\textcolor");
-                if let Some(model) = model {
-                    out.push_str(&format!("[{}]", model));
-                }
-                out.push_str("{");
-                out.push_str(&value);
-                out.push_str("}{");
-                out.push_str(&render_inlines(content, options));
-                out.push('}');
-            }
-            Inline::RawLatex(raw) => out.push_str(raw),
-            Inline::Superscript(content) => {
-                out.push_str("\\textsuperscript{");
-                out.push_str(&render_inlines(content, options));
-                out.push('}');
-            }
-            Inline::Subscript(content) => {
-                out.push_str("\\textsubscript{");
-                out.push_str(&render_inlines(content, options));
-                out.push('}');
-            }
-            Inline::LineBreak => {
-                if !last_was_linebreak {
-                    if !out.trim().is_empty() {
-                        // Add a trailing space to avoid "\\[...]" being parsed as an optional arg.
-                        out.push_str("\\\\ ");
-                        last_was_linebreak = true;
-                    }
-                }
-                continue;
-            }
-        }
-        last_was_linebreak = false;
+    #[test]
+    fn footnotes_protect_citations_and_keep_equations_inline() {
+        let doc = Document::new(vec![Block::Paragraph(vec![
+            Inline::Cite("outside".to_string()),
+            Inline::Footnote(vec![
+                Inline::text("See "),
+                Inline::Cite("knuth".to_string()),
+                Inline::text(" and "),
+                Inline::Math("a &= b".to_string()),
+                Inline::text(" with "),
+                Inline::Math("x \\& y".to_string()),
+                Inline::text(", "),
+                Inline::Math("mat(1, 2)".to_string()),
+            ]),
+        ])]);
+        let out = render_document(&doc, LatexRenderOptions::default());
+        assert!(out.starts_with("\\cite{outside}"));
+        assert!(out.contains(
+            "\\footnote{See \\protect\\cite{knuth} and $a = b$ with $x \\& y$, \
+             $\\begin{pmatrix}1 & 2\\end{pmatrix}$}"
+        ));
     }
-    out
-}
 
-fn is_equation_label(label: &str) -> bool {
-    let lowered = label.trim().to_lowercase();
-    lowered.starts_with("eq:")
-}
+    #[test]
+    fn document_meta_sets_up_the_preamble() {
+        let meta = DocumentMeta {
+            paper: Some("a4".to_string()),
+            margin: PageMargin {
+                top: Some("3cm".to_string()),
+                bottom: Some("3cm".to_string()),
+                left: Some("2cm".to_string()),
+                right: Some("2cm".to_string()),
+            },
+            font_size: Some("11pt".to_string()),
+            lang: Some("de".to_string()),
+            heading_numbering: Some("1.a".to_string()),
+            justify: Some(false),
+            ..DocumentMeta::default()
+        };
+        let doc = Document::new(vec![heading("Intro")]).with_meta(meta);
+        let options = LatexRenderOptions {
+            full_document: true,
+            ..LatexRenderOptions::default()
+        };
+        let out = render_document(&doc, options.clone());
+        assert!(out.starts_with("\\documentclass[11pt]{article}\n"));
+        assert!(out.contains(
+            "\\usepackage[a4paper,top=3cm,bottom=3cm,left=2cm,right=2cm]{geometry}"
+        ));
+        assert!(out.contains("\\usepackage[ngerman]{babel}"));
+        assert!(out.contains("\\usepackage[document]{ragged2e}"));
+        assert!(out.contains(
+            "\\renewcommand{\\thesubsection}{\\thesection.\\alph{subsection}}"
+        ));
 
-fn reference_prefix(label: &str) -> Option<&'static str> {
-    let lowered = label.trim().to_lowercase();
-    if lowered.starts_with("fig:") {
-        return Some("Fig.");
+        let unnumbered = Document::new(vec![heading("Intro")]).with_meta(DocumentMeta {
+            heading_numbering: Some(String::new()),
+            margin: PageMargin {
+                top: Some("1in".to_string()),
+                bottom: Some("1in".to_string()),
+                left: Some("1in".to_string()),
+                right: Some("1in".to_string()),
+            },
+            font_size: Some("9pt".to_string()),
+            ..DocumentMeta::default()
+        });
+        let out = render_document(&unnumbered, options);
+        assert!(out.starts_with("\\documentclass{article}\n"));
+        assert!(out.contains("\\usepackage[fontsize=9pt]{scrextend}"));
+        assert!(out.contains("\\usepackage[margin=1in]{geometry}"));
+        assert!(out.contains("\\setcounter{secnumdepth}{0}"));
+        let body_only = render_document(&unnumbered, LatexRenderOptions::default());
+        assert!(!body_only.contains("geometry"));
     }
-    if lowered.starts_with("tab:") {
-        return Some("Table");
+
+    #[test]
+    fn headers_footers_and_page_numbering_set_the_page_style() {
+        let options = LatexRenderOptions {
+            full_document: true,
+            ..LatexRenderOptions::default()
+        };
+        let meta = DocumentMeta {
+            page_numbering: Some("1 of 1".to_string()),
+            header: Some(PageDecoration {
+                left: vec![Inline::Emph(vec![Inline::text("Draft")])],
+                right: vec![Inline::text("Report")],
+                ..PageDecoration::default()
+            }),
+            ..DocumentMeta::default()
+        };
+        let doc = Document::new(vec![heading("Intro")]).with_meta(meta.clone());
+        let out = render_document(&doc, options.clone());
+        assert!(out.contains("\\usepackage{lastpage}\n"));
+        assert!(out.contains("\\usepackage{fancyhdr}\n\\pagestyle{fancy}\n\\fancyhf{}\n"));
+        assert!(out.contains("\\fancyhead[L]{\\textit{Draft}}\n\\fancyhead[R]{Report}\n"));
+        assert!(out.contains("\\fancyfoot[C]{\\arabic{page} of \\pageref{LastPage}}\n"));
+
+        let footer = DocumentMeta {
+            page_numbering: Some("i".to_string()),
+            footer: Some(PageDecoration {
+                right: vec![Inline::text("p. "), Inline::PageNumber("i".to_string())],
+                ..PageDecoration::default()
+            }),
+            ..DocumentMeta::default()
+        };
+        let out = render_document(&Document::new(Vec::new()).with_meta(footer), options.clone());
+        assert!(out.contains("\\pagenumbering{roman}\n"));
+        assert!(out.contains("\\fancyfoot[R]{p. \\roman{page}}\n"));
+        assert!(!out.contains("\\fancyfoot[C]"));
+        assert!(!out.contains("lastpage"));
+
+        let unnumbered = DocumentMeta {
+            page_numbering: Some(String::new()),
+            ..DocumentMeta::default()
+        };
+        let out = render_document(&Document::new(Vec::new()).with_meta(unnumbered), options);
+        assert!(out.contains("\\pagestyle{empty}\n"));
+        assert!(!out.contains("fancyhdr"));
+
+        let slides = LatexRenderOptions {
+            full_document: true,
+            template: DocumentTemplate::Beamer,
+            ..LatexRenderOptions::default()
+        };
+        assert!(!render_document(&doc, slides).contains("fancyhdr"));
     }
-    if lowered.starts_with("sec:") {
-        return Some("Section");
+
+    #[test]
+    fn alternating_headers_and_running_heads_set_both_sides() {
+        let options = LatexRenderOptions {
+            full_document: true,
+            ..LatexRenderOptions::default()
+        };
+        let meta = DocumentMeta {
+            header: Some(PageDecoration {
+                right: vec![Inline::RunningHead(1)],
+                even: Some(Box::new(PageDecoration {
+                    left: vec![Inline::RunningHead(2)],
+                    ..PageDecoration::default()
+                })),
+                ..PageDecoration::default()
+            }),
+            ..DocumentMeta::default()
+        };
+        let doc = Document::new(vec![heading("Intro")]).with_meta(meta);
+        let out = render_document(&doc, options);
+        assert!(out.starts_with("\\documentclass[twoside]{article}\n"));
+        assert!(out.contains("\\renewcommand{\\sectionmark}[1]{\\markboth{#1}{}}\n"));
+        assert!(out.contains("\\fancyhead[RO]{\\leftmark{}}\n\\fancyhead[LE]{\\rightmark{}}\n"));
+        assert!(!out.contains("lastpage"));
+
+        // A page count in the text needs lastpage as much as one in a footer.
+        let body = Document::new(vec![Block::Paragraph(vec![Inline::PageNumber(
+            "1 / 1".to_string(),
+        )])]);
+        let out = render_document(
+            &body,
+            LatexRenderOptions {
+                full_document: true,
+                template: DocumentTemplate::Custom {
+                    preamble: "\\documentclass{article}\n".to_string(),
+                },
+                ..LatexRenderOptions::default()
+            },
+        );
+        assert!(!out.contains("lastpage"), "custom preambles load their own");
+        let mut preamble = String::new();
+        push_content_packages(&mut preamble, &body, &LatexRenderOptions::default());
+        assert_eq!(preamble, "\\usepackage{lastpage}\n");
     }
-    if lowered.starts_with("alg:") {
-        return Some("Algorithm");
+
+    #[test]
+    fn documents_set_in_columns_use_twocolumn_or_multicols() {
+        let blocks = vec![
+            Block::Paragraph(vec![Inline::text("One.")]),
+            Block::Figure(figure(FigureContent::Image(image("plot.png")))),
+            Block::Paragraph(vec![Inline::text("Two.")]),
+        ];
+        let columns = |count| DocumentMeta {
+            columns: Some(count),
+            ..DocumentMeta::default()
+        };
+        let options = LatexRenderOptions {
+            full_document: true,
+            ..LatexRenderOptions::default()
+        };
+        let two = Document::new(blocks.clone()).with_meta(columns(2));
+        let out = render_document(&two, options);
+        assert!(out.starts_with("\\documentclass[twocolumn]{article}\n"));
+        assert!(out.contains("\\begin{figure}[t]"), "{}", out);
+        assert!(!out.contains("multicols}{2}"), "{}", out);
+
+        let three = Document::new(blocks).with_meta(columns(3));
+        assert_eq!(
+            render_document(&three, LatexRenderOptions::default()),
+            "\\begin{multicols}{3}\nOne.\n\n\\end{multicols}\n\n\
+             \\begin{figure}[h]\n\\centering\n\\includegraphics{plot.png}\n\\end{figure}\n\n\
+             \\begin{multicols}{3}\nTwo.\n\\end{multicols}"
+        );
     }
-    if lowered.starts_with("lst:") {
-        return Some("Listing");
+
+    #[test]
+    fn front_matter_is_set_as_title_block() {
+        let meta = DocumentMeta {
+            title: Some(vec![Inline::text("A & B")]),
+            authors: vec![
+                Author {
+                    name: "Ada".to_string(),
+                    affiliation: Some("Typst GmbH".to_string()),
+                    email: Some("ada@typst.app".to_string()),
+                },
+                Author {
+                    name: "Bo".to_string(),
+                    ..Author::default()
+                },
+            ],
+            abstract_content: vec![Block::Paragraph(vec![Inline::text("We study it.")])],
+            ..DocumentMeta::default()
+        };
+        let doc = Document::new(vec![heading("Intro")]).with_meta(meta);
+        let options = LatexRenderOptions {
+            full_document: true,
+            ..LatexRenderOptions::default()
+        };
+        let out = render_document(&doc, options);
+        assert!(body(&out).starts_with(
+            "\\begin{document}\n\n\\title{A \\& B}\n\
+             \\author{Ada \\\\ Typst GmbH \\\\ \\texttt{ada@typst.app} \\and Bo}\n\
+             \\date{}\n\
+             \\maketitle\n\n\
+             \\begin{abstract}\nWe study it.\n\\end{abstract}\n\n\\section*{Intro}"
+        ));
+        let body_only = render_document(&doc, LatexRenderOptions::default());
+        assert!(!body_only.contains("maketitle"));
     }
-    if lowered.starts_with("thm:") {
-        return Some("Theorem");
+
+    #[test]
+    fn leading_abstract_heading_becomes_environment() {
+        let doc = Document::new(vec![
+            heading("Abstract"),
+            Block::Paragraph(vec![Inline::text("We study it.")]),
+            Block::Paragraph(vec![Inline::text("It works.")]),
+            heading("Introduction"),
+            Block::Paragraph(vec![Inline::text("Start.")]),
+        ]);
+        let options = LatexRenderOptions {
+            full_document: true,
+            ..LatexRenderOptions::default()
+        };
+        let out = render_document(&doc, options.clone());
+        assert!(body(&out).contains(
+            "\\begin{abstract}\nWe study it.\n\nIt works.\n\\end{abstract}"
+        ));
+        assert!(!out.contains("{Abstract}"));
+        assert!(out.contains("{Introduction}"));
+
+        let opted_out = render_document(
+            &doc,
+            LatexRenderOptions {
+                detect_abstract: false,
+                ..options
+            },
+        );
+        assert!(!opted_out.contains("\\begin{abstract}"));
+        let body_only = render_document(&doc, LatexRenderOptions::default());
+        assert!(!body_only.contains("\\begin{abstract}"));
     }
-    if lowered.starts_with("lemma:") || lowered.starts_with("lem:") {
-        return Some("Lemma");
+
+    #[test]
+    fn short_documents_keep_text_after_abstract_paragraph() {
+        let options = LatexRenderOptions {
+            full_document: true,
+            ..LatexRenderOptions::default()
+        };
+        let note = Document::new(vec![
+            heading("Abstract"),
+            Block::Paragraph(vec![Inline::text("Summary.")]),
+            Block::Paragraph(vec![Inline::text("Main text.")]),
+        ]);
+        let out = render_document(&note, options.clone());
+        assert!(out.contains("\\begin{abstract}\nSummary.\n\\end{abstract}\n\nMain text."));
+
+        let later = Document::new(vec![
+            heading("Introduction"),
+            heading("Abstract"),
+            Block::Paragraph(vec![Inline::text("Late.")]),
+        ]);
+        assert!(!render_document(&later, options).contains("\\begin{abstract}"));
     }
-    if lowered.starts_with("prop:") {
-        return Some("Proposition");
+
+    #[test]
+    fn heading_flags_control_contents_and_bookmarks() {
+        let render = |outlined, bookmarked, numbered| {
+            let heading = Block::Heading {
+                level: 2,
+                content: vec![Inline::text("Rate "), Inline::Math("alpha^2".to_string())],
+                numbered,
+                label: None,
+                outlined,
+                bookmarked,
+            };
+            render_block(&heading, &LatexRenderOptions::default())
+        };
+        assert_eq!(
+            render(true, None, true),
+            "\\subsection{\\texorpdfstring{Rate $\\alpha^2$}{Rate alpha2}}"
+        );
+        assert_eq!(
+            render(false, None, true),
+            "\\refstepcounter{subsection}\n\
+             \\subsection*{\\thesubsection\\quad \\texorpdfstring{Rate $\\alpha^2$}{Rate alpha2}}"
+        );
+        assert!(render(false, Some(true), true)
+            .starts_with("\\addtocontents{toc}{\\protect\\setcounter{tocdepth}{1}}\n\\subsection{"));
+        assert!(render(true, Some(true), false)
+            .starts_with("\\pdfbookmark[2]{Rate alpha2}{heading.rate-alpha2}\n\\subsection*{"));
+        assert!(render(true, None, false).starts_with("\\subsection*{"));
     }
-    if lowered.starts_with("def:") {
-        return Some("Definition");
+
+    #[test]
+    fn toc_depth_is_set_before_the_contents_and_restored_after_hidden_headings() {
+        let hidden = Block::Heading {
+            level: 1,
+            content: vec![Inline::text("Aside")],
+            numbered: true,
+            label: None,
+            outlined: false,
+            bookmarked: Some(true),
+        };
+        let doc = Document::new(vec![
+            Block::Outline {
+                title: None,
+                target: OutlineTarget::Headings,
+            },
+            hidden,
+        ]);
+        let out = render_document(&doc, LatexRenderOptions::default());
+        assert!(out.starts_with("\\tableofcontents\n"), "{out}");
+        assert!(
+            out.ends_with(
+                "\\section{Aside}\n\\addtocontents{toc}{\\protect\\setcounter{tocdepth}{3}}"
+            ),
+            "{out}"
+        );
+
+        let options = LatexRenderOptions {
+            toc_depth: Some(2),
+            template: DocumentTemplate::Report,
+            ..LatexRenderOptions::default()
+        };
+        let out = render_document(&doc, options);
+        assert!(
+            out.starts_with("\\setcounter{tocdepth}{1}\n\\tableofcontents\n"),
+            "{out}"
+        );
+        assert!(
+            out.ends_with(
+                "\\chapter{Aside}\n\\addtocontents{toc}{\\protect\\setcounter{tocdepth}{1}}"
+            ),
+            "{out}"
+        );
     }
-    if lowered.starts_with("cor:") {
-        return Some("Corollary");
+
+    #[test]
+    fn unsupported_source_is_commented_out() {
+        let doc = Document::new(vec![Block::Unsupported {
+            source: "#place(top)[\n  Note\n]\n".to_string(),
+            reason: "place not supported".to_string(),
+        }]);
+        let out = render_document(&doc, LatexRenderOptions::default());
+        assert_eq!(
+            out.trim_end(),
+            "% BEGIN unsupported: place not supported\n% #place(top)[\n%   Note\n% ]\n% END unsupported"
+        );
     }
-    if lowered.starts_with("ex:") {
-        return Some("Example");
+
+    #[test]
+    fn units_and_formulas_load_their_packages() {
+        let quantity = |value: Option<&str>, unit: Option<&str>| Inline::Quantity {
+            value: value.map(str::to_string),
+            unit: unit.map(str::to_string),
+        };
+        let doc = Document::new(vec![Block::Paragraph(vec![
+            quantity(Some("1.5"), Some("km/s")),
+            Inline::text(", "),
+            quantity(None, Some("meter per second")),
+            Inline::text(", "),
+            Inline::Emph(vec![Inline::Chemical("H2O".to_string())]),
+        ])]);
+        let options = LatexRenderOptions {
+            full_document: true,
+            ..LatexRenderOptions::default()
+        };
+        let out = render_document(&doc, options.clone());
+        assert!(out.contains("\\usepackage{siunitx}\n"));
+        assert!(out.contains("\\usepackage[version=4]{mhchem}\n"));
+        assert!(body(&out).contains("\\SI{1.5}{km/s}, \\si{\\meter\\per\\second}, \\textit{\\ce{H2O}}"));
+
+        let plain = Document::new(vec![Block::Paragraph(vec![Inline::text("None.")])]);
+        let out = render_document(&plain, options);
+        assert!(!out.contains("siunitx") && !out.contains("mhchem"));
     }
-    if lowered.starts_with("remark:") || lowered.starts_with("rem:") {
-        return Some("Remark");
+
+    #[test]
+    fn algorithms_close_blocks_and_load_packages() {
+        use tylax_ir::{Algorithm, AlgorithmKeyword};
+        let step = |keyword, text: &str, body| AlgorithmStep {
+            keyword,
+            content: vec![Inline::text(text)],
+            comment: None,
+            body,
+        };
+        let statement = |text: &str| step(AlgorithmKeyword::Statement, text, Vec::new());
+        let doc = Document::new(vec![Block::Algorithm(Algorithm {
+            caption: None,
+            label: Some("alg:a".to_string()),
+            line_numbers: false,
+            steps: vec![
+                step(AlgorithmKeyword::Repeat, "", vec![statement("x")]),
+                step(AlgorithmKeyword::Until, "done", Vec::new()),
+                step(AlgorithmKeyword::Else, "stray", Vec::new()),
+                step(AlgorithmKeyword::Input, "n", Vec::new()),
+                AlgorithmStep {
+                    comment: Some(vec![Inline::text("halve")]),
+                    ..statement("y")
+                },
+            ],
+        })]);
+        let options = LatexRenderOptions {
+            full_document: true,
+            ..LatexRenderOptions::default()
+        };
+        let out = render_document(&doc, options);
+        assert!(out.contains("\\usepackage{algorithm}\n\\usepackage{algpseudocode}\n"));
+        assert!(body(&out).contains(
+            "\\begin{algorithm}\n\\label{alg:a}\n\\begin{algorithmic}\n  \\Repeat\n    \\State x\n  \\Until{done}\n  \\State \\textbf{else} stray\n  \\Statex \\textbf{Input:} n\n  \\State y \\Comment{halve}\n\\end{algorithmic}\n\\end{algorithm}"
+        ));
     }
-    if lowered.starts_with("app:") || lowered.starts_with("appendix:") {
-        return Some("Appendix");
+
+    #[test]
+    fn block_hints_override_float_placement_and_width() {
+        let labeled = |label: &str| {
+            Block::Figure(Figure {
+                label: Some(label.to_string()),
+                ..figure(FigureContent::Image(image("a.png")))
+            })
+        };
+        let doc = Document::new(vec![labeled("fig:a"), labeled("fig:b")]).with_hints(vec![
+            Some(BlockHints {
+                placement: Some("bottom".to_string()),
+                wide: true,
+            }),
+            None,
+        ]);
+        let out = render_document(&doc, LatexRenderOptions::default());
+        assert!(out.starts_with("\\begin{figure*}[b]\n"));
+        assert!(out.contains("\\end{figure*}\n\n\\begin{figure}[h]\n"));
     }
-    None
-}
 
-fn escape_latex(input: &str) -> String {
-    let mut out = String::new();
-    for ch in input.chars() {
-        match ch {
-            '\\' => out.push_str("\\textbackslash{}"),
-            '{' => out.push_str("\\{") ,
-            '}' => out.push_str("\\}"),
-            '$' => out.push_str("\\$") ,
-            '&' => out.push_str("\\&") ,
-            '%' => out.push_str("\\%") ,
-            '#' => out.push_str("\\#") ,
-            '_' => out.push_str("\\_") ,
-            '^' => out.push_str("\\textasciicircum{}"),
-            '~' => out.push_str("\\textasciitilde{}"),
-            _ => out.push(ch),
-        }
+    #[test]
+    fn figure_captions_follow_numbering_and_separator() {
+        let captioned = |unnumbered, separator: Option<&str>| Figure {
+            caption: Some(vec![Inline::text("A plot")]),
+            unnumbered,
+            caption_separator: separator.map(|text| vec![Inline::text(text)]),
+            ..figure(FigureContent::Image(image("a.png")))
+        };
+        let options = LatexRenderOptions::default();
+        let output = render_figure(&captioned(true, Some(". ")), &options);
+        assert!(output.contains("\\caption*{A plot}"));
+        assert!(!output.contains("\\captionsetup"));
+
+        let output = render_figure(&captioned(false, Some(". ")), &options);
+        let setup = "\\begin{figure}[h]\n\\centering\n\\captionsetup{labelsep=period}\n";
+        assert!(output.starts_with(setup));
+        assert!(output.contains("\\caption{A plot}"));
+
+        let output = render_figure(&captioned(false, Some(" | ")), &options);
+        assert!(output.contains(
+            "\\DeclareCaptionLabelSeparator{tylax}{ | }\n\\captionsetup{labelsep=tylax}\n"
+        ));
+
+        let doc = Document::new(vec![Block::Figure(captioned(true, None))]);
+        let full = render_document(
+            &doc,
+            LatexRenderOptions {
+                full_document: true,
+                ..options.clone()
+            },
+        );
+        assert!(full.contains("\\usepackage{caption}\n"));
+        let doc = Document::new(vec![Block::Figure(captioned(false, None))]);
+        let full = render_document(
+            &doc,
+            LatexRenderOptions {
+                full_document: true,
+                ..options
+            },
+        );
+        assert!(!full.contains("\\usepackage{caption}"));
+
+        let mut styled = captioned(false, None);
+        styled.short_caption = Some(vec![Inline::text("Plot")]);
+        styled.caption_position = Some(CaptionPosition::Top);
+        styled.numbering = Some("(I)".to_string());
+        let output = render_figure(&styled, &LatexRenderOptions::default());
+        assert!(
+            output.starts_with(concat!(
+                "\\begin{figure}[h]\n\\centering\n",
+                "\\captionsetup{position=top}\n",
+                "\\renewcommand{\\thefigure}{(\\Roman{figure})}\n",
+                "\\caption[Plot]{A plot}\n",
+                "\\includegraphics{a.png}",
+            )),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn links_render_by_target() {
+        let link = |text: &str, url: &str, target| Inline::Link {
+            text: vec![Inline::text(text)],
+            url: url.to_string(),
+            target,
+        };
+        let doc = Document::new(vec![Block::Paragraph(vec![
+            link("https://a.org/x_y#z", "https://a.org/x_y#z", LinkTarget::Url),
+            Inline::text(" "),
+            link("50%", "https://a.org/?q=50%", LinkTarget::Url),
+            Inline::text(" "),
+            link("me", "me@a.org", LinkTarget::Email),
+            Inline::text(" "),
+            link("intro", "sec:intro", LinkTarget::Label),
+        ])]);
+        assert_eq!(
+            render_document(&doc, LatexRenderOptions::default()),
+            "\\url{https://a.org/x_y\\#z} \\href{https://a.org/?q=50\\%}{50\\%} \\href{mailto:me@a.org}{me} \\hyperref[sec:intro]{intro}"
+        );
+    }
+
+    #[test]
+    fn smart_quotes_follow_the_quote_style() {
+        let quote = |double| Inline::SmartQuote { double };
+        // "a 'b' *c*" don't "*d*"
+        let doc = Document::new(vec![Block::Paragraph(vec![
+            quote(true),
+            Inline::text("a "),
+            quote(false),
+            Inline::text("b"),
+            quote(false),
+            Inline::text(" "),
+            Inline::Emph(vec![Inline::text("c")]),
+            quote(true),
+            Inline::text(" don"),
+            quote(false),
+            Inline::text("t "),
+            quote(true),
+            Inline::Strong(vec![Inline::text("d"), quote(true)]),
+        ])]);
+        let render = |quote_style| {
+            render_document(
+                &doc,
+                LatexRenderOptions {
+                    quote_style,
+                    ..LatexRenderOptions::default()
+                },
+            )
+        };
+        assert_eq!(
+            render(QuoteStyle::Straight),
+            "\"a 'b' \\textit{c}\" don't \"\\textbf{d\"}"
+        );
+        assert_eq!(
+            render(QuoteStyle::Tex),
+            "``a `b' \\textit{c}'' don't ``\\textbf{d''}"
+        );
+        // A quote closed in a nested run ends with the run it opened in.
+        assert_eq!(
+            render(QuoteStyle::Csquotes),
+            "\\enquote{a \\enquote{b} \\textit{c}} don't \\enquote{\\textbf{d}}"
+        );
+        let out = render_document(
+            &doc,
+            LatexRenderOptions {
+                full_document: true,
+                quote_style: QuoteStyle::Csquotes,
+                ..LatexRenderOptions::default()
+            },
+        );
+        assert!(out.contains("\\usepackage{csquotes}\n"), "{out}");
+    }
+
+    #[test]
+    fn outline_targets_render_their_lists() {
+        let outline = |target| Block::Outline {
+            title: None,
+            target,
+        };
+        let doc = Document::new(vec![
+            outline(OutlineTarget::Headings),
+            Block::Outline {
+                title: Some(vec![Inline::Text("Figures".to_string())]),
+                target: OutlineTarget::Figures,
+            },
+            outline(OutlineTarget::Tables),
+            outline(OutlineTarget::Listings),
+        ]);
+        let out = render_document(
+            &doc,
+            LatexRenderOptions {
+                full_document: true,
+                ..LatexRenderOptions::default()
+            },
+        );
+        assert!(out.contains("\\usepackage{listings}\n"), "{out}");
+        assert!(body(&out).contains(
+            "\\tableofcontents\n\n\\renewcommand{\\listfigurename}{Figures}\n\\listoffigures\n\n\\listoftables\n\n\\lstlistoflistings"
+        ), "{out}");
+
+        let code = Figure {
+            caption: Some(vec![Inline::text("Setup")]),
+            label: Some("lst:setup".to_string()),
+            ..figure(FigureContent::Raw(vec![Block::CodeBlock {
+                lang: Some("py".to_string()),
+                content: "x = 1".to_string(),
+            }]))
+        };
+        let doc = Document::new(vec![outline(OutlineTarget::Listings), Block::Figure(code)]);
+        let out = render_document(
+            &doc,
+            LatexRenderOptions {
+                full_document: true,
+                ..LatexRenderOptions::default()
+            },
+        );
+        assert!(out.contains("\\usepackage{listings}\n\\lstset{"), "{out}");
+        assert!(body(&out).contains(concat!(
+            "\\lstlistoflistings\n\n",
+            "\\begin{lstlisting}[language={Python}, caption={Setup}, captionpos=b, label={lst:setup}]\n",
+            "x = 1\n",
+            "\\end{lstlisting}",
+        )), "{out}");
+        let minted = render_document(
+            &doc,
+            LatexRenderOptions {
+                full_document: true,
+                code_backend: CodeBackend::Minted,
+                ..LatexRenderOptions::default()
+            },
+        );
+        assert!(minted.contains("\\usepackage{minted}\n"), "{minted}");
+        assert!(!minted.contains("{listings}"), "{minted}");
+        assert!(body(&minted).contains("\\listoflistings\n\n\\begin{listing}[h]\n"), "{minted}");
+    }
+
+    #[test]
+    fn code_blocks_follow_the_code_backend() {
+        let code = |lang: Option<&str>| Block::CodeBlock {
+            lang: lang.map(str::to_string),
+            content: "x = 1".to_string(),
+        };
+        let doc = Document::new(vec![code(Some("py")), code(Some("rust")), code(None)]);
+        let listings = render_document(
+            &doc,
+            LatexRenderOptions {
+                full_document: true,
+                code_backend: CodeBackend::Listings,
+                ..LatexRenderOptions::default()
+            },
+        );
+        assert!(listings.contains("\\usepackage{listings}\n\\lstset{"));
+        assert!(body(&listings).contains(
+            "\\begin{lstlisting}[language={Python}]\nx = 1\n\\end{lstlisting}\n\n\\begin{lstlisting}\nx = 1\n\\end{lstlisting}"
+        ));
+        let minted = render_document(
+            &doc,
+            LatexRenderOptions {
+                full_document: true,
+                code_backend: CodeBackend::Minted,
+                ..LatexRenderOptions::default()
+            },
+        );
+        assert!(minted.contains("\\usepackage{minted}\n"));
+        assert!(body(&minted).contains(
+            "\\begin{minted}{rust}\nx = 1\n\\end{minted}\n\n\\begin{minted}{text}\nx = 1\n\\end{minted}"
+        ));
+        let verbatim = render_document(
+            &doc,
+            LatexRenderOptions {
+                full_document: true,
+                ..LatexRenderOptions::default()
+            },
+        );
+        assert!(!verbatim.contains("\\usepackage{listings}"));
+        assert!(body(&verbatim).contains("\\begin{verbatim}\nx = 1\n\\end{verbatim}"));
+    }
+
+    #[test]
+    fn environment_bodies_keep_block_separation() {
+        let doc = Document::new(vec![Block::Environment(EnvironmentBlock {
+            name: "lemma".to_string(),
+            title: None,
+            label: None,
+            args: Vec::new(),
+            blocks: vec![
+                Block::Paragraph(vec![Inline::text("Then")]),
+                Block::MathBlock(MathBlock {
+                    content: "x = 1".to_string(),
+                    label: None,
+                    latex: false,
+                }),
+                Block::Paragraph(Vec::new()),
+                Block::Paragraph(vec![Inline::text("holds.")]),
+            ],
+        })]);
+        let out = render_document(&doc, LatexRenderOptions::default());
+        assert_eq!(
+            out,
+            "\\begin{lemma}\nThen\n\n\\[\nx = 1\n\\]\n\nholds.\n\\end{lemma}"
+        );
+    }
+
+    #[test]
+    fn environment_arguments_follow_the_title() {
+        let doc = Document::new(vec![Block::Environment(EnvironmentBlock {
+            name: "problem".to_string(),
+            title: Some(vec![Inline::text("Warm-up")]),
+            label: None,
+            args: vec!["[easy]".to_string(), "{10}".to_string()],
+            blocks: vec![Block::Paragraph(vec![Inline::text("Solve it.")])],
+        })]);
+        let out = render_document(&doc, LatexRenderOptions::default());
+        assert_eq!(
+            out,
+            "\\begin{problem}[Warm-up][easy]{10}\nSolve it.\n\\end{problem}"
+        );
+    }
+
+    #[test]
+    fn inline_code_with_a_language_uses_the_code_backend() {
+        let code = |lang: Option<&str>, content: &str| Inline::Code {
+            lang: lang.map(str::to_string),
+            content: content.to_string(),
+        };
+        let doc = Document::new(vec![Block::Paragraph(vec![
+            code(Some("py"), "f(x)"),
+            Inline::text(" "),
+            code(Some("py"), "a|b"),
+            Inline::text(" "),
+            code(None, "x_1"),
+        ])]);
+        let render = |code_backend| {
+            render_document(
+                &doc,
+                LatexRenderOptions {
+                    code_backend,
+                    ..LatexRenderOptions::default()
+                },
+            )
+        };
+        assert_eq!(
+            render(CodeBackend::Listings),
+            "\\lstinline[language={Python}]|f(x)| \\lstinline[language={Python}]!a|b! \\texttt{x\\_1}"
+        );
+        assert_eq!(
+            render(CodeBackend::Minted),
+            "\\mintinline{py}|f(x)| \\mintinline{py}!a|b! \\texttt{x\\_1}"
+        );
+        assert_eq!(
+            render(CodeBackend::Verbatim),
+            "\\texttt{f(x)} \\texttt{a|b} \\texttt{x\\_1}"
+        );
+    }
+
+    #[test]
+    fn inline_code_in_headings_and_captions_stays_texttt() {
+        let code = || Inline::Code {
+            lang: Some("py".to_string()),
+            content: "f(x)".to_string(),
+        };
+        let doc = Document::new(vec![
+            Block::Heading {
+                level: 1,
+                content: vec![Inline::text("Calling "), code()],
+                numbered: true,
+                label: None,
+                outlined: true,
+                bookmarked: None,
+            },
+            Block::Figure(Figure {
+                caption: Some(vec![code()]),
+                ..figure(FigureContent::Raw(vec![Block::Paragraph(vec![code()])]))
+            }),
+        ]);
+        let out = render_document(
+            &doc,
+            LatexRenderOptions {
+                code_backend: CodeBackend::Listings,
+                ..LatexRenderOptions::default()
+            },
+        );
+        assert!(out.contains("\\section{\\texorpdfstring{Calling \\texttt{f(x)}}"));
+        assert!(out.contains("\\caption{\\texttt{f(x)}}"));
+        assert!(out.contains("\\lstinline[language={Python}]|f(x)|"));
+    }
+
+    #[test]
+    fn containers_separate_their_blocks() {
+        let children = || {
+            vec![
+                Block::Paragraph(vec![Inline::text("Before")]),
+                Block::MathBlock(MathBlock {
+                    content: "x".to_string(),
+                    label: None,
+                    latex: false,
+                }),
+                Block::List {
+                    kind: ListKind::Unordered,
+                    items: vec![vec![Block::Paragraph(vec![Inline::text("a")])]],
+                },
+                Block::CodeBlock {
+                    lang: None,
+                    content: "y".to_string(),
+                },
+            ]
+        };
+        let expected = "Before\n\n\\[\nx\n\\]\n\n\\begin{itemize}\n  \\item a\n\\end{itemize}\n\n\\begin{verbatim}\ny\n\\end{verbatim}";
+        let render = |block: Block| {
+            render_document(&Document::new(vec![block]), LatexRenderOptions::default())
+        };
+        assert_eq!(
+            render(Block::Quote(children())),
+            format!("\\begin{{quote}}\n{}\n\\end{{quote}}", expected)
+        );
+        assert_eq!(
+            render(Block::Align {
+                alignment: Some(Alignment::Center),
+                vertical: None,
+                blocks: children(),
+            }),
+            format!("\\begin{{center}}\n{}\n\\end{{center}}", expected)
+        );
+        assert_eq!(
+            render(Block::Box(tylax_ir::BoxBlock { blocks: children() })),
+            format!(
+                "\\fbox{{\\begin{{minipage}}{{\\dimexpr\\linewidth-2\\fboxsep-2\\fboxrule\\relax}}\n{}\n\\end{{minipage}}}}",
+                expected
+            )
+        );
+        assert_eq!(
+            render(Block::Block(tylax_ir::BlockBlock { blocks: children() })),
+            expected
+        );
+        let item = render(Block::List {
+            kind: ListKind::Unordered,
+            items: vec![children()],
+        });
+        assert!(item.contains("\\item Before\n\n\\[\nx\n\\]\n"));
+        assert!(item.contains("\\end{itemize}\n  \\begin{verbatim}"));
+    }
+
+    #[test]
+    fn table_footnotes_follow_the_float() {
+        let noted = |caption: Option<&str>| {
+            let cell = TableCell {
+                content: vec![
+                    Inline::text("a"),
+                    Inline::Footnote(vec![Inline::text("n")]),
+                ],
+                ..cell("")
+            };
+            Block::Table(Table {
+                caption: caption.map(|text| vec![Inline::text(text)]),
+                ..table(1, vec![cell])
+            })
+        };
+        let out = render_block(&noted(Some("T")), &LatexRenderOptions::default());
+        assert!(out.contains("a\\footnotemark"));
+        assert!(out.ends_with("\\end{table}\n\\footnotetext{n}"));
+        let options = LatexRenderOptions {
+            long_tables: true,
+            ..LatexRenderOptions::default()
+        };
+        assert!(render_block(&noted(None), &options).contains("a\\footnote{n}"));
+    }
+
+    #[test]
+    fn column_widths_set_the_column_spec() {
+        let doc = Document::new(vec![Block::Table(Table {
+            widths: vec![
+                ColumnWidth::Fraction("1".to_string()),
+                ColumnWidth::Fraction("3".to_string()),
+                ColumnWidth::Length("2cm".to_string()),
+            ],
+            ..table(3, vec![cell("a"), cell("b"), cell("c")])
+        })]);
+        let options = LatexRenderOptions {
+            full_document: true,
+            table_style: TableStyle::Booktabs,
+            ..LatexRenderOptions::default()
+        };
+        let output = render_document(&doc, options.clone());
+        // The fixed column is taken off the share of the `fr` ones.
+        assert!(output.contains(
+            "\\begin{tabular}{p{\\dimexpr 0.20\\linewidth-14.23pt\\relax}\
+             p{\\dimexpr 0.60\\linewidth-42.68pt\\relax}p{2cm}}"
+        ));
+        assert!(!output.contains("tabularx"));
+
+        let output = render_document(
+            &doc,
+            LatexRenderOptions {
+                tabularx: true,
+                ..options.clone()
+            },
+        );
+        assert!(output.contains("\\usepackage{tabularx}"));
+        assert!(output.contains(
+            "\\begin{tabularx}{\\linewidth}{>{\\hsize=0.50\\hsize}X>{\\hsize=1.50\\hsize}Xp{2cm}}"
+        ));
+        assert!(output.contains("\\end{tabularx}"));
+
+        // longtable has no `X` columns.
+        let output = render_document(
+            &doc,
+            LatexRenderOptions {
+                tabularx: true,
+                long_tables: true,
+                ..options
+            },
+        );
+        assert!(output.contains(
+            "\\begin{longtable}{p{\\dimexpr 0.20\\linewidth-14.23pt\\relax}\
+             p{\\dimexpr 0.60\\linewidth-42.68pt\\relax}p{2cm}}"
+        ));
+    }
+
+    #[test]
+    fn estimated_column_widths_fit_the_line() {
+        let prose = "a sentence long enough to wrap once it is set in a narrow column of a table";
+        let doc = Document::new(vec![Block::Table(table(
+            6,
+            (0..6).map(|_| cell(prose)).collect(),
+        ))]);
+        let output = render_document(&doc, LatexRenderOptions::default());
+        // Six columns at the narrowest share would take 0.90 of the line.
+        assert_eq!(output.matches("|p{0.13\\linewidth}").count(), 6);
+    }
+
+    #[test]
+    fn table_gutters_widen_columns_and_rows() {
+        let spaced = |inset: Option<&str>, gutter: Option<&str>, row_gutter: Option<&str>| {
+            Block::Table(Table {
+                inset: inset.map(str::to_string),
+                gutter: gutter.map(str::to_string),
+                row_gutter: row_gutter.map(str::to_string),
+                ..table(2, vec![cell("a"), cell("b")])
+            })
+        };
+        let options = LatexRenderOptions::default();
+        let out = render_block(&spaced(None, Some("6pt"), None), &options);
+        assert!(out.starts_with(
+            "\\begingroup\n\\setlength{\\tabcolsep}{8pt}\n\\renewcommand{\\arraystretch}{1.5}\n"
+        ));
+        let out = render_block(&spaced(Some("(x: 4pt, y: 8pt)"), None, Some("0pt")), &options);
+        assert!(out.contains(
+            "\\setlength{\\tabcolsep}{4pt}\n\\renewcommand{\\arraystretch}{1.5}\n"
+        ));
+        // Without a gutter the inset is kept as written.
+        let out = render_block(&spaced(Some("0.5em"), None, None), &options);
+        assert!(out.contains("\\setlength{\\tabcolsep}{0.5em}\n\\begin{tabular}"));
+    }
+
+    #[test]
+    fn non_ascii_text_is_transliterated_or_kept_for_unicode_engines() {
+        let doc = Document::new(vec![Block::Paragraph(vec![Inline::text(
+            "Café — “α” 5\u{a0}€ 東京",
+        )])]);
+        let render = |encoding_mode| {
+            render_document(
+                &doc,
+                LatexRenderOptions {
+                    full_document: true,
+                    encoding_mode,
+                    ..LatexRenderOptions::default()
+                },
+            )
+        };
+        let out = render(EncodingMode::Transliterate);
+        assert!(out.contains("\\usepackage[T1]{fontenc}\n"), "{out}");
+        assert!(!out.contains("fontspec"), "{out}");
+        assert!(body(&out).contains(
+            "Caf\\'{e} --- ``\\ensuremath{\\alpha}'' 5~\\texteuro{} 東京"
+        ), "{out}");
+        let out = render(EncodingMode::Unicode);
+        assert!(out.contains("\\usepackage{fontspec}\n"), "{out}");
+        assert!(!out.contains("fontenc"), "{out}");
+        assert!(body(&out).contains("Café — “α” 5\u{a0}€ 東京"), "{out}");
+        // Only the CJK needs the Unicode engine.
+        assert_eq!(unicode_only_char("Café — “α” 5\u{a0}€ 東京"), Some('東'));
+        assert_eq!(unicode_only_char("Café — “α” 5\u{a0}€"), None);
+        // ASCII text needs neither package.
+        let doc = Document::new(vec![Block::Paragraph(vec![Inline::text("plain")])]);
+        let out = render_document(
+            &doc,
+            LatexRenderOptions {
+                full_document: true,
+                ..LatexRenderOptions::default()
+            },
+        );
+        assert!(!out.contains("fontenc"), "{out}");
+    }
+
+    #[test]
+    fn text_decorations_render_as_text_commands() {
+        let doc = Document::new(vec![Block::Paragraph(vec![
+            Inline::Underline(vec![Inline::text("a")]),
+            Inline::text(" "),
+            Inline::Overline(vec![Inline::text("b")]),
+            Inline::text(" "),
+            Inline::Strike(vec![Inline::text("c")]),
+            Inline::text(" "),
+            Inline::SmallCaps(vec![Inline::text("d")]),
+            Inline::text(" "),
+            Inline::Uppercase(vec![Inline::text("e")]),
+            Inline::text(" "),
+            Inline::Lowercase(vec![Inline::text("F")]),
+        ])]);
+        let output = render_document(&doc, LatexRenderOptions::default());
+        assert_eq!(
+            output,
+            "\\uline{a} \\ensuremath{\\overline{\\mbox{b}}} \\sout{c} \\textsc{d} \\MakeUppercase{e} \\MakeLowercase{F}"
+        );
+        let full = render_document(
+            &doc,
+            LatexRenderOptions {
+                full_document: true,
+                ..LatexRenderOptions::default()
+            },
+        );
+        assert!(full.contains("\\usepackage[normalem]{ulem}"));
+    }
+
+    #[test]
+    fn explicit_rules_draw_only_their_lines() {
+        let rule = |axis, position, start, end| TableRule {
+            axis,
+            position,
+            start,
+            end,
+        };
+        let table = Table {
+            stroke: Some("none".to_string()),
+            rules: vec![
+                rule(RuleAxis::Horizontal, 0, 0, None),
+                rule(RuleAxis::Horizontal, 1, 1, Some(3)),
+                rule(RuleAxis::Horizontal, 2, 0, None),
+                rule(RuleAxis::Vertical, 1, 0, None),
+            ],
+            ..table(3, ["a", "b", "c", "d", "e", "f"].map(cell).to_vec())
+        };
+        let output = render_table(&table, None);
+        assert!(output.contains(
+            "\\begin{tabular}{c|cc}\n\\hline\na & b & c \\\\\n\\cline{2-3}\n\
+             d & e & f \\\\\n\\hline\n\\end{tabular}"
+        ));
+
+        let options = LatexRenderOptions {
+            table_style: TableStyle::Booktabs,
+            ..LatexRenderOptions::default()
+        };
+        let output = render_table(&table, Some(&options));
+        assert!(output.contains("\\toprule\na & b & c \\\\\n\\cmidrule{2-3}\n"));
+        assert!(output.contains("d & e & f \\\\\n\\bottomrule\n"));
+        assert!(!output.contains("\\midrule"));
+    }
+
+    #[test]
+    fn parent_scoped_place_splits_columns() {
+        let text = |s: &str| Block::Paragraph(vec![Inline::text(s)]);
+        let place = |source: &str| Block::Unsupported {
+            source: source.to_string(),
+            reason: "place".to_string(),
+        };
+        let columns = |middle| {
+            Block::Columns(tylax_ir::Columns {
+                columns: 2,
+                blocks: vec![text("Before."), middle, text("After.")],
+            })
+        };
+        let options = LatexRenderOptions::default();
+        assert_eq!(
+            render_block(&columns(place("#place(top, scope: \"parent\")[Wide]")), &options),
+            "\\begin{multicols}{2}\nBefore.\n\\end{multicols}\n% BEGIN unsupported: place\n% #place(top, scope: \"parent\")[Wide]\n% END unsupported\n\\begin{multicols}{2}\nAfter.\n\\end{multicols}"
+        );
+        assert!(render_block(&columns(place("#place(top)[Narrow]")), &options)
+            .starts_with("\\begin{multicols}{2}\nBefore.\n\n% BEGIN"));
+    }
+
+    #[test]
+    fn long_tables_close_header_rows() {
+        let header = |text| TableCell {
+            is_header: true,
+            ..cell(text)
+        };
+        let long_table = |repeat_header, caption: Option<&str>| {
+            Block::Table(Table {
+                caption: caption.map(|text| vec![Inline::text(text)]),
+                repeat_header,
+                ..table(2, vec![header("A"), header("B"), cell("1"), cell("2")])
+            })
+        };
+        let options = LatexRenderOptions {
+            long_tables: true,
+            table_style: TableStyle::Booktabs,
+            table_wrap_threshold: None,
+            ..LatexRenderOptions::default()
+        };
+        assert_eq!(
+            render_block(&long_table(true, None), &options),
+            "\\begin{longtable}{cc}\n\\toprule\n\\textbf{A} & \\textbf{B} \\\\\n\\midrule\n\\endhead\n1 & 2 \\\\\n\\bottomrule\n\\end{longtable}"
+        );
+        assert_eq!(
+            render_block(&long_table(false, None), &options),
+            "\\begin{longtable}{cc}\n\\toprule\n\\textbf{A} & \\textbf{B} \\\\\n\\midrule\n\\endfirsthead\n1 & 2 \\\\\n\\bottomrule\n\\end{longtable}"
+        );
+        assert_eq!(
+            render_block(&long_table(true, Some("Runs")), &options),
+            "\\begin{longtable}{cc}\n\\caption{Runs} \\\\\n\\toprule\n\\textbf{A} & \\textbf{B} \\\\\n\\midrule\n\\endfirsthead\n\\caption[]{(continued)} \\\\\n\\toprule\n\\textbf{A} & \\textbf{B} \\\\\n\\midrule\n\\endhead\n1 & 2 \\\\\n\\bottomrule\n\\end{longtable}"
+        );
+        assert_eq!(
+            render_block(&long_table(false, Some("Runs")), &options),
+            "\\begin{longtable}{cc}\n\\caption{Runs} \\\\\n\\toprule\n\\textbf{A} & \\textbf{B} \\\\\n\\midrule\n\\endfirsthead\n\\caption[]{(continued)} \\\\\n\\endhead\n1 & 2 \\\\\n\\bottomrule\n\\end{longtable}"
+        );
+    }
+
+    #[test]
+    fn footer_rows_close_the_table() {
+        let header = |text| TableCell {
+            is_header: true,
+            ..cell(text)
+        };
+        let footer = |text| TableCell {
+            is_footer: true,
+            ..cell(text)
+        };
+        let table = Block::Table(Table {
+            repeat_header: true,
+            ..table(
+                2,
+                vec![
+                    header("A"),
+                    header("B"),
+                    cell("1"),
+                    cell("2"),
+                    footer("S"),
+                    footer("3"),
+                ],
+            )
+        });
+        let options = LatexRenderOptions {
+            table_style: TableStyle::Booktabs,
+            table_wrap_threshold: None,
+            ..LatexRenderOptions::default()
+        };
+        assert_eq!(
+            render_block(&table, &options),
+            "\\begin{tabular}{cc}\n\\toprule\n\\textbf{A} & \\textbf{B} \\\\\n\\midrule\n1 & 2 \\\\\n\\midrule\nS & 3 \\\\\n\\bottomrule\n\\end{tabular}"
+        );
+        let options = LatexRenderOptions {
+            long_tables: true,
+            ..options
+        };
+        assert_eq!(
+            render_block(&table, &options),
+            "\\begin{longtable}{cc}\n\\toprule\n\\textbf{A} & \\textbf{B} \\\\\n\\midrule\n\\endhead\n\\midrule\nS & 3 \\\\\n\\bottomrule\n\\endfoot\n1 & 2 \\\\\n\\end{longtable}"
+        );
     }
-    out
-}
 
-fn escape_label(input: &str) -> String {
-    let mut out = String::new();
-    for ch in input.chars() {
-        if ch.is_ascii_alphanumeric() || matches!(ch, ':' | '-' | '_' | '.') {
-            out.push(ch);
-        } else if ch.is_whitespace() {
-            out.push('-');
-        }
-    }
-    if out.is_empty() {
-        "label".to_string()
-    } else {
-        out
+    #[test]
+    fn tables_past_the_row_limit_become_long() {
+        let data = |rows: usize| Table {
+            caption: Some(vec![Inline::text("Data")]),
+            label: Some("tab:data".to_string()),
+            repeat_header: true,
+            ..table(2, (0..rows * 2).map(|i| cell(&i.to_string())).collect())
+        };
+        let options = LatexRenderOptions {
+            long_table_rows: Some(3),
+            table_wrap_threshold: None,
+            ..LatexRenderOptions::default()
+        };
+        let short = render_block(&Block::Table(data(3)), &options);
+        assert!(short.starts_with("\\begin{table}"));
+        let long = render_block(&Block::Table(data(4)), &options);
+        assert!(long.starts_with(
+            "\\begin{longtable}{|c|c|}\n\\caption{Data}\\label{tab:data} \\\\\n\\hline\n\\endfirsthead\n\\caption[]{(continued)} \\\\\n\\endhead\n"
+        ));
+        let doc = Document::new(vec![Block::Table(data(4))]);
+        let full = render_document(
+            &doc,
+            LatexRenderOptions {
+                full_document: true,
+                ..options.clone()
+            },
+        );
+        assert!(full.contains("\\usepackage{longtable}\n"));
+        let two_column = LatexRenderOptions {
+            two_column: true,
+            ..options
+        };
+        assert!(!render_block(&Block::Table(data(4)), &two_column).contains("longtable"));
     }
-}
 
-fn escape_cite_keys(input: &str) -> String {
-    let mut keys = Vec::new();
-    for part in input.split(',') {
-        let trimmed = part.trim();
-        if trimmed.is_empty() {
-            continue;
+    fn span_table(columns: usize, cells: &[(&str, usize, usize)]) -> Table {
+        let cells = cells
+            .iter()
+            .map(|&(text, rowspan, colspan)| TableCell {
+                colspan,
+                rowspan,
+                ..cell(text)
+            })
+            .collect();
+        Table {
+            repeat_header: true,
+            ..table(columns, cells)
         }
-        keys.push(escape_label(trimmed));
-    }
-    if keys.is_empty() {
-        escape_label(input)
-    } else {
-        keys.join(",")
     }
-}
 
-fn color_to_latex(input: &str) -> (Option<&'static str>, String) {
-    let trimmed = input.trim().trim_matches('"');
-    if let Some(hex) = extract_hex_color(trimmed) {
-        return (Some("HTML"), hex);
+    #[test]
+    fn table_grid_lays_out_mixed_spans() {
+        let table = span_table(
+            3,
+            &[
+                ("a", 2, 2),
+                ("b", 1, 1),
+                ("c", 1, 1),
+                ("d", 1, 1),
+                ("e", 2, 2),
+                ("f", 1, 1),
+                ("g", 1, 2),
+                ("h", 1, 1),
+            ],
+        );
+        let grid = table.grid();
+        assert_eq!(grid.origins[4], (2, 1));
+        assert_eq!(grid.origins[6], (4, 0));
+        assert_eq!(
+            grid.slots,
+            vec![
+                vec![Some(0), Some(0), Some(1)],
+                vec![Some(0), Some(0), Some(2)],
+                vec![Some(3), Some(4), Some(4)],
+                vec![Some(5), Some(4), Some(4)],
+                vec![Some(6), Some(6), Some(7)],
+            ]
+        );
+
+        // A cell spanning columns that a rowspan above still covers moves on
+        // to where it fits, and a rowspan past the last cell adds its rows.
+        let table = span_table(
+            3,
+            &[
+                ("x", 1, 1),
+                ("a", 2, 1),
+                ("y", 1, 1),
+                ("g", 1, 2),
+                ("h", 1, 1),
+                ("k", 2, 2),
+            ],
+        );
+        let grid = table.grid();
+        assert_eq!(
+            grid.origins,
+            vec![(0, 0), (0, 1), (0, 2), (2, 0), (2, 2), (3, 0)]
+        );
+        assert_eq!(grid.slots.len(), 5);
+        assert_eq!(table_row_count(&table), 5);
     }
-    (None, escape_latex(trimmed))
-}
 
-fn normalize_inline_whitespace(input: &str) -> String {
-    let mut out = String::new();
-    let mut prev_space = false;
-    for ch in input.chars() {
-        if ch.is_whitespace() {
-            if !prev_space {
-                out.push(' ');
-                prev_space = true;
-            }
-        } else {
-            prev_space = false;
-            out.push(ch);
-        }
+    #[test]
+    fn mixed_spans_keep_rows_aligned() {
+        let table = span_table(
+            3,
+            &[
+                ("x", 1, 1),
+                ("a", 2, 1),
+                ("y", 1, 1),
+                ("g", 1, 2),
+                ("h", 1, 1),
+                ("k", 2, 2),
+                ("z", 1, 1),
+            ],
+        );
+        let output = render_table(&table, None);
+        assert!(output.contains(
+            "x & \\multirow{2}{*}{a} & y \\\\\n\\cline{1-1}\\cline{3-3}\n &  \\\\\n\\hline\n\
+             \\multicolumn{2}{|c|}{g} & h \\\\\n\\hline\n\
+             \\multicolumn{2}{|c|}{\\multirow{2}{*}{k}} & z \\\\\n\\cline{3-3}\n\
+             \\multicolumn{2}{|c|}{} \\\\\n\\hline\n"
+        ));
     }
-    out.trim().to_string()
-}
 
-fn plain_inline_text(inlines: &[Inline]) -> String {
-    let mut out = String::new();
-    for inline in inlines {
-        match inline {
-            Inline::Text(text) => out.push_str(text),
-            Inline::Code(code) => out.push_str(code),
-            Inline::Math(content) => out.push_str(content),
-            Inline::Size { content, .. } => out.push_str(&plain_inline_text(content)),
-            Inline::Strong(inner) => out.push_str(&plain_inline_text(inner)),
-            Inline::Emph(inner) => out.push_str(&plain_inline_text(inner)),
-            Inline::Link { text, .. } => out.push_str(&plain_inline_text(text)),
-            Inline::Footnote(content) => out.push_str(&plain_inline_text(content)),
-            Inline::Color { content, .. } => out.push_str(&plain_inline_text(content)),
-            Inline::Superscript(content) => out.push_str(&plain_inline_text(content)),
-            Inline::Subscript(content) => out.push_str(&plain_inline_text(content)),
-            Inline::LineBreak => out.push(' '),
-            Inline::RawLatex(_) | Inline::Ref(_) | Inline::Label(_) | Inline::Cite(_) => {}
-        }
+    #[test]
+    fn vertical_alignment_sets_column_types_and_multirows() {
+        let mut table = span_table(2, &[("a", 2, 1), ("b", 1, 1), ("c", 1, 1)]);
+        table.widths = vec![ColumnWidth::Length("2cm".to_string()), ColumnWidth::Auto];
+        table.valign = Some(VerticalAlignment::Horizon);
+        table.cells[1].valign = Some(VerticalAlignment::Top);
+        let output = render_table(&table, None);
+        assert!(output.contains("\\begin{tabular}{|m{2cm}|c|}"), "{output}");
+        assert!(output.contains("\\multirow{2}{*}{a}"), "{output}");
+
+        table.valign = Some(VerticalAlignment::Bottom);
+        table.cells[1].blocks = Some(vec![Block::Paragraph(vec![Inline::text("b")])]);
+        let output = render_table(&table, None);
+        assert!(output.contains("\\begin{tabular}{|b{2cm}|c|}"), "{output}");
+        assert!(output.contains("\\multirow[b]{2}{*}{a}"), "{output}");
+        assert!(output.contains("\\begin{minipage}[t]"), "{output}");
     }
-    out
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn cells_aligned_apart_from_their_column_get_a_multicolumn() {
+        let mut table = span_table(3, &[("a", 1, 1), ("b", 1, 1), ("c", 1, 1), ("d", 2, 1)]);
+        table.align = Some(vec![Alignment::Left; 3]);
+        for cell in &mut table.cells {
+            cell.align = Some(Alignment::Center);
+        }
+        table.cells[0].align = Some(Alignment::Left);
+        let output = render_table(&table, None);
+        assert!(
+            output.contains("a & \\multicolumn{1}{c|}{b} & \\multicolumn{1}{c|}{c} \\\\\n"),
+            "{output}"
+        );
+        // A cell spanning rows keeps its column's alignment.
+        assert!(output.contains("\\multirow{2}{*}{d}"), "{output}");
+    }
 
     #[test]
-    fn strip_references_prefix_inside_size() {
-        let entry = vec![Inline::Size {
-            size: "9pt".to_string(),
-            content: vec![Inline::Text("[1] A.".to_string())],
-        }];
-        let (label, body) = strip_reference_prefix_inlines(&entry);
-        assert_eq!(label.as_deref(), Some("ref1"));
-        let rendered = render_inlines(&body, &LatexRenderOptions::default());
-        assert_eq!(rendered, "{\\small A.}");
+    fn vertical_alignment_fills_around_aligned_blocks() {
+        let align = |alignment, vertical| {
+            render_block(
+                &Block::Align {
+                    alignment,
+                    vertical,
+                    blocks: vec![Block::Paragraph(vec![Inline::text("x")])],
+                },
+                &LatexRenderOptions::default(),
+            )
+        };
+        assert_eq!(
+            align(Some(Alignment::Center), Some(VerticalAlignment::Horizon)),
+            "\\vspace*{\\fill}\n\\begin{center}\nx\n\\end{center}\n\\vspace*{\\fill}"
+        );
+        assert_eq!(
+            align(None, Some(VerticalAlignment::Bottom)),
+            "\\vspace*{\\fill}\nx"
+        );
+        assert_eq!(align(None, Some(VerticalAlignment::Top)), "x");
     }
 }
 
+/// Whether `table` is set as a `longtable` rather than a `tabular`.
+fn is_long_table(table: &Table, options: &LatexRenderOptions) -> bool {
+    options.long_tables
+        || (!options.two_column
+            && options
+                .long_table_rows
+                .is_some_and(|limit| table_row_count(table) > limit))
+}
+
+/// Rows the cells of `table` fill, counting the cells rowspans cover.
+fn table_row_count(table: &Table) -> usize {
+    table.grid().slots.len()
+}
+
 fn render_table(table: &Table, options: Option<&LatexRenderOptions>) -> String {
     let default_opts;
     let opts = if let Some(opts) = options {
@@ -2285,8 +5104,15 @@ fn render_table(table: &Table, options: Option<&LatexRenderOptions>) -> String {
         &default_opts
     };
     let mut out = String::new();
+    let column_sep = table_column_sep(table, opts);
+    let row_stretch = table_row_stretch(table, opts);
     let mut has_style = false;
-    if table.inset.is_some() || table.stroke.is_some() || table.fill.is_some() {
+    if table.inset.is_some()
+        || table.stroke.is_some()
+        || table.fill.is_some()
+        || column_sep.is_some()
+        || row_stretch.is_some()
+    {
         has_style = true;
     }
     let stroke_value = table.stroke.as_deref();
@@ -2310,14 +5136,20 @@ fn render_table(table: &Table, options: Option<&LatexRenderOptions>) -> String {
             }
         }
     }
+    // Rules the source draws on single lines replace the automatic ones,
+    // unless every line is drawn anyway.
+    let ruled = !grid_lines
+        && table
+            .rules
+            .iter()
+            .any(|rule| rule.axis == RuleAxis::Horizontal);
     if has_style {
         out.push_str("\\begingroup\n");
-        if let Some(inset) = table.inset.as_deref() {
-            if let Some(token) = parse_table_inset(inset) {
-                if let Some(value) = convert_length_to_latex(&token) {
-                    out.push_str(&format!("\\setlength{{\\tabcolsep}}{{{}}}\n", value));
-                }
-            }
+        if let Some(value) = column_sep {
+            out.push_str(&format!("\\setlength{{\\tabcolsep}}{{{}}}\n", value));
+        }
+        if let Some(stretch) = row_stretch {
+            out.push_str(&format!("\\renewcommand{{\\arraystretch}}{{{}}}\n", stretch));
         }
         if let Some(stroke) = stroke_width.as_deref() {
             out.push_str(&format!("\\setlength{{\\arrayrulewidth}}{{{}}}\n", stroke));
@@ -2360,74 +5192,110 @@ fn render_table(table: &Table, options: Option<&LatexRenderOptions>) -> String {
             }
         }
     }
-    let col_spec = build_column_spec(table, grid_lines);
-    out.push_str(&format!("\\begin{{tabular}}{{{}}}\n", col_spec));
+    let long = is_long_table(table, opts);
+    let layouts = column_layouts(table, opts, long);
+    let col_spec = build_column_spec(table, grid_lines, &layouts);
+    let flexible = layouts
+        .iter()
+        .any(|layout| matches!(layout, ColumnLayout::Flexible(_)));
+    let env = if long {
+        "longtable"
+    } else if flexible {
+        "tabularx"
+    } else {
+        "tabular"
+    };
+    if long && table.caption.is_none() {
+        if let Some(label) = table.label.as_deref() {
+            out.push_str("\\refstepcounter{table}\n\\label{");
+            out.push_str(&output_label(label, opts));
+            out.push_str("}\n");
+        }
+    }
+    if flexible {
+        out.push_str(&format!("\\begin{{{}}}{{\\linewidth}}{{{}}}\n", env, col_spec));
+    } else {
+        out.push_str(&format!("\\begin{{{}}}{{{}}}\n", env, col_spec));
+    }
+    if long {
+        if let Some(caption) = &table.caption {
+            out.push_str("\\caption{");
+            out.push_str(&render_moving_argument(caption, opts));
+            out.push('}');
+            if let Some(label) = table.label.as_deref() {
+                out.push_str("\\label{");
+                out.push_str(&output_label(label, opts));
+                out.push('}');
+            }
+            out.push_str(" \\\\\n");
+        }
+    }
+    // Start of the header block, which a longtable repeats on later pages.
+    let head_start = out.len();
     if grid_lines {
         out.push_str("\\hline\n");
+    } else if ruled {
+        out.push_str(&explicit_row_rule(table, 0, false, use_booktabs));
     } else if use_booktabs {
         out.push_str("\\toprule\n");
     }
 
-    let columns = table.columns.max(1);
-    let mut col_idx = 0usize;
-    let mut skip: Vec<usize> = vec![0; columns];
-    let mut row_cells: Vec<String> = Vec::new();
-    let mut rows: Vec<(String, bool)> = Vec::new();
-    let mut row_has_header = false;
-
-    let flush_row = |row_cells: &mut Vec<String>, rows: &mut Vec<(String, bool)>, row_has_header: &mut bool| {
-        if !row_cells.is_empty() {
-            rows.push((row_cells.join(" & "), *row_has_header));
-            row_cells.clear();
-            *row_has_header = false;
-        }
-    };
-
-    for cell in &table.cells {
-        // Move to next row if we have filled columns.
-        if col_idx >= columns {
-            flush_row(&mut row_cells, &mut rows, &mut row_has_header);
-            col_idx = 0;
-        }
-
-        // Insert placeholders for covered columns from rowspans.
-        while col_idx < columns && skip[col_idx] > 0 {
-            skip[col_idx] -= 1;
-            row_cells.push(String::new());
-            col_idx += 1;
-            if col_idx >= columns {
-                flush_row(&mut row_cells, &mut rows, &mut row_has_header);
-                col_idx = 0;
+    let grid = table.grid();
+    // Each row keeps the columns a rowspan carries into the next row, so the
+    // rule below it can skip them.
+    let mut rows: Vec<(String, RowPart, Vec<bool>)> = Vec::new();
+    for (row_idx, slots) in grid.slots.iter().enumerate() {
+        let mut row_cells: Vec<String> = Vec::new();
+        let mut row_part = RowPart::Body;
+        // Empty slots at the end of a row need no cells.
+        let end = slots
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |last| last + 1);
+        let mut col_idx = 0usize;
+        while col_idx < end {
+            let Some(cell_idx) = slots[col_idx] else {
+                row_cells.push(String::new());
+                col_idx += 1;
+                continue;
+            };
+            let cell = &table.cells[cell_idx];
+            if cell.is_header {
+                row_part = RowPart::Header;
+            } else if cell.is_footer && row_part == RowPart::Body {
+                row_part = RowPart::Footer;
             }
-        }
-
-        let rendered = normalize_inline_whitespace(&render_inlines(&cell.content, opts));
-        let rendered = apply_cell_style(cell, &rendered);
-        let rendered = apply_cell_header(cell, &rendered);
-        let rendered = apply_cell_alignment(cell, &rendered, col_idx, table);
-        let rendered = apply_cell_spans(cell, &rendered, col_idx, table, grid_lines);
-        if cell.is_header {
-            row_has_header = true;
-        }
-        row_cells.push(rendered);
-
-        if cell.rowspan > 1 {
-            for i in 0..cell.colspan.max(1) {
-                if col_idx + i < columns {
-                    skip[col_idx + i] = skip[col_idx + i].max(cell.rowspan - 1);
-                }
+            let width = grid.width(row_idx, col_idx);
+            if grid.origins[cell_idx] == (row_idx, col_idx) {
+                let wraps = layouts[col_idx] != ColumnLayout::Natural;
+                let outer = IN_CELL.with(|flag| flag.replace(true));
+                let rendered = match &cell.blocks {
+                    Some(blocks) => render_cell_blocks(blocks, cell, table, wraps, opts),
+                    None => normalize_inline_whitespace(&render_inlines(&cell.content, opts)),
+                };
+                IN_CELL.with(|flag| flag.set(outer));
+                let rendered = apply_cell_style(cell, &rendered);
+                let rendered = apply_cell_header(cell, &rendered);
+                let rendered =
+                    apply_cell_alignment(cell, &rendered, col_idx, table, wraps, grid_lines);
+                let rendered = apply_cell_spans(cell, &rendered, col_idx, table, grid_lines);
+                row_cells.push(rendered);
+            } else if width > 1 {
+                // Below a cell that also spans columns, one `\multicolumn`
+                // placeholder keeps column rules from being drawn through it.
+                let spec = multicolumn_spec(cell, col_idx, table, grid_lines);
+                row_cells.push(format!("\\multicolumn{{{}}}{{{}}}{{}}", width, spec));
+            } else {
+                row_cells.push(String::new());
             }
+            col_idx += width;
         }
-
-        col_idx += cell.colspan.max(1);
-        if col_idx >= columns {
-            flush_row(&mut row_cells, &mut rows, &mut row_has_header);
-            col_idx = 0;
-        }
+        let spanned = (0..slots.len())
+            .map(|col| grid.continues(row_idx, col))
+            .collect();
+        rows.push((row_cells.join(" & "), row_part, spanned));
     }
 
-    flush_row(&mut row_cells, &mut rows, &mut row_has_header);
-
     if !rows.is_empty() {
         if has_style && out.contains("\\tylaxHeaderRowColor") {
             if let Some(first) = rows.first_mut() {
@@ -2436,54 +5304,208 @@ fn render_table(table: &Table, options: Option<&LatexRenderOptions>) -> String {
             }
         }
         let mut midrule_added = false;
-        for (row, is_header) in rows {
+        let last = rows.len() - 1;
+        let header_rows = rows
+            .iter()
+            .take_while(|(_, part, _)| *part == RowPart::Header)
+            .count();
+        let footer_start = rows.len()
+            - rows
+                .iter()
+                .rev()
+                .take_while(|(_, part, _)| *part == RowPart::Footer)
+                .count();
+        // A longtable takes its foot right after the head, before the body.
+        let long_foot = long && footer_start < rows.len();
+        let order: Vec<usize> = if long_foot {
+            (0..header_rows)
+                .chain(footer_start..rows.len())
+                .chain(header_rows..footer_start)
+                .collect()
+        } else {
+            (0..rows.len()).collect()
+        };
+        // In a longtable the foot is set apart from the body on every page.
+        let footer_midrule = use_booktabs && !ruled && (long || footer_start > header_rows);
+        if long && header_rows == 0 && table.caption.is_some() {
+            out.push_str("\\endfirsthead\n");
+            out.push_str(LONGTABLE_CONTINUED_CAPTION);
+            out.push_str("\\endhead\n");
+        }
+        for idx in order {
+            let (row, part, spanned) = &rows[idx];
+            let is_header = *part == RowPart::Header;
+            if idx == footer_start && footer_midrule {
+                out.push_str("\\midrule\n");
+            }
             // Wrap rows starting with [ in braces to prevent LaTeX from interpreting
             // it as an optional argument to the preceding \\
             if row.starts_with('[') {
                 out.push_str("{");
-                out.push_str(&row);
+                out.push_str(row);
                 out.push_str("}");
             } else {
-                out.push_str(&row);
+                out.push_str(row);
             }
             out.push_str(" \\\\\n");
-            if grid_lines {
+            if grid_lines && idx < last {
+                out.push_str(&grid_row_rule(spanned));
+            } else if grid_lines {
                 out.push_str("\\hline\n");
+            } else if ruled {
+                let rule = explicit_row_rule(table, idx + 1, idx == last, use_booktabs);
+                out.push_str(&rule);
             } else if use_booktabs && is_header && !midrule_added {
                 out.push_str("\\midrule\n");
                 midrule_added = true;
             }
+            if long && idx + 1 == header_rows {
+                push_longtable_head(&mut out, head_start, table);
+            }
+            if long_foot && idx == last {
+                if use_booktabs && !ruled {
+                    out.push_str("\\bottomrule\n");
+                }
+                out.push_str("\\endfoot\n");
+            }
         }
-        if use_booktabs {
+        if use_booktabs && !ruled && !long_foot {
             out.push_str("\\bottomrule\n");
         }
     }
 
     if has_style {
-        out.push_str("\\end{tabular}\n");
+        out.push_str(&format!("\\end{{{}}}\n", env));
         out.push_str("\\endgroup");
     } else {
-        out.push_str("\\end{tabular}");
+        out.push_str(&format!("\\end{{{}}}", env));
     }
 
+    if long {
+        return out;
+    }
     if let Some(caption) = &table.caption {
         out.push_str("\n");
         out.push_str("\\caption{");
-        out.push_str(&normalize_inline_whitespace(&render_inlines(caption, opts)));
+        out.push_str(&render_moving_argument(caption, opts));
         out.push_str("}");
     }
 
     out
 }
 
-fn render_table_block(
-    table: &Table,
-    options: &LatexRenderOptions,
-    label: Option<&str>,
-) -> String {
+/// Which section of a table a rendered row belongs to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RowPart {
+    Header,
+    Body,
+    Footer,
+}
+
+/// Caption heading the later pages of a captioned longtable. The empty
+/// optional argument keeps it out of the list of tables.
+const LONGTABLE_CONTINUED_CAPTION: &str = "\\caption[]{(continued)} \\\\\n";
+
+/// Close the header rows of a longtable. The head of a captioned table is
+/// written twice: with the caption for the first page, and with a continued
+/// caption for the pages after.
+fn push_longtable_head(out: &mut String, head_start: usize, table: &Table) {
+    if table.caption.is_some() {
+        let head = out[head_start..].to_string();
+        out.push_str("\\endfirsthead\n");
+        out.push_str(LONGTABLE_CONTINUED_CAPTION);
+        if table.repeat_header {
+            out.push_str(&head);
+        }
+        out.push_str("\\endhead\n");
+    } else if !table.repeat_header {
+        out.push_str("\\endfirsthead\n");
+    } else {
+        out.push_str("\\endhead\n");
+    }
+}
+
+/// Rule under a grid-mode row: a full `\hline`, or `\cline` segments that
+/// leave out the columns a multirow cell still covers below.
+fn grid_row_rule(spanned: &[bool]) -> String {
+    if !spanned.contains(&true) {
+        return "\\hline\n".to_string();
+    }
+    let mut out = String::new();
+    let mut col = 0usize;
+    while col < spanned.len() {
+        if spanned[col] {
+            col += 1;
+            continue;
+        }
+        let start = col;
+        while col < spanned.len() && !spanned[col] {
+            col += 1;
+        }
+        out.push_str(&format!("\\cline{{{}-{}}}", start + 1, col));
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+/// Rules the table draws on the line above row `position`: a `\\hline` for
+/// one spanning all columns, `\\cline` segments for shorter ones. In booktabs
+/// mode these become `\\toprule`, `\\midrule` or `\\bottomrule`, and
+/// `\\cmidrule`.
+fn explicit_row_rule(table: &Table, position: usize, last: bool, booktabs: bool) -> String {
+    let columns = table.columns.max(1);
+    let spans: Vec<(usize, usize)> = table
+        .rules
+        .iter()
+        .filter(|rule| rule.axis == RuleAxis::Horizontal && rule.position == position)
+        .map(|rule| (rule.start, rule.end.unwrap_or(columns).min(columns)))
+        .filter(|(start, end)| start < end)
+        .collect();
+    if spans.is_empty() {
+        return String::new();
+    }
+    if spans.contains(&(0, columns)) {
+        let rule = match (booktabs, position, last) {
+            (false, _, _) => "\\hline",
+            (true, 0, _) => "\\toprule",
+            (true, _, true) => "\\bottomrule",
+            (true, _, false) => "\\midrule",
+        };
+        return format!("{}\n", rule);
+    }
+    let command = if booktabs { "cmidrule" } else { "cline" };
+    let mut out: String = spans
+        .iter()
+        .map(|(start, end)| format!("\\{}{{{}-{}}}", command, start + 1, end))
+        .collect();
+    out.push('\n');
+    out
+}
+
+/// Which column boundaries carry a vertical rule, from the left edge to
+/// the right. A rule over only some rows still runs the full height, since
+/// a column spec cannot stop it part way.
+fn column_rules(table: &Table, grid_lines: bool) -> Vec<bool> {
+    let columns = table.columns.max(1);
+    if grid_lines {
+        return vec![true; columns + 1];
+    }
+    let mut drawn = vec![false; columns + 1];
+    for rule in &table.rules {
+        if rule.axis == RuleAxis::Vertical && rule.position <= columns {
+            drawn[rule.position] = true;
+        }
+    }
+    drawn
+}
+
+fn render_table_block(table: &Table, options: &LatexRenderOptions) -> String {
+    let label = table.label.as_deref();
     let has_caption = table.caption.is_some();
     let has_label = label.is_some();
-    if !has_caption && !has_label {
+    if is_long_table(table, options) || (!has_caption && !has_label) {
         return render_table(table, Some(options));
     }
     let caption_first = options.table_caption_position == TableCaptionPosition::Top;
@@ -2502,6 +5524,7 @@ fn render_table_block(
     if wide && placement == "h" {
         placement = "t".to_string();
     }
+    apply_float_hints(&mut env, &mut placement);
     out.push_str(&format!("\\begin{{{}}}[{}]\n\\centering\n", env, placement));
 
     let mut table_body = table.clone();
@@ -2513,11 +5536,11 @@ fn render_table_block(
         if let Some(label) = label {
             if has_caption {
                 out.push_str("\\label{");
-                out.push_str(&escape_label(label));
+                out.push_str(&output_label(label, options));
                 out.push_str("}\n");
             } else {
                 out.push_str("\\refstepcounter{table}\n\\label{");
-                out.push_str(&escape_label(label));
+                out.push_str(&output_label(label, options));
                 out.push_str("}\n");
             }
         }
@@ -2526,7 +5549,7 @@ fn render_table_block(
     if caption_first {
         if let Some(caption) = &table.caption {
             out.push_str("\\caption{");
-            out.push_str(&normalize_inline_whitespace(&render_inlines(caption, options)));
+            out.push_str(&render_moving_argument(caption, options));
             out.push_str("}\n");
         }
         render_label(&mut out);
@@ -2539,7 +5562,7 @@ fn render_table_block(
     if !caption_first {
         if let Some(caption) = &table.caption {
             out.push_str("\n\\caption{");
-            out.push_str(&normalize_inline_whitespace(&render_inlines(caption, options)));
+            out.push_str(&render_moving_argument(caption, options));
             out.push_str("}");
         }
         if has_label && !label_only {
@@ -2611,54 +5634,285 @@ fn parse_stroke_width(value: &str) -> Option<String> {
     Some(format!("{}{}", &trimmed[..num_len], unit))
 }
 
-fn build_column_spec(table: &Table, grid_lines: bool) -> String {
+/// How one column of a tabular is set.
+#[derive(Debug, Clone, PartialEq)]
+enum ColumnLayout {
+    /// `l`, `c` or `r` at the width of the column's content.
+    Natural,
+    /// A wrapping `p{}` column of the given width.
+    Wrap(String),
+    /// A tabularx `X` column, scaled by the given factor of the width the
+    /// `X` columns share. The factors add up to the number of `X` columns.
+    Flexible(f64),
+}
+
+/// Share of `\linewidth` that `fr` columns, and columns of long prose, split
+/// between them when they are set as `p{}` columns, leaving room for the
+/// padding and for the columns sized to their content. Columns of a fixed
+/// width are taken off it too.
+const FRACTION_COLUMNS_SHARE: f64 = 0.8;
+
+/// Lay out the columns of `table`. Widths given in the source win; without
+/// them, columns of long prose wrap once `table_wrap_threshold` is set.
+fn column_layouts(table: &Table, options: &LatexRenderOptions, long: bool) -> Vec<ColumnLayout> {
+    let columns = table.columns.max(1);
+    if table
+        .widths
+        .iter()
+        .all(|width| matches!(width, ColumnWidth::Auto))
+    {
+        let widths = match options.table_wrap_threshold {
+            Some(threshold) => estimate_column_widths(table, threshold),
+            None => vec![None; columns],
+        };
+        return widths
+            .into_iter()
+            .map(|width| match width {
+                Some(width) => ColumnLayout::Wrap(format!("{:.2}\\linewidth", width)),
+                None => ColumnLayout::Natural,
+            })
+            .collect();
+    }
+    let fractions: Vec<Option<f64>> = (0..columns)
+        .map(|i| match table.widths.get(i) {
+            Some(ColumnWidth::Fraction(fr)) => fr.parse::<f64>().ok().filter(|fr| *fr > 0.0),
+            _ => None,
+        })
+        .collect();
+    let total: f64 = fractions.iter().flatten().sum();
+    let count = fractions.iter().flatten().count() as f64;
+    // longtable cannot hold `X` columns, so long tables keep `p{}` columns.
+    let flexible = options.tabularx && !long;
+    // What the columns of a fixed width take of the line, as a share of it
+    // and in points; tabularx works this out itself.
+    let mut line_share = FRACTION_COLUMNS_SHARE;
+    let mut fixed_pt = 0.0;
+    let font_size = options.base_font_size_pt.unwrap_or(10.0);
+    for width in table.widths.iter().take(columns) {
+        let ColumnWidth::Length(length) = width else {
+            continue;
+        };
+        match Length::parse_typst(length) {
+            Some(length) if length.unit == Unit::Percent => line_share -= length.value / 100.0,
+            Some(length) => fixed_pt += length.to_pt(font_size).unwrap_or(0.0),
+            None => {}
+        }
+    }
+    let line_share = line_share.max(0.0);
+    (0..columns)
+        .map(|i| match (fractions[i], table.widths.get(i)) {
+            (Some(fr), _) if flexible => ColumnLayout::Flexible(fr * count / total),
+            (Some(fr), _) => {
+                let share = line_share * fr / total;
+                let fixed = fixed_pt * fr / total;
+                ColumnLayout::Wrap(if fixed < 0.005 {
+                    format!("{:.2}\\linewidth", share)
+                } else {
+                    format!("\\dimexpr {:.2}\\linewidth-{:.2}pt\\relax", share, fixed)
+                })
+            }
+            (None, Some(ColumnWidth::Length(length))) => Length::parse_typst(length)
+                .map_or(ColumnLayout::Natural, |length| {
+                    ColumnLayout::Wrap(length.to_latex())
+                }),
+            _ => ColumnLayout::Natural,
+        })
+        .collect()
+}
+
+fn build_column_spec(table: &Table, grid_lines: bool, layouts: &[ColumnLayout]) -> String {
     let mut spec = String::new();
     let columns = table.columns.max(1);
     let align = table.align.clone().unwrap_or_default();
-    if grid_lines {
+    let rules = column_rules(table, grid_lines);
+    if rules[0] {
         spec.push('|');
     }
     for i in 0..columns {
-        let a = align.get(i).copied().unwrap_or(Alignment::Center);
-        spec.push(match a {
-            Alignment::Left => 'l',
-            Alignment::Right => 'r',
-            Alignment::Center => 'c',
-        });
-        if grid_lines {
+        match layouts.get(i) {
+            Some(ColumnLayout::Wrap(width)) => {
+                let column = match table.valign {
+                    Some(VerticalAlignment::Horizon) => 'm',
+                    Some(VerticalAlignment::Bottom) => 'b',
+                    Some(VerticalAlignment::Top) | None => 'p',
+                    _ => 'p',
+                };
+                spec.push_str(&format!("{}{{{}}}", column, width))
+            }
+            Some(ColumnLayout::Flexible(scale)) if (scale - 1.0).abs() >= 0.005 => {
+                spec.push_str(&format!(">{{\\hsize={:.2}\\hsize}}X", scale))
+            }
+            Some(ColumnLayout::Flexible(_)) => spec.push('X'),
+            _ => {
+                let a = align.get(i).copied().unwrap_or(Alignment::Center);
+                spec.push(match a {
+                    Alignment::Left => 'l',
+                    Alignment::Right => 'r',
+                    Alignment::Center => 'c',
+                    _ => 'c',
+                });
+            }
+        }
+        if rules[i + 1] {
             spec.push('|');
         }
     }
     spec
 }
 
-fn apply_cell_spans(
+/// Estimate `p{}` widths, as fractions of `\linewidth`, for columns that hold
+/// long prose. A column wraps once any of its cells exceeds `threshold`
+/// characters; wrapping columns share the line in proportion to their
+/// average cell length, while the others keep their natural width. Each
+/// wrapping column gets at least a narrow share, and the shares are scaled
+/// down when those minimums add up to more than the line holds.
+fn estimate_column_widths(table: &Table, threshold: usize) -> Vec<Option<f64>> {
+    let columns = table.columns.max(1);
+    let mut max_len = vec![0usize; columns];
+    let mut total_len = vec![0usize; columns];
+    let mut counts = vec![0usize; columns];
+    let grid = table.grid();
+    for (cell, &(_, col_idx)) in table.cells.iter().zip(&grid.origins) {
+        if cell.colspan > 1 || cell.blocks.is_some() {
+            continue;
+        }
+        let len = plain_inline_text(&cell.content).trim().chars().count();
+        max_len[col_idx] = max_len[col_idx].max(len);
+        total_len[col_idx] += len;
+        counts[col_idx] += 1;
+    }
+    if max_len.iter().all(|len| *len <= threshold) {
+        return vec![None; columns];
+    }
+    let averages: Vec<f64> = (0..columns)
+        .map(|i| total_len[i] as f64 / counts[i].max(1) as f64)
+        .collect();
+    let total: f64 = averages.iter().sum::<f64>().max(1.0);
+    let mut widths: Vec<Option<f64>> = (0..columns)
+        .map(|i| {
+            (max_len[i] > threshold).then(|| {
+                (FRACTION_COLUMNS_SHARE * averages[i] / total).clamp(0.15, FRACTION_COLUMNS_SHARE)
+            })
+        })
+        .collect();
+    let wrapped: f64 = widths.iter().flatten().sum();
+    if wrapped > FRACTION_COLUMNS_SHARE {
+        for width in widths.iter_mut().flatten() {
+            *width *= FRACTION_COLUMNS_SHARE / wrapped;
+        }
+    }
+    widths
+}
+
+/// `\multirow` goes inside `\multicolumn`: the other way round LaTeX sees a
+/// `\multicolumn` that does not start its cell and stops with an error.
+fn apply_cell_spans(
+    cell: &TableCell,
+    content: &str,
+    col_idx: usize,
+    table: &Table,
+    grid_lines: bool,
+) -> String {
+    let mut rendered = content.to_string();
+    if cell.rowspan > 1 {
+        // multirow centres by default.
+        let position = match cell.valign.or(table.valign) {
+            Some(VerticalAlignment::Top) => "[t]",
+            Some(VerticalAlignment::Bottom) => "[b]",
+            Some(VerticalAlignment::Horizon) | None => "",
+            _ => "",
+        };
+        rendered = format!(
+            "\\multirow{}{{{}}}{{*}}{{{}}}",
+            position, cell.rowspan, rendered
+        );
+    }
+    if cell.colspan > 1 {
+        rendered = format!(
+            "\\multicolumn{{{}}}{{{}}}{{{}}}",
+            cell.colspan,
+            multicolumn_spec(cell, col_idx, table, grid_lines),
+            rendered
+        );
+    }
+    rendered
+}
+
+fn multicolumn_spec(cell: &TableCell, col_idx: usize, table: &Table, grid_lines: bool) -> String {
+    let spec = column_align_spec(cell, col_idx, table);
+    if grid_lines {
+        // The rule left of any other column belongs to the one before it.
+        let left = if col_idx == 0 { "|" } else { "" };
+        return format!("{}{}|", left, spec);
+    }
+    let rules = column_rules(table, false);
+    let left = if col_idx == 0 && rules[0] { "|" } else { "" };
+    let right = rules
+        .get(col_idx + cell.colspan.max(1))
+        .is_some_and(|drawn| *drawn);
+    format!("{}{}{}", left, spec, if right { "|" } else { "" })
+}
+
+/// Lists and multi-paragraph cells cannot sit in a plain `c`/`l`/`r` column,
+/// so they are wrapped in a minipage sized to an even share of the line and
+/// aligned as the cell is, top by default. Cells in a `p{}` column already
+/// wrap and are emitted as is.
+fn render_cell_blocks(
+    blocks: &[Block],
+    cell: &TableCell,
+    table: &Table,
+    wraps: bool,
+    options: &LatexRenderOptions,
+) -> String {
+    let parts: Vec<String> = blocks
+        .iter()
+        .map(|block| normalize_inline_whitespace(&render_block(block, options)))
+        .filter(|part| !part.is_empty())
+        .collect();
+    if wraps {
+        return parts.join("\\par ");
+    }
+    let width = 0.9 / table.columns.max(1) as f64;
+    let position = match cell.valign.or(table.valign) {
+        Some(VerticalAlignment::Horizon) => 'c',
+        Some(VerticalAlignment::Bottom) => 'b',
+        Some(VerticalAlignment::Top) | None => 't',
+        _ => 't',
+    };
+    format!(
+        "\\begin{{minipage}}[{}]{{{:.2}\\linewidth}}{}\\end{{minipage}}",
+        position,
+        width,
+        parts.join("\\par ")
+    )
+}
+
+/// A cell aligned apart from its column gets a one-column `\multicolumn`
+/// of its own. Cells in wrapping columns and cells spanning rows keep the
+/// column's alignment; those spanning columns are aligned by the
+/// `\multicolumn` they already get.
+fn apply_cell_alignment(
     cell: &TableCell,
     content: &str,
     col_idx: usize,
     table: &Table,
+    wraps: bool,
     grid_lines: bool,
 ) -> String {
-    let mut rendered = content.to_string();
-    if cell.colspan > 1 {
-        let mut spec = column_align_spec(cell, col_idx, table).to_string();
-        if grid_lines {
-            spec = format!("|{}|", spec);
-        }
-        rendered = format!(
-            "\\multicolumn{{{}}}{{{}}}{{{}}}",
-            cell.colspan, spec, rendered
-        );
-    }
-    if cell.rowspan > 1 {
-        rendered = format!("\\multirow{{{}}}{{*}}{{{}}}", cell.rowspan, rendered);
+    let column = table
+        .align
+        .as_ref()
+        .and_then(|a| a.get(col_idx).copied())
+        .unwrap_or(Alignment::Center);
+    let own = cell.align.filter(|align| *align != column);
+    if own.is_none() || wraps || cell.colspan > 1 || cell.rowspan > 1 {
+        return content.to_string();
     }
-    rendered
-}
-
-fn apply_cell_alignment(cell: &TableCell, content: &str, col_idx: usize, table: &Table) -> String {
-    let _ = (cell, col_idx, table);
-    content.to_string()
+    format!(
+        "\\multicolumn{{1}}{{{}}}{{{}}}",
+        multicolumn_spec(cell, col_idx, table, grid_lines),
+        content
+    )
 }
 
 fn apply_cell_style(cell: &TableCell, content: &str) -> String {
@@ -2688,40 +5942,53 @@ fn column_align_spec(cell: &TableCell, col_idx: usize, table: &Table) -> char {
         Alignment::Left => 'l',
         Alignment::Right => 'r',
         Alignment::Center => 'c',
+        _ => 'c',
     }
 }
 
 fn render_figure(figure: &Figure, options: &LatexRenderOptions) -> String {
+    if let FigureContent::Table(table) = &figure.content {
+        // A longtable cannot float; it carries the figure's caption itself.
+        if is_long_table(table, options) {
+            let mut table = table.clone();
+            table.caption = figure.caption.clone();
+            table.label = figure.label.clone();
+            return render_table(&table, Some(options));
+        }
+    }
+    if let Some(listing) = render_listing_figure(figure, options) {
+        return listing;
+    }
+    if let Some(side) = figure.wrap {
+        return render_wrap_figure(figure, side, options);
+    }
     if options.inline_wide_tables && options.two_column {
         if let FigureContent::Table(table) = &figure.content {
             if is_wide_table(table) {
                 let mut out = String::new();
                 out.push_str("\\begin{center}\n");
-                let caption_first =
-                    options.table_caption_position == TableCaptionPosition::Top;
+                out.push_str(&caption_setup(figure, "table", options));
+                let caption_first = caption_on_top(figure, options);
                 if caption_first {
-                    if let Some(caption) = &figure.caption {
-                        out.push_str("\\captionof{table}{");
-                        out.push_str(&normalize_inline_whitespace(&render_inlines(caption, options)));
-                        out.push_str("}");
+                    if let Some(caption) = figure_caption(figure, Some("table"), options) {
+                        out.push_str(&caption);
                     }
                     if let Some(label) = &figure.label {
                         out.push_str("\n\\label{");
-                        out.push_str(&escape_label(label));
+                        out.push_str(&output_label(label, options));
                         out.push_str("}");
                     }
                     out.push('\n');
                 }
                 out.push_str(&render_table(table, Some(options)));
                 if !caption_first {
-                    if let Some(caption) = &figure.caption {
-                        out.push_str("\n\\captionof{table}{");
-                        out.push_str(&normalize_inline_whitespace(&render_inlines(caption, options)));
-                        out.push_str("}");
+                    if let Some(caption) = figure_caption(figure, Some("table"), options) {
+                        out.push('\n');
+                        out.push_str(&caption);
                     }
                     if let Some(label) = &figure.label {
                         out.push_str("\n\\label{");
-                        out.push_str(&escape_label(label));
+                        out.push_str(&output_label(label, options));
                         out.push_str("}");
                     }
                 }
@@ -2744,8 +6011,12 @@ fn render_figure(figure: &Figure, options: &LatexRenderOptions) -> String {
             placement = "H".to_string();
         }
     }
+    // minted's `listing` float is what `\listoflistings` lists.
     let base_env = match figure.content {
         FigureContent::Table(_) => "table",
+        _ if options.code_backend == CodeBackend::Minted && figure_code(figure).is_some() => {
+            "listing"
+        }
         _ => "figure",
     };
     let mut env = base_env.to_string();
@@ -2753,7 +6024,8 @@ fn render_figure(figure: &Figure, options: &LatexRenderOptions) -> String {
         let wide = match &figure.content {
             FigureContent::Image(image) => is_wide_image(image),
             FigureContent::Table(table) => is_wide_table(table),
-            FigureContent::Raw(_) => false,
+            FigureContent::Raw(_) | FigureContent::SubFigures { .. } => false,
+            _ => false,
         };
         if wide {
             env.push('*');
@@ -2762,21 +6034,18 @@ fn render_figure(figure: &Figure, options: &LatexRenderOptions) -> String {
             }
         }
     }
+    apply_float_hints(&mut env, &mut placement);
     out.push_str(&format!("\\begin{{{}}}[{}]\n\\centering\n", env, placement));
+    out.push_str(&caption_setup(figure, base_env, options));
 
-    let caption_first = matches!(
-        figure.content,
-        FigureContent::Table(_)
-    ) && options.table_caption_position == TableCaptionPosition::Top;
+    let caption_first = caption_on_top(figure, options);
     if caption_first {
-        if let Some(caption) = &figure.caption {
-            out.push_str("\\caption{");
-            out.push_str(&normalize_inline_whitespace(&render_inlines(caption, options)));
-            out.push_str("}");
+        if let Some(caption) = figure_caption(figure, None, options) {
+            out.push_str(&caption);
         }
         if let Some(label) = &figure.label {
             out.push_str("\n\\label{");
-            out.push_str(&escape_label(label));
+            out.push_str(&output_label(label, options));
             out.push_str("}");
         }
         out.push('\n');
@@ -2792,17 +6061,20 @@ fn render_figure(figure: &Figure, options: &LatexRenderOptions) -> String {
         FigureContent::Raw(blocks) => {
             out.push_str(&render_blocks_inline(blocks, options));
         }
+        FigureContent::SubFigures { figures, columns } => {
+            out.push_str(&render_subfigures(figures, *columns, options));
+        }
+        _ => {}
     }
 
     if !caption_first {
-        if let Some(caption) = &figure.caption {
-            out.push_str("\n\\caption{");
-            out.push_str(&normalize_inline_whitespace(&render_inlines(caption, options)));
-            out.push_str("}");
+        if let Some(caption) = figure_caption(figure, None, options) {
+            out.push('\n');
+            out.push_str(&caption);
         }
         if let Some(label) = &figure.label {
             out.push_str("\n\\label{");
-            out.push_str(&escape_label(label));
+            out.push_str(&output_label(label, options));
             out.push_str("}");
         }
     }
@@ -2811,6 +6083,280 @@ fn render_figure(figure: &Figure, options: &LatexRenderOptions) -> String {
     out
 }
 
+/// A figure the text flows around, as wrapfig's `wrapfigure`, or
+/// `wraptable` for a table. It is as wide as its image, which then fills
+/// it; content without a width is set at its natural width.
+fn render_wrap_figure(figure: &Figure, side: Side, options: &LatexRenderOptions) -> String {
+    let env = match figure.content {
+        FigureContent::Table(_) => "wraptable",
+        _ => "wrapfigure",
+    };
+    let side = match side {
+        Side::Left => 'l',
+        Side::Right => 'r',
+        _ => 'r',
+    };
+    let width = match &figure.content {
+        FigureContent::Image(image) => image.width.as_deref().and_then(Length::parse_typst),
+        _ => None,
+    }
+    .filter(|width| width.unit != Unit::Fr);
+    let mut out = format!(
+        "\\begin{{{}}}{{{}}}{{{}}}\n\\centering\n",
+        env,
+        side,
+        width.map_or_else(
+            || "0pt".to_string(),
+            |width| width.to_latex_relative_to("\\textwidth")
+        )
+    );
+    let counter = if env == "wraptable" {
+        "table"
+    } else {
+        "figure"
+    };
+    out.push_str(&caption_setup(figure, counter, options));
+    let mut caption = figure_caption(figure, None, options).unwrap_or_default();
+    if let Some(label) = &figure.label {
+        if !caption.is_empty() {
+            caption.push('\n');
+        }
+        caption.push_str("\\label{");
+        caption.push_str(&output_label(label, options));
+        caption.push('}');
+    }
+    let caption_first = caption_on_top(figure, options);
+    if caption_first && !caption.is_empty() {
+        out.push_str(&caption);
+        out.push('\n');
+    }
+    match &figure.content {
+        FigureContent::Image(image) if width.is_some() => {
+            let image = Image {
+                width: Some("100%".to_string()),
+                ..image.clone()
+            };
+            out.push_str(&render_image(&image));
+        }
+        FigureContent::Image(image) => out.push_str(&render_image(image)),
+        FigureContent::Table(table) => out.push_str(&render_table(table, Some(options))),
+        FigureContent::Raw(blocks) => out.push_str(&render_blocks_inline(blocks, options)),
+        FigureContent::SubFigures { figures, columns } => {
+            out.push_str(&render_subfigures(figures, *columns, options))
+        }
+        _ => {}
+    }
+    if !caption_first && !caption.is_empty() {
+        out.push('\n');
+        out.push_str(&caption);
+    }
+    out.push_str(&format!("\n\\end{{{}}}", env));
+    out
+}
+
+/// The figures of a figure as `subfigure` environments in a row, each an
+/// equal share of the line. Their captions are set with `\subcaption`, which
+/// numbers them (a), (b), .. within the figure.
+/// Subfigures `columns` to a row, each row ending in a paragraph break.
+fn render_subfigures(figures: &[Figure], columns: usize, options: &LatexRenderOptions) -> String {
+    let columns = columns.max(1);
+    let width = 0.95 / columns as f64;
+    let mut parts = Vec::new();
+    for figure in figures {
+        let mut out = format!(
+            "\\begin{{subfigure}}[t]{{{:.2}\\linewidth}}\n\\centering\n",
+            width
+        );
+        match &figure.content {
+            // An image takes the width of its subfigure unless it sets a size.
+            FigureContent::Image(image) if image.width.is_none() && image.height.is_none() => {
+                let image = Image {
+                    width: Some("100%".to_string()),
+                    ..image.clone()
+                };
+                out.push_str(&render_image(&image));
+            }
+            FigureContent::Image(image) => out.push_str(&render_image(image)),
+            FigureContent::Table(table) => out.push_str(&render_table(table, Some(options))),
+            FigureContent::Raw(blocks) => out.push_str(&render_blocks_inline(blocks, options)),
+            FigureContent::SubFigures { figures, columns } => {
+                out.push_str(&render_subfigures(figures, *columns, options))
+            }
+            _ => {}
+        }
+        if let Some(caption) = &figure.caption {
+            let star = if figure.unnumbered { "*" } else { "" };
+            let text = render_moving_argument(caption, options);
+            out.push_str(&format!("\n\\subcaption{}{{{}}}", star, text));
+        }
+        if let Some(label) = &figure.label {
+            out.push_str("\n\\label{");
+            out.push_str(&output_label(label, options));
+            out.push('}');
+        }
+        out.push_str("\n\\end{subfigure}");
+        parts.push(out);
+    }
+    parts
+        .chunks(columns)
+        .map(|row| row.join("\\hfill\n"))
+        .collect::<Vec<_>>()
+        .join("\\par\n")
+}
+
+/// The code of a figure that holds a single code block.
+fn figure_code(figure: &Figure) -> Option<(Option<&str>, &str)> {
+    match &figure.content {
+        FigureContent::Raw(blocks) => match blocks.as_slice() {
+            [Block::CodeBlock { lang, content }] => Some((lang.as_deref(), content)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A numbered, captioned figure of code under listings, set as an
+/// `lstlisting` with its caption so that `\lstlistoflistings` lists it.
+fn render_listing_figure(figure: &Figure, options: &LatexRenderOptions) -> Option<String> {
+    if options.code_backend != CodeBackend::Listings || figure.unnumbered {
+        return None;
+    }
+    let caption = figure.caption.as_ref()?;
+    let (lang, content) = figure_code(figure)?;
+    let mut keys = Vec::new();
+    if let Some(language) = lang.and_then(listings_language) {
+        keys.push(format!("language={{{}}}", language));
+    }
+    let text = render_moving_argument(caption, options);
+    match &figure.short_caption {
+        Some(short) => {
+            let short = render_moving_argument(short, options);
+            keys.push(format!("caption={{[{}]{}}}", short, text));
+        }
+        None => keys.push(format!("caption={{{}}}", text)),
+    }
+    if !caption_on_top(figure, options) {
+        keys.push("captionpos=b".to_string());
+    }
+    if let Some(label) = &figure.label {
+        keys.push(format!("label={{{}}}", output_label(label, options)));
+    }
+    Some(format!(
+        "\\begin{{lstlisting}}[{}]\n{}\n\\end{{lstlisting}}",
+        keys.join(", "),
+        content
+    ))
+}
+
+/// `\caption[short]{..}`, or `\captionof{float}[short]{..}` outside a
+/// float. A figure left out of the numbering gets the starred form, which
+/// sets no number.
+fn figure_caption(
+    figure: &Figure,
+    float: Option<&str>,
+    options: &LatexRenderOptions,
+) -> Option<String> {
+    let caption = figure.caption.as_ref()?;
+    let star = if figure.unnumbered { "*" } else { "" };
+    let mut command = match float {
+        Some(float) => format!("\\captionof{}{{{}}}", star, float),
+        None => format!("\\caption{}", star),
+    };
+    // The starred form is left out of the list of figures, and takes no
+    // short caption for it.
+    if let Some(short) = figure.short_caption.as_ref().filter(|_| !figure.unnumbered) {
+        let short = render_moving_argument(short, options);
+        command.push_str(&format!("[{}]", short));
+    }
+    let text = render_moving_argument(caption, options);
+    Some(format!("{}{{{}}}", command, text))
+}
+
+/// What a figure sets of its own caption, written inside the float so that
+/// it ends with it: `\captionsetup` for the position and the separator, and
+/// `\the<counter>` for the numbering. Separators the caption package does
+/// not know are declared first.
+fn caption_setup(figure: &Figure, counter: &str, options: &LatexRenderOptions) -> String {
+    let mut out = String::new();
+    if figure.caption.is_none() {
+        return out;
+    }
+    let mut keys = Vec::new();
+    match figure.caption_position {
+        Some(CaptionPosition::Top) => keys.push("position=top"),
+        Some(CaptionPosition::Bottom) => keys.push("position=bottom"),
+        None => {}
+        _ => {}
+    }
+    if let Some(separator) = figure.caption_separator.as_deref() {
+        if !figure.unnumbered {
+            let known = match plain_inline_text(separator).as_str() {
+                "" => Some("labelsep=none"),
+                ":" | ": " => Some("labelsep=colon"),
+                "." | ". " => Some("labelsep=period"),
+                " " => Some("labelsep=space"),
+                " \u{2013} " | " -- " => Some("labelsep=endash"),
+                _ => None,
+            };
+            keys.push(known.unwrap_or_else(|| {
+                out.push_str(&format!(
+                    "\\DeclareCaptionLabelSeparator{{tylax}}{{{}}}\n",
+                    render_inlines(separator, options)
+                ));
+                "labelsep=tylax"
+            }));
+        }
+    }
+    if !keys.is_empty() {
+        out.push_str(&format!("\\captionsetup{{{}}}\n", keys.join(",")));
+    }
+    let format = figure
+        .numbering
+        .as_deref()
+        .filter(|_| !figure.unnumbered)
+        .and_then(|pattern| counter_format(pattern, counter));
+    if let Some(format) = format {
+        out.push_str(&format!(
+            "\\renewcommand{{\\the{}}}{{{}}}\n",
+            counter, format
+        ));
+    }
+    out
+}
+
+/// `\the<counter>` for a Typst numbering pattern such as `I` or `(a)`: the
+/// counting symbol becomes the counter, the text around it is kept.
+fn counter_format(pattern: &str, counter: &str) -> Option<String> {
+    let start = pattern.find(['1', 'a', 'A', 'i', 'I', '*'])?;
+    let style = match &pattern[start..start + 1] {
+        "1" => "arabic",
+        "a" => "alph",
+        "A" => "Alph",
+        "i" => "roman",
+        "I" => "Roman",
+        _ => "fnsymbol",
+    };
+    Some(format!(
+        "{}\\{}{{{}}}{}",
+        escape_latex(&pattern[..start]),
+        style,
+        counter,
+        escape_latex(&pattern[start + 1..])
+    ))
+}
+
+/// Whether a figure's caption goes above its content: where the source
+/// says, and otherwise for tables when the options put them there.
+fn caption_on_top(figure: &Figure, options: &LatexRenderOptions) -> bool {
+    match figure.caption_position {
+        Some(position) => position == CaptionPosition::Top,
+        None => {
+            matches!(figure.content, FigureContent::Table(_))
+                && options.table_caption_position == TableCaptionPosition::Top
+        }
+    }
+}
+
 fn is_wide_image(image: &Image) -> bool {
     let Some(width) = image.width.as_deref() else {
         return false;
@@ -2823,10 +6369,8 @@ fn is_wide_image(image: &Image) -> bool {
 }
 
 fn parse_width_ratio(raw: &str) -> Option<f64> {
-    let trimmed = raw.trim();
-    let percent = trimmed.strip_suffix('%')?;
-    let val = percent.trim().parse::<f64>().ok()?;
-    Some((val / 100.0).clamp(0.0, 10.0))
+    let length = Length::parse_typst(raw).filter(|length| length.unit == Unit::Percent)?;
+    Some((length.value / 100.0).clamp(0.0, 10.0))
 }
 
 fn is_wide_table(table: &Table) -> bool {
@@ -2834,24 +6378,40 @@ fn is_wide_table(table: &Table) -> bool {
     table.columns >= 6
 }
 
+/// The path an image at `path` is included from, when it is another one.
+/// pdflatex reads neither SVG nor GIF, so those are included as the PDF or
+/// PNG they are converted to, which must exist next to the output.
+pub fn included_image_path(path: &str) -> Option<String> {
+    let (stem, extension) = path.rsplit_once('.')?;
+    match extension.to_ascii_lowercase().as_str() {
+        "svg" => Some(format!("{}.pdf", stem)),
+        "gif" => Some(format!("{}.png", stem)),
+        _ => None,
+    }
+}
+
+fn image_path(path: &str) -> String {
+    included_image_path(path).unwrap_or_else(|| path.to_string())
+}
+
 fn render_image(image: &Image) -> String {
-    let path = if image.path.to_lowercase().ends_with(".svg") {
-        format!("{}.pdf", image.path.trim_end_matches(".svg"))
-    } else {
-        image.path.clone()
-    };
+    let path = image_path(&image.path);
     let mut opts = Vec::new();
-    if let Some(width) = image.width.as_deref().and_then(convert_length_to_latex) {
-        opts.push(format!("width={}", width));
+    if let Some(width) = image.width.as_deref().and_then(Length::parse_typst) {
+        opts.push(format!("width={}", width.to_latex()));
     }
-    if let Some(height) = image.height.as_deref().and_then(convert_length_to_latex) {
-        opts.push(format!("height={}", height));
+    if let Some(height) = image.height.as_deref().and_then(Length::parse_typst) {
+        opts.push(format!("height={}", height.to_latex()));
     }
     if let Some(fit) = image.fit.as_deref() {
         if fit.contains("contain") || fit.contains("fit") {
             opts.push("keepaspectratio".to_string());
         }
     }
+    // graphicx reads `alt` since LaTeX 2021-06; tagged PDFs carry it.
+    if let Some(alt) = image.alt.as_deref().filter(|alt| !alt.trim().is_empty()) {
+        opts.push(format!("alt={{{}}}", escape_latex(alt.trim())));
+    }
     let opt_str = if opts.is_empty() {
         String::new()
     } else {
@@ -2883,23 +6443,72 @@ fn default_float_placement(options: &LatexRenderOptions) -> &'static str {
     }
 }
 
-fn convert_length_to_latex(raw: &str) -> Option<String> {
-    let trimmed = raw.trim();
-    if let Some(percent) = trimmed.strip_suffix('%') {
-        let val = percent.trim().parse::<f64>().ok()?;
-        if (val - 100.0).abs() < 0.1 {
-            return Some("\\linewidth".to_string());
+/// Typst's default inset of table cells, in points.
+const TYPST_TABLE_INSET_PT: f64 = 5.0;
+
+/// `\tabcolsep` pads each side of a column, so it is the cell inset plus
+/// half the column gutter. Without a gutter the inset is taken as written.
+fn table_column_sep(table: &Table, options: &LatexRenderOptions) -> Option<String> {
+    let inset = table
+        .inset
+        .as_deref()
+        .and_then(parse_table_inset)
+        .and_then(|token| Length::parse_typst(&token));
+    let base_pt = options.base_font_size_pt.unwrap_or(10.0);
+    let gutter_pt = table
+        .column_gutter
+        .as_deref()
+        .or(table.gutter.as_deref())
+        .and_then(Length::parse_typst)
+        .and_then(|gutter| gutter.to_pt(base_pt));
+    let inset_pt = match inset {
+        Some(inset) => inset.to_pt(base_pt),
+        None => Some(TYPST_TABLE_INSET_PT),
+    };
+    match (inset_pt, gutter_pt) {
+        (Some(inset_pt), Some(gutter_pt)) => {
+            Some(Length::pt(inset_pt + gutter_pt / 2.0).to_latex())
         }
-        let scale = val / 100.0;
-        return Some(format!("{:.2}\\linewidth", scale));
+        _ => inset.map(|inset| inset.to_latex()),
     }
-    if trimmed
-        .chars()
-        .all(|ch| ch.is_ascii_digit() || ch == '.')
-    {
-        return Some(format!("{}pt", trimmed));
+}
+
+/// `\arraystretch` opening each row by the row gutter and by whatever the
+/// vertical inset adds to Typst's default, against the baseline skip.
+fn table_row_stretch(table: &Table, options: &LatexRenderOptions) -> Option<String> {
+    let gutter = table.row_gutter.as_deref().or(table.gutter.as_deref())?;
+    let base_pt = options.base_font_size_pt.unwrap_or(10.0);
+    let inset_pt = table
+        .inset
+        .as_deref()
+        .and_then(parse_table_inset_y)
+        .and_then(|token| Length::parse_typst(&token)?.to_pt(base_pt))
+        .unwrap_or(TYPST_TABLE_INSET_PT);
+    let gutter_pt = Length::parse_typst(gutter)?.to_pt(base_pt)?;
+    let extra = gutter_pt + 2.0 * (inset_pt - TYPST_TABLE_INSET_PT);
+    let stretch = 1.0 + extra / (1.2 * base_pt);
+    (stretch > 0.0 && (stretch - 1.0).abs() >= 0.01).then(|| {
+        format!("{:.2}", stretch)
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    })
+}
+
+/// The vertical part of a cell inset: its `y`, or the one length of all
+/// sides.
+fn parse_table_inset_y(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    if trimmed.contains("=>") {
+        return None;
+    }
+    if let Some(idx) = trimmed.find("y:") {
+        return extract_length_token(&trimmed[idx + 2..]);
+    }
+    if trimmed.contains(':') || trimmed.ends_with('%') {
+        return None;
     }
-    Some(trimmed.to_string())
+    extract_length_token(trimmed)
 }
 
 fn parse_table_inset(raw: &str) -> Option<String> {
@@ -2923,29 +6532,85 @@ fn parse_table_inset(raw: &str) -> Option<String> {
     Some(candidate)
 }
 
-fn extract_length_token(raw: &str) -> Option<String> {
-    let mut token = String::new();
-    let mut started = false;
-    for ch in raw.chars() {
-        if !started {
-            if ch.is_ascii_digit() || ch == '.' || ch == '-' {
-                token.push(ch);
-                started = true;
-            }
-            continue;
-        }
-        if ch.is_ascii_digit() || ch == '.' || ch == '-' || ch.is_ascii_alphabetic() {
-            token.push(ch);
-            started = true;
-        } else if started {
-            break;
+/// A `full` bibliography lists the uncited entries too: inline, by listing
+/// the whole database, otherwise through `\nocite{*}`. A title renames the
+/// references heading within a group; an empty one removes it.
+fn render_bibliography_block(
+    file: &str,
+    style: Option<&str>,
+    full: bool,
+    title: Option<&[Inline]>,
+    options: &LatexRenderOptions,
+) -> String {
+    if citation_package(options) == CitationPackage::Biblatex {
+        return render_printbibliography(full, title, options);
+    }
+    let style = style.or(options.bibliography_style_default.as_deref());
+    let list = match &options.inline_bibliography {
+        Some(db) => {
+            let cited = if full {
+                Vec::new()
+            } else {
+                CITED_KEYS.with(|keys| keys.borrow().clone())
+            };
+            render_thebibliography(db, &cited, BibStyle::from_name(style.unwrap_or("plain")))
         }
-    }
-    if token.is_empty() {
-        None
+        None if full => format!("\\nocite{{*}}\n{}", render_bibliography(file, style)),
+        None => render_bibliography(file, style),
+    };
+    let Some(title) = title else {
+        return list;
+    };
+    let mut out = String::from("\\begingroup\n");
+    if title.is_empty() {
+        // thebibliography sets its heading as an unnumbered top-level
+        // heading, `\section*{\refname}` or `\chapter*{\bibname}`, which
+        // titlesec sets as nothing within the group. The class keeps its
+        // own sectioning commands.
+        let command = options
+            .template
+            .sections()
+            .first()
+            .map_or("section", |&(command, _)| command);
+        out.push_str("\\makeatletter\n");
+        out.push_str(&format!(
+            "\\titleformat{{name=\\{0},numberless}}{{}}{{}}{{0pt}}{{\\@gobble}}\n\
+             \\titlespacing*{{name=\\{0},numberless}}{{0pt}}{{0pt}}{{0pt}}\n",
+            command
+        ));
+        out.push_str("\\makeatother\n");
     } else {
-        Some(token)
+        out.push_str("\\renewcommand{\\refname}{");
+        out.push_str(&render_moving_argument(title, options));
+        out.push_str("}\n");
+    }
+    out.push_str(&list);
+    out.push_str("\n\\endgroup");
+    out
+}
+
+/// biblatex lists the resources the preamble added and takes the heading
+/// as an option.
+fn render_printbibliography(
+    full: bool,
+    title: Option<&[Inline]>,
+    options: &LatexRenderOptions,
+) -> String {
+    let mut out = String::new();
+    if full {
+        out.push_str("\\nocite{*}\n");
+    }
+    out.push_str("\\printbibliography");
+    match title {
+        Some([]) => out.push_str("[heading=none]"),
+        Some(title) => {
+            out.push_str("[title={");
+            out.push_str(&render_moving_argument(title, options));
+            out.push_str("}]");
+        }
+        None => {}
     }
+    out
 }
 
 fn render_bibliography(file: &str, style: Option<&str>) -> String {
@@ -2955,7 +6620,10 @@ fn render_bibliography(file: &str, style: Option<&str>) -> String {
         if trimmed.is_empty() {
             continue;
         }
-        let trimmed = trimmed.trim_end_matches(".bib");
+        let trimmed = trimmed
+            .trim_end_matches(".bib")
+            .trim_end_matches(".yml")
+            .trim_end_matches(".yaml");
         if !trimmed.is_empty() {
             files.push(escape_latex(trimmed));
         }
@@ -2981,38 +6649,12 @@ fn render_vspace(raw: &str) -> String {
     if trimmed.is_empty() {
         return "\\par\\vspace{0pt}".to_string();
     }
-    if let Some(converted) = convert_vspace_length(trimmed) {
-        return format!("\\par\\vspace{{{}}}", converted);
+    if let Some(length) = Length::parse_typst(trimmed) {
+        return format!("\\par\\vspace{{{}}}", length.to_latex());
     }
     format!("\\par\\vspace{{{}}}", trimmed)
 }
 
-fn convert_vspace_length(raw: &str) -> Option<String> {
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return None;
-    }
-    if let Some(fr) = trimmed.strip_suffix("fr") {
-        let val = fr.trim().parse::<f64>().ok()?;
-        if (val - 1.0).abs() < 0.01 {
-            return Some("\\fill".to_string());
-        }
-        if val > 0.0 {
-            if (val.fract()).abs() < 0.01 {
-                return Some(format!("\\stretch{{{}}}", val.round() as i64));
-            }
-            return Some(format!("\\stretch{{{:.2}}}", val));
-        }
-    }
-    if trimmed
-        .chars()
-        .all(|ch| ch.is_ascii_digit() || ch == '.')
-    {
-        return Some(format!("{}pt", trimmed));
-    }
-    Some(trimmed.to_string())
-}
-
 fn map_bibliography_style(raw: &str) -> String {
     let lowered = raw.trim().to_lowercase();
     if lowered.contains("ieee") {
@@ -3030,37 +6672,187 @@ fn map_bibliography_style(raw: &str) -> String {
     raw.trim().to_string()
 }
 
-fn render_outline(title: Option<&[Inline]>, options: &LatexRenderOptions) -> String {
+fn render_outline(
+    title: Option<&[Inline]>,
+    target: OutlineTarget,
+    options: &LatexRenderOptions,
+) -> String {
+    let (name, command) = match target {
+        OutlineTarget::Headings => ("contentsname", "tableofcontents"),
+        OutlineTarget::Figures => ("listfigurename", "listoffigures"),
+        OutlineTarget::Tables => ("listtablename", "listoftables"),
+        OutlineTarget::Listings if options.code_backend == CodeBackend::Minted => {
+            ("listoflistingscaption", "listoflistings")
+        }
+        OutlineTarget::Listings => ("lstlistlistingname", "lstlistoflistings"),
+        _ => ("contentsname", "tableofcontents"),
+    };
     let mut out = String::new();
+    if target == OutlineTarget::Headings && options.toc_depth.is_some() {
+        out.push_str(&format!(
+            "\\setcounter{{tocdepth}}{{{}}}\n",
+            toc_depth(options)
+        ));
+    }
     if let Some(title) = title {
-        out.push_str("\\renewcommand{\\contentsname}{");
-        out.push_str(&normalize_inline_whitespace(&render_inlines(title, options)));
+        out.push_str(&format!("\\renewcommand{{\\{}}}{{", name));
+        out.push_str(&render_moving_argument(title, options));
         out.push_str("}\n");
     }
-    out.push_str("\\tableofcontents");
+    out.push('\\');
+    out.push_str(command);
     out
 }
 
+/// `\fbox` holds a single line of text, so a box with several paragraphs or
+/// display content is set in a full-width minipage inside the frame.
 fn render_box(blocks: &[Block], options: &LatexRenderOptions) -> String {
     let mut out = String::new();
     out.push_str("\\fbox{");
-    out.push_str(&render_blocks_inline(blocks, options));
+    match blocks {
+        [] | [Block::Paragraph(_)] => out.push_str(&render_blocks_inline(blocks, options)),
+        _ => {
+            out.push_str(
+                "\\begin{minipage}{\\dimexpr\\linewidth-2\\fboxsep-2\\fboxrule\\relax}\n",
+            );
+            out.push_str(&render_blocks_separated(blocks, options));
+            out.push_str("\n\\end{minipage}");
+        }
+    }
     out.push('}');
     out
 }
 
 fn render_block_wrapper(blocks: &[Block], options: &LatexRenderOptions) -> String {
-    render_blocks_inline(blocks, options)
+    render_blocks_separated(blocks, options)
 }
 
+/// `multicols` cannot hold floats or anything wider than a column, so the
+/// environment is closed around such blocks and reopened after them.
 fn render_columns(columns: &tylax_ir::Columns, options: &LatexRenderOptions) -> String {
-    let mut out = String::new();
-    out.push_str(&format!("\\begin{{multicols}}{{{}}}\n", columns.columns.max(1)));
-    out.push_str(&render_blocks_inline(&columns.blocks, options));
-    out.push_str("\n\\end{multicols}");
+    let count = columns.columns.max(1);
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (i, block) in columns.blocks.iter().enumerate() {
+        if spans_columns(block, options) {
+            push_multicols(&mut parts, count, &columns.blocks[start..i], options);
+            parts.push(render_block(block, options));
+            start = i + 1;
+        }
+    }
+    push_multicols(&mut parts, count, &columns.blocks[start..], options);
+    if parts.is_empty() {
+        parts.push(format!("\\begin{{multicols}}{{{}}}\n\n\\end{{multicols}}", count));
+    }
+    parts.join("\n")
+}
+
+/// A frame on slides. Elsewhere the title is set as an unnumbered
+/// section and the content follows it.
+fn render_slide(slide: &Slide, options: &LatexRenderOptions) -> String {
+    let title = slide
+        .title
+        .as_ref()
+        .map(|title| render_moving_argument(title, options));
+    let body = render_blocks_separated(&slide.blocks, options);
+    if options.template != DocumentTemplate::Beamer {
+        return match title {
+            Some(title) if body.trim().is_empty() => format!("\\section*{{{}}}", title),
+            Some(title) => format!("\\section*{{{}}}\n\n{}", title, body),
+            None => body,
+        };
+    }
+    let mut out = String::from("\\begin{frame}[fragile]");
+    if let Some(title) = title {
+        out.push_str(&format!("{{{}}}", title));
+    }
+    out.push('\n');
+    if !body.trim().is_empty() {
+        out.push_str(&body);
+        out.push('\n');
+    }
+    out.push_str("\\end{frame}");
     out
 }
 
+/// Beamer's `columns` on slides and top-aligned minipages elsewhere. Columns
+/// without a width share what the others leave.
+fn render_side_by_side(side: &SideBySide, options: &LatexRenderOptions) -> String {
+    let beamer = options.template == DocumentTemplate::Beamer;
+    let count = side.columns.len().max(1);
+    let fractions: Vec<Option<f64>> = (0..count)
+        .map(|i| match side.widths.get(i) {
+            Some(ColumnWidth::Fraction(fr)) => fr.parse().ok(),
+            Some(ColumnWidth::Length(_)) => None,
+            _ => Some(1.0),
+        })
+        .collect();
+    let total: f64 = fractions.iter().flatten().sum();
+    let line = if beamer { "\\textwidth" } else { "\\linewidth" };
+    let widths: Vec<String> = (0..count)
+        .map(|i| match (fractions[i], side.widths.get(i)) {
+            (Some(fr), _) => format!("{:.2}{}", 0.95 * fr / total, line),
+            (None, Some(ColumnWidth::Length(length))) => Length::parse_typst(length)
+                .map_or_else(|| length.clone(), |length| length.to_latex()),
+            (None, _) => format!("{:.2}{}", 0.95 / count as f64, line),
+        })
+        .collect();
+    let mut out = String::new();
+    if beamer {
+        out.push_str("\\begin{columns}[T]\n");
+    } else {
+        out.push_str("\\noindent\n");
+    }
+    for (i, (column, width)) in side.columns.iter().zip(&widths).enumerate() {
+        let body = render_blocks_separated(column, options);
+        if beamer {
+            out.push_str(&format!("\\begin{{column}}{{{}}}\n{}\n\\end{{column}}\n", width, body));
+        } else {
+            if i > 0 {
+                out.push_str("\\hfill\n");
+            }
+            out.push_str(&format!(
+                "\\begin{{minipage}}[t]{{{}}}\n{}\n\\end{{minipage}}\n",
+                width, body
+            ));
+        }
+    }
+    if beamer {
+        out.push_str("\\end{columns}");
+    }
+    out.trim_end().to_string()
+}
+
+fn push_multicols(
+    parts: &mut Vec<String>,
+    count: usize,
+    blocks: &[Block],
+    options: &LatexRenderOptions,
+) {
+    let body = render_blocks_separated(blocks, options);
+    if body.trim().is_empty() {
+        return;
+    }
+    parts.push(format!(
+        "\\begin{{multicols}}{{{}}}\n{}\n\\end{{multicols}}",
+        count, body
+    ));
+}
+
+/// Floats, tables too wide for a column and parent-scoped `#place` calls
+/// span the whole page width in Typst.
+fn spans_columns(block: &Block, options: &LatexRenderOptions) -> bool {
+    match block {
+        Block::Figure(_) | Block::Algorithm(_) => true,
+        Block::Table(table) => is_long_table(table, options) || is_wide_table(table),
+        Block::Unsupported { source, .. } => {
+            let compact: String = source.chars().filter(|c| !c.is_whitespace()).collect();
+            compact.starts_with("#place(") && compact.contains("scope:\"parent\"")
+        }
+        _ => false,
+    }
+}
+
 fn render_grid(grid: &Grid, options: &LatexRenderOptions) -> String {
     let mut out = String::new();
     let col_gutter = grid
@@ -3119,14 +6911,18 @@ fn render_grid(grid: &Grid, options: &LatexRenderOptions) -> String {
     out
 }
 
+/// A color for `\rowcolors` and `\cellcolor`, which take no model: its
+/// xcolor expression, or `default_name` defined with its value.
 fn resolve_color(raw: &str, default_name: &str) -> (String, Option<String>) {
-    if let Some(hex) = extract_hex_color(raw) {
-        let name = default_name.to_string();
-        let def = format!("\\definecolor{{{}}}{{HTML}}{{{}}}", name, hex);
-        return (name, Some(def));
+    match parse_color(raw).map(|color| color.to_latex()) {
+        Some((None, expression)) => (expression, None),
+        Some((Some(model), value)) => {
+            let name = default_name.to_string();
+            let def = format!("\\definecolor{{{}}}{{{}}}{{{}}}", name, model, value);
+            (name, Some(def))
+        }
+        None => ("black".to_string(), None),
     }
-    let name = sanitize_color_name(raw);
-    (name, None)
 }
 
 struct RowColors {
@@ -3224,44 +7020,3 @@ fn extract_color_tokens(raw: &str) -> Vec<String> {
     }
     tokens
 }
-
-fn extract_hex_color(raw: &str) -> Option<String> {
-    let s = raw.trim();
-    if let Some(idx) = s.find('#') {
-        let mut hex = String::new();
-        for ch in s[idx + 1..].chars() {
-            if ch.is_ascii_hexdigit() {
-                hex.push(ch.to_ascii_uppercase());
-                if hex.len() == 6 {
-                    break;
-                }
-            } else {
-                break;
-            }
-        }
-        if hex.len() == 3 || hex.len() == 6 {
-            return Some(hex);
-        }
-    }
-    None
-}
-
-fn sanitize_color_name(raw: &str) -> String {
-    let mut name = String::new();
-    let trimmed = raw
-        .trim()
-        .trim_start_matches("rgb(")
-        .trim_start_matches("rgba(")
-        .trim_end_matches(')')
-        .trim_matches('"');
-    for ch in trimmed.chars() {
-        if ch.is_ascii_alphanumeric() {
-            name.push(ch);
-        }
-    }
-    if name.is_empty() {
-        "black".to_string()
-    } else {
-        name
-    }
-}
@@ -0,0 +1,1399 @@
+//! Typst math to LaTeX.
+//!
+//! A formula is parsed with typst-syntax, lowered by a small recursive-descent
+//! pass into a [`MathNode`] tree and rendered from there. Source the parser
+//! does not single out (operators, letters, spacing, LaTeX commands that were
+//! passed through verbatim) is copied unchanged.
+
+use typst_syntax::{SyntaxKind, SyntaxNode};
+
+use crate::{escape_latex, RenderDepthGuard};
+
+/// Deepest nesting the math parser descends into. Deeper subtrees are kept
+/// as source, and formulas whose brackets nest deeper are not parsed at all
+/// because typst-syntax itself recurses once per bracket.
+const MAX_MATH_NESTING: usize = 64;
+
+/// Most script and fraction operators a formula may hold before it is left
+/// unparsed. Chains like `x^x^x` nest one syntax node per operator.
+const MAX_MATH_OPERATORS: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MathNode {
+    /// Source copied through, with `%` escaped.
+    Text(String),
+    /// Source copied through untouched, such as escapes.
+    Verbatim(String),
+    /// An identifier or dotted name such as `alpha` or `tilde.op`.
+    Symbol(String),
+    /// An operator shorthand such as `->` or `!=`.
+    Shorthand(String),
+    /// A string literal, already unescaped.
+    Str(String),
+    /// An `&` alignment point.
+    AlignPoint,
+    /// A `\` row break.
+    LineBreak,
+    /// A sub- or superscript. Parenthesized scripts lose their parens and
+    /// every script longer than one symbol is braced.
+    Script {
+        op: char,
+        body: Vec<MathNode>,
+        grouped: bool,
+    },
+    /// A parenthesized group that keeps its parens.
+    Group(Vec<MathNode>),
+    /// A group between scaled delimiters, from `lr(...)` or a `|x|` pair.
+    Fence {
+        open: &'static str,
+        body: Vec<MathNode>,
+        close: &'static str,
+    },
+    /// A call of a function with a LaTeX counterpart.
+    Call(Call),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Call {
+    name: String,
+    /// Arguments row by row; only `mat` uses more than one row.
+    rows: Vec<Vec<Arg>>,
+    /// Source between the parens, for calls whose arguments do not fit.
+    source: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Arg {
+    Positional {
+        nodes: Vec<MathNode>,
+        source: String,
+    },
+    /// A named argument, kept both as plain text for option values like
+    /// `delim` and parsed for math values like the scripts of `attach`.
+    Named {
+        name: String,
+        value: String,
+        nodes: Vec<MathNode>,
+    },
+}
+
+impl Call {
+    fn positional(&self) -> Vec<&Arg> {
+        self.rows
+            .iter()
+            .flatten()
+            .filter(|arg| matches!(arg, Arg::Positional { .. }))
+            .collect()
+    }
+
+    fn first(&self) -> Option<&Arg> {
+        self.positional().into_iter().next()
+    }
+
+    fn named(&self, name: &str) -> Option<&str> {
+        self.rows.iter().flatten().find_map(|arg| match arg {
+            Arg::Named {
+                name: key, value, ..
+            } if key == name => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    fn named_nodes(&self, name: &str) -> Option<&[MathNode]> {
+        self.rows.iter().flatten().find_map(|arg| match arg {
+            Arg::Named {
+                name: key, nodes, ..
+            } if key == name => Some(nodes.as_slice()),
+            _ => None,
+        })
+    }
+}
+
+/// How a formula is laid out in rows, from its own alignment points and
+/// row breaks. Those of calls that set their own rows, as `cases` and
+/// `mat`, do not count.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct MathRows {
+    pub(crate) align_points: bool,
+    pub(crate) line_breaks: bool,
+}
+
+pub(crate) fn convert_math_content(input: &str) -> String {
+    convert_math_rows(input).0
+}
+
+/// Convert a formula and tell how its rows are laid out.
+pub(crate) fn convert_math_rows(input: &str) -> (String, MathRows) {
+    convert_math_nodes(input, |_, _| {})
+}
+
+pub(crate) fn convert_math_content_inline(input: &str) -> String {
+    let converted = convert_math_content(input);
+    replace_inline_setminus(&converted)
+}
+
+/// Convert a formula set inline where display environments cannot go, as in
+/// a table cell or a footnote, and tell how its rows are laid out. A single
+/// row loses its alignment points, which would end a table cell. Where rows
+/// are broken with `\\`, an escaped backslash cannot be told from a break
+/// by the text, so it is set as `\setminus` before rendering.
+pub(crate) fn convert_math_inline_context(input: &str) -> (String, MathRows) {
+    let (converted, rows) = convert_math_nodes(input, |nodes, rows| {
+        if rows.line_breaks {
+            for node in nodes.iter_mut() {
+                if *node == MathNode::Verbatim("\\\\".to_string()) {
+                    *node = MathNode::Verbatim("\\setminus{}".to_string());
+                }
+            }
+        } else {
+            nodes.retain(|node| *node != MathNode::AlignPoint);
+        }
+    });
+    if rows.line_breaks {
+        (converted, rows)
+    } else {
+        (replace_inline_setminus(&converted), rows)
+    }
+}
+
+/// Parse a formula, let `adjust` rework its top-level nodes knowing how its
+/// rows are laid out, and render it.
+fn convert_math_nodes(
+    input: &str,
+    adjust: impl FnOnce(&mut Vec<MathNode>, MathRows),
+) -> (String, MathRows) {
+    let Some(_guard) = RenderDepthGuard::enter() else {
+        return (input.to_string(), MathRows::default());
+    };
+    if too_deep_to_parse(input) {
+        return (input.replace('%', "\\%"), MathRows::default());
+    }
+    let root = typst_syntax::parse_math(input);
+    let mut nodes = MathParser::default().parse_children(root.children().as_slice());
+    let rows = MathRows {
+        align_points: nodes.contains(&MathNode::AlignPoint),
+        line_breaks: nodes.contains(&MathNode::LineBreak),
+    };
+    adjust(&mut nodes, rows);
+    // Typst spaces out multi-letter words that are not identifiers; join the
+    // usual operator names back together.
+    (join_spaced_letters(&render(&nodes)), rows)
+}
+
+fn too_deep_to_parse(input: &str) -> bool {
+    let mut depth = 0usize;
+    let mut max = 0usize;
+    let mut operators = 0usize;
+    for ch in input.chars() {
+        match ch {
+            '(' | '[' | '{' => {
+                depth += 1;
+                max = max.max(depth);
+            }
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            '^' | '_' | '/' => operators += 1,
+            _ => {}
+        }
+    }
+    max > MAX_MATH_NESTING || operators > MAX_MATH_OPERATORS
+}
+
+/// Lowers typst-syntax math nodes to [`MathNode`]s. It remembers the last
+/// source character so that names glued to a preceding word, like the `pi`
+/// in `2pi` or the tail of a passed-through `\beta`, stay plain text.
+#[derive(Default)]
+struct MathParser {
+    prev: Option<char>,
+    depth: usize,
+}
+
+impl MathParser {
+    fn parse_children(&mut self, children: &[SyntaxNode]) -> Vec<MathNode> {
+        let mut out = Vec::new();
+        let mut idx = 0;
+        while idx < children.len() {
+            if let Some((end, bar)) = closing_bar(children, idx) {
+                let (open, close) = match bar {
+                    "||" => ("\\lVert", "\\rVert"),
+                    _ => ("\\lvert", "\\rvert"),
+                };
+                self.note(bar);
+                let body = self.parse_children(&children[idx + 1..end]);
+                self.note(bar);
+                out.push(MathNode::Fence { open, body, close });
+                // `|x|^2` hangs the scripts off the closing bar.
+                if children[end].kind() == SyntaxKind::MathAttach {
+                    self.parse_attach(&children[end].children().as_slice()[1..], &mut out);
+                }
+                idx = end + 1;
+                continue;
+            }
+            self.parse_node(&children[idx], &mut out);
+            idx += 1;
+        }
+        out
+    }
+
+    fn parse_node(&mut self, node: &SyntaxNode, out: &mut Vec<MathNode>) {
+        if self.depth >= MAX_MATH_NESTING {
+            let text = node_text(node);
+            self.note(&text);
+            out.push(MathNode::Text(text));
+            return;
+        }
+        self.depth += 1;
+        match node.kind() {
+            SyntaxKind::MathIdent | SyntaxKind::Ident => out.push(self.symbol(node.text())),
+            SyntaxKind::FieldAccess => {
+                let name = node_text(node);
+                if !self.after_word() && lookup_symbol(&name).is_some() {
+                    self.note(&name);
+                    out.push(MathNode::Symbol(name));
+                } else {
+                    for child in node.children() {
+                        self.parse_node(child, out);
+                    }
+                }
+            }
+            SyntaxKind::FuncCall => self.parse_call(node, out),
+            SyntaxKind::MathAttach => self.parse_attach(node.children().as_slice(), out),
+            SyntaxKind::Math if is_paren_group(node) => {
+                out.push(MathNode::Group(self.parse_paren_group(node)));
+            }
+            SyntaxKind::Str => {
+                self.note(node.text());
+                out.push(MathNode::Str(unescape_typst_string(unquote(node.text()))));
+            }
+            SyntaxKind::Shorthand => {
+                self.note(node.text());
+                out.push(MathNode::Shorthand(node.text().to_string()));
+            }
+            SyntaxKind::MathAlignPoint => {
+                self.note(node.text());
+                out.push(MathNode::AlignPoint);
+            }
+            SyntaxKind::Linebreak => {
+                self.note(node.text());
+                out.push(MathNode::LineBreak);
+            }
+            SyntaxKind::Escape => {
+                self.note(node.text());
+                out.push(MathNode::Verbatim(node.text().to_string()));
+            }
+            _ if node.children().len() == 0 => {
+                self.note(node.text());
+                out.push(MathNode::Text(node.text().to_string()));
+            }
+            _ => {
+                for child in node.children() {
+                    self.parse_node(child, out);
+                }
+            }
+        }
+        self.depth -= 1;
+    }
+
+    fn symbol(&mut self, name: &str) -> MathNode {
+        let glued = self.after_word();
+        self.note(name);
+        if glued {
+            MathNode::Text(name.to_string())
+        } else {
+            MathNode::Symbol(name.to_string())
+        }
+    }
+
+    fn parse_call(&mut self, node: &SyntaxNode, out: &mut Vec<MathNode>) {
+        let name = node
+            .children()
+            .next()
+            .filter(|callee| callee.kind() == SyntaxKind::MathIdent)
+            .map(|callee| callee.text().to_string())
+            .unwrap_or_default();
+        let args = node
+            .children()
+            .find(|child| child.kind() == SyntaxKind::Args);
+        if name == "lr" && !self.after_word() {
+            if let Some(args) = args {
+                self.parse_lr(args, out);
+                return;
+            }
+        }
+        let args = match args {
+            Some(args) if !self.after_word() && is_converted_call(&name) => args,
+            _ => {
+                for child in node.children() {
+                    self.parse_node(child, out);
+                }
+                return;
+            }
+        };
+        self.note(&name);
+        let inner: Vec<SyntaxNode> = args
+            .children()
+            .filter(|child| !matches!(child.kind(), SyntaxKind::LeftParen | SyntaxKind::RightParen))
+            .cloned()
+            .collect();
+        self.note("(");
+        let source = inner.iter().map(node_text).collect::<String>();
+        let mut rows = Vec::new();
+        for row in split_nodes(&inner, SyntaxKind::Semicolon) {
+            let row: Vec<SyntaxNode> = row
+                .into_iter()
+                .flat_map(|node| match node.kind() {
+                    SyntaxKind::Array => node.children().cloned().collect(),
+                    _ => vec![node],
+                })
+                .collect();
+            let mut parsed = Vec::new();
+            for arg in split_nodes(&row, SyntaxKind::Comma) {
+                let arg = trim_spaces(&arg);
+                if arg.is_empty() {
+                    continue;
+                }
+                if let [named] = arg {
+                    if named.kind() == SyntaxKind::Named {
+                        parsed.push(self.parse_named(named));
+                        continue;
+                    }
+                }
+                let source = arg.iter().map(node_text).collect::<String>();
+                let nodes = self.parse_children(arg);
+                self.note(",");
+                parsed.push(Arg::Positional { nodes, source });
+            }
+            rows.push(parsed);
+        }
+        self.note(")");
+        out.push(MathNode::Call(Call { name, rows, source }));
+    }
+
+    fn parse_named(&mut self, node: &SyntaxNode) -> Arg {
+        let name = node
+            .children()
+            .find(|child| child.kind() == SyntaxKind::Ident)
+            .map(|ident| ident.text().to_string())
+            .unwrap_or_default();
+        let value: Vec<SyntaxNode> = node
+            .children()
+            .skip_while(|child| child.kind() != SyntaxKind::Colon)
+            .skip(1)
+            .cloned()
+            .collect();
+        let source = value.iter().map(node_text).collect::<String>();
+        let nodes = self.parse_children(trim_spaces(&value));
+        self.note(",");
+        Arg::Named {
+            name,
+            value: source.trim().trim_matches('"').to_string(),
+            nodes,
+        }
+    }
+
+    fn parse_attach(&mut self, children: &[SyntaxNode], out: &mut Vec<MathNode>) {
+        let mut idx = 0;
+        while idx < children.len() {
+            let child = &children[idx];
+            let op = match child.kind() {
+                SyntaxKind::Underscore => Some('_'),
+                SyntaxKind::Hat => Some('^'),
+                _ => None,
+            };
+            match (op, children.get(idx + 1)) {
+                (Some(op), Some(script)) => {
+                    self.note(child.text());
+                    let grouped = is_paren_group(script);
+                    let body = if grouped {
+                        self.parse_paren_group(script)
+                    } else {
+                        let mut body = Vec::new();
+                        self.parse_node(script, &mut body);
+                        body
+                    };
+                    out.push(MathNode::Script { op, body, grouped });
+                    idx += 2;
+                }
+                _ => {
+                    self.parse_node(child, out);
+                    idx += 1;
+                }
+            }
+        }
+    }
+
+    /// `lr(...)` scales the delimiters at both ends of its body. A side
+    /// without a delimiter gets LaTeX's empty `.` fence, and a body without
+    /// any is left as is. `size:` is dropped, since `\left`/`\right` already
+    /// size to the content.
+    fn parse_lr(&mut self, args: &SyntaxNode, out: &mut Vec<MathNode>) {
+        // Commas belong to the body, so `lr(]a, b/2])` fences one list.
+        let mut nodes: Vec<SyntaxNode> = Vec::new();
+        for child in args.children() {
+            match child.kind() {
+                SyntaxKind::LeftParen | SyntaxKind::RightParen => {}
+                SyntaxKind::Named => {
+                    while nodes.last().is_some_and(|node| {
+                        matches!(node.kind(), SyntaxKind::Space | SyntaxKind::Comma)
+                    }) {
+                        nodes.pop();
+                    }
+                }
+                _ => flatten_arg(child, &mut nodes),
+            }
+        }
+        if let [single] = nodes.as_slice() {
+            if single.kind() == SyntaxKind::MathDelimited {
+                nodes = single.children().cloned().collect();
+            }
+        }
+        let nodes = trim_spaces(&nodes);
+        let open = nodes.first().and_then(fence_delimiter);
+        let close = if nodes.len() > 1 {
+            nodes.last().and_then(fence_delimiter)
+        } else {
+            None
+        };
+        let start = usize::from(open.is_some());
+        let end = nodes.len() - usize::from(close.is_some());
+        self.note("(");
+        let body = self.parse_children(trim_spaces(&nodes[start..end]));
+        self.note(")");
+        if open.is_none() && close.is_none() {
+            out.extend(body);
+            return;
+        }
+        out.push(MathNode::Fence {
+            open: open.unwrap_or("."),
+            body,
+            close: close.unwrap_or("."),
+        });
+    }
+
+    /// Parse the inside of a `(...)` math node, leaving out the parens.
+    fn parse_paren_group(&mut self, node: &SyntaxNode) -> Vec<MathNode> {
+        let children = node.children().as_slice();
+        self.note("(");
+        let body = self.parse_children(&children[1..children.len() - 1]);
+        self.note(")");
+        body
+    }
+
+    fn after_word(&self) -> bool {
+        self.prev.is_some_and(|ch| ch.is_ascii_alphanumeric())
+    }
+
+    fn note(&mut self, text: &str) {
+        if let Some(last) = text.chars().last() {
+            self.prev = Some(last);
+        }
+    }
+}
+
+fn is_paren_group(node: &SyntaxNode) -> bool {
+    if node.kind() != SyntaxKind::Math {
+        return false;
+    }
+    let children = node.children().as_slice();
+    children.len() >= 2
+        && children[0].kind() == SyntaxKind::LeftParen
+        && children[children.len() - 1].kind() == SyntaxKind::RightParen
+}
+
+/// Spread an argument into its tokens, looking through the `Math` and
+/// `Array` wrappers typst-syntax puts around multi-token arguments.
+fn flatten_arg(node: &SyntaxNode, out: &mut Vec<SyntaxNode>) {
+    match node.kind() {
+        SyntaxKind::Math | SyntaxKind::Array => {
+            for child in node.children() {
+                flatten_arg(child, out);
+            }
+        }
+        _ => out.push(node.clone()),
+    }
+}
+
+fn split_nodes(nodes: &[SyntaxNode], separator: SyntaxKind) -> Vec<Vec<SyntaxNode>> {
+    let mut parts = vec![Vec::new()];
+    for node in nodes {
+        if node.kind() == separator {
+            parts.push(Vec::new());
+        } else if let Some(part) = parts.last_mut() {
+            part.push(node.clone());
+        }
+    }
+    parts
+}
+
+fn trim_spaces(nodes: &[SyntaxNode]) -> &[SyntaxNode] {
+    let is_space = |node: &SyntaxNode| node.kind() == SyntaxKind::Space;
+    let start = nodes
+        .iter()
+        .position(|node| !is_space(node))
+        .unwrap_or(nodes.len());
+    let end = nodes
+        .iter()
+        .rposition(|node| !is_space(node))
+        .map_or(start, |idx| idx + 1);
+    &nodes[start..end]
+}
+
+/// Source text of `node`, gathered without recursion.
+fn node_text(node: &SyntaxNode) -> String {
+    if node.children().len() == 0 {
+        return node.text().to_string();
+    }
+    let mut out = String::with_capacity(node.len());
+    let mut stack = vec![node];
+    while let Some(current) = stack.pop() {
+        if current.children().len() == 0 {
+            out.push_str(current.text());
+        } else {
+            stack.extend(current.children().rev());
+        }
+    }
+    out
+}
+
+fn unquote(text: &str) -> &str {
+    let text = text.strip_prefix('"').unwrap_or(text);
+    text.strip_suffix('"').unwrap_or(text)
+}
+
+fn render(nodes: &[MathNode]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        render_node(node, &mut out);
+    }
+    out
+}
+
+fn render_node(node: &MathNode, out: &mut String) {
+    match node {
+        MathNode::Text(text) => out.push_str(&text.replace('%', "\\%")),
+        MathNode::Verbatim(text) => out.push_str(text),
+        MathNode::Symbol(name) => out.push_str(lookup_symbol(name).unwrap_or(name)),
+        MathNode::Shorthand(text) => out.push_str(&render_shorthand(text)),
+        MathNode::Str(text) => {
+            out.push_str("\\text{");
+            out.push_str(text);
+            out.push('}');
+        }
+        MathNode::AlignPoint => out.push('&'),
+        MathNode::LineBreak => out.push_str("\\\\"),
+        MathNode::Script { op, body, grouped } => {
+            out.push(*op);
+            let body = render(body);
+            if *grouped || needs_script_braces(&body) {
+                out.push('{');
+                out.push_str(&body);
+                out.push('}');
+            } else {
+                out.push_str(&body);
+            }
+        }
+        MathNode::Group(body) => {
+            out.push('(');
+            out.push_str(&render(body));
+            out.push(')');
+        }
+        MathNode::Call(call) => out.push_str(&render_call(call)),
+        MathNode::Fence { open, body, close } => {
+            out.push_str("\\left");
+            out.push_str(open);
+            out.push(' ');
+            out.push_str(&render(body));
+            out.push_str(" \\right");
+            out.push_str(close);
+        }
+    }
+}
+
+/// Index of the bar closing the `|` or `||` at `idx`. Bars pair up only
+/// when they hug their content, as in `|x - y|`, so the spaced bar of
+/// `p(a | b)` stays a plain relation.
+fn closing_bar(children: &[SyntaxNode], idx: usize) -> Option<(usize, &'static str)> {
+    let bar = bar_text(&children[idx])?;
+    if children[idx].kind() == SyntaxKind::MathAttach {
+        return None;
+    }
+    if children.get(idx + 1)?.kind() == SyntaxKind::Space {
+        return None;
+    }
+    let end = (idx + 2..children.len()).find(|&end| bar_text(&children[end]) == Some(bar))?;
+    (children[end - 1].kind() != SyntaxKind::Space).then_some((end, bar))
+}
+
+/// The bar a node starts with, looking through the base of an attachment.
+/// Bars are told by their text, whichever token kind the parser gives them.
+fn bar_text(node: &SyntaxNode) -> Option<&'static str> {
+    let node = match node.kind() {
+        SyntaxKind::MathAttach => node.children().next()?,
+        _ => node,
+    };
+    if node.children().len() != 0 {
+        return None;
+    }
+    match node.text().as_str() {
+        "|" => Some("|"),
+        "||" => Some("||"),
+        _ => None,
+    }
+}
+
+/// LaTeX delimiter for a Typst delimiter token or symbol.
+fn fence_delimiter(node: &SyntaxNode) -> Option<&'static str> {
+    let delim = match node_text(node).as_str() {
+        "(" | "paren.l" => "(",
+        ")" | "paren.r" => ")",
+        "[" | "bracket.l" => "[",
+        "]" | "bracket.r" => "]",
+        "{" | "\\{" | "brace.l" => "\\{",
+        "}" | "\\}" | "brace.r" => "\\}",
+        "|" | "bar.v" => "|",
+        "||" | "‖" | "bar.v.double" => "\\|",
+        "⟨" | "angle.l" | "chevron.l" => "\\langle",
+        "⟩" | "angle.r" | "chevron.r" => "\\rangle",
+        "⌈" | "ceil.l" => "\\lceil",
+        "⌉" | "ceil.r" => "\\rceil",
+        "⌊" | "floor.l" => "\\lfloor",
+        "⌋" | "floor.r" => "\\rfloor",
+        _ => return None,
+    };
+    Some(delim)
+}
+
+fn render_shorthand(text: &str) -> String {
+    const MAP: &[(&str, &str)] = &[
+        ("<=>", "\\Leftrightarrow"),
+        ("<->", "\\leftrightarrow"),
+        ("=>", "\\Rightarrow"),
+        ("<-", "\\leftarrow"),
+        ("->", "\\to"),
+        ("<=", "\\le"),
+        (">=", "\\ge"),
+        ("!=", "\\ne"),
+        ("...", "\\ldots"),
+    ];
+    let mut out = String::new();
+    let mut rest = text;
+    'outer: while let Some(ch) = rest.chars().next() {
+        for (token, latex) in MAP {
+            if let Some(tail) = rest.strip_prefix(token) {
+                out.push_str(latex);
+                rest = tail;
+                continue 'outer;
+            }
+        }
+        out.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+    out
+}
+
+fn is_converted_call(name: &str) -> bool {
+    matches!(
+        name,
+        "text"
+            | "attach"
+            | "upright"
+            | "cases"
+            | "mat"
+            | "frac"
+            | "sqrt"
+            | "root"
+            | "binom"
+            | "sin"
+            | "cos"
+            | "tan"
+            | "log"
+            | "ln"
+            | "exp"
+            | "lim"
+            | "max"
+            | "min"
+            | "sup"
+            | "inf"
+            | "argmax"
+            | "argmin"
+            | "abs"
+            | "norm"
+            | "ceil"
+            | "floor"
+            | "vec"
+            | "hat"
+            | "tilde"
+            | "bar"
+            | "dot"
+            | "ddot"
+            | "overline"
+            | "underline"
+            | "bb"
+            | "cal"
+            | "frak"
+            | "bold"
+    )
+}
+
+fn render_call(call: &Call) -> String {
+    let name = call.name.as_str();
+    match name {
+        "text" => {
+            let text = call.first().map(arg_text).unwrap_or_default();
+            format!("\\text{{{}}}", escape_latex(&text))
+        }
+        "upright" => format!(
+            "\\mathrm{{{}}}",
+            call.first().map(render_styled).unwrap_or_default()
+        ),
+        "bb" => format!(
+            "\\mathbb{{{}}}",
+            call.first().map(render_styled).unwrap_or_default()
+        ),
+        "cal" => format!(
+            "\\mathcal{{{}}}",
+            call.first().map(render_styled).unwrap_or_default()
+        ),
+        "frak" => format!(
+            "\\mathfrak{{{}}}",
+            call.first().map(render_styled).unwrap_or_default()
+        ),
+        "bold" => format!(
+            "\\mathbf{{{}}}",
+            call.first().map(render_styled).unwrap_or_default()
+        ),
+        "cases" => render_cases(call),
+        "attach" => render_attach(call),
+        "mat" => render_mat(call),
+        "frac" | "binom" => match call.positional().as_slice() {
+            [num, den, ..] => format!("\\{}{{{}}}{{{}}}", name, render_arg(num), render_arg(den)),
+            _ => format!(
+                "\\operatorname{{{}}}\\left({}\\right)",
+                name,
+                convert_math_content(&call.source)
+            ),
+        },
+        "sqrt" => format!(
+            "\\sqrt{{{}}}",
+            call.first().map(render_arg).unwrap_or_default()
+        ),
+        "root" => match call.positional().as_slice() {
+            [index, radicand, ..] => {
+                format!("\\sqrt[{}]{{{}}}", render_arg(index), render_arg(radicand))
+            }
+            [radicand] => format!("\\sqrt{{{}}}", render_arg(radicand)),
+            [] => "\\sqrt{}".to_string(),
+        },
+        "sin" | "cos" | "tan" | "log" | "ln" | "exp" | "lim" => match call.first() {
+            Some(arg) => format!("\\{}\\left({}\\right)", name, render_arg(arg)),
+            None => format!("\\{}", name),
+        },
+        "max" | "min" | "sup" | "inf" => {
+            let args = call.positional();
+            if args.is_empty() {
+                return format!("\\{}", name);
+            }
+            let rendered = args
+                .into_iter()
+                .map(render_arg)
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("\\{}\\left({}\\right)", name, rendered)
+        }
+        "argmax" | "argmin" => match call.first() {
+            Some(arg) => format!(
+                "\\operatorname*{{{}}}\\left({}\\right)",
+                name,
+                render_arg(arg)
+            ),
+            None => format!("\\operatorname*{{{}}}", name),
+        },
+        "abs" => render_wrapped(call, "\\left\\lvert ", " \\right\\rvert"),
+        "norm" => render_wrapped(call, "\\left\\lVert ", " \\right\\rVert"),
+        "ceil" => render_wrapped(call, "\\left\\lceil ", " \\right\\rceil"),
+        "floor" => render_wrapped(call, "\\left\\lfloor ", " \\right\\rfloor"),
+        _ => format!(
+            "\\{}{{{}}}",
+            name,
+            call.first().map(render_arg).unwrap_or_default()
+        ),
+    }
+}
+
+/// `attach(base, t: .., b: .., tl: .., bl: .., tr: .., br: ..)`. Typst puts
+/// `t` and `b` at the top and bottom right unless the base takes limits,
+/// which matches what LaTeX does with plain scripts; left scripts hang off
+/// an empty group in front of the base.
+fn render_attach(call: &Call) -> String {
+    let script = |name: &str| call.named_nodes(name).map(render);
+    let mut out = String::new();
+    let (tl, bl) = (script("tl"), script("bl"));
+    if tl.is_some() || bl.is_some() {
+        out.push_str("{}");
+        push_scripts(&mut out, bl, tl);
+    }
+    out.push_str(&call.first().map(render_arg).unwrap_or_default());
+    let (t, b, tr, br) = (script("t"), script("b"), script("tr"), script("br"));
+    if t.is_none() && b.is_none() {
+        push_scripts(&mut out, br, tr);
+    } else {
+        push_scripts(&mut out, b, t);
+        if tr.is_some() || br.is_some() {
+            out.push_str("{}");
+            push_scripts(&mut out, br, tr);
+        }
+    }
+    out
+}
+
+fn push_scripts(out: &mut String, sub: Option<String>, sup: Option<String>) {
+    if let Some(sub) = sub {
+        out.push_str("_{");
+        out.push_str(&sub);
+        out.push('}');
+    }
+    if let Some(sup) = sup {
+        out.push_str("^{");
+        out.push_str(&sup);
+        out.push('}');
+    }
+}
+
+/// A script wider than one token needs braces, unless it already is a
+/// `{...}` group or its braces do not balance, as in LaTeX passed through
+/// verbatim where adding a group would only break it further.
+fn needs_script_braces(latex: &str) -> bool {
+    if is_single_atom(latex) {
+        return false;
+    }
+    let mut depth = 0i32;
+    let mut escaped = false;
+    let mut one_group = latex.starts_with('{');
+    for (idx, ch) in latex.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth < 0 {
+                    return false;
+                }
+                if depth == 0 && idx + 1 < latex.len() {
+                    one_group = false;
+                }
+            }
+            _ => {}
+        }
+    }
+    depth == 0 && !escaped && !one_group
+}
+
+/// Whether LaTeX takes `latex` as a single script token: one character or
+/// one control word like `\alpha`.
+fn is_single_atom(latex: &str) -> bool {
+    let mut chars = latex.chars();
+    match chars.next() {
+        Some('\\') => {
+            let rest = chars.as_str();
+            !rest.is_empty() && rest.chars().all(|ch| ch.is_ascii_alphabetic())
+        }
+        Some(_) => chars.next().is_none(),
+        None => false,
+    }
+}
+
+fn render_arg(arg: &Arg) -> String {
+    match arg {
+        Arg::Positional { nodes, .. } => render(nodes),
+        Arg::Named { .. } => String::new(),
+    }
+}
+
+/// A string argument spelled out as text, or the argument's source.
+fn arg_text(arg: &Arg) -> String {
+    match arg {
+        Arg::Positional { nodes, source } => match nodes.as_slice() {
+            [MathNode::Str(text)] => text.clone(),
+            _ => source.trim().to_string(),
+        },
+        Arg::Named { .. } => String::new(),
+    }
+}
+
+/// Font and style calls read a string argument as math, so `bb("R")` and
+/// `bb(R)` agree.
+fn render_styled(arg: &Arg) -> String {
+    match arg {
+        Arg::Positional { nodes: inner, .. } => match inner.as_slice() {
+            [MathNode::Str(text)] => convert_math_content(text),
+            _ => render(inner),
+        },
+        Arg::Named { .. } => String::new(),
+    }
+}
+
+fn render_wrapped(call: &Call, left: &str, right: &str) -> String {
+    let inner = call.first().map(render_arg).unwrap_or_default();
+    format!("{}{}{}", left, inner, right)
+}
+
+fn render_cases(call: &Call) -> String {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut pending_expr: Option<String> = None;
+
+    for arg in call.positional() {
+        let Arg::Positional { nodes, .. } = arg else {
+            continue;
+        };
+        let cells = split_align_points(nodes);
+        if cells.len() > 1 && cells[0].iter().all(is_blank) {
+            // A leading `&` makes the row a condition for the pending expression.
+            let mut row: Vec<String> = cells[1..].iter().map(|cell| render_cell(cell)).collect();
+            row.insert(0, pending_expr.take().unwrap_or_default());
+            rows.push(row);
+            continue;
+        }
+        if let Some(expr) = pending_expr.take() {
+            rows.push(vec![expr]);
+        }
+        if cells.len() > 1 {
+            rows.push(cells.iter().map(|cell| render_cell(cell)).collect());
+            continue;
+        }
+        match condition_start(nodes) {
+            Some(idx) => rows.push(vec![render_cell(&nodes[..idx]), render_cell(&nodes[idx..])]),
+            None => pending_expr = Some(render_cell(nodes)),
+        }
+    }
+    if let Some(expr) = pending_expr {
+        rows.push(vec![expr]);
+    }
+
+    let body = rows
+        .iter()
+        .map(|cells| cells.join(" & ").trim_start().to_string())
+        .collect::<Vec<_>>()
+        .join(" \\\\ ");
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let (open, close) = cases_delimiters(call.named("delim"));
+    let reverse = call.named("reverse") == Some("true");
+
+    // amsmath `cases` only covers a left brace with two columns; everything
+    // else is spelled out as an array with the same column spacing.
+    if open == "\\{" && !reverse && columns <= 2 {
+        return format!("\\begin{{cases}}{}\\end{{cases}}", body);
+    }
+    let spec = vec!["l"; columns.max(1)].join("@{\\quad}");
+    let (left, right) = if reverse { (".", close) } else { (open, ".") };
+    format!(
+        "\\left{}\\begin{{array}}{{@{{}}{}@{{}}}}{}\\end{{array}}\\right{}",
+        left, spec, body, right
+    )
+}
+
+fn split_align_points(nodes: &[MathNode]) -> Vec<&[MathNode]> {
+    nodes.split(|node| *node == MathNode::AlignPoint).collect()
+}
+
+fn is_blank(node: &MathNode) -> bool {
+    matches!(node, MathNode::Text(text) if text.trim().is_empty())
+}
+
+fn render_cell(nodes: &[MathNode]) -> String {
+    render(nodes).trim().to_string()
+}
+
+/// Index of the `"if"`, `"else"` or `"otherwise"` string that opens the
+/// condition of a `cases` row written without an alignment point.
+fn condition_start(nodes: &[MathNode]) -> Option<usize> {
+    ["if", "else", "otherwise"].iter().find_map(|word| {
+        nodes
+            .iter()
+            .position(|node| matches!(node, MathNode::Str(text) if text == word))
+    })
+}
+
+/// Map a Typst `delim:` value to the opening and closing LaTeX delimiters.
+fn cases_delimiters(delim: Option<&str>) -> (&'static str, &'static str) {
+    let Some(raw) = delim else {
+        return ("\\{", "\\}");
+    };
+    let lower = raw.trim().trim_matches('"').to_lowercase();
+    if lower.contains("none") {
+        return (".", ".");
+    }
+    if lower.contains('[') || lower.contains(']') || lower.contains("bracket") {
+        return ("[", "]");
+    }
+    if lower.contains('(') || lower.contains(')') || lower.contains("paren") {
+        return ("(", ")");
+    }
+    if lower.contains("||") || lower.contains('‖') || lower.contains("double") {
+        return ("\\|", "\\|");
+    }
+    if lower.contains('|') || lower.contains("bar") {
+        return ("|", "|");
+    }
+    if lower.contains('⟨') || lower.contains("angle") {
+        return ("\\langle", "\\rangle");
+    }
+    ("\\{", "\\}")
+}
+
+fn render_mat(call: &Call) -> String {
+    let rows: Vec<String> = call
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .filter(|arg| matches!(arg, Arg::Positional { .. }))
+                .map(|arg| render_arg(arg).trim().to_string())
+                .collect::<Vec<_>>()
+        })
+        .filter(|cols| !cols.is_empty())
+        .map(|cols| cols.join(" & "))
+        .collect();
+    let env = mat_env_from_delim(call.named("delim"));
+    format!("\\begin{{{}}}{}\\end{{{}}}", env, rows.join(" \\\\ "), env)
+}
+
+fn mat_env_from_delim(delim: Option<&str>) -> &'static str {
+    let Some(raw) = delim else {
+        return "pmatrix";
+    };
+    let lower = raw.trim().trim_matches('"').to_lowercase();
+    if lower.contains("none") {
+        return "matrix";
+    }
+    if lower.contains('[') || lower.contains("bracket") {
+        return "bmatrix";
+    }
+    if lower.contains('{') || lower.contains("brace") {
+        return "Bmatrix";
+    }
+    if lower.contains("||") || lower.contains('‖') || lower.contains("double") {
+        return "Vmatrix";
+    }
+    if lower.contains('|') || lower.contains("bar") {
+        return "vmatrix";
+    }
+    if lower.contains('(') || lower.contains("paren") {
+        return "pmatrix";
+    }
+    "pmatrix"
+}
+
+/// Join specific spaced letter patterns that are known math identifiers
+/// Only joins patterns that match known words to avoid breaking valid spacing
+fn join_spaced_letters(input: &str) -> String {
+    // Known multi-letter identifiers that get spaced in Typst math mode
+    const KNOWN_WORDS: &[(&str, &str)] = &[
+        ("m a x", "max"),
+        ("m i n", "min"),
+        ("l e n", "len"),
+        ("l o g", "log"),
+        ("e x p", "exp"),
+        ("s i n", "sin"),
+        ("c o s", "cos"),
+        ("t a n", "tan"),
+        ("l i m", "lim"),
+        ("s u p", "sup"),
+        ("i n f", "inf"),
+        ("d e t", "det"),
+        ("d i m", "dim"),
+        ("k e r", "ker"),
+        ("g c d", "gcd"),
+        ("l c m", "lcm"),
+        ("a r g", "arg"),
+        ("d e g", "deg"),
+        ("h o m", "hom"),
+        ("m o d", "mod"),
+        ("s e c", "sec"),
+        ("c s c", "csc"),
+        ("c o t", "cot"),
+    ];
+
+    let mut result = input.to_string();
+    for (spaced, joined) in KNOWN_WORDS {
+        if result.contains(spaced) {
+            result = result.replace(spaced, joined);
+        }
+    }
+    result
+}
+
+fn replace_inline_setminus(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = String::new();
+    let mut i = 0usize;
+    while i < bytes.len() {
+        if i + 1 < bytes.len() && bytes[i] == b'\\' && bytes[i + 1] == b'\\' {
+            let prev = input[..i].chars().rev().find(|c| !c.is_whitespace());
+            let next = input[i + 2..].chars().find(|c| !c.is_whitespace());
+            let prev_is_word = prev.is_some_and(|c| c.is_alphanumeric() || c == '}' || c == ')');
+            let next_is_word = next.is_some_and(|c| c.is_alphanumeric() || c == '{' || c == '(');
+            if prev_is_word && next_is_word {
+                out.push_str("\\setminus");
+                i += 2;
+                continue;
+            }
+        }
+        out.push(bytes[i] as char);
+        i += 1;
+    }
+    out
+}
+
+/// Unescape Typst string escape sequences back to their original characters.
+/// Handles: \@ → @, \_ → _, \# → #, \$ → $, \* → *, \` → `, \< → <, \> → >,
+///          \\ → \, \" → ", \n → newline, \t → tab
+fn unescape_typst_string(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        let unescaped = match chars.peek() {
+            Some(&c @ ('@' | '_' | '#' | '$' | '*' | '`' | '<' | '>' | '\\' | '"')) => c,
+            Some('n') => '\n',
+            Some('t') => '\t',
+            _ => {
+                out.push(ch);
+                continue;
+            }
+        };
+        chars.next();
+        out.push(unescaped);
+    }
+    out
+}
+
+fn lookup_symbol(name: &str) -> Option<&'static str> {
+    const MAP: &[(&str, &str)] = &[
+        ("square.stroked", "\\square"),
+        ("sym.square.stroked", "\\square"),
+        ("infinity", "\\infty"),
+        ("oo", "\\infty"),
+        ("dif", "\\mathrm{d}"),
+        ("varepsilon", "\\varepsilon"),
+        ("vartheta", "\\vartheta"),
+        ("varsigma", "\\varsigma"),
+        ("varphi", "\\varphi"),
+        ("varrho", "\\varrho"),
+        ("varpi", "\\varpi"),
+        ("epsilon", "\\epsilon"),
+        ("theta", "\\theta"),
+        ("sigma", "\\sigma"),
+        ("phi", "\\phi"),
+        ("rho", "\\rho"),
+        ("pi", "\\pi"),
+        ("alpha", "\\alpha"),
+        ("beta", "\\beta"),
+        ("gamma", "\\gamma"),
+        ("delta", "\\delta"),
+        ("zeta", "\\zeta"),
+        ("eta", "\\eta"),
+        ("iota", "\\iota"),
+        ("kappa", "\\kappa"),
+        ("lambda", "\\lambda"),
+        ("mu", "\\mu"),
+        ("nu", "\\nu"),
+        ("xi", "\\xi"),
+        ("tau", "\\tau"),
+        ("upsilon", "\\upsilon"),
+        ("chi", "\\chi"),
+        ("psi", "\\psi"),
+        ("omega", "\\omega"),
+        ("Gamma", "\\Gamma"),
+        ("Delta", "\\Delta"),
+        ("Theta", "\\Theta"),
+        ("Lambda", "\\Lambda"),
+        ("Xi", "\\Xi"),
+        ("Pi", "\\Pi"),
+        ("Sigma", "\\Sigma"),
+        ("Upsilon", "\\Upsilon"),
+        ("Phi", "\\Phi"),
+        ("Psi", "\\Psi"),
+        ("Omega", "\\Omega"),
+        ("argmax", "\\operatorname*{argmax}"),
+        ("argmin", "\\operatorname*{argmin}"),
+        ("supseteq", "\\supseteq"),
+        ("subseteq", "\\subseteq"),
+        ("supset", "\\supset"),
+        ("subset", "\\subset"),
+        ("notin", "\\notin"),
+        ("infty", "\\infty"),
+        ("forall", "\\forall"),
+        ("exists", "\\exists"),
+        ("partial", "\\partial"),
+        ("nabla", "\\nabla"),
+        ("implies", "\\implies"),
+        ("iff", "\\iff"),
+        ("approx", "\\approx"),
+        ("sim", "\\sim"),
+        ("tilde.op", "\\sim"),
+        ("tilde.eq", "\\simeq"),
+        ("tilde.equiv", "\\cong"),
+        ("integral", "\\int"),
+        ("prod", "\\prod"),
+        ("sum", "\\sum"),
+        ("lim", "\\lim"),
+        ("max", "\\max"),
+        ("min", "\\min"),
+        ("sup", "\\sup"),
+        ("inf", "\\inf"),
+        ("int", "\\int"),
+        ("in", "\\in"),
+        ("RR", "\\mathbb{R}"),
+        ("NN", "\\mathbb{N}"),
+        ("ZZ", "\\mathbb{Z}"),
+        ("QQ", "\\mathbb{Q}"),
+        ("CC", "\\mathbb{C}"),
+        ("HH", "\\mathbb{H}"),
+        ("FF", "\\mathbb{F}"),
+        ("EE", "\\mathbb{E}"),
+        ("PP", "\\mathbb{P}"),
+    ];
+    MAP.iter()
+        .find(|(token, _)| *token == name)
+        .map(|(_, latex)| *latex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_calls_convert_inside_out() {
+        assert_eq!(
+            convert_math_content("frac(abs(x), norm(y)+1)"),
+            "\\frac{\\left\\lvert x \\right\\rvert}{\\left\\lVert y \\right\\rVert+1}"
+        );
+        assert_eq!(
+            convert_math_content("sqrt(frac(a, \"b, c\"))"),
+            "\\sqrt{\\frac{a}{\\text{b, c}}}"
+        );
+        assert_eq!(
+            convert_math_content("sum_(i=1)^(n-1) x_i"),
+            "\\sum_{i=1}^{n-1} x_i"
+        );
+    }
+
+    #[test]
+    fn scripts_wider_than_one_token_are_braced() {
+        assert_eq!(convert_math_content("x^(a+b)_(i j)"), "x^{a+b}_{i j}");
+        assert_eq!(convert_math_content("10^18 x_alpha"), "10^{18} x_\\alpha");
+        assert_eq!(
+            convert_math_content("a^\"ab\" e^{2}"),
+            "a^{\\text{ab}} e^{2}"
+        );
+    }
+
+    #[test]
+    fn attach_maps_to_scripts() {
+        assert_eq!(convert_math_content("attach(x, t: a, b: b)"), "x_{b}^{a}");
+        assert_eq!(
+            convert_math_content("attach(sum, tl: 1, bl: 2, tr: 3, br: 4)"),
+            "{}_{2}^{1}\\sum_{4}^{3}"
+        );
+        assert_eq!(convert_math_content("attach(A, t: n+1)"), "A^{n+1}");
+    }
+
+    #[test]
+    fn lr_and_bar_pairs_scale_their_delimiters() {
+        assert_eq!(
+            convert_math_content("lr((a + b))"),
+            "\\left( a + b \\right)"
+        );
+        assert_eq!(
+            convert_math_content("lr([x], size: #150%)"),
+            "\\left[ x \\right]"
+        );
+        assert_eq!(
+            convert_math_content("lr(angle.l x angle.r)"),
+            "\\left\\langle x \\right\\rangle"
+        );
+        assert_eq!(convert_math_content("lr(\\{ x)"), "\\left\\{ x \\right.");
+        assert_eq!(
+            convert_math_content("|x - y|^2 <= ||v||"),
+            "\\left\\lvert x - y \\right\\rvert^2 \\le \\left\\lVert v \\right\\rVert"
+        );
+        assert_eq!(convert_math_content("P(A | B)"), "P(A | B)");
+    }
+
+    #[test]
+    fn row_breaks_end_rows() {
+        assert_eq!(
+            convert_math_rows("a &= b \\ c &= d"),
+            (
+                "a &= b \\\\ c &= d".to_string(),
+                MathRows {
+                    align_points: true,
+                    line_breaks: true,
+                }
+            )
+        );
+        assert_eq!(
+            convert_math_rows("x \\ y"),
+            (
+                "x \\\\ y".to_string(),
+                MathRows {
+                    align_points: false,
+                    line_breaks: true,
+                }
+            )
+        );
+        let (_, rows) = convert_math_rows("cases(1 & x, 0 & y) \"&\"");
+        assert_eq!(rows, MathRows::default());
+    }
+
+    #[test]
+    fn names_glued_to_words_stay_text() {
+        assert_eq!(convert_math_content("2pi r"), "2pi r");
+        assert_eq!(convert_math_content("\\beta + beta"), "\\beta + \\beta");
+    }
+
+    #[test]
+    fn deep_nesting_falls_back_to_source() {
+        let parens = format!("{}x{}", "(".repeat(10_000), ")".repeat(10_000));
+        assert_eq!(convert_math_content(&parens), parens);
+        let scripts = vec!["x"; 10_000].join("^");
+        assert_eq!(convert_math_content(&scripts), scripts);
+        let calls = format!("{}x{}", "abs(".repeat(40), ")".repeat(40));
+        assert!(convert_math_content(&calls).starts_with("\\left\\lvert \\left\\lvert"));
+    }
+
+    #[test]
+    fn cases_keep_alignment_columns() {
+        assert_eq!(
+            convert_math_content(r#"cases(1 "if" x < 0, 0 "else")"#),
+            "\\begin{cases}1 & \\text{if} x < 0 \\\\ 0 & \\text{else}\\end{cases}"
+        );
+        assert_eq!(
+            convert_math_content(r#"cases(x & "if" y, z & "else")"#),
+            "\\begin{cases}x & \\text{if} y \\\\ z & \\text{else}\\end{cases}"
+        );
+        assert_eq!(
+            convert_math_content(r#"cases(a & b & "m", c & d & "s")"#),
+            "\\left\\{\\begin{array}{@{}l@{\\quad}l@{\\quad}l@{}}a & b & \\text{m} \\\\ c & d & \\text{s}\\end{array}\\right."
+        );
+    }
+
+    #[test]
+    fn cases_honor_delim_and_reverse() {
+        assert_eq!(
+            convert_math_content(r#"cases(delim: "[", x & "if" y, z)"#),
+            "\\left[\\begin{array}{@{}l@{\\quad}l@{}}x & \\text{if} y \\\\ z\\end{array}\\right."
+        );
+        assert_eq!(
+            convert_math_content("cases(reverse: true, x, y)"),
+            "\\left.\\begin{array}{@{}l@{}}x \\\\ y\\end{array}\\right\\}"
+        );
+        assert_eq!(
+            convert_math_content(r#"cases(delim: "(", reverse: true, x & y)"#),
+            "\\left.\\begin{array}{@{}l@{\\quad}l@{}}x & y\\end{array}\\right)"
+        );
+    }
+}
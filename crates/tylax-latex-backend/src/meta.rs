@@ -0,0 +1,550 @@
+//! Preamble lines for the document-wide settings of [`DocumentMeta`]: the
+//! class font size, `geometry`, `babel`, justification and the heading
+//! numbering pattern, the `fancyhdr` page style of its header, footer and
+//! page numbering, and the title block its front matter sets.
+
+use tylax_ir::{Author, DocumentMeta, Inline, PageDecoration};
+
+use super::{
+    escape_latex, escape_text, push_begin, push_end, render_blocks_separated, render_inlines,
+    DocumentTemplate, LatexRenderOptions,
+};
+
+/// The `\documentclass` line of `template`, with the options of
+/// [`class_options`]. Not used for a custom preamble.
+pub(crate) fn document_class(meta: &DocumentMeta, template: &DocumentTemplate) -> String {
+    let class = match template {
+        DocumentTemplate::Report => "report",
+        DocumentTemplate::Book => "book",
+        DocumentTemplate::IeeeTran => "IEEEtran",
+        DocumentTemplate::Acm => "acmart",
+        DocumentTemplate::Beamer => "beamer",
+        DocumentTemplate::Article | DocumentTemplate::Custom { .. } => "article",
+    };
+    let class_options = class_options(meta, template);
+    if class_options.is_empty() {
+        format!("\\documentclass{{{}}}\n", class)
+    } else {
+        format!(
+            "\\documentclass[{}]{{{}}}\n",
+            class_options.join(","),
+            class
+        )
+    }
+}
+
+/// The class options of `template` for `meta`: the font size when the
+/// class offers it and `twocolumn` for a document set in two columns. A
+/// custom preamble counts as `article`.
+pub fn class_options(meta: &DocumentMeta, template: &DocumentTemplate) -> Vec<&'static str> {
+    let mut class_options = match template {
+        DocumentTemplate::IeeeTran => vec!["conference"],
+        DocumentTemplate::Acm => vec!["sigconf"],
+        _ => Vec::new(),
+    };
+    if *template != DocumentTemplate::Acm {
+        class_options.extend(meta.font_size.as_deref().and_then(class_font_size));
+    }
+    // IEEEtran and acmart set two columns already, beamer none.
+    let plain_class = matches!(
+        template,
+        DocumentTemplate::Article
+            | DocumentTemplate::Report
+            | DocumentTemplate::Book
+            | DocumentTemplate::Custom { .. }
+    );
+    if plain_class && meta.columns == Some(2) {
+        class_options.push("twocolumn");
+    }
+    // Headers and footers that alternate need the pages set two-sided;
+    // books are already.
+    let alternates = [&meta.header, &meta.footer]
+        .into_iter()
+        .flatten()
+        .any(|decoration| decoration.even.is_some());
+    if alternates && plain_class && *template != DocumentTemplate::Book {
+        class_options.push("twoside");
+    }
+    class_options
+}
+
+/// Packages and settings for the rest of `meta`: the font size the class
+/// does not offer, `geometry`, `babel`, justification and the heading
+/// numbering pattern. Classes with a layout of their own keep it: they get
+/// no geometry, and slides no numbering.
+pub fn push_meta_preamble(out: &mut String, meta: &DocumentMeta, template: &DocumentTemplate) {
+    let own_layout = matches!(
+        template,
+        DocumentTemplate::IeeeTran | DocumentTemplate::Acm | DocumentTemplate::Beamer
+    );
+    if let Some(size) = meta.font_size.as_deref() {
+        if class_font_size(size).is_none() && !own_layout {
+            out.push_str(&format!("\\usepackage[fontsize={}]{{scrextend}}\n", size));
+        }
+    }
+    let mut geometry = Vec::new();
+    if let Some(paper) = meta.paper.as_deref().and_then(paper_option) {
+        geometry.push(paper.to_string());
+    }
+    let margin = &meta.margin;
+    let sides = [
+        ("top", &margin.top),
+        ("bottom", &margin.bottom),
+        ("left", &margin.left),
+        ("right", &margin.right),
+    ];
+    if sides
+        .iter()
+        .all(|(_, side)| side.is_some() && *side == &margin.top)
+    {
+        geometry.push(format!(
+            "margin={}",
+            margin.top.as_deref().unwrap_or_default()
+        ));
+    } else {
+        for (name, side) in sides {
+            if let Some(length) = side {
+                geometry.push(format!("{}={}", name, length));
+            }
+        }
+    }
+    if !geometry.is_empty() && !own_layout {
+        out.push_str(&format!(
+            "\\usepackage[{}]{{geometry}}\n",
+            geometry.join(",")
+        ));
+    }
+    if let Some(language) = meta.lang.as_deref().and_then(babel_language) {
+        out.push_str(&format!("\\usepackage[{}]{{babel}}\n", language));
+    }
+    if meta.justify == Some(false) {
+        out.push_str("\\usepackage[document]{ragged2e}\n");
+    }
+    if *template == DocumentTemplate::Beamer {
+        return;
+    }
+    match meta.heading_numbering.as_deref() {
+        Some("") => out.push_str("\\setcounter{secnumdepth}{0}\n"),
+        Some(pattern) => out.push_str(&heading_numbering(pattern, template)),
+        None => {}
+    }
+}
+
+/// Whether the classes of `template` keep their own page style.
+fn keeps_page_style(template: &DocumentTemplate) -> bool {
+    matches!(
+        template,
+        DocumentTemplate::IeeeTran | DocumentTemplate::Acm | DocumentTemplate::Beamer
+    )
+}
+
+/// Every inline of the header and footer of `meta`, on odd and even pages.
+fn decoration_inlines(meta: &DocumentMeta) -> Vec<&Inline> {
+    let mut inlines = Vec::new();
+    let mut pending: Vec<&PageDecoration> = meta.header.iter().chain(&meta.footer).collect();
+    while let Some(decoration) = pending.pop() {
+        inlines.extend(
+            decoration
+                .left
+                .iter()
+                .chain(&decoration.center)
+                .chain(&decoration.right),
+        );
+        pending.extend(decoration.even.as_deref());
+    }
+    inlines
+}
+
+/// Whether the output shows the number of pages, which `\pageref{LastPage}`
+/// takes from the `lastpage` package: in the page style of `meta` under
+/// `template`, or in a page number of the text.
+pub(crate) fn shows_page_count(
+    meta: &DocumentMeta,
+    inlines: &[&Inline],
+    template: &DocumentTemplate,
+) -> bool {
+    let page_style = if keeps_page_style(template) {
+        Vec::new()
+    } else {
+        decoration_inlines(meta)
+    };
+    let numbering_counts = meta
+        .page_numbering
+        .as_deref()
+        .is_some_and(|pattern| counting_symbols(pattern) > 1);
+    (numbering_counts && !keeps_page_style(template))
+        || page_style.into_iter().any(counts_pages)
+        || inlines.iter().copied().any(counts_pages)
+}
+
+/// Whether `inline` is a page number that shows the number of pages.
+fn counts_pages(inline: &Inline) -> bool {
+    matches!(inline, Inline::PageNumber(pattern) if counting_symbols(pattern) > 1)
+}
+
+/// The page style of the header, footer and page numbering, in `fancyhdr`
+/// slots. Pages numbered without a footer of their own have the number
+/// centered at the foot, as in Typst, and running heads show the current
+/// headings through the marks of the sectioning commands. Classes with a
+/// layout of their own keep their page style.
+pub fn push_page_style(out: &mut String, meta: &DocumentMeta, options: &LatexRenderOptions) {
+    if keeps_page_style(&options.template) {
+        return;
+    }
+    let numbering = meta.page_numbering.as_deref();
+    let decorations = [("fancyhead", &meta.header), ("fancyfoot", &meta.footer)];
+    let style = numbering.and_then(|pattern| pattern.chars().find_map(counting_command));
+    if let Some(style) = style.filter(|&style| style != "arabic") {
+        out.push_str(&format!("\\pagenumbering{{{}}}\n", style));
+    }
+    if meta.header.is_none() && meta.footer.is_none() {
+        match numbering {
+            None => return,
+            Some("") => {
+                out.push_str("\\pagestyle{empty}\n");
+                return;
+            }
+            // A bare counting symbol is LaTeX's own page number.
+            Some(pattern) if pattern.trim().chars().count() == 1 && style.is_some() => return,
+            Some(_) => {}
+        }
+    }
+    out.push_str("\\usepackage{fancyhdr}\n");
+    out.push_str("\\pagestyle{fancy}\n");
+    out.push_str("\\fancyhf{}\n");
+    out.push_str("\\renewcommand{\\headrulewidth}{0pt}\n");
+    let running_heads = decoration_inlines(meta)
+        .into_iter()
+        .any(|inline| matches!(inline, Inline::RunningHead(_)));
+    if running_heads {
+        // The first two levels of headings set the left and right marks.
+        let sections = options.template.sections();
+        if let [(first, _), (second, _), ..] = sections {
+            out.push_str(&format!(
+                "\\renewcommand{{\\{}mark}}[1]{{\\markboth{{#1}}{{}}}}\n",
+                first
+            ));
+            out.push_str(&format!(
+                "\\renewcommand{{\\{}mark}}[1]{{\\markright{{#1}}}}\n",
+                second
+            ));
+        }
+    }
+    for (command, decoration) in decorations {
+        if let Some(decoration) = decoration {
+            push_fancy_slots(out, command, decoration, options);
+        }
+    }
+    if let (None, Some(pattern)) = (&meta.footer, numbering.filter(|p| !p.is_empty())) {
+        out.push_str(&format!("\\fancyfoot[C]{{{}}}\n", page_number(pattern)));
+    }
+}
+
+/// The slots of a header or footer, for odd and even pages apart when it
+/// alternates.
+fn push_fancy_slots(
+    out: &mut String,
+    command: &str,
+    decoration: &PageDecoration,
+    options: &LatexRenderOptions,
+) {
+    let pages = match decoration.even.as_deref() {
+        Some(even) => vec![(decoration, "O"), (even, "E")],
+        None => vec![(decoration, "")],
+    };
+    for (decoration, page) in pages {
+        for (slot, content) in [
+            ("L", &decoration.left),
+            ("C", &decoration.center),
+            ("R", &decoration.right),
+        ] {
+            let content = render_inlines(content, options);
+            if !content.trim().is_empty() {
+                out.push_str(&format!(
+                    "\\{}[{}{}]{{{}}}\n",
+                    command,
+                    slot,
+                    page,
+                    content.trim()
+                ));
+            }
+        }
+    }
+}
+
+/// The mark of a running head of `level`: the first level sets the left
+/// mark, deeper ones the right.
+pub(crate) fn running_head(level: u8) -> &'static str {
+    if level <= 1 {
+        "\\leftmark{}"
+    } else {
+        "\\rightmark{}"
+    }
+}
+
+/// A page number after a Typst numbering pattern: the first counting
+/// symbol stands for the page, the second for the number of pages.
+pub(crate) fn page_number(pattern: &str) -> String {
+    let mut out = String::new();
+    let mut text = String::new();
+    let mut symbols = 0;
+    for ch in pattern.chars() {
+        let Some(command) = counting_command(ch) else {
+            text.push(ch);
+            continue;
+        };
+        out.push_str(&escape_latex(&std::mem::take(&mut text)));
+        symbols += 1;
+        match symbols {
+            1 => out.push_str(&format!("\\{}{{page}}", command)),
+            2 => out.push_str("\\pageref{LastPage}"),
+            _ => {}
+        }
+    }
+    out.push_str(&escape_latex(&text));
+    out
+}
+
+fn counting_symbols(pattern: &str) -> usize {
+    pattern
+        .chars()
+        .filter(|&ch| counting_command(ch).is_some())
+        .count()
+}
+
+/// The LaTeX counter style of a counting symbol of a numbering pattern.
+fn counting_command(symbol: char) -> Option<&'static str> {
+    match symbol {
+        '1' => Some("arabic"),
+        'a' => Some("alph"),
+        'A' => Some("Alph"),
+        'i' => Some("roman"),
+        'I' => Some("Roman"),
+        _ => None,
+    }
+}
+
+/// The title block with the authors and date, then the abstract, at the
+/// start of the document body, in the form the class expects. Nothing
+/// without a title or abstract.
+pub(crate) fn push_front_matter(
+    out: &mut String,
+    meta: &DocumentMeta,
+    options: &LatexRenderOptions,
+) {
+    let template = &options.template;
+    let abstract_body = render_blocks_separated(&meta.abstract_content, options);
+    let mut abstract_body = (!abstract_body.trim().is_empty()).then_some(abstract_body);
+    if let Some(title) = &meta.title {
+        out.push_str(&format!("\\title{{{}}}\n", render_inlines(title, options)));
+        push_authors(out, &meta.authors, options);
+        // Without a date LaTeX would print the day of the build.
+        let date = meta.date.as_deref().map(escape_latex).unwrap_or_default();
+        out.push_str(&format!("\\date{{{}}}\n", date));
+        if *template == DocumentTemplate::Acm {
+            // acmart sets the abstract as part of the title block.
+            if let Some(body) = abstract_body.take() {
+                push_abstract(out, &body, template);
+            }
+        }
+        if *template == DocumentTemplate::Beamer {
+            out.push_str("\\begin{frame}\n\\titlepage\n\\end{frame}\n\n");
+        } else {
+            out.push_str("\\maketitle\n\n");
+        }
+    }
+    if let Some(body) = abstract_body {
+        push_abstract(out, &body, template);
+    }
+}
+
+fn push_authors(out: &mut String, authors: &[Author], options: &LatexRenderOptions) {
+    let text = |text: &str| escape_text(text, options);
+    let email = |author: &Author| author.email.as_deref().map(escape_latex);
+    match &options.template {
+        DocumentTemplate::Acm => {
+            for author in authors {
+                out.push_str(&format!("\\author{{{}}}\n", text(&author.name)));
+                if let Some(affiliation) = &author.affiliation {
+                    out.push_str(&format!(
+                        "\\affiliation{{\\institution{{{}}}}}\n",
+                        text(affiliation)
+                    ));
+                }
+                if let Some(email) = email(author) {
+                    out.push_str(&format!("\\email{{{}}}\n", email));
+                }
+            }
+        }
+        DocumentTemplate::IeeeTran => {
+            let blocks: Vec<String> = authors
+                .iter()
+                .map(|author| {
+                    let mut block = format!("\\IEEEauthorblockN{{{}}}", text(&author.name));
+                    let lines: Vec<String> = author
+                        .affiliation
+                        .as_deref()
+                        .map(text)
+                        .into_iter()
+                        .chain(email(author).map(|email| format!("\\texttt{{{}}}", email)))
+                        .collect();
+                    if !lines.is_empty() {
+                        block
+                            .push_str(&format!("\n\\IEEEauthorblockA{{{}}}", lines.join(" \\\\ ")));
+                    }
+                    block
+                })
+                .collect();
+            out.push_str(&format!("\\author{{{}}}\n", blocks.join("\n\\and\n")));
+        }
+        DocumentTemplate::Beamer => {
+            let names: Vec<String> = authors
+                .iter()
+                .map(|author| text(&author.name))
+                .collect();
+            out.push_str(&format!("\\author{{{}}}\n", names.join(" \\and ")));
+            let affiliations: Vec<String> = authors
+                .iter()
+                .filter_map(|author| author.affiliation.as_deref().map(text))
+                .collect();
+            if !affiliations.is_empty() {
+                out.push_str(&format!(
+                    "\\institute{{{}}}\n",
+                    affiliations.join(" \\and ")
+                ));
+            }
+        }
+        _ => {
+            let authors: Vec<String> = authors
+                .iter()
+                .map(|author| {
+                    let mut lines = vec![text(&author.name)];
+                    lines.extend(author.affiliation.as_deref().map(text));
+                    lines.extend(email(author).map(|email| format!("\\texttt{{{}}}", email)));
+                    lines.join(" \\\\ ")
+                })
+                .collect();
+            out.push_str(&format!("\\author{{{}}}\n", authors.join(" \\and ")));
+        }
+    }
+}
+
+/// The abstract, or a section titled Abstract in a class without the
+/// environment.
+fn push_abstract(out: &mut String, body: &str, template: &DocumentTemplate) {
+    match template {
+        DocumentTemplate::Beamer => {
+            out.push_str("\\begin{frame}{Abstract}\n");
+            out.push_str(body);
+            out.push_str("\n\\end{frame}");
+        }
+        DocumentTemplate::Book => {
+            out.push_str("\\chapter*{Abstract}\n\n");
+            out.push_str(body);
+        }
+        _ => {
+            push_begin(out, "abstract");
+            out.push_str(body);
+            out.push('\n');
+            push_end(out, "abstract");
+        }
+    }
+    out.push_str("\n\n");
+}
+
+/// Sizes `article` takes as a class option.
+fn class_font_size(size: &str) -> Option<&'static str> {
+    match size.trim() {
+        "10pt" => Some("10pt"),
+        "11pt" => Some("11pt"),
+        "12pt" => Some("12pt"),
+        _ => None,
+    }
+}
+
+/// geometry's name for a Typst paper size.
+fn paper_option(paper: &str) -> Option<&'static str> {
+    match paper.trim() {
+        "a3" => Some("a3paper"),
+        "a4" => Some("a4paper"),
+        "a5" => Some("a5paper"),
+        "a6" => Some("a6paper"),
+        "b5" => Some("b5paper"),
+        "us-letter" => Some("letterpaper"),
+        "us-legal" => Some("legalpaper"),
+        "us-executive" => Some("executivepaper"),
+        _ => None,
+    }
+}
+
+/// babel's name for an ISO 639-1 language code.
+fn babel_language(lang: &str) -> Option<&'static str> {
+    let language = match lang.trim().to_ascii_lowercase().as_str() {
+        "en" => "english",
+        "de" => "ngerman",
+        "fr" => "french",
+        "es" => "spanish",
+        "it" => "italian",
+        "pt" => "portuguese",
+        "nl" => "dutch",
+        "sv" => "swedish",
+        "da" => "danish",
+        "nb" | "no" => "norsk",
+        "fi" => "finnish",
+        "pl" => "polish",
+        "cs" => "czech",
+        "ru" => "russian",
+        "uk" => "ukrainian",
+        "el" => "greek",
+        "tr" => "turkish",
+        "hu" => "magyar",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// `\the<counter>` definitions that number headings after a Typst pattern
+/// such as "1.a" or "I.1". Each level takes the next counting symbol, and
+/// the last one repeats; text around the symbols is left to LaTeX.
+fn heading_numbering(pattern: &str, template: &DocumentTemplate) -> String {
+    let mut symbols = Vec::new();
+    let mut separators = Vec::new();
+    let mut pending = String::new();
+    for ch in pattern.chars() {
+        let Some(command) = counting_command(ch) else {
+            pending.push(ch);
+            continue;
+        };
+        if !symbols.is_empty() {
+            separators.push(std::mem::take(&mut pending));
+        }
+        pending.clear();
+        symbols.push(command);
+    }
+    let Some(&last) = symbols.last() else {
+        return String::new();
+    };
+    let last_separator = separators
+        .last()
+        .cloned()
+        .unwrap_or_else(|| ".".to_string());
+    if symbols.iter().all(|&symbol| symbol == "arabic")
+        && separators.iter().all(|separator| separator == ".")
+    {
+        // LaTeX's own numbering.
+        return String::new();
+    }
+
+    let counters: Vec<&str> = template.sections().iter().map(|&(name, _)| name).collect();
+    let mut out = String::new();
+    for (level, counter) in counters.iter().enumerate() {
+        let symbol = symbols.get(level).copied().unwrap_or(last);
+        out.push_str(&format!("\\renewcommand{{\\the{}}}{{", counter));
+        if level > 0 {
+            let separator = separators.get(level - 1).unwrap_or(&last_separator);
+            out.push_str(&format!("\\the{}{}", counters[level - 1], separator));
+        }
+        out.push_str(&format!("\\{}{{{}}}}}\n", symbol, counter));
+    }
+    out
+}
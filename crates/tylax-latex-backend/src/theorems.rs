@@ -0,0 +1,143 @@
+//! `\newtheorem` declarations for the theorem-like environments a document uses.
+
+use tylax_ir::{Block, Document, FigureContent};
+
+/// How generated theorem environments share and reset their counters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TheoremCounter {
+    /// Every environment counts on the counter of the first one declared.
+    Shared,
+    /// Each environment keeps its own counter.
+    PerEnvironment,
+    /// Declare starred, unnumbered environments.
+    Unnumbered,
+}
+
+/// Numbering scheme for the `\newtheorem` lines emitted in full-document mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TheoremNumbering {
+    pub counter: TheoremCounter,
+    /// Counter that resets theorem numbers, e.g. `section`.
+    pub within: Option<String>,
+}
+
+impl Default for TheoremNumbering {
+    fn default() -> Self {
+        Self {
+            counter: TheoremCounter::Shared,
+            within: None,
+        }
+    }
+}
+
+/// amsthm styles in the order their declarations have to appear.
+const STYLES: [&str; 3] = ["plain", "definition", "remark"];
+
+/// Render `\theoremstyle` and `\newtheorem` lines for every theorem-like
+/// environment in `doc`. Returns an empty string when there is none.
+pub fn render_theorem_definitions(doc: &Document, numbering: &TheoremNumbering) -> String {
+    let mut names = theorem_environments(doc);
+    if names.is_empty() {
+        return String::new();
+    }
+    // The counter owner is declared first so the others can refer to it.
+    names.sort_by_key(|name| (style_rank(name), name != "theorem"));
+
+    let mut out = String::new();
+    let mut current_style = None;
+    let lead = names[0].clone();
+    for name in &names {
+        let style = style_rank(name);
+        if current_style != Some(style) {
+            out.push_str("\\theoremstyle{");
+            out.push_str(STYLES[style]);
+            out.push_str("}\n");
+            current_style = Some(style);
+        }
+        let title = display_name(name);
+        let within = numbering.within.as_deref().filter(|w| !w.trim().is_empty());
+        match numbering.counter {
+            TheoremCounter::Unnumbered => {
+                out.push_str(&format!("\\newtheorem*{{{}}}{{{}}}\n", name, title));
+            }
+            TheoremCounter::Shared if *name != lead => {
+                out.push_str(&format!(
+                    "\\newtheorem{{{}}}[{}]{{{}}}\n",
+                    name, lead, title
+                ));
+            }
+            _ => match within {
+                Some(within) => out.push_str(&format!(
+                    "\\newtheorem{{{}}}{{{}}}[{}]\n",
+                    name, title, within
+                )),
+                None => out.push_str(&format!("\\newtheorem{{{}}}{{{}}}\n", name, title)),
+            },
+        }
+    }
+    out
+}
+
+/// Sanitized names of the environments that need a declaration, in order of
+/// first use. `proof` is provided by amsthm itself.
+fn theorem_environments(doc: &Document) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut stack: Vec<&Block> = doc.blocks.iter().rev().collect();
+    while let Some(block) = stack.pop() {
+        let children: Vec<&Block> = match block {
+            Block::Environment(env) => {
+                let name = crate::sanitize_env_name(&env.name);
+                if name != "proof" && !names.contains(&name) {
+                    names.push(name);
+                }
+                env.blocks.iter().collect()
+            }
+            Block::List { items, .. } => items.iter().flatten().collect(),
+            Block::TermList(items) => items.iter().flat_map(|item| &item.description).collect(),
+            Block::Quote(blocks) | Block::Align { blocks, .. } => blocks.iter().collect(),
+            Block::Box(inner) => inner.blocks.iter().collect(),
+            Block::Block(inner) => inner.blocks.iter().collect(),
+            Block::Columns(columns) => columns.blocks.iter().collect(),
+            Block::Grid(grid) => grid.cells.iter().flatten().collect(),
+            Block::Slide(slide) => slide.blocks.iter().collect(),
+            Block::SideBySide(side) => side.columns.iter().flatten().collect(),
+            Block::Table(table) => table
+                .cells
+                .iter()
+                .filter_map(|cell| cell.blocks.as_ref())
+                .flatten()
+                .collect(),
+            Block::Figure(figure) => match &figure.content {
+                FigureContent::Raw(blocks) => blocks.iter().collect(),
+                FigureContent::Table(table) => table
+                    .cells
+                    .iter()
+                    .filter_map(|cell| cell.blocks.as_ref())
+                    .flatten()
+                    .collect(),
+                FigureContent::Image(_) | FigureContent::SubFigures { .. } => Vec::new(),
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+        stack.extend(children.into_iter().rev());
+    }
+    names
+}
+
+fn style_rank(name: &str) -> usize {
+    match name {
+        "definition" | "example" | "exercise" | "problem" => 1,
+        "remark" | "note" | "notation" => 2,
+        _ => 0,
+    }
+}
+
+fn display_name(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,206 @@
+//! Characters pdflatex cannot read from the source, written as the LaTeX
+//! commands that set them: accented letters, Greek, dashes, quotes, spaces
+//! and common symbols. Scripts LaTeX has no commands for, as CJK, are kept
+//! as they are.
+
+/// Accent commands and the letters they set: each accented letter above
+/// the letter it is put on.
+const ACCENTS: &[(&str, &str, &str)] = &[
+    ("`", "àèìòùÀÈÌÒÙ", "aeiouAEIOU"),
+    ("'", "áéíóúýÁÉÍÓÚÝćĺńŕśźĆĹŃŔŚŹ", "aeiouyAEIOUYclnrszCLNRSZ"),
+    ("^", "âêîôûÂÊÎÔÛĉĝĥĵŝŵŷ", "aeiouAEIOUcghjswy"),
+    ("~", "ãñõĩũÃÑÕĨŨ", "anoiuANOIU"),
+    ("\"", "äëïöüÿÄËÏÖÜŸ", "aeiouyAEIOUY"),
+    ("=", "āēīōūĀĒĪŌŪ", "aeiouAEIOU"),
+    ("u", "ăĕğĭŏŭĂĔĞĬŎŬ", "aegiouAEGIOU"),
+    (".", "ċėġżĊĖĠİŻ", "cegzCEGIZ"),
+    ("r", "ůŮ", "uU"),
+    ("H", "őűŐŰ", "ouOU"),
+    ("v", "čďěľňřšťžČĎĚĽŇŘŠŤŽ", "cdelnrstzCDELNRSTZ"),
+    ("c", "çģķļņşţÇĢĶĻŅŞŢ", "cgklnstCGKLNST"),
+    ("k", "ąęįųĄĘĮŲ", "aeiuAEIU"),
+];
+
+/// Letters and symbols with a command of their own.
+const SYMBOLS: &[(char, &str)] = &[
+    ('ß', "\\ss{}"),
+    ('æ', "\\ae{}"),
+    ('Æ', "\\AE{}"),
+    ('œ', "\\oe{}"),
+    ('Œ', "\\OE{}"),
+    ('ø', "\\o{}"),
+    ('Ø', "\\O{}"),
+    ('å', "\\aa{}"),
+    ('Å', "\\AA{}"),
+    ('ł', "\\l{}"),
+    ('Ł', "\\L{}"),
+    ('ı', "\\i{}"),
+    ('đ', "\\dj{}"),
+    ('Đ', "\\DJ{}"),
+    ('ð', "\\dh{}"),
+    ('Ð', "\\DH{}"),
+    ('þ', "\\th{}"),
+    ('Þ', "\\TH{}"),
+    ('\u{a0}', "~"),
+    ('\u{ad}', "\\-"),
+    ('\u{2002}', "\\enspace{}"),
+    ('\u{2003}', "\\quad{}"),
+    ('\u{2009}', "\\,"),
+    ('\u{200b}', ""),
+    ('\u{202f}', "\\,"),
+    ('–', "--"),
+    ('—', "---"),
+    ('‘', "`"),
+    ('’', "'"),
+    ('“', "``"),
+    ('”', "''"),
+    ('„', "\\quotedblbase{}"),
+    ('‚', "\\quotesinglbase{}"),
+    ('«', "\\guillemotleft{}"),
+    ('»', "\\guillemotright{}"),
+    ('‹', "\\guilsinglleft{}"),
+    ('›', "\\guilsinglright{}"),
+    ('…', "\\ldots{}"),
+    ('•', "\\textbullet{}"),
+    ('·', "\\textperiodcentered{}"),
+    ('†', "\\dag{}"),
+    ('‡', "\\ddag{}"),
+    ('§', "\\S{}"),
+    ('¶', "\\P{}"),
+    ('©', "\\copyright{}"),
+    ('®', "\\textregistered{}"),
+    ('™', "\\texttrademark{}"),
+    ('°', "\\textdegree{}"),
+    ('′', "\\ensuremath{'}"),
+    ('″', "\\ensuremath{''}"),
+    ('£', "\\pounds{}"),
+    ('€', "\\texteuro{}"),
+    ('¥', "\\textyen{}"),
+    ('¢', "\\textcent{}"),
+    ('¿', "\\textquestiondown{}"),
+    ('¡', "\\textexclamdown{}"),
+    ('‰', "\\textperthousand{}"),
+    ('№', "\\textnumero{}"),
+    ('¹', "\\textsuperscript{1}"),
+    ('²', "\\textsuperscript{2}"),
+    ('³', "\\textsuperscript{3}"),
+    ('×', "\\texttimes{}"),
+    ('÷', "\\textdiv{}"),
+    ('±', "\\textpm{}"),
+    ('−', "\\textminus{}"),
+    ('µ', "\\textmu{}"),
+    ('→', "\\textrightarrow{}"),
+    ('←', "\\textleftarrow{}"),
+    ('≤', "\\ensuremath{\\leq}"),
+    ('≥', "\\ensuremath{\\geq}"),
+    ('≠', "\\ensuremath{\\neq}"),
+    ('≈', "\\ensuremath{\\approx}"),
+    ('∞', "\\ensuremath{\\infty}"),
+];
+
+/// Greek letters, lowercase from alpha and uppercase from Alpha. Capitals
+/// that look like Latin ones are set as those.
+const GREEK_LOWER: [&str; 25] = [
+    "\\alpha",
+    "\\beta",
+    "\\gamma",
+    "\\delta",
+    "\\epsilon",
+    "\\zeta",
+    "\\eta",
+    "\\theta",
+    "\\iota",
+    "\\kappa",
+    "\\lambda",
+    "\\mu",
+    "\\nu",
+    "\\xi",
+    "o",
+    "\\pi",
+    "\\rho",
+    "\\varsigma",
+    "\\sigma",
+    "\\tau",
+    "\\upsilon",
+    "\\phi",
+    "\\chi",
+    "\\psi",
+    "\\omega",
+];
+const GREEK_UPPER: [&str; 25] = [
+    "A",
+    "B",
+    "\\Gamma",
+    "\\Delta",
+    "E",
+    "Z",
+    "H",
+    "\\Theta",
+    "I",
+    "K",
+    "\\Lambda",
+    "M",
+    "N",
+    "\\Xi",
+    "O",
+    "\\Pi",
+    "P",
+    "",
+    "\\Sigma",
+    "T",
+    "\\Upsilon",
+    "\\Phi",
+    "X",
+    "\\Psi",
+    "\\Omega",
+];
+
+/// `text` with each character pdflatex cannot read replaced by the command
+/// that sets it. ASCII passes through untouched, so LaTeX's own special
+/// characters must be escaped before or after.
+pub(crate) fn transliterate(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len() + 8);
+    for ch in text.chars() {
+        if ch.is_ascii() {
+            out.push(ch);
+        } else if let Some(command) = transliteration(ch) {
+            out.push_str(&command);
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
+/// The first character of `text` pdflatex cannot set even transliterated:
+/// past Latin-1, which `inputenc` reads under T1, only the letters and
+/// symbols above have commands. CJK and other scripts need a Unicode engine.
+pub fn unicode_only_char(text: &str) -> Option<char> {
+    text.chars()
+        .find(|&ch| !matches!(ch, '\0'..='\u{ff}') && transliteration(ch).is_none())
+}
+
+fn transliteration(ch: char) -> Option<String> {
+    if let Some((_, command)) = SYMBOLS.iter().find(|(symbol, _)| *symbol == ch) {
+        return Some(command.to_string());
+    }
+    for (accent, accented, bases) in ACCENTS {
+        if let Some(idx) = accented.chars().position(|letter| letter == ch) {
+            let base = bases.chars().nth(idx)?;
+            return Some(format!("\\{}{{{}}}", accent, base));
+        }
+    }
+    let greek = match ch {
+        'α'..='ω' => GREEK_LOWER[ch as usize - 'α' as usize],
+        'Α'..='Ω' => GREEK_UPPER[ch as usize - 'Α' as usize],
+        _ => return None,
+    };
+    match greek {
+        "" => None,
+        command if command.starts_with('\\') => Some(format!("\\ensuremath{{{}}}", command)),
+        letter => Some(letter.to_string()),
+    }
+}
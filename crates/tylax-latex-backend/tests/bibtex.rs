@@ -0,0 +1,261 @@
+use tylax_ir::{Block, Citation, CiteForm, Document, Inline};
+use tylax_latex_backend::{
+    render_document, render_thebibliography, BibDatabase, BibStyle, CitationPackage,
+    LatexRenderOptions,
+};
+
+const SOURCE: &str = r#"
+@string{ annalen = "Annalen der " # {Physik} }
+@comment{ ignored @article{nope, title = {Nope}} }
+
+@Article{einstein1905,
+  AUTHOR  = {Albert Einstein},
+  title   = {Zur {E}lektrodynamik bewegter
+             K{\"o}rper},
+  journal = annalen,
+  volume  = 17,
+  number  = "10",
+  pages   = {891-921},
+  year    = 1905,
+  month   = jun
+}
+
+@inproceedings(vaswani2017,
+  author    = {Vaswani, Ashish and Shazeer, Noam and others},
+  title     = "Attention Is All You Need",
+  crossref  = {nips17},
+  pages     = {5998--6008},
+)
+
+@proceedings{nips17,
+  title     = {Advances in Neural Information Processing Systems},
+  publisher = {Curran Associates},
+  year      = {2017},
+}
+
+@book{knuth,
+  author    = {Donald E. Knuth and Ludwig van Beethoven and de la Fontaine, Jr., Jean},
+  title     = {The {\TeX}book},
+  publisher = {Addison-Wesley},
+  year      = {1984},
+}
+"#;
+
+#[test]
+fn parses_macros_crossrefs_and_names() {
+    let db = BibDatabase::parse(SOURCE);
+    assert_eq!(db.len(), 4);
+
+    let einstein = db.get("Einstein1905").unwrap();
+    assert_eq!(einstein.kind, "article");
+    assert_eq!(einstein.field("journal"), Some("Annalen der Physik"));
+    assert_eq!(
+        einstein.field("title"),
+        Some("Zur {E}lektrodynamik bewegter K{\\\"o}rper")
+    );
+    assert_eq!(einstein.field("month"), Some("June"));
+    assert_eq!(einstein.year(), Some("1905"));
+
+    let vaswani = db.get("vaswani2017").unwrap();
+    assert_eq!(
+        vaswani.field("booktitle"),
+        Some("Advances in Neural Information Processing Systems")
+    );
+    assert_eq!(vaswani.year(), Some("2017"));
+    let authors = vaswani.authors();
+    assert_eq!(authors.len(), 3);
+    assert_eq!(authors[0].last, "Vaswani");
+    assert_eq!(authors[0].first, "Ashish");
+    assert!(authors[2].is_others());
+
+    let names = db.get("knuth").unwrap().authors();
+    assert_eq!(names[0].first, "Donald E.");
+    assert_eq!(names[1].von, "van");
+    assert_eq!(names[1].last, "Beethoven");
+    assert_eq!(names[2].von, "de la");
+    assert_eq!(names[2].last, "Fontaine");
+    assert_eq!(names[2].jr, "Jr.");
+    assert_eq!(names[2].initials_first(), "J. de la Fontaine, Jr.");
+}
+
+#[test]
+fn formats_entries_per_style() {
+    let db = BibDatabase::parse(SOURCE);
+    let cited = vec!["vaswani2017".to_string(), "einstein1905".to_string()];
+
+    let plain = render_thebibliography(&db, &cited, BibStyle::Plain);
+    assert!(plain.starts_with("\\begin{thebibliography}{2}\n"));
+    assert!(plain.contains(
+        "\\bibitem{einstein1905}\nAlbert Einstein.\n\\newblock Zur {E}lektrodynamik bewegter K{\\\"o}rper.\n\
+         \\newblock \\emph{Annalen der Physik}, 17(10):891--921, June 1905.\n"
+    ));
+    assert!(plain.find("einstein1905").unwrap() < plain.find("vaswani2017").unwrap());
+    assert!(plain.contains("Ashish Vaswani et~al.\n"));
+    assert!(!plain.contains("knuth"));
+
+    let ieee = render_thebibliography(&db, &cited, BibStyle::Ieee);
+    assert!(ieee.find("vaswani2017").unwrap() < ieee.find("einstein1905").unwrap());
+    assert!(ieee.contains(
+        "A. Vaswani et~al., ``Attention Is All You Need,'' in \\emph{Advances in Neural \
+         Information Processing Systems}, Curran Associates, 2017, pp.~5998--6008.\n"
+    ));
+    assert!(ieee.contains("\\emph{Annalen der Physik}, vol.~17, no.~10, pp.~891--921, 1905."));
+
+    let apa = render_thebibliography(&db, &cited, BibStyle::Apa);
+    assert!(apa.contains("\\bibitem[Einstein(1905)]{einstein1905}\nEinstein, A. (1905).\n"));
+    assert!(apa.contains("\\newblock \\emph{Annalen der Physik}, \\emph{17}(10), 891--921.\n"));
+    assert!(apa.contains("\\bibitem[Vaswani et~al.(2017)]{vaswani2017}"));
+
+    let alpha = render_thebibliography(&db, &[], BibStyle::Alpha);
+    assert!(alpha.contains("\\bibitem[Ein05]{einstein1905}"));
+    assert!(alpha.contains("\\bibitem[KvBdlF84]{knuth}"));
+    assert!(
+        alpha.contains("\\newblock \\emph{The {\\TeX}book}.\n\\newblock Addison-Wesley, 1984.\n")
+    );
+}
+
+#[test]
+fn style_names_map_to_layouts() {
+    assert_eq!(BibStyle::from_name("ieee"), BibStyle::Ieee);
+    assert_eq!(BibStyle::from_name("IEEEtran"), BibStyle::Ieee);
+    assert_eq!(BibStyle::from_name("chicago-author-date"), BibStyle::Apa);
+    assert_eq!(BibStyle::from_name("alpha"), BibStyle::Alpha);
+    assert_eq!(BibStyle::from_name("unsrt"), BibStyle::Unsrt);
+    assert_eq!(BibStyle::from_name("springer-basic"), BibStyle::Plain);
+}
+
+#[test]
+fn inline_bibliography_lists_cited_entries() {
+    let doc = Document::new(vec![
+        Block::Paragraph(vec![
+            Inline::text("See "),
+            Inline::Cite("knuth".to_string()),
+            Inline::text("."),
+        ]),
+        Block::Bibliography {
+            file: "refs.bib".to_string(),
+            style: Some("ieee".to_string()),
+            full: false,
+            title: None,
+        },
+    ]);
+    let external = render_document(&doc, LatexRenderOptions::default());
+    assert!(external.contains("\\bibliography{refs}"));
+
+    let inline = render_document(
+        &doc,
+        LatexRenderOptions {
+            inline_bibliography: Some(BibDatabase::parse(SOURCE)),
+            ..LatexRenderOptions::default()
+        },
+    );
+    assert!(!inline.contains("\\bibliography{"));
+    assert!(inline.contains("\\begin{thebibliography}{1}"));
+    assert!(inline.contains("\\bibitem{knuth}\nD. E. Knuth, L. van Beethoven, and J. de la Fontaine, Jr., \\emph{The {\\TeX}book}. Addison-Wesley, 1984.\n"));
+    assert!(!inline.contains("einstein1905"));
+}
+
+#[test]
+fn full_bibliography_lists_uncited_entries_under_its_title() {
+    let bibliography = |title: Option<Vec<Inline>>| {
+        Document::new(vec![
+            Block::Paragraph(vec![Inline::Cite("knuth".to_string())]),
+            Block::Bibliography {
+                file: "refs.bib".to_string(),
+                style: None,
+                full: true,
+                title,
+            },
+        ])
+    };
+    let external = render_document(
+        &bibliography(Some(vec![Inline::text("Further reading")])),
+        LatexRenderOptions::default(),
+    );
+    assert!(external.contains(
+        "\\begingroup\n\\renewcommand{\\refname}{Further reading}\n\\nocite{*}\n\
+         \\bibliographystyle{plain}\n\\bibliography{refs}\n\\endgroup"
+    ));
+
+    let inline = render_document(
+        &bibliography(Some(Vec::new())),
+        LatexRenderOptions {
+            full_document: true,
+            inline_bibliography: Some(BibDatabase::parse(SOURCE)),
+            ..LatexRenderOptions::default()
+        },
+    );
+    assert!(inline.contains("\\usepackage{titlesec}\n"));
+    assert!(inline.contains(
+        "\\begingroup\n\\makeatletter\n\
+         \\titleformat{name=\\section,numberless}{}{}{0pt}{\\@gobble}\n\
+         \\titlespacing*{name=\\section,numberless}{0pt}{0pt}{0pt}\n\
+         \\makeatother\n\\begin{thebibliography}"
+    ));
+    assert!(!inline.contains("\\renewcommand{\\section}"));
+    assert!(inline.contains("\\bibitem{knuth}"));
+    assert!(inline.contains("\\bibitem{einstein1905}"));
+    assert!(!inline.contains("\\refname"));
+}
+
+#[test]
+fn citation_forms_follow_the_citation_package() {
+    let citation = |form, postnote: Option<&str>| {
+        Inline::Citation(Citation {
+            keys: "knuth".to_string(),
+            form,
+            prenote: None,
+            postnote: postnote.map(str::to_string),
+        })
+    };
+    let doc = Document::new(vec![
+        Block::Paragraph(vec![
+            Inline::Cite("knuth".to_string()),
+            citation(CiteForm::Normal, Some("p.~5")),
+            citation(CiteForm::Prose, None),
+            citation(CiteForm::Full, None),
+            citation(CiteForm::Author, None),
+            citation(CiteForm::Year, None),
+            citation(CiteForm::Hidden, Some("ignored")),
+        ]),
+        Block::Bibliography {
+            file: "refs.bib,more.yml".to_string(),
+            style: Some("alpha".to_string()),
+            full: false,
+            title: Some(vec![Inline::text("Sources")]),
+        },
+    ]);
+
+    let natbib = render_document(
+        &doc,
+        LatexRenderOptions {
+            full_document: true,
+            ..LatexRenderOptions::default()
+        },
+    );
+    assert!(natbib.contains("\\usepackage{natbib}\n"));
+    assert!(natbib.contains(
+        "\\cite{knuth}\\citep[p.~5]{knuth}\\citet{knuth}\\citet*{knuth}\
+         \\citeauthor{knuth}\\citeyearpar{knuth}\\nocite{knuth}"
+    ));
+    assert!(natbib.contains("\\bibliography{refs,more}"));
+
+    let biblatex = render_document(
+        &doc,
+        LatexRenderOptions {
+            full_document: true,
+            citation_package: CitationPackage::Biblatex,
+            ..LatexRenderOptions::default()
+        },
+    );
+    assert!(!biblatex.contains("natbib"));
+    assert!(biblatex.contains(
+        "\\usepackage[style=alphabetic]{biblatex}\n\
+         \\addbibresource{refs.bib}\n\\addbibresource{more.bib}\n"
+    ));
+    assert!(biblatex.contains(
+        "\\autocite{knuth}\\autocite[p.~5]{knuth}\\textcite{knuth}\\fullcite{knuth}\
+         \\citeauthor{knuth}\\parencite*{knuth}\\nocite{knuth}"
+    ));
+    assert!(biblatex.contains("\\printbibliography[title={Sources}]"));
+}
@@ -0,0 +1,49 @@
+use tylax_latex_backend::{format_latex, FormatOptions};
+
+#[test]
+fn indents_environment_bodies() {
+    let input = "\\begin{document}\n\\begin{itemize}\n\\item A\n\\begin{enumerate}\n\\item B\n\\end{enumerate}\n\\end{itemize}\n\\end{document}";
+    let output = format_latex(input, &FormatOptions::default());
+    assert_eq!(
+        output,
+        "\\begin{document}\n\\begin{itemize}\n  \\item A\n  \\begin{enumerate}\n    \\item B\n  \\end{enumerate}\n\\end{itemize}\n\\end{document}"
+    );
+}
+
+#[test]
+fn verbatim_blocks_are_untouched() {
+    let input = "\\begin{figure}\n\\begin{verbatim}\n  x   =  1\n\\begin{itemize}\n\\end{verbatim}\nText\n\\end{figure}";
+    let options = FormatOptions {
+        indent_width: 4,
+        max_line_length: Some(20),
+    };
+    let output = format_latex(input, &options);
+    assert_eq!(
+        output,
+        "\\begin{figure}\n    \\begin{verbatim}\n  x   =  1\n\\begin{itemize}\n    \\end{verbatim}\n    Text\n\\end{figure}"
+    );
+}
+
+#[test]
+fn long_lines_wrap_at_spaces() {
+    let input = "\\begin{quote}\nalpha beta gamma delta epsilon zeta eta theta\n\\end{quote}";
+    let options = FormatOptions {
+        indent_width: 2,
+        max_line_length: Some(24),
+    };
+    let output = format_latex(input, &options);
+    assert_eq!(
+        output,
+        "\\begin{quote}\n  alpha beta gamma delta\n  epsilon zeta eta theta\n\\end{quote}"
+    );
+}
+
+#[test]
+fn comments_are_not_wrapped() {
+    let input = "some words here % and a comment that is rather long";
+    let options = FormatOptions {
+        indent_width: 2,
+        max_line_length: Some(20),
+    };
+    assert_eq!(format_latex(input, &options), input);
+}
@@ -0,0 +1,98 @@
+use tylax_latex_backend::{render_thebibliography, BibDatabase, BibStyle};
+
+const SOURCE: &str = r#"
+harry:
+  type: Book
+  title: Harry Potter & the Order of the Phoenix
+  author: Rowling, J. K.
+  volume: 5
+  date: 2003-06-21
+  publisher:
+    name: Bloomsbury
+    location: London
+
+kinetics:
+  type: Article
+  title: Kinetics and luminescence of the excitations of a nonequilibrium polariton condensate
+  author: ["Doan, T. D.", "Tran Thai, H.", "Deng, H."]
+  page-range: 233-240
+  serial-number:
+    doi: 10.1103/PhysRevB.72.085301
+  parent:
+    type: Periodical
+    title: Physical Review B
+    volume: 72
+    issue: 8
+    date: 2005-08-19
+
+attention:
+  type: Article
+  title: Attention is all you need
+  author:
+    - Vaswani, Ashish
+    - Shazeer, Noam
+  parent:
+    type: Proceedings
+    title: Advances in Neural Information Processing Systems
+    date: 2017
+
+electronic:
+  type: Web
+  title: Ishkur's Guide to Electronic Music
+  author: Ishkur
+  url:
+    value: http://www.techno.org/electronic-music-guide/
+    date: 2022-03-01
+"#;
+
+#[test]
+fn converts_hayagriva_entries_to_bibtex() {
+    let db = BibDatabase::from_hayagriva(SOURCE);
+    assert_eq!(db.len(), 4);
+
+    let harry = db.get("harry").unwrap();
+    assert_eq!(harry.kind, "book");
+    assert_eq!(
+        harry.field("title"),
+        Some("Harry Potter \\& the Order of the Phoenix")
+    );
+    assert_eq!(harry.field("publisher"), Some("Bloomsbury"));
+    assert_eq!(harry.field("address"), Some("London"));
+    assert_eq!(harry.field("month"), Some("June"));
+
+    let kinetics = db.get("kinetics").unwrap();
+    assert_eq!(kinetics.kind, "article");
+    assert_eq!(kinetics.field("journal"), Some("Physical Review B"));
+    assert_eq!(kinetics.field("number"), Some("8"));
+    assert_eq!(kinetics.field("pages"), Some("233--240"));
+    assert_eq!(kinetics.field("year"), Some("2005"));
+    assert_eq!(kinetics.authors().len(), 3);
+
+    let attention = db.get("attention").unwrap();
+    assert_eq!(attention.kind, "inproceedings");
+    assert_eq!(attention.authors()[1].last, "Shazeer");
+
+    let web = db.get("electronic").unwrap();
+    assert_eq!(web.kind, "misc");
+    assert_eq!(
+        web.field("url"),
+        Some("http://www.techno.org/electronic-music-guide/")
+    );
+
+    let bib = db.to_bibtex();
+    assert!(bib.contains(
+        "@article{kinetics,\n  author = {Doan, T. D. and Tran Thai, H. and Deng, H.},\n"
+    ));
+    assert!(bib.contains("  doi = {10.1103/PhysRevB.72.085301},\n"));
+    assert_eq!(BibDatabase::parse(&bib), db);
+}
+
+#[test]
+fn hayagriva_entries_render_inline() {
+    let db = BibDatabase::from_hayagriva(SOURCE);
+    let out = render_thebibliography(&db, &["harry".to_string()], BibStyle::Plain);
+    assert!(out.contains(
+        "\\bibitem{harry}\nJ. K. Rowling.\n\\newblock \\emph{Harry Potter \\& the Order of the Phoenix}.\n\
+         \\newblock Volume 5.\n\\newblock Bloomsbury, London, June 2003.\n"
+    ));
+}
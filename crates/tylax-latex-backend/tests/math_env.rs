@@ -1,11 +1,12 @@
-use tylax_ir::{Block, Document, MathBlock};
-use tylax_latex_backend::{render_document, LatexRenderOptions};
+use tylax_ir::{Block, Document, Inline, MathBlock};
+use tylax_latex_backend::{render_document, LatexRenderOptions, MathDelimiters};
 
 #[test]
 fn math_block_uses_gather_without_alignment() {
     let doc = Document::new(vec![Block::MathBlock(MathBlock {
-        content: "a \\\\ b".to_string(),
+        content: "a \\ b".to_string(),
         label: None,
+        latex: false,
     })]);
     let out = render_document(
         &doc,
@@ -22,17 +23,36 @@ fn math_block_uses_gather_without_alignment() {
             cite_command: None,
             base_font_size_pt: None,
             heading_numbering_none: false,
+            table_wrap_threshold: Some(tylax_latex_backend::DEFAULT_TABLE_WRAP_THRESHOLD),
+            code_backend: tylax_latex_backend::CodeBackend::Verbatim,
+            math_emphasis: false,
+            theorem_numbering: tylax_latex_backend::TheoremNumbering::default(),
+            cleveref: false,
+            detect_abstract: true,
+            inline_bibliography: None,
+            math_delimiters: tylax_latex_backend::MathDelimiters::Dollar,
+            long_tables: false,
+            long_table_rows: None,
+            tabularx: false,
+            citation_package: tylax_latex_backend::CitationPackage::Natbib,
+            template: tylax_latex_backend::DocumentTemplate::Article,
+            label_rewrites: Vec::new(),
+            encoding_mode: tylax_latex_backend::EncodingMode::Transliterate,
+            quote_style: tylax_latex_backend::QuoteStyle::Straight,
+            toc_depth: None,
         },
     );
     assert!(out.contains("\\begin{gather}"));
     assert!(out.contains("\\end{gather}"));
+    assert!(out.contains("a \\\\ b"));
 }
 
 #[test]
 fn math_block_uses_align_with_alignment_points() {
     let doc = Document::new(vec![Block::MathBlock(MathBlock {
-        content: "a &= b \\\\ c &= d".to_string(),
+        content: "a &= b \\ c &= d".to_string(),
         label: None,
+        latex: false,
     })]);
     let out = render_document(
         &doc,
@@ -49,8 +69,74 @@ fn math_block_uses_align_with_alignment_points() {
             cite_command: None,
             base_font_size_pt: None,
             heading_numbering_none: false,
+            table_wrap_threshold: Some(tylax_latex_backend::DEFAULT_TABLE_WRAP_THRESHOLD),
+            code_backend: tylax_latex_backend::CodeBackend::Verbatim,
+            math_emphasis: false,
+            theorem_numbering: tylax_latex_backend::TheoremNumbering::default(),
+            cleveref: false,
+            detect_abstract: true,
+            inline_bibliography: None,
+            math_delimiters: tylax_latex_backend::MathDelimiters::Dollar,
+            long_tables: false,
+            long_table_rows: None,
+            tabularx: false,
+            citation_package: tylax_latex_backend::CitationPackage::Natbib,
+            template: tylax_latex_backend::DocumentTemplate::Article,
+            label_rewrites: Vec::new(),
+            encoding_mode: tylax_latex_backend::EncodingMode::Transliterate,
+            quote_style: tylax_latex_backend::QuoteStyle::Straight,
+            toc_depth: None,
         },
     );
     assert!(out.contains("\\begin{align}"));
     assert!(out.contains("\\end{align}"));
 }
+
+#[test]
+fn math_delimiters_apply_to_inline_and_display_math() {
+    let doc = Document::new(vec![
+        Block::Paragraph(vec![Inline::text("Let "), Inline::Math("x".to_string())]),
+        Block::MathBlock(MathBlock {
+            content: "x^2".to_string(),
+            label: None,
+            latex: false,
+        }),
+    ]);
+    let render = |math_delimiters| {
+        render_document(
+            &doc,
+            LatexRenderOptions {
+                math_delimiters,
+                ..LatexRenderOptions::default()
+            },
+        )
+    };
+    assert_eq!(render(MathDelimiters::Dollar), "Let $x$\n\n\\[\nx^2\n\\]");
+    assert_eq!(
+        render(MathDelimiters::Paren),
+        "Let \\(x\\)\n\n\\[\nx^2\n\\]"
+    );
+    assert_eq!(
+        render(MathDelimiters::Equation),
+        "Let \\(x\\)\n\n\\begin{equation*}\nx^2\n\\end{equation*}"
+    );
+}
+
+#[test]
+fn latex_math_is_set_as_written() {
+    let doc = Document::new(vec![
+        Block::Paragraph(vec![
+            Inline::text("Let "),
+            Inline::LatexMath("\\alpha^2".to_string()),
+        ]),
+        Block::MathBlock(MathBlock {
+            content: "\\frac{a}{b} &= c \\\\ d &= e".to_string(),
+            label: None,
+            latex: true,
+        }),
+    ]);
+    let out = render_document(&doc, LatexRenderOptions::default());
+    assert!(out.starts_with("Let $\\alpha^2$\n\n"), "{out}");
+    assert!(out.contains("\\frac{a}{b} &= c \\\\ d &= e"), "{out}");
+    assert!(out.contains("\\begin{align"), "{out}");
+}
@@ -0,0 +1,92 @@
+use tylax_ir::{partition, Block, Document, Inline};
+use tylax_latex_backend::{render_document, render_document_split, LatexRenderOptions};
+
+fn heading(level: u8, title: &str, label: &str) -> Block {
+    Block::Heading {
+        level,
+        content: vec![Inline::text(title)],
+        numbered: true,
+        label: Some(label.to_string()),
+        outlined: true,
+        bookmarked: None,
+    }
+}
+
+fn sample() -> Document {
+    Document::new(vec![
+        Block::Paragraph(vec![Inline::text("Preface.")]),
+        heading(1, "Getting Started", "start"),
+        Block::Paragraph(vec![
+            Inline::text("See "),
+            Inline::Ref("results".to_string()),
+            Inline::text("."),
+        ]),
+        heading(2, "Setup & Tools", "setup"),
+        Block::Paragraph(vec![Inline::text("Install it.")]),
+        heading(1, "Results", "results"),
+        Block::Paragraph(vec![Inline::text("Done.")]),
+    ])
+}
+
+fn options(full_document: bool) -> LatexRenderOptions {
+    LatexRenderOptions {
+        full_document,
+        ..LatexRenderOptions::default()
+    }
+}
+
+#[test]
+fn partition_starts_parts_at_headings_of_level() {
+    let doc = sample();
+    let parts = partition(&doc, 1);
+    let names: Vec<&str> = parts.iter().map(|part| part.name.as_str()).collect();
+    assert_eq!(names, ["01-getting-started", "02-results"]);
+    assert_eq!(parts[0].blocks, 1..5);
+    assert_eq!(parts[1].blocks, 5..7);
+
+    let names: Vec<String> = partition(&doc, 2).into_iter().map(|part| part.name).collect();
+    assert_eq!(names, ["01-getting-started", "02-setup-tools", "03-results"]);
+}
+
+#[test]
+fn main_file_inputs_each_part() {
+    let doc = sample();
+    let split = render_document_split(&doc, options(true), 1);
+    let whole = render_document(&doc, options(true));
+
+    assert_eq!(split.parts.len(), 2);
+    let (name, first) = &split.parts[0];
+    assert_eq!(name, "01-getting-started");
+    assert!(first.starts_with("\\section{Getting Started}\n\\label{start}"));
+    assert!(first.contains("\\subsection{Setup \\& Tools}"));
+    assert!(first.contains("\\ref{results}"));
+    assert!(split.parts[1].1.contains("\\label{results}"));
+
+    let body = whole.split("\\begin{document}").next().unwrap();
+    assert!(split.main.starts_with(body));
+    assert!(split.main.contains(
+        "Preface.\n\n\\input{01-getting-started}\n\\input{02-results}\n\\end{document}"
+    ));
+    assert!(!split.main.contains("\\section"));
+}
+
+#[test]
+fn parts_concatenate_to_the_body() {
+    let doc = sample();
+    let split = render_document_split(&doc, options(false), 2);
+    let whole = render_document(&doc, options(false));
+    let mut rebuilt = String::from("Preface.\n\n");
+    for (_, content) in &split.parts {
+        rebuilt.push_str(content);
+        rebuilt.push('\n');
+    }
+    assert_eq!(rebuilt.trim_end(), whole);
+}
+
+#[test]
+fn document_without_parts_stays_whole() {
+    let doc = Document::new(vec![Block::Paragraph(vec![Inline::text("Short.")])]);
+    let split = render_document_split(&doc, options(true), 1);
+    assert!(split.parts.is_empty());
+    assert_eq!(split.main, render_document(&doc, options(true)));
+}
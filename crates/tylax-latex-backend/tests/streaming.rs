@@ -0,0 +1,73 @@
+use tylax_ir::{Block, Document, Inline};
+use tylax_latex_backend::{
+    render_document, render_document_to, BibDatabase, DocumentRenderer, LatexRenderOptions,
+};
+
+fn sample() -> Document {
+    Document::new(vec![
+        Block::Heading {
+            level: 1,
+            content: vec![Inline::text("Intro")],
+            numbered: true,
+            label: None,
+            outlined: true,
+            bookmarked: None,
+        },
+        Block::Paragraph(vec![
+            Inline::text("As shown in "),
+            Inline::Cite("knuth".to_string()),
+            Inline::text("."),
+        ]),
+        Block::Paragraph(vec![]),
+        Block::CodeBlock {
+            lang: None,
+            content: "let x = 1;".to_string(),
+        },
+        Block::Bibliography {
+            file: "refs.bib".to_string(),
+            style: None,
+            full: false,
+            title: None,
+        },
+    ])
+}
+
+fn options(full_document: bool) -> LatexRenderOptions {
+    LatexRenderOptions {
+        full_document,
+        inline_bibliography: Some(BibDatabase::parse(
+            "@book{knuth, author = {Donald Knuth}, title = {Literate Programming}, year = 1992}",
+        )),
+        ..LatexRenderOptions::default()
+    }
+}
+
+#[test]
+fn chunks_concatenate_to_rendered_document() {
+    let doc = sample();
+    for full_document in [false, true] {
+        let chunks: Vec<String> = DocumentRenderer::new(&doc, options(full_document)).collect();
+        assert_eq!(
+            chunks.concat(),
+            render_document(&doc, options(full_document))
+        );
+        let expected = if full_document { 6 } else { 4 };
+        assert_eq!(chunks.len(), expected);
+    }
+
+    let body: Vec<String> = DocumentRenderer::new(&doc, options(false)).collect();
+    assert_eq!(body[0], "\\section{Intro}");
+    assert!(body[1].starts_with("\n\nAs shown in"));
+    assert!(body[3].contains("\\bibitem{knuth}"));
+}
+
+#[test]
+fn writer_receives_the_same_output() {
+    let doc = sample();
+    let mut out = Vec::new();
+    render_document_to(&doc, options(true), &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        render_document(&doc, options(true))
+    );
+}
@@ -0,0 +1,166 @@
+use tylax_ir::{Author, Block, ColumnWidth, Document, DocumentMeta, Inline, SideBySide, Slide};
+use tylax_latex_backend::{render_document, DocumentTemplate, LatexRenderOptions};
+
+fn heading(level: u8, title: &str) -> Block {
+    Block::Heading {
+        level,
+        content: vec![Inline::text(title)],
+        numbered: true,
+        label: None,
+        outlined: true,
+        bookmarked: None,
+    }
+}
+
+fn para(text: &str) -> Block {
+    Block::Paragraph(vec![Inline::text(text)])
+}
+
+fn full(doc: &Document, template: DocumentTemplate) -> String {
+    render_document(
+        doc,
+        LatexRenderOptions {
+            full_document: true,
+            template,
+            ..LatexRenderOptions::default()
+        },
+    )
+}
+
+fn body(out: &str) -> &str {
+    let start = out.find("\\begin{document}").unwrap();
+    &out[start + "\\begin{document}\n\n".len()..]
+}
+
+#[test]
+fn books_and_reports_start_with_chapters() {
+    let doc = Document::new(vec![heading(1, "One"), heading(2, "Part"), para("Text.")]);
+    let book = full(&doc, DocumentTemplate::Book);
+    assert!(book.starts_with("\\documentclass{book}\n"));
+    assert!(body(&book).starts_with("\\chapter{One}\n\n\\section{Part}\n\nText."));
+    let report = full(&doc, DocumentTemplate::Report);
+    assert!(report.starts_with("\\documentclass{report}\n"));
+    assert!(report.contains("\\chapter{One}"));
+
+    let article = full(&doc, DocumentTemplate::Article);
+    assert!(article.starts_with("\\documentclass{article}\n"));
+    assert!(body(&article).starts_with("\\section{One}\n\n\\subsection{Part}"));
+}
+
+#[test]
+fn conference_classes_keep_their_layout() {
+    let doc = Document::new(vec![para("Text.")]).with_meta(DocumentMeta {
+        font_size: Some("11pt".to_string()),
+        paper: Some("a4".to_string()),
+        title: Some(vec![Inline::text("Paper")]),
+        authors: vec![Author {
+            name: "Ada".to_string(),
+            affiliation: Some("Typst GmbH".to_string()),
+            email: Some("ada@typst.app".to_string()),
+        }],
+        abstract_content: vec![para("We study it.")],
+        ..DocumentMeta::default()
+    });
+
+    let ieee = full(&doc, DocumentTemplate::IeeeTran);
+    assert!(ieee.starts_with("\\documentclass[conference,11pt]{IEEEtran}\n"));
+    assert!(!ieee.contains("geometry"));
+    assert!(ieee.contains(
+        "\\author{\\IEEEauthorblockN{Ada}\n\\IEEEauthorblockA{Typst GmbH \\\\ \\texttt{ada@typst.app}}}"
+    ));
+    assert!(ieee.contains("\\maketitle\n\n\\begin{abstract}"));
+
+    let acm = full(&doc, DocumentTemplate::Acm);
+    assert!(acm
+        .starts_with("\\PassOptionsToPackage{table}{xcolor}\n\\documentclass[sigconf]{acmart}\n"));
+    assert!(!acm.contains("amssymb"));
+    assert!(!acm.contains("\\usepackage[table]{xcolor}"));
+    assert!(acm.contains(
+        "\\author{Ada}\n\\affiliation{\\institution{Typst GmbH}}\n\\email{ada@typst.app}\n"
+    ));
+    // acmart wants the abstract before \maketitle.
+    let abstract_at = acm.find("\\begin{abstract}").unwrap();
+    assert!(abstract_at < acm.find("\\maketitle").unwrap());
+}
+
+#[test]
+fn beamer_sets_second_level_headings_as_frames() {
+    let doc = Document::new(vec![
+        para("Opening."),
+        heading(1, "Part"),
+        heading(2, "First"),
+        para("One."),
+        heading(3, "Detail"),
+        para("Two."),
+        heading(2, "Second"),
+        para("Three."),
+    ]);
+    let out = full(&doc, DocumentTemplate::Beamer);
+    assert!(out.starts_with("\\PassOptionsToPackage{table}{xcolor}\n\\documentclass{beamer}\n"));
+    assert!(!out.contains("enumitem"));
+    assert_eq!(
+        body(&out),
+        "\\begin{frame}[fragile]\nOpening.\n\n\
+         \\end{frame}\n\n\\section{Part}\n\n\
+         \\begin{frame}[fragile]{First}\n\nOne.\n\n\\textbf{Detail}\n\nTwo.\n\n\
+         \\end{frame}\n\n\\begin{frame}[fragile]{Second}\n\nThree.\n\n\
+         \\end{frame}\n\\end{document}\n"
+    );
+}
+
+#[test]
+fn custom_preamble_replaces_the_generated_one() {
+    let doc = Document::new(vec![heading(1, "One")]);
+    let out = full(
+        &doc,
+        DocumentTemplate::Custom {
+            preamble: "\\documentclass{scrartcl}\n\\usepackage{hyperref}".to_string(),
+        },
+    );
+    assert_eq!(
+        out,
+        "\\documentclass{scrartcl}\n\\usepackage{hyperref}\n\\begin{document}\n\n\
+         \\section{One}\n\\end{document}\n"
+    );
+}
+
+#[test]
+fn slides_are_frames_with_pauses_and_columns() {
+    let doc = Document::new(vec![
+        para("Opening."),
+        Block::Slide(Slide {
+            title: Some(vec![Inline::text("Results")]),
+            blocks: vec![
+                Block::Paragraph(vec![
+                    Inline::text("First"),
+                    Inline::Pause,
+                    Inline::text("second."),
+                ]),
+                Block::SideBySide(SideBySide {
+                    widths: vec![
+                        ColumnWidth::Fraction("1".to_string()),
+                        ColumnWidth::Fraction("3".to_string()),
+                    ],
+                    columns: vec![vec![para("Left")], vec![para("Right")]],
+                }),
+            ],
+        }),
+    ]);
+    let out = full(&doc, DocumentTemplate::Beamer);
+    assert_eq!(
+        body(&out),
+        "\\begin{frame}[fragile]\nOpening.\n\n\\end{frame}\n\n\
+         \\begin{frame}[fragile]{Results}\nFirst\\pause second.\n\n\
+         \\begin{columns}[T]\n\
+         \\begin{column}{0.24\\textwidth}\nLeft\n\\end{column}\n\
+         \\begin{column}{0.71\\textwidth}\nRight\n\\end{column}\n\
+         \\end{columns}\n\\end{frame}\n\\end{document}\n"
+    );
+
+    // Elsewhere a slide is a titled part of the text.
+    let out = full(&doc, DocumentTemplate::Article);
+    assert!(body(&out).contains(
+        "\\section*{Results}\n\nFirstsecond.\n\n\\noindent\n\
+         \\begin{minipage}[t]{0.24\\linewidth}\nLeft\n\\end{minipage}\n\\hfill\n"
+    ));
+}
@@ -0,0 +1,117 @@
+use tylax_ir::{Block, Document, EnvironmentBlock, Inline};
+use tylax_latex_backend::{
+    push_content_packages, render_document, render_theorem_definitions, DocumentTemplate,
+    LatexRenderOptions, TheoremCounter, TheoremNumbering,
+};
+
+fn env(name: &str, blocks: Vec<Block>) -> Block {
+    Block::Environment(EnvironmentBlock {
+        name: name.to_string(),
+        title: None,
+        blocks,
+        label: None,
+        args: Vec::new(),
+    })
+}
+
+fn para(text: &str) -> Block {
+    Block::Paragraph(vec![Inline::text(text)])
+}
+
+fn sample() -> Document {
+    Document::new(vec![
+        env("remark", vec![para("Note.")]),
+        env("lemma", vec![para("Small.")]),
+        Block::Quote(vec![env("definition", vec![para("Term.")])]),
+        env("theorem", vec![para("Main.")]),
+        env("proof", vec![para("Trivial.")]),
+        env("lemma", vec![para("Again.")]),
+    ])
+}
+
+#[test]
+fn full_document_declares_used_theorems() {
+    let out = render_document(
+        &sample(),
+        LatexRenderOptions {
+            full_document: true,
+            ..LatexRenderOptions::default()
+        },
+    );
+    let preamble = &out[..out.find("\\begin{document}").unwrap()];
+    assert!(preamble.contains("\\usepackage{amsthm}"));
+    assert!(preamble.contains(
+        "\\theoremstyle{plain}\n\\newtheorem{theorem}{Theorem}\n\\newtheorem{lemma}[theorem]{Lemma}\n\
+         \\theoremstyle{definition}\n\\newtheorem{definition}[theorem]{Definition}\n\
+         \\theoremstyle{remark}\n\\newtheorem{remark}[theorem]{Remark}\n"
+    ));
+    assert!(!preamble.contains("{proof}"));
+}
+
+#[test]
+fn a_preamble_read_from_the_source_gets_the_declarations_once() {
+    let options = LatexRenderOptions {
+        cleveref: true,
+        ..LatexRenderOptions::default()
+    };
+    let mut preamble = "\\documentclass{article}\n".to_string();
+    push_content_packages(&mut preamble, &sample(), &options);
+    assert!(
+        preamble.contains("\\usepackage{amsthm}\n\\usepackage{cleveref}\n\\theoremstyle{plain}")
+    );
+
+    let out = render_document(
+        &sample(),
+        LatexRenderOptions {
+            full_document: true,
+            template: DocumentTemplate::Custom { preamble },
+            ..options
+        },
+    );
+    assert_eq!(out.matches("\\newtheorem{theorem}").count(), 1);
+    assert_eq!(out.matches("\\usepackage{amsthm}").count(), 1);
+}
+
+#[test]
+fn body_only_and_theorem_free_documents_skip_declarations() {
+    let body = render_document(&sample(), LatexRenderOptions::default());
+    assert!(!body.contains("\\newtheorem"));
+
+    let plain = render_document(
+        &Document::new(vec![para("Text.")]),
+        LatexRenderOptions {
+            full_document: true,
+            ..LatexRenderOptions::default()
+        },
+    );
+    assert!(!plain.contains("amsthm"));
+}
+
+#[test]
+fn numbering_scheme_is_configurable() {
+    let doc = Document::new(vec![env("lemma", vec![]), env("claim", vec![])]);
+    let shared = TheoremNumbering {
+        counter: TheoremCounter::Shared,
+        within: Some("section".to_string()),
+    };
+    assert_eq!(
+        render_theorem_definitions(&doc, &shared),
+        "\\theoremstyle{plain}\n\\newtheorem{lemma}{Lemma}[section]\n\\newtheorem{claim}[lemma]{Claim}\n"
+    );
+    let separate = TheoremNumbering {
+        counter: TheoremCounter::PerEnvironment,
+        within: Some("section".to_string()),
+    };
+    assert_eq!(
+        render_theorem_definitions(&doc, &separate),
+        "\\theoremstyle{plain}\n\\newtheorem{lemma}{Lemma}[section]\n\\newtheorem{claim}{Claim}[section]\n"
+    );
+    let unnumbered = TheoremNumbering {
+        counter: TheoremCounter::Unnumbered,
+        within: None,
+    };
+    assert_eq!(
+        render_theorem_definitions(&doc, &unnumbered),
+        "\\theoremstyle{plain}\n\\newtheorem*{lemma}{Lemma}\n\\newtheorem*{claim}{Claim}\n"
+    );
+}
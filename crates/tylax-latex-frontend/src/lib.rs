@@ -0,0 +1,1417 @@
+//! LaTeX AST (mitex) to IR frontend.
+
+use mitex_parser::syntax::{CmdItem, EnvItem, FormulaItem, SyntaxElement, SyntaxKind, SyntaxNode};
+use mitex_parser::CommandSpec;
+use mitex_spec_gen::DEFAULT_SPEC;
+use rowan::ast::AstNode;
+use tylax_ir::{
+    Alignment, Block, Budget, CancellationToken, CaptionPosition, Citation, CiteForm,
+    ConversionLimits, Document, EnvironmentBlock, Figure, FigureContent, Image, Inline, LinkTarget,
+    ListKind, Loss, MathBlock, OutlineTarget, Severity, Side, Span, Table, TableCell, TermItem,
+};
+use tylax_units::Length;
+
+#[derive(Clone, Default)]
+pub struct LatexFrontendOptions {
+    /// Command specification handed to the parser. `None` uses mitex's
+    /// built-in specification.
+    pub spec: Option<CommandSpec>,
+    /// Converts LaTeX math into the math syntax stored in the IR. Without a
+    /// converter math is kept as LaTeX source.
+    pub math: Option<fn(&str) -> String>,
+    /// Bounds on input size, output size and time; content past them is
+    /// dropped as a `limit` loss.
+    pub limits: ConversionLimits,
+    /// Stops the conversion early when cancelled from another thread.
+    pub cancel: Option<CancellationToken>,
+    /// Environments kept as environment blocks besides the theorem-like
+    /// ones, e.g. a class's `problem`. The spec declares their required
+    /// arguments.
+    pub environments: Vec<String>,
+}
+
+pub fn latex_to_ir(input: &str) -> Document {
+    latex_to_ir_with(input, &LatexFrontendOptions::default())
+}
+
+pub fn latex_to_ir_with(input: &str, options: &LatexFrontendOptions) -> Document {
+    let spec = options
+        .spec
+        .clone()
+        .unwrap_or_else(|| CommandSpec::clone(&DEFAULT_SPEC));
+    let budget = Budget::new(&options.limits, options.cancel.as_ref());
+    let (input, cut) = budget.truncate_input(input);
+    let root = mitex_parser::parse(input, spec);
+    let elements = match find_environment(&root, "document") {
+        Some(document) => environment_body(&document),
+        None => flatten_scopes(&root),
+    };
+    let mut lowerer = Lowerer::new(options, budget);
+    lowerer.losses.extend(cut);
+    let (blocks, spans) = lowerer.blocks_with_spans(&elements);
+    Document::with_losses(blocks, lowerer.losses).with_spans(spans)
+}
+
+const THEOREM_ENVIRONMENTS: &[&str] = &[
+    "theorem",
+    "lemma",
+    "corollary",
+    "proposition",
+    "definition",
+    "remark",
+    "example",
+    "proof",
+    "conjecture",
+    "claim",
+    "note",
+    "abstract",
+];
+
+/// Commands that carry no content worth keeping in the IR.
+const IGNORED_COMMANDS: &[&str] = &[
+    "documentclass",
+    "usepackage",
+    "RequirePackage",
+    "newcommand",
+    "renewcommand",
+    "providecommand",
+    "DeclareMathOperator",
+    "newtheorem",
+    "centering",
+    "raggedright",
+    "raggedleft",
+    "noindent",
+    "indent",
+    "hline",
+    "toprule",
+    "midrule",
+    "bottomrule",
+    "cline",
+    "cmidrule",
+    "smallskip",
+    "medskip",
+    "bigskip",
+    "hfill",
+    "vfill",
+    "quad",
+    "qquad",
+    "relax",
+    "protect",
+    "nocite",
+];
+
+const RULE_COMMANDS: &[&str] = &[
+    "hline",
+    "toprule",
+    "midrule",
+    "bottomrule",
+    "cline",
+    "cmidrule",
+];
+
+enum Lowered {
+    Inline(Vec<Inline>),
+    Block(Block),
+    Break,
+    Nothing,
+}
+
+/// How many block and inline sequences may be nested inside each other
+/// before deeper content is dropped as a loss rather than risking the stack.
+const MAX_NESTING_DEPTH: usize = 48;
+
+struct Lowerer<'a> {
+    options: &'a LatexFrontendOptions,
+    losses: Vec<Loss>,
+    bibliography_style: Option<String>,
+    depth: usize,
+    budget: Budget,
+}
+
+impl<'a> Lowerer<'a> {
+    fn new(options: &'a LatexFrontendOptions, budget: Budget) -> Self {
+        Self {
+            options,
+            losses: Vec::new(),
+            bibliography_style: None,
+            depth: 0,
+            budget,
+        }
+    }
+
+    /// Count the blocks added since the last call and check the budget. The
+    /// limit loss covers `rest`, the content that is dropped.
+    fn over_budget(&mut self, counted: &mut usize, blocks: &[Block], rest: Span) -> bool {
+        let added = blocks.len().saturating_sub(*counted);
+        self.budget.add_blocks(added);
+        *counted = blocks.len();
+        let reported = self.losses.len();
+        if !self.budget.exhausted(&mut self.losses) {
+            return false;
+        }
+        for loss in &mut self.losses[reported..] {
+            loss.span = Some(rest);
+        }
+        true
+    }
+
+    /// Enter one nesting level, or report the elements as lost when the
+    /// limit is reached. Callers pair a successful entry with `leave`.
+    fn enter(&mut self, elements: &[SyntaxElement]) -> bool {
+        if self.depth < MAX_NESTING_DEPTH {
+            self.depth += 1;
+            return true;
+        }
+        if let (Some(first), Some(last)) = (elements.first(), elements.last()) {
+            let span = Span::new(element_span(first).start, element_span(last).end);
+            self.loss(
+                "nesting",
+                format!(
+                    "content nested more than {} levels deep was dropped",
+                    MAX_NESTING_DEPTH
+                ),
+                span,
+                Severity::Error,
+            );
+        }
+        false
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn loss(&mut self, kind: &str, message: String, span: Span, severity: Severity) {
+        self.losses.push(
+            Loss::new(kind, message)
+                .with_span(span)
+                .with_severity(severity),
+        );
+    }
+
+    fn math(&self, source: &str) -> String {
+        let source = source.trim();
+        match self.options.math {
+            Some(convert) => convert(source).trim().to_string(),
+            None => source.to_string(),
+        }
+    }
+
+    fn blocks(&mut self, elements: &[SyntaxElement]) -> Vec<Block> {
+        self.blocks_with_spans(elements).0
+    }
+
+    /// Lower a sequence of body elements, returning the blocks together with
+    /// the source span each one came from.
+    fn blocks_with_spans(&mut self, elements: &[SyntaxElement]) -> (Vec<Block>, Vec<Option<Span>>) {
+        if !self.enter(elements) {
+            return (Vec::new(), Vec::new());
+        }
+        let lowered = self.lower_blocks(elements);
+        self.leave();
+        lowered
+    }
+
+    fn lower_blocks(&mut self, elements: &[SyntaxElement]) -> (Vec<Block>, Vec<Option<Span>>) {
+        let mut blocks = Vec::new();
+        let mut spans = Vec::new();
+        let mut inlines: Vec<Inline> = Vec::new();
+        let mut inline_span: Option<Span> = None;
+        let mut newlines = 0usize;
+
+        macro_rules! flush {
+            () => {
+                flush_paragraph(&mut blocks, &mut spans, &mut inlines, inline_span.take());
+            };
+        }
+
+        let end = elements.last().map_or(0, |last| element_span(last).end);
+        let mut counted = 0;
+        for elem in elements {
+            let span = element_span(elem);
+            if self.over_budget(&mut counted, &blocks, Span::new(span.start, end)) {
+                break;
+            }
+            match elem.kind() {
+                SyntaxKind::TokenLineBreak => {
+                    newlines += elem_text(elem).matches('\n').count().max(1);
+                    if newlines >= 2 {
+                        flush!();
+                    } else {
+                        inlines.push(Inline::text(" "));
+                    }
+                    continue;
+                }
+                SyntaxKind::TokenWhiteSpace => {
+                    inlines.push(Inline::text(" "));
+                    continue;
+                }
+                SyntaxKind::TokenComment | SyntaxKind::ItemBlockComment => continue,
+                _ => newlines = 0,
+            }
+
+            let lowered = match elem {
+                SyntaxElement::Node(node) => match node.kind() {
+                    SyntaxKind::ItemEnv => match self.environment(node) {
+                        Some(block) => Lowered::Block(block),
+                        None => Lowered::Nothing,
+                    },
+                    SyntaxKind::ItemFormula => self.formula(node),
+                    SyntaxKind::ItemCmd => self.command(node),
+                    _ => {
+                        let mut out = Vec::new();
+                        self.inline_element(elem, &mut out);
+                        Lowered::Inline(out)
+                    }
+                },
+                SyntaxElement::Token(_) => {
+                    let mut out = Vec::new();
+                    self.inline_element(elem, &mut out);
+                    Lowered::Inline(out)
+                }
+            };
+
+            match lowered {
+                Lowered::Inline(out) => {
+                    if out.is_empty() {
+                        continue;
+                    }
+                    // A label right after a heading or equation names it.
+                    if let [Inline::Label(label)] = out.as_slice() {
+                        if !has_content(&inlines) && attach_label(blocks.last_mut(), label) {
+                            continue;
+                        }
+                    }
+                    inline_span = Some(match inline_span {
+                        Some(existing) => Span::new(existing.start, span.end),
+                        None => span,
+                    });
+                    inlines.extend(out);
+                }
+                Lowered::Block(block) => {
+                    flush!();
+                    blocks.push(block);
+                    spans.push(Some(span));
+                }
+                Lowered::Break => flush!(),
+                Lowered::Nothing => {}
+            }
+        }
+        flush!();
+        (blocks, spans)
+    }
+
+    fn inlines(&mut self, elements: &[SyntaxElement]) -> Vec<Inline> {
+        if !self.enter(elements) {
+            return Vec::new();
+        }
+        let mut out = Vec::new();
+        for elem in elements {
+            self.inline_element(elem, &mut out);
+        }
+        self.leave();
+        normalize_inlines(out)
+    }
+
+    fn inline_element(&mut self, elem: &SyntaxElement, out: &mut Vec<Inline>) {
+        match elem {
+            SyntaxElement::Token(token) if token.kind() == SyntaxKind::TokenError => {
+                self.loss(
+                    "parse",
+                    format!("parse error: {}", token.text()),
+                    element_span(elem),
+                    Severity::Error,
+                );
+            }
+            SyntaxElement::Token(token) => {
+                if let Some(text) = token_text(token.kind(), token.text()) {
+                    out.push(Inline::text(text));
+                }
+            }
+            SyntaxElement::Node(node) => match node.kind() {
+                SyntaxKind::ItemCmd => match self.command(node) {
+                    Lowered::Inline(inlines) => out.extend(inlines),
+                    Lowered::Block(block) => self.block_as_inlines(block, node, out),
+                    Lowered::Break | Lowered::Nothing => {}
+                },
+                SyntaxKind::ItemFormula => match self.formula(node) {
+                    Lowered::Inline(inlines) => out.extend(inlines),
+                    Lowered::Block(Block::MathBlock(math)) => out.push(Inline::Math(math.content)),
+                    _ => {}
+                },
+                SyntaxKind::ItemEnv => {
+                    if let Some(block) = self.environment(node) {
+                        self.block_as_inlines(block, node, out);
+                    }
+                }
+                SyntaxKind::ItemNewLine => out.push(Inline::LineBreak),
+                SyntaxKind::TokenError => {
+                    self.loss(
+                        "parse",
+                        format!("parse error: {}", node.text()),
+                        node_span(node),
+                        Severity::Error,
+                    );
+                }
+                SyntaxKind::ItemBegin | SyntaxKind::ItemEnd | SyntaxKind::ItemBlockComment => {}
+                _ => {
+                    if !self.enter(std::slice::from_ref(elem)) {
+                        return;
+                    }
+                    for child in node.children_with_tokens() {
+                        self.inline_element(&child, out);
+                    }
+                    self.leave();
+                }
+            },
+        }
+    }
+
+    fn block_as_inlines(&mut self, block: Block, node: &SyntaxNode, out: &mut Vec<Inline>) {
+        match block {
+            Block::Paragraph(inlines) => out.extend(inlines),
+            Block::MathBlock(math) => out.push(Inline::Math(math.content)),
+            Block::CodeBlock { lang, content } => out.push(Inline::Code { lang, content }),
+            _ => self.loss(
+                "inline",
+                "block content inside inline context dropped".to_string(),
+                node_span(node),
+                Severity::Error,
+            ),
+        }
+    }
+
+    fn formula(&mut self, node: &SyntaxNode) -> Lowered {
+        let Some(formula) = FormulaItem::cast(node.clone()) else {
+            return Lowered::Nothing;
+        };
+        let source: String = node
+            .children_with_tokens()
+            .filter(|child| {
+                !matches!(
+                    child.kind(),
+                    SyntaxKind::TokenDollar | SyntaxKind::TokenBeginMath | SyntaxKind::TokenEndMath
+                )
+            })
+            .map(|child| elem_text(&child))
+            .collect();
+        let content = self.math(&source);
+        if content.is_empty() {
+            return Lowered::Nothing;
+        }
+        if formula.is_inline() {
+            Lowered::Inline(vec![Inline::Math(content)])
+        } else {
+            Lowered::Block(Block::MathBlock(MathBlock {
+                content,
+                label: None,
+                latex: false,
+            }))
+        }
+    }
+
+    fn command(&mut self, node: &SyntaxNode) -> Lowered {
+        let Some(cmd) = CmdItem::cast(node.clone()) else {
+            return Lowered::Nothing;
+        };
+        let raw_name = cmd
+            .name_tok()
+            .map(|tok| tok.text().trim_start_matches('\\').to_string())
+            .unwrap_or_default();
+        let starred = raw_name.ends_with('*') || has_star_argument(node);
+        let name = raw_name.trim_end_matches('*');
+        let args = required_args(node);
+
+        match name {
+            "part" | "chapter" | "section" | "subsection" | "subsubsection" | "paragraph"
+            | "subparagraph" => {
+                let level = match name {
+                    "subsection" => 2,
+                    "subsubsection" => 3,
+                    "paragraph" => 4,
+                    "subparagraph" => 5,
+                    _ => 1,
+                };
+                let content = args
+                    .first()
+                    .map(|arg| self.inlines(&arg_elements(arg)))
+                    .unwrap_or_default();
+                Lowered::Block(Block::Heading {
+                    level,
+                    content,
+                    numbered: !starred,
+                    label: None,
+                    outlined: true,
+                    bookmarked: None,
+                })
+            }
+            "textbf" => Lowered::Inline(vec![Inline::Strong(self.arg_inlines(&args, 0))]),
+            "textit" | "emph" | "textsl" => {
+                Lowered::Inline(vec![Inline::Emph(self.arg_inlines(&args, 0))])
+            }
+            "texttt" => Lowered::Inline(vec![Inline::Code {
+                lang: None,
+                content: arg_text(&args, 0),
+            }]),
+            "text" | "textrm" | "textsf" | "textnormal" | "textup" | "mbox" => {
+                Lowered::Inline(self.arg_inlines(&args, 0))
+            }
+            "textsuperscript" => {
+                Lowered::Inline(vec![Inline::Superscript(self.arg_inlines(&args, 0))])
+            }
+            "textsubscript" => Lowered::Inline(vec![Inline::Subscript(self.arg_inlines(&args, 0))]),
+            "textsc" => Lowered::Inline(vec![Inline::SmallCaps(self.arg_inlines(&args, 0))]),
+            "underline" | "uline" => {
+                Lowered::Inline(vec![Inline::Underline(self.arg_inlines(&args, 0))])
+            }
+            "sout" => Lowered::Inline(vec![Inline::Strike(self.arg_inlines(&args, 0))]),
+            "MakeUppercase" => {
+                Lowered::Inline(vec![Inline::Uppercase(self.arg_inlines(&args, 0))])
+            }
+            "MakeLowercase" => {
+                Lowered::Inline(vec![Inline::Lowercase(self.arg_inlines(&args, 0))])
+            }
+            "textcolor" => Lowered::Inline(vec![Inline::Color {
+                color: arg_text(&args, 0),
+                content: self.arg_inlines(&args, 1),
+            }]),
+            "footnote" => Lowered::Inline(vec![Inline::Footnote(self.arg_inlines(&args, 0))]),
+            "href" => {
+                let url = arg_text(&args, 0);
+                let (url, target) = match url.strip_prefix("mailto:") {
+                    Some(address) => (address.to_string(), LinkTarget::Email),
+                    None => (url, LinkTarget::Url),
+                };
+                Lowered::Inline(vec![Inline::Link {
+                    text: self.arg_inlines(&args, 1),
+                    url,
+                    target,
+                }])
+            }
+            "hyperref" => match optional_args(node).first() {
+                Some(label) => Lowered::Inline(vec![Inline::Link {
+                    text: self.arg_inlines(&args, 0),
+                    url: elements_text(&arg_elements(label)).trim().to_string(),
+                    target: LinkTarget::Label,
+                }]),
+                // `\hyperref{url}{category}{name}{text}` keeps only its text.
+                None => Lowered::Inline(self.arg_inlines(&args, args.len().saturating_sub(1))),
+            },
+            "url" => {
+                let url = arg_text(&args, 0);
+                Lowered::Inline(vec![Inline::Link {
+                    text: vec![Inline::text(url.clone())],
+                    url,
+                    target: LinkTarget::Url,
+                }])
+            }
+            "ref" | "eqref" | "autoref" | "cref" | "Cref" | "pageref" => {
+                Lowered::Inline(vec![Inline::Ref(arg_text(&args, 0))])
+            }
+            "crefrange" | "Crefrange" => {
+                let (from, to) = (arg_text(&args, 0), arg_text(&args, 1));
+                if to.is_empty() {
+                    Lowered::Inline(vec![Inline::Ref(from)])
+                } else {
+                    Lowered::Inline(vec![Inline::RefRange { from, to }])
+                }
+            }
+            "label" => Lowered::Inline(vec![Inline::Label(arg_text(&args, 0))]),
+            "cite" | "citep" | "citet" | "parencite" | "textcite" | "autocite" | "citeauthor"
+            | "citeyear" | "citeyearpar" | "fullcite" | "nocite" => {
+                let keys: Vec<String> = arg_text(&args, 0)
+                    .split(',')
+                    .map(|key| key.trim().to_string())
+                    .filter(|key| !key.is_empty())
+                    .collect();
+                if keys.is_empty() {
+                    return Lowered::Nothing;
+                }
+                let keys = keys.join(",");
+                let form = match name {
+                    "citet" | "textcite" => CiteForm::Prose,
+                    "citeauthor" => CiteForm::Author,
+                    "citeyear" | "citeyearpar" => CiteForm::Year,
+                    "fullcite" => CiteForm::Full,
+                    "nocite" => CiteForm::Hidden,
+                    _ => CiteForm::Normal,
+                };
+                // A single optional argument is the note after the reference.
+                let notes: Vec<String> = optional_args(node)
+                    .iter()
+                    .map(|arg| elements_text(&arg_elements(arg)).trim().to_string())
+                    .collect();
+                let (prenote, postnote) = match notes.as_slice() {
+                    [] => (None, None),
+                    [post] => (None, Some(post.clone())),
+                    [pre, post, ..] => (Some(pre.clone()), Some(post.clone())),
+                };
+                let prenote = prenote.filter(|note| !note.is_empty());
+                let postnote = postnote.filter(|note| !note.is_empty());
+                if form == CiteForm::Normal && prenote.is_none() && postnote.is_none() {
+                    Lowered::Inline(vec![Inline::Cite(keys)])
+                } else {
+                    Lowered::Inline(vec![Inline::Citation(Citation {
+                        keys,
+                        form,
+                        prenote,
+                        postnote,
+                    })])
+                }
+            }
+            "vspace" => Lowered::Block(Block::VSpace(arg_text(&args, 0))),
+            "par" => Lowered::Break,
+            "tableofcontents" => Lowered::Block(Block::Outline {
+                title: None,
+                target: OutlineTarget::Headings,
+            }),
+            "listoffigures" => Lowered::Block(Block::Outline {
+                title: None,
+                target: OutlineTarget::Figures,
+            }),
+            "listoftables" => Lowered::Block(Block::Outline {
+                title: None,
+                target: OutlineTarget::Tables,
+            }),
+            "lstlistoflistings" => Lowered::Block(Block::Outline {
+                title: None,
+                target: OutlineTarget::Listings,
+            }),
+            "bibliographystyle" => {
+                self.bibliography_style = Some(arg_text(&args, 0));
+                Lowered::Nothing
+            }
+            "bibliography" => Lowered::Block(Block::Bibliography {
+                file: arg_text(&args, 0),
+                style: self.bibliography_style.clone(),
+                full: false,
+                title: None,
+            }),
+            "includegraphics" => Lowered::Block(Block::Figure(Figure {
+                content: FigureContent::Image(image(node, &args)),
+                caption: None,
+                label: None,
+                placement: None,
+                unnumbered: false,
+                caption_separator: None,
+                caption_position: None,
+                short_caption: None,
+                numbering: None,
+                wrap: None,
+            })),
+            name if IGNORED_COMMANDS.contains(&name) => Lowered::Nothing,
+            _ => {
+                self.loss(
+                    "command",
+                    format!("unsupported command \\{} kept as text", name),
+                    node_span(node),
+                    Severity::Warning,
+                );
+                let mut out = Vec::new();
+                for arg in &args {
+                    out.extend(self.inlines(&arg_elements(arg)));
+                }
+                Lowered::Inline(out)
+            }
+        }
+    }
+
+    fn arg_inlines(&mut self, args: &[SyntaxNode], index: usize) -> Vec<Inline> {
+        args.get(index)
+            .map(|arg| self.inlines(&arg_elements(arg)))
+            .unwrap_or_default()
+    }
+
+    fn environment(&mut self, node: &SyntaxNode) -> Option<Block> {
+        let env = EnvItem::cast(node.clone())?;
+        let raw_name = env
+            .name_tok()
+            .map(|tok| tok.text().trim().to_string())
+            .unwrap_or_default();
+        let name = raw_name.trim_end_matches('*');
+        let body = environment_body(node);
+
+        let block = match name {
+            "itemize" | "compactitem" => self.list(ListKind::Unordered, &body),
+            "enumerate" | "compactenum" => self.list(ListKind::Ordered, &body),
+            "description" => self.term_list(&body),
+            "equation" | "align" | "gather" | "multline" | "flalign" | "displaymath"
+            | "eqnarray" | "math" => {
+                let (source, label) = math_source(&body);
+                Block::MathBlock(MathBlock {
+                    content: self.math(&source),
+                    label,
+                    latex: false,
+                })
+            }
+            "quote" | "quotation" | "verse" => Block::Quote(self.blocks(&body)),
+            "center" | "flushleft" | "flushright" => Block::Align {
+                alignment: Some(match name {
+                    "flushleft" => Alignment::Left,
+                    "flushright" => Alignment::Right,
+                    _ => Alignment::Center,
+                }),
+                vertical: None,
+                blocks: self.blocks(&body),
+            },
+            "verbatim" | "lstlisting" | "minted" | "Verbatim" => Block::CodeBlock {
+                lang: code_language(name, node),
+                content: verbatim_text(&body),
+            },
+            "figure" | "table" | "wrapfigure" | "wraptable" => self.figure(node, &body),
+            "tabular" | "tabularx" | "longtable" | "tabu" => Block::Table(self.table(node, &body)),
+            name if THEOREM_ENVIRONMENTS.contains(&name)
+                || self.options.environments.iter().any(|env| env == name) =>
+            {
+                let title = env_optional_arg(node).map(|arg| self.inlines(&arg));
+                let label = find_label(&body);
+                let blocks = self.blocks(&skip_leading_bracket(&body));
+                Block::Environment(EnvironmentBlock {
+                    name: name.to_string(),
+                    title,
+                    blocks,
+                    label,
+                    args: env_args(node),
+                })
+            }
+            _ => {
+                self.loss(
+                    "environment",
+                    format!(
+                        "unsupported environment {} lowered to its content",
+                        raw_name
+                    ),
+                    node_span(node),
+                    Severity::Warning,
+                );
+                let mut blocks = self.blocks(&body);
+                return match blocks.len() {
+                    0 => None,
+                    1 => blocks.pop(),
+                    _ => Some(Block::Quote(blocks)),
+                };
+            }
+        };
+        Some(block)
+    }
+
+    fn list(&mut self, kind: ListKind, body: &[SyntaxElement]) -> Block {
+        let items = split_items(body)
+            .into_iter()
+            .map(|(_, elements)| self.blocks(&elements))
+            .collect();
+        Block::List { kind, items }
+    }
+
+    fn term_list(&mut self, body: &[SyntaxElement]) -> Block {
+        let items = split_items(body)
+            .into_iter()
+            .map(|(term, elements)| TermItem {
+                term: term.map(|term| self.inlines(&term)).unwrap_or_default(),
+                description: self.blocks(&elements),
+            })
+            .collect();
+        Block::TermList(items)
+    }
+
+    fn figure(&mut self, node: &SyntaxNode, body: &[SyntaxElement]) -> Block {
+        let wrap = wrap_args(node);
+        // wrapfig's optional argument is a number of lines, not a placement.
+        let placement = env_optional_arg(node)
+            .filter(|_| wrap.is_none())
+            .map(|arg| elements_text(&arg));
+        let mut caption = None;
+        let mut short_caption = None;
+        let mut caption_first = false;
+        let mut label = None;
+        let mut image_content = None;
+        let mut table = None;
+        let mut rest = Vec::new();
+        for elem in skip_leading_bracket(body) {
+            if let SyntaxElement::Node(child) = &elem {
+                match child.kind() {
+                    SyntaxKind::ItemCmd => match command_name(child).as_deref() {
+                        Some("caption") => {
+                            let args = required_args(child);
+                            caption = Some(self.arg_inlines(&args, 0));
+                            short_caption = optional_args(child)
+                                .first()
+                                .map(|arg| self.inlines(&arg_elements(arg)));
+                            caption_first = image_content.is_none() && table.is_none();
+                            continue;
+                        }
+                        Some("label") => {
+                            label = Some(arg_text(&required_args(child), 0));
+                            continue;
+                        }
+                        Some("includegraphics") if image_content.is_none() => {
+                            image_content = Some(image(child, &required_args(child)));
+                            continue;
+                        }
+                        _ => {}
+                    },
+                    SyntaxKind::ItemEnv if table.is_none() => {
+                        if let Some(found) = self.nested_table(child) {
+                            table = Some(found);
+                            continue;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            rest.push(elem);
+        }
+        // A caption above an image or a table is kept there.
+        let caption_position = (caption_first && (image_content.is_some() || table.is_some()))
+            .then_some(CaptionPosition::Top);
+        let content = if let Some(table) = table {
+            FigureContent::Table(Box::new(table))
+        } else if let Some(mut image) = image_content {
+            // An image filling its wrapfigure is as wide as the wrapfigure.
+            if let Some((_, Some(width))) = &wrap {
+                if matches!(image.width.as_deref(), None | Some("100%")) {
+                    image.width = Some(width.to_typst());
+                }
+            }
+            FigureContent::Image(image)
+        } else {
+            FigureContent::Raw(self.blocks(&rest))
+        };
+        Block::Figure(Figure {
+            content,
+            caption,
+            label,
+            placement,
+            unnumbered: false,
+            caption_separator: None,
+            caption_position,
+            short_caption,
+            numbering: None,
+            wrap: wrap.map(|(side, _)| side),
+        })
+    }
+
+    /// A tabular directly inside a float, possibly wrapped in `center`.
+    fn nested_table(&mut self, node: &SyntaxNode) -> Option<Table> {
+        let name = env_name(node)?;
+        match name.trim_end_matches('*') {
+            "tabular" | "tabularx" | "longtable" | "tabu" => {
+                Some(self.table(node, &environment_body(node)))
+            }
+            "center" => {
+                let inner = environment_body(node)
+                    .into_iter()
+                    .find_map(|elem| match elem {
+                        SyntaxElement::Node(child) if child.kind() == SyntaxKind::ItemEnv => {
+                            Some(child)
+                        }
+                        _ => None,
+                    })?;
+                self.nested_table(&inner)
+            }
+            _ => None,
+        }
+    }
+
+    fn table(&mut self, node: &SyntaxNode, body: &[SyntaxElement]) -> Table {
+        let spec_args = env_required_args(node);
+        // `tabularx` takes the total width before the column specification.
+        let spec = spec_args
+            .last()
+            .map(|arg| elements_text(&arg))
+            .unwrap_or_default();
+        let align = parse_column_spec(&spec);
+
+        let mut rows: Vec<Vec<TableCell>> = Vec::new();
+        let mut row: Vec<TableCell> = Vec::new();
+        let mut cell: Vec<SyntaxElement> = Vec::new();
+        let mut rules = 0usize;
+        let mut booktabs = false;
+        let mut header_rule = false;
+
+        for elem in body {
+            match elem.kind() {
+                SyntaxKind::TokenAmpersand => {
+                    row.push(self.table_cell(&cell));
+                    cell.clear();
+                }
+                SyntaxKind::ItemNewLine => {
+                    row.push(self.table_cell(&cell));
+                    cell.clear();
+                    rows.push(std::mem::take(&mut row));
+                }
+                SyntaxKind::ItemCmd => {
+                    let SyntaxElement::Node(child) = elem else {
+                        continue;
+                    };
+                    let name = command_name(child).unwrap_or_default();
+                    if RULE_COMMANDS.contains(&name.as_str()) {
+                        rules += 1;
+                        booktabs |= name != "hline" && name != "cline";
+                        if rows.len() == 1 && row.is_empty() && name != "bottomrule" {
+                            header_rule = true;
+                        }
+                    } else {
+                        cell.push(elem.clone());
+                    }
+                }
+                _ => cell.push(elem.clone()),
+            }
+        }
+        if cell.iter().any(|elem| !is_blank(elem)) {
+            row.push(self.table_cell(&cell));
+        }
+        if !row.is_empty() {
+            rows.push(row);
+        }
+
+        let columns = align
+            .len()
+            .max(
+                rows.iter()
+                    .map(|row| row.iter().map(|c| c.colspan).sum::<usize>())
+                    .max()
+                    .unwrap_or(0),
+            )
+            .max(1);
+        let header = header_rule && rows.len() > 1;
+        let mut cells = Vec::new();
+        for (idx, row) in rows.into_iter().enumerate() {
+            for mut cell in row {
+                cell.is_header = header && idx == 0;
+                cells.push(cell);
+            }
+        }
+        let stroke = if rules == 0 && !spec.contains('|') {
+            Some("none".to_string())
+        } else if booktabs {
+            Some("(top: 0.5pt, bottom: 0.5pt)".to_string())
+        } else {
+            None
+        };
+        Table {
+            columns,
+            cells,
+            align: (!align.is_empty()).then_some(align),
+            valign: None,
+            caption: None,
+            stroke,
+            fill: None,
+            inset: None,
+            label: None,
+            repeat_header: true,
+            widths: Vec::new(),
+            gutter: None,
+            row_gutter: None,
+            column_gutter: None,
+            rules: Vec::new(),
+        }
+    }
+
+    fn table_cell(&mut self, elements: &[SyntaxElement]) -> TableCell {
+        let mut colspan = 1;
+        let mut align = None;
+        let mut content = Vec::new();
+        for elem in elements {
+            if let SyntaxElement::Node(node) = elem {
+                if command_name(node).as_deref() == Some("multicolumn") {
+                    let args = required_args(node);
+                    colspan = arg_text(&args, 0).parse::<usize>().unwrap_or(1).max(1);
+                    align = parse_column_spec(&arg_text(&args, 1)).first().copied();
+                    content.extend(self.arg_inlines(&args, 2));
+                    continue;
+                }
+            }
+            self.inline_element(elem, &mut content);
+        }
+        let content = trim_inlines(normalize_inlines(content));
+        TableCell {
+            content,
+            blocks: None,
+            colspan,
+            rowspan: 1,
+            align,
+            valign: None,
+            is_header: false,
+            is_footer: false,
+            fill: None,
+            stroke: None,
+            inset: None,
+        }
+    }
+}
+
+fn flush_paragraph(
+    blocks: &mut Vec<Block>,
+    spans: &mut Vec<Option<Span>>,
+    inlines: &mut Vec<Inline>,
+    span: Option<Span>,
+) {
+    let content = trim_inlines(normalize_inlines(std::mem::take(inlines)));
+    if has_content(&content) {
+        blocks.push(Block::Paragraph(content));
+        spans.push(span);
+    }
+}
+
+fn attach_label(block: Option<&mut Block>, new_label: &str) -> bool {
+    let slot = match block {
+        Some(Block::Heading { label, .. }) => label,
+        Some(Block::MathBlock(math)) => &mut math.label,
+        Some(Block::Figure(figure)) => &mut figure.label,
+        _ => return false,
+    };
+    if slot.is_some() {
+        return false;
+    }
+    *slot = Some(new_label.to_string());
+    true
+}
+
+fn has_content(inlines: &[Inline]) -> bool {
+    inlines.iter().any(|inline| match inline {
+        Inline::Text(text) => !text.trim().is_empty(),
+        _ => true,
+    })
+}
+
+/// Merge adjacent text runs, collapse whitespace, and apply TeX ligatures.
+fn normalize_inlines(inlines: Vec<Inline>) -> Vec<Inline> {
+    let mut out: Vec<Inline> = Vec::with_capacity(inlines.len());
+    for inline in inlines {
+        match (out.last_mut(), inline) {
+            (Some(Inline::Text(prev)), Inline::Text(text)) => prev.push_str(&text),
+            (_, inline) => out.push(inline),
+        }
+    }
+    for inline in &mut out {
+        if let Inline::Text(text) = inline {
+            *text = normalize_text(text);
+        }
+    }
+    out
+}
+
+fn normalize_text(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut prev_space = false;
+    for ch in text.chars() {
+        if ch == ' ' || ch == '\t' || ch == '\n' {
+            if !prev_space {
+                collapsed.push(' ');
+            }
+            prev_space = true;
+        } else {
+            collapsed.push(ch);
+            prev_space = false;
+        }
+    }
+    collapsed
+        .replace("---", "\u{2014}")
+        .replace("--", "\u{2013}")
+        .replace("``", "\u{201c}")
+        .replace("''", "\u{201d}")
+}
+
+fn trim_inlines(mut inlines: Vec<Inline>) -> Vec<Inline> {
+    if let Some(Inline::Text(text)) = inlines.first_mut() {
+        *text = text.trim_start().to_string();
+    }
+    if let Some(Inline::Text(text)) = inlines.last_mut() {
+        *text = text.trim_end().to_string();
+    }
+    inlines.retain(|inline| !matches!(inline, Inline::Text(text) if text.is_empty()));
+    inlines
+}
+
+/// Plain-text rendering of a token in text mode; `None` for syntax-only tokens.
+fn token_text(kind: SyntaxKind, text: &str) -> Option<String> {
+    let mapped = match kind {
+        SyntaxKind::TokenLBrace
+        | SyntaxKind::TokenRBrace
+        | SyntaxKind::TokenComment
+        | SyntaxKind::TokenBeginMath
+        | SyntaxKind::TokenEndMath => return None,
+        SyntaxKind::TokenWhiteSpace | SyntaxKind::TokenLineBreak => " ".to_string(),
+        SyntaxKind::TokenTilde => "\u{a0}".to_string(),
+        SyntaxKind::TokenCommandSym => return command_symbol(text),
+        _ => text.to_string(),
+    };
+    Some(mapped)
+}
+
+fn command_symbol(text: &str) -> Option<String> {
+    let sym = text.strip_prefix('\\')?;
+    match sym {
+        "%" | "&" | "$" | "#" | "_" | "{" | "}" => Some(sym.to_string()),
+        "," | ";" | ":" | " " => Some(" ".to_string()),
+        _ => None,
+    }
+}
+
+fn node_span(node: &SyntaxNode) -> Span {
+    let range = node.text_range();
+    Span::new(usize::from(range.start()), usize::from(range.end()))
+}
+
+fn element_span(elem: &SyntaxElement) -> Span {
+    let range = elem.text_range();
+    Span::new(usize::from(range.start()), usize::from(range.end()))
+}
+
+fn elem_text(elem: &SyntaxElement) -> String {
+    match elem {
+        SyntaxElement::Node(node) => node.text().to_string(),
+        SyntaxElement::Token(token) => token.text().to_string(),
+    }
+}
+
+fn elements_text(elements: &[SyntaxElement]) -> String {
+    elements
+        .iter()
+        .map(elem_text)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn is_blank(elem: &SyntaxElement) -> bool {
+    matches!(
+        elem.kind(),
+        SyntaxKind::TokenWhiteSpace | SyntaxKind::TokenLineBreak | SyntaxKind::TokenComment
+    )
+}
+
+/// Root children with the parser's scope wrappers removed.
+fn flatten_scopes(node: &SyntaxNode) -> Vec<SyntaxElement> {
+    let mut out = Vec::new();
+    for child in node.children_with_tokens() {
+        match child {
+            SyntaxElement::Node(inner) if inner.kind() == SyntaxKind::ScopeRoot => {
+                out.extend(flatten_scopes(&inner));
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+fn find_environment(root: &SyntaxNode, name: &str) -> Option<SyntaxNode> {
+    root.descendants()
+        .find(|node| node.kind() == SyntaxKind::ItemEnv && env_name(node).as_deref() == Some(name))
+}
+
+fn env_name(node: &SyntaxNode) -> Option<String> {
+    EnvItem::cast(node.clone())?
+        .name_tok()
+        .map(|tok| tok.text().trim().to_string())
+}
+
+fn command_name(node: &SyntaxNode) -> Option<String> {
+    if node.kind() != SyntaxKind::ItemCmd {
+        return None;
+    }
+    CmdItem::cast(node.clone())?
+        .name_tok()
+        .map(|tok| tok.text().trim_start_matches('\\').to_string())
+}
+
+/// Environment content without the `\begin{..}` / `\end{..}` items.
+fn environment_body(node: &SyntaxNode) -> Vec<SyntaxElement> {
+    node.children_with_tokens()
+        .filter(|child| !matches!(child.kind(), SyntaxKind::ItemBegin | SyntaxKind::ItemEnd))
+        .collect()
+}
+
+/// Split list content on `\item`, pairing each item's optional `[term]`
+/// argument with the elements that follow it.
+fn split_items(body: &[SyntaxElement]) -> Vec<(Option<Vec<SyntaxElement>>, Vec<SyntaxElement>)> {
+    let mut items = Vec::new();
+    let mut current: Option<(Option<Vec<SyntaxElement>>, Vec<SyntaxElement>)> = None;
+    for elem in body {
+        if let SyntaxElement::Node(node) = elem {
+            if command_name(node).as_deref() == Some("item") {
+                items.extend(current.take());
+                let term = optional_args(node).first().map(arg_elements);
+                let mut elements = Vec::new();
+                // Content the parser attached to `\item` as a curly argument.
+                for arg in required_args(node) {
+                    elements.extend(arg_elements(&arg));
+                }
+                current = Some((term, elements));
+                continue;
+            }
+        }
+        if let Some((_, elements)) = current.as_mut() {
+            elements.push(elem.clone());
+        }
+    }
+    items.extend(current);
+    items
+}
+
+/// Drop an optional `[..]` argument that the parser left as loose tokens at
+/// the start of an environment body.
+fn skip_leading_bracket(body: &[SyntaxElement]) -> Vec<SyntaxElement> {
+    let start = body
+        .iter()
+        .position(|elem| !is_blank(elem))
+        .unwrap_or(body.len());
+    if body.get(start).map(|elem| elem.kind()) != Some(SyntaxKind::TokenLBracket) {
+        return body.to_vec();
+    }
+    match body[start..]
+        .iter()
+        .position(|elem| elem.kind() == SyntaxKind::TokenRBracket)
+    {
+        Some(end) => body[start + end + 1..].to_vec(),
+        None => body.to_vec(),
+    }
+}
+
+/// Optional `[..]` argument of `\begin{env}`, wherever the parser put it.
+fn env_optional_arg(node: &SyntaxNode) -> Option<Vec<SyntaxElement>> {
+    if let Some(begin) = node.children().find(|c| c.kind() == SyntaxKind::ItemBegin) {
+        if let Some(arg) = optional_args(&begin).first() {
+            return Some(arg_elements(arg));
+        }
+    }
+    let body = environment_body(node);
+    let start = body.iter().position(|elem| !is_blank(elem))?;
+    if body[start].kind() != SyntaxKind::TokenLBracket {
+        return None;
+    }
+    let end = body[start..]
+        .iter()
+        .position(|elem| elem.kind() == SyntaxKind::TokenRBracket)?;
+    Some(body[start + 1..start + end].to_vec())
+}
+
+fn env_required_args(node: &SyntaxNode) -> Vec<Vec<SyntaxElement>> {
+    node.children()
+        .find(|c| c.kind() == SyntaxKind::ItemBegin)
+        .map(|begin| required_args(&begin).iter().map(arg_elements).collect())
+        .unwrap_or_default()
+}
+
+/// The side and width of a `wrapfigure` or `wraptable`, from its
+/// `{placement}{width}` arguments. The outer and inner sides of two-sided
+/// documents are taken as right and left.
+fn wrap_args(node: &SyntaxNode) -> Option<(Side, Option<Length>)> {
+    if !matches!(env_name(node)?.as_str(), "wrapfigure" | "wraptable") {
+        return None;
+    }
+    let args = env_required_args(node);
+    let side = match elements_text(args.first()?).trim().chars().next()? {
+        'r' | 'R' | 'o' | 'O' => Side::Right,
+        _ => Side::Left,
+    };
+    let width = args
+        .get(1)
+        .and_then(|arg| Length::parse_latex(&elements_text(arg)));
+    Some((side, width))
+}
+
+/// Arguments of `\begin{env}` past its optional title, as written.
+fn env_args(node: &SyntaxNode) -> Vec<String> {
+    let Some(begin) = node.children().find(|c| c.kind() == SyntaxKind::ItemBegin) else {
+        return Vec::new();
+    };
+    let optional = optional_args(&begin)
+        .iter()
+        .skip(1)
+        .map(|arg| format!("[{}]", elements_text(&arg_elements(arg))))
+        .collect::<Vec<_>>();
+    let required = required_args(&begin)
+        .iter()
+        .map(|arg| format!("{{{}}}", elements_text(&arg_elements(arg))));
+    optional.into_iter().chain(required).collect()
+}
+
+fn is_bracket_arg(arg: &SyntaxNode) -> bool {
+    arg.children().any(|c| c.kind() == SyntaxKind::ItemBracket)
+}
+
+fn has_star_argument(node: &SyntaxNode) -> bool {
+    node.children()
+        .filter(|c| c.kind() == SyntaxKind::ClauseArgument && !is_bracket_arg(c))
+        .take(1)
+        .any(|arg| elements_text(&arg_elements(&arg)) == "*")
+}
+
+/// Curly arguments of a command, skipping a leading `*` the parser may
+/// have taken as an argument.
+fn required_args(node: &SyntaxNode) -> Vec<SyntaxNode> {
+    node.children()
+        .filter(|c| c.kind() == SyntaxKind::ClauseArgument && !is_bracket_arg(c))
+        .filter(|arg| elements_text(&arg_elements(arg)) != "*")
+        .collect()
+}
+
+fn optional_args(node: &SyntaxNode) -> Vec<SyntaxNode> {
+    node.children()
+        .filter(|c| c.kind() == SyntaxKind::ClauseArgument && is_bracket_arg(c))
+        .collect()
+}
+
+/// Argument content with its delimiters stripped.
+fn arg_elements(arg: &SyntaxNode) -> Vec<SyntaxElement> {
+    let mut out = Vec::new();
+    for child in arg.children_with_tokens() {
+        match child.kind() {
+            SyntaxKind::TokenLBrace
+            | SyntaxKind::TokenRBrace
+            | SyntaxKind::TokenLBracket
+            | SyntaxKind::TokenRBracket => {}
+            SyntaxKind::ItemCurly | SyntaxKind::ItemBracket => {
+                if let SyntaxElement::Node(inner) = &child {
+                    out.extend(arg_elements(inner));
+                }
+            }
+            _ => out.push(child),
+        }
+    }
+    out
+}
+
+fn arg_text(args: &[SyntaxNode], index: usize) -> String {
+    args.get(index)
+        .map(|arg| elements_text(&arg_elements(arg)))
+        .unwrap_or_default()
+}
+
+fn find_label(body: &[SyntaxElement]) -> Option<String> {
+    body.iter().find_map(|elem| match elem {
+        SyntaxElement::Node(node) if command_name(node).as_deref() == Some("label") => {
+            Some(arg_text(&required_args(node), 0))
+        }
+        _ => None,
+    })
+}
+
+/// Source of a display math environment with any `\label` pulled out.
+fn math_source(body: &[SyntaxElement]) -> (String, Option<String>) {
+    let mut source = String::new();
+    let mut label = None;
+    for elem in body {
+        if let SyntaxElement::Node(node) = elem {
+            if command_name(node).as_deref() == Some("label") {
+                label.get_or_insert_with(|| arg_text(&required_args(node), 0));
+                continue;
+            }
+        }
+        source.push_str(&elem_text(elem));
+    }
+    (source.trim().to_string(), label)
+}
+
+fn verbatim_text(body: &[SyntaxElement]) -> String {
+    let text: String = skip_leading_bracket(body).iter().map(elem_text).collect();
+    let text = text
+        .strip_prefix("\r\n")
+        .or_else(|| text.strip_prefix('\n'))
+        .unwrap_or(&text);
+    text.trim_end().to_string()
+}
+
+/// `\begin{minted}{lang}` or `\begin{lstlisting}[language=lang]`.
+fn code_language(name: &str, node: &SyntaxNode) -> Option<String> {
+    let lang = match name {
+        "minted" => env_required_args(node)
+            .first()
+            .map(|arg| elements_text(arg)),
+        "lstlisting" => env_optional_arg(node).and_then(|arg| {
+            elements_text(&arg).split(',').find_map(|option| {
+                let (key, value) = option.split_once('=')?;
+                (key.trim() == "language").then(|| value.trim().to_string())
+            })
+        }),
+        _ => None,
+    }?;
+    let lang = lang.trim().trim_matches(['{', '}']).to_string();
+    (!lang.is_empty()).then_some(lang)
+}
+
+fn image(node: &SyntaxNode, args: &[SyntaxNode]) -> Image {
+    let mut width = None;
+    let mut height = None;
+    let mut alt = None;
+    if let Some(options) = optional_args(node).first() {
+        let options = elements_text(&arg_elements(options));
+        for option in split_options(&options) {
+            let Some((key, value)) = option.split_once('=') else {
+                continue;
+            };
+            match key.trim() {
+                "width" => width = Some(convert_length(value.trim())),
+                "height" => height = Some(convert_length(value.trim())),
+                "alt" => alt = Some(value.trim().trim_matches(['{', '}']).to_string()),
+                _ => {}
+            }
+        }
+    }
+    Image {
+        path: arg_text(args, 0),
+        width,
+        height,
+        fit: None,
+        alt,
+    }
+}
+
+/// Split a `key=value` list at the commas outside braces.
+fn split_options(options: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (idx, ch) in options.char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&options[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&options[start..]);
+    parts
+}
+
+/// `0.5\linewidth` style lengths become relative widths and units Typst
+/// lacks are converted; what is not a length passes through unchanged.
+fn convert_length(value: &str) -> String {
+    Length::parse_latex(value).map_or_else(|| value.to_string(), |length| length.to_typst())
+}
+
+/// Column alignments of a tabular spec such as `|l|c|p{3cm}|`.
+fn parse_column_spec(spec: &str) -> Vec<Alignment> {
+    let mut out = Vec::new();
+    let mut chars = spec.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            'l' | 'p' | 'm' | 'b' | 'X' => out.push(Alignment::Left),
+            'c' => out.push(Alignment::Center),
+            'r' => out.push(Alignment::Right),
+            _ => {}
+        }
+        // Skip `{..}` groups such as `p{3cm}` or `@{}`.
+        if chars.peek() == Some(&'{') {
+            let mut depth = 0;
+            for next in chars.by_ref() {
+                match next {
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    out
+}
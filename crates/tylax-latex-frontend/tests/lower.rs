@@ -0,0 +1,212 @@
+use mitex_parser::spec::{preludes::command::define_normal_env, CommandSpec};
+use mitex_spec_gen::DEFAULT_SPEC;
+use tylax_ir::{
+    Block, CancellationToken, CaptionPosition, ConversionLimits, FigureContent, Inline, ListKind,
+    Side,
+};
+use tylax_latex_frontend::{latex_to_ir, latex_to_ir_with, LatexFrontendOptions};
+
+#[test]
+fn lowers_document_body_only() {
+    let input = "\\documentclass{article}\n\\usepackage{amsmath}\n\\begin{document}\n\\section{Intro}\\label{sec:intro}\nSome \\textbf{bold} and \\emph{soft} text.\n\n\\subsection*{Aside}\n\\end{document}\n";
+    let doc = latex_to_ir(input);
+    assert_eq!(doc.blocks.len(), 3);
+    assert!(matches!(
+        &doc.blocks[0],
+        Block::Heading { level: 1, numbered: true, label: Some(label), .. } if label == "sec:intro"
+    ));
+    let Block::Paragraph(inlines) = &doc.blocks[1] else {
+        panic!("expected paragraph, got {:?}", doc.blocks[1]);
+    };
+    assert!(inlines.contains(&Inline::Strong(vec![Inline::text("bold")])));
+    assert!(inlines.contains(&Inline::Emph(vec![Inline::text("soft")])));
+    assert!(matches!(
+        &doc.blocks[2],
+        Block::Heading {
+            level: 2,
+            numbered: false,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn lowers_lists_and_math() {
+    let input = "\\begin{enumerate}\n\\item First $x^2$\n\\item Second\n\\end{enumerate}\n\\begin{equation}\nE = mc^2 \\label{eq:e}\n\\end{equation}\n";
+    let doc = latex_to_ir(input);
+    let Block::List { kind, items } = &doc.blocks[0] else {
+        panic!("expected list, got {:?}", doc.blocks[0]);
+    };
+    assert_eq!(*kind, ListKind::Ordered);
+    assert_eq!(items.len(), 2);
+    assert!(matches!(
+        &items[0][0],
+        Block::Paragraph(inlines) if inlines.contains(&Inline::Math("x^2".to_string()))
+    ));
+    let Block::MathBlock(math) = &doc.blocks[1] else {
+        panic!("expected math block, got {:?}", doc.blocks[1]);
+    };
+    assert_eq!(math.content, "E = mc^2");
+    assert_eq!(math.label.as_deref(), Some("eq:e"));
+}
+
+#[test]
+fn lowers_description_to_term_list() {
+    let input = "\\begin{description}\n\\item[Alpha] First letter.\n\\item[Beta] Second.\n\\end{description}\n";
+    let doc = latex_to_ir(input);
+    let Block::TermList(items) = &doc.blocks[0] else {
+        panic!("expected term list, got {:?}", doc.blocks[0]);
+    };
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].term, vec![Inline::text("Alpha")]);
+    assert!(matches!(
+        items[1].description.as_slice(),
+        [Block::Paragraph(inlines)] if inlines == &vec![Inline::text("Second.")]
+    ));
+}
+
+#[test]
+fn lowers_figures_and_tables() {
+    let input = "\\begin{figure}[t]\n\\centering\n\\includegraphics[width=0.5\\linewidth, alt={A plot, in color}]{plot.png}\n\\caption{A plot}\\label{fig:plot}\n\\end{figure}\n\\begin{tabular}{lc}\n\\hline\nA & B \\\\\n\\hline\n1 & 2 \\\\\n\\end{tabular}\n";
+    let doc = latex_to_ir(input);
+    let Block::Figure(figure) = &doc.blocks[0] else {
+        panic!("expected figure, got {:?}", doc.blocks[0]);
+    };
+    let FigureContent::Image(image) = &figure.content else {
+        panic!("expected image, got {:?}", figure.content);
+    };
+    assert_eq!(image.path, "plot.png");
+    assert_eq!(image.width.as_deref(), Some("50%"));
+    assert_eq!(image.alt.as_deref(), Some("A plot, in color"));
+    assert_eq!(figure.caption, Some(vec![Inline::text("A plot")]));
+    assert_eq!(figure.label.as_deref(), Some("fig:plot"));
+    assert_eq!(figure.placement.as_deref(), Some("t"));
+
+    let Block::Table(table) = &doc.blocks[1] else {
+        panic!("expected table, got {:?}", doc.blocks[1]);
+    };
+    assert_eq!(table.columns, 2);
+    assert_eq!(table.cells.len(), 4);
+    assert!(table.cells[0].is_header);
+    assert_eq!(table.cells[3].content, vec![Inline::text("2")]);
+}
+
+#[test]
+fn wrapfigure_keeps_its_side_and_width() {
+    let input = "\\begin{wrapfigure}[10]{r}{0.4\\textwidth}\n\\centering\n\\includegraphics[width=\\linewidth]{plot.png}\n\\caption{A plot}\n\\end{wrapfigure}\n";
+    let doc = latex_to_ir(input);
+    let Block::Figure(figure) = &doc.blocks[0] else {
+        panic!("expected figure, got {:?}", doc.blocks[0]);
+    };
+    assert_eq!(figure.wrap, Some(Side::Right));
+    assert_eq!(figure.placement, None);
+    let FigureContent::Image(image) = &figure.content else {
+        panic!("expected image, got {:?}", figure.content);
+    };
+    assert_eq!(image.width.as_deref(), Some("40%"));
+}
+
+#[test]
+fn caption_keeps_its_short_form_and_position() {
+    let input = "\\begin{table}\n\\caption[Short]{A long caption}\n\\begin{tabular}{l}\nA \\\\\n\\end{tabular}\n\\end{table}\n";
+    let doc = latex_to_ir(input);
+    let Block::Figure(figure) = &doc.blocks[0] else {
+        panic!("expected figure, got {:?}", doc.blocks[0]);
+    };
+    assert_eq!(figure.caption, Some(vec![Inline::text("A long caption")]));
+    assert_eq!(figure.short_caption, Some(vec![Inline::text("Short")]));
+    assert_eq!(figure.caption_position, Some(CaptionPosition::Top));
+}
+
+#[test]
+fn unsupported_commands_are_reported() {
+    let input = "Keep \\mystery{this} text.";
+    let doc = latex_to_ir(input);
+    assert_eq!(doc.losses.len(), 1);
+    assert_eq!(doc.losses[0].kind, "command");
+    let span = doc.losses[0].span.expect("loss span");
+    assert!(input[span.start..span.end].starts_with("\\mystery"));
+    let Block::Paragraph(inlines) = &doc.blocks[0] else {
+        panic!("expected paragraph, got {:?}", doc.blocks[0]);
+    };
+    let text: String = inlines
+        .iter()
+        .filter_map(|inline| match inline {
+            Inline::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(text, "Keep this text.");
+}
+
+#[test]
+fn conversion_stops_at_its_limits() {
+    let input = "One.\n\nTwo.\n\nThree.\n\nFour.\n\nFive.";
+    let options = LatexFrontendOptions {
+        limits: ConversionLimits {
+            max_blocks: Some(2),
+            ..ConversionLimits::default()
+        },
+        ..LatexFrontendOptions::default()
+    };
+    let doc = latex_to_ir_with(input, &options);
+    assert!(doc.blocks.len() < 5, "{:?}", doc.blocks);
+    let limits: Vec<_> = doc
+        .losses
+        .iter()
+        .filter(|loss| loss.kind == "limit")
+        .collect();
+    assert_eq!(limits.len(), 1);
+    assert_eq!(limits[0].span.expect("limit span").end, input.len());
+
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let options = LatexFrontendOptions {
+        cancel: Some(cancel),
+        ..LatexFrontendOptions::default()
+    };
+    let doc = latex_to_ir_with(input, &options);
+    assert!(doc.blocks.is_empty());
+    assert_eq!(doc.losses[0].kind, "limit");
+}
+
+#[test]
+fn configured_environments_keep_their_arguments() {
+    // The spec gives `problem` its one required argument.
+    let spec = CommandSpec::new(
+        DEFAULT_SPEC
+            .items()
+            .map(|(name, item)| (name.to_string(), item.clone()))
+            .chain([("problem".to_string(), define_normal_env(Some(1), "problem"))])
+            .collect(),
+    );
+    let input = "\\begin{problem}{10}\nSolve it.\n\\end{problem}\n";
+    let options = LatexFrontendOptions {
+        spec: Some(spec.clone()),
+        environments: vec!["problem".to_string()],
+        ..LatexFrontendOptions::default()
+    };
+    let doc = latex_to_ir_with(input, &options);
+    let Some(Block::Environment(environment)) = doc.blocks.first() else {
+        panic!("expected environment, got {:?}", doc.blocks);
+    };
+    assert_eq!(environment.name, "problem");
+    assert_eq!(environment.args, ["{10}"]);
+    assert!(
+        matches!(environment.blocks.as_slice(), [Block::Paragraph(_)]),
+        "{:?}",
+        environment.blocks
+    );
+
+    // Environments not named are not kept.
+    let options = LatexFrontendOptions {
+        spec: Some(spec),
+        ..LatexFrontendOptions::default()
+    };
+    let doc = latex_to_ir_with(input, &options);
+    assert!(
+        !matches!(doc.blocks.first(), Some(Block::Environment(_))),
+        "{:?}",
+        doc.blocks
+    );
+}
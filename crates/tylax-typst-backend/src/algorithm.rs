@@ -0,0 +1,93 @@
+//! Pseudocode set as a figure of its own kind: a line to each step, the
+//! steps under it indented, and the blocks it opens closed as
+//! algpseudocode closes them.
+
+use tylax_ir::{Algorithm, AlgorithmKeyword, AlgorithmStep};
+
+use crate::{indent, push_label, Renderer};
+
+pub(crate) fn render_algorithm(renderer: &mut Renderer, algorithm: &Algorithm) -> String {
+    let mut lines = Vec::new();
+    push_steps(renderer, &mut lines, &algorithm.steps, 0);
+    if algorithm.line_numbers {
+        for (idx, line) in lines.iter_mut().enumerate() {
+            *line = format!("#box(width: 1.5em)[{}] {}", idx + 1, line);
+        }
+    }
+    let mut args = vec![
+        "kind: \"algorithm\"".to_string(),
+        "supplement: [Algorithm]".to_string(),
+    ];
+    if let Some(caption) = &algorithm.caption {
+        args.push(format!("caption: [{}]", renderer.inlines(caption)));
+    }
+    args.push(format!("align(left)[\n{}\n]", lines.join(" \\\n")));
+    let mut out = format!("#figure(\n{},\n)", indent(&args.join(",\n")));
+    push_label(&mut out, algorithm.label.as_deref());
+    out
+}
+
+/// Push the lines of sibling steps. An `if` is closed after the `else if`
+/// and `else` steps that follow it, and a `repeat` by its `until`.
+fn push_steps(
+    renderer: &mut Renderer,
+    lines: &mut Vec<String>,
+    steps: &[AlgorithmStep],
+    depth: usize,
+) {
+    for (idx, step) in steps.iter().enumerate() {
+        let content = renderer.inlines(&step.content).trim().to_string();
+        let line = match step.keyword {
+            AlgorithmKeyword::Require => format!("*Require:* {}", content),
+            AlgorithmKeyword::Ensure => format!("*Ensure:* {}", content),
+            AlgorithmKeyword::Input => format!("*Input:* {}", content),
+            AlgorithmKeyword::Output => format!("*Output:* {}", content),
+            AlgorithmKeyword::If => format!("*if* {} *then*", content),
+            AlgorithmKeyword::ElseIf => format!("*else if* {} *then*", content),
+            AlgorithmKeyword::Else => "*else*".to_string(),
+            AlgorithmKeyword::For => format!("*for* {} *do*", content),
+            AlgorithmKeyword::ForAll => format!("*for all* {} *do*", content),
+            AlgorithmKeyword::While => format!("*while* {} *do*", content),
+            AlgorithmKeyword::Repeat => "*repeat*".to_string(),
+            AlgorithmKeyword::Until => format!("*until* {}", content),
+            AlgorithmKeyword::Return => format!("*return* {}", content),
+            AlgorithmKeyword::Function => format!("*function* {}", content),
+            AlgorithmKeyword::Procedure => format!("*procedure* {}", content),
+            _ => content,
+        };
+        let comment = step
+            .comment
+            .as_ref()
+            .map(|comment| format!(" #h(1fr) ▷ {}", renderer.inlines(comment).trim()))
+            .unwrap_or_default();
+        push_line(lines, depth, &format!("{}{}", line, comment));
+        push_steps(renderer, lines, &step.body, depth + 1);
+        let next = steps.get(idx + 1).map(|next| next.keyword);
+        let closing = match step.keyword {
+            AlgorithmKeyword::If | AlgorithmKeyword::ElseIf | AlgorithmKeyword::Else
+                if !matches!(
+                    next,
+                    Some(AlgorithmKeyword::ElseIf | AlgorithmKeyword::Else)
+                ) =>
+            {
+                Some("*end if*")
+            }
+            AlgorithmKeyword::For | AlgorithmKeyword::ForAll => Some("*end for*"),
+            AlgorithmKeyword::While => Some("*end while*"),
+            AlgorithmKeyword::Function => Some("*end function*"),
+            AlgorithmKeyword::Procedure => Some("*end procedure*"),
+            _ => None,
+        };
+        if let Some(closing) = closing {
+            push_line(lines, depth, closing);
+        }
+    }
+}
+
+fn push_line(lines: &mut Vec<String>, depth: usize, line: &str) {
+    if depth == 0 {
+        lines.push(line.trim_end().to_string());
+    } else {
+        lines.push(format!("#h({}em) {}", depth, line.trim_end()));
+    }
+}
@@ -0,0 +1,1146 @@
+//! IR to Typst backend.
+
+use tylax_color::Color;
+use tylax_ir::{
+    Alignment, Block, CaptionPosition, Citation, CiteForm, ColumnWidth, Document, EnvironmentBlock,
+    Figure, FigureContent, Image, Inline, LinkTarget, ListKind, MathBlock, OutlineTarget,
+    PageDecoration, RuleAxis, Table, TableCell, VerticalAlignment,
+};
+use tylax_units::Length;
+
+mod algorithm;
+
+use algorithm::render_algorithm;
+
+#[derive(Debug, Clone, Default)]
+pub struct TypstRenderOptions {
+    /// Set the page, text and heading settings of the document's metadata
+    /// and its title block before the content, and import the packages the
+    /// content calls.
+    pub full_document: bool,
+}
+
+/// Packages whose functions the rendered content calls.
+#[derive(Default)]
+struct Packages {
+    mitex: bool,
+    unify: bool,
+    whalogen: bool,
+    hydra: bool,
+    polylux: bool,
+}
+
+const BLOCK_SEPARATOR: &str = "\n\n";
+
+pub fn render_document(doc: &Document, options: TypstRenderOptions) -> String {
+    let mut renderer = Renderer::default();
+    let body = renderer.blocks(&doc.blocks);
+    if !options.full_document {
+        return body;
+    }
+    let mut parts = renderer.preamble(doc);
+    parts.push(body);
+    parts.retain(|part| !part.is_empty());
+    parts.join(BLOCK_SEPARATOR)
+}
+
+#[derive(Default)]
+struct Renderer {
+    packages: Packages,
+}
+
+impl Renderer {
+    /// Imports, settings and the title block of a full document.
+    fn preamble(&mut self, doc: &Document) -> Vec<String> {
+        let meta = &doc.meta;
+        let mut settings = Vec::new();
+        let mut page = Vec::new();
+        if let Some(paper) = &meta.paper {
+            page.push(format!("paper: {}", string(paper)));
+        }
+        let margins: Vec<String> = [
+            ("top", &meta.margin.top),
+            ("bottom", &meta.margin.bottom),
+            ("left", &meta.margin.left),
+            ("right", &meta.margin.right),
+        ]
+        .into_iter()
+        .filter_map(|(side, length)| Some(format!("{}: {}", side, length_arg(length.as_deref()?)?)))
+        .collect();
+        if !margins.is_empty() {
+            page.push(format!("margin: ({})", margins.join(", ")));
+        }
+        if let Some(numbering) = &meta.page_numbering {
+            page.push(format!("numbering: {}", string(numbering)));
+        }
+        if let Some(columns) = meta.columns.filter(|columns| *columns > 1) {
+            page.push(format!("columns: {}", columns));
+        }
+        if let Some(header) = &meta.header {
+            page.push(format!("header: {}", self.decoration(header)));
+        }
+        if let Some(footer) = &meta.footer {
+            page.push(format!("footer: {}", self.decoration(footer)));
+        }
+        if !page.is_empty() {
+            settings.push(format!("#set page({})", page.join(", ")));
+        }
+        let mut text = Vec::new();
+        if let Some(size) = meta.font_size.as_deref().and_then(length_arg) {
+            text.push(format!("size: {}", size));
+        }
+        if let Some(lang) = &meta.lang {
+            text.push(format!("lang: {}", string(lang)));
+        }
+        if !text.is_empty() {
+            settings.push(format!("#set text({})", text.join(", ")));
+        }
+        if let Some(justify) = meta.justify {
+            settings.push(format!("#set par(justify: {})", justify));
+        }
+        // Headings are numbered by the document's setting; those that are
+        // not numbered opt out one by one.
+        let numbered = doc
+            .blocks
+            .iter()
+            .any(|block| matches!(block, Block::Heading { numbered: true, .. }));
+        if numbered {
+            let numbering = meta.heading_numbering.as_deref().unwrap_or("1.");
+            settings.push(format!("#set heading(numbering: {})", string(numbering)));
+        }
+        // Only numbered equations can be referred to.
+        let labeled_math = doc
+            .blocks
+            .iter()
+            .any(|block| matches!(block, Block::MathBlock(MathBlock { label: Some(_), .. })));
+        if labeled_math {
+            settings.push("#set math.equation(numbering: \"(1)\")".to_string());
+        }
+
+        let title = self.title_block(doc);
+        vec![
+            self.packages.imports().join("\n"),
+            settings.join("\n"),
+            title,
+        ]
+    }
+
+    /// The title, authors and date, centered, then the abstract.
+    fn title_block(&mut self, doc: &Document) -> String {
+        let meta = &doc.meta;
+        let mut lines = Vec::new();
+        if let Some(title) = &meta.title {
+            lines.push(format!(
+                "#text(size: 17pt, weight: \"bold\")[{}]",
+                self.inlines(title)
+            ));
+        }
+        if !meta.authors.is_empty() {
+            let authors: Vec<String> = meta
+                .authors
+                .iter()
+                .map(|author| {
+                    let mut lines = vec![escape_text(&author.name)];
+                    if let Some(affiliation) = &author.affiliation {
+                        lines.push(escape_text(affiliation));
+                    }
+                    if let Some(email) = &author.email {
+                        lines.push(format!("#link({})", string(&format!("mailto:{}", email))));
+                    }
+                    format!("[{}]", lines.join(" \\ "))
+                })
+                .collect();
+            lines.push(format!(
+                "#grid(columns: {}, gutter: 1em, {})",
+                authors.len().min(3),
+                authors.join(", ")
+            ));
+        }
+        if let Some(date) = &meta.date {
+            lines.push(escape_text(date));
+        }
+        let mut out = String::new();
+        if !lines.is_empty() {
+            out.push_str(&format!("#align(center)[\n{}\n]", lines.join("\n\n")));
+        }
+        if !meta.abstract_content.is_empty() {
+            if !out.is_empty() {
+                out.push_str(BLOCK_SEPARATOR);
+            }
+            out.push_str("#heading(outlined: false, numbering: none)[Abstract]");
+            out.push_str(BLOCK_SEPARATOR);
+            out.push_str(&self.blocks(&meta.abstract_content));
+        }
+        out
+    }
+
+    /// A page header or footer: its left, center and right parts spread
+    /// over the line, with the even pages' own when they have one.
+    fn decoration(&mut self, decoration: &PageDecoration) -> String {
+        let odd = self.decoration_line(decoration);
+        match &decoration.even {
+            Some(even) => format!(
+                "context if calc.even(here().page()) {} else {}",
+                self.decoration_line(even),
+                odd
+            ),
+            None => odd,
+        }
+    }
+
+    fn decoration_line(&mut self, decoration: &PageDecoration) -> String {
+        let parts = [
+            self.inlines(&decoration.left),
+            self.inlines(&decoration.center),
+            self.inlines(&decoration.right),
+        ];
+        format!("[{}]", parts.join(" #h(1fr) ").trim())
+    }
+
+    fn blocks(&mut self, blocks: &[Block]) -> String {
+        let rendered: Vec<String> = blocks
+            .iter()
+            .map(|block| self.block(block))
+            .filter(|block| !block.is_empty())
+            .collect();
+        rendered.join(BLOCK_SEPARATOR)
+    }
+
+    fn block(&mut self, block: &Block) -> String {
+        match block {
+            Block::Paragraph(inlines) => escape_line_start(self.inlines(inlines).trim()),
+            Block::VSpace(length) => match length_arg(length) {
+                Some(length) => format!("#v({})", length),
+                None => String::new(),
+            },
+            Block::Heading {
+                level,
+                content,
+                numbered,
+                label,
+                outlined,
+                bookmarked,
+            } => {
+                let content = self.inlines(content);
+                let mut args = Vec::new();
+                if !numbered {
+                    args.push("numbering: none".to_string());
+                }
+                if !outlined {
+                    args.push("outlined: false".to_string());
+                }
+                if let Some(bookmarked) = bookmarked.filter(|bookmarked| bookmarked != outlined) {
+                    args.push(format!("bookmarked: {}", bookmarked));
+                }
+                let mut out = if args.is_empty() {
+                    format!(
+                        "{} {}",
+                        "=".repeat((*level).max(1) as usize),
+                        content.trim()
+                    )
+                } else {
+                    format!(
+                        "#heading(level: {}, {})[{}]",
+                        (*level).max(1),
+                        args.join(", "),
+                        content.trim()
+                    )
+                };
+                push_label(&mut out, label.as_deref());
+                out
+            }
+            Block::List { kind, items } => {
+                let marker = match kind {
+                    ListKind::Ordered => "+",
+                    _ => "-",
+                };
+                let items: Vec<String> = items
+                    .iter()
+                    .map(|item| list_item(marker, &self.blocks(item)))
+                    .collect();
+                items.join("\n")
+            }
+            Block::TermList(items) => {
+                let items: Vec<String> = items
+                    .iter()
+                    .map(|item| {
+                        let term = self.inlines(&item.term);
+                        let marker = format!("/ {}:", term.trim());
+                        list_item(&marker, &self.blocks(&item.description))
+                    })
+                    .collect();
+                items.join("\n")
+            }
+            Block::MathBlock(math) => {
+                let mut out = if math.latex {
+                    self.packages.mitex = true;
+                    format!("#mitex({})", string(math.content.trim()))
+                } else {
+                    format!("$ {} $", math.content.trim())
+                };
+                push_label(&mut out, math.label.as_deref());
+                out
+            }
+            Block::CodeBlock { lang, content } => {
+                let fence = "`".repeat(longest_run(content, '`').max(2) + 1);
+                format!(
+                    "{}{}\n{}\n{}",
+                    fence,
+                    lang.as_deref().unwrap_or(""),
+                    content.trim_end_matches('\n'),
+                    fence
+                )
+            }
+            Block::Quote(blocks) => {
+                format!("#quote(block: true)[\n{}\n]", self.blocks(blocks))
+            }
+            Block::Align {
+                alignment,
+                vertical,
+                blocks,
+            } => {
+                let alignment = [
+                    alignment.map(alignment_name),
+                    vertical.map(vertical_alignment_name),
+                ];
+                let alignment: Vec<&str> = alignment.into_iter().flatten().collect();
+                if alignment.is_empty() {
+                    return self.blocks(blocks);
+                }
+                format!(
+                    "#align({})[\n{}\n]",
+                    alignment.join(" + "),
+                    self.blocks(blocks)
+                )
+            }
+            Block::Table(table) => {
+                let expression = self.table(table);
+                if table.caption.is_none() && table.label.is_none() {
+                    return format!("#{}", expression);
+                }
+                let mut out = format!("#figure(\n{},", indent(&expression));
+                if let Some(caption) = &table.caption {
+                    out.push_str(&format!("\n  caption: [{}],", self.inlines(caption)));
+                }
+                out.push_str("\n)");
+                push_label(&mut out, table.label.as_deref());
+                out
+            }
+            Block::Figure(figure) => self.figure(figure),
+            Block::Environment(environment) => self.environment(environment),
+            Block::Bibliography {
+                file,
+                style,
+                full,
+                title,
+            } => {
+                let files: Vec<String> = file
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|file| !file.is_empty())
+                    .map(|file| {
+                        if file.contains('.') {
+                            string(file)
+                        } else {
+                            string(&format!("{}.bib", file))
+                        }
+                    })
+                    .collect();
+                let mut args = vec![match files.len() {
+                    1 => files[0].clone(),
+                    _ => format!("({})", files.join(", ")),
+                }];
+                if let Some(title) = title {
+                    args.push(format!("title: {}", self.optional_content(title)));
+                }
+                if let Some(style) = style.as_deref().and_then(bibliography_style) {
+                    args.push(format!("style: {}", string(style)));
+                }
+                if *full {
+                    args.push("full: true".to_string());
+                }
+                format!("#bibliography({})", args.join(", "))
+            }
+            Block::Outline { title, target } => {
+                let mut args = Vec::new();
+                let default_title = match target {
+                    OutlineTarget::Figures => Some("List of Figures"),
+                    OutlineTarget::Tables => Some("List of Tables"),
+                    OutlineTarget::Listings => Some("List of Listings"),
+                    _ => None,
+                };
+                match (title, default_title) {
+                    (Some(title), _) => {
+                        args.push(format!("title: {}", self.optional_content(title)))
+                    }
+                    (None, Some(title)) => args.push(format!("title: [{}]", title)),
+                    (None, None) => {}
+                }
+                match target {
+                    OutlineTarget::Figures => {
+                        args.push("target: figure.where(kind: image)".to_string())
+                    }
+                    OutlineTarget::Tables => {
+                        args.push("target: figure.where(kind: table)".to_string())
+                    }
+                    OutlineTarget::Listings => {
+                        args.push("target: figure.where(kind: raw)".to_string())
+                    }
+                    _ => {}
+                }
+                format!("#outline({})", args.join(", "))
+            }
+            Block::Box(inner) => format!("#box[\n{}\n]", self.blocks(&inner.blocks)),
+            Block::Block(inner) => format!("#block[\n{}\n]", self.blocks(&inner.blocks)),
+            Block::Columns(columns) => format!(
+                "#columns({})[\n{}\n]",
+                columns.columns.max(1),
+                self.blocks(&columns.blocks)
+            ),
+            Block::Grid(grid) => {
+                let mut args = vec![format!("columns: {}", grid.columns.max(1))];
+                for (name, value) in [
+                    ("gutter", &grid.gutter),
+                    ("row-gutter", &grid.row_gutter),
+                    ("column-gutter", &grid.column_gutter),
+                ] {
+                    if let Some(value) = value.as_deref().and_then(length_arg) {
+                        args.push(format!("{}: {}", name, value));
+                    }
+                }
+                for cell in &grid.cells {
+                    args.push(format!("[{}]", self.blocks(cell)));
+                }
+                format!("#grid(\n{},\n)", indent(&args.join(",\n")))
+            }
+            Block::Algorithm(algorithm) => render_algorithm(self, algorithm),
+            Block::Slide(slide) => {
+                self.packages.polylux = true;
+                let mut body = String::new();
+                if let Some(title) = &slide.title {
+                    body.push_str(&format!("== {}", self.inlines(title).trim()));
+                    body.push_str(BLOCK_SEPARATOR);
+                }
+                body.push_str(&self.blocks(&slide.blocks));
+                format!("#polylux-slide[\n{}\n]", body.trim_end())
+            }
+            Block::SideBySide(side_by_side) => {
+                let widths: Vec<String> = (0..side_by_side.columns.len())
+                    .map(|i| match side_by_side.widths.get(i) {
+                        Some(width) => column_width(width),
+                        None => "1fr".to_string(),
+                    })
+                    .collect();
+                let mut args = vec![
+                    format!("columns: ({},)", widths.join(", ")),
+                    "gutter: 1em".to_string(),
+                ];
+                for column in &side_by_side.columns {
+                    args.push(format!("[{}]", self.blocks(column)));
+                }
+                format!("#grid(\n{},\n)", indent(&args.join(",\n")))
+            }
+            Block::Unsupported { source, reason } => render_unsupported(source, reason),
+            _ => String::new(),
+        }
+    }
+
+    /// A figure with its caption, placement and numbering, then its label.
+    /// An image with nothing else to it is set as it is.
+    fn figure(&mut self, figure: &Figure) -> String {
+        if let FigureContent::Image(image) = &figure.content {
+            if figure.caption.is_none() && figure.label.is_none() && figure.placement.is_none() {
+                return format!("#{}", image_call(image));
+            }
+        }
+        let mut out = format!("#{}", self.figure_call(figure, false));
+        push_label(&mut out, figure.label.as_deref());
+        out
+    }
+
+    /// `figure(..)` in code mode, without its label. A subfigure is of a
+    /// kind of its own, numbered by letter.
+    fn figure_call(&mut self, figure: &Figure, sub: bool) -> String {
+        let content = match &figure.content {
+            FigureContent::Table(table) => self.table(table),
+            FigureContent::Image(image) => image_call(image),
+            FigureContent::Raw(blocks) => format!("[\n{}\n]", self.blocks(blocks)),
+            FigureContent::SubFigures { figures, columns } => {
+                let mut args = vec![
+                    format!("columns: {}", (*columns).max(1)),
+                    "gutter: 1em".to_string(),
+                ];
+                for sub in figures {
+                    let mut cell = format!("[#{}", self.figure_call(sub, true));
+                    push_label(&mut cell, sub.label.as_deref());
+                    cell.push(']');
+                    args.push(cell);
+                }
+                format!("grid(\n{},\n)", indent(&args.join(",\n")))
+            }
+            _ => String::new(),
+        };
+        let mut args = vec![content];
+        if let Some(caption) = &figure.caption {
+            let caption = self.inlines(caption);
+            args.push(match figure.caption_position {
+                Some(CaptionPosition::Top) => {
+                    format!("caption: figure.caption(position: top)[{}]", caption)
+                }
+                _ => format!("caption: [{}]", caption),
+            });
+        }
+        if let Some(placement) = figure.placement.as_deref().and_then(placement) {
+            args.push(format!("placement: {}", placement));
+        }
+        if sub {
+            args.push("kind: \"subfigure\"".to_string());
+            args.push("supplement: none".to_string());
+        }
+        if figure.unnumbered {
+            args.push("numbering: none".to_string());
+        } else if let Some(numbering) = &figure.numbering {
+            args.push(format!("numbering: {}", string(numbering)));
+        } else if sub {
+            args.push("numbering: \"(a)\"".to_string());
+        }
+        format!("figure(\n{},\n)", indent(&args.join(",\n")))
+    }
+
+    /// `table(..)` in code mode: its settings, then its cells a row to a
+    /// line, with the header and footer rows grouped.
+    fn table(&mut self, table: &Table) -> String {
+        let columns = table.columns.max(1);
+        let mut args = Vec::new();
+        if table
+            .widths
+            .iter()
+            .all(|width| matches!(width, ColumnWidth::Auto))
+        {
+            args.push(format!("columns: {}", columns));
+        } else {
+            let widths: Vec<String> = (0..columns)
+                .map(|i| table.widths.get(i).map_or("auto".to_string(), column_width))
+                .collect();
+            args.push(format!("columns: ({},)", widths.join(", ")));
+        }
+        let vertical = table.valign.map(vertical_alignment_name);
+        match (&table.align, vertical) {
+            (Some(align), _) if !align.is_empty() => {
+                let align: Vec<String> = align
+                    .iter()
+                    .map(|alignment| match vertical {
+                        Some(vertical) => format!("{} + {}", alignment_name(*alignment), vertical),
+                        None => alignment_name(*alignment).to_string(),
+                    })
+                    .collect();
+                args.push(format!("align: ({},)", align.join(", ")));
+            }
+            (_, Some(vertical)) => args.push(format!("align: {}", vertical)),
+            _ => {}
+        }
+        for (name, value) in [
+            ("stroke", &table.stroke),
+            ("fill", &table.fill),
+            ("inset", &table.inset),
+        ] {
+            if let Some(value) = value {
+                args.push(format!("{}: {}", name, value));
+            }
+        }
+        for (name, value) in [
+            ("gutter", &table.gutter),
+            ("row-gutter", &table.row_gutter),
+            ("column-gutter", &table.column_gutter),
+        ] {
+            if let Some(value) = value.as_deref().and_then(length_arg) {
+                args.push(format!("{}: {}", name, value));
+            }
+        }
+
+        let grid = table.grid();
+        let header_end = table
+            .cells
+            .iter()
+            .position(|cell| !cell.is_header)
+            .unwrap_or(table.cells.len());
+        let footer_start = table.cells[header_end..]
+            .iter()
+            .rposition(|cell| !cell.is_footer)
+            .map_or(header_end, |last| header_end + last + 1);
+        let mut rows = |cells: std::ops::Range<usize>| -> Vec<String> {
+            let mut rows: Vec<Vec<String>> = Vec::new();
+            let mut row = None;
+            for idx in cells {
+                let (origin, _) = grid.origins[idx];
+                if row != Some(origin) {
+                    rows.push(Vec::new());
+                    row = Some(origin);
+                }
+                let cell = self.cell(&table.cells[idx]);
+                if let Some(row) = rows.last_mut() {
+                    row.push(cell);
+                }
+            }
+            rows.into_iter().map(|row| row.join(", ")).collect()
+        };
+        let header = rows(0..header_end);
+        let body = rows(header_end..footer_start);
+        let footer = rows(footer_start..table.cells.len());
+        if !header.is_empty() {
+            let repeat = if table.repeat_header {
+                ""
+            } else {
+                "repeat: false, "
+            };
+            args.push(format!("table.header({}{})", repeat, header.join(", ")));
+        }
+        args.extend(body);
+        if !footer.is_empty() {
+            args.push(format!("table.footer({})", footer.join(", ")));
+        }
+        for rule in &table.rules {
+            let (function, axis) = match rule.axis {
+                RuleAxis::Vertical => ("vline", "x"),
+                _ => ("hline", "y"),
+            };
+            let mut rule_args = vec![format!("{}: {}", axis, rule.position)];
+            if rule.start > 0 {
+                rule_args.push(format!("start: {}", rule.start));
+            }
+            if let Some(end) = rule.end {
+                rule_args.push(format!("end: {}", end));
+            }
+            args.push(format!("table.{}({})", function, rule_args.join(", ")));
+        }
+        format!("table(\n{},\n)", indent(&args.join(",\n")))
+    }
+
+    /// A cell's content, as `table.cell(..)` when it spans or is set apart.
+    fn cell(&mut self, cell: &TableCell) -> String {
+        let content = match &cell.blocks {
+            Some(blocks) => self.blocks(blocks),
+            None => self.inlines(&cell.content).trim().to_string(),
+        };
+        let mut args = Vec::new();
+        if cell.colspan > 1 {
+            args.push(format!("colspan: {}", cell.colspan));
+        }
+        if cell.rowspan > 1 {
+            args.push(format!("rowspan: {}", cell.rowspan));
+        }
+        let alignment = [
+            cell.align.map(alignment_name),
+            cell.valign.map(vertical_alignment_name),
+        ];
+        let alignment: Vec<&str> = alignment.into_iter().flatten().collect();
+        if !alignment.is_empty() {
+            args.push(format!("align: {}", alignment.join(" + ")));
+        }
+        for (name, value) in [
+            ("fill", &cell.fill),
+            ("stroke", &cell.stroke),
+            ("inset", &cell.inset),
+        ] {
+            if let Some(value) = value {
+                args.push(format!("{}: {}", name, value));
+            }
+        }
+        if args.is_empty() {
+            format!("[{}]", content)
+        } else {
+            format!("table.cell({})[{}]", args.join(", "), content)
+        }
+    }
+
+    /// Theorem-like environments are figures of their own kind, so they
+    /// are numbered and can be referred to; a proof is set in the text
+    /// and ends with a square.
+    fn environment(&mut self, environment: &EnvironmentBlock) -> String {
+        let name = display_name(&environment.name);
+        let title = environment
+            .title
+            .as_ref()
+            .map(|title| format!("_({}.)_ ", self.inlines(title).trim()));
+        let body = self.blocks(&environment.blocks);
+        match environment.name.as_str() {
+            "proof" => format!(
+                "_{}._ {}{} #h(1fr) $square.stroked$",
+                name,
+                title.unwrap_or_default(),
+                body
+            ),
+            "abstract" => format!(
+                "#heading(outlined: false, numbering: none)[{}]{}{}",
+                name, BLOCK_SEPARATOR, body
+            ),
+            _ => {
+                let mut out = format!(
+                    "#figure(kind: \"theorem\", supplement: [{}], caption: [])[\n{}{}\n]",
+                    name,
+                    title.unwrap_or_default(),
+                    body
+                );
+                push_label(&mut out, environment.label.as_deref());
+                out
+            }
+        }
+    }
+
+    /// `[..]` for a title, `none` for an empty one.
+    fn optional_content(&mut self, inlines: &[Inline]) -> String {
+        if inlines.is_empty() {
+            "none".to_string()
+        } else {
+            format!("[{}]", self.inlines(inlines))
+        }
+    }
+
+    fn inlines(&mut self, inlines: &[Inline]) -> String {
+        let mut out = String::new();
+        for inline in inlines {
+            self.inline(&mut out, inline);
+        }
+        out
+    }
+
+    fn inline(&mut self, out: &mut String, inline: &Inline) {
+        match inline {
+            Inline::Text(text) => out.push_str(&escape_text(text)),
+            Inline::Size { size, content } => {
+                let content = self.inlines(content);
+                match length_arg(size) {
+                    Some(size) => out.push_str(&format!("#text(size: {})[{}]", size, content)),
+                    None => out.push_str(&content),
+                }
+            }
+            Inline::Strong(content) => self.wrap(out, "strong", content),
+            Inline::Emph(content) => self.wrap(out, "emph", content),
+            Inline::Code { lang, content } => match lang {
+                None if !content.contains('`') && !content.is_empty() => {
+                    out.push('`');
+                    out.push_str(content);
+                    out.push('`');
+                }
+                _ => {
+                    out.push_str("#raw(");
+                    out.push_str(&string(content));
+                    if let Some(lang) = lang {
+                        out.push_str(&format!(", lang: {}", string(lang)));
+                    }
+                    out.push(')');
+                }
+            },
+            Inline::Math(content) => {
+                out.push('$');
+                out.push_str(content.trim());
+                out.push('$');
+            }
+            Inline::LatexMath(content) => {
+                self.packages.mitex = true;
+                out.push_str(&format!("#mi({})", string(content.trim())));
+            }
+            Inline::Link { text, url, target } => {
+                let destination = match target {
+                    LinkTarget::Label => format!("<{}>", label_name(url)),
+                    LinkTarget::Email => string(&format!("mailto:{}", url)),
+                    _ => string(url),
+                };
+                out.push_str(&format!("#link({})", destination));
+                if !text.is_empty() {
+                    out.push_str(&format!("[{}]", self.inlines(text)));
+                }
+            }
+            Inline::Ref(label) => push_ref(out, label),
+            Inline::RefRange { from, to } => {
+                push_ref(out, from);
+                out.push_str(&format!(
+                    "\u{2013}#ref(<{}>, supplement: none)",
+                    label_name(to)
+                ));
+            }
+            Inline::SupplementedRef { label, supplement } => {
+                if supplement.is_empty() {
+                    out.push_str(&format!("#ref(<{}>, supplement: none)", label_name(label)));
+                } else {
+                    push_ref(out, label);
+                    out.push_str(&format!("[{}]", self.inlines(supplement)));
+                }
+            }
+            Inline::Label(label) => out.push_str(&format!("<{}>", label_name(label))),
+            Inline::Cite(keys) => {
+                for (idx, key) in citation_keys(keys).enumerate() {
+                    if idx > 0 {
+                        out.push(' ');
+                    }
+                    push_ref(out, key);
+                }
+            }
+            Inline::Citation(citation) => self.citation(out, citation),
+            Inline::Footnote(content) => self.wrap(out, "footnote", content),
+            Inline::Color { color, content } => {
+                let content = self.inlines(content);
+                let color = Color::parse_typst(color).or_else(|| Color::parse_latex(color));
+                match color {
+                    Some(color) => {
+                        out.push_str(&format!("#text(fill: {})[{}]", color.to_typst(), content))
+                    }
+                    None => out.push_str(&content),
+                }
+            }
+            // LaTeX has no place in Typst output; it is kept in a comment.
+            Inline::RawLatex(raw) => {
+                out.push_str(&format!("/* {} */", raw.replace("*/", "* /")));
+            }
+            Inline::Superscript(content) => self.wrap(out, "super", content),
+            Inline::Subscript(content) => self.wrap(out, "sub", content),
+            Inline::Underline(content) => self.wrap(out, "underline", content),
+            Inline::Overline(content) => self.wrap(out, "overline", content),
+            Inline::Strike(content) => self.wrap(out, "strike", content),
+            Inline::SmallCaps(content) => self.wrap(out, "smallcaps", content),
+            Inline::Uppercase(content) => self.wrap(out, "upper", content),
+            Inline::Lowercase(content) => self.wrap(out, "lower", content),
+            Inline::LineBreak => out.push_str("\\ "),
+            Inline::SmartQuote { double } => out.push(if *double { '"' } else { '\'' }),
+            Inline::Quantity { value, unit } => {
+                self.packages.unify = true;
+                match (value, unit) {
+                    (Some(value), Some(unit)) => {
+                        out.push_str(&format!("#qty({}, {})", string(value), string(unit)))
+                    }
+                    (Some(value), None) => out.push_str(&format!("#num({})", string(value))),
+                    (None, Some(unit)) => out.push_str(&format!("#unit({})", string(unit))),
+                    (None, None) => {}
+                }
+            }
+            Inline::Chemical(formula) => {
+                self.packages.whalogen = true;
+                out.push_str(&format!("#ce({})", string(formula)));
+            }
+            Inline::Pause => out.push_str("#pause"),
+            Inline::PageNumber(pattern) => {
+                let both = pattern.matches('1').count() > 1;
+                out.push_str(&format!(
+                    "#context counter(page).display({}{})",
+                    string(pattern),
+                    if both { ", both: true" } else { "" }
+                ));
+            }
+            Inline::RunningHead(level) => {
+                self.packages.hydra = true;
+                out.push_str(&format!("#context hydra({})", level));
+            }
+            _ => {}
+        }
+    }
+
+    fn wrap(&mut self, out: &mut String, function: &str, content: &[Inline]) {
+        out.push('#');
+        out.push_str(function);
+        out.push('[');
+        out.push_str(&self.inlines(content));
+        out.push(']');
+    }
+
+    /// A citation of each key, the form and notes going with the last.
+    /// Typst has no note before the reference, so it is set as text.
+    fn citation(&mut self, out: &mut String, citation: &Citation) {
+        if let Some(prenote) = &citation.prenote {
+            out.push_str(&escape_text(prenote));
+            out.push(' ');
+        }
+        let keys: Vec<&str> = citation_keys(&citation.keys).collect();
+        for (idx, key) in keys.iter().enumerate() {
+            if idx > 0 {
+                out.push(' ');
+            }
+            let mut args = vec![format!("<{}>", label_name(key))];
+            let form = match citation.form {
+                CiteForm::Prose => Some("\"prose\""),
+                CiteForm::Full => Some("\"full\""),
+                CiteForm::Author => Some("\"author\""),
+                CiteForm::Year => Some("\"year\""),
+                CiteForm::Hidden => Some("none"),
+                _ => None,
+            };
+            if let Some(form) = form {
+                args.push(format!("form: {}", form));
+            }
+            if idx + 1 == keys.len() {
+                if let Some(postnote) = &citation.postnote {
+                    args.push(format!("supplement: [{}]", escape_text(postnote)));
+                }
+            }
+            out.push_str(&format!("#cite({})", args.join(", ")));
+        }
+    }
+}
+
+impl Packages {
+    fn imports(&self) -> Vec<&'static str> {
+        [
+            (self.mitex, "#import \"@preview/mitex:0.2.4\": mi, mitex"),
+            (
+                self.unify,
+                "#import \"@preview/unify:0.7.1\": num, qty, unit",
+            ),
+            (self.whalogen, "#import \"@preview/whalogen:0.2.0\": ce"),
+            (self.hydra, "#import \"@preview/hydra:0.5.1\": hydra"),
+            (self.polylux, "#import \"@preview/polylux:0.3.1\": *"),
+        ]
+        .into_iter()
+        .filter_map(|(used, import)| used.then_some(import))
+        .collect()
+    }
+}
+
+/// A list item: `marker` before the first block, the others indented
+/// under it so they stay in the item.
+fn list_item(marker: &str, content: &str) -> String {
+    if content.is_empty() {
+        return marker.to_string();
+    }
+    let mut out = format!("{} ", marker);
+    for (idx, line) in content.lines().enumerate() {
+        if idx > 0 {
+            out.push('\n');
+            if !line.is_empty() {
+                out.push_str("  ");
+            }
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// Indent every line of `text` by two spaces, as arguments set a line
+/// apiece are.
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("  {}", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn push_label(out: &mut String, label: Option<&str>) {
+    if let Some(label) = label {
+        out.push_str(&format!(" <{}>", label_name(label)));
+    }
+}
+
+fn push_ref(out: &mut String, label: &str) {
+    out.push('@');
+    out.push_str(&label_name(label));
+}
+
+/// `label` with the characters a Typst label cannot hold replaced by
+/// dashes, and without the trailing dots and colons a reference would
+/// leave out of it.
+fn label_name(label: &str) -> String {
+    let name: String = label
+        .trim()
+        .chars()
+        .map(|ch| {
+            if ch.is_alphanumeric() || matches!(ch, '_' | '-' | ':' | '.') {
+                ch
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let name = name.trim_end_matches(['.', ':']);
+    if name.is_empty() {
+        "label".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn citation_keys(keys: &str) -> impl Iterator<Item = &str> {
+    keys.split(',').map(str::trim).filter(|key| !key.is_empty())
+}
+
+/// Escape `text` so Typst markup sets it as written. Line breaks in text
+/// are spaces, so that nothing in it starts a line.
+fn escape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' | '#' | '*' | '_' | '`' | '$' | '<' | '>' | '@' | '[' | ']' | '~' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            // `//` and `/*` start comments.
+            '/' if matches!(chars.peek(), Some('/' | '*')) => out.push_str("\\/"),
+            '\n' | '\r' => out.push(' '),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Escape what Typst would read at the start of a paragraph as a heading,
+/// a list or enum item or a term.
+fn escape_line_start(paragraph: &str) -> String {
+    if paragraph.starts_with(['=', '-', '+', '/']) {
+        return format!("\\{}", paragraph);
+    }
+    let number = paragraph.trim_start_matches(|ch: char| ch.is_ascii_digit());
+    if number.len() < paragraph.len() && number.starts_with('.') {
+        let split = paragraph.len() - number.len();
+        return format!("{}\\{}", &paragraph[..split], number);
+    }
+    paragraph.to_string()
+}
+
+/// A Typst string literal holding `text`.
+fn string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            _ => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A length as Typst writes it, read as Typst or as LaTeX writes it.
+fn length_arg(raw: &str) -> Option<String> {
+    Length::parse_typst(raw)
+        .or_else(|| Length::parse_latex(raw))
+        .map(|length| length.to_typst())
+}
+
+fn column_width(width: &ColumnWidth) -> String {
+    match width {
+        ColumnWidth::Fraction(fr) => format!("{}fr", fr),
+        ColumnWidth::Length(length) => length_arg(length).unwrap_or_else(|| "auto".to_string()),
+        _ => "auto".to_string(),
+    }
+}
+
+fn alignment_name(alignment: Alignment) -> &'static str {
+    match alignment {
+        Alignment::Left => "left",
+        Alignment::Right => "right",
+        _ => "center",
+    }
+}
+
+fn vertical_alignment_name(alignment: VerticalAlignment) -> &'static str {
+    match alignment {
+        VerticalAlignment::Top => "top",
+        VerticalAlignment::Bottom => "bottom",
+        _ => "horizon",
+    }
+}
+
+/// A figure placement, as Typst or LaTeX gives it: `top`, `bottom` and
+/// `auto`, or the first of LaTeX's `t`, `b` and `p`. Here (`h`) keeps the
+/// figure in the flow.
+fn placement(placement: &str) -> Option<&'static str> {
+    match placement.trim() {
+        "top" => return Some("top"),
+        "bottom" => return Some("bottom"),
+        "auto" => return Some("auto"),
+        _ => {}
+    }
+    placement.chars().find_map(|ch| match ch {
+        't' => Some("top"),
+        'b' => Some("bottom"),
+        'p' => Some("auto"),
+        _ => None,
+    })
+}
+
+/// A bibliography style Typst knows: its own names as they are, the common
+/// BibTeX styles as the nearest of them.
+fn bibliography_style(style: &str) -> Option<&str> {
+    const TYPST_STYLES: &[&str] = &[
+        "ieee",
+        "apa",
+        "mla",
+        "chicago-author-date",
+        "chicago-notes",
+        "harvard-cite-them-right",
+        "vancouver",
+        "alphanumeric",
+        "american-physics-society",
+        "nature",
+        "springer-basic",
+        "elsevier-harvard",
+    ];
+    let style = style.trim();
+    if TYPST_STYLES.contains(&style) || style.ends_with(".csl") {
+        return Some(style);
+    }
+    Some(match style {
+        "plain" | "unsrt" | "ieeetr" | "IEEEtran" | "abbrv" => "ieee",
+        "alpha" => "alphanumeric",
+        "apalike" | "apa" | "plainnat" | "abbrvnat" | "unsrtnat" => "apa",
+        _ => return None,
+    })
+}
+
+fn image_call(image: &Image) -> String {
+    let mut args = vec![string(&image.path)];
+    if let Some(width) = image.width.as_deref().and_then(length_arg) {
+        args.push(format!("width: {}", width));
+    }
+    if let Some(height) = image.height.as_deref().and_then(length_arg) {
+        args.push(format!("height: {}", height));
+    }
+    if let Some(fit) = &image.fit {
+        args.push(format!("fit: {}", string(fit)));
+    }
+    if let Some(alt) = &image.alt {
+        args.push(format!("alt: {}", string(alt)));
+    }
+    format!("image({})", args.join(", "))
+}
+
+/// The name a theorem-like environment is set under: a capital first.
+fn display_name(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn longest_run(text: &str, ch: char) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        if c == ch {
+            run += 1;
+            longest = longest.max(run);
+        } else {
+            run = 0;
+        }
+    }
+    longest
+}
+
+fn render_unsupported(source: &str, reason: &str) -> String {
+    let mut out = format!("// BEGIN unsupported: {}", reason.replace('\n', " "));
+    for line in source.trim_end().lines() {
+        out.push_str("\n// ");
+        out.push_str(line);
+    }
+    out.push_str("\n// END unsupported");
+    out
+}
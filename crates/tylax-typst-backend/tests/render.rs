@@ -0,0 +1,206 @@
+use tylax_ir::{
+    Block, Citation, CiteForm, Document, EnvironmentBlock, Figure, FigureContent, Image, Inline,
+    ListKind, MathBlock, Table, TableCell,
+};
+use tylax_typst_backend::{render_document, TypstRenderOptions};
+
+fn heading(level: u8, text: &str, numbered: bool, label: Option<&str>) -> Block {
+    Block::Heading {
+        level,
+        content: vec![Inline::text(text)],
+        numbered,
+        label: label.map(str::to_string),
+        outlined: true,
+        bookmarked: None,
+    }
+}
+
+fn cell(text: &str, is_header: bool) -> TableCell {
+    TableCell {
+        content: vec![Inline::text(text)],
+        blocks: None,
+        colspan: 1,
+        rowspan: 1,
+        align: None,
+        valign: None,
+        is_header,
+        is_footer: false,
+        fill: None,
+        stroke: None,
+        inset: None,
+    }
+}
+
+fn table(columns: usize, cells: Vec<TableCell>) -> Table {
+    Table {
+        columns,
+        cells,
+        align: None,
+        valign: None,
+        caption: None,
+        stroke: None,
+        fill: None,
+        inset: None,
+        label: None,
+        repeat_header: true,
+        widths: Vec::new(),
+        gutter: None,
+        row_gutter: None,
+        column_gutter: None,
+        rules: Vec::new(),
+    }
+}
+
+fn render(blocks: Vec<Block>) -> String {
+    render_document(&Document::new(blocks), TypstRenderOptions::default())
+}
+
+#[test]
+fn text_is_escaped_and_marked_up() {
+    let out = render(vec![
+        heading(1, "Intro", true, Some("sec:intro")),
+        Block::Paragraph(vec![
+            Inline::text("Costs $5 #1 in a_b, see "),
+            Inline::Ref("sec:intro".to_string()),
+            Inline::text(" and "),
+            Inline::Strong(vec![Inline::text("bold")]),
+            Inline::text(" "),
+            Inline::Math("x^2".to_string()),
+            Inline::text("."),
+        ]),
+        heading(2, "Aside", false, None),
+        Block::Paragraph(vec![Inline::text("- not a list item")]),
+    ]);
+    assert_eq!(
+        out,
+        "= Intro <sec:intro>\n\n\
+         Costs \\$5 \\#1 in a\\_b, see @sec:intro and #strong[bold] $x^2$.\n\n\
+         #heading(level: 2, numbering: none)[Aside]\n\n\
+         \\- not a list item"
+    );
+}
+
+#[test]
+fn lists_keep_later_blocks_in_their_item() {
+    let out = render(vec![Block::List {
+        kind: ListKind::Ordered,
+        items: vec![
+            vec![
+                Block::Paragraph(vec![Inline::text("First")]),
+                Block::List {
+                    kind: ListKind::Unordered,
+                    items: vec![vec![Block::Paragraph(vec![Inline::text("inner")])]],
+                },
+            ],
+            vec![Block::Paragraph(vec![Inline::text("Second")])],
+        ],
+    }]);
+    assert_eq!(out, "+ First\n\n  - inner\n+ Second");
+}
+
+#[test]
+fn citations_carry_their_form_and_notes() {
+    let out = render(vec![Block::Paragraph(vec![
+        Inline::Cite("knuth, lamport".to_string()),
+        Inline::text(" "),
+        Inline::Citation(Citation {
+            keys: "knuth".to_string(),
+            form: CiteForm::Prose,
+            prenote: Some("see".to_string()),
+            postnote: Some("p. 12".to_string()),
+        }),
+    ])]);
+    assert_eq!(
+        out,
+        "@knuth @lamport see #cite(<knuth>, form: \"prose\", supplement: [p. 12])"
+    );
+}
+
+#[test]
+fn captioned_tables_are_figures_with_their_header() {
+    let table = Table {
+        caption: Some(vec![Inline::text("Results")]),
+        label: Some("tab:results".to_string()),
+        ..table(
+            2,
+            vec![
+                cell("Name", true),
+                cell("Score", true),
+                cell("A", false),
+                cell("1", false),
+            ],
+        )
+    };
+    let out = render(vec![Block::Table(table)]);
+    assert_eq!(
+        out,
+        "#figure(\n  table(\n    columns: 2,\n    table.header([Name], [Score]),\n    [A], [1],\n  ),\n  caption: [Results],\n) <tab:results>"
+    );
+}
+
+#[test]
+fn figures_theorems_and_equations() {
+    let out = render(vec![
+        Block::Figure(Figure {
+            content: FigureContent::Image(Image {
+                path: "plot.png".to_string(),
+                width: Some("50%".to_string()),
+                height: None,
+                fit: None,
+                alt: None,
+            }),
+            caption: Some(vec![Inline::text("A plot")]),
+            label: Some("fig:plot".to_string()),
+            placement: Some("htbp".to_string()),
+            unnumbered: false,
+            caption_separator: None,
+            caption_position: None,
+            short_caption: None,
+            numbering: None,
+            wrap: None,
+        }),
+        Block::Environment(EnvironmentBlock {
+            name: "lemma".to_string(),
+            title: None,
+            blocks: vec![Block::Paragraph(vec![Inline::text("It holds.")])],
+            label: Some("lem:a".to_string()),
+            args: Vec::new(),
+        }),
+        Block::MathBlock(MathBlock {
+            content: "E = m c^2".to_string(),
+            label: Some("eq:e".to_string()),
+            latex: false,
+        }),
+    ]);
+    assert_eq!(
+        out,
+        "#figure(\n  image(\"plot.png\", width: 50%),\n  caption: [A plot],\n  placement: top,\n) <fig:plot>\n\n\
+         #figure(kind: \"theorem\", supplement: [Lemma], caption: [])[\nIt holds.\n] <lem:a>\n\n\
+         $ E = m c^2 $ <eq:e>"
+    );
+}
+
+#[test]
+fn full_documents_set_numbering_and_import_packages() {
+    let doc = Document::new(vec![
+        heading(1, "Intro", true, None),
+        Block::Paragraph(vec![Inline::Chemical("H2O".to_string())]),
+        Block::MathBlock(MathBlock {
+            content: "x".to_string(),
+            label: Some("eq:x".to_string()),
+            latex: false,
+        }),
+    ]);
+    let out = render_document(
+        &doc,
+        TypstRenderOptions {
+            full_document: true,
+        },
+    );
+    assert!(out.starts_with(
+        "#import \"@preview/whalogen:0.2.0\": ce\n\n\
+         #set heading(numbering: \"1.\")\n\
+         #set math.equation(numbering: \"(1)\")\n\n\
+         = Intro"
+    ));
+}
@@ -0,0 +1,352 @@
+//! Pseudocode from the lovelace and algo packages.
+//!
+//! Both packages lay out lines by indentation and leave keywords to the
+//! author, so each line is read back into a step whose leading keyword
+//! (`if`, `while`, `return`, ...) decides how the backend renders it.
+
+use tylax_ir::{Algorithm, AlgorithmKeyword, AlgorithmStep, Block, Inline, Loss};
+use typst_syntax::{parse, SyntaxKind, SyntaxNode};
+
+use super::{
+    collect_blocks, collect_caption_inlines, collect_inlines, extract_named_key,
+    extract_named_value_node, get_func_call_name, node_full_text, parse_bool_literal,
+};
+
+/// Leading words that open a step, longest first so `else if` wins over
+/// `else`. `End` lines only close a block and are dropped.
+const KEYWORDS: &[(&str, Option<AlgorithmKeyword>)] = &[
+    ("else if", Some(AlgorithmKeyword::ElseIf)),
+    ("elseif", Some(AlgorithmKeyword::ElseIf)),
+    ("elif", Some(AlgorithmKeyword::ElseIf)),
+    ("else", Some(AlgorithmKeyword::Else)),
+    ("if", Some(AlgorithmKeyword::If)),
+    ("for all", Some(AlgorithmKeyword::ForAll)),
+    ("for each", Some(AlgorithmKeyword::ForAll)),
+    ("forall", Some(AlgorithmKeyword::ForAll)),
+    ("foreach", Some(AlgorithmKeyword::ForAll)),
+    ("for", Some(AlgorithmKeyword::For)),
+    ("while", Some(AlgorithmKeyword::While)),
+    ("repeat", Some(AlgorithmKeyword::Repeat)),
+    ("until", Some(AlgorithmKeyword::Until)),
+    ("return", Some(AlgorithmKeyword::Return)),
+    ("function", Some(AlgorithmKeyword::Function)),
+    ("procedure", Some(AlgorithmKeyword::Procedure)),
+    ("require", Some(AlgorithmKeyword::Require)),
+    ("input", Some(AlgorithmKeyword::Input)),
+    ("ensure", Some(AlgorithmKeyword::Ensure)),
+    ("output", Some(AlgorithmKeyword::Output)),
+    ("endif", None),
+    ("endfor", None),
+    ("endwhile", None),
+    ("end", None),
+];
+
+/// Words that close the head of a step, as in `if x then` or `for i do`.
+const TRAILING_WORDS: &[&str] = &["then", "do"];
+
+/// Parse `pseudocode-list`, `pseudocode` (lovelace) or `algo` calls.
+pub(crate) fn parse_algorithm(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Algorithm> {
+    let func_name = get_func_call_name(node)?;
+    let args = node.children().find(|c| c.kind() == SyntaxKind::Args)?;
+    let mut algorithm = Algorithm {
+        caption: None,
+        label: None,
+        line_numbers: true,
+        steps: Vec::new(),
+    };
+    let mut parameters: Vec<String> = Vec::new();
+    let mut lines: Vec<Line> = Vec::new();
+    let mut depth = 0usize;
+
+    for child in args.children() {
+        match child.kind() {
+            SyntaxKind::Named => {
+                let key = extract_named_key(child).unwrap_or_default();
+                let Some(value) = extract_named_value_node(child) else {
+                    continue;
+                };
+                match key.as_str() {
+                    "title" => algorithm.caption = Some(title_inlines(&value, losses)),
+                    "line-numbering" => algorithm.line_numbers = value.kind() != SyntaxKind::None,
+                    "line-numbers" => {
+                        algorithm.line_numbers = parse_bool_literal(&value).unwrap_or(true)
+                    }
+                    "parameters" => {
+                        parameters = value
+                            .children()
+                            .filter(|c| c.kind() == SyntaxKind::Str)
+                            .map(|c| c.text().trim_matches('"').to_string())
+                            .collect();
+                    }
+                    _ => {}
+                }
+            }
+            SyntaxKind::ContentBlock => match func_name.as_str() {
+                "pseudocode-list" => algorithm.steps = list_steps(&collect_blocks(child, losses)),
+                "pseudocode" => lines.push((depth, collect_inlines(child, losses), None)),
+                "algo" => lines.extend(algo_lines(child, losses)),
+                _ => return None,
+            },
+            SyntaxKind::Ident if func_name == "pseudocode" => match child.text().as_str() {
+                "ind" => depth += 1,
+                "ded" => depth = depth.saturating_sub(1),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    if !matches!(
+        func_name.as_str(),
+        "pseudocode-list" | "pseudocode" | "algo"
+    ) {
+        return None;
+    }
+    if !lines.is_empty() {
+        algorithm.steps = nest_steps(lines);
+    }
+    if func_name == "algo" {
+        if let Some(title) = algorithm.caption.clone() {
+            // algo prints its title as a signature heading the body.
+            let mut content = title;
+            content.push(Inline::text(format!("({})", parameters.join(", "))));
+            algorithm.steps = vec![AlgorithmStep {
+                keyword: AlgorithmKeyword::Procedure,
+                content,
+                comment: None,
+                body: std::mem::take(&mut algorithm.steps),
+            }];
+        }
+    }
+    Some(algorithm)
+}
+
+fn title_inlines(value: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
+    match value.kind() {
+        SyntaxKind::Str => vec![Inline::text(value.text().trim_matches('"'))],
+        _ => collect_caption_inlines(value, losses),
+    }
+}
+
+/// lovelace writes one list item per line and nests the body of a block as a
+/// sub-list of its head.
+fn list_steps(blocks: &[Block]) -> Vec<AlgorithmStep> {
+    let mut steps = Vec::new();
+    for block in blocks {
+        let Block::List { items, .. } = block else {
+            continue;
+        };
+        for item in items {
+            let mut content = Vec::new();
+            for block in item {
+                match block {
+                    Block::Paragraph(inlines) => content.extend(inlines.iter().cloned()),
+                    // A line that is only an equation is read as a display
+                    // block, but it is still one line of the listing.
+                    Block::MathBlock(math) => content.push(Inline::Math(math.content.clone())),
+                    _ => {}
+                }
+            }
+            if let Some(mut step) = classify_step(content) {
+                step.body = list_steps(item);
+                steps.push(step);
+            }
+        }
+    }
+    steps
+}
+
+/// A line of pseudocode: its indentation, its content and its comment.
+type Line = (usize, Vec<Inline>, Option<Vec<Inline>>);
+
+/// Split an `algo` body into lines and their indentation. `#i` indents the
+/// lines after it and `#d` dedents them; `#comment[...]` becomes the comment
+/// of its line.
+fn algo_lines(body: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Line> {
+    let text = node_full_text(body);
+    let text = text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+        .unwrap_or(&text);
+    let mut lines = Vec::new();
+    let mut depth = 0usize;
+    for line in text.lines() {
+        let root = parse(line);
+        let mut kept = String::new();
+        let mut comment = None;
+        let mut seen_content = false;
+        let mut line_depth = depth;
+        let mut children = root.children().peekable();
+        while let Some(child) = children.next() {
+            if child.kind() == SyntaxKind::Hash {
+                if let Some(next) = children.peek() {
+                    let mark = match next.kind() {
+                        SyntaxKind::Ident => next.text().to_string(),
+                        SyntaxKind::FuncCall => get_func_call_name(next).unwrap_or_default(),
+                        _ => String::new(),
+                    };
+                    match mark.as_str() {
+                        "i" | "d" => {
+                            if mark == "i" {
+                                depth += 1;
+                            } else {
+                                depth = depth.saturating_sub(1);
+                            }
+                            if !seen_content {
+                                line_depth = depth;
+                            }
+                            children.next();
+                            continue;
+                        }
+                        "comment" => {
+                            if let Some(call) = children.next() {
+                                comment = Some(comment_inlines(call, losses));
+                            }
+                            continue;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            if child.kind() == SyntaxKind::Linebreak {
+                continue;
+            }
+            if child.kind() != SyntaxKind::Space {
+                seen_content = true;
+            }
+            kept.push_str(&node_full_text(child));
+        }
+        if kept.trim().is_empty() && comment.is_none() {
+            continue;
+        }
+        let content = collect_inlines(&parse(kept.trim()), losses);
+        lines.push((line_depth, content, comment));
+    }
+    lines
+}
+
+/// The content of a `comment[...]` call.
+fn comment_inlines(call: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    if let Some(args) = call.children().find(|c| c.kind() == SyntaxKind::Args) {
+        for child in args.children() {
+            if child.kind() == SyntaxKind::ContentBlock {
+                inlines.extend(collect_inlines(child, losses));
+            }
+        }
+    }
+    trim_text_edges(&mut inlines);
+    inlines
+}
+
+/// Hang each line under the last line one level shallower.
+fn nest_steps(lines: Vec<Line>) -> Vec<AlgorithmStep> {
+    let mut steps: Vec<AlgorithmStep> = Vec::new();
+    for (depth, content, comment) in lines {
+        let Some(mut step) = classify_step(content) else {
+            continue;
+        };
+        step.comment = comment;
+        let mut parent = &mut steps;
+        for _ in 0..depth {
+            if parent.is_empty() {
+                break;
+            }
+            parent = &mut parent.last_mut().unwrap().body;
+        }
+        parent.push(step);
+    }
+    steps
+}
+
+/// Strip the leading keyword and trailing `then`/`do`/`:` of a line. Returns
+/// `None` for `end` lines, which the LaTeX environments close themselves.
+fn classify_step(mut content: Vec<Inline>) -> Option<AlgorithmStep> {
+    trim_text_edges(&mut content);
+    let mut keyword = AlgorithmKeyword::Statement;
+    match content.first() {
+        Some(Inline::Strong(inner)) => {
+            let word = normalize_keyword(&inline_plain_text(inner));
+            if let Some((_, kind)) = KEYWORDS.iter().find(|(name, _)| *name == word) {
+                keyword = (*kind)?;
+                content.remove(0);
+            }
+        }
+        Some(Inline::Text(text)) => {
+            let lowered = text.to_lowercase();
+            for (name, kind) in KEYWORDS {
+                let Some(rest) = lowered.strip_prefix(name) else {
+                    continue;
+                };
+                if !rest.is_empty() && !rest.starts_with([' ', ':']) {
+                    continue;
+                }
+                let Some(rest) = text.get(name.len()..) else {
+                    continue;
+                };
+                keyword = (*kind)?;
+                let rest = rest.trim_start_matches(':').to_string();
+                if rest.trim().is_empty() {
+                    content.remove(0);
+                } else {
+                    content[0] = Inline::Text(rest);
+                }
+                break;
+            }
+        }
+        _ => {}
+    }
+    trim_text_edges(&mut content);
+    match content.last_mut() {
+        Some(Inline::Strong(inner))
+            if TRAILING_WORDS.contains(&inline_plain_text(inner).trim()) =>
+        {
+            content.pop();
+        }
+        Some(Inline::Text(text)) => {
+            let trimmed = text.trim_end().trim_end_matches(':');
+            let trimmed = TRAILING_WORDS
+                .iter()
+                .find_map(|word| {
+                    trimmed
+                        .strip_suffix(word)
+                        .filter(|rest| rest.is_empty() || rest.ends_with(' '))
+                })
+                .unwrap_or(trimmed)
+                .to_string();
+            *text = trimmed;
+        }
+        _ => {}
+    }
+    trim_text_edges(&mut content);
+    Some(AlgorithmStep {
+        keyword,
+        content,
+        comment: None,
+        body: Vec::new(),
+    })
+}
+
+fn normalize_keyword(text: &str) -> String {
+    text.trim().trim_end_matches(':').trim().to_lowercase()
+}
+
+fn inline_plain_text(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => text.as_str(),
+            _ => "",
+        })
+        .collect()
+}
+
+/// Drop whitespace at both ends of the line, and text left empty.
+fn trim_text_edges(content: &mut Vec<Inline>) {
+    if let Some(Inline::Text(text)) = content.first_mut() {
+        *text = text.trim_start().to_string();
+    }
+    if let Some(Inline::Text(text)) = content.last_mut() {
+        *text = text.trim_end().to_string();
+    }
+    content.retain(|inline| !matches!(inline, Inline::Text(text) if text.is_empty()));
+}
@@ -0,0 +1,197 @@
+//! Alignment functions of tables, as in
+//!
+//! ```typst
+//! #table(align: (x, y) => if y == 0 { center } else { left }, ..)
+//! ```
+//!
+//! A function that tells the first rows or columns apart from the rest is
+//! read back into column alignments and alignments of the cells of those
+//! rows. Any other function is a loss.
+
+use tylax_ir::{Alignment, Loss, TableCell, TableGrid, VerticalAlignment};
+use typst_syntax::{SyntaxKind, SyntaxNode};
+
+use super::{node_full_text, parse_alignment};
+
+/// The rows or columns a condition picks out: the first `count` of them
+/// when `leading`, all but those otherwise.
+struct Split {
+    rows: bool,
+    count: usize,
+    leading: bool,
+}
+
+/// Apply an alignment function to the cells of a table of `columns`
+/// columns, setting the column alignments and the cells' own alignment
+/// where they differ by row. Cells aligned by `table.cell` keep it.
+pub(crate) fn apply_align_function(
+    function: &SyntaxNode,
+    cells: &mut [TableCell],
+    columns: usize,
+    align: &mut Option<Vec<Alignment>>,
+    valign: &mut Option<VerticalAlignment>,
+    losses: &mut Vec<Loss>,
+) {
+    let Some((then, otherwise, split)) = read_function(function) else {
+        let source: String = node_full_text(function)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        losses.push(
+            Loss::new(
+                "align",
+                format!("alignment function `{}` was not applied", source),
+            )
+            .with_suggestion(
+                "compare `x` or `y` with a number, \
+                 e.g. `(x, y) => if y == 0 { center } else { left }`",
+            ),
+        );
+        return;
+    };
+    let Some(split) = split else {
+        let (horizontal, vertical) = then;
+        *align = horizontal.map(|horizontal| vec![horizontal; columns]);
+        *valign = vertical;
+        return;
+    };
+    let (first, rest) = if split.leading {
+        (then, otherwise)
+    } else {
+        (otherwise, then)
+    };
+    if split.rows {
+        *align = rest.0.map(|horizontal| vec![horizontal; columns]);
+        *valign = rest.1;
+        let placed = TableGrid::new(cells, columns).origins;
+        for (cell, (row, _)) in cells.iter_mut().zip(placed) {
+            if row < split.count && cell.align.is_none() && cell.valign.is_none() {
+                cell.align = first.0.or(rest.0);
+                cell.valign = first.1.or(rest.1);
+            }
+        }
+    } else {
+        if let (Some(first), Some(rest)) = (first.0, rest.0) {
+            *align = Some(
+                (0..columns)
+                    .map(|column| if column < split.count { first } else { rest })
+                    .collect(),
+            );
+        }
+        if first.1 != rest.1 {
+            losses.push(Loss::new(
+                "align",
+                "columns with different vertical alignments all took the first one",
+            ));
+        }
+        *valign = first.1.or(rest.1);
+    }
+}
+
+type Aligned = (Option<Alignment>, Option<VerticalAlignment>);
+
+/// The alignments of both branches of the function and the rows or columns
+/// its condition picks out, or the single alignment of a function without
+/// a condition.
+fn read_function(function: &SyntaxNode) -> Option<(Aligned, Aligned, Option<Split>)> {
+    if function.kind() != SyntaxKind::Closure {
+        return None;
+    }
+    let params = function
+        .children()
+        .find(|c| c.kind() == SyntaxKind::Params)?;
+    let names: Vec<&str> = params
+        .children()
+        .filter(|c| c.kind() == SyntaxKind::Ident)
+        .map(|c| c.text().as_str())
+        .collect();
+    let [x, y] = names.as_slice() else {
+        return None;
+    };
+    let body = function
+        .children()
+        .skip_while(|c| c.kind() != SyntaxKind::Arrow)
+        .skip(1)
+        .find(|c| !c.kind().is_trivia())?;
+    if body.kind() != SyntaxKind::Conditional {
+        let aligned = alignment_of(body)?;
+        return Some((aligned, aligned, None));
+    }
+    let mut parts = body.children().filter(|c| {
+        !c.kind().is_trivia() && !matches!(c.kind(), SyntaxKind::If | SyntaxKind::Else)
+    });
+    let condition = parts.next()?;
+    let then = alignment_of(parts.next()?)?;
+    let otherwise = alignment_of(parts.next()?)?;
+    let split = split_of(condition, x, y)?;
+    Some((then, otherwise, Some(split)))
+}
+
+/// A branch that is an alignment and nothing else.
+fn alignment_of(branch: &SyntaxNode) -> Option<Aligned> {
+    let text = node_full_text(branch);
+    let text = text
+        .trim()
+        .trim_start_matches(['{', '['])
+        .trim_end_matches(['}', ']'])
+        .trim();
+    let aligned = parse_alignment(text);
+    let known = text.split('+').all(|part| {
+        matches!(
+            part.trim(),
+            "left" | "start" | "right" | "end" | "center" | "top" | "horizon" | "bottom"
+        )
+    });
+    (known && aligned != (None, None)).then_some(aligned)
+}
+
+/// The rows or columns `y == 0`, `y < 2`, `x > 0` and the like pick out.
+fn split_of(condition: &SyntaxNode, x: &str, y: &str) -> Option<Split> {
+    if condition.kind() != SyntaxKind::Binary {
+        return None;
+    }
+    let parts: Vec<&SyntaxNode> = condition
+        .children()
+        .filter(|c| !c.kind().is_trivia())
+        .collect();
+    let [lhs, op, rhs] = parts.as_slice() else {
+        return None;
+    };
+    // `0 == y` reads as `y == 0`, `1 > y` as `y < 1`.
+    let (var, op, bound) = match (lhs.kind(), rhs.kind()) {
+        (SyntaxKind::Ident, SyntaxKind::Int) => (lhs.text(), op.kind(), rhs.text()),
+        (SyntaxKind::Int, SyntaxKind::Ident) => {
+            let flipped = match op.kind() {
+                SyntaxKind::Lt => SyntaxKind::Gt,
+                SyntaxKind::LtEq => SyntaxKind::GtEq,
+                SyntaxKind::Gt => SyntaxKind::Lt,
+                SyntaxKind::GtEq => SyntaxKind::LtEq,
+                kind => kind,
+            };
+            (rhs.text(), flipped, lhs.text())
+        }
+        _ => return None,
+    };
+    let rows = if var == y {
+        true
+    } else if var == x {
+        false
+    } else {
+        return None;
+    };
+    let bound: usize = bound.parse().ok()?;
+    let (count, leading) = match op {
+        SyntaxKind::EqEq if bound == 0 => (1, true),
+        SyntaxKind::ExclEq if bound == 0 => (1, false),
+        SyntaxKind::Lt => (bound, true),
+        SyntaxKind::LtEq => (bound + 1, true),
+        SyntaxKind::Gt => (bound + 1, false),
+        SyntaxKind::GtEq => (bound, false),
+        _ => return None,
+    };
+    Some(Split {
+        rows,
+        count,
+        leading,
+    })
+}
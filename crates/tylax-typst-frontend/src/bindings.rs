@@ -0,0 +1,160 @@
+//! Content bound once with `#let` and referenced later:
+//!
+//! ```typst
+//! #let fig = figure(image("cat.png"), caption: [A cat.])
+//! #fig <fig:cat>
+//! ```
+//!
+//! The preprocessor expands such references when it runs. This resolver
+//! covers the common case without it: a top-level name bound exactly once to
+//! a content block or a call stands for that node wherever `#name` appears.
+//! A binding that refers back to itself, as `#let a = [#a #a]`, would expand
+//! without end and is left unresolved as a loss.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use tylax_ir::{Loss, Severity};
+use typst_syntax::{SyntaxKind, SyntaxNode};
+
+use super::flatten_markup_children;
+
+/// How many bindings may be resolved inside each other. Deeper chains are
+/// dropped like cycles, since each level can multiply the content.
+const MAX_BINDING_DEPTH: usize = 16;
+
+thread_local! {
+    static CONTENT_BINDINGS: RefCell<HashMap<String, SyntaxNode>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Makes the content bindings of one document visible to the collectors.
+pub(crate) struct BindingScope(Option<HashMap<String, SyntaxNode>>);
+
+impl BindingScope {
+    pub(crate) fn enter(root: &SyntaxNode, losses: &mut Vec<Loss>) -> Self {
+        let bindings = content_bindings(root, losses);
+        let previous = CONTENT_BINDINGS.with(|cell| cell.replace(bindings));
+        BindingScope(Some(previous))
+    }
+}
+
+impl Drop for BindingScope {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            CONTENT_BINDINGS.with(|cell| *cell.borrow_mut() = previous);
+        }
+    }
+}
+
+/// The node bound to `name`, if it is single-assignment content.
+pub(crate) fn resolve(name: &str) -> Option<SyntaxNode> {
+    CONTENT_BINDINGS.with(|cell| cell.borrow().get(name).cloned())
+}
+
+/// Replace each `#name` among `children` with the content bound to it.
+pub(crate) fn resolve_references(children: &mut [SyntaxNode]) {
+    for idx in 1..children.len() {
+        if children[idx].kind() != SyntaxKind::Ident || children[idx - 1].kind() != SyntaxKind::Hash
+        {
+            continue;
+        }
+        if let Some(node) = resolve(children[idx].text()) {
+            children[idx] = node;
+        }
+    }
+}
+
+/// Collect the top-level `#let name = [..]` and `#let name = call(..)`
+/// bindings. A name bound more than once, or also bound to anything else,
+/// has no single value and is left out; so is one whose value refers back
+/// to itself.
+fn content_bindings(root: &SyntaxNode, losses: &mut Vec<Loss>) -> HashMap<String, SyntaxNode> {
+    let mut children = Vec::new();
+    flatten_markup_children(root, &mut children);
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut bindings = HashMap::new();
+    for binding in children
+        .iter()
+        .filter(|node| node.kind() == SyntaxKind::LetBinding)
+    {
+        let Some(name) = binding
+            .children()
+            .find(|c| c.kind() == SyntaxKind::Ident)
+            .map(|ident| ident.text().to_string())
+        else {
+            continue;
+        };
+        *counts.entry(name.clone()).or_default() += 1;
+        let value = binding
+            .children()
+            .rev()
+            .find(|c| c.kind() != SyntaxKind::Space)
+            .filter(|c| matches!(c.kind(), SyntaxKind::ContentBlock | SyntaxKind::FuncCall));
+        if let Some(value) = value {
+            bindings.insert(name, value.clone());
+        }
+    }
+    bindings.retain(|name, _| counts.get(name) == Some(&1));
+
+    let mut finite = HashMap::new();
+    let mut names: Vec<String> = bindings.keys().cloned().collect();
+    names.sort();
+    for name in names {
+        if !resolves(&name, &bindings, &mut Vec::new(), &mut finite) {
+            bindings.remove(&name);
+            losses.push(
+                Loss::new(
+                    "binding",
+                    format!("#{} refers to itself and was not expanded", name),
+                )
+                .with_severity(Severity::Error),
+            );
+        }
+    }
+    bindings
+}
+
+/// Whether the content bound to `name` can be expanded: the bound names its
+/// value refers to, followed in turn, never lead back to a name in
+/// `resolving` nor more than [`MAX_BINDING_DEPTH`] levels down. `finite`
+/// remembers the names already decided.
+fn resolves(
+    name: &str,
+    bindings: &HashMap<String, SyntaxNode>,
+    resolving: &mut Vec<String>,
+    finite: &mut HashMap<String, bool>,
+) -> bool {
+    if let Some(&known) = finite.get(name) {
+        return known;
+    }
+    if resolving.iter().any(|open| open == name) || resolving.len() >= MAX_BINDING_DEPTH {
+        return false;
+    }
+    let Some(value) = bindings.get(name) else {
+        return true;
+    };
+    resolving.push(name.to_string());
+    let mut referenced = Vec::new();
+    bound_names(value, bindings, &mut referenced);
+    let result = referenced
+        .iter()
+        .all(|next| resolves(next, bindings, resolving, finite));
+    resolving.pop();
+    // A name found cyclic only through an open name higher up may still
+    // resolve on its own, so only the outermost answer is kept.
+    if resolving.is_empty() || result {
+        finite.insert(name.to_string(), result);
+    }
+    result
+}
+
+/// The bound names `node` refers to.
+fn bound_names(node: &SyntaxNode, bindings: &HashMap<String, SyntaxNode>, names: &mut Vec<String>) {
+    if node.kind() == SyntaxKind::Ident && bindings.contains_key(node.text().as_str()) {
+        names.push(node.text().to_string());
+    }
+    for child in node.children() {
+        bound_names(child, bindings, names);
+    }
+}
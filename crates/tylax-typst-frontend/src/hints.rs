@@ -0,0 +1,143 @@
+//! Conversion hints authors attach to blocks with Typst metadata:
+//!
+//! ```typst
+//! #figure(..) <fig:a>
+//! #metadata((tylax: (placement: "tb", wide: true))) <tylax:fig:a>
+//! ```
+//!
+//! The metadata's label names the block it applies to behind a `tylax:`
+//! prefix, so the block's own label stays unique for references.
+
+use tylax_ir::{Block, BlockHints, Loss, Severity, Span};
+use typst_syntax::{SyntaxKind, SyntaxNode};
+
+use super::{
+    extract_label_text, extract_named_key, extract_named_value_node, flatten_markup_children,
+    get_func_call_name, parse_bool_literal, parse_string_literal,
+};
+
+const LABEL_PREFIX: &str = "tylax:";
+
+struct Annotation {
+    target: String,
+    hints: BlockHints,
+    no_convert: bool,
+}
+
+/// Apply the hints in `root` to the top-level `blocks`, returning them index
+/// for index. Blocks marked `no-convert` are replaced by their source.
+pub(crate) fn apply_block_hints(
+    root: &SyntaxNode,
+    source: &str,
+    blocks: &mut [Block],
+    spans: &[Span],
+    losses: &mut Vec<Loss>,
+) -> Vec<Option<BlockHints>> {
+    let annotations = annotations(root);
+    if annotations.is_empty() {
+        return Vec::new();
+    }
+    let mut hints = vec![None; blocks.len()];
+    for annotation in annotations {
+        let Some(idx) = blocks
+            .iter()
+            .position(|block| block_label(block) == Some(annotation.target.as_str()))
+        else {
+            losses.push(
+                Loss::new(
+                    "hint",
+                    format!(
+                        "no block labelled <{}> for tylax metadata",
+                        annotation.target
+                    ),
+                )
+                .with_severity(Severity::Info),
+            );
+            continue;
+        };
+        if annotation.no_convert {
+            if let Some(text) = spans
+                .get(idx)
+                .and_then(|span| source.get(span.start..span.end))
+            {
+                blocks[idx] = Block::Unsupported {
+                    source: text.to_string(),
+                    reason: "marked no-convert".to_string(),
+                };
+            }
+        }
+        hints[idx] = Some(annotation.hints);
+    }
+    hints
+}
+
+fn annotations(root: &SyntaxNode) -> Vec<Annotation> {
+    let mut children = Vec::new();
+    flatten_markup_children(root, &mut children);
+    let mut out = Vec::new();
+    for (idx, child) in children.iter().enumerate() {
+        if get_func_call_name(child).as_deref() != Some("metadata") {
+            continue;
+        }
+        let Some(target) = children[idx + 1..]
+            .iter()
+            .find(|node| node.kind() != SyntaxKind::Space)
+            .filter(|node| node.kind() == SyntaxKind::Label)
+            .and_then(extract_label_text)
+            .and_then(|label| label.strip_prefix(LABEL_PREFIX).map(str::to_string))
+        else {
+            continue;
+        };
+        if let Some((hints, no_convert)) = parse_hints(child) {
+            out.push(Annotation {
+                target,
+                hints,
+                no_convert,
+            });
+        }
+    }
+    out
+}
+
+/// Read the `tylax` entry of the metadata's dictionary.
+fn parse_hints(call: &SyntaxNode) -> Option<(BlockHints, bool)> {
+    let args = call.children().find(|c| c.kind() == SyntaxKind::Args)?;
+    let dict = args.children().find(|c| c.kind() == SyntaxKind::Dict)?;
+    let entry = dict
+        .children()
+        .filter(|c| c.kind() == SyntaxKind::Named)
+        .find(|c| extract_named_key(c).as_deref() == Some("tylax"))?;
+    let fields = extract_named_value_node(entry)?;
+    let mut hints = BlockHints::default();
+    let mut no_convert = false;
+    for field in fields.children().filter(|c| c.kind() == SyntaxKind::Named) {
+        let Some(value) = extract_named_value_node(field) else {
+            continue;
+        };
+        // Keys may be written as identifiers or strings: `"no-convert": true`.
+        let key = field
+            .children()
+            .next()
+            .map(|key| key.text().trim_matches('"').to_string());
+        match key.as_deref() {
+            Some("placement") => hints.placement = parse_string_literal(&value),
+            Some("wide") => hints.wide = parse_bool_literal(&value).unwrap_or(false),
+            Some("no-convert") => no_convert = parse_bool_literal(&value).unwrap_or(false),
+            _ => {}
+        }
+    }
+    Some((hints, no_convert))
+}
+
+fn block_label(block: &Block) -> Option<&str> {
+    let label = match block {
+        Block::Heading { label, .. } => label,
+        Block::MathBlock(math) => &math.label,
+        Block::Table(table) => &table.label,
+        Block::Figure(figure) => &figure.label,
+        Block::Environment(env) => &env.label,
+        Block::Algorithm(algorithm) => &algorithm.label,
+        _ => return None,
+    };
+    label.as_deref()
+}
@@ -0,0 +1,180 @@
+//! Files included into the document, as in
+//!
+//! ```typst
+//! #include "chapters/intro.typ"
+//! ```
+//!
+//! The frontend reads no files itself: an [`IncludeResolver`] from the
+//! embedder supplies the included source, which is converted with the
+//! options of the including file and set in place of the include. With
+//! [`IncludeStrategy::Input`] the include becomes an `\input` of the file
+//! converted on its own instead.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::Arc;
+
+use tylax_ir::{Block, Inline, Loss, Severity};
+use typst_syntax::{parse, SyntaxKind, SyntaxNode};
+
+use super::{
+    node_full_text, typst_to_ir_with, unescape_typst_string, TypstFrontendOptions, OPTIONS,
+};
+
+/// How `#include` is converted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IncludeStrategy {
+    /// Convert the included file and set its content in place.
+    #[default]
+    Inline,
+    /// Set `\input` of the included file, which is converted on its own to
+    /// a `.tex` file of the same name.
+    Input,
+}
+
+/// Reads an included file, given its path relative to the directory of
+/// the main file, or returns `None` when it cannot be read.
+#[derive(Clone)]
+pub struct IncludeResolver(Arc<ResolveFn>);
+
+type ResolveFn = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+impl IncludeResolver {
+    pub fn new(resolve: impl Fn(&str) -> Option<String> + Send + Sync + 'static) -> Self {
+        IncludeResolver(Arc::new(resolve))
+    }
+}
+
+impl fmt::Debug for IncludeResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("IncludeResolver(..)")
+    }
+}
+
+impl PartialEq for IncludeResolver {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for IncludeResolver {}
+
+thread_local! {
+    /// The files being included, innermost last.
+    static INCLUDING: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The blocks an `#include` stands for.
+pub(crate) fn include_blocks(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Block> {
+    let Some(path) = included_path(node) else {
+        let msg = format!("`{}` does not name a file", node_full_text(node).trim());
+        losses.push(Loss::new("include", msg).with_severity(Severity::Error));
+        return Vec::new();
+    };
+    let options = OPTIONS.with(|cell| cell.borrow().clone());
+    let path = resolve_path(options.include_path.as_deref(), &path);
+    match options.include_strategy {
+        IncludeStrategy::Input => {
+            let name = path.strip_suffix(".typ").unwrap_or(&path);
+            let input = format!("\\input{{{}}}", name);
+            vec![Block::Paragraph(vec![Inline::RawLatex(input)])]
+        }
+        IncludeStrategy::Inline => inline_include(path, options, losses),
+    }
+}
+
+fn inline_include(
+    path: String,
+    options: TypstFrontendOptions,
+    losses: &mut Vec<Loss>,
+) -> Vec<Block> {
+    if INCLUDING.with(|including| including.borrow().contains(&path)) {
+        let msg = format!("{} includes itself", path);
+        losses.push(Loss::new("include", msg).with_severity(Severity::Error));
+        return Vec::new();
+    }
+    let Some(resolver) = &options.include_resolver else {
+        let msg = format!("included file {} was not read", path);
+        losses.push(
+            Loss::new("include", msg)
+                .with_severity(Severity::Error)
+                .with_suggestion("convert with `--multi-file` to read the files it includes"),
+        );
+        return Vec::new();
+    };
+    let Some(source) = (resolver.0)(&path) else {
+        let msg = format!("included file {} could not be read", path);
+        losses.push(
+            Loss::new("include", msg)
+                .with_severity(Severity::Error)
+                .with_suggestion("give its path relative to the file that includes it"),
+        );
+        return Vec::new();
+    };
+    INCLUDING.with(|including| including.borrow_mut().push(path.clone()));
+    let options = TypstFrontendOptions {
+        include_path: Some(path.clone()),
+        ..options
+    };
+    let doc = typst_to_ir_with(&source, &options);
+    INCLUDING.with(|including| including.borrow_mut().pop());
+    // Spans of the included file do not point into this one; the losses
+    // take the span of the include instead.
+    losses.extend(doc.losses.into_iter().map(|mut loss| {
+        loss.span = None;
+        loss.message = format!("{}: {}", path, loss.message);
+        loss
+    }));
+    doc.blocks
+}
+
+/// The files `input` includes, relative to the directory of the main file,
+/// in order and each once. `path` is where `input` is relative to that
+/// directory; `None` for the main file.
+pub fn included_files(input: &str, path: Option<&str>) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut stack = vec![parse(input)];
+    while let Some(node) = stack.pop() {
+        if node.kind() == SyntaxKind::ModuleInclude {
+            if let Some(file) = included_path(&node) {
+                let file = resolve_path(path, &file);
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+            continue;
+        }
+        stack.extend(node.children().rev().cloned());
+    }
+    files
+}
+
+fn included_path(node: &SyntaxNode) -> Option<String> {
+    node.children()
+        .find(|child| child.kind() == SyntaxKind::Str)
+        .map(|path| unescape_typst_string(path.text().trim_matches('"')))
+}
+
+/// The path of `path` included from the file at `including`, both relative
+/// to the directory of the main file. A path starting with `/` is relative
+/// to that directory already.
+fn resolve_path(including: Option<&str>, path: &str) -> String {
+    let mut parts: Vec<&str> = match (path.starts_with('/'), including) {
+        (false, Some(including)) => {
+            let mut parts: Vec<&str> = including.split('/').collect();
+            parts.pop();
+            parts
+        }
+        _ => Vec::new(),
+    };
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." if parts.last().is_some_and(|last| *last != "..") => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
@@ -1,19 +1,403 @@
 //! Typst AST to IR frontend.
 
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
 use typst_syntax::{parse, SyntaxKind, SyntaxNode};
+use tylax_color::Color;
 use tylax_ir::{
-    Alignment, Block, BlockBlock, BoxBlock, Columns, Document, EnvironmentBlock, Figure,
-    FigureContent, Grid, Image, Inline, ListKind, Loss, MathBlock, Table, TableCell,
+    Alignment, Block, BlockBlock, BoxBlock, Budget, CancellationToken, CaptionPosition, Citation,
+    CiteForm, ColumnWidth, Columns, ConversionLimits, Document, EnvironmentBlock, Figure,
+    FigureContent, Grid, Image, Inline, LinkTarget, ListKind, Loss, MathBlock, OutlineTarget,
+    Severity, Span, Table, TableCell, TermItem, VerticalAlignment,
 };
 
+mod algorithm;
+mod align;
+mod bindings;
+mod hints;
+mod includes;
+mod meta;
 mod preprocess;
+mod rules;
+mod slides;
+mod wrap;
+
+pub use includes::{included_files, IncludeResolver, IncludeStrategy};
+use preprocess::{PackageFunction, SourceMap};
+
+/// Settings for the Typst frontend that are not expressed in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypstFrontendOptions {
+    /// Functions read as a reference range, called as `name(<from>, <to>)`.
+    pub ref_range_functions: Vec<String>,
+    /// Expand `#let`, `#if` and `#for` before lowering. Without it, content
+    /// bound once with `#let` is still resolved where it is referenced.
+    pub expand_macros: bool,
+    /// Bounds on input size, output size and time; content past them is
+    /// dropped as a `limit` loss.
+    pub limits: ConversionLimits,
+    /// Stops the conversion early when cancelled from another thread.
+    pub cancel: Option<CancellationToken>,
+    /// Evaluates the spread arguments of a table, as in
+    /// `..csv("data.csv").flatten()`. Without it their cells are dropped.
+    pub spread_evaluator: Option<SpreadEvaluator>,
+    /// Whether `#include` sets the included file in place or an `\input`
+    /// of it.
+    pub include_strategy: IncludeStrategy,
+    /// Reads included files. Without it their content is dropped, unless
+    /// they are set as `\input`.
+    pub include_resolver: Option<IncludeResolver>,
+    /// Path of the converted file relative to the directory of the main
+    /// file, which its includes are relative to; `None` for the main file.
+    pub include_path: Option<String>,
+}
+
+impl Default for TypstFrontendOptions {
+    fn default() -> Self {
+        Self {
+            ref_range_functions: vec!["refrange".to_string()],
+            expand_macros: true,
+            limits: ConversionLimits::default(),
+            cancel: None,
+            spread_evaluator: None,
+            include_strategy: IncludeStrategy::default(),
+            include_resolver: None,
+            include_path: None,
+        }
+    }
+}
+
+/// Turns the expression of a spread argument into the Typst markup of each
+/// value it spreads, or `None` when it cannot be evaluated. The frontend
+/// does not run code; embedders with an evaluator and file access plug in
+/// here.
+#[derive(Clone)]
+pub struct SpreadEvaluator(Arc<SpreadFn>);
+
+type SpreadFn = dyn Fn(&Spread<'_>) -> Option<Vec<String>> + Send + Sync;
+
+impl SpreadEvaluator {
+    pub fn new(eval: impl Fn(&Spread<'_>) -> Option<Vec<String>> + Send + Sync + 'static) -> Self {
+        SpreadEvaluator(Arc::new(eval))
+    }
+}
+
+/// A spread argument for the [`SpreadEvaluator`] to evaluate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spread<'a> {
+    /// The spread expression, without its `..`.
+    pub expr: &'a str,
+    /// Where the top-level element the spread is in starts in the input.
+    /// The top-level definitions before it are in scope; later ones are not.
+    pub offset: usize,
+}
+
+impl Spread<'_> {
+    /// Whether the conversion's limits are hit, for an evaluator to check
+    /// while it runs the code the expression depends on.
+    pub fn exhausted(&self) -> bool {
+        BUDGET.with(|cell| cell.borrow().is_exhausted())
+    }
+}
+
+impl fmt::Debug for SpreadEvaluator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SpreadEvaluator(..)")
+    }
+}
+
+impl PartialEq for SpreadEvaluator {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SpreadEvaluator {}
 
 pub fn typst_to_ir(input: &str) -> Document {
-    let pre = preprocess::preprocess_typst(input);
+    typst_to_ir_with(input, &TypstFrontendOptions::default())
+}
+
+pub fn typst_to_ir_with(input: &str, options: &TypstFrontendOptions) -> Document {
+    let _options = OptionsScope::enter(options);
+    let budget = Budget::new(&options.limits, options.cancel.as_ref());
+    let (input, cut) = budget.truncate_input(input);
+    let _budget = BudgetScope::enter(budget);
+    // Preprocessing drops set and show rules, so they are read from the input.
+    let source = parse(input);
+    let pre = preprocess::preprocess_typst(input, options.expand_macros);
+    let _position = PositionScope::enter(pre.source_map.clone());
+    let _packages = PackageScope::enter(pre.package_functions);
     let root = parse(&pre.source);
     let mut losses = pre.losses;
-    let blocks = collect_blocks(&root, &mut losses);
+    losses.extend(cut);
+    let _bindings = bindings::BindingScope::enter(&root, &mut losses);
+    let preprocess_losses = losses.len();
+    let meta = meta::document_meta(&source, &mut losses);
+    let _supplement = RefSupplementScope::enter(meta::ref_supplement(&source, &mut losses));
+    let mut spans = BlockSpans::default();
+    let mut blocks = collect_blocks_tracked(&root, &mut losses, Some(&mut spans));
+    let hints =
+        hints::apply_block_hints(&root, &pre.source, &mut blocks, &spans.blocks, &mut losses);
+    let inline_spans = spans
+        .inlines
+        .into_iter()
+        .map(|inlines| {
+            inlines
+                .into_iter()
+                .map(|span| pre.source_map.span_to_input(span))
+                .collect()
+        })
+        .collect();
+    let spans = spans
+        .blocks
+        .into_iter()
+        .map(|span| Some(pre.source_map.span_to_input(span)))
+        .collect();
+    for loss in &mut losses[preprocess_losses..] {
+        if let Some(span) = loss.span {
+            loss.span = Some(pre.source_map.span_to_input(span));
+        }
+    }
     Document::with_losses(blocks, losses)
+        .with_spans(spans)
+        .with_inline_spans(inline_spans)
+        .with_hints(hints)
+        .with_meta(meta)
+}
+
+/// How many block and inline collections may be nested inside each other.
+/// Ordinary documents stay far below this; generated input can nest deep
+/// enough to overflow the stack, so deeper content is dropped as a loss.
+const MAX_NESTING_DEPTH: usize = 48;
+
+thread_local! {
+    static NESTING_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Marks one level of frontend recursion for as long as it is alive.
+struct NestingGuard;
+
+impl NestingGuard {
+    fn enter(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Self> {
+        let depth = NESTING_DEPTH.with(Cell::get);
+        if depth >= MAX_NESTING_DEPTH {
+            losses.push(
+                Loss::new(
+                    "nesting",
+                    format!(
+                        "{:?} nested more than {} levels deep was dropped",
+                        node.kind(),
+                        MAX_NESTING_DEPTH
+                    ),
+                )
+                .with_severity(Severity::Error),
+            );
+            return None;
+        }
+        NESTING_DEPTH.with(|cell| cell.set(depth + 1));
+        Some(NestingGuard)
+    }
+}
+
+impl Drop for NestingGuard {
+    fn drop(&mut self) {
+        NESTING_DEPTH.with(|cell| cell.set(cell.get() - 1));
+    }
+}
+
+thread_local! {
+    static BUDGET: RefCell<Budget> = RefCell::new(Budget::unlimited());
+}
+
+/// Makes one conversion's budget visible to the collectors.
+struct BudgetScope(Option<Budget>);
+
+impl BudgetScope {
+    fn enter(budget: Budget) -> Self {
+        let previous = BUDGET.with(|cell| cell.replace(budget));
+        BudgetScope(Some(previous))
+    }
+}
+
+impl Drop for BudgetScope {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            BUDGET.with(|cell| *cell.borrow_mut() = previous);
+        }
+    }
+}
+
+/// Check the budget without counting blocks, for the passes that run
+/// before block collection or evaluate code while it runs.
+pub(crate) fn budget_exhausted(losses: &mut Vec<Loss>) -> bool {
+    BUDGET.with(|cell| cell.borrow_mut().exhausted(losses))
+}
+
+/// Count the blocks added since the last call and check the budget.
+fn over_budget(counted: &mut usize, blocks: &[Block], losses: &mut Vec<Loss>) -> bool {
+    let added = blocks.len().saturating_sub(*counted);
+    *counted = blocks.len();
+    BUDGET.with(|cell| {
+        let mut budget = cell.borrow_mut();
+        budget.add_blocks(added);
+        budget.exhausted(losses)
+    })
+}
+
+/// The top-level node being converted, as where it starts in the
+/// preprocessed source and the map from there back to the input.
+#[derive(Default)]
+struct Position {
+    start: usize,
+    source_map: SourceMap,
+}
+
+thread_local! {
+    static POSITION: RefCell<Position> = RefCell::new(Position::default());
+}
+
+/// Tracks the top-level node being converted for one conversion.
+struct PositionScope(Option<Position>);
+
+impl PositionScope {
+    fn enter(source_map: SourceMap) -> Self {
+        let position = Position {
+            start: 0,
+            source_map,
+        };
+        PositionScope(Some(POSITION.with(|cell| cell.replace(position))))
+    }
+}
+
+impl Drop for PositionScope {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            POSITION.with(|cell| *cell.borrow_mut() = previous);
+        }
+    }
+}
+
+/// Where the top-level node being converted starts in the input.
+fn top_level_offset() -> usize {
+    POSITION.with(|cell| {
+        let position = cell.borrow();
+        position.source_map.to_input(position.start)
+    })
+}
+
+thread_local! {
+    static OPTIONS: RefCell<TypstFrontendOptions> = RefCell::new(TypstFrontendOptions::default());
+}
+
+/// Makes `options` visible to the collectors for one conversion and restores
+/// the previous settings afterwards.
+struct OptionsScope(Option<TypstFrontendOptions>);
+
+impl OptionsScope {
+    fn enter(options: &TypstFrontendOptions) -> Self {
+        let previous = OPTIONS.with(|cell| cell.replace(options.clone()));
+        OptionsScope(Some(previous))
+    }
+}
+
+impl Drop for OptionsScope {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            OPTIONS.with(|cell| *cell.borrow_mut() = previous);
+        }
+    }
+}
+
+thread_local! {
+    static PACKAGE_FUNCTIONS: RefCell<HashMap<String, PackageFunction>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Makes the package functions the document imports visible to the
+/// collectors for one conversion.
+struct PackageScope(Option<HashMap<String, PackageFunction>>);
+
+impl PackageScope {
+    fn enter(functions: HashMap<String, PackageFunction>) -> Self {
+        let previous = PACKAGE_FUNCTIONS.with(|cell| cell.replace(functions));
+        PackageScope(Some(previous))
+    }
+}
+
+impl Drop for PackageScope {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            PACKAGE_FUNCTIONS.with(|cell| *cell.borrow_mut() = previous);
+        }
+    }
+}
+
+thread_local! {
+    static REF_SUPPLEMENT: RefCell<Option<Vec<Inline>>> = const { RefCell::new(None) };
+}
+
+/// Makes the supplement of the document's `set ref(supplement: ..)`
+/// visible to the collectors for one conversion.
+struct RefSupplementScope(Option<Option<Vec<Inline>>>);
+
+impl RefSupplementScope {
+    fn enter(supplement: Option<Vec<Inline>>) -> Self {
+        let previous = REF_SUPPLEMENT.with(|cell| cell.replace(supplement));
+        RefSupplementScope(Some(previous))
+    }
+}
+
+impl Drop for RefSupplementScope {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            REF_SUPPLEMENT.with(|cell| *cell.borrow_mut() = previous);
+        }
+    }
+}
+
+fn package_function(name: &str) -> Option<PackageFunction> {
+    PACKAGE_FUNCTIONS.with(|cell| cell.borrow().get(name).copied())
+}
+
+/// The markup of the values `expr` spreads, from the embedder's evaluator.
+fn eval_spread(expr: &str) -> Option<Vec<String>> {
+    let evaluator = OPTIONS.with(|cell| cell.borrow().spread_evaluator.clone())?;
+    let offset = top_level_offset();
+    (evaluator.0)(&Spread { expr, offset })
+}
+
+/// The cells a table or grid spreads in, as in `..cells` or
+/// `..csv("data.csv").flatten()`, each parsed as markup.
+fn spread_cells(spread: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<SyntaxNode> {
+    let expr = node_full_text(spread);
+    let expr = expr.trim_start_matches("..");
+    if budget_exhausted(losses) {
+        return Vec::new();
+    }
+    let values = eval_spread(expr);
+    // The evaluation is charged to the budget; cells past it are dropped.
+    if budget_exhausted(losses) {
+        return Vec::new();
+    }
+    let Some(values) = values else {
+        let reason = format!("cells spread from `{}` were not evaluated", expr);
+        losses.push(Loss::new("unsupported", reason).with_severity(Severity::Error));
+        return Vec::new();
+    };
+    values.iter().map(|value| parse(value)).collect()
+}
+
+fn is_ref_range_function(name: &str) -> bool {
+    OPTIONS.with(|cell| {
+        cell.borrow()
+            .ref_range_functions
+            .iter()
+            .any(|func| func == name)
+    })
 }
 
 struct PageBlock {
@@ -21,7 +405,148 @@ struct PageBlock {
     numbering_none: bool,
 }
 
+/// Tracks which source range produced each top-level block and loss while
+/// `collect_blocks_tracked` walks the root markup.
+struct SpanTracker<'a> {
+    spans: &'a mut BlockSpans,
+    offsets: Vec<usize>,
+    step: Option<TrackerStep>,
+    inline_start: Option<usize>,
+    /// Spans of the inlines of the pending paragraph, index for index.
+    inlines: Vec<Span>,
+}
+
+/// Spans of the top-level blocks and of the inlines of their paragraphs,
+/// block for block.
+#[derive(Default)]
+struct BlockSpans {
+    blocks: Vec<Span>,
+    inlines: Vec<Vec<Span>>,
+}
+
+struct TrackerStep {
+    start: usize,
+    blocks: usize,
+    losses: usize,
+    had_inline: bool,
+    inlines: usize,
+    /// Length of the pending paragraph's last inline when it is text,
+    /// which the step's first text is merged into.
+    last_text: Option<usize>,
+}
+
+impl<'a> SpanTracker<'a> {
+    fn new(spans: &'a mut BlockSpans, children: &[SyntaxNode]) -> Self {
+        let mut offsets = Vec::with_capacity(children.len() + 1);
+        let mut offset = 0;
+        for (idx, child) in children.iter().enumerate() {
+            // Attribute the `#` of an embedded expression to the expression.
+            let hashed = idx > 0 && children[idx - 1].kind() == SyntaxKind::Hash;
+            offsets.push(if hashed { offset - 1 } else { offset });
+            offset += child.len();
+        }
+        offsets.push(offset);
+        Self {
+            spans,
+            offsets,
+            step: None,
+            inline_start: None,
+            inlines: Vec::new(),
+        }
+    }
+
+    /// Close the previous step at child `idx` and open a new one there.
+    fn advance(&mut self, idx: usize, blocks: &[Block], losses: &mut [Loss], inline: &[Inline]) {
+        self.finish(self.offsets[idx], blocks, losses, inline);
+        POSITION.with(|cell| cell.borrow_mut().start = self.offsets[idx]);
+        self.step = Some(TrackerStep {
+            start: self.offsets[idx],
+            blocks: blocks.len(),
+            losses: losses.len(),
+            had_inline: !inline.is_empty(),
+            inlines: inline.len(),
+            last_text: match inline.last() {
+                Some(Inline::Text(text)) => Some(text.len()),
+                _ => None,
+            },
+        });
+    }
+
+    fn finish(&mut self, end: usize, blocks: &[Block], losses: &mut [Loss], inline: &[Inline]) {
+        let Some(step) = self.step.take() else {
+            return;
+        };
+        let mut new_blocks = blocks.len() - step.blocks;
+        let flushed = step.had_inline && new_blocks > 0;
+        if flushed {
+            // A block-level child flushes the pending paragraph first.
+            let start = self.inline_start.take().unwrap_or(step.start);
+            self.spans.blocks.push(Span::new(start, step.start));
+            self.push_paragraph_inlines(&blocks[step.blocks]);
+            new_blocks -= 1;
+        }
+        for _ in 0..new_blocks {
+            self.spans.blocks.push(Span::new(step.start, end));
+            self.spans.inlines.push(Vec::new());
+        }
+        if !flushed {
+            let merged = step.inlines.checked_sub(1).filter(|&last| {
+                matches!(
+                    (step.last_text, inline.get(last)),
+                    (Some(len), Some(Inline::Text(text))) if text.len() > len
+                )
+            });
+            if let Some(span) = merged.and_then(|last| self.inlines.get_mut(last)) {
+                span.end = end;
+            }
+        }
+        // Inlines the child added span all of it.
+        self.inlines
+            .resize(inline.len(), Span::new(step.start, end));
+        for loss in &mut losses[step.losses..] {
+            if loss.span.is_none() {
+                loss.span = Some(Span::new(step.start, end));
+            }
+        }
+        if !has_inline_content(inline) {
+            self.inline_start = None;
+        } else if flushed || self.inline_start.is_none() {
+            self.inline_start = Some(step.start);
+        }
+    }
+
+    /// Record the span of the paragraph flushed after the last child.
+    fn finish_paragraph(&mut self, paragraph: &Block) {
+        let end = self.offsets.last().copied().unwrap_or(0);
+        let start = self.inline_start.take().unwrap_or(end);
+        self.spans.blocks.push(Span::new(start, end));
+        self.push_paragraph_inlines(paragraph);
+    }
+
+    /// Give the spans of the pending inlines to `paragraph`, which they
+    /// were flushed into, less those flushing dropped from its end.
+    fn push_paragraph_inlines(&mut self, paragraph: &Block) {
+        let mut inlines = std::mem::take(&mut self.inlines);
+        match paragraph {
+            Block::Paragraph(content) => inlines.truncate(content.len()),
+            _ => inlines.clear(),
+        }
+        self.spans.inlines.push(inlines);
+    }
+}
+
 fn collect_blocks(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Block> {
+    collect_blocks_tracked(node, losses, None)
+}
+
+fn collect_blocks_tracked(
+    node: &SyntaxNode,
+    losses: &mut Vec<Loss>,
+    spans: Option<&mut BlockSpans>,
+) -> Vec<Block> {
+    let Some(_guard) = NestingGuard::enter(node, losses) else {
+        return Vec::new();
+    };
     let mut blocks = Vec::new();
     let mut current_inline: Vec<Inline> = Vec::new();
 
@@ -33,8 +558,19 @@ fn collect_blocks(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Block> {
             children.push(child.clone());
         }
     }
+    let mut tracker = spans.map(|spans| SpanTracker::new(spans, &children));
+    bindings::resolve_references(&mut children);
+    let mut counted = 0;
     let mut i = 0;
     while i < children.len() {
+        if let Some(tracker) = tracker.as_mut() {
+            tracker.advance(i, &blocks, losses, &current_inline);
+        }
+        // A limit loss lands in the step just opened, whose span then runs
+        // to the end: the content that is dropped.
+        if over_budget(&mut counted, &blocks, losses) {
+            break;
+        }
         let child = &children[i];
         match child.kind() {
             SyntaxKind::SetRule => {
@@ -55,20 +591,33 @@ fn collect_blocks(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Block> {
                 }
                 i += 1;
             }
+            SyntaxKind::ModuleInclude => {
+                flush_paragraph(&mut blocks, &mut current_inline);
+                blocks.extend(includes::include_blocks(child, losses));
+                i += 1;
+            }
             SyntaxKind::Import
             | SyntaxKind::ModuleImport
             | SyntaxKind::Include
-            | SyntaxKind::ModuleInclude => {
+            | SyntaxKind::LetBinding => {
                 i += 1;
             }
             SyntaxKind::Heading => {
                 flush_paragraph(&mut blocks, &mut current_inline);
                 let level = count_heading_markers(&child) as u8;
                 let content = collect_inlines(&child, losses);
+                let mut label = None;
+                if let Some((lab, label_idx)) = trailing_label(&children, i) {
+                    label = Some(lab);
+                    i = label_idx;
+                }
                 blocks.push(Block::Heading {
                     level,
                     content,
                     numbered: true,
+                    label,
+                    outlined: true,
+                    bookmarked: None,
                 });
                 i += 1;
             }
@@ -89,6 +638,22 @@ fn collect_blocks(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Block> {
                 blocks.push(list_block);
                 i += consumed;
             }
+            SyntaxKind::Raw => {
+                let (lang, content, block) = raw_literal(child);
+                if block {
+                    flush_paragraph(&mut blocks, &mut current_inline);
+                    blocks.push(Block::CodeBlock { lang, content });
+                } else if !content.is_empty() {
+                    current_inline.push(Inline::Code { lang, content });
+                }
+                i += 1;
+            }
+            SyntaxKind::TermItem => {
+                flush_paragraph(&mut blocks, &mut current_inline);
+                let (term_list, consumed) = collect_term_list(&children[i..], losses);
+                blocks.push(term_list);
+                i += consumed;
+            }
             SyntaxKind::Equation => {
                 let mut prev = i as isize - 1;
                 while prev >= 0 && matches!(children[prev as usize].kind(), SyntaxKind::Space) {
@@ -148,21 +713,15 @@ fn collect_blocks(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Block> {
                 flush_paragraph(&mut blocks, &mut current_inline);
                 if let Some(math) = math {
                     let mut label: Option<String> = None;
-                    let mut lookahead = i + 1;
-                    while lookahead < children.len()
-                        && matches!(children[lookahead].kind(), SyntaxKind::Space)
-                    {
-                        lookahead += 1;
-                    }
-                    if lookahead < children.len()
-                        && children[lookahead].kind() == SyntaxKind::Label
-                    {
-                        if let Some(lab) = extract_label_text(&children[lookahead]) {
-                            label = Some(lab);
-                            i = lookahead;
-                        }
+                    if let Some((lab, label_idx)) = trailing_label(&children, i) {
+                        label = Some(lab);
+                        i = label_idx;
                     }
-                    blocks.push(Block::MathBlock(MathBlock { content: math, label }));
+                    blocks.push(Block::MathBlock(MathBlock {
+                        content: math,
+                        label,
+                        latex: false,
+                    }));
                 }
                 i += 1;
             }
@@ -172,24 +731,28 @@ fn collect_blocks(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Block> {
                     // Check for trailing label
                     if let Block::MathBlock(ref mut math) = &mut block {
                         if math.label.is_none() {
-                            let mut lookahead = i + 1;
-                            while lookahead < children.len()
-                                && matches!(children[lookahead].kind(), SyntaxKind::Space)
-                            {
-                                lookahead += 1;
-                            }
-                            if lookahead < children.len()
-                                && children[lookahead].kind() == SyntaxKind::Label
-                            {
-                                if let Some(lab) = extract_label_text(&children[lookahead]) {
-                                    math.label = Some(lab);
-                                    i = lookahead;
-                                }
+                            if let Some((lab, label_idx)) = trailing_label(&children, i) {
+                                math.label = Some(lab);
+                                i = label_idx;
                             }
                         }
                     }
                     flush_paragraph(&mut blocks, &mut current_inline);
                     blocks.push(block);
+                } else if let Some(mut block) = maybe_latex_math_block(child) {
+                    if let Block::MathBlock(math) = &mut block {
+                        if let Some((lab, label_idx)) = trailing_label(&children, i) {
+                            math.label = Some(lab);
+                            i = label_idx;
+                        }
+                    }
+                    flush_paragraph(&mut blocks, &mut current_inline);
+                    blocks.push(block);
+                } else if get_func_call_name(child).as_deref() == Some("metadata") {
+                    // Metadata is invisible; `tylax` hints are read separately.
+                    if let Some((_, label_idx)) = trailing_label(&children, i) {
+                        i = label_idx;
+                    }
                 } else if let Some(page) = maybe_page_block(&child, losses) {
                     flush_paragraph(&mut blocks, &mut current_inline);
                     if !blocks.is_empty() && !last_is_pagebreak(&blocks) {
@@ -208,52 +771,87 @@ fn collect_blocks(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Block> {
                             "\\newpage".to_string(),
                         )]));
                     }
-                } else if let Some(block) = maybe_pagebreak_block(&child) {
+                } else if let Some((block, weak)) = maybe_pagebreak_block(&child, losses) {
                     flush_paragraph(&mut blocks, &mut current_inline);
-                    blocks.push(block);
-                } else if let Some(block) = maybe_heading_block(&child, losses) {
+                    // A weak break is skipped where a page starts anyway.
+                    if !weak || !at_page_start(&blocks) {
+                        blocks.push(block);
+                    }
+                } else if let Some(mut block) = maybe_heading_block(&child, losses) {
+                    if let Block::Heading { label, .. } = &mut block {
+                        if let Some((lab, label_idx)) = trailing_label(&children, i) {
+                            *label = Some(lab);
+                            i = label_idx;
+                        }
+                    }
                     flush_paragraph(&mut blocks, &mut current_inline);
                     blocks.push(block);
-                } else if let Some(block) = maybe_environment_block(&child, losses) {
+                } else if let Some(mut block) = maybe_environment_block(&child, losses) {
+                    if let Block::Environment(env) = &mut block {
+                        if let Some((lab, label_idx)) = trailing_label(&children, i) {
+                            env.label = Some(lab);
+                            i = label_idx;
+                        }
+                    }
                     flush_paragraph(&mut blocks, &mut current_inline);
                     blocks.push(block);
                 } else if let Some(block) = maybe_named_block(&child, losses) {
                     flush_paragraph(&mut blocks, &mut current_inline);
                     blocks.push(block);
-                } else if let Some(block) = maybe_table_block(&child, losses) {
+                } else if let Some(mut block) = maybe_table_block(&child, losses) {
+                    if let Block::Table(table) = &mut block {
+                        if let Some((lab, label_idx)) = trailing_label(&children, i) {
+                            table.label = Some(lab);
+                            i = label_idx;
+                        }
+                    }
                     flush_paragraph(&mut blocks, &mut current_inline);
                     blocks.push(block);
                 } else if let Some(block) = maybe_bibliography_block(&child, losses) {
                     flush_paragraph(&mut blocks, &mut current_inline);
                     blocks.push(block);
                 } else if let Some(mut block) = maybe_figure_block(&child, losses) {
-                    if let Block::Figure(fig) = &mut block {
-                        if fig.label.is_none() {
-                            let mut lookahead = i + 1;
-                            while lookahead < children.len()
-                                && matches!(children[lookahead].kind(), SyntaxKind::Space)
-                            {
-                                lookahead += 1;
-                            }
-                            if lookahead < children.len()
-                                && children[lookahead].kind() == SyntaxKind::Label
-                            {
-                                if let Some(lab) = extract_label_text(&children[lookahead]) {
-                                    fig.label = Some(lab);
-                                    i = lookahead;
-                                }
-                            }
+                    let label = match &mut block {
+                        Block::Figure(fig) => Some(&mut fig.label),
+                        Block::Algorithm(algorithm) => Some(&mut algorithm.label),
+                        _ => None,
+                    };
+                    if let Some(label) = label.filter(|label| label.is_none()) {
+                        if let Some((lab, label_idx)) = trailing_label(&children, i) {
+                            *label = Some(lab);
+                            i = label_idx;
                         }
                     }
                     flush_paragraph(&mut blocks, &mut current_inline);
                     blocks.push(block);
+                } else if let Some(mut algorithm) = algorithm::parse_algorithm(&child, losses) {
+                    if let Some((lab, label_idx)) = trailing_label(&children, i) {
+                        algorithm.label = Some(lab);
+                        i = label_idx;
+                    }
+                    flush_paragraph(&mut blocks, &mut current_inline);
+                    blocks.push(Block::Algorithm(algorithm));
                 } else if let Some(block) = maybe_image_block(&child, losses) {
                     flush_paragraph(&mut blocks, &mut current_inline);
                     blocks.push(block);
                 } else if let Some(block) = maybe_quote_block(&child, losses) {
                     flush_paragraph(&mut blocks, &mut current_inline);
                     blocks.push(block);
-                } else if let Some(block) = maybe_code_block(&child, losses) {
+                } else if let Some(block) =
+                    maybe_code_block(&children, i, &current_inline, losses)
+                {
+                    flush_paragraph(&mut blocks, &mut current_inline);
+                    blocks.push(block);
+                } else if let Some(block) = slides::parse_slide(child, losses) {
+                    flush_paragraph(&mut blocks, &mut current_inline);
+                    blocks.push(block);
+                } else if let Some(block) = slides::parse_side_by_side(child, losses) {
+                    flush_paragraph(&mut blocks, &mut current_inline);
+                    blocks.push(block);
+                } else if let Some(wrapped) = wrap::parse_wrap_content(child, losses) {
+                    flush_paragraph(&mut blocks, &mut current_inline);
+                    blocks.extend(wrapped);
+                } else if let Some(block) = wrap::parse_placed_figure(child, losses) {
                     flush_paragraph(&mut blocks, &mut current_inline);
                     blocks.push(block);
                 } else if let Some(block) = maybe_block_block(&child, losses) {
@@ -280,31 +878,81 @@ fn collect_blocks(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Block> {
                 } else if let Some(block) = maybe_vspace_block(&child, losses) {
                     flush_paragraph(&mut blocks, &mut current_inline);
                     blocks.push(block);
+                } else if let Some(block) =
+                    maybe_unsupported_block(&children, i, &current_inline, losses)
+                {
+                    flush_paragraph(&mut blocks, &mut current_inline);
+                    blocks.push(block);
                 } else {
-                    current_inline.extend(collect_inlines(&child, losses));
+                    extend_inlines(&mut current_inline, collect_inlines(&child, losses));
                 }
                 i += 1;
             }
             _ => {
-                current_inline.extend(collect_inlines(&child, losses));
+                extend_inlines(&mut current_inline, collect_inlines(&child, losses));
                 i += 1;
             }
         }
     }
 
+    if let Some(tracker) = tracker.as_mut() {
+        let end = tracker.offsets.last().copied().unwrap_or(0);
+        tracker.finish(end, &blocks, losses, &current_inline);
+    }
+    let before_flush = blocks.len();
     flush_paragraph(&mut blocks, &mut current_inline);
+    if let Some(tracker) = tracker.as_mut() {
+        if blocks.len() > before_flush {
+            tracker.finish_paragraph(&blocks[before_flush]);
+        }
+    }
     blocks
 }
 
+/// Return the label attached to `children[idx]`, i.e. a `<label>` that follows
+/// it with only spaces in between, together with the label's index.
+fn trailing_label(children: &[SyntaxNode], idx: usize) -> Option<(String, usize)> {
+    let mut lookahead = idx + 1;
+    while lookahead < children.len() && matches!(children[lookahead].kind(), SyntaxKind::Space) {
+        lookahead += 1;
+    }
+    let node = children.get(lookahead)?;
+    if node.kind() != SyntaxKind::Label {
+        return None;
+    }
+    extract_label_text(node).map(|label| (label, lookahead))
+}
+
+/// Starts the next odd page, leaving a blank even page when needed.
+const CLEAR_TO_ODD_PAGE: &str = "\\clearpage\\ifodd\\value{page}\\else\\hbox{}\\newpage\\fi";
+
+/// Starts the next even page, leaving a blank odd page when needed.
+const CLEAR_TO_EVEN_PAGE: &str = "\\clearpage\\ifodd\\value{page}\\hbox{}\\newpage\\fi";
+
 fn last_is_pagebreak(blocks: &[Block]) -> bool {
     matches!(
         blocks.last(),
         Some(Block::Paragraph(inlines))
             if inlines.len() == 1
-                && matches!(&inlines[0], Inline::RawLatex(raw) if raw.trim() == "\\newpage")
+                && matches!(
+                    &inlines[0],
+                    Inline::RawLatex(raw)
+                        if raw.trim() == "\\newpage"
+                            || raw.trim() == CLEAR_TO_ODD_PAGE
+                            || raw.trim() == CLEAR_TO_EVEN_PAGE
+                )
     )
 }
 
+/// Whether nothing but empty paragraphs follows the last page break, or the
+/// start.
+fn at_page_start(blocks: &[Block]) -> bool {
+    let last = blocks
+        .iter()
+        .rposition(|block| !matches!(block, Block::Paragraph(inlines) if inlines.is_empty()));
+    last.is_none_or(|last| last_is_pagebreak(&blocks[..=last]))
+}
+
 fn has_more_content(children: &[SyntaxNode], start: usize) -> bool {
     let mut idx = start;
     while idx < children.len() {
@@ -374,6 +1022,8 @@ fn maybe_environment_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<
         name: env_name,
         title,
         blocks,
+        label: None,
+        args: Vec::new(),
     }))
 }
 
@@ -385,6 +1035,8 @@ fn maybe_heading_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Bloc
 
     let mut level: u8 = 1;
     let mut numbered = true;
+    let mut outlined = true;
+    let mut bookmarked: Option<bool> = None;
     let mut content: Option<Vec<Inline>> = None;
 
     if let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) {
@@ -412,6 +1064,15 @@ fn maybe_heading_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Bloc
                                     }
                                 }
                             }
+                            "outlined" => {
+                                if let Some(flag) = parse_bool_literal(&value) {
+                                    outlined = flag;
+                                }
+                            }
+                            "bookmarked" => {
+                                // `auto` leaves the bookmark to follow `outlined`.
+                                bookmarked = parse_bool_literal(&value);
+                            }
                             _ => {}
                         }
                     }
@@ -432,6 +1093,9 @@ fn maybe_heading_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Bloc
         level,
         content: content.unwrap_or_default(),
         numbered,
+        label: None,
+        outlined,
+        bookmarked,
     })
 }
 
@@ -445,9 +1109,11 @@ fn collect_list(nodes: &[SyntaxNode], kind: ListKind, losses: &mut Vec<Loss>) ->
         let is_item = match kind {
             ListKind::Unordered => node.kind() == SyntaxKind::ListItem,
             ListKind::Ordered => node.kind() == SyntaxKind::EnumItem,
+            _ => false,
         };
         if is_item {
-            let item_blocks = collect_blocks(node, losses);
+            let mut item_blocks = collect_blocks(node, losses);
+            trim_item_start(&mut item_blocks);
             items.push(if item_blocks.is_empty() {
                 vec![Block::Paragraph(vec![])]
             } else {
@@ -470,13 +1136,159 @@ fn collect_list(nodes: &[SyntaxNode], kind: ListKind, losses: &mut Vec<Loss>) ->
     (Block::List { kind, items }, consumed)
 }
 
+fn collect_term_list(nodes: &[SyntaxNode], losses: &mut Vec<Loss>) -> (Block, usize) {
+    let mut items = Vec::new();
+    let mut consumed = 0;
+    for node in nodes {
+        match node.kind() {
+            SyntaxKind::TermItem => items.push(collect_term_item(node, losses)),
+            SyntaxKind::Space | SyntaxKind::Parbreak => {}
+            _ => break,
+        }
+        consumed += 1;
+    }
+    (Block::TermList(items), consumed)
+}
+
+fn collect_term_item(node: &SyntaxNode, losses: &mut Vec<Loss>) -> TermItem {
+    let mut parts = node.children().filter(|c| c.kind() == SyntaxKind::Markup);
+    let term = parts
+        .next()
+        .map(|term| collect_inlines(term, losses))
+        .unwrap_or_default();
+    let mut description = parts
+        .next()
+        .map(|desc| collect_blocks(desc, losses))
+        .unwrap_or_default();
+    trim_item_start(&mut description);
+    TermItem { term, description }
+}
+
+/// Drop the space between a list marker and the item text, which is
+/// otherwise merged into the item's first text node.
+fn trim_item_start(blocks: &mut [Block]) {
+    if let Some(Block::Paragraph(inlines)) = blocks.first_mut() {
+        if let Some(Inline::Text(text)) = inlines.first_mut() {
+            let trimmed = text.trim_start();
+            if trimmed.is_empty() {
+                inlines.remove(0);
+            } else if trimmed.len() != text.len() {
+                *text = trimmed.to_string();
+            }
+        }
+    }
+}
+
+/// Render a `/ Term: description` item as a bold term followed by its
+/// description.
+fn term_item_inlines(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
+    let mut parts = node.children().filter(|c| c.kind() == SyntaxKind::Markup);
+    let term = parts
+        .next()
+        .map(|term| collect_inlines(term, losses))
+        .unwrap_or_default();
+    let description = parts
+        .next()
+        .map(|desc| collect_inlines(desc, losses))
+        .unwrap_or_default();
+    let mut out = vec![Inline::Strong(term), Inline::Text(": ".to_string())];
+    out.extend(description);
+    out
+}
+
+/// Whether the markup of a content block holds list, enum or term items, or
+/// more than one paragraph.
+fn has_structured_content(node: &SyntaxNode) -> bool {
+    node.children().any(|child| match child.kind() {
+        SyntaxKind::ListItem
+        | SyntaxKind::EnumItem
+        | SyntaxKind::TermItem
+        | SyntaxKind::Parbreak => true,
+        SyntaxKind::Markup | SyntaxKind::ContentBlock => has_structured_content(child),
+        _ => false,
+    })
+}
+
+/// Block content for a table cell holding lists or several paragraphs; plain
+/// cells keep only their inline content. Losses are reported by the inline
+/// pass, so this one collects them into a scratch buffer.
+fn collect_cell_blocks(node: &SyntaxNode) -> Option<Vec<Block>> {
+    if !has_structured_content(node) {
+        return None;
+    }
+    let mut scratch = Vec::new();
+    Some(collect_blocks(node, &mut scratch))
+}
+
+/// `\caption` cannot hold block content, so list, enum and term items in a
+/// caption are demoted to inline runs separated by semicolons.
+fn collect_caption_inlines(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
+    if !has_structured_content(node) {
+        return collect_inlines(node, losses);
+    }
+    let mut children = Vec::new();
+    flatten_markup_children(node, &mut children);
+    let mut out = Vec::new();
+    let mut enum_index = 0usize;
+    let mut after_item = false;
+    for child in &children {
+        let item = match child.kind() {
+            SyntaxKind::ListItem => Some(collect_inlines(child, losses)),
+            SyntaxKind::EnumItem => {
+                enum_index += 1;
+                let mut inlines = vec![Inline::Text(format!("({}) ", enum_index))];
+                inlines.extend(collect_inlines(child, losses));
+                Some(inlines)
+            }
+            SyntaxKind::TermItem => Some(term_item_inlines(child, losses)),
+            _ => None,
+        };
+        match item {
+            Some(inlines) => {
+                if after_item {
+                    out.push(Inline::Text("; ".to_string()));
+                } else if has_inline_content(&out) {
+                    out.push(Inline::Text(" ".to_string()));
+                }
+                out.extend(inlines);
+                after_item = true;
+            }
+            None => {
+                if after_item && matches!(child.kind(), SyntaxKind::Space | SyntaxKind::Parbreak) {
+                    continue;
+                }
+                if child.kind() == SyntaxKind::Parbreak {
+                    out.push(Inline::Text(" ".to_string()));
+                } else {
+                    extend_inlines(&mut out, collect_inlines(child, losses));
+                }
+                after_item = false;
+            }
+        }
+    }
+    out
+}
+
 fn collect_inlines(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
+    let Some(_guard) = NestingGuard::enter(node, losses) else {
+        return Vec::new();
+    };
     let mut out = Vec::new();
     match node.kind() {
         SyntaxKind::Import
         | SyntaxKind::ModuleImport
         | SyntaxKind::Include
-        | SyntaxKind::ModuleInclude => {}
+        | SyntaxKind::ModuleInclude
+        | SyntaxKind::LetBinding => {}
+        SyntaxKind::Ident => {
+            if let Some(bound) = bindings::resolve(node.text()) {
+                if !budget_exhausted(losses) {
+                    out = collect_inlines(&bound, losses);
+                }
+            } else if node.text() == "pause" {
+                out.push(Inline::Pause);
+            }
+        }
         SyntaxKind::SetRule => {
             if !is_supported_set_rule(node) {
                 let msg = set_rule_name(node)
@@ -494,22 +1306,18 @@ fn collect_inlines(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
             }
         }
         SyntaxKind::Text | SyntaxKind::Str => {
-            let text = node.text().to_string();
-            if !text.is_empty() {
-                out.push(Inline::Text(text));
-            }
+            push_text(&mut out, node.text());
         }
         SyntaxKind::Space => {
-            out.push(Inline::Text(" ".to_string()));
+            push_text(&mut out, " ");
         }
         SyntaxKind::Parbreak | SyntaxKind::Linebreak => {
             out.push(Inline::LineBreak);
         }
         SyntaxKind::SmartQuote => {
-            let text = map_smart_quote(node.text());
-            if !text.is_empty() {
-                out.push(Inline::Text(text));
-            }
+            out.push(Inline::SmartQuote {
+                double: node.text() == "\"",
+            });
         }
         SyntaxKind::Shorthand => {
             if let Some(inline) = map_shorthand_inline(node.text()) {
@@ -517,17 +1325,14 @@ fn collect_inlines(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
             }
         }
         SyntaxKind::Escape => {
-            let text = decode_escape(node.text());
-            if !text.is_empty() {
-                out.push(Inline::Text(text));
-            }
+            push_text(&mut out, &decode_escape(node.text()));
         }
         SyntaxKind::ListMarker | SyntaxKind::EnumMarker => {}
         SyntaxKind::Strong => {
             let mut inner = Vec::new();
             for child in node.children() {
                 if child.kind() != SyntaxKind::Star {
-                    inner.extend(collect_inlines(&child, losses));
+                    extend_inlines(&mut inner, collect_inlines(&child, losses));
                 }
             }
             out.push(Inline::Strong(inner));
@@ -536,15 +1341,24 @@ fn collect_inlines(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
             let mut inner = Vec::new();
             for child in node.children() {
                 if child.kind() != SyntaxKind::Underscore {
-                    inner.extend(collect_inlines(&child, losses));
+                    extend_inlines(&mut inner, collect_inlines(&child, losses));
                 }
             }
             out.push(Inline::Emph(inner));
         }
+        SyntaxKind::Raw => {
+            let (lang, content, _) = raw_literal(node);
+            if !content.is_empty() {
+                out.push(Inline::Code { lang, content });
+            }
+        }
         SyntaxKind::Code => {
-            let text = node.text().to_string();
-            if !text.is_empty() {
-                out.push(Inline::Code(text));
+            let content = node.text().to_string();
+            if !content.is_empty() {
+                out.push(Inline::Code {
+                    lang: None,
+                    content,
+                });
             }
         }
         SyntaxKind::Equation | SyntaxKind::Math => {
@@ -557,14 +1371,33 @@ fn collect_inlines(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
             out.push(Inline::Link {
                 text: vec![Inline::Text(url.clone())],
                 url,
+                target: LinkTarget::Url,
             });
         }
         SyntaxKind::Ref => {
-            let text = node_full_text(node);
-            let label = text.trim_start_matches('@').to_string();
+            let label = node
+                .children()
+                .find(|c| c.kind() == SyntaxKind::RefMarker)
+                .map(|marker| marker.text().trim_start_matches('@').to_string())
+                .unwrap_or_default();
+            // `@label[..]`: the supplement of a reference, the note after
+            // a citation.
+            let supplement = node
+                .children()
+                .find(|c| c.kind() == SyntaxKind::ContentBlock);
             if !label.is_empty() {
                 if is_cross_ref_label(&label) {
-                    out.push(Inline::Ref(label));
+                    let supplement = supplement.map(|block| collect_inlines(block, losses));
+                    out.push(reference(label, supplement));
+                } else if let Some(block) = supplement {
+                    let note = node_full_text(block);
+                    let note = note.trim_start_matches('[').trim_end_matches(']').trim();
+                    out.push(Inline::Citation(Citation {
+                        keys: label,
+                        form: CiteForm::Normal,
+                        prenote: None,
+                        postnote: Some(note.to_string()),
+                    }));
                 } else {
                     out.push(Inline::Cite(label));
                 }
@@ -579,10 +1412,21 @@ fn collect_inlines(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
         }
         SyntaxKind::FuncCall => {
             if let Some(inlines) = maybe_inline_func(node, losses) {
-                out.extend(inlines);
+                extend_inlines(&mut out, inlines);
             } else {
+                if let Some(name) = get_func_call_name(node) {
+                    if is_unsupported_function(&name) {
+                        losses.push(
+                            Loss::new(
+                                "unsupported",
+                                format!("{} inside a paragraph not supported, content kept", name),
+                            )
+                            .with_severity(Severity::Error),
+                        );
+                    }
+                }
                 for child in node.children() {
-                    out.extend(collect_inlines(&child, losses));
+                    extend_inlines(&mut out, collect_inlines(&child, losses));
                 }
             }
         }
@@ -599,18 +1443,42 @@ fn collect_inlines(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
         }
         SyntaxKind::ContentBlock | SyntaxKind::Markup | SyntaxKind::CodeBlock => {
             for child in node.children() {
-                out.extend(collect_inlines(&child, losses));
+                extend_inlines(&mut out, collect_inlines(&child, losses));
             }
         }
         _ => {
             for child in node.children() {
-                out.extend(collect_inlines(&child, losses));
+                extend_inlines(&mut out, collect_inlines(&child, losses));
             }
         }
     }
     out
 }
 
+/// Append `text`, growing a preceding text node instead of starting a new
+/// one. Runs of words and spaces thus collapse into a single `Inline::Text`,
+/// and a space after text never allocates a node of its own.
+fn push_text(out: &mut Vec<Inline>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match out.last_mut() {
+        Some(Inline::Text(last)) => last.push_str(text),
+        _ => out.push(Inline::Text(text.to_string())),
+    }
+}
+
+/// Append `inlines`, merging a leading text node into a trailing one.
+fn extend_inlines(out: &mut Vec<Inline>, inlines: Vec<Inline>) {
+    let mut inlines = inlines.into_iter();
+    match (out.last_mut(), inlines.next()) {
+        (Some(Inline::Text(last)), Some(Inline::Text(text))) => last.push_str(&text),
+        (_, Some(first)) => out.push(first),
+        (_, None) => return,
+    }
+    out.extend(inlines);
+}
+
 fn has_inline_content(inlines: &[Inline]) -> bool {
     for inline in inlines {
         match inline {
@@ -636,7 +1504,7 @@ fn flush_paragraph(blocks: &mut Vec<Block>, current: &mut Vec<Inline>) {
 
 fn strip_trailing_bracket_artifact(inlines: &mut Vec<Inline>) {
     loop {
-        while let Some(last) = inlines.last() {
+        while let Some(last) = inlines.last_mut() {
             match last {
                 Inline::LineBreak => {
                     inlines.pop();
@@ -644,6 +1512,11 @@ fn strip_trailing_bracket_artifact(inlines: &mut Vec<Inline>) {
                 Inline::Text(text) if text.trim().is_empty() => {
                     inlines.pop();
                 }
+                Inline::Text(text) => {
+                    // Trailing spaces are merged into the last text node.
+                    text.truncate(text.trim_end().len());
+                    break;
+                }
                 _ => break,
             }
         }
@@ -694,7 +1567,7 @@ fn strip_trailing_bracket_artifact(inlines: &mut Vec<Inline>) {
 fn extract_math(node: &SyntaxNode) -> Option<String> {
     for child in node.children() {
         if child.kind() == SyntaxKind::Math {
-            let text = node_full_text(&child);
+            let text = node_full_text(child);
             if !text.trim().is_empty() {
                 return Some(text);
             }
@@ -723,8 +1596,22 @@ fn extract_label_text(node: &SyntaxNode) -> Option<String> {
     }
 }
 
+/// Source text of `node` and its descendants. Walks the tree with an explicit
+/// stack because `SyntaxNode::into_text` recurses once per nesting level.
 fn node_full_text(node: &SyntaxNode) -> String {
-    node.clone().into_text().to_string()
+    if node.children().len() == 0 {
+        return node.text().to_string();
+    }
+    let mut out = String::with_capacity(node.len());
+    let mut stack = vec![node];
+    while let Some(current) = stack.pop() {
+        if current.children().len() == 0 {
+            out.push_str(current.text());
+        } else {
+            stack.extend(current.children().rev());
+        }
+    }
+    out
 }
 
 /// Unescape Typst string escape sequences back to their original characters.
@@ -793,19 +1680,18 @@ fn maybe_align_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block>
     }
 
     let args = children.next()?;
-    let mut alignment = Alignment::Center;
+    let mut alignment = Some(Alignment::Center);
+    let mut vertical = None;
     let mut content_blocks: Vec<Block> = Vec::new();
 
     if name == "align" {
         // Try to find alignment keyword in args
         for child in args.children() {
-            if child.kind() == SyntaxKind::Ident {
-                let text = child.text().to_string();
-                alignment = match text.as_str() {
-                    "left" | "start" => Alignment::Left,
-                    "right" | "end" => Alignment::Right,
-                    _ => Alignment::Center,
-                };
+            if matches!(child.kind(), SyntaxKind::Ident | SyntaxKind::Binary) {
+                let parsed = parse_alignment(&node_full_text(child));
+                if parsed != (None, None) {
+                    (alignment, vertical) = parsed;
+                }
             }
             if child.kind() == SyntaxKind::ContentBlock {
                 content_blocks = collect_blocks(&child, losses);
@@ -818,6 +1704,7 @@ fn maybe_align_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block>
 
     Some(Block::Align {
         alignment,
+        vertical,
         blocks: if content_blocks.is_empty() {
             vec![Block::Paragraph(collect_inlines(&args, losses))]
         } else {
@@ -840,10 +1727,12 @@ fn maybe_figure_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block
     if func_name != "figure" {
         return None;
     }
-    let mut caption: Option<Vec<Inline>> = None;
+    let mut caption: Option<FigureCaption> = None;
     let mut label: Option<String> = None;
     let mut placement: Option<String> = None;
     let mut content: Option<FigureContent> = None;
+    let mut unnumbered = false;
+    let mut numbering: Option<String> = None;
 
     if let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) {
         for child in args.children() {
@@ -853,8 +1742,19 @@ fn maybe_figure_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block
                     if let Some(key) = key {
                         if key == "caption" {
                             if let Some(value) = extract_named_value_node(&child) {
-                                caption = Some(collect_inlines(&value, losses));
+                                if value.kind() != SyntaxKind::None {
+                                    caption = Some(figure_caption(&value, losses));
+                                }
                             }
+                        } else if key == "numbering" {
+                            let value = extract_named_value_node(child);
+                            unnumbered = value
+                                .as_ref()
+                                .is_some_and(|value| value.kind() == SyntaxKind::None);
+                            numbering = value
+                                .filter(|value| value.kind() == SyntaxKind::Str)
+                                .map(|value| value.text().trim_matches('"').to_string())
+                                .filter(|pattern| pattern != "1");
                         } else if key == "label" {
                             if let Some(value) = extract_named_value_node(&child) {
                                 let text = value.text().to_string();
@@ -870,14 +1770,33 @@ fn maybe_figure_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block
                     }
                 }
                 SyntaxKind::FuncCall => {
-                    if let Some(table) = parse_table_from_func_call(&child, losses) {
-                        content = Some(FigureContent::Table(table));
+                    if let Some(subfigures) = subfigures(child, losses) {
+                        content = Some(subfigures);
+                    } else if let Some(algorithm) = algorithm::parse_algorithm(&child, losses) {
+                        content = Some(FigureContent::Raw(vec![Block::Algorithm(algorithm)]));
+                    } else if let Some(table) = parse_table_from_func_call(&child, losses) {
+                        content = Some(FigureContent::Table(Box::new(table)));
                     } else if let Some(image) = parse_image_from_func_call(&child) {
                         content = Some(FigureContent::Image(image));
+                    } else if let Some((code, lang, _)) = raw_call(child)
+                        .filter(|_| get_func_call_name(child).as_deref() == Some("raw"))
+                    {
+                        content = Some(FigureContent::Raw(vec![Block::CodeBlock {
+                            lang,
+                            content: code,
+                        }]));
                     } else {
                         content = Some(FigureContent::Raw(collect_blocks(&child, losses)));
                     }
                 }
+                // Code set as a figure is a block whichever way it is written.
+                SyntaxKind::Raw => {
+                    let (lang, code, _) = raw_literal(child);
+                    content = Some(FigureContent::Raw(vec![Block::CodeBlock {
+                        lang,
+                        content: code,
+                    }]));
+                }
                 SyntaxKind::ContentBlock | SyntaxKind::Markup => {
                     content = Some(extract_figure_content_from_node(&child, losses));
                 }
@@ -887,18 +1806,107 @@ fn maybe_figure_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block
     }
 
     let content = content.unwrap_or(FigureContent::Raw(Vec::new()));
+    if let FigureContent::Raw(blocks) = &content {
+        if let [Block::Algorithm(algorithm)] = blocks.as_slice() {
+            // The figure only frames the pseudocode; LaTeX's `algorithm`
+            // float takes its caption and label.
+            let mut algorithm = algorithm.clone();
+            if let Some(caption) = caption {
+                algorithm.caption = caption.body;
+            }
+            algorithm.label = label.or(algorithm.label);
+            return Some(Block::Algorithm(algorithm));
+        }
+    }
+    let caption = caption.unwrap_or_default();
     Some(Block::Figure(Figure {
         content,
-        caption,
+        caption: caption.body,
         label,
         placement,
+        unnumbered,
+        caption_separator: caption.separator,
+        caption_position: caption.position,
+        short_caption: caption.short,
+        numbering,
+        wrap: None,
     }))
 }
 
+/// A figure's caption, with what `figure.caption(..)` says of it.
+#[derive(Default)]
+struct FigureCaption {
+    body: Option<Vec<Inline>>,
+    short: Option<Vec<Inline>>,
+    separator: Option<Vec<Inline>>,
+    position: Option<CaptionPosition>,
+}
+
+/// The caption of a figure, written plainly, as
+/// `figure.caption(position: .., separator: .., [body])`, or as the
+/// `flex-caption([long], [short])` that sets a shorter caption in the
+/// outline.
+fn figure_caption(node: &SyntaxNode, losses: &mut Vec<Loss>) -> FigureCaption {
+    let mut caption = FigureCaption::default();
+    let name = get_func_call_name(node).unwrap_or_default();
+    let args = node.children().find(|c| c.kind() == SyntaxKind::Args);
+    let Some(args) = args.filter(|_| matches!(name.as_str(), "figure.caption" | "flex-caption"))
+    else {
+        caption.body = Some(collect_caption_inlines(node, losses));
+        return caption;
+    };
+    let mut bodies = Vec::new();
+    for child in args.children() {
+        match child.kind() {
+            SyntaxKind::Named => {
+                let Some(value) = extract_named_value_node(child) else {
+                    continue;
+                };
+                match extract_named_key(child).as_deref() {
+                    Some("separator") => {
+                        caption.separator = Some(if value.kind() == SyntaxKind::Str {
+                            let text = unescape_typst_string(value.text().trim_matches('"'));
+                            vec![Inline::Text(text)]
+                        } else {
+                            collect_inlines(&value, losses)
+                        });
+                    }
+                    Some("position") => {
+                        caption.position = match parse_alignment(&node_full_text(&value)).1 {
+                            Some(VerticalAlignment::Top) => Some(CaptionPosition::Top),
+                            Some(VerticalAlignment::Bottom) => Some(CaptionPosition::Bottom),
+                            _ => None,
+                        };
+                    }
+                    _ => {}
+                }
+            }
+            SyntaxKind::ContentBlock | SyntaxKind::FuncCall => bodies.push(child),
+            _ => {}
+        }
+    }
+    let mut bodies = bodies.into_iter();
+    if name == "flex-caption" {
+        caption.body = bodies
+            .next()
+            .map(|body| collect_caption_inlines(body, losses));
+        caption.short = bodies
+            .next()
+            .map(|short| collect_caption_inlines(short, losses));
+    } else if let Some(body) = bodies.next() {
+        let inner = figure_caption(body, losses);
+        caption.body = inner.body;
+        caption.short = inner.short;
+    } else {
+        caption.body = Some(Vec::new());
+    }
+    caption
+}
+
 fn extract_figure_content_from_blocks(blocks: Vec<Block>) -> FigureContent {
     if blocks.len() == 1 {
         match blocks.into_iter().next().unwrap() {
-            Block::Table(table) => FigureContent::Table(table),
+            Block::Table(table) => FigureContent::Table(Box::new(table)),
             Block::Figure(fig) => {
                 if fig.caption.is_none() && fig.label.is_none() {
                     fig.content
@@ -914,9 +1922,13 @@ fn extract_figure_content_from_blocks(blocks: Vec<Block>) -> FigureContent {
 }
 
 fn extract_figure_content_from_node(node: &SyntaxNode, losses: &mut Vec<Loss>) -> FigureContent {
+    let grid = find_grid_call(node);
+    if let Some(subfigures) = grid.and_then(|grid| subfigures(&grid, losses)) {
+        return subfigures;
+    }
     if let Some(func) = find_descendant_func_call(node) {
         if let Some(table) = parse_table_from_func_call(&func, losses) {
-            return FigureContent::Table(table);
+            return FigureContent::Table(Box::new(table));
         }
         if let Some(image) = parse_image_from_func_call(&func) {
             return FigureContent::Image(image);
@@ -926,6 +1938,65 @@ fn extract_figure_content_from_node(node: &SyntaxNode, losses: &mut Vec<Loss>) -
     extract_figure_content_from_blocks(blocks)
 }
 
+/// The figures of a `grid(..)` whose every cell is a figure, the way a
+/// figure of subfigures is written:
+/// `figure(grid(columns: 2, [#figure(..) <a>], [#figure(..) <b>]), caption: ..)`.
+/// Without `columns:` the grid has one column, as in Typst.
+fn subfigures(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<FigureContent> {
+    if get_func_call_name(node)? != "grid" {
+        return None;
+    }
+    let args = node.children().find(|c| c.kind() == SyntaxKind::Args)?;
+    // Kept apart until every cell is known to be a figure, so a grid read
+    // again as plain content does not record its losses twice.
+    let mut cell_losses = Vec::new();
+    let mut figures = Vec::new();
+    let mut columns = 1;
+    for child in args.children() {
+        let blocks = match child.kind() {
+            SyntaxKind::Named => {
+                if extract_named_key(child).as_deref() == Some("columns") {
+                    let value = extract_named_value_text(child).unwrap_or_default();
+                    columns = infer_table_columns(&value).unwrap_or(columns).max(1);
+                }
+                continue;
+            }
+            SyntaxKind::Comma
+            | SyntaxKind::Space
+            | SyntaxKind::LeftParen
+            | SyntaxKind::RightParen => continue,
+            SyntaxKind::FuncCall => maybe_figure_block(child, &mut cell_losses)
+                .into_iter()
+                .collect(),
+            SyntaxKind::ContentBlock => collect_blocks(child, &mut cell_losses),
+            _ => return None,
+        };
+        match <[Block; 1]>::try_from(blocks) {
+            Ok([Block::Figure(figure)]) => figures.push(figure),
+            _ => return None,
+        }
+    }
+    if figures.is_empty() {
+        return None;
+    }
+    losses.extend(cell_losses);
+    Some(FigureContent::SubFigures { figures, columns })
+}
+
+fn find_grid_call(node: &SyntaxNode) -> Option<SyntaxNode> {
+    let mut stack = vec![node.clone()];
+    while let Some(current) = stack.pop() {
+        if current.kind() == SyntaxKind::FuncCall {
+            if get_func_call_name(&current).as_deref() == Some("grid") {
+                return Some(current);
+            }
+            continue;
+        }
+        stack.extend(current.children().cloned());
+    }
+    None
+}
+
 fn find_descendant_func_call(node: &SyntaxNode) -> Option<SyntaxNode> {
     let mut stack = vec![node.clone()];
     while let Some(current) = stack.pop() {
@@ -950,17 +2021,25 @@ fn maybe_image_block(node: &SyntaxNode, _losses: &mut Vec<Loss>) -> Option<Block
         caption: None,
         label: None,
         placement: None,
+        unnumbered: false,
+        caption_separator: None,
+        caption_position: None,
+        short_caption: None,
+        numbering: None,
+        wrap: None,
     };
     Some(Block::Figure(figure))
 }
 
-fn maybe_bibliography_block(node: &SyntaxNode, _losses: &mut Vec<Loss>) -> Option<Block> {
+fn maybe_bibliography_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block> {
     let func_name = get_func_call_name(node)?;
     if func_name != "bibliography" {
         return None;
     }
     let mut files: Vec<String> = Vec::new();
     let mut style: Option<String> = None;
+    let mut full = false;
+    let mut title: Option<Vec<Inline>> = None;
     if let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) {
         for child in args.children() {
             match child.kind() {
@@ -971,6 +2050,10 @@ fn maybe_bibliography_block(node: &SyntaxNode, _losses: &mut Vec<Loss>) -> Optio
                             style = Some(value.text().trim_matches('"').to_string());
                         } else if key == "file" || key == "files" {
                             collect_bibliography_files(&value, &mut files);
+                        } else if key == "full" {
+                            full = parse_bool_literal(&value).unwrap_or(false);
+                        } else if key == "title" {
+                            title = bibliography_title(&value, losses);
                         }
                     }
                 }
@@ -984,7 +2067,25 @@ fn maybe_bibliography_block(node: &SyntaxNode, _losses: &mut Vec<Loss>) -> Optio
         return None;
     }
     let file = files.join(",");
-    Some(Block::Bibliography { file, style })
+    Some(Block::Bibliography {
+        file,
+        style,
+        full,
+        title,
+    })
+}
+
+/// `auto` keeps the default heading, `none` drops it.
+fn bibliography_title(value: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<Inline>> {
+    match value.kind() {
+        SyntaxKind::Auto => None,
+        SyntaxKind::None => Some(Vec::new()),
+        SyntaxKind::Str => {
+            let text = unescape_typst_string(value.text().trim_matches('"'));
+            Some(vec![Inline::Text(text)])
+        }
+        _ => Some(collect_inlines(value, losses)),
+    }
 }
 
 fn collect_bibliography_files(node: &SyntaxNode, files: &mut Vec<String>) {
@@ -1010,6 +2111,7 @@ fn parse_image_from_func_call(node: &SyntaxNode) -> Option<Image> {
     let mut width: Option<String> = None;
     let mut height: Option<String> = None;
     let mut fit: Option<String> = None;
+    let mut alt: Option<String> = None;
     if let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) {
         for child in args.children() {
             match child.kind() {
@@ -1026,6 +2128,7 @@ fn parse_image_from_func_call(node: &SyntaxNode) -> Option<Image> {
                             "width" => width = Some(text),
                             "height" => height = Some(text),
                             "fit" => fit = Some(text),
+                            "alt" if value.kind() == SyntaxKind::Str => alt = Some(text),
                             _ => {}
                         }
                     }
@@ -1040,6 +2143,7 @@ fn parse_image_from_func_call(node: &SyntaxNode) -> Option<Image> {
         width,
         height,
         fit,
+        alt,
     })
 }
 
@@ -1159,9 +2263,14 @@ fn maybe_math_equation_block(node: &SyntaxNode, _losses: &mut Vec<Loss>) -> Opti
     Some(Block::MathBlock(MathBlock {
         content: math_content,
         label: None,
+        latex: false,
     }))
 }
 
+thread_local! {
+    static IN_COLUMNS: Cell<bool> = const { Cell::new(false) };
+}
+
 fn maybe_columns_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block> {
     let func_name = get_func_call_name(node)?;
     if func_name != "columns" {
@@ -1183,8 +2292,15 @@ fn maybe_columns_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Bloc
                         }
                     }
                 }
+                SyntaxKind::Int => {
+                    if let Ok(n) = child.text().trim().parse::<usize>() {
+                        columns = n.max(1);
+                    }
+                }
                 SyntaxKind::ContentBlock | SyntaxKind::Markup => {
-                    blocks.push(Block::Paragraph(collect_inlines(&child, losses)));
+                    let outer = IN_COLUMNS.with(|cell| cell.replace(true));
+                    blocks.extend(collect_blocks(&child, losses));
+                    IN_COLUMNS.with(|cell| cell.set(outer));
                 }
                 _ => {}
             }
@@ -1227,11 +2343,26 @@ fn maybe_grid_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block>
                         if let Some(value) = extract_named_value_node(&child) {
                             column_gutter = Some(node_full_text(&value));
                         }
+                    } else if key == "align" {
+                        // Grid cells are set on one line each, where
+                        // vertical alignment has nothing to move.
+                        let value = extract_named_value_text(child).unwrap_or_default();
+                        if parse_alignment(&value).1.is_some() {
+                            losses.push(Loss::new(
+                                "align",
+                                "vertical alignment of grid cells was dropped",
+                            ));
+                        }
                     }
                 }
                 SyntaxKind::ContentBlock | SyntaxKind::Markup => {
                     cells.push(collect_blocks(&child, losses));
                 }
+                SyntaxKind::Spread => {
+                    for markup in spread_cells(child, losses) {
+                        cells.push(collect_blocks(&markup, losses));
+                    }
+                }
                 _ => {}
             }
         }
@@ -1246,34 +2377,98 @@ fn maybe_grid_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block>
     }))
 }
 
-fn maybe_code_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block> {
+/// A `raw(..)` call set as its own block: `block: true`, or standing alone
+/// between paragraph breaks. In running text it stays inline code.
+fn maybe_code_block(
+    children: &[SyntaxNode],
+    idx: usize,
+    current_inline: &[Inline],
+    losses: &mut Vec<Loss>,
+) -> Option<Block> {
+    let node = &children[idx];
     let func_name = get_func_call_name(node)?;
     if func_name != "raw" {
         return None;
     }
-    if let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) {
-        for child in args.children() {
-            if child.kind() == SyntaxKind::Str || child.kind() == SyntaxKind::Text {
-                let text = child.text().to_string();
-                let unquoted = text.trim_matches('"');
-                // Unescape Typst string escapes (e.g., \\ → \, \" → ")
-                return Some(Block::CodeBlock(unescape_typst_string(unquoted)));
-            }
-            if child.kind() == SyntaxKind::ContentBlock {
-                let text = node_full_text(&child);
-                return Some(Block::CodeBlock(text));
-            }
+    let Some((content, lang, block)) = raw_call(node) else {
+        losses.push(
+            Loss::new(
+                "raw",
+                "raw block without simple string content not supported",
+            )
+            .with_suggestion("pass the text as a string literal"),
+        );
+        return Some(Block::CodeBlock {
+            lang: None,
+            content: node_full_text(node),
+        });
+    };
+    if block != Some(true) {
+        let mut next = idx + 1;
+        while next < children.len() && children[next].kind() == SyntaxKind::Space {
+            next += 1;
+        }
+        let followed = next < children.len() && children[next].kind() != SyntaxKind::Parbreak;
+        if block == Some(false) || has_inline_content(current_inline) || followed {
+            return None;
         }
-        if let Some(text) = find_first_string(&args) {
-            return Some(Block::CodeBlock(text));
+    }
+    Some(Block::CodeBlock { lang, content })
+}
+
+/// The text, `lang:` and `block:` arguments of a `raw(..)` call.
+fn raw_call(node: &SyntaxNode) -> Option<(String, Option<String>, Option<bool>)> {
+    let args = node.children().find(|c| c.kind() == SyntaxKind::Args)?;
+    let mut lang = None;
+    let mut block = None;
+    for child in args.children().filter(|c| c.kind() == SyntaxKind::Named) {
+        let Some(value) = extract_named_value_node(child) else {
+            continue;
+        };
+        match extract_named_key(child).as_deref() {
+            Some("lang") => lang = parse_string_literal(&value),
+            Some("block") => block = parse_bool_literal(&value),
+            _ => {}
+        }
+    }
+    for child in args.children() {
+        if child.kind() == SyntaxKind::Str || child.kind() == SyntaxKind::Text {
+            let text = child.text().to_string();
+            let unquoted = text.trim_matches('"');
+            // Unescape Typst string escapes (e.g., \\ → \, \" → ")
+            return Some((unescape_typst_string(unquoted), lang, block));
+        }
+        if child.kind() == SyntaxKind::ContentBlock {
+            return Some((node_full_text(child), lang, block));
         }
-        return Some(Block::CodeBlock(node_full_text(&args)));
     }
-    losses.push(Loss::new(
-        "raw",
-        "raw block without simple string content not supported",
-    ));
-    Some(Block::CodeBlock(node_full_text(node)))
+    let content = find_first_string(&args).unwrap_or_else(|| node_full_text(&args));
+    Some((content, lang, block))
+}
+
+/// The language tag and lines of a backtick raw literal. Only fenced literals
+/// spanning several lines are blocks.
+fn raw_literal(node: &SyntaxNode) -> (Option<String>, String, bool) {
+    let fenced = node
+        .children()
+        .find(|c| c.kind() == SyntaxKind::RawDelim)
+        .is_some_and(|delim| delim.text().len() >= 3);
+    let lang = node
+        .children()
+        .find(|c| c.kind() == SyntaxKind::RawLang)
+        .map(|lang| lang.text().to_string())
+        .filter(|_| fenced);
+    let block = fenced
+        && node
+            .children()
+            .any(|c| c.kind() == SyntaxKind::RawTrimmed && c.text().contains('\n'));
+    let content = node
+        .children()
+        .filter(|c| c.kind() == SyntaxKind::Text)
+        .map(|c| c.text().as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (lang, content, block)
 }
 
 fn find_first_string(node: &SyntaxNode) -> Option<String> {
@@ -1301,11 +2496,20 @@ fn parse_table_from_func_call(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Opti
 
     let mut columns: Option<usize> = None;
     let mut align: Option<Vec<Alignment>> = None;
+    let mut valign: Option<VerticalAlignment> = None;
+    let mut align_function: Option<SyntaxNode> = None;
     let mut caption: Option<Vec<Inline>> = None;
     let mut cells: Vec<TableCell> = Vec::new();
     let mut stroke: Option<String> = None;
     let mut fill: Option<String> = None;
     let mut inset: Option<String> = None;
+    let mut gutter: Option<String> = None;
+    let mut row_gutter: Option<String> = None;
+    let mut column_gutter: Option<String> = None;
+    let mut repeat_header = true;
+    let mut widths: Vec<ColumnWidth> = Vec::new();
+    let mut lines: Vec<rules::PendingLine> = Vec::new();
+    let mut footer: Vec<TableCell> = Vec::new();
 
     let args = node.children().find(|c| c.kind() == SyntaxKind::Args)?;
     for child in args.children() {
@@ -1315,20 +2519,59 @@ fn parse_table_from_func_call(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Opti
                 let value_text = extract_named_value_text(&child);
                 if let Some(value) = extract_named_value_node(&child) {
                     if key == "caption" {
-                        caption = Some(collect_inlines(&value, losses));
+                        caption = Some(collect_caption_inlines(&value, losses));
                     } else if key == "stroke" {
                         stroke = Some(node_full_text(&value));
                     } else if key == "fill" {
                         fill = Some(node_full_text(&value));
                     } else if key == "inset" {
                         inset = Some(node_full_text(&value));
+                    } else if matches!(key.as_str(), "gutter" | "row-gutter" | "column-gutter") {
+                        let text = node_full_text(&value);
+                        let slot = match key.as_str() {
+                            "gutter" => &mut gutter,
+                            "row-gutter" => &mut row_gutter,
+                            _ => &mut column_gutter,
+                        };
+                        // Only one length for all rows or columns carries
+                        // over to `\tabcolsep` and `\arraystretch`.
+                        if value.kind() == SyntaxKind::Numeric
+                            && !text.ends_with("fr")
+                            && !text.ends_with('%')
+                        {
+                            *slot = Some(text);
+                        } else if text != "auto" {
+                            losses.push(
+                                Loss::new(
+                                    "gutter",
+                                    format!("table {} `{}` was not applied", key, text),
+                                )
+                                .with_suggestion("give one fixed length, e.g. `6pt`"),
+                            );
+                        }
                     }
                 }
                 if let Some(value_text) = value_text {
                     if key == "columns" {
                         columns = infer_table_columns(&value_text);
+                        widths = parse_column_widths(&value_text);
                     } else if key == "align" {
-                        align = Some(parse_typst_align(&value_text));
+                        match extract_named_value_node(child) {
+                            Some(value) if value.kind() == SyntaxKind::Closure => {
+                                align_function = Some(value);
+                            }
+                            Some(value)
+                                if value.children().any(|c| c.kind() == SyntaxKind::Array) =>
+                            {
+                                let reason =
+                                    format!("nested alignment `{}` was not applied", value_text);
+                                losses.push(Loss::new("align", reason));
+                            }
+                            _ => {
+                                align = Some(parse_typst_align(&value_text));
+                                valign = parse_typst_valign(&value_text, losses);
+                            }
+                        }
                     } else if key == "stroke" && stroke.is_none() {
                         stroke = Some(value_text);
                     } else if key == "fill" && fill.is_none() {
@@ -1342,10 +2585,13 @@ fn parse_table_from_func_call(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Opti
                 let content = collect_inlines(&child, losses);
                 cells.push(TableCell {
                     content,
+                    blocks: collect_cell_blocks(child),
                     colspan: 1,
                     rowspan: 1,
                     align: None,
+                    valign: None,
                     is_header: false,
+                    is_footer: false,
                     fill: None,
                     stroke: None,
                     inset: None,
@@ -1354,16 +2600,24 @@ fn parse_table_from_func_call(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Opti
             SyntaxKind::FuncCall => {
                 // Skip table structural elements that aren't data cells
                 if let Some(func_name) = get_func_call_name(&child) {
-                    if matches!(
-                        func_name.as_str(),
-                        "table.hline" | "table.vline" | "table.footer"
-                    ) {
-                        // Skip layout directives - they don't contribute data cells
+                    if matches!(func_name.as_str(), "table.hline" | "table.vline") {
+                        lines.push(rules::PendingLine::new(child, cells.len()));
+                        continue;
+                    }
+                    if func_name == "table.footer" {
+                        // Typst places the footer at the bottom wherever it
+                        // is written.
+                        if let Some(cells) = extract_table_section_cells(child, losses) {
+                            footer.extend(cells);
+                        }
                         continue;
                     }
                 }
                 // If it's table.cell(...) just capture its content block as a cell.
-                if let Some(header_cells) = extract_table_header_cells(&child, losses) {
+                if let Some(header_cells) = extract_table_section_cells(&child, losses) {
+                    if let Some(repeat) = table_header_repeat(child) {
+                        repeat_header = repeat;
+                    }
                     cells.extend(header_cells);
                 } else if let Some(cell) = extract_cell_from_table_cell(&child, losses) {
                     cells.push(cell);
@@ -1371,10 +2625,30 @@ fn parse_table_from_func_call(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Opti
                     let content = collect_inlines(&child, losses);
                     cells.push(TableCell {
                         content,
+                        blocks: None,
+                        colspan: 1,
+                        rowspan: 1,
+                        align: None,
+                        valign: None,
+                        is_header: false,
+                        is_footer: false,
+                        fill: None,
+                        stroke: None,
+                        inset: None,
+                    });
+                }
+            }
+            SyntaxKind::Spread => {
+                for markup in spread_cells(child, losses) {
+                    cells.push(TableCell {
+                        content: collect_inlines(&markup, losses),
+                        blocks: collect_cell_blocks(&markup),
                         colspan: 1,
                         rowspan: 1,
                         align: None,
+                        valign: None,
                         is_header: false,
+                        is_footer: false,
                         fill: None,
                         stroke: None,
                         inset: None,
@@ -1386,10 +2660,13 @@ fn parse_table_from_func_call(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Opti
                 if has_inline_content(&content) {
                     cells.push(TableCell {
                         content,
+                        blocks: None,
                         colspan: 1,
                         rowspan: 1,
                         align: None,
+                        valign: None,
                         is_header: false,
+                        is_footer: false,
                         fill: None,
                         stroke: None,
                         inset: None,
@@ -1399,15 +2676,35 @@ fn parse_table_from_func_call(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Opti
         }
     }
 
+    cells.extend(footer);
     let columns = columns.unwrap_or_else(|| infer_columns_from_cells(cells.len()));
+    if let Some(function) = align_function {
+        align::apply_align_function(
+            &function,
+            &mut cells,
+            columns.max(1),
+            &mut align,
+            &mut valign,
+            losses,
+        );
+    }
+    let rules = rules::table_rules(&lines, &cells, columns.max(1), &mut stroke);
     Some(Table {
         columns: columns.max(1),
         cells,
         align,
+        valign,
         caption,
         stroke,
         fill,
         inset,
+        label: None,
+        repeat_header,
+        widths,
+        gutter,
+        row_gutter,
+        column_gutter,
+        rules,
     })
 }
 
@@ -1499,6 +2796,7 @@ fn is_supported_set_rule(node: &SyntaxNode) -> bool {
             | "align"
             | "heading"
             | "cite"
+            | "ref"
             | "enum"
             | "document"
     )
@@ -1584,8 +2882,10 @@ fn extract_cell_from_table_cell(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Op
     let mut colspan = 1usize;
     let mut rowspan = 1usize;
     let mut align: Option<Alignment> = None;
+    let mut valign: Option<VerticalAlignment> = None;
     let mut is_header = false;
     let mut content: Option<Vec<Inline>> = None;
+    let mut blocks: Option<Vec<Block>> = None;
     let mut fill: Option<String> = None;
     let mut stroke: Option<String> = None;
     let mut inset: Option<String> = None;
@@ -1608,10 +2908,7 @@ fn extract_cell_from_table_cell(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Op
                                         rowspan = n.max(1);
                                     }
                                 } else if key == "align" {
-                                    let parsed = parse_typst_align(&text);
-                                    if let Some(first) = parsed.first() {
-                                        align = Some(*first);
-                                    }
+                                    (align, valign) = parse_alignment(&node_full_text(&value));
                                 } else if key == "header" {
                                     is_header = text.contains("true");
                                 } else if key == "stroke" {
@@ -1625,6 +2922,7 @@ fn extract_cell_from_table_cell(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Op
                         }
                         SyntaxKind::ContentBlock | SyntaxKind::Markup => {
                             content = Some(collect_inlines(&arg, losses));
+                            blocks = collect_cell_blocks(arg);
                         }
                         _ => {}
                     }
@@ -1632,6 +2930,7 @@ fn extract_cell_from_table_cell(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Op
             }
             SyntaxKind::ContentBlock | SyntaxKind::Markup => {
                 content = Some(collect_inlines(&child, losses));
+                blocks = collect_cell_blocks(child);
             }
             _ => {}
         }
@@ -1639,57 +2938,69 @@ fn extract_cell_from_table_cell(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Op
     let content = content.unwrap_or_default();
     Some(TableCell {
         content,
+        blocks,
         colspan,
         rowspan,
         align,
+        valign,
         is_header,
+        is_footer: false,
         fill,
         stroke,
         inset,
     })
 }
 
-fn extract_table_header_cells(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<TableCell>> {
+/// The `repeat:` flag of a `table.header(...)` call, if given.
+fn table_header_repeat(node: &SyntaxNode) -> Option<bool> {
+    let args = node.children().find(|c| c.kind() == SyntaxKind::Args)?;
+    args.children()
+        .filter(|child| child.kind() == SyntaxKind::Named)
+        .find(|child| extract_named_key(child).as_deref() == Some("repeat"))
+        .and_then(extract_named_value_node)
+        .and_then(|value| parse_bool_literal(&value))
+}
+
+/// The cells of a `table.header(...)` or `table.footer(...)` call, flagged
+/// as header or footer cells.
+fn extract_table_section_cells(
+    node: &SyntaxNode,
+    losses: &mut Vec<Loss>,
+) -> Option<Vec<TableCell>> {
     let name = get_func_call_name(node)?;
-    if name != "table.header" {
-        return None;
-    }
-    let mut cells = Vec::new();
+    let footer = match name.as_str() {
+        "table.header" => false,
+        "table.footer" => true,
+        _ => return None,
+    };
+    let mut sections = Vec::new();
     for child in node.children() {
         match child.kind() {
             SyntaxKind::Args => {
-                for arg in child.children() {
-                    if matches!(arg.kind(), SyntaxKind::ContentBlock | SyntaxKind::Markup) {
-                        let content = collect_inlines(&arg, losses);
-                        cells.push(TableCell {
-                            content,
-                            colspan: 1,
-                            rowspan: 1,
-                            align: None,
-                            is_header: true,
-                            fill: None,
-                            stroke: None,
-                            inset: None,
-                        });
-                    }
-                }
-            }
-            SyntaxKind::ContentBlock | SyntaxKind::Markup => {
-                let content = collect_inlines(&child, losses);
-                cells.push(TableCell {
-                    content,
-                    colspan: 1,
-                    rowspan: 1,
-                    align: None,
-                    is_header: true,
-                    fill: None,
-                    stroke: None,
-                    inset: None,
-                });
+                sections.extend(child.children().filter(|arg| {
+                    matches!(arg.kind(), SyntaxKind::ContentBlock | SyntaxKind::Markup)
+                }))
             }
+            SyntaxKind::ContentBlock | SyntaxKind::Markup => sections.push(child),
             _ => {}
         }
     }
+    let cells: Vec<TableCell> = sections
+        .into_iter()
+        .map(|section| TableCell {
+            content: collect_inlines(section, losses),
+            blocks: collect_cell_blocks(section),
+            colspan: 1,
+            rowspan: 1,
+            align: None,
+            valign: None,
+            is_header: !footer,
+            is_footer: footer,
+            fill: None,
+            stroke: None,
+            inset: None,
+        })
+        .collect();
     if cells.is_empty() {
         return None;
     }
@@ -1736,6 +3047,85 @@ fn infer_table_columns(value: &str) -> Option<usize> {
     Some(1)
 }
 
+/// Read the widths of `columns: (auto, 1fr, 3cm)`. A plain count, an
+/// expression such as `(1fr,) * 3`, or a width that is not a literal length
+/// gives no widths, and the columns size to their content.
+fn parse_column_widths(value: &str) -> Vec<ColumnWidth> {
+    let Some(inner) = value
+        .trim()
+        .strip_prefix('(')
+        .and_then(|rest| rest.strip_suffix(')'))
+    else {
+        return Vec::new();
+    };
+    let mut widths = Vec::new();
+    for entry in inner.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let width = if entry == "auto" {
+            ColumnWidth::Auto
+        } else if let Some(fr) = entry
+            .strip_suffix("fr")
+            .filter(|fr| fr.parse::<f64>().is_ok_and(|fr| fr > 0.0))
+        {
+            ColumnWidth::Fraction(fr.to_string())
+        } else if is_literal_length(entry) {
+            ColumnWidth::Length(entry.to_string())
+        } else {
+            return Vec::new();
+        };
+        widths.push(width);
+    }
+    widths
+}
+
+fn is_literal_length(value: &str) -> bool {
+    ["pt", "mm", "cm", "in", "em", "%"].iter().any(|unit| {
+        value
+            .strip_suffix(unit)
+            .is_some_and(|number| number.parse::<f64>().is_ok_and(|n| n >= 0.0))
+    })
+}
+
+/// The horizontal and vertical parts of an alignment such as `left`,
+/// `horizon` or `center + bottom`.
+fn parse_alignment(value: &str) -> (Option<Alignment>, Option<VerticalAlignment>) {
+    let mut horizontal = None;
+    let mut vertical = None;
+    for part in value.split('+') {
+        match part.trim() {
+            "left" | "start" => horizontal = Some(Alignment::Left),
+            "right" | "end" => horizontal = Some(Alignment::Right),
+            "center" => horizontal = Some(Alignment::Center),
+            "top" => vertical = Some(VerticalAlignment::Top),
+            "horizon" => vertical = Some(VerticalAlignment::Horizon),
+            "bottom" => vertical = Some(VerticalAlignment::Bottom),
+            _ => {}
+        }
+    }
+    (horizontal, vertical)
+}
+
+/// The vertical alignment of a table's `align`. A LaTeX column type aligns
+/// a whole row, so columns aligned differently all take the first one.
+fn parse_typst_valign(value: &str, losses: &mut Vec<Loss>) -> Option<VerticalAlignment> {
+    let inner = value
+        .trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .trim();
+    let verticals: Vec<Option<VerticalAlignment>> = inner
+        .split(',')
+        .map(|column| parse_alignment(column).1)
+        .collect();
+    let first = verticals.iter().copied().flatten().next();
+    if verticals.iter().any(|vertical| *vertical != verticals[0]) {
+        losses.push(Loss::new(
+            "align",
+            "columns with different vertical alignments all took the first one",
+        ));
+    }
+    first
+}
+
 fn parse_typst_align(value: &str) -> Vec<Alignment> {
     let inner = value
         .trim()
@@ -1769,6 +3159,7 @@ fn maybe_outline_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Bloc
         return None;
     }
     let mut title: Option<Vec<Inline>> = None;
+    let mut target = OutlineTarget::Headings;
     if let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) {
         for child in args.children() {
             if child.kind() == SyntaxKind::Named {
@@ -1778,15 +3169,40 @@ fn maybe_outline_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Bloc
                         title = Some(collect_inlines(&value, losses));
                     }
                 } else if key == "target" {
-                    losses.push(Loss::new(
-                        "outline",
-                        "outline target not supported in IR pipeline",
-                    ));
+                    let value = extract_named_value_node(child)
+                        .map(|value| node_full_text(&value))
+                        .unwrap_or_default();
+                    match outline_target(&value) {
+                        Some(kind) => target = kind,
+                        None => losses.push(
+                            Loss::new("outline", "outline target not supported in IR pipeline")
+                                .with_severity(Severity::Error)
+                                .with_suggestion(
+                                    "target `heading`, `figure` or `figure.where(kind: ..)` \
+                                     with `image`, `table` or `raw`",
+                                ),
+                        ),
+                    }
                 }
             }
         }
     }
-    Some(Block::Outline { title })
+    Some(Block::Outline { title, target })
+}
+
+/// The outline a `target:` selector lists: headings, or figures of one of
+/// the kinds LaTeX keeps a list of.
+fn outline_target(selector: &str) -> Option<OutlineTarget> {
+    let selector: String = selector.chars().filter(|c| !c.is_whitespace()).collect();
+    match selector.as_str() {
+        "heading" | "selector(heading)" => Some(OutlineTarget::Headings),
+        "figure" | "selector(figure)" | "figure.where(kind:image)" => {
+            Some(OutlineTarget::Figures)
+        }
+        "figure.where(kind:table)" => Some(OutlineTarget::Tables),
+        "figure.where(kind:raw)" => Some(OutlineTarget::Listings),
+        _ => None,
+    }
 }
 
 fn maybe_appendix_blocks(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<Block>> {
@@ -1826,6 +3242,9 @@ fn maybe_appendix_blocks(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Ve
             level: 1,
             content: title,
             numbered: true,
+            label: None,
+            outlined: true,
+            bookmarked: None,
         });
     }
     blocks.extend(body_blocks);
@@ -1911,14 +3330,46 @@ fn maybe_page_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<PageBlo
     })
 }
 
-fn maybe_pagebreak_block(node: &SyntaxNode) -> Option<Block> {
-    let func_name = get_func_call_name(node)?;
-    if func_name != "pagebreak" {
-        return None;
+/// A page or column break, and whether it is weak. A break to an odd or
+/// even page leaves a blank page where needed, in one- and two-sided
+/// classes alike.
+fn maybe_pagebreak_block(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<(Block, bool)> {
+    let mut command = match get_func_call_name(node)?.as_str() {
+        "pagebreak" => "\\newpage",
+        // `\columnbreak` only breaks the columns of a `multicols`; in a
+        // page set in two columns by the class, `\newpage` ends the column.
+        "colbreak" if IN_COLUMNS.with(Cell::get) => "\\columnbreak",
+        "colbreak" => "\\newpage",
+        _ => return None,
+    };
+    let mut weak = false;
+    let args = node.children().find(|c| c.kind() == SyntaxKind::Args);
+    for child in args.iter().flat_map(|args| args.children()) {
+        if child.kind() != SyntaxKind::Named {
+            continue;
+        }
+        let Some(value) = extract_named_value_node(child) else {
+            continue;
+        };
+        match extract_named_key(child).as_deref() {
+            Some("weak") => weak = node_full_text(&value).trim() == "true",
+            Some("to") if command == "\\newpage" => match parse_string_literal(&value).as_deref() {
+                Some("odd") => command = CLEAR_TO_ODD_PAGE,
+                Some("even") => command = CLEAR_TO_EVEN_PAGE,
+                _ if value.kind() == SyntaxKind::None => {}
+                _ => losses.push(
+                    Loss::new(
+                        "pagebreak",
+                        format!("page break target `{}` was not applied", node_full_text(&value)),
+                    )
+                    .with_suggestion("break to `\"odd\"` or `\"even\"`"),
+                ),
+            },
+            _ => {}
+        }
     }
-    Some(Block::Paragraph(vec![Inline::RawLatex(
-        "\\newpage".to_string(),
-    )]))
+    let block = Block::Paragraph(vec![Inline::RawLatex(command.to_string())]);
+    Some((block, weak))
 }
 
 fn maybe_vspace_block(node: &SyntaxNode, _losses: &mut Vec<Loss>) -> Option<Block> {
@@ -1930,9 +3381,82 @@ fn maybe_vspace_block(node: &SyntaxNode, _losses: &mut Vec<Loss>) -> Option<Bloc
     Some(Block::VSpace(size))
 }
 
+/// Built-ins whose effect the IR cannot express. Flattening their arguments
+/// into the surrounding text garbles it, so they are kept as source instead.
+fn is_unsupported_function(name: &str) -> bool {
+    matches!(
+        name,
+        "rotate"
+            | "scale"
+            | "move"
+            | "skew"
+            | "place"
+            | "repeat"
+            | "stack"
+            | "layout"
+            | "measure"
+            | "locate"
+            | "query"
+            | "context"
+            | "circle"
+            | "ellipse"
+            | "rect"
+            | "square"
+            | "line"
+            | "polygon"
+            | "path"
+            | "curve"
+            | "lorem"
+    )
+}
+
+/// A call to an unsupported built-in that stands on its own becomes an
+/// `Unsupported` block carrying its verbatim source, `#` included. Calls
+/// inside running text stay inline so the paragraph is not split.
+fn maybe_unsupported_block(
+    children: &[SyntaxNode],
+    idx: usize,
+    current_inline: &[Inline],
+    losses: &mut Vec<Loss>,
+) -> Option<Block> {
+    let node = &children[idx];
+    let func_name = get_func_call_name(node)?;
+    if !is_unsupported_function(&func_name) || has_inline_content(current_inline) {
+        return None;
+    }
+    let mut next = idx + 1;
+    while next < children.len() && children[next].kind() == SyntaxKind::Space {
+        next += 1;
+    }
+    if next < children.len() && children[next].kind() != SyntaxKind::Parbreak {
+        return None;
+    }
+    let mut source = node_full_text(node);
+    if idx > 0 && children[idx - 1].kind() == SyntaxKind::Hash {
+        source.insert(0, '#');
+    }
+    let reason = format!("{} not supported", func_name);
+    losses.push(Loss::new("unsupported", reason.clone()).with_severity(Severity::Error));
+    Some(Block::Unsupported { source, reason })
+}
+
 fn maybe_inline_func(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<Inline>> {
     let func_name = get_func_call_name(node)?;
+    if is_ref_range_function(&func_name) {
+        let args = node.children().find(|c| c.kind() == SyntaxKind::Args)?;
+        let mut labels = extract_ref_labels(args).into_iter();
+        if let (Some(from), Some(to)) = (labels.next(), labels.next()) {
+            return Some(vec![Inline::RefRange { from, to }]);
+        }
+    }
+    if let Some(kind) = package_function(&func_name) {
+        return package_inline(node, kind);
+    }
     match func_name.as_str() {
+        "raw" => {
+            let (content, lang, _) = raw_call(node)?;
+            return Some(vec![Inline::Code { lang, content }]);
+        }
         "important" => {
             let content = extract_inline_content_from_call(node, losses);
             if !content.is_empty() {
@@ -1941,8 +3465,8 @@ fn maybe_inline_func(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<In
         }
         "cite" => {
             let mut keys: Vec<String> = Vec::new();
-            let mut style: Option<String> = None;
-            let mut pre_note: Option<String> = None;
+            let mut form = CiteForm::Normal;
+            let mut supplement: Option<String> = None;
             let mut post_note: Option<String> = None;
             if let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) {
                 for child in args.children() {
@@ -1953,11 +3477,11 @@ fn maybe_inline_func(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<In
                                 let val = parse_string_literal(&value)
                                     .unwrap_or_else(|| value.text().to_string());
                                 let cleaned = val.trim_matches('"').to_string();
-                                if matches!(key.as_str(), "style" | "form" | "mode") {
-                                    style = Some(cleaned);
+                                if key == "form" {
+                                    form = cite_form(&value);
                                 } else if key == "supplement" {
                                     if !cleaned.is_empty() {
-                                        pre_note = Some(cleaned);
+                                        supplement = Some(cleaned);
                                     }
                                 } else if key == "page" {
                                     if !cleaned.is_empty() {
@@ -1984,41 +3508,21 @@ fn maybe_inline_func(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<In
             }
 
             if !keys.is_empty() {
-                let command = if let Some(style) = style.as_deref() {
-                    let lowered = style.to_lowercase();
-                    if lowered.contains("author") || lowered.contains("text") {
-                        "citet"
-                    } else if lowered.contains("year") {
-                        if pre_note.is_some() || post_note.is_some() {
-                            "citep"
-                        } else {
-                            "citeyearpar"
-                        }
-                    } else if lowered.contains("paren") {
-                        "citep"
-                    } else {
-                        "cite"
-                    }
-                } else if pre_note.is_some() || post_note.is_some() {
-                    "citep"
-                } else {
-                    "cite"
+                // Typst sets a lone supplement after the reference; next to
+                // a page or note it reads as the text before it.
+                let (prenote, postnote) = match post_note {
+                    Some(post) => (supplement, Some(post)),
+                    None => (None, supplement),
                 };
-
-                if pre_note.is_some() || post_note.is_some() || command != "cite" {
-                    let cite = match (pre_note.as_deref(), post_note.as_deref()) {
-                        (Some(pre), Some(post)) => {
-                            format!("\\{}[{}][{}]{{{}}}", command, pre, post, keys.join(","))
-                        }
-                        (Some(note), None) | (None, Some(note)) => {
-                            format!("\\{}[{}]{{{}}}", command, note, keys.join(","))
-                        }
-                        (None, None) => format!("\\{}{{{}}}", command, keys.join(",")),
-                    };
-                    return Some(vec![Inline::RawLatex(cite)]);
+                if form == CiteForm::Normal && prenote.is_none() && postnote.is_none() {
+                    return Some(vec![Inline::Cite(keys.join(","))]);
                 }
-
-                return Some(vec![Inline::Cite(keys.join(","))]);
+                return Some(vec![Inline::Citation(Citation {
+                    keys: keys.join(","),
+                    form,
+                    prenote,
+                    postnote,
+                })]);
             }
         }
         "footnote" => {
@@ -2026,7 +3530,7 @@ fn maybe_inline_func(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<In
                 for child in args.children() {
                     if child.kind() == SyntaxKind::ContentBlock || child.kind() == SyntaxKind::Markup
                     {
-                        return Some(vec![Inline::Footnote(collect_inlines(&child, losses))]);
+                        return Some(vec![Inline::Footnote(footnote_inlines(&child, losses))]);
                     }
                 }
             }
@@ -2051,21 +3555,13 @@ fn maybe_inline_func(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<In
                         let key = extract_named_key(&child).unwrap_or_default();
                         if key == "supplement" {
                             if let Some(value) = extract_named_value_node(&child) {
-                                let inlines = collect_inlines(&value, losses);
-                                if !inlines.is_empty() {
-                                    supplement = Some(inlines);
-                                }
+                                supplement = supplement_inlines(&value, losses);
                             }
                         }
                     }
                 }
                 if let Some(label) = extract_ref_label(&args) {
-                    if let Some(mut supplement) = supplement {
-                        supplement.push(Inline::RawLatex("\\nobreakspace{}".to_string()));
-                        supplement.push(Inline::Ref(label));
-                        return Some(supplement);
-                    }
-                    return Some(vec![Inline::Ref(label)]);
+                    return Some(vec![reference(label, supplement)]);
                 }
             }
         }
@@ -2076,22 +3572,37 @@ fn maybe_inline_func(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<In
         }
         "link" => {
             if let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) {
-                let mut url: Option<String> = None;
+                let mut url: Option<(String, LinkTarget)> = None;
                 let mut text: Option<Vec<Inline>> = None;
                 for child in args.children() {
                     if child.kind() == SyntaxKind::Str {
-                        url = Some(child.text().trim_matches('"').to_string());
+                        let dest = child.text().trim_matches('"');
+                        url = Some(match dest.strip_prefix("mailto:") {
+                            Some(address) => (address.to_string(), LinkTarget::Email),
+                            None => (dest.to_string(), LinkTarget::Url),
+                        });
+                    } else if child.kind() == SyntaxKind::Label {
+                        let label = child.text().trim_start_matches('<').trim_end_matches('>');
+                        url = Some((label.to_string(), LinkTarget::Label));
                     } else if child.kind() == SyntaxKind::ContentBlock
                         || child.kind() == SyntaxKind::Markup
                     {
-                        text = Some(collect_inlines(&child, losses));
+                        text = Some(collect_inlines(child, losses));
                     }
                 }
-                if let Some(url) = url {
-                    return Some(vec![Inline::Link {
-                        text: text.unwrap_or_else(|| vec![Inline::Text(url.clone())]),
-                        url,
-                    }]);
+                match (url, text) {
+                    // A link to a label without a body reads as a reference.
+                    (Some((label, LinkTarget::Label)), None) => {
+                        return Some(vec![Inline::Ref(label)]);
+                    }
+                    (Some((url, target)), text) => {
+                        return Some(vec![Inline::Link {
+                            text: text.unwrap_or_else(|| vec![Inline::Text(url.clone())]),
+                            url,
+                            target,
+                        }]);
+                    }
+                    (None, _) => {}
                 }
             }
         }
@@ -2195,6 +3706,21 @@ fn maybe_inline_func(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<In
             let content = extract_inline_content_from_call(node, losses);
             return Some(vec![Inline::Subscript(content)]);
         }
+        "underline" | "overline" | "strike" | "smallcaps" | "upper" | "lower" => {
+            let content = extract_inline_content_from_call(node, losses);
+            if content.is_empty() {
+                return None;
+            }
+            let inline = match func_name.as_str() {
+                "underline" => Inline::Underline(content),
+                "overline" => Inline::Overline(content),
+                "strike" => Inline::Strike(content),
+                "smallcaps" => Inline::SmallCaps(content),
+                "upper" => Inline::Uppercase(content),
+                _ => Inline::Lowercase(content),
+            };
+            return Some(vec![inline]);
+        }
         _ if func_name.starts_with("sym.") => {
             let parts: Vec<String> = func_name.split('.').map(|s| s.to_string()).collect();
             if let Some(inline) = sym_inline_from_parts(&parts) {
@@ -2206,6 +3732,117 @@ fn maybe_inline_func(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<In
     None
 }
 
+/// The text of a footnote. LaTeX sets a footnote as a single paragraph, so
+/// block content in it is flattened to text and a footnote inside it is set
+/// in parentheses, each with a loss.
+fn footnote_inlines(content: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
+    let mut blocks = Vec::new();
+    footnote_blocks(content, &mut blocks);
+    for what in blocks {
+        losses.push(Loss::new(
+            "footnote",
+            format!("{} inside a footnote was set as text", what),
+        ));
+    }
+    let mut out = Vec::new();
+    for inline in collect_inlines(content, losses) {
+        match inline {
+            Inline::Footnote(inner) => {
+                losses.push(Loss::new(
+                    "footnote",
+                    "footnote inside a footnote was set in parentheses",
+                ));
+                let spaced = matches!(out.last(), Some(Inline::Text(text)) if text.ends_with(' '));
+                push_text(&mut out, if spaced { "(" } else { " (" });
+                extend_inlines(&mut out, inner);
+                push_text(&mut out, ")");
+            }
+            other => extend_inlines(&mut out, vec![other]),
+        }
+    }
+    out
+}
+
+/// Names of the block-level elements under `node`, in source order.
+fn footnote_blocks(node: &SyntaxNode, out: &mut Vec<&'static str>) {
+    for child in node.children() {
+        let what = match child.kind() {
+            SyntaxKind::Heading => Some("heading"),
+            SyntaxKind::ListItem | SyntaxKind::EnumItem | SyntaxKind::TermItem => {
+                Some("list item")
+            }
+            SyntaxKind::Raw if node_full_text(child).starts_with("```") => Some("code block"),
+            SyntaxKind::FuncCall => match get_func_call_name(child).as_deref() {
+                Some("table") => Some("table"),
+                Some("grid") => Some("grid"),
+                Some("figure") => Some("figure"),
+                Some("image") => Some("image"),
+                _ => None,
+            },
+            SyntaxKind::Equation => continue,
+            _ => None,
+        };
+        match what {
+            Some(what) => out.push(what),
+            None => footnote_blocks(child, out),
+        }
+    }
+}
+
+/// The display equation of a call to mitex's `mitex`, its LaTeX kept as
+/// written.
+fn maybe_latex_math_block(node: &SyntaxNode) -> Option<Block> {
+    let func_name = get_func_call_name(node)?;
+    if package_function(&func_name) != Some(PackageFunction::LatexMath) {
+        return None;
+    }
+    let Some(Inline::LatexMath(content)) = package_inline(node, PackageFunction::LatexMath)?.pop()
+    else {
+        return None;
+    };
+    Some(Block::MathBlock(MathBlock {
+        content,
+        label: None,
+        latex: true,
+    }))
+}
+
+/// Lower a call to an imported unit, chemistry or LaTeX math function. Its
+/// positional arguments are the number and unit, the formula, or the LaTeX
+/// source as a string or raw text.
+fn package_inline(node: &SyntaxNode, kind: PackageFunction) -> Option<Vec<Inline>> {
+    let args = node.children().find(|c| c.kind() == SyntaxKind::Args)?;
+    let mut values = args.children().filter_map(|child| match child.kind() {
+        SyntaxKind::Str => Some(unescape_typst_string(child.text().trim_matches('"'))),
+        SyntaxKind::Raw => Some(raw_literal(child).1.trim().to_string()),
+        SyntaxKind::Int | SyntaxKind::Float => Some(child.text().to_string()),
+        SyntaxKind::ContentBlock => {
+            let text = node_full_text(child);
+            Some(text.trim_start_matches('[').trim_end_matches(']').trim().to_string())
+        }
+        _ => None,
+    });
+    let inline = match kind {
+        PackageFunction::Number => Inline::Quantity {
+            value: Some(values.next()?),
+            unit: None,
+        },
+        PackageFunction::Unit => Inline::Quantity {
+            value: None,
+            unit: Some(values.next()?),
+        },
+        PackageFunction::Quantity => Inline::Quantity {
+            value: Some(values.next()?),
+            unit: Some(values.next()?),
+        },
+        PackageFunction::Chemical => Inline::Chemical(values.next()?),
+        PackageFunction::LatexMath | PackageFunction::InlineLatexMath => {
+            Inline::LatexMath(values.next()?)
+        }
+    };
+    Some(vec![inline])
+}
+
 fn extract_inline_content_from_call(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
     for child in node.children() {
         if matches!(child.kind(), SyntaxKind::ContentBlock | SyntaxKind::Markup) {
@@ -2231,14 +3868,6 @@ fn extract_inline_content_from_call(node: &SyntaxNode, losses: &mut Vec<Loss>) -
     Vec::new()
 }
 
-fn map_smart_quote(raw: &str) -> String {
-    match raw {
-        "\u{201C}" | "\u{201D}" => "\"".to_string(),
-        "\u{2018}" | "\u{2019}" => "'".to_string(),
-        _ => raw.to_string(),
-    }
-}
-
 fn map_shorthand_inline(raw: &str) -> Option<Inline> {
     match raw {
         "~" => Some(Inline::RawLatex("\\nobreakspace{}".to_string())),
@@ -2278,20 +3907,12 @@ fn parse_color_value(node: &SyntaxNode) -> Option<String> {
     match node.kind() {
         SyntaxKind::Str => return Some(node.text().trim_matches('"').to_string()),
         SyntaxKind::Ident | SyntaxKind::Text => return Some(node.text().to_string()),
-        SyntaxKind::FuncCall => {
-            let name = get_func_call_name(node)?;
-            if name == "rgb" {
-                for child in node.children() {
-                    if child.kind() == SyntaxKind::Args {
-                        for arg in child.children() {
-                            if arg.kind() == SyntaxKind::Str {
-                                return Some(arg.text().trim_matches('"').to_string());
-                            }
-                        }
-                    }
-                }
-                // Fallback to raw text if rgb(...) uses numeric values.
-                return Some(node.text().to_string());
+        // `rgb(..)`, `red.lighten(..)` and the like, kept as written for
+        // the backend to read.
+        SyntaxKind::FuncCall | SyntaxKind::FieldAccess => {
+            let text = node_full_text(node);
+            if Color::parse_typst(&text).is_some() {
+                return Some(text);
             }
         }
         _ => {}
@@ -2352,6 +3973,29 @@ fn sym_inline_from_parts(parts: &[String]) -> Option<Inline> {
     }
 }
 
+/// A reference to `label`, after its own supplement or the one of the
+/// document's `set ref(supplement: ..)`.
+fn reference(label: String, supplement: Option<Vec<Inline>>) -> Inline {
+    match supplement.or_else(|| REF_SUPPLEMENT.with(|cell| cell.borrow().clone())) {
+        Some(supplement) => Inline::SupplementedRef { label, supplement },
+        None => Inline::Ref(label),
+    }
+}
+
+/// The inlines of a `supplement:` argument; empty for `none`, which drops
+/// the supplement. `None` for a function, which is not run.
+fn supplement_inlines(value: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<Inline>> {
+    match value.kind() {
+        SyntaxKind::None => Some(Vec::new()),
+        SyntaxKind::Closure => {
+            let reason = "a supplement computed by a function was dropped";
+            losses.push(Loss::new("unsupported", reason).with_severity(Severity::Error));
+            None
+        }
+        _ => Some(collect_inlines(value, losses)).filter(|inlines| !inlines.is_empty()),
+    }
+}
+
 fn is_cross_ref_label(label: &str) -> bool {
     let lower = label.to_lowercase();
     for prefix in [
@@ -2389,32 +4033,52 @@ fn first_arg_text(node: &SyntaxNode) -> Option<String> {
 }
 
 fn extract_ref_label(args: &SyntaxNode) -> Option<String> {
+    extract_ref_labels(args).into_iter().next()
+}
+
+fn extract_ref_labels(args: &SyntaxNode) -> Vec<String> {
+    let mut labels = Vec::new();
     for child in args.children() {
         if child.kind() == SyntaxKind::Named {
             continue;
         }
         match child.kind() {
             SyntaxKind::Label | SyntaxKind::Ref => {
-                let text = node_full_text(&child);
+                let text = node_full_text(child);
                 let cleaned = text
                     .trim()
                     .trim_start_matches('@')
                     .trim_start_matches('<')
                     .trim_end_matches('>');
                 if !cleaned.is_empty() {
-                    return Some(cleaned.to_string());
+                    labels.push(cleaned.to_string());
                 }
             }
             SyntaxKind::Str | SyntaxKind::Text | SyntaxKind::Ident => {
                 let text = child.text().trim_matches('"').to_string();
                 if !text.is_empty() {
-                    return Some(text);
+                    labels.push(text);
                 }
             }
             _ => {}
         }
     }
-    None
+    labels
+}
+
+/// The `form` of a `cite` call: a form name, or `none` to only list the
+/// entry in the bibliography.
+fn cite_form(value: &SyntaxNode) -> CiteForm {
+    if value.kind() == SyntaxKind::None {
+        return CiteForm::Hidden;
+    }
+    match parse_string_literal(value).as_deref() {
+        Some("prose") => CiteForm::Prose,
+        Some("full") => CiteForm::Full,
+        Some("author") => CiteForm::Author,
+        Some("year") => CiteForm::Year,
+        _ => CiteForm::Normal,
+    }
 }
 
 fn format_page_note(raw: &str, plural: bool) -> String {
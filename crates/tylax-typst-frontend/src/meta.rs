@@ -0,0 +1,639 @@
+//! Document-wide settings from top-level set rules:
+//!
+//! ```typst
+//! #set page(paper: "a4", margin: (x: 2cm, y: 3cm))
+//! #set text(size: 11pt, lang: "de")
+//! #set heading(numbering: "1.a")
+//! #set page(header: [Draft #h(1fr) Report], numbering: "1 of 1")
+//! #set ref(supplement: [Fig.])
+//! #show: doc => columns(2, doc)
+//! ```
+//!
+//! A later rule overrides an earlier one, as it would for the rest of the
+//! document in Typst.
+//!
+//! The front matter comes from the arguments of a template applied to the
+//! whole document, as the IEEE, ACM and arkheion templates are:
+//!
+//! ```typst
+//! #show: ieee.with(
+//!   title: [A Paper],
+//!   authors: ((name: "Ada", organization: [Typst GmbH], email: "ada@typst.app"),),
+//!   abstract: [We study it.],
+//! )
+//! ```
+
+use tylax_ir::{
+    Alignment, Author, Block, DocumentMeta, Inline, Loss, PageDecoration, PageMargin, Severity,
+};
+use typst_syntax::{SyntaxKind, SyntaxNode};
+
+use super::preprocess::is_supported_page_decoration;
+use super::{
+    collect_blocks, collect_inlines, extend_inlines, extract_named_key, extract_named_value_node,
+    flatten_markup_children, get_func_call_name, node_full_text, parse_alignment,
+    parse_bool_literal, parse_string_literal, set_rule_name, supplement_inlines,
+    unescape_typst_string,
+};
+
+pub(crate) fn document_meta(root: &SyntaxNode, losses: &mut Vec<Loss>) -> DocumentMeta {
+    let mut children = Vec::new();
+    flatten_markup_children(root, &mut children);
+    let mut meta = DocumentMeta::default();
+    for rule in children
+        .iter()
+        .filter(|node| node.kind() == SyntaxKind::ShowRule)
+    {
+        if let Some(args) = template_args(rule) {
+            front_matter(&args, &mut meta, losses);
+        }
+        if let Some(columns) = wrapper_columns(rule) {
+            meta.columns = Some(columns);
+        }
+    }
+    for rule in children
+        .iter()
+        .filter(|node| node.kind() == SyntaxKind::SetRule)
+    {
+        let Some(name) = set_rule_name(rule) else {
+            continue;
+        };
+        let Some(args) = rule.children().find(|c| c.kind() == SyntaxKind::Args) else {
+            continue;
+        };
+        for arg in args.children().filter(|c| c.kind() == SyntaxKind::Named) {
+            let (Some(key), Some(value)) = (extract_named_key(arg), extract_named_value_node(arg))
+            else {
+                continue;
+            };
+            match (name.as_str(), key.as_str()) {
+                ("page", "paper") => meta.paper = parse_string_literal(&value),
+                ("page", "margin") => {
+                    if let Some(margin) = page_margin(&value) {
+                        meta.margin = margin;
+                    }
+                }
+                ("text", "size") => meta.font_size = length(&value),
+                ("text", "lang") => meta.lang = parse_string_literal(&value),
+                ("par", "justify") => meta.justify = parse_bool_literal(&value),
+                ("heading", "numbering") => meta.heading_numbering = numbering(&value),
+                ("page", "numbering") => meta.page_numbering = numbering(&value),
+                ("page", "header") => meta.header = page_decoration(&value, &key, losses),
+                ("page", "footer") => meta.footer = page_decoration(&value, &key, losses),
+                ("page", "columns") => meta.columns = column_count(&value),
+                _ => {}
+            }
+        }
+    }
+    // `counter(page).display()` shows the page in the page numbering.
+    let pattern = meta
+        .page_numbering
+        .clone()
+        .filter(|pattern| !pattern.is_empty())
+        .unwrap_or_else(|| "1".to_string());
+    for decoration in meta.header.iter_mut().chain(meta.footer.iter_mut()) {
+        let slots = [
+            &mut decoration.left,
+            &mut decoration.center,
+            &mut decoration.right,
+        ];
+        for inline in slots.into_iter().flatten() {
+            if let Inline::PageNumber(display) = inline {
+                if display.is_empty() {
+                    display.clone_from(&pattern);
+                }
+            }
+        }
+    }
+    meta
+}
+
+/// The supplement of `set ref(supplement: ..)`, which references without
+/// one of their own are set after.
+pub(crate) fn ref_supplement(root: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<Inline>> {
+    let mut children = Vec::new();
+    flatten_markup_children(root, &mut children);
+    let mut supplement = None;
+    for rule in children
+        .iter()
+        .filter(|node| node.kind() == SyntaxKind::SetRule)
+        .filter(|rule| set_rule_name(rule).as_deref() == Some("ref"))
+    {
+        let Some(args) = rule.children().find(|c| c.kind() == SyntaxKind::Args) else {
+            continue;
+        };
+        for arg in args.children().filter(|c| c.kind() == SyntaxKind::Named) {
+            if extract_named_key(arg).as_deref() != Some("supplement") {
+                continue;
+            }
+            if let Some(value) = extract_named_value_node(arg) {
+                supplement = supplement_inlines(&value, losses);
+            }
+        }
+    }
+    supplement
+}
+
+/// A numbering pattern, empty for `none`.
+fn numbering(value: &SyntaxNode) -> Option<String> {
+    if value.kind() == SyntaxKind::None {
+        Some(String::new())
+    } else {
+        parse_string_literal(value)
+    }
+}
+
+fn column_count(value: &SyntaxNode) -> Option<usize> {
+    if value.kind() != SyntaxKind::Int {
+        return None;
+    }
+    value.text().parse().ok().filter(|&count| count >= 1)
+}
+
+/// The column count of a show rule setting the whole document in columns:
+/// `#show: doc => columns(2, doc)` or `#show: columns.with(2)`.
+fn wrapper_columns(rule: &SyntaxNode) -> Option<usize> {
+    let transform = rule
+        .children()
+        .skip_while(|c| c.kind() != SyntaxKind::Colon)
+        .find(|c| matches!(c.kind(), SyntaxKind::FuncCall | SyntaxKind::Closure))?;
+    let (call, param) = match transform.kind() {
+        SyntaxKind::Closure => {
+            let params = transform
+                .children()
+                .find(|c| c.kind() == SyntaxKind::Params)?;
+            let param = params.children().find(|c| c.kind() == SyntaxKind::Ident)?;
+            let call = transform
+                .children()
+                .rfind(|c| c.kind() == SyntaxKind::FuncCall)?;
+            (call, Some(param.text().to_string()))
+        }
+        _ => (transform, None),
+    };
+    let expected = if param.is_some() {
+        "columns"
+    } else {
+        "columns.with"
+    };
+    if get_func_call_name(call)? != expected {
+        return None;
+    }
+    let args = call.children().find(|c| c.kind() == SyntaxKind::Args)?;
+    let mut positional = args.children().filter(|c| {
+        !c.kind().is_trivia()
+            && !matches!(
+                c.kind(),
+                SyntaxKind::LeftParen
+                    | SyntaxKind::RightParen
+                    | SyntaxKind::Comma
+                    | SyntaxKind::Named
+            )
+    });
+    let count = column_count(positional.next()?)?;
+    // The closure has to hand the whole document to `columns`.
+    match (param, positional.next()) {
+        (None, None) => Some(count),
+        (Some(param), Some(arg)) if arg.kind() == SyntaxKind::Ident && arg.text() == &param => {
+            Some(count)
+        }
+        _ => None,
+    }
+}
+
+fn length(value: &SyntaxNode) -> Option<String> {
+    (value.kind() == SyntaxKind::Numeric).then(|| value.text().to_string())
+}
+
+/// A margin for all sides, or a dictionary of sides. A side takes its own
+/// key first, then `x` or `y`, then `rest`.
+fn page_margin(value: &SyntaxNode) -> Option<PageMargin> {
+    if let Some(all) = length(value) {
+        return Some(PageMargin {
+            top: Some(all.clone()),
+            bottom: Some(all.clone()),
+            left: Some(all.clone()),
+            right: Some(all),
+        });
+    }
+    if value.kind() != SyntaxKind::Dict {
+        return None;
+    }
+    let entries: Vec<(String, String)> = value
+        .children()
+        .filter(|c| c.kind() == SyntaxKind::Named)
+        .filter_map(|entry| {
+            let length = length(&extract_named_value_node(entry)?)?;
+            Some((extract_named_key(entry)?, length))
+        })
+        .collect();
+    let side = |keys: &[&str]| {
+        keys.iter().find_map(|key| {
+            entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, length)| length.clone())
+        })
+    };
+    Some(PageMargin {
+        top: side(&["top", "y", "rest"]),
+        bottom: side(&["bottom", "y", "rest"]),
+        left: side(&["left", "x", "rest"]),
+        right: side(&["right", "x", "rest"]),
+    })
+}
+
+/// A header or footer of content split into left, center and right by
+/// `h(1fr)`, or placed with `align(..)`, or one that alternates with the
+/// page parity. `none` is no header.
+fn page_decoration(
+    value: &SyntaxNode,
+    key: &str,
+    losses: &mut Vec<Loss>,
+) -> Option<PageDecoration> {
+    let value = single_expression(&contextual_body(value));
+    let markup = match value.kind() {
+        SyntaxKind::None => return None,
+        SyntaxKind::Conditional => {
+            if let Some(decoration) = alternating_decoration(&value, key, losses) {
+                return Some(decoration);
+            }
+            None
+        }
+        SyntaxKind::Str => {
+            return Some(PageDecoration {
+                left: content_inlines(&value, losses),
+                ..PageDecoration::default()
+            })
+        }
+        SyntaxKind::ContentBlock => value.children().find(|c| c.kind() == SyntaxKind::Markup),
+        _ => None,
+    };
+    let Some(markup) = markup else {
+        // The preprocessor reports code it does not understand itself.
+        if is_supported_page_decoration(&value) {
+            losses.push(
+                Loss::new(
+                    "page-header",
+                    format!(
+                        "page {} `{}` was not converted",
+                        key,
+                        node_full_text(&value)
+                    ),
+                )
+                .with_severity(Severity::Error),
+            );
+        }
+        return None;
+    };
+    let mut segments = vec![Vec::new()];
+    let mut aligned = PageDecoration::default();
+    for child in markup.children() {
+        if child.kind() == SyntaxKind::FuncCall {
+            match get_func_call_name(child).as_deref() {
+                Some("h") if node_full_text(child).contains("fr") => {
+                    segments.push(Vec::new());
+                    continue;
+                }
+                Some("align") => {
+                    if let Some((alignment, body)) = aligned_body(child) {
+                        let slot = match alignment {
+                            Alignment::Left => &mut aligned.left,
+                            Alignment::Center => &mut aligned.center,
+                            Alignment::Right => &mut aligned.right,
+                            _ => continue,
+                        };
+                        *slot = trimmed(page_inlines(&body, losses));
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(segment) = segments.last_mut() {
+            extend_inlines(segment, page_inlines(child, losses));
+        }
+    }
+    let mut segments = segments.into_iter().map(trimmed);
+    let mut decoration = match segments.len() {
+        1 => PageDecoration {
+            left: segments.next().unwrap_or_default(),
+            ..PageDecoration::default()
+        },
+        2 => PageDecoration {
+            left: segments.next().unwrap_or_default(),
+            right: segments.next().unwrap_or_default(),
+            ..PageDecoration::default()
+        },
+        3 => PageDecoration {
+            left: segments.next().unwrap_or_default(),
+            center: segments.next().unwrap_or_default(),
+            right: segments.next().unwrap_or_default(),
+            even: None,
+        },
+        count => {
+            losses.push(
+                Loss::new(
+                    "page-header",
+                    format!("page {} of {} parts was not converted", key, count),
+                )
+                .with_severity(Severity::Error),
+            );
+            return None;
+        }
+    };
+    for (slot, aligned) in [
+        (&mut decoration.left, aligned.left),
+        (&mut decoration.center, aligned.center),
+        (&mut decoration.right, aligned.right),
+    ] {
+        if !aligned.is_empty() {
+            *slot = aligned;
+        }
+    }
+    Some(decoration)
+}
+
+/// A header or footer switching on the page parity:
+/// `if calc.odd(here().page()) [..] else [..]`.
+fn alternating_decoration(
+    conditional: &SyntaxNode,
+    key: &str,
+    losses: &mut Vec<Loss>,
+) -> Option<PageDecoration> {
+    let condition = conditional
+        .children()
+        .find(|c| c.kind() == SyntaxKind::FuncCall)?;
+    if !node_full_text(condition).contains("page") {
+        return None;
+    }
+    let odd_first = match get_func_call_name(condition)?.as_str() {
+        "calc.odd" => true,
+        "calc.even" => false,
+        _ => return None,
+    };
+    let branches: Vec<&SyntaxNode> = conditional
+        .children()
+        .filter(|c| matches!(c.kind(), SyntaxKind::ContentBlock | SyntaxKind::CodeBlock))
+        .collect();
+    let [first, second] = branches.as_slice() else {
+        return None;
+    };
+    let first = page_decoration(first, key, losses).unwrap_or_default();
+    let second = page_decoration(second, key, losses).unwrap_or_default();
+    let (odd, even) = if odd_first {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    let even = (even != odd).then(|| Box::new(even));
+    Some(PageDecoration { even, ..odd })
+}
+
+/// The expression of a code block holding only one, or the node itself.
+fn single_expression(node: &SyntaxNode) -> SyntaxNode {
+    if node.kind() != SyntaxKind::CodeBlock {
+        return node.clone();
+    }
+    let Some(code) = node.children().find(|c| c.kind() == SyntaxKind::Code) else {
+        return node.clone();
+    };
+    let mut exprs = code
+        .children()
+        .filter(|c| !c.kind().is_trivia() && c.kind() != SyntaxKind::Semicolon);
+    match (exprs.next(), exprs.next()) {
+        (Some(expr), None) => expr.clone(),
+        _ => node.clone(),
+    }
+}
+
+/// The horizontal alignment and body of `align(right)[..]`.
+fn aligned_body(call: &SyntaxNode) -> Option<(Alignment, SyntaxNode)> {
+    let args = call.children().find(|c| c.kind() == SyntaxKind::Args)?;
+    let mut alignment = None;
+    let mut body = None;
+    for arg in args.children() {
+        match arg.kind() {
+            SyntaxKind::ContentBlock => body = Some(arg.clone()),
+            SyntaxKind::Ident | SyntaxKind::Binary => {
+                alignment = parse_alignment(&node_full_text(arg)).0;
+            }
+            _ => {}
+        }
+    }
+    Some((alignment?, body?))
+}
+
+/// Inlines of header or footer content, with the page counter as the page
+/// number. A number without a pattern of its own is left empty, for the
+/// page numbering to fill in.
+fn page_inlines(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
+    let text = node_full_text(node);
+    match node.kind() {
+        SyntaxKind::Contextual => page_inlines(&contextual_body(node), losses),
+        SyntaxKind::FuncCall if text.starts_with("counter(page)") && text.contains(".final") => {
+            losses.push(
+                Loss::new("page-header", format!("page count `{}` was dropped", text))
+                    .with_severity(Severity::Error),
+            );
+            Vec::new()
+        }
+        SyntaxKind::FuncCall if text.starts_with("counter(page)") => {
+            let pattern = node
+                .children()
+                .find(|c| c.kind() == SyntaxKind::Args)
+                .and_then(|args| args.children().find(|c| c.kind() == SyntaxKind::Str))
+                .and_then(parse_string_literal)
+                .unwrap_or_default();
+            vec![Inline::PageNumber(pattern)]
+        }
+        SyntaxKind::FuncCall if text.starts_with("here().page()") => {
+            vec![Inline::PageNumber("1".to_string())]
+        }
+        SyntaxKind::FuncCall if get_func_call_name(node).as_deref() == Some("hydra") => {
+            let level = node
+                .children()
+                .find(|c| c.kind() == SyntaxKind::Args)
+                .and_then(|args| args.children().find(|c| c.kind() == SyntaxKind::Int))
+                .and_then(|level| level.text().parse().ok())
+                .unwrap_or(1);
+            vec![Inline::RunningHead(level)]
+        }
+        SyntaxKind::ContentBlock | SyntaxKind::Markup => {
+            let mut out = Vec::new();
+            for child in node.children() {
+                extend_inlines(&mut out, page_inlines(child, losses));
+            }
+            out
+        }
+        _ => collect_inlines(node, losses),
+    }
+}
+
+/// What `context` applies to, or the node itself.
+fn contextual_body(node: &SyntaxNode) -> SyntaxNode {
+    if node.kind() != SyntaxKind::Contextual {
+        return node.clone();
+    }
+    node.children()
+        .find(|c| !c.kind().is_trivia() && c.kind() != SyntaxKind::Context)
+        .cloned()
+        .unwrap_or_else(|| node.clone())
+}
+
+/// Inlines without the whitespace at their ends.
+fn trimmed(mut inlines: Vec<Inline>) -> Vec<Inline> {
+    if let Some(Inline::Text(text)) = inlines.first_mut() {
+        *text = text.trim_start().to_string();
+    }
+    if let Some(Inline::Text(text)) = inlines.last_mut() {
+        *text = text.trim_end().to_string();
+    }
+    inlines.retain(|inline| !matches!(inline, Inline::Text(text) if text.is_empty()));
+    inlines
+}
+
+/// The arguments of the template in `#show: template.with(..)` or
+/// `#show: doc => template(.., doc)`.
+fn template_args(rule: &SyntaxNode) -> Option<SyntaxNode> {
+    let transform = rule
+        .children()
+        .skip_while(|c| c.kind() != SyntaxKind::Colon)
+        .find(|c| matches!(c.kind(), SyntaxKind::FuncCall | SyntaxKind::Closure))?;
+    let call = match transform.kind() {
+        SyntaxKind::Closure => transform
+            .children()
+            .rfind(|c| c.kind() == SyntaxKind::FuncCall)?
+            .clone(),
+        _ => transform.clone(),
+    };
+    call.children()
+        .find(|c| c.kind() == SyntaxKind::Args)
+        .cloned()
+}
+
+fn front_matter(args: &SyntaxNode, meta: &mut DocumentMeta, losses: &mut Vec<Loss>) {
+    for arg in args.children().filter(|c| c.kind() == SyntaxKind::Named) {
+        let (Some(key), Some(value)) = (extract_named_key(arg), extract_named_value_node(arg))
+        else {
+            continue;
+        };
+        match key.as_str() {
+            "title" => meta.title = Some(content_inlines(&value, losses)),
+            "authors" | "author" => meta.authors = authors(&value, losses),
+            "date" => meta.date = Some(plain_text(&value, losses)).filter(|date| !date.is_empty()),
+            "abstract" => meta.abstract_content = content_blocks(&value, losses),
+            _ => {}
+        }
+    }
+}
+
+/// One author, or an array of authors, each a name or a dictionary with a
+/// `name` and the usual keys of the common templates for the rest.
+fn authors(value: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Author> {
+    let entries: Vec<SyntaxNode> = match value.kind() {
+        SyntaxKind::Array => value
+            .children()
+            .filter(|c| is_element(c))
+            .cloned()
+            .collect(),
+        _ => vec![value.clone()],
+    };
+    entries
+        .iter()
+        .filter_map(|entry| author(entry, losses))
+        .collect()
+}
+
+fn author(entry: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Author> {
+    if entry.kind() != SyntaxKind::Dict {
+        let name = plain_text(entry, losses);
+        return (!name.is_empty()).then(|| Author {
+            name,
+            ..Author::default()
+        });
+    }
+    let mut author = Author::default();
+    let mut affiliation = Vec::new();
+    for field in entry.children().filter(|c| c.kind() == SyntaxKind::Named) {
+        let (Some(key), Some(value)) = (extract_named_key(field), extract_named_value_node(field))
+        else {
+            continue;
+        };
+        match key.as_str() {
+            "name" => author.name = plain_text(&value, losses),
+            "email" => author.email = Some(plain_text(&value, losses)),
+            "department" | "organization" | "institution" | "affiliation" | "affiliations"
+            | "location" => affiliation_parts(&value, &mut affiliation, losses),
+            _ => {}
+        }
+    }
+    if !affiliation.is_empty() {
+        author.affiliation = Some(affiliation.join(", "));
+    }
+    (!author.name.is_empty()).then_some(author)
+}
+
+/// Text of an affiliation, which may itself be a dictionary, as ACM's
+/// `(institution: .., city: ..)`, or an array of them.
+fn affiliation_parts(value: &SyntaxNode, parts: &mut Vec<String>, losses: &mut Vec<Loss>) {
+    match value.kind() {
+        SyntaxKind::Dict | SyntaxKind::Array => {
+            for child in value.children() {
+                let child = match child.kind() {
+                    SyntaxKind::Named => extract_named_value_node(child),
+                    _ if is_element(child) => Some(child.clone()),
+                    _ => None,
+                };
+                if let Some(child) = child {
+                    affiliation_parts(&child, parts, losses);
+                }
+            }
+        }
+        _ => {
+            let text = plain_text(value, losses);
+            if !text.is_empty() {
+                parts.push(text);
+            }
+        }
+    }
+}
+
+fn content_inlines(value: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Inline> {
+    match value.kind() {
+        SyntaxKind::Str => vec![Inline::Text(unescape_typst_string(
+            value.text().trim_matches('"'),
+        ))],
+        _ => collect_inlines(value, losses),
+    }
+}
+
+fn content_blocks(value: &SyntaxNode, losses: &mut Vec<Loss>) -> Vec<Block> {
+    match value.kind() {
+        SyntaxKind::ContentBlock => value
+            .children()
+            .find(|c| c.kind() == SyntaxKind::Markup)
+            .map(|markup| collect_blocks(markup, losses))
+            .unwrap_or_default(),
+        SyntaxKind::None => Vec::new(),
+        _ => vec![Block::Paragraph(content_inlines(value, losses))],
+    }
+}
+
+fn plain_text(value: &SyntaxNode, losses: &mut Vec<Loss>) -> String {
+    let mut text = String::new();
+    for inline in content_inlines(value, losses) {
+        if let Inline::Text(part) = inline {
+            text.push_str(&part);
+        }
+    }
+    text.trim().to_string()
+}
+
+/// Whether a child of an array or dictionary is one of its values.
+fn is_element(node: &SyntaxNode) -> bool {
+    !node.kind().is_trivia()
+        && !matches!(
+            node.kind(),
+            SyntaxKind::LeftParen | SyntaxKind::RightParen | SyntaxKind::Comma | SyntaxKind::Named
+        )
+}
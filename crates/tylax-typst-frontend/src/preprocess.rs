@@ -9,7 +9,9 @@
 use std::collections::HashMap;
 
 use typst_syntax::{parse, SyntaxKind, SyntaxNode};
-use tylax_ir::Loss;
+use tylax_ir::{Loss, Severity, Span};
+
+use super::node_full_text;
 
 #[derive(Debug, Clone, PartialEq)]
 enum Value {
@@ -19,6 +21,9 @@ enum Value {
     None,
     Counter(String),
     Array(Vec<Value>),
+    /// Markup standing for a content value the evaluator cannot compute
+    /// itself, e.g. `#figure(..)` bound with `#let fig = figure(..)`.
+    Content(String),
 }
 
 impl Value {
@@ -30,6 +35,7 @@ impl Value {
             Value::None => String::new(),
             Value::Counter(name) => counters.get(name).copied().unwrap_or(0).to_string(),
             Value::Array(_) => String::new(),
+            Value::Content(markup) => markup.clone(),
         }
     }
 }
@@ -74,33 +80,148 @@ impl DefDb {
 pub struct PreprocessResult {
     pub source: String,
     pub losses: Vec<Loss>,
+    pub source_map: SourceMap,
+    /// Functions imported from known packages, by the name the document
+    /// calls them with.
+    pub package_functions: HashMap<String, PackageFunction>,
+}
+
+/// What a function imported from a known Typst package stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageFunction {
+    Number,
+    Unit,
+    Quantity,
+    Chemical,
+    /// LaTeX math set as a display equation.
+    LatexMath,
+    /// LaTeX math set inline.
+    InlineLatexMath,
+}
+
+/// Package functions with a LaTeX counterpart, as `(package, function, kind)`.
+const PACKAGE_FUNCTIONS: &[(&str, &str, PackageFunction)] = &[
+    ("unify", "num", PackageFunction::Number),
+    ("unify", "unit", PackageFunction::Unit),
+    ("unify", "qty", PackageFunction::Quantity),
+    ("metro", "num", PackageFunction::Number),
+    ("metro", "unit", PackageFunction::Unit),
+    ("metro", "qty", PackageFunction::Quantity),
+    ("whalogen", "ce", PackageFunction::Chemical),
+    ("typsium", "ce", PackageFunction::Chemical),
+    ("mitex", "mitex", PackageFunction::LatexMath),
+    ("mitex", "mi", PackageFunction::InlineLatexMath),
+];
+
+/// Maps byte offsets in the expanded source back to the original input.
+///
+/// Anchors are recorded for every kept input line and every top-level markup
+/// node; offsets inside expanded text resolve to the node they came from.
+#[derive(Debug, Default, Clone)]
+pub struct SourceMap {
+    anchors: Vec<(usize, usize)>,
+    input_len: Option<usize>,
+}
+
+impl SourceMap {
+    fn push(&mut self, output: usize, input: usize) {
+        self.anchors.push((output, input));
+    }
+
+    pub fn to_input(&self, offset: usize) -> usize {
+        let idx = self.anchors.partition_point(|(output, _)| *output <= offset);
+        if idx == 0 {
+            return offset;
+        }
+        let (output, input) = self.anchors[idx - 1];
+        let mut mapped = input + (offset - output);
+        if let Some((_, next)) = self.anchors.get(idx) {
+            mapped = mapped.min((*next).max(input));
+        }
+        match self.input_len {
+            Some(len) => mapped.min(len),
+            None => mapped,
+        }
+    }
+
+    pub fn span_to_input(&self, span: Span) -> Span {
+        Span::new(self.to_input(span.start), self.to_input(span.end))
+    }
 }
 
-pub fn preprocess_typst(input: &str) -> PreprocessResult {
+/// Expand `input`. Without `expand` only imports are stripped (and read for
+/// package functions); `#let`, `#if` and `#for` are left for the frontend.
+pub fn preprocess_typst(input: &str, expand: bool) -> PreprocessResult {
     if !input.contains('#') {
         return PreprocessResult {
             source: input.to_string(),
             losses: Vec::new(),
+            source_map: SourceMap::default(),
+            package_functions: HashMap::new(),
         };
     }
 
-    let filtered = strip_imports(input);
+    let (filtered, line_map, imports) = strip_imports(input);
+    let mut package_functions = HashMap::new();
+    for import in &imports {
+        collect_package_functions(import, &mut package_functions);
+    }
+    if !expand {
+        return PreprocessResult {
+            source: filtered,
+            losses: Vec::new(),
+            source_map: line_map,
+            package_functions,
+        };
+    }
     let root = parse(&filtered);
     let mut eval = Evaluator::new();
-    let source = eval.expand_node(&root);
+    let mut anchors = SourceMap::default();
+    let source = eval.expand_root(&root, &mut anchors);
+
+    let mut source_map = SourceMap {
+        anchors: Vec::with_capacity(anchors.anchors.len()),
+        input_len: Some(input.len()),
+    };
+    for (output, filtered_offset) in anchors.anchors {
+        source_map.push(output, line_map.to_input(filtered_offset));
+    }
+    let mut losses = eval.losses;
+    for loss in &mut losses {
+        if let Some(span) = loss.span {
+            loss.span = Some(line_map.span_to_input(span));
+        }
+    }
     PreprocessResult {
         source,
-        losses: eval.losses,
+        losses,
+        source_map,
+        package_functions,
     }
 }
 
-fn strip_imports(input: &str) -> String {
+/// Drop `#import` lines, returning the remaining source, its map back to
+/// `input`, and the text of each dropped statement.
+fn strip_imports(input: &str) -> (String, SourceMap, Vec<String>) {
     let mut out = String::new();
+    let mut map = SourceMap {
+        anchors: Vec::new(),
+        input_len: Some(input.len()),
+    };
+    let mut imports: Vec<String> = Vec::new();
     let mut skipping = false;
     let mut depth: i32 = 0;
-    for line in input.lines() {
+    let mut offset = 0;
+    for raw in input.split_inclusive('\n') {
+        let line_start = offset;
+        offset += raw.len();
+        let line = match raw.strip_suffix('\n') {
+            Some(line) => line.strip_suffix('\r').unwrap_or(line),
+            None => raw,
+        };
         let trimmed = line.trim_start();
-        if !skipping && (trimmed.starts_with("#import") || trimmed.starts_with("#include")) {
+        if !skipping && trimmed.starts_with("#import") {
+            imports.push(trimmed.to_string());
             depth = count_paren_delta(trimmed);
             if depth <= 0 {
                 skipping = false;
@@ -111,6 +232,10 @@ fn strip_imports(input: &str) -> String {
             continue;
         }
         if skipping {
+            if let Some(import) = imports.last_mut() {
+                import.push('\n');
+                import.push_str(trimmed);
+            }
             depth += count_paren_delta(trimmed);
             if depth <= 0 {
                 skipping = false;
@@ -118,10 +243,68 @@ fn strip_imports(input: &str) -> String {
             }
             continue;
         }
+        map.push(out.len(), line_start);
         out.push_str(line);
         out.push('\n');
     }
-    out
+    (out, map, imports)
+}
+
+/// Record the functions an `#import` statement brings in from a package
+/// listed in `PACKAGE_FUNCTIONS`, under the names the document uses.
+fn collect_package_functions(statement: &str, out: &mut HashMap<String, PackageFunction>) {
+    let root = parse(statement);
+    let Some(import) = root.children().find(|c| c.kind() == SyntaxKind::ModuleImport) else {
+        return;
+    };
+    let Some(source) = import.children().find(|c| c.kind() == SyntaxKind::Str) else {
+        return;
+    };
+    let spec = source.text().trim_matches('"');
+    let Some(package) = spec
+        .strip_prefix('@')
+        .and_then(|spec| spec.split_once('/'))
+        .map(|(_, rest)| rest.split(':').next().unwrap_or(rest))
+    else {
+        return;
+    };
+    let lookup = |name: &str| {
+        PACKAGE_FUNCTIONS
+            .iter()
+            .find(|(pkg, func, _)| *pkg == package && *func == name)
+            .map(|(_, _, kind)| *kind)
+    };
+    if import.children().any(|c| c.kind() == SyntaxKind::Star) {
+        for (pkg, func, kind) in PACKAGE_FUNCTIONS {
+            if *pkg == package {
+                out.insert(func.to_string(), *kind);
+            }
+        }
+        return;
+    }
+    let Some(items) = import.children().find(|c| c.kind() == SyntaxKind::ImportItems) else {
+        return;
+    };
+    for item in items.children() {
+        let (name, alias) = match item.kind() {
+            SyntaxKind::Ident => (item.text().to_string(), item.text().to_string()),
+            SyntaxKind::RenamedImportItem => {
+                let idents: Vec<_> = item
+                    .children()
+                    .filter(|c| c.kind() == SyntaxKind::Ident)
+                    .map(|c| c.text().to_string())
+                    .collect();
+                match idents.as_slice() {
+                    [name, alias] => (name.clone(), alias.clone()),
+                    _ => continue,
+                }
+            }
+            _ => continue,
+        };
+        if let Some(kind) = lookup(&name) {
+            out.insert(alias, kind);
+        }
+    }
 }
 
 fn count_paren_delta(line: &str) -> i32 {
@@ -143,6 +326,10 @@ struct Evaluator {
     losses: Vec<Loss>,
     max_depth: usize,
     depth: usize,
+    /// Syntax tree levels `expand_node` may descend before copying the rest
+    /// of a subtree through unexpanded.
+    max_nesting: usize,
+    nesting: usize,
 }
 
 impl Evaluator {
@@ -154,20 +341,34 @@ impl Evaluator {
             losses: Vec::new(),
             max_depth: 32,
             depth: 0,
+            max_nesting: 256,
+            nesting: 0,
         }
     }
 
     fn expand_node(&mut self, node: &SyntaxNode) -> String {
-        if self.depth > self.max_depth {
+        if self.depth > self.max_depth || self.nesting >= self.max_nesting {
             return node_full_text(node);
         }
+        // Loops and function calls can expand without producing a block, so
+        // the conversion's budget is checked here as well as in collection.
+        if crate::budget_exhausted(&mut self.losses) {
+            return String::new();
+        }
+
+        self.nesting += 1;
+        let out = self.expand_node_kind(node);
+        self.nesting -= 1;
+        out
+    }
 
+    fn expand_node_kind(&mut self, node: &SyntaxNode) -> String {
         match node.kind() {
-            SyntaxKind::LetBinding
-            | SyntaxKind::Import
-            | SyntaxKind::ModuleImport
-            | SyntaxKind::Include
-            | SyntaxKind::ModuleInclude => String::new(),
+            SyntaxKind::LetBinding | SyntaxKind::Import | SyntaxKind::ModuleImport => String::new(),
+            // Included files are read by the frontend.
+            SyntaxKind::ModuleInclude => node_full_text(node),
+            // The supplement of `@label[..]` keeps its brackets.
+            SyntaxKind::Ref => node_full_text(node),
             SyntaxKind::SetRule => {
                 if !is_supported_set_rule(node) {
                     let name = set_rule_name(node)
@@ -176,6 +377,12 @@ impl Evaluator {
                     self.losses
                         .push(Loss::new("set-rule", name));
                 }
+                for key in unsupported_page_decorations(node) {
+                    self.losses.push(
+                        Loss::new("page-header", format!("page {} code not supported", key))
+                            .with_severity(Severity::Error),
+                    );
+                }
                 String::new()
             }
             SyntaxKind::ShowRule => {
@@ -195,15 +402,49 @@ impl Evaluator {
         }
     }
 
+    /// Expand the top-level markup, recording where each child starts in the
+    /// output and attaching the child's span to the losses it raised.
+    fn expand_root(&mut self, root: &SyntaxNode, map: &mut SourceMap) -> String {
+        self.expand_sequence(root, Some(map))
+    }
+
     fn expand_children(&mut self, node: &SyntaxNode) -> String {
+        self.expand_sequence(node, None)
+    }
+
+    fn expand_sequence(&mut self, node: &SyntaxNode, mut map: Option<&mut SourceMap>) -> String {
         let children: Vec<_> = node.children().collect();
         if children.is_empty() {
             return node_full_text(node);
         }
 
+        let mut offsets = Vec::with_capacity(children.len() + 1);
+        let mut offset = 0;
+        for child in &children {
+            offsets.push(offset);
+            offset += child.len();
+        }
+        offsets.push(offset);
+        // Loss spans of an embedded expression include its leading `#`.
+        let loss_start = |idx: usize| {
+            if idx > 0 && children[idx - 1].kind() == SyntaxKind::Hash {
+                offsets[idx - 1]
+            } else {
+                offsets[idx]
+            }
+        };
+
         let mut out = String::new();
         let mut i = 0;
+        let mut step: Option<(usize, usize)> = None;
         while i < children.len() {
+            if let Some(map) = map.as_deref_mut() {
+                if let Some((start, loss_mark)) = step {
+                    self.attach_loss_spans(loss_mark, Span::new(loss_start(start), offsets[i]));
+                }
+                step = Some((i, self.losses.len()));
+                map.push(out.len(), offsets[i]);
+            }
             let child = children[i];
             if child.kind() == SyntaxKind::Hash {
                 if let Some(next) = children.get(i + 1) {
@@ -211,6 +452,18 @@ impl Evaluator {
                         SyntaxKind::LetBinding => {
                             self.handle_let_binding(next);
                             i += 2;
+                            // A binding on a line of its own takes its line
+                            // break along, so the lines around it do not
+                            // become a paragraph break.
+                            let own_line = out.is_empty() || out.ends_with('\n');
+                            if own_line
+                                && children.get(i).is_some_and(|space| {
+                                    space.kind() == SyntaxKind::Space
+                                        && space.text().matches('\n').count() == 1
+                                })
+                            {
+                                i += 1;
+                            }
                             continue;
                         }
                         SyntaxKind::Conditional => {
@@ -252,10 +505,21 @@ impl Evaluator {
             out.push_str(&self.expand_node(child));
             i += 1;
         }
+        if let Some((start, loss_mark)) = step {
+            self.attach_loss_spans(loss_mark, Span::new(loss_start(start), offset));
+        }
 
         out
     }
 
+    fn attach_loss_spans(&mut self, from: usize, span: Span) {
+        for loss in &mut self.losses[from..] {
+            if loss.span.is_none() {
+                loss.span = Some(span);
+            }
+        }
+    }
+
     fn expand_content_block(&mut self, node: &SyntaxNode) -> String {
         let mut out = String::new();
         for child in node.children() {
@@ -313,10 +577,11 @@ impl Evaluator {
                 {
                     return self.expand_block_body(&body);
                 }
-                self.losses.push(Loss::new(
-                    "preprocess-for",
-                    "Unsupported for-loop; dropping content",
-                ));
+                self.losses.push(
+                    Loss::new("preprocess-for", "Unsupported for-loop; dropping content")
+                        .with_severity(Severity::Error)
+                        .with_suggestion("loop over an array literal or a `range` of numbers"),
+                );
                 return String::new();
             }
         };
@@ -362,6 +627,11 @@ impl Evaluator {
         if let Some((name, value_node)) = self.parse_let_value(node) {
             if let Some(value) = self.eval_value(&value_node) {
                 self.db.define_var(&name, value);
+            } else if value_node.kind() == SyntaxKind::FuncCall {
+                // Calls such as `figure(..)` are left for the frontend, so
+                // references to the binding splice the call back into markup.
+                let markup = format!("#{}", node_full_text(&value_node));
+                self.db.define_var(&name, Value::Content(markup));
             } else {
                 let raw = node_full_text(&value_node);
                 self.db.define_var(&name, Value::Text(raw));
@@ -556,6 +826,7 @@ impl Evaluator {
                     Some(value != 0)
                 }
                 Some(Value::Array(values)) => Some(!values.is_empty()),
+                Some(Value::Content(_)) => Some(true),
                 None => None,
             },
             _ => None,
@@ -582,6 +853,7 @@ impl Evaluator {
                 Some(value != 0)
             }
             Value::Array(values) => Some(!values.is_empty()),
+            Value::Content(_) => Some(true),
         }
     }
 
@@ -1045,7 +1317,7 @@ impl Evaluator {
                 Value::Number(n) => format_number(*n),
                 Value::None => "none".to_string(),
                 Value::Counter(s) => s.clone(),
-                Value::Array(_) => return None,
+                Value::Array(_) | Value::Content(_) => return None,
             };
             return Some(Value::Counter(key));
         }
@@ -1276,14 +1548,6 @@ impl Evaluator {
     }
 }
 
-fn node_full_text(node: &SyntaxNode) -> String {
-    let text = node.text().to_string();
-    if !text.is_empty() {
-        return text;
-    }
-    node.clone().into_text().to_string()
-}
-
 fn is_supported_set_rule(node: &SyntaxNode) -> bool {
     let Some(name) = set_rule_name(node) else {
         return false;
@@ -1299,11 +1563,78 @@ fn is_supported_set_rule(node: &SyntaxNode) -> bool {
             | "align"
             | "heading"
             | "cite"
+            | "ref"
             | "enum"
             | "document"
     )
 }
 
+/// Return the `header`/`footer` keys of a `#set page(...)` rule whose value
+/// goes beyond the running-head subset understood by the preamble renderer.
+fn unsupported_page_decorations(node: &SyntaxNode) -> Vec<String> {
+    let mut keys = Vec::new();
+    if set_rule_name(node).as_deref() != Some("page") {
+        return keys;
+    }
+    let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) else {
+        return keys;
+    };
+    for child in args.children() {
+        if child.kind() != SyntaxKind::Named {
+            continue;
+        }
+        let Some(key) = child
+            .children()
+            .find(|c| c.kind() == SyntaxKind::Ident)
+            .map(|n| n.text().to_string())
+        else {
+            continue;
+        };
+        if key != "header" && key != "footer" {
+            continue;
+        }
+        if !is_supported_page_decoration(child) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+pub(crate) fn is_supported_page_decoration(node: &SyntaxNode) -> bool {
+    let mut stack = vec![node.clone()];
+    while let Some(current) = stack.pop() {
+        match current.kind() {
+            SyntaxKind::LetBinding
+            | SyntaxKind::SetRule
+            | SyntaxKind::ShowRule
+            | SyntaxKind::ForLoop
+            | SyntaxKind::WhileLoop
+            | SyntaxKind::Closure => return false,
+            SyntaxKind::FuncCall => {
+                let text = node_full_text(&current);
+                if text.starts_with("counter(page)") || text.starts_with("here()") {
+                    continue;
+                }
+                let Some(name) = get_func_call_name(&current) else {
+                    return false;
+                };
+                if !matches!(
+                    name.as_str(),
+                    "hydra" | "h" | "align" | "strong" | "emph" | "smallcaps" | "calc.odd"
+                        | "calc.even"
+                ) {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+        for child in current.children() {
+            stack.push(child.clone());
+        }
+    }
+    true
+}
+
 fn is_supported_show_rule(node: &SyntaxNode) -> bool {
     if node_contains_kind(node, SyntaxKind::Closure) {
         return true;
@@ -1480,7 +1811,7 @@ fn value_to_i64(value: &Value) -> Option<i64> {
 
 #[cfg(test)]
 mod tests {
-    use super::preprocess_typst;
+    use super::{preprocess_typst, PackageFunction};
 
     fn norm(s: &str) -> String {
         s.trim().replace("\r\n", "\n")
@@ -1489,29 +1820,59 @@ mod tests {
     #[test]
     fn expands_let_and_function() {
         let input = "#let foo = [Hello]\n#let bar(x) = [Hi #x]\n\n#foo\n\n#bar(1)\n";
-        let result = preprocess_typst(input);
+        let result = preprocess_typst(input, true);
         assert_eq!(norm(&result.source), "Hello\n\nHi 1");
     }
 
     #[test]
     fn expands_if_and_for() {
         let input = "#if true [A] else [B]\n#for x in (1,2) [#x ]\n";
-        let result = preprocess_typst(input);
+        let result = preprocess_typst(input, true);
         assert_eq!(norm(&result.source), "A\n1 2");
     }
 
     #[test]
     fn expands_counter_methods() {
         let input = "#let c = counter(\"t\")\n#c.step()\n#c.display()";
-        let result = preprocess_typst(input);
+        let result = preprocess_typst(input, true);
         assert_eq!(norm(&result.source), "1");
     }
 
+    #[test]
+    fn page_header_with_running_heads_is_supported() {
+        let input = "#set page(header: context [#hydra(1) #h(1fr) #counter(page).display()])\nBody";
+        let result = preprocess_typst(input, true);
+        assert!(!result.losses.iter().any(|l| l.kind == "page-header"));
+    }
+
+    #[test]
+    fn page_header_with_arbitrary_code_reports_loss() {
+        let input = "#set page(header: context { let t = query(heading); t.first().body })\nBody";
+        let result = preprocess_typst(input, true);
+        assert!(result.losses.iter().any(|l| l.kind == "page-header"));
+    }
+
+    #[test]
+    fn maps_imported_package_functions() {
+        let input = "#import \"@preview/unify:0.7.1\": qty, num as n\n#import \"@preview/whalogen:0.2.0\": *\n#import \"@preview/other:0.1.0\": qty as q\nBody";
+        let result = preprocess_typst(input, true);
+        let mut names: Vec<_> = result.package_functions.into_iter().collect();
+        names.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            names,
+            vec![
+                ("ce".to_string(), PackageFunction::Chemical),
+                ("n".to_string(), PackageFunction::Number),
+                ("qty".to_string(), PackageFunction::Quantity),
+            ]
+        );
+    }
+
     #[test]
     fn expands_logic_and_range() {
         let input =
             "#if true and not false [yes] #if \"a\" == \"a\" [eq]\n#let nums = range(1,4)\n#for x in nums [#x ]";
-        let result = preprocess_typst(input);
+        let result = preprocess_typst(input, true);
         assert_eq!(norm(&result.source), "yes eq\n1 2 3");
     }
 }
@@ -0,0 +1,198 @@
+//! Rules on single grid lines of a table: `table.hline()`, `table.vline()`
+//! and strokes given per side, as in `stroke: (x: none, bottom: 1pt)`.
+
+use tylax_ir::{RuleAxis, TableCell, TableGrid, TableRule};
+use typst_syntax::{parse_code, SyntaxKind, SyntaxNode};
+
+use super::{extract_named_key, extract_named_value_node, get_func_call_name};
+
+/// A `table.hline()` or `table.vline()` and the number of cells before it,
+/// which places the line when it gives no position of its own.
+pub(crate) struct PendingLine {
+    node: SyntaxNode,
+    cells_before: usize,
+}
+
+impl PendingLine {
+    pub(crate) fn new(node: &SyntaxNode, cells_before: usize) -> Self {
+        PendingLine {
+            node: node.clone(),
+            cells_before,
+        }
+    }
+}
+
+/// Whether a stroke names each side of a cell, in the order top, bottom,
+/// left, right: `Some(false)` for `none`, `None` when the side is left out.
+type Sides = [Option<bool>; 4];
+
+/// Resolve the lines of a table once its cells and columns are known, and
+/// add the rules that per-side strokes draw. A table stroke turned into
+/// rules is replaced by `none`, so only those rules are drawn.
+pub(crate) fn table_rules(
+    lines: &[PendingLine],
+    cells: &[TableCell],
+    columns: usize,
+    stroke: &mut Option<String>,
+) -> Vec<TableRule> {
+    let placed = TableGrid::new(cells, columns).origins;
+    let rows = placed
+        .iter()
+        .zip(cells)
+        .map(|((row, _), cell)| row + cell.rowspan.max(1))
+        .max()
+        .unwrap_or(0);
+    let mut rules = Vec::new();
+
+    if let Some(sides) = stroke.as_deref().and_then(stroke_sides) {
+        // Sides the dictionary leaves out keep Typst's default stroke.
+        let [top, bottom, left, right] = sides.map(|side| side.unwrap_or(true));
+        if !(top && bottom && left && right) {
+            for position in 0..=rows {
+                if (position < rows && top) || (position > 0 && bottom) {
+                    rules.push(full_rule(RuleAxis::Horizontal, position));
+                }
+            }
+            for position in 0..=columns {
+                if (position < columns && left) || (position > 0 && right) {
+                    rules.push(full_rule(RuleAxis::Vertical, position));
+                }
+            }
+            *stroke = Some("none".to_string());
+        }
+    }
+
+    for (cell, &(row, col)) in cells.iter().zip(&placed) {
+        let Some(sides) = cell.stroke.as_deref().and_then(stroke_sides) else {
+            continue;
+        };
+        let (rows_end, cols_end) = (row + cell.rowspan.max(1), col + cell.colspan.max(1));
+        let edges = [
+            (RuleAxis::Horizontal, row, col, cols_end),
+            (RuleAxis::Horizontal, rows_end, col, cols_end),
+            (RuleAxis::Vertical, col, row, rows_end),
+            (RuleAxis::Vertical, cols_end, row, rows_end),
+        ];
+        for (side, (axis, position, start, end)) in sides.into_iter().zip(edges) {
+            if side == Some(true) {
+                rules.push(TableRule {
+                    axis,
+                    position,
+                    start,
+                    end: Some(end),
+                });
+            }
+        }
+    }
+
+    rules.extend(
+        lines
+            .iter()
+            .filter_map(|line| resolve_line(line, cells, &placed)),
+    );
+    rules
+}
+
+fn full_rule(axis: RuleAxis, position: usize) -> TableRule {
+    TableRule {
+        axis,
+        position,
+        start: 0,
+        end: None,
+    }
+}
+
+/// Read `y`/`x`, `start`, `end` and `position` of a line. Without its own
+/// index an hline runs below the row of the cell before it and a vline
+/// after that cell's columns. A line stroked with `none` draws nothing.
+fn resolve_line(
+    line: &PendingLine,
+    cells: &[TableCell],
+    placed: &[(usize, usize)],
+) -> Option<TableRule> {
+    let axis = match get_func_call_name(&line.node)?.as_str() {
+        "table.hline" => RuleAxis::Horizontal,
+        "table.vline" => RuleAxis::Vertical,
+        _ => return None,
+    };
+    let horizontal = axis == RuleAxis::Horizontal;
+    let index_key = if horizontal { "y" } else { "x" };
+    let mut index = None;
+    let mut start = 0;
+    let mut end = None;
+    let mut after = false;
+    let args = line.node.children().find(|c| c.kind() == SyntaxKind::Args);
+    for arg in args
+        .iter()
+        .flat_map(|args| args.children())
+        .filter(|c| c.kind() == SyntaxKind::Named)
+    {
+        let Some(value) = extract_named_value_node(arg) else {
+            continue;
+        };
+        match extract_named_key(arg).as_deref() {
+            Some(key) if key == index_key => index = parse_index(&value),
+            Some("start") => start = parse_index(&value).unwrap_or(0),
+            Some("end") => end = parse_index(&value),
+            Some("stroke") if value.kind() == SyntaxKind::None => return None,
+            Some("position") => after = matches!(value.text().as_str(), "bottom" | "end"),
+            _ => {}
+        }
+    }
+    let position = index.unwrap_or_else(|| {
+        let Some(last) = line.cells_before.checked_sub(1) else {
+            return 0;
+        };
+        let (row, col) = placed.get(last).copied().unwrap_or_default();
+        if horizontal {
+            row + 1
+        } else {
+            col + cells.get(last).map_or(1, |cell| cell.colspan.max(1))
+        }
+    });
+    Some(TableRule {
+        axis,
+        position: position + usize::from(after),
+        start,
+        end,
+    })
+}
+
+fn parse_index(value: &SyntaxNode) -> Option<usize> {
+    (value.kind() == SyntaxKind::Int)
+        .then(|| value.text().parse().ok())
+        .flatten()
+}
+
+/// The sides a stroke dictionary sets, or `None` for any other stroke. A
+/// side takes its own key first, then `x` or `y`, then `rest`.
+fn stroke_sides(stroke: &str) -> Option<Sides> {
+    let root = parse_code(stroke);
+    let dict = root.children().find(|c| c.kind() == SyntaxKind::Dict)?;
+    let mut entries = Vec::new();
+    for entry in dict.children().filter(|c| c.kind() == SyntaxKind::Named) {
+        let key = extract_named_key(entry)?;
+        if !matches!(
+            key.as_str(),
+            "top" | "bottom" | "left" | "right" | "x" | "y" | "rest"
+        ) {
+            return None;
+        }
+        let drawn = extract_named_value_node(entry)?.kind() != SyntaxKind::None;
+        entries.push((key, drawn));
+    }
+    let lookup = |keys: &[&str]| {
+        keys.iter().find_map(|key| {
+            entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, drawn)| *drawn)
+        })
+    };
+    Some([
+        lookup(&["top", "y", "rest"]),
+        lookup(&["bottom", "y", "rest"]),
+        lookup(&["left", "x", "rest"]),
+        lookup(&["right", "x", "rest"]),
+    ])
+}
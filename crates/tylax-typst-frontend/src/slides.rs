@@ -0,0 +1,102 @@
+//! Slides of the polylux and touying packages.
+//!
+//! ```typst
+//! #slide(title: [Results])[
+//!   First point
+//!   #pause
+//!   #side-by-side(columns: (1fr, 2fr))[Left][Right]
+//! ]
+//! ```
+//!
+//! A slide without a `title:` takes its leading heading as the title.
+//! Touying's `#slide[..][..]` sets its bodies side by side, in the widths
+//! of its `composer:`.
+
+use tylax_ir::{Block, Inline, Loss, SideBySide, Slide};
+use typst_syntax::{SyntaxKind, SyntaxNode};
+
+use super::{
+    collect_blocks, collect_inlines, extract_named_key, extract_named_value_node,
+    get_func_call_name, has_inline_content, node_full_text, parse_column_widths,
+};
+
+pub(crate) fn parse_slide(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block> {
+    let name = get_func_call_name(node)?;
+    if !matches!(name.as_str(), "slide" | "polylux-slide") {
+        return None;
+    }
+    let (named, bodies) = call_args(node, losses);
+    let mut title = None;
+    let mut widths = Vec::new();
+    for (key, value) in &named {
+        match key.as_str() {
+            "title" => title = Some(collect_inlines(value, losses)),
+            "composer" => widths = parse_column_widths(&node_full_text(value)),
+            _ => {}
+        }
+    }
+    let mut blocks = match bodies.len() {
+        0 => Vec::new(),
+        1 => bodies.into_iter().next().unwrap_or_default(),
+        _ => vec![Block::SideBySide(SideBySide {
+            widths,
+            columns: bodies,
+        })],
+    };
+    blocks.retain(
+        |block| !matches!(block, Block::Paragraph(inlines) if !has_inline_content(inlines)),
+    );
+    if title.is_none() {
+        if let Some(Block::Heading { content, .. }) = blocks.first() {
+            let mut content = content.clone();
+            if let Some(Inline::Text(text)) = content.first_mut() {
+                *text = text.trim_start().to_string();
+            }
+            title = Some(content);
+            blocks.remove(0);
+        }
+    }
+    Some(Block::Slide(Slide { title, blocks }))
+}
+
+/// polylux's `#side-by-side(columns: ..)[..][..]`, also reached through
+/// its `toolbox`.
+pub(crate) fn parse_side_by_side(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block> {
+    let name = get_func_call_name(node)?;
+    if name != "side-by-side" && !name.ends_with(".side-by-side") {
+        return None;
+    }
+    let (named, columns) = call_args(node, losses);
+    let widths = named
+        .iter()
+        .find(|(key, _)| key == "columns")
+        .map(|(_, value)| parse_column_widths(&node_full_text(value)))
+        .unwrap_or_default();
+    Some(Block::SideBySide(SideBySide { widths, columns }))
+}
+
+/// The named arguments of a call and the blocks of each content argument.
+fn call_args(
+    node: &SyntaxNode,
+    losses: &mut Vec<Loss>,
+) -> (Vec<(String, SyntaxNode)>, Vec<Vec<Block>>) {
+    let mut named = Vec::new();
+    let mut bodies = Vec::new();
+    let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) else {
+        return (named, bodies);
+    };
+    for arg in args.children() {
+        match arg.kind() {
+            SyntaxKind::Named => {
+                if let (Some(key), Some(value)) =
+                    (extract_named_key(arg), extract_named_value_node(arg))
+                {
+                    named.push((key, value));
+                }
+            }
+            SyntaxKind::ContentBlock => bodies.push(collect_blocks(arg, losses)),
+            _ => {}
+        }
+    }
+    (named, bodies)
+}
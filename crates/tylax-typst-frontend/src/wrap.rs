@@ -0,0 +1,117 @@
+//! Figures with the text flowing around them.
+//!
+//! ```typst
+//! #wrap-content(figure(image("plot.png", width: 40%)), align: right)[
+//!   The text set beside the figure.
+//! ]
+//! #place(left, figure(image("logo.png", width: 3cm)))
+//! ```
+//!
+//! The wrap-it package's `wrap-content` sets its first argument at a side
+//! of its second, on the left unless `align:` says otherwise. A figure
+//! `place`d at the left or right edge, without floating, stands beside the
+//! text that follows. Both become a figure with the side in
+//! [`Figure::wrap`], followed by the text.
+
+use tylax_ir::{Alignment, Block, Figure, Loss, Side};
+use typst_syntax::{SyntaxKind, SyntaxNode};
+
+use super::{
+    collect_blocks, collect_inlines, extract_named_key, extract_named_value_node,
+    get_func_call_name, has_inline_content, maybe_figure_block, maybe_image_block, node_full_text,
+    parse_alignment,
+};
+
+/// `wrap-content(fixed, to-wrap, align: ..)`, when `fixed` is a figure or
+/// an image: the figure, then the text around it.
+pub(crate) fn parse_wrap_content(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Vec<Block>> {
+    let name = get_func_call_name(node)?;
+    if name != "wrap-content" && !name.ends_with(".wrap-content") {
+        return None;
+    }
+    let args = node.children().find(|c| c.kind() == SyntaxKind::Args)?;
+    let mut side = Side::Left;
+    let mut contents = Vec::new();
+    for child in args.children() {
+        match child.kind() {
+            SyntaxKind::Named if extract_named_key(child).as_deref() == Some("align") => {
+                let value = extract_named_value_node(child);
+                let align = value.map(|value| parse_alignment(&node_full_text(&value)).0);
+                if align == Some(Some(Alignment::Right)) {
+                    side = Side::Right;
+                }
+            }
+            SyntaxKind::FuncCall | SyntaxKind::ContentBlock => contents.push(child),
+            _ => {}
+        }
+    }
+    let [fixed, body] = contents[..] else {
+        return None;
+    };
+    // Kept apart until the call is known to wrap a figure, so a call read
+    // again as plain content does not record its losses twice.
+    let mut wrap_losses = Vec::new();
+    let mut figure = wrapped_figure(fixed, &mut wrap_losses)?;
+    figure.wrap = Some(side);
+    let mut blocks = vec![Block::Figure(figure)];
+    if body.kind() == SyntaxKind::ContentBlock {
+        blocks.extend(collect_blocks(body, &mut wrap_losses));
+    } else {
+        blocks.push(Block::Paragraph(collect_inlines(body, &mut wrap_losses)));
+    }
+    losses.extend(wrap_losses);
+    Some(blocks)
+}
+
+/// `place(left, figure(..))` or `place(right, ..)`, unless it floats.
+pub(crate) fn parse_placed_figure(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Block> {
+    if get_func_call_name(node)? != "place" {
+        return None;
+    }
+    let args = node.children().find(|c| c.kind() == SyntaxKind::Args)?;
+    let mut side = None;
+    let mut content = None;
+    for child in args.children() {
+        match child.kind() {
+            SyntaxKind::Named if extract_named_key(child).as_deref() == Some("float") => {
+                let value = extract_named_value_node(child);
+                if value.is_some_and(|value| value.text() == "true") {
+                    return None;
+                }
+            }
+            SyntaxKind::Ident | SyntaxKind::Binary => {
+                side = match parse_alignment(&node_full_text(child)).0 {
+                    Some(Alignment::Left) => Some(Side::Left),
+                    Some(Alignment::Right) => Some(Side::Right),
+                    _ => None,
+                };
+            }
+            SyntaxKind::FuncCall | SyntaxKind::ContentBlock => content = Some(child),
+            _ => {}
+        }
+    }
+    let mut placed_losses = Vec::new();
+    let mut figure = wrapped_figure(content?, &mut placed_losses)?;
+    figure.wrap = Some(side?);
+    losses.extend(placed_losses);
+    Some(Block::Figure(figure))
+}
+
+/// The figure a `figure(..)`, an `image(..)` or a content block holding
+/// nothing else stands for.
+fn wrapped_figure(node: &SyntaxNode, losses: &mut Vec<Loss>) -> Option<Figure> {
+    let mut blocks = match node.kind() {
+        SyntaxKind::FuncCall => maybe_figure_block(node, losses)
+            .or_else(|| maybe_image_block(node, losses))
+            .into_iter()
+            .collect(),
+        _ => collect_blocks(node, losses),
+    };
+    blocks.retain(
+        |block| !matches!(block, Block::Paragraph(inlines) if !has_inline_content(inlines)),
+    );
+    match <[Block; 1]>::try_from(blocks) {
+        Ok([Block::Figure(figure)]) => Some(figure),
+        _ => None,
+    }
+}
@@ -0,0 +1,98 @@
+use tylax_ir::{Algorithm, AlgorithmKeyword, AlgorithmStep, Block, Inline};
+use tylax_typst_frontend::typst_to_ir;
+
+fn step(
+    keyword: AlgorithmKeyword,
+    content: Vec<Inline>,
+    body: Vec<AlgorithmStep>,
+) -> AlgorithmStep {
+    AlgorithmStep {
+        keyword,
+        content,
+        comment: None,
+        body,
+    }
+}
+
+#[test]
+fn lovelace_list_nests_steps_and_drops_end_lines() {
+    let doc = typst_to_ir(
+        "#figure(\n  pseudocode-list[\n    + *for* $i$ *do*\n      + swap\n    + *end*\n    + *return* $x$\n  ],\n  caption: [Sort],\n) <alg:sort>",
+    );
+    assert_eq!(
+        doc.blocks.first(),
+        Some(&Block::Algorithm(Algorithm {
+            caption: Some(vec![Inline::text("Sort")]),
+            label: Some("alg:sort".to_string()),
+            line_numbers: true,
+            steps: vec![
+                step(
+                    AlgorithmKeyword::For,
+                    vec![Inline::Math("i".to_string())],
+                    vec![step(
+                        AlgorithmKeyword::Statement,
+                        vec![Inline::text("swap")],
+                        vec![]
+                    )],
+                ),
+                step(
+                    AlgorithmKeyword::Return,
+                    vec![Inline::Math("x".to_string())],
+                    vec![]
+                ),
+            ],
+        }))
+    );
+}
+
+#[test]
+fn algo_body_follows_indent_marks() {
+    let doc = typst_to_ir(
+        "#algo(title: \"Halve\", parameters: (\"n\",), line-numbers: false)[\n  while $n > 1$:#i\n    $n <- n / 2$#d\n  else: done\n]",
+    );
+    let Some(Block::Algorithm(algorithm)) = doc.blocks.first() else {
+        panic!("expected algorithm, got {:?}", doc.blocks);
+    };
+    assert!(!algorithm.line_numbers);
+    assert_eq!(
+        algorithm.steps,
+        vec![step(
+            AlgorithmKeyword::Procedure,
+            vec![Inline::text("Halve"), Inline::text("(n)")],
+            vec![
+                step(
+                    AlgorithmKeyword::While,
+                    vec![Inline::Math("n > 1".to_string())],
+                    vec![step(
+                        AlgorithmKeyword::Statement,
+                        vec![Inline::Math("n <- n / 2".to_string())],
+                        vec![],
+                    )],
+                ),
+                step(AlgorithmKeyword::Else, vec![Inline::text("done")], vec![]),
+            ],
+        )]
+    );
+}
+
+#[test]
+fn algo_keeps_comments_and_input_lines() {
+    let doc = typst_to_ir(
+        "#algo(title: \"Sum\", parameters: (\"xs\",))[\n  input: a list\n  $s <- 0$ #comment[start empty]\n  return $s$\n]",
+    );
+    let Some(Block::Algorithm(algorithm)) = doc.blocks.first() else {
+        panic!("expected algorithm, got {:?}", doc.blocks);
+    };
+    let body = &algorithm.steps[0].body;
+    assert_eq!(
+        body[0],
+        step(
+            AlgorithmKeyword::Input,
+            vec![Inline::text("a list")],
+            vec![]
+        )
+    );
+    assert_eq!(body[1].content, vec![Inline::Math("s <- 0".to_string())]);
+    assert_eq!(body[1].comment, Some(vec![Inline::text("start empty")]));
+    assert_eq!(body[2].keyword, AlgorithmKeyword::Return);
+}
@@ -0,0 +1,62 @@
+use tylax_ir::{Block, Document, Inline};
+use tylax_typst_frontend::{typst_to_ir, typst_to_ir_with, TypstFrontendOptions};
+
+const INPUT: &str = "#let fig = figure(image(\"cat.png\"), caption: [A cat.])\n#let note = [a *bold* note]\n\nBefore.\n\n#fig <fig:cat>\n\nSay #note.\n";
+
+fn without_macros(input: &str) -> Document {
+    let options = TypstFrontendOptions {
+        expand_macros: false,
+        ..TypstFrontendOptions::default()
+    };
+    typst_to_ir_with(input, &options)
+}
+
+fn content_blocks(doc: &Document) -> Vec<&Block> {
+    doc.blocks
+        .iter()
+        .filter(|block| !matches!(block, Block::Paragraph(inlines) if inlines.is_empty()))
+        .collect()
+}
+
+#[test]
+fn bound_content_is_resolved_where_referenced() {
+    for doc in [typst_to_ir(INPUT), without_macros(INPUT)] {
+        let blocks = content_blocks(&doc);
+        assert_eq!(blocks.len(), 3, "{:?}", doc.blocks);
+        let Block::Figure(figure) = blocks[1] else {
+            panic!("expected figure, got {:?}", blocks[1]);
+        };
+        assert_eq!(figure.label.as_deref(), Some("fig:cat"));
+        assert_eq!(figure.caption, Some(vec![Inline::text("A cat.")]));
+        assert_eq!(
+            blocks[2],
+            &Block::Paragraph(vec![
+                Inline::text("Say a "),
+                Inline::Strong(vec![Inline::text("bold")]),
+                Inline::text(" note."),
+            ])
+        );
+    }
+}
+
+#[test]
+fn rebound_names_are_not_resolved() {
+    let doc = without_macros("#let x = [one]\n#let x = [two]\n\nSee #x.");
+    assert_eq!(
+        content_blocks(&doc),
+        vec![&Block::Paragraph(vec![Inline::text("See .")])]
+    );
+}
+
+#[test]
+fn bindings_that_refer_to_themselves_are_not_resolved() {
+    for input in [
+        "#let a = [x #a #a #a]\n\nSee #a.",
+        "#let a = [#b]\n#let b = [#a #a]\n\nSee #a and #b.",
+    ] {
+        let doc = without_macros(input);
+        let cyclic = doc.losses.iter().filter(|loss| loss.kind == "binding");
+        assert!(cyclic.count() >= 1, "{:?}", doc.losses);
+        assert!(doc.losses.iter().all(|loss| loss.kind != "nesting"));
+    }
+}
@@ -0,0 +1,50 @@
+use tylax_ir::{Block, Columns, Inline};
+use tylax_typst_frontend::typst_to_ir;
+
+fn raw_blocks(input: &str) -> Vec<String> {
+    typst_to_ir(input)
+        .blocks
+        .into_iter()
+        .filter_map(|block| match block {
+            Block::Paragraph(inlines) => match inlines.as_slice() {
+                [Inline::RawLatex(raw)] => Some(raw.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn weak_page_breaks_are_skipped_at_a_page_start() {
+    let input = "#pagebreak(weak: true)\nA\n\n#pagebreak()\n#pagebreak(weak: true)\nB\n\n\
+                 #pagebreak(weak: true)\nC";
+    assert_eq!(raw_blocks(input), ["\\newpage", "\\newpage"]);
+}
+
+#[test]
+fn page_breaks_go_to_odd_or_even_pages() {
+    let input = "A\n\n#pagebreak(to: \"odd\")\nB\n\n#pagebreak(to: \"even\")\nC";
+    assert_eq!(
+        raw_blocks(input),
+        [
+            "\\clearpage\\ifodd\\value{page}\\else\\hbox{}\\newpage\\fi",
+            "\\clearpage\\ifodd\\value{page}\\hbox{}\\newpage\\fi"
+        ]
+    );
+    assert!(typst_to_ir(input).losses.is_empty());
+}
+
+#[test]
+fn column_breaks_end_the_column_they_are_in() {
+    let input = "#set page(columns: 2)\nA\n\n#colbreak()\nB";
+    assert_eq!(raw_blocks(input), ["\\newpage"]);
+
+    let doc = typst_to_ir("#columns(2)[A\n\n#colbreak()\nB]\n\n#colbreak()\nC");
+    let Some(Block::Columns(Columns { blocks, .. })) = doc.blocks.first() else {
+        panic!("{:?}", doc.blocks);
+    };
+    let raw = |latex: &str| Block::Paragraph(vec![Inline::RawLatex(latex.to_string())]);
+    assert!(blocks.contains(&raw("\\columnbreak")));
+    assert!(doc.blocks[1..].contains(&raw("\\newpage")));
+}
@@ -0,0 +1,378 @@
+use std::sync::{Arc, Mutex};
+
+use tylax_ir::{
+    Alignment, Block, CaptionPosition, ColumnWidth, Inline, RuleAxis, TableRule, VerticalAlignment,
+};
+use tylax_typst_frontend::{typst_to_ir, typst_to_ir_with, SpreadEvaluator, TypstFrontendOptions};
+
+#[test]
+fn list_cell_keeps_blocks() {
+    let input = "#table(columns: 2, [Terms], [/ A: first\n/ B: second])";
+    let doc = typst_to_ir(input);
+    let Some(Block::Table(table)) = doc.blocks.first() else {
+        panic!("expected table, got {:?}", doc.blocks);
+    };
+    assert!(table.cells[0].blocks.is_none());
+    let blocks = table.cells[1].blocks.as_ref().expect("cell blocks");
+    let terms = blocks
+        .iter()
+        .find_map(|block| match block {
+            Block::TermList(items) => Some(items.len()),
+            _ => None,
+        })
+        .expect("term list");
+    assert_eq!(terms, 2);
+}
+
+#[test]
+fn caption_list_is_demoted_to_inline() {
+    let input = "#figure([x], caption: [Steps:\n+ mix\n+ bake])";
+    let doc = typst_to_ir(input);
+    let Some(Block::Figure(figure)) = doc.blocks.first() else {
+        panic!("expected figure, got {:?}", doc.blocks);
+    };
+    let caption = figure.caption.as_ref().expect("caption");
+    let text: String = caption
+        .iter()
+        .filter_map(|inline| match inline {
+            Inline::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    assert!(text.contains("(1) mix; (2) bake"), "{text}");
+}
+
+#[test]
+fn header_repeat_flag_is_kept() {
+    let table_of = |input: &str| match typst_to_ir(input).blocks.into_iter().next() {
+        Some(Block::Table(table)) => table,
+        other => panic!("expected table, got {:?}", other),
+    };
+    let repeated = table_of("#table(columns: 2, table.header([A], [B]), [1], [2])");
+    assert!(repeated.repeat_header);
+    let once = table_of("#table(columns: 2, table.header(repeat: false, [A], [B]), [1], [2])");
+    assert!(!once.repeat_header);
+    assert_eq!(once.cells.iter().filter(|cell| cell.is_header).count(), 2);
+}
+
+#[test]
+fn footer_cells_go_last() {
+    let input = "#table(columns: 2, table.header([A], [B]), table.footer([Total], [3]), [1], [2])";
+    let doc = typst_to_ir(input);
+    let Some(Block::Table(table)) = doc.blocks.first() else {
+        panic!("expected table, got {:?}", doc.blocks);
+    };
+    let flags: Vec<_> = table
+        .cells
+        .iter()
+        .map(|cell| (cell.is_header, cell.is_footer))
+        .collect();
+    assert_eq!(
+        flags,
+        vec![
+            (true, false),
+            (true, false),
+            (false, false),
+            (false, false),
+            (false, true),
+            (false, true),
+        ]
+    );
+    assert_eq!(table.cells[4].content, vec![Inline::text("Total")]);
+}
+
+#[test]
+fn column_widths_are_kept() {
+    let input = "#table(columns: (auto, 1fr, 2.5fr, 3cm), [a], [b], [c], [d])";
+    let doc = typst_to_ir(input);
+    let Some(Block::Table(table)) = doc.blocks.first() else {
+        panic!("expected table, got {:?}", doc.blocks);
+    };
+    assert_eq!(table.columns, 4);
+    assert_eq!(
+        table.widths,
+        vec![
+            ColumnWidth::Auto,
+            ColumnWidth::Fraction("1".to_string()),
+            ColumnWidth::Fraction("2.5".to_string()),
+            ColumnWidth::Length("3cm".to_string()),
+        ]
+    );
+
+    // Computed columns only give a count.
+    let doc = typst_to_ir("#table(columns: (1fr,) * 2, [a], [b])");
+    let Some(Block::Table(table)) = doc.blocks.first() else {
+        panic!("expected table, got {:?}", doc.blocks);
+    };
+    assert!(table.widths.is_empty());
+}
+
+#[test]
+fn table_lines_become_rules() {
+    let table_of = |input: &str| match typst_to_ir(input).blocks.into_iter().next() {
+        Some(Block::Table(table)) => table,
+        other => panic!("expected table, got {:?}", other),
+    };
+    let rule = |axis, position, start, end| TableRule {
+        axis,
+        position,
+        start,
+        end,
+    };
+    let table = table_of(
+        "#table(columns: 2, stroke: none, table.hline(), [a], [b], table.hline(start: 1), \
+         [c], [d], table.vline(x: 1, end: none), table.hline(y: 2, stroke: none))",
+    );
+    assert_eq!(
+        table.rules,
+        vec![
+            rule(RuleAxis::Horizontal, 0, 0, None),
+            rule(RuleAxis::Horizontal, 1, 1, None),
+            rule(RuleAxis::Vertical, 1, 0, None),
+        ]
+    );
+
+    // Sides a stroke dictionary leaves out keep the default stroke.
+    let table = table_of("#table(columns: 2, stroke: (x: none), [a], [b], [c], [d])");
+    assert_eq!(table.stroke.as_deref(), Some("none"));
+    assert_eq!(
+        table.rules,
+        (0..=2)
+            .map(|position| rule(RuleAxis::Horizontal, position, 0, None))
+            .collect::<Vec<_>>()
+    );
+
+    let table = table_of(
+        "#table(columns: 2, stroke: none, table.cell(colspan: 2, stroke: (bottom: 1pt))[A], \
+         [b], [c])",
+    );
+    assert_eq!(table.rules, vec![rule(RuleAxis::Horizontal, 1, 0, Some(2))]);
+}
+
+#[test]
+fn spread_cells_come_from_the_evaluator() {
+    let input = "#table(columns: 2, [Name], [Score], ..csv(\"data.csv\").flatten())";
+    let options = TypstFrontendOptions {
+        spread_evaluator: Some(SpreadEvaluator::new(|spread| {
+            (spread.expr == "csv(\"data.csv\").flatten()")
+                .then(|| ["Ada", "*12*", "Grace", "9"].map(str::to_string).to_vec())
+        })),
+        ..TypstFrontendOptions::default()
+    };
+    let doc = typst_to_ir_with(input, &options);
+    let Some(Block::Table(table)) = doc.blocks.first() else {
+        panic!("expected table, got {:?}", doc.blocks);
+    };
+    assert_eq!(table.cells.len(), 6);
+    assert_eq!(table.cells[2].content, vec![Inline::text("Ada")]);
+    assert!(matches!(table.cells[3].content[..], [Inline::Strong(_)]));
+
+    // Without an evaluator the spread cells are reported as lost.
+    let doc = typst_to_ir(input);
+    let Some(Block::Table(table)) = doc.blocks.first() else {
+        panic!("expected table, got {:?}", doc.blocks);
+    };
+    assert_eq!(table.cells.len(), 2);
+    assert!(doc
+        .losses
+        .iter()
+        .any(|loss| loss.message.contains("csv(\"data.csv\")")));
+}
+
+#[test]
+fn grid_cells_can_be_spread() {
+    let options = TypstFrontendOptions {
+        spread_evaluator: Some(SpreadEvaluator::new(|spread| {
+            (spread.expr == "cells").then(|| vec!["a".to_string(), "_b_".to_string()])
+        })),
+        ..TypstFrontendOptions::default()
+    };
+    let doc = typst_to_ir_with("#grid(columns: 2, [x], ..cells)", &options);
+    let Some(Block::Grid(grid)) = doc.blocks.first() else {
+        panic!("expected grid, got {:?}", doc.blocks);
+    };
+    assert_eq!(grid.cells.len(), 3);
+    assert_eq!(
+        grid.cells[1],
+        vec![Block::Paragraph(vec![Inline::text("a")])]
+    );
+    assert!(matches!(
+        grid.cells[2][..],
+        [Block::Paragraph(ref inlines)] if matches!(inlines[..], [Inline::Emph(_)])
+    ));
+}
+
+#[test]
+fn spreads_know_where_their_top_level_element_starts() {
+    let offsets = Arc::new(Mutex::new(Vec::new()));
+    let seen = offsets.clone();
+    let options = TypstFrontendOptions {
+        spread_evaluator: Some(SpreadEvaluator::new(move |spread| {
+            seen.lock().unwrap().push(spread.offset);
+            Some(Vec::new())
+        })),
+        ..TypstFrontendOptions::default()
+    };
+    let input = "#let cells = ([a],)\n\nText.\n\n#table(columns: 1, ..cells)\n\n\
+                 #grid(columns: 1, ..cells)\n";
+    typst_to_ir_with(input, &options);
+    assert_eq!(
+        *offsets.lock().unwrap(),
+        [input.find("#table").unwrap(), input.find("#grid").unwrap()]
+    );
+}
+
+#[test]
+fn figure_numbering_and_caption_separator_are_kept() {
+    let figure_of = |input: &str| match typst_to_ir(input).blocks.into_iter().next() {
+        Some(Block::Figure(figure)) => figure,
+        other => panic!("expected figure, got {:?}", other),
+    };
+    let figure = figure_of("#figure([x], caption: [Plain], numbering: none)");
+    assert!(figure.unnumbered);
+    assert_eq!(figure.caption, Some(vec![Inline::text("Plain")]));
+
+    let figure = figure_of("#figure([x], caption: figure.caption(separator: \". \")[Dotted])");
+    assert!(!figure.unnumbered);
+    assert_eq!(figure.caption, Some(vec![Inline::text("Dotted")]));
+    assert_eq!(figure.caption_separator, Some(vec![Inline::text(". ")]));
+
+    let figure = figure_of("#figure([x], caption: none)");
+    assert_eq!(figure.caption, None);
+
+    let figure = figure_of(concat!(
+        "#figure([x], numbering: \"I\", caption: figure.caption(\n",
+        "  position: top,\n",
+        "  flex-caption[A long caption][Short],\n",
+        "))",
+    ));
+    assert_eq!(figure.caption, Some(vec![Inline::text("A long caption")]));
+    assert_eq!(figure.short_caption, Some(vec![Inline::text("Short")]));
+    assert_eq!(figure.caption_position, Some(CaptionPosition::Top));
+    assert_eq!(figure.numbering.as_deref(), Some("I"));
+}
+
+#[test]
+fn vertical_alignment_is_kept_apart_from_horizontal() {
+    let input = "#align(center + horizon)[Title]\n\n#align(bottom)[End]\n\n\
+                 #table(columns: 2, align: left + horizon, \
+                 [a], table.cell(align: bottom)[b])";
+    let doc = typst_to_ir(input);
+    let aligns: Vec<_> = doc
+        .blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Align {
+                alignment,
+                vertical,
+                ..
+            } => Some((*alignment, *vertical)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        aligns,
+        [
+            (Some(Alignment::Center), Some(VerticalAlignment::Horizon)),
+            (None, Some(VerticalAlignment::Bottom)),
+        ]
+    );
+    let Some(Block::Table(table)) = doc.blocks.get(2) else {
+        panic!("expected table, got {:?}", doc.blocks);
+    };
+    assert_eq!(table.align, Some(vec![Alignment::Left]));
+    assert_eq!(table.valign, Some(VerticalAlignment::Horizon));
+    // A cell aligned only vertically keeps its column's alignment.
+    assert_eq!(table.cells[1].align, None);
+    assert_eq!(table.cells[1].valign, Some(VerticalAlignment::Bottom));
+}
+
+#[test]
+fn vertical_alignment_latex_cannot_set_is_a_loss() {
+    let doc = typst_to_ir("#table(columns: 2, align: (top, bottom), [a], [b])");
+    let Some(Block::Table(table)) = doc.blocks.first() else {
+        panic!("expected table, got {:?}", doc.blocks);
+    };
+    assert_eq!(table.valign, Some(VerticalAlignment::Top));
+    assert!(doc.losses.iter().any(|loss| loss.kind == "align"));
+
+    let doc = typst_to_ir("#grid(columns: 2, align: horizon, [p], [q])");
+    assert!(doc.losses.iter().any(|loss| loss.kind == "align"));
+}
+
+#[test]
+fn alignment_functions_split_header_rows_and_first_columns() {
+    let table_of = |input: &str| {
+        let doc = typst_to_ir(input);
+        match doc.blocks.into_iter().next() {
+            Some(Block::Table(table)) => (table, doc.losses),
+            other => panic!("expected table, got {:?}", other),
+        }
+    };
+    let (table, losses) = table_of(
+        "#table(columns: 2, align: (x, y) => if y == 0 { center } else { left + horizon }, \
+         [A], [B], [1], table.cell(align: right)[2])",
+    );
+    assert!(losses.is_empty(), "{losses:?}");
+    assert_eq!(table.align, Some(vec![Alignment::Left, Alignment::Left]));
+    assert_eq!(table.valign, Some(VerticalAlignment::Horizon));
+    let aligns: Vec<_> = table.cells.iter().map(|cell| cell.align).collect();
+    assert_eq!(
+        aligns,
+        [
+            Some(Alignment::Center),
+            Some(Alignment::Center),
+            None,
+            Some(Alignment::Right)
+        ]
+    );
+
+    let (table, _) = table_of(
+        "#table(columns: 3, align: (col, row) => if col > 0 { right } else { left }, \
+         [a], [b], [c])",
+    );
+    assert_eq!(
+        table.align,
+        Some(vec![Alignment::Left, Alignment::Right, Alignment::Right])
+    );
+}
+
+#[test]
+fn alignment_the_frontend_cannot_read_is_a_loss() {
+    for input in [
+        "#table(columns: 2, align: (x, y) => if calc.odd(y) { left } else { right }, [a], [b])",
+        "#table(columns: 2, align: ((left, right), (center, center)), [a], [b])",
+    ] {
+        let doc = typst_to_ir(input);
+        let Some(Block::Table(table)) = doc.blocks.first() else {
+            panic!("expected table, got {:?}", doc.blocks);
+        };
+        assert_eq!(table.align, None);
+        assert!(
+            doc.losses.iter().any(|loss| loss.kind == "align"),
+            "{:?}",
+            doc.losses
+        );
+    }
+}
+
+#[test]
+fn table_gutters_are_kept() {
+    let doc = typst_to_ir("#table(columns: 2, gutter: 6pt, row-gutter: 2mm, [a], [b])");
+    let Some(Block::Table(table)) = doc.blocks.first() else {
+        panic!("expected table, got {:?}", doc.blocks);
+    };
+    assert_eq!(table.gutter.as_deref(), Some("6pt"));
+    assert_eq!(table.row_gutter.as_deref(), Some("2mm"));
+    assert_eq!(table.column_gutter, None);
+    assert!(doc.losses.is_empty(), "{:?}", doc.losses);
+
+    let doc = typst_to_ir("#table(columns: 2, column-gutter: (1pt, 2pt), [a], [b])");
+    let Some(Block::Table(table)) = doc.blocks.first() else {
+        panic!("expected table, got {:?}", doc.blocks);
+    };
+    assert_eq!(table.column_gutter, None);
+    assert_eq!(doc.losses.len(), 1);
+    assert_eq!(doc.losses[0].kind, "gutter");
+}
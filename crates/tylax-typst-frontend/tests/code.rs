@@ -0,0 +1,93 @@
+use tylax_ir::{Block, FigureContent, Inline};
+use tylax_typst_frontend::typst_to_ir;
+
+#[test]
+fn fenced_and_called_raw_keep_their_language() {
+    let doc = typst_to_ir(
+        "```rust\nfn main() {\n\n    run();\n}\n```\n\n#raw(\"a = 1\", lang: \"python\", block: true)",
+    );
+    assert_eq!(
+        doc.blocks[..2],
+        [
+            Block::CodeBlock {
+                lang: Some("rust".to_string()),
+                content: "fn main() {\n\n    run();\n}".to_string(),
+            },
+            Block::CodeBlock {
+                lang: Some("python".to_string()),
+                content: "a = 1".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn inline_raw_stays_in_the_paragraph() {
+    let doc = typst_to_ir("Call `run()` or ```rust x``` here.");
+    assert_eq!(
+        doc.blocks,
+        vec![Block::Paragraph(vec![
+            Inline::text("Call "),
+            Inline::Code {
+                lang: None,
+                content: "run()".to_string(),
+            },
+            Inline::text(" or "),
+            Inline::Code {
+                lang: Some("rust".to_string()),
+                content: "x".to_string(),
+            },
+            Inline::text(" here."),
+        ])]
+    );
+}
+
+#[test]
+fn raw_calls_in_running_text_are_inline() {
+    let doc = typst_to_ir("Use #raw(\"f(x)\", lang: \"py\") here.\n\n#raw(\"g()\", block: false)");
+    assert_eq!(
+        doc.blocks,
+        vec![
+            Block::Paragraph(vec![
+                Inline::text("Use "),
+                Inline::Code {
+                    lang: Some("py".to_string()),
+                    content: "f(x)".to_string(),
+                },
+                Inline::text(" here."),
+            ]),
+            Block::Paragraph(vec![Inline::Code {
+                lang: None,
+                content: "g()".to_string(),
+            }]),
+        ]
+    );
+}
+
+#[test]
+fn figures_of_code_keep_the_code() {
+    let doc = typst_to_ir(
+        "#figure(\n```py\nx = 1\n```,\ncaption: [Setup])\n\n#figure(raw(\"y = 2\", block: true), caption: [More])",
+    );
+    let code: Vec<&FigureContent> = doc
+        .blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Figure(figure) => Some(&figure.content),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        code,
+        [
+            &FigureContent::Raw(vec![Block::CodeBlock {
+                lang: Some("py".to_string()),
+                content: "x = 1".to_string(),
+            }]),
+            &FigureContent::Raw(vec![Block::CodeBlock {
+                lang: None,
+                content: "y = 2".to_string(),
+            }]),
+        ]
+    );
+}
@@ -0,0 +1,62 @@
+use tylax_ir::{Block, OutlineTarget};
+use tylax_typst_frontend::typst_to_ir;
+
+fn heading_flags(input: &str) -> (bool, Option<bool>) {
+    let doc = typst_to_ir(input);
+    let Some(Block::Heading {
+        outlined,
+        bookmarked,
+        ..
+    }) = doc.blocks.first()
+    else {
+        panic!("expected heading, got {:?}", doc.blocks);
+    };
+    (*outlined, *bookmarked)
+}
+
+#[test]
+fn heading_outline_and_bookmark_flags() {
+    assert_eq!(heading_flags("= Intro"), (true, None));
+    assert_eq!(heading_flags("#heading[Intro]"), (true, None));
+    assert_eq!(
+        heading_flags("#heading(outlined: false)[Intro]"),
+        (false, None)
+    );
+    assert_eq!(
+        heading_flags("#heading(outlined: false, bookmarked: true)[Intro]"),
+        (false, Some(true))
+    );
+    assert_eq!(
+        heading_flags("#heading(bookmarked: auto)[Intro]"),
+        (true, None)
+    );
+}
+
+#[test]
+fn outline_targets_pick_the_list() {
+    let target = |input: &str| {
+        let doc = typst_to_ir(input);
+        assert!(doc.losses.is_empty(), "{:?}", doc.losses);
+        match doc.blocks.first() {
+            Some(Block::Outline { target, .. }) => *target,
+            other => panic!("expected outline, got {:?}", other),
+        }
+    };
+    assert_eq!(target("#outline()"), OutlineTarget::Headings);
+    assert_eq!(target("#outline(target: heading)"), OutlineTarget::Headings);
+    assert_eq!(target("#outline(target: figure)"), OutlineTarget::Figures);
+    assert_eq!(
+        target("#outline(target: figure.where(kind: image))"),
+        OutlineTarget::Figures
+    );
+    assert_eq!(
+        target("#outline(title: [Tables], target: figure.where(kind: table))"),
+        OutlineTarget::Tables
+    );
+    assert_eq!(
+        target("#outline(target: figure.where(kind: raw))"),
+        OutlineTarget::Listings
+    );
+    let doc = typst_to_ir("#outline(target: figure.where(kind: \"algo\"))");
+    assert_eq!(doc.losses.len(), 1);
+}
@@ -0,0 +1,39 @@
+use tylax_ir::{Block, BlockHints};
+use tylax_typst_frontend::typst_to_ir;
+
+#[test]
+fn metadata_hints_attach_to_the_labelled_block() {
+    let doc = typst_to_ir(
+        "#figure(image(\"a.png\"), caption: [A]) <fig:a>\n#metadata((tylax: (placement: \"tb\", wide: true))) <tylax:fig:a>\n\nText.",
+    );
+    assert!(matches!(doc.blocks[0], Block::Figure(_)));
+    assert_eq!(
+        doc.block_hints(0),
+        Some(&BlockHints {
+            placement: Some("tb".to_string()),
+            wide: true,
+        })
+    );
+    assert!(doc
+        .blocks
+        .iter()
+        .all(|block| !format!("{:?}", block).contains("tylax")));
+}
+
+#[test]
+fn no_convert_keeps_source_and_unknown_targets_are_reported() {
+    let doc = typst_to_ir(
+        "#table(columns: 2, [a], [b]) <tab:x>\n#metadata((tylax: (no-convert: true))) <tylax:tab:x>\n#metadata((tylax: (wide: true))) <tylax:missing>",
+    );
+    assert_eq!(
+        doc.blocks[0],
+        Block::Unsupported {
+            source: "#table(columns: 2, [a], [b]) <tab:x>".to_string(),
+            reason: "marked no-convert".to_string(),
+        }
+    );
+    assert!(doc
+        .losses
+        .iter()
+        .any(|loss| loss.kind == "hint" && loss.message.contains("<missing>")));
+}
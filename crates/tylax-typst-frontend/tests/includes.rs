@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use tylax_ir::{Block, Inline, Severity};
+use tylax_typst_frontend::{
+    included_files, typst_to_ir, typst_to_ir_with, IncludeResolver, IncludeStrategy,
+    TypstFrontendOptions,
+};
+
+fn resolver(files: &[(&str, &str)]) -> IncludeResolver {
+    let files: HashMap<String, String> = files
+        .iter()
+        .map(|(path, source)| (path.to_string(), source.to_string()))
+        .collect();
+    IncludeResolver::new(move |path| files.get(path).cloned())
+}
+
+/// The text of each heading and paragraph, without empty paragraphs.
+fn texts(blocks: &[Block]) -> Vec<String> {
+    blocks
+        .iter()
+        .map(|block| match block {
+            Block::Heading { content, .. } | Block::Paragraph(content) => content
+                .iter()
+                .map(|inline| match inline {
+                    Inline::Text(text) | Inline::RawLatex(text) => text.as_str(),
+                    _ => " ",
+                })
+                .collect(),
+            _ => String::new(),
+        })
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+        .collect()
+}
+
+#[test]
+fn included_files_are_converted_in_place() {
+    let options = TypstFrontendOptions {
+        include_resolver: Some(resolver(&[
+            (
+                "chapters/intro.typ",
+                "= Intro\nHello.\n#include \"../shared/end.typ\"",
+            ),
+            ("shared/end.typ", "The end."),
+        ])),
+        ..TypstFrontendOptions::default()
+    };
+    let doc = typst_to_ir_with("Before.\n#include \"chapters/intro.typ\"\nAfter.", &options);
+    assert_eq!(
+        texts(&doc.blocks),
+        ["Before.", "Intro", "Hello.", "The end.", "After."]
+    );
+    assert!(doc.losses.is_empty(), "{:?}", doc.losses);
+}
+
+#[test]
+fn included_files_can_be_set_as_input() {
+    let options = TypstFrontendOptions {
+        include_strategy: IncludeStrategy::Input,
+        include_path: Some("chapters/intro.typ".to_string()),
+        ..TypstFrontendOptions::default()
+    };
+    let doc = typst_to_ir_with("#include \"methods.typ\"", &options);
+    assert_eq!(texts(&doc.blocks), ["\\input{chapters/methods}"]);
+}
+
+#[test]
+fn unreadable_and_cyclic_includes_are_losses() {
+    let doc = typst_to_ir("A\n#include \"missing.typ\"");
+    assert_eq!(texts(&doc.blocks), ["A"]);
+    let losses: Vec<_> = doc
+        .losses
+        .iter()
+        .map(|loss| (&*loss.kind, loss.severity))
+        .collect();
+    assert_eq!(losses, [("include", Severity::Error)]);
+    assert!(doc.losses[0].span.is_some());
+
+    let options = TypstFrontendOptions {
+        include_resolver: Some(resolver(&[("a.typ", "A\n#include \"a.typ\"")])),
+        ..TypstFrontendOptions::default()
+    };
+    let doc = typst_to_ir_with("#include \"a.typ\"", &options);
+    assert_eq!(texts(&doc.blocks), ["A"]);
+    assert_eq!(doc.losses.len(), 1);
+    assert_eq!(doc.losses[0].message, "a.typ: a.typ includes itself");
+}
+
+#[test]
+fn included_files_are_listed_relative_to_the_main_file() {
+    let input = "#include \"a.typ\"\n#block[#include \"../b.typ\"]\n#include \"/c.typ\"\n\
+                 #include \"a.typ\"";
+    assert_eq!(
+        included_files(input, Some("chapters/main.typ")),
+        ["chapters/a.typ", "b.typ", "c.typ"]
+    );
+}
@@ -0,0 +1,197 @@
+use tylax_ir::{Block, Inline, LinkTarget, MathBlock};
+use tylax_typst_frontend::typst_to_ir;
+
+#[test]
+fn text_between_quotes_merges_with_escapes() {
+    let doc = typst_to_ir("Plain words, \"quoted\" and \\#escaped here.");
+    let Some(Block::Paragraph(inlines)) = doc.blocks.first() else {
+        panic!("expected paragraph, got {:?}", doc.blocks);
+    };
+    assert_eq!(
+        inlines,
+        &vec![
+            Inline::text("Plain words, "),
+            Inline::SmartQuote { double: true },
+            Inline::text("quoted"),
+            Inline::SmartQuote { double: true },
+            Inline::text(" and #escaped here."),
+        ]
+    );
+}
+
+#[test]
+fn text_merges_across_markup_boundaries() {
+    let doc = typst_to_ir("Before *bold words* after $x$ end.");
+    let Some(Block::Paragraph(inlines)) = doc.blocks.first() else {
+        panic!("expected paragraph, got {:?}", doc.blocks);
+    };
+    assert_eq!(
+        inlines,
+        &vec![
+            Inline::text("Before "),
+            Inline::Strong(vec![Inline::text("bold words")]),
+            Inline::text(" after "),
+            Inline::Math("x".to_string()),
+            Inline::text(" end."),
+        ]
+    );
+}
+
+#[test]
+fn imported_unit_and_chemistry_calls_are_lowered() {
+    let doc = typst_to_ir(
+        "#import \"@preview/unify:0.7.1\": qty, num\n#import \"@preview/whalogen:0.2.0\": ce\n#qty(\"1.5\", \"m/s\") and #num(3) of #ce(\"H2O\")",
+    );
+    let Some(Block::Paragraph(inlines)) = doc.blocks.first() else {
+        panic!("expected paragraph, got {:?}", doc.blocks);
+    };
+    assert_eq!(
+        inlines,
+        &vec![
+            Inline::Quantity {
+                value: Some("1.5".to_string()),
+                unit: Some("m/s".to_string()),
+            },
+            Inline::text(" and "),
+            Inline::Quantity {
+                value: Some("3".to_string()),
+                unit: None,
+            },
+            Inline::text(" of "),
+            Inline::Chemical("H2O".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn mitex_calls_keep_their_latex() {
+    let doc = typst_to_ir(
+        "#import \"@preview/mitex:0.2.4\": *\n\
+         Inline #mi(`\\alpha^2`) here.\n\n\
+         #mitex(`\\frac{a}{b} \\\\ c`) <eq:frac>\n",
+    );
+    assert_eq!(
+        doc.blocks[..2],
+        [
+            Block::Paragraph(vec![
+                Inline::text("Inline "),
+                Inline::LatexMath("\\alpha^2".to_string()),
+                Inline::text(" here."),
+            ]),
+            Block::MathBlock(MathBlock {
+                content: "\\frac{a}{b} \\\\ c".to_string(),
+                label: Some("eq:frac".to_string()),
+                latex: true,
+            }),
+        ]
+    );
+}
+
+#[test]
+fn text_decoration_functions_keep_their_meaning() {
+    let doc = typst_to_ir(
+        "#underline[a] #overline[b] #strike[c] #smallcaps[d] #upper[e] #lower(\"F\")",
+    );
+    let Some(Block::Paragraph(inlines)) = doc.blocks.first() else {
+        panic!("expected paragraph, got {:?}", doc.blocks);
+    };
+    let styled: Vec<&Inline> = inlines
+        .iter()
+        .filter(|inline| !matches!(inline, Inline::Text(_)))
+        .collect();
+    assert_eq!(
+        styled,
+        vec![
+            &Inline::Underline(vec![Inline::text("a")]),
+            &Inline::Overline(vec![Inline::text("b")]),
+            &Inline::Strike(vec![Inline::text("c")]),
+            &Inline::SmallCaps(vec![Inline::text("d")]),
+            &Inline::Uppercase(vec![Inline::text("e")]),
+            &Inline::Lowercase(vec![Inline::text("F")]),
+        ]
+    );
+}
+
+#[test]
+fn footnotes_flatten_what_a_footnote_cannot_hold() {
+    let doc = typst_to_ir("A#footnote[Items:\n- one\n- two\nand #footnote[inner].] b");
+    let Some(Block::Paragraph(inlines)) = doc.blocks.first() else {
+        panic!("expected paragraph, got {:?}", doc.blocks);
+    };
+    let Some(Inline::Footnote(note)) = inlines.get(1) else {
+        panic!("expected footnote, got {:?}", inlines);
+    };
+    assert!(!note
+        .iter()
+        .any(|inline| matches!(inline, Inline::Footnote(_))));
+    assert!(matches!(note.last(), Some(Inline::Text(text)) if text.ends_with("(inner).")));
+    let messages: Vec<&str> = doc
+        .losses
+        .iter()
+        .filter(|loss| loss.kind == "footnote")
+        .map(|loss| loss.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "list item inside a footnote was set as text",
+            "list item inside a footnote was set as text",
+            "footnote inside a footnote was set in parentheses",
+        ]
+    );
+}
+
+#[test]
+fn smart_quotes_are_kept_apart_from_text() {
+    let doc = typst_to_ir("\"Don't,\" she said. \\\"raw\\\"");
+    let Some(Block::Paragraph(inlines)) = doc.blocks.first() else {
+        panic!("expected paragraph, got {:?}", doc.blocks);
+    };
+    assert_eq!(
+        inlines,
+        &vec![
+            Inline::SmartQuote { double: true },
+            Inline::text("Don"),
+            Inline::SmartQuote { double: false },
+            Inline::text("t,"),
+            Inline::SmartQuote { double: true },
+            Inline::text(" she said. \"raw\""),
+        ]
+    );
+}
+
+#[test]
+fn links_know_their_target() {
+    let doc = typst_to_ir(
+        "#link(\"https://typst.app\")[Typst] #link(\"mailto:me@example.com\") \
+         #link(<intro>)[the intro] #link(<intro>)",
+    );
+    let Some(Block::Paragraph(inlines)) = doc.blocks.first() else {
+        panic!("expected paragraph, got {:?}", doc.blocks);
+    };
+    let links: Vec<_> = inlines
+        .iter()
+        .filter(|inline| !matches!(inline, Inline::Text(_)))
+        .collect();
+    assert_eq!(
+        links,
+        vec![
+            &Inline::Link {
+                text: vec![Inline::text("Typst")],
+                url: "https://typst.app".to_string(),
+                target: LinkTarget::Url,
+            },
+            &Inline::Link {
+                text: vec![Inline::text("me@example.com")],
+                url: "me@example.com".to_string(),
+                target: LinkTarget::Email,
+            },
+            &Inline::Link {
+                text: vec![Inline::text("the intro")],
+                url: "intro".to_string(),
+                target: LinkTarget::Label,
+            },
+            &Inline::Ref("intro".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,132 @@
+use tylax_ir::{Block, FigureContent, Inline, Side};
+use tylax_typst_frontend::typst_to_ir;
+
+#[test]
+fn table_label_is_attached_to_table() {
+    let input = "#table(columns: 2, [A], [B]) <tab:sample>\n\nAfter.";
+    let doc = typst_to_ir(input);
+    let Some(Block::Table(table)) = doc.blocks.first() else {
+        panic!("expected table, got {:?}", doc.blocks);
+    };
+    assert_eq!(table.label.as_deref(), Some("tab:sample"));
+    assert!(matches!(doc.blocks.get(1), Some(Block::Paragraph(_))));
+}
+
+#[test]
+fn label_with_trailing_text_keeps_text() {
+    let input = "#theorem[Claim.] <thm:a> Then more text.";
+    let doc = typst_to_ir(input);
+    let Some(Block::Environment(env)) = doc.blocks.first() else {
+        panic!("expected environment, got {:?}", doc.blocks);
+    };
+    assert_eq!(env.label.as_deref(), Some("thm:a"));
+    let Some(Block::Paragraph(inlines)) = doc.blocks.get(1) else {
+        panic!("trailing text was dropped: {:?}", doc.blocks);
+    };
+    assert!(inlines
+        .iter()
+        .any(|inline| matches!(inline, Inline::Text(text) if text.contains("Then more text"))));
+}
+
+#[test]
+fn heading_label_on_next_line_is_attached() {
+    let input = "= Intro\n<sec:intro>\n\nText.";
+    let doc = typst_to_ir(input);
+    assert_eq!(doc.blocks.len(), 2);
+    let Block::Heading { label, .. } = &doc.blocks[0] else {
+        panic!("expected heading, got {:?}", doc.blocks);
+    };
+    assert_eq!(label.as_deref(), Some("sec:intro"));
+}
+
+#[test]
+fn consecutive_blocks_keep_their_order() {
+    let input = "#figure(image(\"a.png\"), caption: [A]) <fig:a>\n#table(columns: 1, [x]) <tab:b>\n= Next";
+    let doc = typst_to_ir(input);
+    let kinds: Vec<&str> = doc
+        .blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Figure(_) => Some("figure"),
+            Block::Table(_) => Some("table"),
+            Block::Heading { .. } => Some("heading"),
+            Block::Paragraph(inlines)
+                if inlines
+                    .iter()
+                    .all(|inline| matches!(inline, Inline::Text(text) if text.trim().is_empty())) =>
+            {
+                None
+            }
+            _ => Some("other"),
+        })
+        .collect();
+    assert_eq!(kinds, vec!["figure", "table", "heading"]);
+}
+
+#[test]
+fn grid_of_figures_becomes_subfigures() {
+    let input = concat!(
+        "#figure(\n",
+        "  grid(columns: 2,\n",
+        "    [#figure(image(\"a.png\"), caption: [Left]) <fig:a>],\n",
+        "    figure(image(\"b.png\"), caption: [Right]),\n",
+        "  ),\n",
+        "  caption: [Both],\n",
+        ") <fig:both>",
+    );
+    let doc = typst_to_ir(input);
+    let Some(Block::Figure(figure)) = doc.blocks.first() else {
+        panic!("expected figure, got {:?}", doc.blocks);
+    };
+    assert_eq!(figure.label.as_deref(), Some("fig:both"));
+    let FigureContent::SubFigures {
+        figures: subfigures,
+        columns,
+    } = &figure.content
+    else {
+        panic!("expected subfigures, got {:?}", figure.content);
+    };
+    assert_eq!(*columns, 2);
+    let labels: Vec<Option<&str>> = subfigures.iter().map(|sub| sub.label.as_deref()).collect();
+    assert_eq!(labels, [Some("fig:a"), None]);
+    assert_eq!(subfigures[1].caption, Some(vec![Inline::text("Right")]));
+    assert!(matches!(subfigures[0].content, FigureContent::Image(_)));
+
+    let mixed = "#figure(grid(columns: 2, [#figure(image(\"a.png\"))], [Text]))";
+    let Some(Block::Figure(figure)) = typst_to_ir(mixed).blocks.first().cloned() else {
+        panic!("expected figure");
+    };
+    assert!(!matches!(figure.content, FigureContent::SubFigures { .. }));
+
+    let stacked = "#figure(grid([#figure(image(\"a.png\"))], [#figure(image(\"b.png\"))]))";
+    let Some(Block::Figure(figure)) = typst_to_ir(stacked).blocks.first().cloned() else {
+        panic!("expected figure");
+    };
+    assert!(matches!(
+        figure.content,
+        FigureContent::SubFigures { columns: 1, .. }
+    ));
+}
+
+#[test]
+fn wrapped_and_placed_figures_record_their_side() {
+    let input = concat!(
+        "#wrap-content(\n",
+        "  figure(image(\"plot.png\", width: 40%), caption: [Plot]),\n",
+        "  align: top + right,\n",
+        ")[Text beside the plot.]\n\n",
+        "#place(left, figure(image(\"logo.png\", width: 3cm)))\n\n",
+        "#place(top + right, float: true, figure(image(\"c.png\")))",
+    );
+    let doc = typst_to_ir(input);
+    let sides: Vec<Option<Side>> = doc
+        .blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Figure(figure) => Some(figure.wrap),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(sides, [Some(Side::Right), Some(Side::Left)], "{:?}", doc.blocks);
+    assert!(matches!(doc.blocks.get(1), Some(Block::Paragraph(_))));
+}
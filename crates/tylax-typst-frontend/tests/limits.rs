@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use tylax_ir::{Block, CancellationToken, ConversionLimits, Document, Severity, Span};
+use tylax_typst_frontend::{typst_to_ir_with, TypstFrontendOptions};
+
+fn convert(input: &str, limits: ConversionLimits, cancel: Option<CancellationToken>) -> Document {
+    let options = TypstFrontendOptions {
+        limits,
+        cancel,
+        ..TypstFrontendOptions::default()
+    };
+    typst_to_ir_with(input, &options)
+}
+
+fn paragraphs(count: usize) -> String {
+    (0..count)
+        .map(|idx| format!("Paragraph {idx}."))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn limits_of(max_blocks: usize) -> ConversionLimits {
+    ConversionLimits {
+        max_blocks: Some(max_blocks),
+        ..ConversionLimits::default()
+    }
+}
+
+fn limit_losses(doc: &Document) -> usize {
+    doc.losses
+        .iter()
+        .filter(|loss| loss.kind == "limit" && loss.severity == Severity::Error)
+        .count()
+}
+
+#[test]
+fn block_limit_keeps_the_blocks_before_it() {
+    let input = paragraphs(20);
+    let doc = convert(&input, limits_of(5), None);
+    assert!(
+        doc.blocks.len() > 5 && doc.blocks.len() < 20,
+        "{:?}",
+        doc.blocks
+    );
+    assert!(matches!(doc.blocks.first(), Some(Block::Paragraph(_))));
+    assert_eq!(limit_losses(&doc), 1);
+    let span = doc.losses[0].span.expect("limit span");
+    assert_eq!(span.end, input.len());
+    assert!(input[span.start..].starts_with("Paragraph"));
+
+    // A document within the limit converts without a loss.
+    let doc = convert(&paragraphs(5), limits_of(5), None);
+    assert_eq!(doc.blocks.len(), 5);
+    assert_eq!(limit_losses(&doc), 0);
+}
+
+#[test]
+fn long_input_is_cut_at_a_line_break() {
+    let input = paragraphs(10);
+    let limits = ConversionLimits {
+        max_input_bytes: Some(40),
+        ..ConversionLimits::default()
+    };
+    // The line break closing the third paragraph lies past byte 40.
+    let doc = convert(&input, limits, None);
+    assert_eq!(doc.blocks.len(), 2);
+    assert_eq!(limit_losses(&doc), 1);
+    let cut = input[..40].rfind('\n').unwrap() + 1;
+    assert_eq!(doc.losses[0].span, Some(Span::new(cut, input.len())));
+}
+
+#[test]
+fn cancelled_and_timed_out_conversions_stop() {
+    let input = paragraphs(10);
+    let token = CancellationToken::new();
+    token.cancel();
+    let doc = convert(&input, ConversionLimits::default(), Some(token));
+    assert!(doc.blocks.is_empty());
+    assert_eq!(limit_losses(&doc), 1);
+
+    let limits = ConversionLimits {
+        timeout: Some(Duration::ZERO),
+        ..ConversionLimits::default()
+    };
+    let doc = convert(&input, limits, None);
+    assert!(doc.blocks.is_empty());
+    assert!(doc.losses[0].message.contains("took longer"));
+}
+
+#[test]
+fn macro_expansion_runs_within_the_budget() {
+    let input = "#let cell(x) = [#x, ]\n#for x in range(1000000) [#cell(x)]\n\nAfter.";
+    let limits = ConversionLimits {
+        timeout: Some(Duration::from_millis(50)),
+        ..ConversionLimits::default()
+    };
+    let started = std::time::Instant::now();
+    let doc = convert(input, limits, None);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(limit_losses(&doc), 1);
+}
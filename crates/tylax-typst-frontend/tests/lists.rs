@@ -0,0 +1,48 @@
+use tylax_ir::{Block, Inline, ListKind};
+use tylax_typst_frontend::typst_to_ir;
+
+fn para(text: &str) -> Block {
+    Block::Paragraph(vec![Inline::text(text)])
+}
+
+#[test]
+fn nested_items_keep_hierarchy() {
+    let input = "- top\n  - inner a\n  - inner b\n    + deep\n- next";
+    let doc = typst_to_ir(input);
+    let deep = Block::List {
+        kind: ListKind::Ordered,
+        items: vec![vec![para("deep")]],
+    };
+    let inner = Block::List {
+        kind: ListKind::Unordered,
+        items: vec![vec![para("inner a")], vec![para("inner b"), deep]],
+    };
+    assert_eq!(
+        doc.blocks,
+        vec![Block::List {
+            kind: ListKind::Unordered,
+            items: vec![vec![para("top"), inner], vec![para("next")]],
+        }]
+    );
+}
+
+#[test]
+fn term_items_form_a_term_list() {
+    let input = "/ Term: first\n/ *Other*: second\n  - nested\n\nAfter.";
+    let doc = typst_to_ir(input);
+    let Some(Block::TermList(items)) = doc.blocks.first() else {
+        panic!("expected term list, got {:?}", doc.blocks);
+    };
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].term, vec![Inline::text("Term")]);
+    assert_eq!(items[0].description, vec![para("first")]);
+    assert_eq!(
+        items[1].term,
+        vec![Inline::Strong(vec![Inline::text("Other")])]
+    );
+    assert!(matches!(
+        items[1].description.as_slice(),
+        [Block::Paragraph(_), Block::List { .. }]
+    ));
+    assert_eq!(doc.blocks.get(1), Some(&para("After.")));
+}
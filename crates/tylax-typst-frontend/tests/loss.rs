@@ -1,3 +1,4 @@
+use tylax_ir::{Block, Severity};
 use tylax_typst_frontend::typst_to_ir;
 
 #[test]
@@ -34,3 +35,54 @@ fn set_rule_unsupported_reports_loss() {
     let doc = typst_to_ir(input);
     assert!(doc.losses.iter().any(|l| l.kind == "set-rule"));
 }
+
+#[test]
+fn standalone_unsupported_call_keeps_source() {
+    let input = "Intro text.\n\n#rotate(45deg)[Turned *text*]\n\nOutro.";
+    let doc = typst_to_ir(input);
+    assert_eq!(doc.blocks.len(), 3);
+    assert_eq!(
+        doc.blocks[1],
+        Block::Unsupported {
+            source: "#rotate(45deg)[Turned *text*]".to_string(),
+            reason: "rotate not supported".to_string(),
+        }
+    );
+    assert!(doc.losses.iter().any(|l| l.kind == "unsupported"));
+}
+
+#[test]
+fn unsupported_call_in_text_stays_inline() {
+    let input = "Some #place(top)[floating] text.";
+    let doc = typst_to_ir(input);
+    assert!(matches!(doc.blocks.as_slice(), [Block::Paragraph(_)]));
+    assert!(doc.losses.iter().any(|l| l.kind == "unsupported"));
+}
+
+#[test]
+fn losses_are_classified_by_severity() {
+    let input = "#set figure(caption: none)\n\n#metadata((tylax: (wide: true))) <tylax:missing>";
+    let doc = typst_to_ir(input);
+    let severity = |kind: &str| doc.losses.iter().find(|l| l.kind == kind).map(|l| l.severity);
+    assert_eq!(severity("set-rule"), Some(Severity::Warning));
+    assert_eq!(severity("hint"), Some(Severity::Info));
+
+    let input = "#set page(header: [#counter(page).final()])\n\n#outline(target: image)\n\n\
+                 #rotate(45deg)[Turned]";
+    let doc = typst_to_ir(input);
+    for kind in ["page-header", "outline", "unsupported"] {
+        let loss = doc.losses.iter().find(|l| l.kind == kind);
+        assert_eq!(loss.map(|l| l.severity), Some(Severity::Error), "{kind}");
+    }
+}
+
+#[test]
+fn losses_with_a_known_fix_suggest_it() {
+    let input = "#outline(target: image)\n\n#table(columns: 2, gutter: 1em + 2pt, [A], [B])";
+    let doc = typst_to_ir(input);
+    for kind in ["outline", "gutter"] {
+        let loss = doc.losses.iter().find(|l| l.kind == kind).unwrap();
+        assert!(loss.suggestion.is_some(), "{kind} loss without a suggestion");
+    }
+    assert!(doc.losses.iter().all(|l| l.kind != "unsupported"));
+}
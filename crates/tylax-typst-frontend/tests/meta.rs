@@ -0,0 +1,169 @@
+use tylax_ir::{Author, Block, Inline, PageDecoration, PageMargin};
+use tylax_typst_frontend::typst_to_ir;
+
+#[test]
+fn set_rules_fill_document_meta() {
+    let doc = typst_to_ir(
+        "#set page(paper: \"a4\", margin: (x: 2cm, y: 3cm))\n\
+         #set text(size: 11pt, lang: \"de\")\n\
+         #set heading(numbering: \"1.a\")\n\
+         #set par(justify: false)\n\n\
+         = Intro\n",
+    );
+    let meta = &doc.meta;
+    assert_eq!(meta.paper.as_deref(), Some("a4"));
+    assert_eq!(
+        meta.margin,
+        PageMargin {
+            top: Some("3cm".to_string()),
+            bottom: Some("3cm".to_string()),
+            left: Some("2cm".to_string()),
+            right: Some("2cm".to_string()),
+        }
+    );
+    assert_eq!(meta.font_size.as_deref(), Some("11pt"));
+    assert_eq!(meta.lang.as_deref(), Some("de"));
+    assert_eq!(meta.heading_numbering.as_deref(), Some("1.a"));
+    assert_eq!(meta.justify, Some(false));
+}
+
+#[test]
+fn later_set_rules_override_earlier_ones() {
+    let doc = typst_to_ir(
+        "#set page(margin: 1in)\n#set heading(numbering: \"1.\")\n\
+         #set heading(numbering: none)\n",
+    );
+    assert_eq!(doc.meta.margin.left.as_deref(), Some("1in"));
+    assert_eq!(doc.meta.margin.bottom.as_deref(), Some("1in"));
+    assert_eq!(doc.meta.heading_numbering.as_deref(), Some(""));
+}
+
+#[test]
+fn whole_document_column_wrappers_set_the_columns() {
+    let columns = |source: &str| typst_to_ir(source).meta.columns;
+    assert_eq!(columns("#show: doc => columns(2, doc)\n\nText."), Some(2));
+    assert_eq!(columns("#show: columns.with(3, gutter: 1em)\n\nText."), Some(3));
+    assert_eq!(columns("#set page(columns: 2)\n\nText."), Some(2));
+    assert_eq!(columns("#show: doc => columns(2, [Intro])\n\nText."), None);
+    assert_eq!(columns("#columns(2)[Text.]"), None);
+    assert!(typst_to_ir("#show: doc => columns(2, doc)\n\nText.")
+        .losses
+        .is_empty());
+}
+
+#[test]
+fn page_headers_footers_and_numbering_are_read() {
+    let doc = typst_to_ir(
+        "#set page(numbering: \"1 of 1\", header: [_Draft_ #h(1fr) Report])\n\
+         #set page(footer: context [#align(right)[Page #counter(page).display()]])\n",
+    );
+    let meta = &doc.meta;
+    assert_eq!(meta.page_numbering.as_deref(), Some("1 of 1"));
+    assert_eq!(
+        meta.header,
+        Some(PageDecoration {
+            left: vec![Inline::Emph(vec![Inline::Text("Draft".to_string())])],
+            center: Vec::new(),
+            right: vec![Inline::Text("Report".to_string())],
+            even: None,
+        })
+    );
+    assert_eq!(
+        meta.footer,
+        Some(PageDecoration {
+            right: vec![
+                Inline::Text("Page ".to_string()),
+                Inline::PageNumber("1 of 1".to_string()),
+            ],
+            ..PageDecoration::default()
+        })
+    );
+    assert!(doc.losses.is_empty(), "{:?}", doc.losses);
+}
+
+#[test]
+fn alternating_headers_keep_even_pages_apart() {
+    let doc = typst_to_ir(
+        "#set page(header: context {\n\
+         if calc.even(here().page()) [#hydra(2) #h(1fr)] else [#h(1fr) #hydra(1)]\n\
+         })\n",
+    );
+    assert_eq!(
+        doc.meta.header,
+        Some(PageDecoration {
+            right: vec![Inline::RunningHead(1)],
+            even: Some(Box::new(PageDecoration {
+                left: vec![Inline::RunningHead(2)],
+                ..PageDecoration::default()
+            })),
+            ..PageDecoration::default()
+        })
+    );
+    assert!(doc.losses.is_empty(), "{:?}", doc.losses);
+}
+
+#[test]
+fn page_decorations_the_frontend_cannot_read_are_a_loss() {
+    let doc = typst_to_ir(
+        "#set page(numbering: none, header: [A #h(1fr) B #h(1fr) C #h(1fr) D])\n",
+    );
+    assert_eq!(doc.meta.page_numbering.as_deref(), Some(""));
+    assert_eq!(doc.meta.header, None);
+    assert!(doc.losses.iter().any(|loss| loss.kind == "page-header"));
+}
+
+#[test]
+fn template_arguments_fill_front_matter() {
+    let doc = typst_to_ir(
+        "#show: ieee.with(\n\
+         title: [A _Typesetting_ System],\n\
+         authors: (\n\
+         (name: \"Martin Haug\", organization: [Typst GmbH], location: [Berlin],\n\
+         email: \"haug@typst.app\"),\n\
+         (name: \"Laurenz Mädje\", affiliation: (institution: \"Typst\", city: \"Berlin\")),\n\
+         ),\n\
+         abstract: [We study it.],\n\
+         )\n\n\
+         = Introduction\n",
+    );
+    let meta = &doc.meta;
+    assert_eq!(
+        meta.title,
+        Some(vec![
+            Inline::text("A "),
+            Inline::Emph(vec![Inline::text("Typesetting")]),
+            Inline::text(" System"),
+        ])
+    );
+    assert_eq!(
+        meta.authors,
+        [
+            Author {
+                name: "Martin Haug".to_string(),
+                affiliation: Some("Typst GmbH, Berlin".to_string()),
+                email: Some("haug@typst.app".to_string()),
+            },
+            Author {
+                name: "Laurenz Mädje".to_string(),
+                affiliation: Some("Typst, Berlin".to_string()),
+                email: None,
+            },
+        ]
+    );
+    assert_eq!(
+        meta.abstract_content,
+        [Block::Paragraph(vec![Inline::text("We study it.")])]
+    );
+    assert!(matches!(doc.blocks.first(), Some(Block::Heading { .. })));
+}
+
+#[test]
+fn closures_and_single_authors_are_read_too() {
+    let doc = typst_to_ir(
+        "#show: doc => arkheion(title: \"Notes\", author: \"Ada\", date: \"May 2024\", doc)\n",
+    );
+    assert_eq!(doc.meta.title, Some(vec![Inline::text("Notes")]));
+    assert_eq!(doc.meta.authors.len(), 1);
+    assert_eq!(doc.meta.authors[0].name, "Ada");
+    assert_eq!(doc.meta.date.as_deref(), Some("May 2024"));
+}
@@ -0,0 +1,51 @@
+use tylax_ir::Severity;
+use tylax_typst_frontend::typst_to_ir;
+
+/// Shapes of generated input that used to overflow the stack while lowering.
+/// Depths stay within what the Typst parser itself handles on a test thread.
+fn deep_inputs() -> Vec<(&'static str, String)> {
+    let depth = 800;
+    vec![
+        (
+            "boxes",
+            format!("{}x{}", "#box[".repeat(depth), "]".repeat(depth)),
+        ),
+        (
+            "quotes",
+            format!("{}x{}", "#quote[".repeat(depth), "]".repeat(depth)),
+        ),
+        (
+            "strong-emph",
+            format!("{}x{}", "*a _b ".repeat(depth), "_ a*".repeat(depth)),
+        ),
+        (
+            "lists",
+            (0..depth / 2)
+                .map(|level| format!("{}- item\n", "  ".repeat(level)))
+                .collect(),
+        ),
+    ]
+}
+
+#[test]
+fn deeply_nested_input_reports_loss() {
+    for (name, input) in deep_inputs() {
+        let doc = typst_to_ir(&input);
+        assert!(!doc.blocks.is_empty(), "{name}: no blocks");
+        assert!(
+            doc.losses
+                .iter()
+                .any(|loss| loss.kind == "nesting" && loss.severity == Severity::Error),
+            "{name}: missing nesting loss in {:?}",
+            doc.losses
+        );
+    }
+}
+
+#[test]
+fn shallow_nesting_is_kept() {
+    let input = format!("{}deep{}", "#box[".repeat(8), "]".repeat(8));
+    let doc = typst_to_ir(&input);
+    assert!(doc.losses.iter().all(|loss| loss.kind != "nesting"));
+    assert!(format!("{:?}", doc.blocks).contains("deep"));
+}
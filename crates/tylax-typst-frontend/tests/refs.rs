@@ -0,0 +1,159 @@
+use tylax_ir::{Block, Citation, CiteForm, Inline, ReferenceIssueKind};
+use tylax_typst_frontend::{typst_to_ir, typst_to_ir_with, TypstFrontendOptions};
+
+fn first_paragraph(blocks: &[Block]) -> &[Inline] {
+    match blocks.first() {
+        Some(Block::Paragraph(inlines)) => inlines,
+        other => panic!("expected paragraph, got {:?}", other),
+    }
+}
+
+#[test]
+fn refrange_call_becomes_ref_range() {
+    let doc = typst_to_ir("See #refrange(<fig:a>, <fig:c>).");
+    assert_eq!(
+        first_paragraph(&doc.blocks),
+        &[
+            Inline::text("See "),
+            Inline::RefRange {
+                from: "fig:a".to_string(),
+                to: "fig:c".to_string(),
+            },
+            Inline::text("."),
+        ]
+    );
+}
+
+#[test]
+fn ref_range_function_name_is_configurable() {
+    let options = TypstFrontendOptions {
+        ref_range_functions: vec!["figs".to_string()],
+        ..TypstFrontendOptions::default()
+    };
+    let doc = typst_to_ir_with("#figs(<fig:a>, <fig:b>)", &options);
+    assert_eq!(
+        first_paragraph(&doc.blocks),
+        &[Inline::RefRange {
+            from: "fig:a".to_string(),
+            to: "fig:b".to_string(),
+        }]
+    );
+    let default = typst_to_ir("#figs(<fig:a>, <fig:b>)");
+    assert!(!format!("{:?}", default.blocks).contains("RefRange"));
+}
+
+#[test]
+fn bibliography_full_and_title_are_kept() {
+    let bibliography = |source: &str| match typst_to_ir(source).blocks.first() {
+        Some(Block::Bibliography { full, title, .. }) => (*full, title.clone()),
+        other => panic!("expected bibliography, got {:?}", other),
+    };
+    assert_eq!(
+        bibliography("#bibliography(\"refs.bib\", full: true, title: none)"),
+        (true, Some(Vec::new()))
+    );
+    assert_eq!(
+        bibliography("#bibliography(\"refs.bib\", title: [Sources])"),
+        (false, Some(vec![Inline::text("Sources")]))
+    );
+    assert_eq!(bibliography("#bibliography(\"refs.bib\")"), (false, None));
+}
+
+#[test]
+fn cite_forms_and_notes_are_kept() {
+    let cite = |source: &str| first_paragraph(&typst_to_ir(source).blocks).to_vec();
+    let citation = |form, prenote: Option<&str>, postnote: Option<&str>| {
+        vec![Inline::Citation(Citation {
+            keys: "knuth".to_string(),
+            form,
+            prenote: prenote.map(str::to_string),
+            postnote: postnote.map(str::to_string),
+        })]
+    };
+    assert_eq!(
+        cite("#cite(<knuth>)"),
+        vec![Inline::Cite("knuth".to_string())]
+    );
+    assert_eq!(
+        cite("#cite(<knuth>, form: \"prose\")"),
+        citation(CiteForm::Prose, None, None)
+    );
+    assert_eq!(
+        cite("#cite(<knuth>, form: \"year\")"),
+        citation(CiteForm::Year, None, None)
+    );
+    assert_eq!(
+        cite("#cite(<knuth>, form: none)"),
+        citation(CiteForm::Hidden, None, None)
+    );
+    assert_eq!(
+        cite("#cite(<knuth>, supplement: \"ch. 2\")"),
+        citation(CiteForm::Normal, None, Some("ch. 2"))
+    );
+    assert_eq!(
+        cite("#cite(<knuth>, form: \"author\", supplement: \"see\", page: \"3\")"),
+        citation(CiteForm::Author, Some("see"), Some("p.~3"))
+    );
+}
+
+#[test]
+fn ref_supplements_are_kept() {
+    let supplemented = |label: &str, supplement: Vec<Inline>| Inline::SupplementedRef {
+        label: label.to_string(),
+        supplement,
+    };
+    let doc = typst_to_ir("@fig:a[Plot] and #ref(<fig:b>, supplement: none) and @fig:c");
+    assert_eq!(
+        first_paragraph(&doc.blocks),
+        &[
+            supplemented("fig:a", vec![Inline::text("Plot")]),
+            Inline::text(" and "),
+            supplemented("fig:b", Vec::new()),
+            Inline::text(" and "),
+            Inline::Ref("fig:c".to_string()),
+        ]
+    );
+    let doc = typst_to_ir("#set ref(supplement: [Fig.])\n\nSee @fig:a and @fig:b[Plot].");
+    assert_eq!(
+        first_paragraph(&doc.blocks),
+        &[
+            Inline::text("See "),
+            supplemented("fig:a", vec![Inline::text("Fig.")]),
+            Inline::text(" and "),
+            supplemented("fig:b", vec![Inline::text("Plot")]),
+            Inline::text("."),
+        ]
+    );
+    let doc = typst_to_ir("As @knuth[p. 7] shows.");
+    assert_eq!(
+        first_paragraph(&doc.blocks)[1],
+        Inline::Citation(Citation {
+            keys: "knuth".to_string(),
+            form: CiteForm::Normal,
+            prenote: None,
+            postnote: Some("p. 7".to_string()),
+        })
+    );
+}
+
+#[test]
+fn references_are_checked_against_the_labels_of_the_document() {
+    let source = "= Intro <sec:intro>\n\n\
+                  #figure(table(columns: 1, [a]), caption: [T]) <tab:t>\n\n\
+                  $ x = 1 $ <eq:one>\n\n\
+                  See @sec:intro, @tab:t, @eq:one and @eq:two.\n\n\
+                  = Again <sec:intro>\n";
+    let doc = typst_to_ir(source);
+    let issues: Vec<_> = doc
+        .check_references()
+        .into_iter()
+        .map(|issue| (issue.kind, issue.label, issue.span.is_some()))
+        .collect();
+    assert_eq!(
+        issues,
+        [
+            (ReferenceIssueKind::Duplicate, "sec:intro".to_string(), true),
+            (ReferenceIssueKind::Undefined, "eq:two".to_string(), true),
+        ]
+    );
+}
@@ -0,0 +1,56 @@
+use tylax_ir::{Block, ColumnWidth, Inline, SideBySide, Slide};
+use tylax_typst_frontend::typst_to_ir;
+
+#[test]
+fn slides_take_their_title_and_pauses() {
+    let input = "#slide[\n  = Intro\n  First point\n  #pause\n  Second point\n]\n\n\
+                 #polylux-slide(title: [Results])[Done]";
+    let doc = typst_to_ir(input);
+    let slides: Vec<&Slide> = doc
+        .blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Slide(slide) => Some(slide),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(slides.len(), 2, "{:?}", doc.blocks);
+    assert_eq!(slides[0].title, Some(vec![Inline::text("Intro")]));
+    let Some(Block::Paragraph(content)) = slides[0].blocks.first() else {
+        panic!("expected paragraph, got {:?}", slides[0].blocks);
+    };
+    assert!(content.contains(&Inline::Pause), "{content:?}");
+    assert_eq!(slides[1].title, Some(vec![Inline::text("Results")]));
+}
+
+#[test]
+fn side_by_side_columns_keep_their_widths() {
+    let doc = typst_to_ir(
+        "#slide[#side-by-side(columns: (1fr, 2fr))[Left][Right]]\n\n\
+         #slide(composer: (1fr, auto))[A][B]",
+    );
+    let columns: Vec<&SideBySide> = doc
+        .blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Slide(slide) => match slide.blocks.as_slice() {
+                [Block::SideBySide(side)] => Some(side),
+                other => panic!("expected columns, got {:?}", other),
+            },
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        columns[0].widths,
+        [
+            ColumnWidth::Fraction("1".to_string()),
+            ColumnWidth::Fraction("2".to_string())
+        ]
+    );
+    assert_eq!(columns[0].columns.len(), 2);
+    assert_eq!(
+        columns[1].widths,
+        [ColumnWidth::Fraction("1".to_string()), ColumnWidth::Auto]
+    );
+    assert_eq!(columns[1].columns.len(), 2);
+}
@@ -0,0 +1,69 @@
+use tylax_ir::Block;
+use tylax_typst_frontend::typst_to_ir;
+
+fn block_text<'a>(input: &'a str, doc: &tylax_ir::Document, idx: usize) -> &'a str {
+    let span = doc.block_span(idx).expect("block span");
+    &input[span.start..span.end]
+}
+
+#[test]
+fn top_level_blocks_have_spans() {
+    let input = "Plain *text*\n\n= Head <sec:h>\n\n- a\n- b\n\n#table(columns: 2, [A], [B])";
+    let doc = typst_to_ir(input);
+    assert_eq!(doc.spans.len(), doc.blocks.len());
+    assert_eq!(block_text(input, &doc, 0), "Plain *text*");
+    assert_eq!(block_text(input, &doc, 1), "= Head <sec:h>");
+    assert!(block_text(input, &doc, 2).starts_with("- a\n- b"));
+    assert!(matches!(doc.blocks[3], Block::Table(_)));
+    assert_eq!(block_text(input, &doc, 3), "#table(columns: 2, [A], [B])");
+}
+
+#[test]
+fn spans_survive_preprocessing() {
+    let input = "#import \"x.typ\": y\n#let name = [World]\nHello #name.\n\n= Next";
+    let doc = typst_to_ir(input);
+    assert_eq!(block_text(input, &doc, 0), "Hello #name.");
+    assert_eq!(block_text(input, &doc, 1), "= Next");
+}
+
+#[test]
+fn losses_carry_spans() {
+    let input = "= A\n\n#set grid(gutter: 1pt)\n\n#outline(target: <intro>)\n";
+    let doc = typst_to_ir(input);
+    let texts: Vec<&str> = doc
+        .losses
+        .iter()
+        .map(|loss| {
+            let span = loss.span.expect("loss span");
+            &input[span.start..span.end]
+        })
+        .collect();
+    assert_eq!(
+        texts,
+        vec!["#set grid(gutter: 1pt)", "#outline(target: <intro>)"]
+    );
+}
+
+#[test]
+fn paragraph_inlines_have_spans() {
+    let input = "Some *bold* and `code` here.\n\n= Head\n\nA $x$ end";
+    let doc = typst_to_ir(input);
+    let texts = |block: usize| -> Vec<&str> {
+        doc.inline_spans[block]
+            .iter()
+            .map(|span| &input[span.start..span.end])
+            .collect()
+    };
+    let Block::Paragraph(inlines) = &doc.blocks[0] else {
+        panic!("{:?}", doc.blocks[0]);
+    };
+    assert_eq!(inlines.len(), doc.inline_spans[0].len());
+    assert_eq!(texts(0), ["Some ", "*bold*", " and ", "`code`", " here."]);
+    assert!(doc.inline_spans[1].is_empty());
+    assert_eq!(texts(2), ["A ", "$x$", " end"]);
+    assert_eq!(
+        doc.inline_span(2, 1)
+            .map(|span| &input[span.start..span.end]),
+        Some("$x$")
+    );
+}
@@ -0,0 +1,41 @@
+use tylax_ir::{stats, HeadingNode};
+use tylax_typst_frontend::typst_to_ir;
+
+#[test]
+fn stats_count_what_the_document_holds() {
+    let doc = typst_to_ir(
+        "= Introduction\n\
+         Some *bold* words @knuth and $x^2$ more.\n\
+         == Background\n\
+         See @lamport @knuth.\n\n\
+         $ E = m c^2 $\n\n\
+         #figure(image(\"a.png\"), caption: [A plot])\n\
+         #figure(table(columns: 2, [a], [b]), caption: [Data])\n\
+         = Results\n",
+    );
+    let stats = stats(&doc);
+    let heading = |level, title: &str, children| HeadingNode {
+        level,
+        title: title.to_string(),
+        children,
+    };
+    assert_eq!(
+        stats.headings,
+        vec![
+            heading(
+                1,
+                "Introduction",
+                vec![heading(2, "Background", Vec::new())]
+            ),
+            heading(1, "Results", Vec::new()),
+        ]
+    );
+    // Headings, the two paragraphs, both captions and the table cells.
+    assert_eq!(stats.words, 3 + 5 + 1 + 2 + 1 + 2);
+    assert_eq!(stats.equations, 1);
+    assert_eq!(stats.figures, 1);
+    assert_eq!(stats.tables, 1);
+    assert_eq!(stats.citations, vec!["knuth", "lamport"]);
+    // Three headings, two paragraphs, the equation and the two figures.
+    assert_eq!(stats.blocks, 8);
+}
@@ -0,0 +1,345 @@
+//! Lengths as Typst and LaTeX write them. A [`Length`] is read from the
+//! syntax of either and written in the syntax of either, so both directions
+//! of the conversion agree on units, on how numbers are written and on what
+//! a share of the line stands for.
+//!
+//! Typst's `pt` is the PostScript point, LaTeX's a little smaller; the two
+//! are taken as equal, so that `12pt` stays `12pt` both ways.
+
+use std::ops::{Div, Mul, Neg};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Unit {
+    Pt,
+    Bp,
+    Mm,
+    Cm,
+    In,
+    Pc,
+    Sp,
+    Dd,
+    Cc,
+    Em,
+    Ex,
+    /// A share of the line width, in percent.
+    Percent,
+    /// A share of the space left over.
+    Fr,
+}
+
+impl Unit {
+    /// The unit of a LaTeX length suffix such as `pt` or `ex`.
+    pub fn parse_latex(suffix: &str) -> Option<Unit> {
+        Some(match suffix {
+            "pt" => Unit::Pt,
+            "bp" => Unit::Bp,
+            "mm" => Unit::Mm,
+            "cm" => Unit::Cm,
+            "in" => Unit::In,
+            "pc" => Unit::Pc,
+            "sp" => Unit::Sp,
+            "dd" => Unit::Dd,
+            "cc" => Unit::Cc,
+            "em" => Unit::Em,
+            "ex" => Unit::Ex,
+            _ => return None,
+        })
+    }
+
+    /// Points in one of the unit, for the absolute units.
+    fn points(self) -> Option<f64> {
+        Some(match self {
+            Unit::Pt | Unit::Bp => 1.0,
+            Unit::Mm => 72.27 / 25.4,
+            Unit::Cm => 72.27 / 2.54,
+            Unit::In => 72.27,
+            Unit::Pc => 12.0,
+            Unit::Sp => 1.0 / 65536.0,
+            Unit::Dd => 1238.0 / 1157.0,
+            Unit::Cc => 12.0 * 1238.0 / 1157.0,
+            Unit::Em | Unit::Ex | Unit::Percent | Unit::Fr => return None,
+        })
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            Unit::Pt => "pt",
+            Unit::Bp => "bp",
+            Unit::Mm => "mm",
+            Unit::Cm => "cm",
+            Unit::In => "in",
+            Unit::Pc => "pc",
+            Unit::Sp => "sp",
+            Unit::Dd => "dd",
+            Unit::Cc => "cc",
+            Unit::Em => "em",
+            Unit::Ex => "ex",
+            Unit::Percent => "%",
+            Unit::Fr => "fr",
+        }
+    }
+}
+
+/// The height of an `x` in ems, for `ex`, which Typst does not have.
+const EX_IN_EM: f64 = 0.43;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Length {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Length {
+    pub const fn new(value: f64, unit: Unit) -> Self {
+        Length { value, unit }
+    }
+
+    pub const fn pt(value: f64) -> Self {
+        Length::new(value, Unit::Pt)
+    }
+
+    /// A length as Typst writes it: `12pt`, `1.5em`, `50%` or `1fr`. A bare
+    /// number is taken as points.
+    pub fn parse_typst(raw: &str) -> Option<Length> {
+        let (value, unit) = split_number_unit(raw)?;
+        let unit = match unit {
+            "" | "pt" => Unit::Pt,
+            "mm" => Unit::Mm,
+            "cm" => Unit::Cm,
+            "in" => Unit::In,
+            "em" => Unit::Em,
+            "%" => Unit::Percent,
+            "fr" => Unit::Fr,
+            _ => return None,
+        };
+        Some(Length::new(value, unit))
+    }
+
+    /// A length as LaTeX writes it: `12pt`, `2ex`, `0.5\linewidth`, `\fill`,
+    /// `\stretch{2}` or the kernel's `2\p@`. The stretch and shrink of glue
+    /// (`plus ..`, `minus ..`) are dropped, `\baselineskip` is taken as an
+    /// em and a bare number as points.
+    pub fn parse_latex(raw: &str) -> Option<Length> {
+        let raw = raw
+            .trim()
+            .trim_matches(|c| c == '{' || c == '}')
+            .replace("\\p@", "pt")
+            .replace("\\z@", "0pt")
+            .replace("\\@plus", " plus ")
+            .replace("\\@minus", " minus ");
+        let end = ["plus", "minus"]
+            .iter()
+            .filter_map(|word| raw.find(word))
+            .min()
+            .unwrap_or(raw.len());
+        let raw = raw[..end].trim();
+        if matches!(raw, "\\fill" | "\\hfill" | "\\vfill") {
+            return Some(Length::new(1.0, Unit::Fr));
+        }
+        if let Some(rest) = raw.strip_prefix("\\stretch") {
+            let factor = rest.trim().trim_start_matches('{').trim_end_matches('}');
+            return Some(Length::new(parse_factor(factor)?, Unit::Fr));
+        }
+        if let Some(idx) = raw.find('\\') {
+            let (factor, name) = raw.split_at(idx);
+            let factor = parse_factor(factor)?;
+            return match name.trim() {
+                "\\linewidth" | "\\textwidth" | "\\columnwidth" | "\\hsize" => {
+                    Some(Length::new(factor * 100.0, Unit::Percent))
+                }
+                "\\baselineskip" => Some(Length::new(factor, Unit::Em)),
+                _ => None,
+            };
+        }
+        let (value, unit) = split_number_unit(raw)?;
+        let unit = match unit {
+            "" => Unit::Pt,
+            unit => Unit::parse_latex(unit)?,
+        };
+        Some(Length::new(value, unit))
+    }
+
+    /// The length in points, with `em` and `ex` taken of a font of
+    /// `font_size` points. `None` for shares of the line or of the space
+    /// left over, and for `em` and `ex` without a font size.
+    pub fn to_pt(&self, font_size: f64) -> Option<f64> {
+        match self.unit {
+            Unit::Em | Unit::Ex if font_size <= 0.0 => None,
+            Unit::Em => Some(self.value * font_size),
+            Unit::Ex => Some(self.value * EX_IN_EM * font_size),
+            unit => unit.points().map(|points| self.value * points),
+        }
+    }
+
+    /// The length as a number of `unit`, an absolute unit, with `em` and
+    /// `ex` taken of a font of `font_size` points.
+    pub fn to_unit(&self, unit: Unit, font_size: f64) -> Option<f64> {
+        Some(self.to_pt(font_size)? / unit.points()?)
+    }
+
+    /// The sum of two lengths: in their unit where they share one, in
+    /// points where both are absolute. `None` for sums such as `1em + 2pt`,
+    /// which depend on the font.
+    pub fn checked_add(self, other: Length) -> Option<Length> {
+        if self.unit == other.unit {
+            return Some(Length::new(self.value + other.value, self.unit));
+        }
+        let points = self.value * self.unit.points()? + other.value * other.unit.points()?;
+        Some(Length::pt(points))
+    }
+
+    pub fn checked_sub(self, other: Length) -> Option<Length> {
+        self.checked_add(-other)
+    }
+
+    /// The length in Typst syntax. Units Typst does not have are converted:
+    /// `bp`, `pc`, `sp`, `dd` and `cc` to points, `ex` to ems.
+    pub fn to_typst(&self) -> String {
+        match self.unit {
+            Unit::Pt | Unit::Mm | Unit::Cm | Unit::In | Unit::Em | Unit::Percent | Unit::Fr => {
+                format!("{}{}", format_number(self.value), self.unit.suffix())
+            }
+            Unit::Ex => format!("{}em", format_number(self.value * EX_IN_EM)),
+            unit => {
+                let points = self.value * unit.points().unwrap_or(1.0);
+                format!("{}pt", format_number(points))
+            }
+        }
+    }
+
+    /// The length in LaTeX syntax: a share of the line as a factor of
+    /// `\linewidth`, a share of the space left over as `\fill` or
+    /// `\stretch{..}`.
+    pub fn to_latex(&self) -> String {
+        self.to_latex_relative_to("\\linewidth")
+    }
+
+    /// [`Length::to_latex`] with shares of the line taken of `width`, such
+    /// as `\textwidth`.
+    pub fn to_latex_relative_to(&self, width: &str) -> String {
+        match self.unit {
+            Unit::Percent if (self.value - 100.0).abs() < 0.1 => width.to_string(),
+            Unit::Percent => format!("{:.2}{}", self.value / 100.0, width),
+            Unit::Fr if (self.value - 1.0).abs() < 0.01 => "\\fill".to_string(),
+            Unit::Fr => format!("\\stretch{{{}}}", format_number(self.value)),
+            unit => format!("{}{}", format_number(self.value), unit.suffix()),
+        }
+    }
+}
+
+impl Mul<f64> for Length {
+    type Output = Length;
+
+    fn mul(self, factor: f64) -> Length {
+        Length::new(self.value * factor, self.unit)
+    }
+}
+
+impl Div<f64> for Length {
+    type Output = Length;
+
+    fn div(self, divisor: f64) -> Length {
+        Length::new(self.value / divisor, self.unit)
+    }
+}
+
+impl Neg for Length {
+    type Output = Length;
+
+    fn neg(self) -> Length {
+        Length::new(-self.value, self.unit)
+    }
+}
+
+/// A number to at most four places, without trailing zeros.
+pub fn format_number(value: f64) -> String {
+    let text = format!("{:.4}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// The factor in front of a LaTeX length macro, as in `0.5\linewidth`;
+/// none is one.
+fn parse_factor(raw: &str) -> Option<f64> {
+    match raw.trim() {
+        "" | "+" => Some(1.0),
+        "-" => Some(-1.0),
+        factor => factor.parse().ok(),
+    }
+}
+
+fn split_number_unit(raw: &str) -> Option<(f64, &str)> {
+    let trimmed = raw.trim();
+    let split = trimmed
+        .find(|ch: char| !(ch.is_ascii_digit() || matches!(ch, '.' | '-' | '+')))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let value = number.parse::<f64>().ok()?;
+    Some((value, unit.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typst(raw: &str) -> Option<String> {
+        Length::parse_typst(raw).map(|length| length.to_latex())
+    }
+
+    fn latex(raw: &str) -> Option<String> {
+        Length::parse_latex(raw).map(|length| length.to_typst())
+    }
+
+    #[test]
+    fn typst_lengths_in_latex() {
+        assert_eq!(typst("12pt").as_deref(), Some("12pt"));
+        assert_eq!(typst(" 2.50cm ").as_deref(), Some("2.5cm"));
+        assert_eq!(typst("8").as_deref(), Some("8pt"));
+        assert_eq!(typst("50%").as_deref(), Some("0.50\\linewidth"));
+        assert_eq!(typst("100%").as_deref(), Some("\\linewidth"));
+        assert_eq!(typst("1fr").as_deref(), Some("\\fill"));
+        assert_eq!(typst("2fr").as_deref(), Some("\\stretch{2}"));
+        assert_eq!(typst("auto"), None);
+        assert_eq!(
+            Length::parse_typst("25%").map(|l| l.to_latex_relative_to("\\textwidth")),
+            Some("0.25\\textwidth".to_string())
+        );
+    }
+
+    #[test]
+    fn latex_lengths_in_typst() {
+        assert_eq!(latex("1.5in").as_deref(), Some("1.5in"));
+        assert_eq!(latex("2pc").as_deref(), Some("24pt"));
+        assert_eq!(latex("1ex").as_deref(), Some("0.43em"));
+        assert_eq!(latex("72bp").as_deref(), Some("72pt"));
+        assert_eq!(latex("0.5\\linewidth").as_deref(), Some("50%"));
+        assert_eq!(latex("\\textwidth").as_deref(), Some("100%"));
+        assert_eq!(latex("1.2\\baselineskip").as_deref(), Some("1.2em"));
+        assert_eq!(latex("\\stretch{3}").as_deref(), Some("3fr"));
+        assert_eq!(latex("\\vfill").as_deref(), Some("1fr"));
+        assert_eq!(latex("6\\p@ \\@plus 2\\p@").as_deref(), Some("6pt"));
+        assert_eq!(latex("10pt plus 1fil minus 2pt").as_deref(), Some("10pt"));
+        assert_eq!(latex("\\parindent"), None);
+    }
+
+    #[test]
+    fn lengths_add_up_in_a_shared_unit() {
+        let em = Length::new(1.0, Unit::Em);
+        assert_eq!(em.checked_add(em * 0.5), Some(Length::new(1.5, Unit::Em)));
+        assert_eq!(em.checked_add(Length::pt(2.0)), None);
+        let sum = Length::new(1.0, Unit::In).checked_sub(Length::pt(0.27));
+        assert_eq!(sum.map(|length| length.to_typst()).as_deref(), Some("72pt"));
+        assert_eq!(Length::new(2.0, Unit::Em).to_pt(10.0), Some(20.0));
+        assert_eq!(Length::new(2.0, Unit::Em).to_pt(0.0), None);
+        assert_eq!(Length::new(50.0, Unit::Percent).to_pt(10.0), None);
+        let cm = |length: Length| length.to_unit(Unit::Cm, 10.0).map(format_number);
+        assert_eq!(cm(Length::new(10.0, Unit::Mm)).as_deref(), Some("1"));
+        assert_eq!(cm(Length::new(1.0, Unit::In)).as_deref(), Some("2.54"));
+        assert_eq!(cm(Length::new(1.0, Unit::Em)).as_deref(), Some("0.3515"));
+        assert_eq!(Length::pt(1.0).to_unit(Unit::Em, 10.0), None);
+    }
+}
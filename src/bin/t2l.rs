@@ -5,6 +5,7 @@ use clap::{Parser, Subcommand, ValueEnum};
 use std::fs;
 use std::io::{self, Read, Write};
 use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 use std::time::Instant;
 use tylax::core::latex2typst::utils::{
     collect_bibliography_entries, collect_graphicspath_entries, collect_includegraphics_paths,
@@ -14,16 +15,27 @@ use tylax::core::latex2typst::utils::{
 use tylax::{
     convert_auto, convert_auto_document, detect_format,
     diagnostics::{check_latex, format_diagnostics},
-    latex_document_to_typst, latex_math_to_typst_with_report, latex_to_typst,
-    latex_to_typst_with_diagnostics, latex_to_typst_with_report,
+    export_tables,
+    files::{FileResolver, StdFileResolver},
+    format_latex, hayagriva_bibliographies_to_bibtex, included_files, ir_from_json, ir_to_json,
+    ir_to_latex_with_template, ir_to_typst, label_map_to_json, latex_document_to_typst,
+    latex_math_to_typst_with_report, latex_to_ir, latex_to_ir_with_environments, latex_to_typst,
+    latex_to_typst_ir_with_report, latex_to_typst_with_report,
     tikz::{convert_cetz_to_tikz, convert_tikz_to_cetz, is_cetz_code},
-    typst_document_to_latex, typst_to_latex, typst_to_latex_ir, typst_to_latex_ir_with_report,
+    typst_document_to_latex, typst_to_ir, typst_to_latex, typst_to_latex_ir,
+    typst_to_latex_ir_and_report, typst_to_latex_ir_conversion, typst_to_latex_ir_with_report,
     typst_to_latex_with_diagnostics,
     utils::latex_analysis::metrics_source as latex_metrics_source,
-    utils::loss::{LossKind, LossRecord, LossReport, LOSS_MARKER_PREFIX},
+    utils::loss::{FidelityScore, LossKind, LossRecord, LossReport, LOSS_MARKER_PREFIX},
     utils::repair::{maybe_repair_typst_to_latex, AiRepairConfig},
     utils::typst_analysis::metrics_source as typst_metrics_source,
-    CliDiagnostic, T2LOptions,
+    utils::verify::{verify_latex, verify_typst, Verification},
+    roundtrip::{round_trip, Language},
+    stats, AssetMode, AssetOptions, CitationPackage, CliDiagnostic, DiagnosticSeverity, DocStats,
+    DocumentTemplate,
+    EncodingMode, FormatOptions, HeadingNode, IncludeOptions, IncludeStrategy, L2TOptions,
+    LatexConverter, LatexOutputOptions, QuoteStyle,
+    ReviewNotes, SvgTarget, T2LOptions, TableExportOptions, TableFormat, TylaxError,
 };
 
 #[cfg(feature = "cli")]
@@ -56,6 +68,14 @@ struct Cli {
     #[arg(short, long)]
     pretty: bool,
 
+    /// Indentation per nested environment when pretty printing LaTeX
+    #[arg(long, default_value_t = 2)]
+    indent_width: usize,
+
+    /// Soft-wrap pretty printed LaTeX lines longer than this (0 disables)
+    #[arg(long, default_value_t = 100)]
+    max_line_length: usize,
+
     /// Enable AI auto-repair for LaTeX -> Typst conversions
     #[arg(long)]
     auto_repair: bool,
@@ -64,22 +84,137 @@ struct Cli {
     #[arg(long)]
     ai_cmd: Option<String>,
 
-    /// Write a loss report JSON to this path (LaTeX -> Typst only)
-    #[arg(long)]
+    /// Write a loss report JSON to this path, with each loss's severity,
+    /// source span and suggested fix
+    #[arg(long, visible_alias = "loss-report")]
     loss_log: Option<String>,
 
     /// Write a post-repair report JSON to this path
     #[arg(long)]
     post_repair_log: Option<String>,
 
+    /// Print the fidelity score of the loss report at the end; with
+    /// --loss-log or --auto-repair, which produce one
+    #[arg(long)]
+    summary: bool,
+
     /// Allow AI output even if it does not reduce loss markers
     #[arg(long)]
     allow_no_gain: bool,
 
-    /// Use the IR-based Typst → LaTeX pipeline
+    /// Convert through the shared IR: the IR-based Typst → LaTeX pipeline,
+    /// or the LaTeX frontend and the Typst backend for LaTeX → Typst
     #[arg(long)]
     ir: bool,
 
+    /// Typeset the bibliography inline from its .bib files instead of
+    /// referencing them (Typst → LaTeX, implies --ir)
+    #[arg(long)]
+    inline_bib: bool,
+
+    /// How text outside ASCII is written; by default kept as written when
+    /// the document sets a system font, and transliterated otherwise
+    /// (Typst → LaTeX, implies --ir)
+    #[arg(long, value_enum)]
+    encoding: Option<Encoding>,
+
+    /// How smart quotes are set (Typst → LaTeX, implies --ir)
+    #[arg(long, value_enum)]
+    quotes: Option<Quotes>,
+
+    /// Set bold and emphasized math as \boldsymbol and \mathit inside the
+    /// formula (Typst → LaTeX, implies --ir)
+    #[arg(long)]
+    math_emphasis: bool,
+
+    /// Deepest heading level the table of contents lists (Typst → LaTeX,
+    /// implies --ir)
+    #[arg(long, value_name = "LEVEL")]
+    toc_depth: Option<u8>,
+
+    /// Set cross-references with cleveref's \cref, which names the target
+    /// itself (Typst → LaTeX, implies --ir)
+    #[arg(long)]
+    cleveref: bool,
+
+    /// Set every table as a longtable, which breaks across pages
+    /// (Typst → LaTeX, implies --ir)
+    #[arg(long)]
+    long_tables: bool,
+
+    /// Set tables with more rows than this as longtables (Typst → LaTeX,
+    /// implies --ir)
+    #[arg(long, value_name = "ROWS")]
+    long_table_rows: Option<usize>,
+
+    /// Set tables with fr columns as tabularx, whose X columns share the
+    /// line (Typst → LaTeX, implies --ir)
+    #[arg(long)]
+    tabularx: bool,
+
+    /// Package that sets citations and the bibliography (Typst → LaTeX,
+    /// implies --ir)
+    #[arg(long, value_enum)]
+    citations: Option<Citations>,
+
+    /// Convert the files a Typst document includes, setting their content in
+    /// place (inline) or an \input of each, converted on its own next to the
+    /// output (input) (Typst → LaTeX, implies --ir)
+    #[arg(long, value_enum)]
+    multi_file: Option<MultiFile>,
+
+    /// Write a .bib file next to the output for each Hayagriva .yml
+    /// bibliography the input uses (Typst → LaTeX)
+    #[arg(long)]
+    yaml_bib: bool,
+
+    /// Document class of the LaTeX output, with headings mapped to its
+    /// sectioning commands (Typst → LaTeX, implies --ir and -f)
+    #[arg(long, value_enum)]
+    template: Option<LatexTemplate>,
+
+    /// File whose contents replace the generated preamble, up to
+    /// \begin{document} (Typst → LaTeX, implies --ir and -f)
+    #[arg(long, conflicts_with = "template")]
+    preamble: Option<String>,
+
+    /// Replace a label prefix in the output, as FROM=TO, e.g. fig:=fig-
+    /// (Typst → LaTeX, implies --ir; repeatable)
+    #[arg(long, value_parser = parse_label_rewrite)]
+    label_rewrite: Vec<(String, String)>,
+
+    /// Write a JSON map of each Typst label to its LaTeX label to this
+    /// path (Typst → LaTeX, implies --ir)
+    #[arg(long)]
+    label_map: Option<String>,
+
+    /// Write each part of the document from a heading of this level or
+    /// above to a .tex file of its own next to the output, which the output
+    /// \inputs, e.g. 1 for a file per chapter (Typst → LaTeX, implies --ir)
+    #[arg(long, value_name = "LEVEL", requires = "output")]
+    split: Option<u8>,
+
+    /// Copy or link the images of the document next to the output and
+    /// point the output at them, converting SVG and GIF when tylax is built
+    /// with the svg and gif features (Typst → LaTeX, implies --ir)
+    #[arg(long, value_name = "MODE", requires = "output")]
+    assets: Option<Assets>,
+
+    /// What --assets converts SVG images to, for pdflatex; needs tylax
+    /// built with the svg feature [default: pdf]
+    #[arg(long, value_name = "FORMAT", requires = "assets")]
+    svg_to: Option<SvgTo>,
+
+    /// Write each table of the document to a file of its own next to the
+    /// output, named after its label, e.g. tab-results.csv
+    #[arg(long, value_name = "FORMAT", requires = "output")]
+    export_tables: Option<TablesAs>,
+
+    /// Keep \todo, \marginpar and % TODO review notes as Typst comments or
+    /// calls of a todo function (LaTeX → Typst)
+    #[arg(long, value_name = "MODE")]
+    review_notes: Option<Notes>,
+
     /// Detect and print the input format without converting
     #[arg(long)]
     detect: bool,
@@ -98,6 +233,7 @@ struct Cli {
     no_eval: bool,
 
     /// Strict mode: exit with error if any conversion warnings occur
+    /// (with --ir, only if the conversion dropped content)
     #[arg(long)]
     strict: bool,
 
@@ -108,6 +244,11 @@ struct Cli {
     /// Embed warnings as comments in the output file
     #[arg(long)]
     embed_warnings: bool,
+
+    /// Compile the output, LaTeX with latexmk or tectonic and Typst with the
+    /// typst CLI, when installed, and report errors at their source locations
+    #[arg(long)]
+    verify: bool,
 }
 
 #[cfg(feature = "cli")]
@@ -152,17 +293,29 @@ enum Commands {
         #[arg(long)]
         ai_cmd: Option<String>,
 
-        /// Write a loss report JSON to this path (LaTeX -> Typst only)
-        #[arg(long)]
+        /// Write a loss report JSON to this path, with each loss's severity,
+        /// source span and suggested fix
+        #[arg(long, visible_alias = "loss-report")]
         loss_log: Option<String>,
 
         /// Write a post-repair report JSON to this path
         #[arg(long)]
         post_repair_log: Option<String>,
 
+        /// Print the fidelity score of the loss report at the end; with
+        /// --loss-log or --auto-repair, which produce one
+        #[arg(long)]
+        summary: bool,
+
         /// Allow AI output even if it does not reduce loss markers
         #[arg(long)]
         allow_no_gain: bool,
+
+        /// Compile the output, LaTeX with latexmk or tectonic and Typst with
+        /// the typst CLI, when installed, and report errors at their source
+        /// locations
+        #[arg(long, visible_alias = "check-compile")]
+        verify: bool,
     },
 
     /// Convert TikZ to CeTZ or vice versa
@@ -203,12 +356,370 @@ enum Commands {
         /// Use the IR-based Typst → LaTeX pipeline
         #[arg(long)]
         ir: bool,
+
+        /// Print each file's fidelity score at the end, lowest first, from
+        /// the conversion the other flags select. Typst is scored with --ir
+        /// only; auto direction is not scored
+        #[arg(long)]
+        summary: bool,
+    },
+
+    /// Dump or render the intermediate representation
+    Ir {
+        #[command(subcommand)]
+        action: IrCommand,
+    },
+
+    /// Count the words, headings, equations, tables, figures and citations
+    /// of documents, to compare a source with its conversion
+    Stats {
+        /// Input files, LaTeX or Typst
+        #[arg(required = true)]
+        inputs: Vec<String>,
+
+        /// Frontend to parse with (auto-detected by default)
+        #[arg(long, value_enum, default_value_t = IrFrontend::Auto)]
+        from: IrFrontend,
+
+        /// Write the counts as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Convert a document to the other language and back, and compare the
+    /// structure of the two
+    CheckRoundtrip {
+        /// Input file, LaTeX or Typst
+        input: String,
+
+        /// Language of the input (auto-detected by default)
+        #[arg(long, value_enum, default_value_t = IrFrontend::Auto)]
+        from: IrFrontend,
+
+        /// Also compile both ends, with whichever of typst and latexmk or
+        /// tectonic is installed
+        #[arg(long)]
+        compile: bool,
     },
 
     /// Show version and feature info
     Info,
 }
 
+#[cfg(feature = "cli")]
+#[derive(Subcommand)]
+enum IrCommand {
+    /// Parse a LaTeX or Typst file and write its IR as JSON
+    Dump {
+        /// Input file path (reads from stdin if not provided)
+        input: Option<String>,
+
+        /// Output file path
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Frontend to parse with (auto-detected by default)
+        #[arg(long, value_enum, default_value_t = IrFrontend::Auto)]
+        from: IrFrontend,
+
+        /// A LaTeX environment to keep as an environment block, like the
+        /// theorem-like ones; may be repeated
+        #[arg(long = "environment", value_name = "NAME")]
+        environments: Vec<String>,
+    },
+
+    /// Render LaTeX from an IR JSON file written by `ir dump`
+    Render {
+        /// IR JSON file (reads from stdin if not provided)
+        input: Option<String>,
+
+        /// Output file path
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Full document mode
+        #[arg(short = 'f', long)]
+        full_document: bool,
+
+        /// Document class of a full document, and the heading commands
+        #[arg(long, value_enum, default_value_t = LatexTemplate::Article)]
+        template: LatexTemplate,
+
+        /// File whose contents replace the generated preamble of a full
+        /// document
+        #[arg(long)]
+        preamble: Option<String>,
+    },
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, ValueEnum)]
+enum MultiFile {
+    /// Set the content of included files in place
+    Inline,
+    /// Set \input of each included file, converted to a .tex file of its own
+    Input,
+}
+
+#[cfg(feature = "cli")]
+impl From<MultiFile> for IncludeStrategy {
+    fn from(multi_file: MultiFile) -> Self {
+        match multi_file {
+            MultiFile::Inline => IncludeStrategy::Inline,
+            MultiFile::Input => IncludeStrategy::Input,
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, ValueEnum)]
+enum Encoding {
+    /// Accented letters and symbols as the commands that set them under
+    /// pdflatex
+    Transliterate,
+    /// Text as written, with fontspec, for XeLaTeX and LuaLaTeX
+    Unicode,
+}
+
+#[cfg(feature = "cli")]
+impl From<Encoding> for EncodingMode {
+    fn from(encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Transliterate => EncodingMode::Transliterate,
+            Encoding::Unicode => EncodingMode::Unicode,
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, ValueEnum)]
+enum Citations {
+    /// \cite, and natbib's \citep and \citet where a citation needs them
+    Natbib,
+    /// biblatex's \autocite and \textcite with \printbibliography
+    Biblatex,
+}
+
+#[cfg(feature = "cli")]
+impl From<Citations> for CitationPackage {
+    fn from(citations: Citations) -> Self {
+        match citations {
+            Citations::Natbib => CitationPackage::Natbib,
+            Citations::Biblatex => CitationPackage::Biblatex,
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, ValueEnum)]
+enum Quotes {
+    /// " and ' as written, set as straight quotes
+    Straight,
+    /// TeX's `` and '' pairs
+    Tex,
+    /// csquotes' \enquote, following the document's language
+    Csquotes,
+}
+
+#[cfg(feature = "cli")]
+impl From<Quotes> for QuoteStyle {
+    fn from(quotes: Quotes) -> Self {
+        match quotes {
+            Quotes::Straight => QuoteStyle::Straight,
+            Quotes::Tex => QuoteStyle::Tex,
+            Quotes::Csquotes => QuoteStyle::Csquotes,
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, ValueEnum)]
+enum Assets {
+    /// Copy each image
+    Copy,
+    /// Link each image with a symbolic link
+    Link,
+}
+
+#[cfg(feature = "cli")]
+impl From<Assets> for AssetMode {
+    fn from(assets: Assets) -> Self {
+        match assets {
+            Assets::Copy => AssetMode::Copy,
+            Assets::Link => AssetMode::Link,
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, ValueEnum)]
+enum SvgTo {
+    Pdf,
+    Png,
+}
+
+#[cfg(feature = "cli")]
+impl From<SvgTo> for SvgTarget {
+    fn from(svg_to: SvgTo) -> Self {
+        match svg_to {
+            SvgTo::Pdf => SvgTarget::Pdf,
+            SvgTo::Png => SvgTarget::Png,
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, ValueEnum)]
+enum TablesAs {
+    /// Comma-separated values
+    Csv,
+    /// Tab-separated values
+    Tsv,
+}
+
+#[cfg(feature = "cli")]
+impl From<TablesAs> for TableFormat {
+    fn from(tables_as: TablesAs) -> Self {
+        match tables_as {
+            TablesAs::Csv => TableFormat::Csv,
+            TablesAs::Tsv => TableFormat::Tsv,
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, ValueEnum)]
+enum Notes {
+    /// \todo as red text and margin notes as footnotes; comments dropped
+    Inline,
+    /// Each note as a /* TODO: ... */ comment
+    Comments,
+    /// Each note as #todo[...], with todo defined at the top
+    Helper,
+}
+
+#[cfg(feature = "cli")]
+impl From<Notes> for ReviewNotes {
+    fn from(notes: Notes) -> Self {
+        match notes {
+            Notes::Inline => ReviewNotes::Inline,
+            Notes::Comments => ReviewNotes::Comments,
+            Notes::Helper => ReviewNotes::Helper,
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, ValueEnum)]
+enum IrFrontend {
+    /// Auto-detect based on file extension or content
+    Auto,
+    /// LaTeX frontend
+    Latex,
+    /// Typst frontend
+    Typst,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, ValueEnum)]
+enum LatexTemplate {
+    /// article
+    Article,
+    /// report, with chapters
+    Report,
+    /// book, with chapters
+    Book,
+    /// IEEEtran conference paper
+    Ieeetran,
+    /// acmart sigconf paper
+    Acm,
+    /// beamer slides, a frame per second-level heading
+    Beamer,
+}
+
+/// The template a full LaTeX document is set in: the contents of a preamble
+/// file, or one of the classes the backend knows.
+#[cfg(feature = "cli")]
+fn document_template(
+    template: Option<LatexTemplate>,
+    preamble: Option<&str>,
+) -> io::Result<Option<DocumentTemplate>> {
+    if let Some(path) = preamble {
+        let preamble = fs::read_to_string(path)?;
+        return Ok(Some(DocumentTemplate::Custom { preamble }));
+    }
+    Ok(template.map(|template| match template {
+        LatexTemplate::Article => DocumentTemplate::Article,
+        LatexTemplate::Report => DocumentTemplate::Report,
+        LatexTemplate::Book => DocumentTemplate::Book,
+        LatexTemplate::Ieeetran => DocumentTemplate::IeeeTran,
+        LatexTemplate::Acm => DocumentTemplate::Acm,
+        LatexTemplate::Beamer => DocumentTemplate::Beamer,
+    }))
+}
+
+/// Parse `content` with the frontend `from` names, or the one the file
+/// extension or the content suggests.
+#[cfg(feature = "cli")]
+fn parse_to_ir(
+    content: &str,
+    path: Option<&str>,
+    from: &IrFrontend,
+    environments: &[String],
+) -> tylax_ir::Document {
+    if is_latex_input(content, path, from) {
+        latex_to_ir_with_environments(content, environments)
+    } else {
+        typst_to_ir(content)
+    }
+}
+
+/// Whether `content` is LaTeX, by `from` or else by the file extension or
+/// the content itself.
+#[cfg(feature = "cli")]
+fn is_latex_input(content: &str, path: Option<&str>, from: &IrFrontend) -> bool {
+    match from {
+        IrFrontend::Auto => match path {
+            Some(name) if name.ends_with(".typ") => false,
+            Some(name) if name.ends_with(".tex") => true,
+            _ => detect_format(content) == "latex",
+        },
+        IrFrontend::Latex => true,
+        IrFrontend::Typst => false,
+    }
+}
+
+/// Print the counts of one document as `tylax stats` shows them.
+#[cfg(feature = "cli")]
+fn print_stats(path: &str, stats: &DocStats) {
+    fn print_headings(headings: &[HeadingNode], depth: usize) {
+        for heading in headings {
+            println!("    {}{}", "  ".repeat(depth), heading.title);
+            print_headings(&heading.children, depth + 1);
+        }
+    }
+    println!("{}", path);
+    println!("  words:      {}", stats.words);
+    println!("  equations:  {}", stats.equations);
+    println!("  tables:     {}", stats.tables);
+    println!("  figures:    {}", stats.figures);
+    println!("  citations:  {}", stats.citations.len());
+    if !stats.citations.is_empty() {
+        println!("    {}", stats.citations.join(", "));
+    }
+    println!("  headings:");
+    print_headings(&stats.headings, 0);
+}
+
+/// A `--label-rewrite` of the form `FROM=TO`.
+#[cfg(feature = "cli")]
+fn parse_label_rewrite(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((from, to)) if !from.is_empty() => Ok((from.to_string(), to.to_string())),
+        _ => Err(format!("expected FROM=TO, got `{}`", value)),
+    }
+}
+
 #[cfg(feature = "cli")]
 #[derive(Clone, ValueEnum)]
 enum TikzDirection {
@@ -232,13 +743,19 @@ enum Direction {
 }
 
 #[cfg(feature = "cli")]
-fn main() -> io::Result<()> {
+fn main() -> std::process::ExitCode {
     // Spawn main logic in a thread with larger stack to handle deeply nested documents
     // mitex parser can create deeply nested ASTs for complex LaTeX templates
     const STACK_SIZE: usize = 256 * 1024 * 1024; // 256 MB stack
     let builder = std::thread::Builder::new().stack_size(STACK_SIZE);
     let handle = builder.spawn(main_inner).expect("Failed to spawn main thread");
-    handle.join().expect("Main thread panicked")
+    match handle.join().expect("Main thread panicked") {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", TylaxError::from(err));
+            std::process::ExitCode::FAILURE
+        }
+    }
 }
 
 #[cfg(feature = "cli")]
@@ -316,8 +833,12 @@ fn main_inner() -> io::Result<()> {
     let mut graphic_dirs: Vec<String> = Vec::new();
     let mut graphics_base_dir: Option<PathBuf> = None;
 
-    // Determine if this is a full document based on content or flag.
-    let is_full_document = cli.full_document || is_latex_document(&input);
+    // Determine if this is a full document based on content or flag. A
+    // template is set as a full document.
+    let is_full_document = cli.full_document
+        || is_latex_document(&input)
+        || (matches!(direction, Direction::T2l)
+            && (cli.template.is_some() || cli.preamble.is_some()));
     let template_assets = if matches!(direction, Direction::L2t) && is_full_document {
         let packages = collect_usepackage_entries(&input);
         TemplateAssetFlags {
@@ -401,12 +922,28 @@ fn main_inner() -> io::Result<()> {
     let mut loss_report: Option<LossReport> = None;
     let mut post_report: Option<LossReport> = None;
 
+    // Files the input includes are read relative to its directory.
+    let input_dir = filename
+        .as_deref()
+        .and_then(|name| Path::new(name).parent())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let include_files: Arc<dyn FileResolver> = Arc::new(StdFileResolver::with_base_dir(input_dir));
+
     let mut diagnostics: Vec<CliDiagnostic> = Vec::new();
+    let mut split_parts: Vec<(String, String)> = Vec::new();
+    let mut unsplit: Option<String> = None;
+    // The IR a conversion went through, for the passes after it.
+    let mut converted_doc: Option<tylax_ir::Document> = None;
     // Convert
     let mut result = match direction {
         Direction::L2t => {
             if cli.auto_repair || cli.loss_log.is_some() || cli.post_repair_log.is_some() {
-                let report = latex_to_typst_with_report(&input);
+                let report = if cli.ir {
+                    latex_to_typst_ir_with_report(&input, is_full_document)
+                } else {
+                    latex_to_typst_with_report(&input)
+                };
                 loss_report = Some(report.report.clone());
                 let repaired = tylax::utils::repair::maybe_repair_latex_to_typst(
                     &input,
@@ -418,8 +955,18 @@ fn main_inner() -> io::Result<()> {
                     post_report = Some(build_post_report_typst(&repaired));
                 }
                 repaired
+            } else if cli.ir {
+                let doc = latex_to_ir(&input);
+                let output = ir_to_typst(&doc, is_full_document);
+                converted_doc = Some(doc);
+                output
             } else {
-                let conv_result = latex_to_typst_with_diagnostics(&input);
+                let options = L2TOptions {
+                    review_notes: cli.review_notes.map(ReviewNotes::from).unwrap_or_default(),
+                    ..Default::default()
+                };
+                let conv_result =
+                    LatexConverter::with_options(options).convert_document_with_diagnostics(&input);
                 diagnostics = conv_result
                     .warnings
                     .into_iter()
@@ -434,25 +981,119 @@ fn main_inner() -> io::Result<()> {
             } else {
                 T2LOptions::default()
             };
+            let template = document_template(cli.template.clone(), cli.preamble.as_deref())?;
             let use_ir = cli.ir
+                || template.is_some()
+                || cli.split.is_some()
+                || cli.assets.is_some()
+                || !cli.label_rewrite.is_empty()
+                || cli.label_map.is_some()
+                || cli.inline_bib
+                || cli.encoding.is_some()
+                || cli.quotes.is_some()
+                || cli.math_emphasis
+                || cli.toc_depth.is_some()
+                || cli.cleveref
+                || cli.long_tables
+                || cli.long_table_rows.is_some()
+                || cli.tabularx
+                || cli.citations.is_some()
+                || cli.multi_file.is_some()
                 || cli.auto_repair
                 || cli.loss_log.is_some()
-                || cli.post_repair_log.is_some();
-            if cli.auto_repair || cli.loss_log.is_some() || cli.post_repair_log.is_some() {
-                let report = typst_to_latex_ir_with_report(&input, is_full_document);
-                loss_report = Some(report.report.clone());
-                let repaired = maybe_repair_typst_to_latex(
-                    &input,
-                    &report.content,
-                    &report.report,
-                    &repair_config,
-                );
-                if cli.post_repair_log.is_some() {
-                    post_report = Some(build_post_report_latex(&repaired));
+                || cli.post_repair_log.is_some()
+                || cli.export_tables.is_some();
+            let output_dir = cli
+                .output
+                .as_deref()
+                .and_then(|path| Path::new(path).parent())
+                .unwrap_or(Path::new("."));
+            let output_options = LatexOutputOptions {
+                encoding_mode: cli.encoding.map(EncodingMode::from),
+                quote_style: cli.quotes.map(QuoteStyle::from).unwrap_or_default(),
+                math_emphasis: cli.math_emphasis,
+                toc_depth: cli.toc_depth,
+                cleveref: cli.cleveref,
+                long_tables: cli.long_tables,
+                long_table_rows: cli.long_table_rows,
+                tabularx: cli.tabularx,
+                citation_package: cli.citations.map(CitationPackage::from).unwrap_or_default(),
+                inline_bibliography: cli.inline_bib.then(|| {
+                    filename
+                        .as_deref()
+                        .and_then(|path| Path::new(path).parent())
+                        .unwrap_or(Path::new("."))
+                        .to_path_buf()
+                }),
+                data_root: Some(input_dir.to_path_buf()),
+                template,
+                label_rewrites: cli.label_rewrite.clone(),
+                split_level: cli.split,
+                assets: cli.assets.map(|mode| AssetOptions {
+                    mode: mode.into(),
+                    svg: cli.svg_to.map(SvgTarget::from).unwrap_or_default(),
+                    ..AssetOptions::new(input_dir, output_dir)
+                }),
+                includes: cli.multi_file.map(|multi_file| {
+                    IncludeOptions::new(include_files.clone(), multi_file.into())
+                }),
+            };
+            if use_ir {
+                let conversion =
+                    typst_to_latex_ir_conversion(&input, is_full_document, &output_options);
+                for path in &conversion.assets.missing {
+                    eprintln!("⚠ Image not found: {}", path);
+                }
+                for warning in &conversion.assets.warnings {
+                    eprintln!("⚠ {}", warning);
+                }
+                if !conversion.assets.written.is_empty() && !cli.quiet {
+                    eprintln!(
+                        "✓ {} image(s) written to: {}",
+                        conversion.assets.written.len(),
+                        output_dir.display()
+                    );
+                }
+                if cli.strict {
+                    if let Err(err) = conversion.report.check_strict() {
+                        eprintln!("Error: {}", err);
+                        std::process::exit(1);
+                    }
+                }
+                if let Some(path) = &cli.label_map {
+                    let json = label_map_to_json(&conversion.labels)
+                        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+                    fs::write(path, json)?;
+                }
+                if cli.export_tables.is_some() {
+                    converted_doc = Some(conversion.document.clone());
+                }
+                let mut content = conversion.output.clone();
+                if cli.auto_repair || cli.loss_log.is_some() || cli.post_repair_log.is_some() {
+                    let report = conversion.report.clone();
+                    report.append_markers(&mut content, is_full_document);
+                    content =
+                        maybe_repair_typst_to_latex(&input, &content, &report, &repair_config);
+                    loss_report = Some(report);
+                    if cli.post_repair_log.is_some() {
+                        post_report = Some(build_post_report_latex(&content));
+                    }
+                }
+                if cli.split.is_some() {
+                    let split = conversion.split(&content);
+                    if split.parts.is_empty()
+                        && !conversion.split(&conversion.output).parts.is_empty()
+                    {
+                        eprintln!("⚠ --split: the repaired output is kept in one file");
+                    }
+                    // --verify compiles the document whole, with the parts
+                    // in place.
+                    unsplit = Some(content);
+                    split_parts = split.parts;
+                    split.main
+                } else {
+                    content
                 }
-                repaired
-            } else if use_ir {
-                typst_to_latex_ir(&input, is_full_document)
             } else if !cli.no_eval {
                 let conv_result = typst_to_latex_with_diagnostics(&input, &options);
                 diagnostics = conv_result
@@ -498,16 +1139,43 @@ fn main_inner() -> io::Result<()> {
     }
 
     if let (Some(path), Some(report)) = (cli.loss_log.as_ref(), loss_report.as_ref()) {
-        let serialized = serde_json::to_string_pretty(report)
+        let serialized = report
+            .to_loss_log()
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
         fs::write(path, serialized)?;
     }
+    if cli.summary {
+        print_report_summary(filename.as_deref(), loss_report.as_ref());
+    }
     if let (Some(path), Some(report)) = (cli.post_repair_log.as_ref(), post_report.as_ref()) {
-        let serialized = serde_json::to_string_pretty(report)
+        let serialized = report
+            .to_loss_log()
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
         fs::write(path, serialized)?;
     }
 
+    if cli.yaml_bib && matches!(direction, Direction::T2l) {
+        let input_dir = filename
+            .as_deref()
+            .and_then(|path| Path::new(path).parent())
+            .unwrap_or(Path::new("."));
+        let out_dir = cli
+            .output
+            .as_deref()
+            .and_then(|path| Path::new(path).parent())
+            .unwrap_or(input_dir);
+        for (name, bibtex) in hayagriva_bibliographies_to_bibtex(&input, input_dir) {
+            let path = out_dir.join(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, bibtex)?;
+            if !cli.quiet {
+                eprintln!("Wrote {}", path.display());
+            }
+        }
+    }
+
     // Bibliography handling
     if let Some(output_path) = cli.output.as_ref() {
         let out_dir = Path::new(output_path)
@@ -579,12 +1247,65 @@ fn main_inner() -> io::Result<()> {
         result = rewrite_extensionless_images(&result, &out_dir);
     }
 
-    let result = if cli.pretty {
+    let format_options = FormatOptions {
+        indent_width: cli.indent_width,
+        max_line_length: (cli.max_line_length > 0).then_some(cli.max_line_length),
+    };
+    let result = if cli.pretty && matches!(direction, Direction::T2l) {
+        format_latex(&result, &format_options)
+    } else if cli.pretty {
         pretty_print(&result)
     } else {
         result
     };
 
+    if matches!(cli.multi_file, Some(MultiFile::Input)) && matches!(direction, Direction::T2l) {
+        match cli.output.as_deref().and_then(|path| Path::new(path).parent()) {
+            Some(out_dir) => write_included_files(&input, &include_files, out_dir)?,
+            None => eprintln!(
+                "⚠ --multi-file input: included files are only converted with --output"
+            ),
+        }
+    }
+
+    if let Some(out_dir) = cli.output.as_deref().and_then(|path| Path::new(path).parent()) {
+        for (name, content) in &split_parts {
+            let target = out_dir.join(format!("{}.tex", name));
+            if cli.pretty {
+                fs::write(&target, format_latex(content, &format_options))?;
+            } else {
+                fs::write(&target, content)?;
+            }
+            eprintln!("✓ Part written to: {}", target.display());
+        }
+    }
+
+    if let Some(format) = cli.export_tables {
+        let out_dir = cli
+            .output
+            .as_deref()
+            .and_then(|path| Path::new(path).parent())
+            .unwrap_or(Path::new("."));
+        let doc = match (converted_doc.take(), direction) {
+            (Some(doc), _) => doc,
+            (None, Direction::L2t) => latex_to_ir(&input),
+            (None, _) => typst_to_ir(&input),
+        };
+        let options = TableExportOptions {
+            format: format.into(),
+            ..TableExportOptions::new(out_dir)
+        };
+        match export_tables(&doc, &options) {
+            Ok(report) if !cli.quiet => eprintln!(
+                "✓ {} table(s) written to: {}",
+                report.written.len(),
+                out_dir.display()
+            ),
+            Ok(_) => {}
+            Err(err) => eprintln!("⚠ Unable to write tables: {}", err),
+        }
+    }
+
     // Output
     match cli.output {
         Some(path) => {
@@ -614,6 +1335,26 @@ fn main_inner() -> io::Result<()> {
         }
     }
 
+    if cli.verify {
+        let search_dir = filename
+            .as_deref()
+            .and_then(|name| Path::new(name).parent())
+            .filter(|dir| !dir.as_os_str().is_empty());
+        let verification = if matches!(direction, Direction::T2l) {
+            verify_latex(
+                unsplit.as_deref().unwrap_or(&result),
+                &input,
+                is_full_document,
+                search_dir,
+            )
+        } else {
+            verify_typst(&result, false, search_dir)
+        };
+        if report_verification(&verification, filename.as_deref().unwrap_or("<stdin>")) {
+            std::process::exit(1);
+        }
+    }
+
     Ok(())
 }
 
@@ -1939,7 +2680,9 @@ fn handle_subcommand(cmd: Commands) -> io::Result<()> {
             ai_cmd,
             loss_log,
             post_repair_log,
+            summary,
             allow_no_gain,
+            verify,
         } => {
             let (mut content, filename) = match input {
                 Some(ref path) => (fs::read_to_string(path)?, Some(path.clone())),
@@ -2189,16 +2932,36 @@ fn handle_subcommand(cmd: Commands) -> io::Result<()> {
             }
 
             if let (Some(path), Some(report)) = (loss_log.as_ref(), loss_report.as_ref()) {
-                let serialized = serde_json::to_string_pretty(report)
+                let serialized = report
+                    .to_loss_log()
                     .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
                 fs::write(path, serialized)?;
             }
+            if summary {
+                print_report_summary(filename.as_deref(), loss_report.as_ref());
+            }
             if let (Some(path), Some(report)) = (post_repair_log.as_ref(), post_report.as_ref()) {
-                let serialized = serde_json::to_string_pretty(report)
+                let serialized = report
+                    .to_loss_log()
                     .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
                 fs::write(path, serialized)?;
             }
 
+            let compile_failed = verify && {
+                let search_dir = filename
+                    .as_deref()
+                    .and_then(|name| Path::new(name).parent())
+                    .filter(|dir| !dir.as_os_str().is_empty());
+                let verification = if matches!(direction, Direction::T2l) {
+                    verify_latex(&result, &content, full_document, search_dir)
+                } else {
+                    // Without a full document, LaTeX is converted as math.
+                    let math = matches!(direction, Direction::L2t) && !full_document;
+                    verify_typst(&result, math, search_dir)
+                };
+                report_verification(&verification, filename.as_deref().unwrap_or("<stdin>"))
+            };
+
             match output {
                 Some(path) => {
                     let mut file = fs::File::create(&path)?;
@@ -2209,6 +2972,10 @@ fn handle_subcommand(cmd: Commands) -> io::Result<()> {
                     println!("{}", result);
                 }
             }
+
+            if compile_failed {
+                std::process::exit(1);
+            }
         }
 
         Commands::Tikz {
@@ -2261,6 +3028,7 @@ fn handle_subcommand(cmd: Commands) -> io::Result<()> {
             full_document,
             extension,
             ir,
+            summary,
         } => {
             // Create output directory if it doesn't exist
             fs::create_dir_all(&output_dir)?;
@@ -2294,6 +3062,7 @@ fn handle_subcommand(cmd: Commands) -> io::Result<()> {
 
             let mut success_count = 0;
             let mut error_count = 0;
+            let mut scores = Vec::new();
 
             for file_path in files {
                 let filename = file_path
@@ -2305,7 +3074,26 @@ fn handle_subcommand(cmd: Commands) -> io::Result<()> {
 
                 match fs::read_to_string(&file_path) {
                     Ok(content) => {
-                        let result = if full_document {
+                        let scored = match direction {
+                            Direction::L2t if summary => {
+                                let report = if full_document {
+                                    latex_to_typst_with_report(&content)
+                                } else {
+                                    latex_math_to_typst_with_report(&content)
+                                };
+                                Some((report.score(), report.content))
+                            }
+                            Direction::T2l if summary && ir => {
+                                let (output, report) =
+                                    typst_to_latex_ir_and_report(&content, full_document);
+                                Some((report.score(), output))
+                            }
+                            _ => None,
+                        };
+                        let result = if let Some((score, output)) = scored {
+                            scores.push((file_path.display().to_string(), score));
+                            output
+                        } else if full_document {
                             match direction {
                                 Direction::L2t => latex_document_to_typst(&content),
                                 Direction::T2l => {
@@ -2353,12 +3141,149 @@ fn handle_subcommand(cmd: Commands) -> io::Result<()> {
                 "\nBatch conversion complete: {} succeeded, {} failed",
                 success_count, error_count
             );
+            if !scores.is_empty() {
+                print_fidelity_summary(&mut scores);
+            } else if summary && matches!(direction, Direction::T2l) && !ir {
+                eprintln!("Typst files are scored with --ir only");
+            }
 
             if error_count > 0 {
                 std::process::exit(1);
             }
         }
 
+        Commands::Ir { action } => {
+            let (input, output) = match &action {
+                IrCommand::Dump { input, output, .. } | IrCommand::Render { input, output, .. } => {
+                    (input.clone(), output.clone())
+                }
+            };
+            let content = match input.as_deref() {
+                Some(path) => fs::read_to_string(path)?,
+                None => {
+                    let mut buffer = String::new();
+                    io::stdin().read_to_string(&mut buffer)?;
+                    buffer
+                }
+            };
+
+            let result = match action {
+                IrCommand::Dump {
+                    from, environments, ..
+                } => {
+                    let doc = parse_to_ir(&content, input.as_deref(), &from, &environments);
+                    ir_to_json(&doc)
+                        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
+                }
+                IrCommand::Render {
+                    full_document,
+                    template,
+                    preamble,
+                    ..
+                } => {
+                    let doc = ir_from_json(&content).map_err(|e| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Invalid IR JSON: {}", e),
+                        )
+                    })?;
+                    let template =
+                        document_template(Some(template), preamble.as_deref())?.unwrap_or_default();
+                    ir_to_latex_with_template(&doc, full_document, template)
+                }
+            };
+
+            match output {
+                Some(path) => {
+                    let mut file = fs::File::create(&path)?;
+                    writeln!(file, "{}", result)?;
+                    eprintln!("✓ IR output written to: {}", path);
+                }
+                None => {
+                    println!("{}", result);
+                }
+            }
+        }
+
+        Commands::Stats { inputs, from, json } => {
+            let mut all = Vec::new();
+            for path in &inputs {
+                let content = fs::read_to_string(path)?;
+                let doc = parse_to_ir(&content, Some(path.as_str()), &from, &[]);
+                all.push((path, stats(&doc)));
+            }
+            if json {
+                let map: serde_json::Map<String, serde_json::Value> = all
+                    .into_iter()
+                    .map(|(path, stats)| {
+                        serde_json::to_value(stats).map(|stats| (path.clone(), stats))
+                    })
+                    .collect::<serde_json::Result<_>>()
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+                let serialized = serde_json::to_string_pretty(&map)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+                println!("{}", serialized);
+            } else {
+                for (index, (path, stats)) in all.iter().enumerate() {
+                    if index > 0 {
+                        println!();
+                    }
+                    print_stats(path, stats);
+                }
+            }
+        }
+
+        Commands::CheckRoundtrip {
+            input,
+            from,
+            compile,
+        } => {
+            let content = fs::read_to_string(&input)?;
+            let language = if is_latex_input(&content, Some(input.as_str()), &from) {
+                Language::Latex
+            } else {
+                Language::Typst
+            };
+            let trip = round_trip(&content, language);
+            let mut failed = !trip.is_faithful();
+            for difference in &trip.differences {
+                eprintln!(
+                    "✗ {}: {} before, {} after the round trip",
+                    difference.what, difference.original, difference.round_tripped
+                );
+            }
+            if compile {
+                let search_dir = Path::new(&input)
+                    .parent()
+                    .filter(|dir| !dir.as_os_str().is_empty());
+                for (language, outcome) in trip.compile(search_dir) {
+                    match outcome {
+                        Ok(outcome) if outcome.success => {
+                            eprintln!("✓ {} compiles", language.name());
+                        }
+                        Ok(outcome) => {
+                            failed = true;
+                            eprintln!("✗ {} does not compile", language.name());
+                            for error in outcome.errors {
+                                match error.line {
+                                    Some(line) => eprintln!("    line {}: {}", line, error.message),
+                                    None => eprintln!("    {}", error.message),
+                                }
+                            }
+                        }
+                        Err(err) => {
+                            failed = true;
+                            eprintln!("✗ {} could not be compiled: {}", language.name(), err);
+                        }
+                    }
+                }
+            }
+            if failed {
+                std::process::exit(1);
+            }
+            eprintln!("✓ {} keeps its structure through the round trip", input);
+        }
+
         Commands::Info => {
             println!("Tylax - High-performance bidirectional LaTeX ↔ Typst converter");
             println!("Version: {}", env!("CARGO_PKG_VERSION"));
@@ -2368,6 +3293,9 @@ fn handle_subcommand(cmd: Commands) -> io::Result<()> {
             println!("  ✓ Typst → LaTeX conversion (math + documents)");
             println!("  ✓ TikZ ↔ CeTZ graphics conversion");
             println!("  ✓ Batch file processing");
+            println!("  ✓ IR dump and render (JSON)");
+            println!("  ✓ Document statistics");
+            println!("  ✓ Round-trip checks");
             println!("  ✓ LaTeX diagnostics and checking");
             println!("  ✓ Auto-detection of input format");
             println!();
@@ -2434,6 +3362,48 @@ fn pretty_print(input: &str) -> String {
     result.trim().to_string()
 }
 
+/// Print the fidelity score of a single file's conversion, or why there is
+/// none.
+fn print_report_summary(filename: Option<&str>, report: Option<&LossReport>) {
+    match report {
+        Some(report) => {
+            let name = filename.unwrap_or("<stdin>").to_string();
+            print_fidelity_summary(&mut [(name, report.score())]);
+        }
+        None => eprintln!("No loss report to score; add --loss-log"),
+    }
+}
+
+/// Print a fidelity score per file, lowest first, so the files that need a
+/// look by hand come at the top.
+fn print_fidelity_summary(rows: &mut [(String, FidelityScore)]) {
+    rows.sort_by(|a, b| a.1.percent.total_cmp(&b.1.percent));
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max(4);
+    eprintln!(
+        "\n{:<width$}  {:>7}  {:>6}  {:>6}  Losses by kind",
+        "File", "Score", "Nodes", "Lossy"
+    );
+    for (name, score) in rows.iter() {
+        let kinds: Vec<String> = score
+            .by_kind
+            .iter()
+            .map(|(kind, count)| format!("{} {}", kind, count))
+            .collect();
+        eprintln!(
+            "{:<width$}  {:>6.1}%  {:>6}  {:>6}  {}",
+            name,
+            score.percent,
+            score.nodes,
+            score.lossy_nodes,
+            kinds.join(", ")
+        );
+    }
+    let review = rows.iter().filter(|(_, score)| score.needs_review()).count();
+    if review > 0 {
+        eprintln!("{} file(s) need manual review", review);
+    }
+}
+
 fn build_post_report_typst(output: &str) -> LossReport {
     let metrics = typst_metrics_source(output, LOSS_MARKER_PREFIX);
     let mut records = Vec::new();
@@ -2468,6 +3438,84 @@ fn build_post_report_latex(output: &str) -> LossReport {
     LossReport::new("typst", "latex", records, Vec::new())
 }
 
+/// Convert each file `input` includes, and the files those include, on its
+/// own to a `.tex` file under `out_dir`, where the `\input`s of the
+/// converted document look for it.
+#[cfg(feature = "cli")]
+fn write_included_files(
+    input: &str,
+    files: &Arc<dyn FileResolver>,
+    out_dir: &Path,
+) -> io::Result<()> {
+    let mut queue: std::collections::VecDeque<String> = included_files(input, None).into();
+    let mut converted = std::collections::HashSet::new();
+    while let Some(path) = queue.pop_front() {
+        if !converted.insert(path.clone()) {
+            continue;
+        }
+        if path.starts_with("..") {
+            eprintln!("⚠ {} is outside the input's directory; not converted", path);
+            continue;
+        }
+        let source = match files.read_file(&path) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("⚠ Unable to read included file {}: {}", path, err);
+                continue;
+            }
+        };
+        let options = LatexOutputOptions {
+            includes: Some(IncludeOptions {
+                path: Some(path.clone()),
+                ..IncludeOptions::new(files.clone(), IncludeStrategy::Input)
+            }),
+            ..LatexOutputOptions::default()
+        };
+        let latex = typst_to_latex_ir_conversion(&source, false, &options).output;
+        let target = out_dir.join(Path::new(&path).with_extension("tex"));
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, latex)?;
+        eprintln!("✓ Included file written to: {}", target.display());
+        queue.extend(included_files(&source, Some(&path)));
+    }
+    Ok(())
+}
+
+/// Print the outcome of `--verify` for the output of `name`. Returns
+/// whether compilation failed.
+#[cfg(feature = "cli")]
+fn report_verification(verification: &Verification, name: &str) -> bool {
+    let (compiler, errors) = match verification {
+        Verification::Passed { compiler } => {
+            eprintln!("✓ Verify passed ({})", compiler);
+            return false;
+        }
+        Verification::Skipped { reason } => {
+            eprintln!("⚠ --verify: {}; skipping", reason);
+            return false;
+        }
+        Verification::Failed { compiler, errors } => (compiler, errors),
+    };
+    let output = if *compiler == "typst" { "Typst" } else { "LaTeX" };
+    eprintln!("✗ Verify failed ({}):", compiler);
+    for error in errors {
+        let diagnostic =
+            CliDiagnostic::new(DiagnosticSeverity::Error, "compile error", &error.message);
+        let diagnostic = match (error.output_line, error.source_location) {
+            (Some(line), Some((source_line, column))) => diagnostic.with_location(format!(
+                "{}:{}:{} ({} line {})",
+                name, source_line, column, output, line
+            )),
+            (Some(line), None) => diagnostic.with_location(format!("{} line {}", output, line)),
+            (None, _) => diagnostic,
+        };
+        eprintln!("  {}", diagnostic);
+    }
+    true
+}
+
 /// Print diagnostics to stderr with optional color coding (unified for L2T and T2L).
 #[cfg(feature = "cli")]
 fn print_diagnostics_to_stderr(diagnostics: &[CliDiagnostic], use_color: bool) {
@@ -26,10 +26,10 @@ use super::engine::{ArgumentErrorType, EngineWarning};
 use super::{ConversionResult, ConversionWarning, WarningKind};
 
 use super::utils::{
-    attach_orphan_labels, clean_whitespace, convert_caption_text, escape_typst_string,
-    extract_arg_content, extract_arg_content_with_braces, extract_curly_inner_content,
-    protect_zero_arg_commands, replace_verb_commands, resolve_reference_markers,
-    restore_protected_commands, strip_latex_comments,
+    attach_orphan_labels, clean_whitespace, convert_caption_text, convert_dimension,
+    escape_typst_string, extract_arg_content, extract_arg_content_with_braces,
+    extract_curly_inner_content, protect_zero_arg_commands, replace_verb_commands,
+    resolve_reference_markers, restore_protected_commands, strip_latex_comments,
 };
 
 struct ElementProfileGuard {
@@ -101,6 +101,26 @@ pub struct L2TOptions {
     /// When true, macros defined with \newcommand, \def, etc. are expanded
     /// Default: true
     pub expand_macros: bool,
+
+    /// How review notes (`\todo`, `\marginpar`, `\marginnote` and
+    /// `% TODO` comments) are converted
+    /// Default: `ReviewNotes::Inline`
+    pub review_notes: ReviewNotes,
+}
+
+/// How review notes are converted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReviewNotes {
+    /// `\todo` as red text and margin notes as footnotes; `% TODO`
+    /// comments are dropped with the other comments.
+    #[default]
+    Inline,
+    /// Every note as a Typst comment, `/* TODO: ... */`, which keeps it in
+    /// the source without setting it.
+    Comments,
+    /// Every note as a call of a `todo` function defined at the top of the
+    /// document, which sets it highlighted in the text.
+    Helper,
 }
 
 impl Default for L2TOptions {
@@ -113,6 +133,7 @@ impl Default for L2TOptions {
             non_strict: true,
             optimize: true,
             expand_macros: true,
+            review_notes: ReviewNotes::Inline,
         }
     }
 }
@@ -133,6 +154,7 @@ impl L2TOptions {
             non_strict: true,
             optimize: true,
             expand_macros: true,
+            review_notes: ReviewNotes::Inline,
         }
     }
 
@@ -146,6 +168,7 @@ impl L2TOptions {
             non_strict: true,
             optimize: false,
             expand_macros: true,
+            review_notes: ReviewNotes::Inline,
         }
     }
 
@@ -210,6 +233,7 @@ pub enum EnvironmentContext {
     TikZ,
     Verbatim,
     Savequote,
+    Subequations,
     Theorem(String), // Theorem-like environment with name
 }
 
@@ -398,6 +422,8 @@ pub struct ConversionState {
     pub citation_mode: CitationMode,
     /// Custom theorem environments defined in preamble
     pub custom_theorems: HashMap<String, String>,
+    /// Column types declared with \newcolumntype (letter -> argument count, spec)
+    pub column_types: HashMap<char, (usize, String)>,
     /// Color definitions collected from preamble (name -> Typst color expression)
     pub color_defs: Vec<(String, String)>,
     /// Page margin overrides collected from preamble
@@ -419,12 +445,18 @@ pub struct ConversionState {
     /// Profiling flags and counters (debug)
     pub profile_enabled: bool,
     pub profile_nodes: usize,
+    /// Environments, formulas and commands of the parsed source, for the
+    /// loss report
+    pub source_nodes: usize,
     pub profile_step: usize,
     pub profile_last: Option<String>,
     pub profile_last_env: Option<String>,
     pub profile_start: Option<Instant>,
     /// Current recursion depth for visit_node/visit_element (stack overflow protection)
     pub visit_depth: usize,
+    /// Whether a review note was set with the `todo` helper, which the
+    /// document then defines
+    pub uses_todo_helper: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -846,6 +878,7 @@ impl LatexConverter {
         self.state.structured_warnings.clear();
         self.state.losses.clear();
         self.state.loss_seq = 0;
+        self.state.uses_todo_helper = false;
         // Only enter preamble mode if there's actually a \begin{document}
         // that is NOT inside a comment. This avoids false positives from:
         //   % \begin{document}  (commented out)
@@ -990,6 +1023,7 @@ impl LatexConverter {
         }
         // Parse with mitex-parser
         let tree = mitex_parser::parse(&expanded_input, self.spec.clone());
+        self.state.source_nodes = structure_nodes(&tree);
         mark_timing("parse", &mut last_mark, timing_enabled);
 
         // Convert AST to Typst with pre-allocated buffer
@@ -1031,6 +1065,7 @@ impl LatexConverter {
 
         // Parse
         let tree = mitex_parser::parse(&expanded_input, self.spec.clone());
+        self.state.source_nodes = structure_nodes(&tree);
 
         // Convert with pre-allocated buffer
         let mut output = String::with_capacity(expanded_input.len().max(256));
@@ -1100,7 +1135,7 @@ impl LatexConverter {
     pub fn take_loss_report(&mut self) -> LossReport {
         let losses = std::mem::take(&mut self.state.losses);
         let warnings = dedupe_string_warnings(std::mem::take(&mut self.state.warnings));
-        LossReport::new("latex", "typst", losses, warnings)
+        LossReport::new("latex", "typst", losses, warnings).with_nodes(self.state.source_nodes)
     }
 
     /// Visit a syntax node and convert it
@@ -1504,9 +1539,24 @@ impl LatexConverter {
                 }
             }
 
+            TokenComment => {
+                if self.state.options.review_notes != ReviewNotes::Inline
+                    && matches!(self.state.mode, ConversionMode::Text)
+                {
+                    let note = match &elem {
+                        SyntaxElement::Token(t) => review_comment(t.text()),
+                        SyntaxElement::Node(_) => None,
+                    };
+                    if let Some(note) = note {
+                        let content = super::utils::escape_typst_text(note);
+                        super::markup::write_review_note(self, note, &content, output);
+                    }
+                }
+            }
+
             // Ignore these
-            TokenLBrace | TokenRBrace | TokenBeginMath | TokenEndMath | TokenComment
-            | ItemBlockComment | ClauseCommandName | ItemBegin | ItemEnd | ItemBracket => {}
+            TokenLBrace | TokenRBrace | TokenBeginMath | TokenEndMath | ItemBlockComment
+            | ClauseCommandName | ItemBegin | ItemEnd | ItemBracket => {}
 
             // Command symbol
             TokenCommandSym => {
@@ -1713,6 +1763,7 @@ impl LatexConverter {
         capture_pagenumbering_hints(&mut self.state, &hint_source);
         capture_hypersetup_hints(&mut self.state, &hint_source);
         capture_color_defs(&mut self.state, &hint_source);
+        capture_column_types(&mut self.state, &hint_source);
         if preamble.contains("\\doublespacing") {
             self.state.line_spacing = Some("1.4em".to_string());
         } else if preamble.contains("\\onehalfspacing") {
@@ -2326,6 +2377,9 @@ impl LatexConverter {
             }
             doc.push('\n');
         }
+        if self.state.uses_todo_helper {
+            doc.push_str(TODO_HELPER);
+        }
         if let Some(color) = self.state.link_color.as_deref() {
             // Skip invalid color values (like "none" from unexpanded macro args)
             if !color.is_empty() && color != "none" && !color.contains('#') {
@@ -5552,19 +5606,20 @@ fn apply_geometry_options_state(state: &mut ConversionState, options: &str) {
         if let Some((key, value)) = opt.split_once('=') {
             let key = key.trim();
             let value = value.trim();
+            let length = convert_dimension(value);
             match key {
-                "margin" => state.page_margin.all = Some(value.to_string()),
-                "left" => state.page_margin.left = Some(value.to_string()),
-                "right" => state.page_margin.right = Some(value.to_string()),
-                "top" => state.page_margin.top = Some(value.to_string()),
-                "bottom" => state.page_margin.bottom = Some(value.to_string()),
+                "margin" => state.page_margin.all = Some(length),
+                "left" => state.page_margin.left = Some(length),
+                "right" => state.page_margin.right = Some(length),
+                "top" => state.page_margin.top = Some(length),
+                "bottom" => state.page_margin.bottom = Some(length),
                 "hmargin" => {
-                    state.page_margin.left = Some(value.to_string());
-                    state.page_margin.right = Some(value.to_string());
+                    state.page_margin.left = Some(length.clone());
+                    state.page_margin.right = Some(length);
                 }
                 "vmargin" => {
-                    state.page_margin.top = Some(value.to_string());
-                    state.page_margin.bottom = Some(value.to_string());
+                    state.page_margin.top = Some(length.clone());
+                    state.page_margin.bottom = Some(length);
                 }
                 "paper" => {
                     state.page_paper = Some(value.to_string());
@@ -5597,67 +5652,7 @@ fn normalize_paper_size(paper: &str) -> &str {
 fn apply_length_setting_state(state: &mut ConversionState, target: &str, value: &str) {
     let mut name = target.trim().trim_start_matches('\\').to_string();
     name.retain(|c| c.is_ascii_alphabetic());
-    // Handle LaTeX internal dimension macros first
-    let raw = value
-        .trim()
-        .trim_matches(|c| c == '{' || c == '}')
-        .replace("\\p@", "pt")
-        .replace("\\z@", "0pt")
-        .replace("\\@plus", " ")
-        .replace("\\@minus", " ");
-    let raw = raw.trim();
-    let val = if let Some(mult) = raw.strip_suffix("\\baselineskip") {
-        let factor = mult.trim();
-        if factor.is_empty() {
-            "1em".to_string()
-        } else if let Ok(value) = factor.parse::<f32>() {
-            let mut out = format!("{value:.3}");
-            while out.contains('.') && out.ends_with('0') {
-                out.pop();
-            }
-            if out.ends_with('.') {
-                out.pop();
-            }
-            format!("{out}em")
-        } else {
-            "1em".to_string()
-        }
-    } else if let Some(rest) = raw.strip_prefix("\\stretch") {
-        let rest = rest.trim();
-        if let Some(arg) = rest.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
-            let inner = arg.trim();
-            if !inner.is_empty() {
-                format!("{inner}fr")
-            } else {
-                "1fr".to_string()
-            }
-        } else {
-            "1fr".to_string()
-        }
-    } else if raw == "\\fill" || raw == "\\hfill" || raw == "\\vfill" {
-        "1fr".to_string()
-    } else if raw.ends_with("pc") {
-        let number = raw.trim_end_matches("pc").trim();
-        if let Ok(value) = number.parse::<f32>() {
-            let pts = value * 12.0;
-            let mut out = format!("{:.4}", pts);
-            while out.contains('.') && out.ends_with('0') {
-                out.pop();
-            }
-            if out.ends_with('.') {
-                out.pop();
-            }
-            format!("{out}pt")
-        } else {
-            raw.to_string()
-        }
-    } else if raw.parse::<f32>().is_ok() {
-        format!("{raw}pt")
-    } else {
-        // Handle ex unit - Typst parser can misinterpret "0.05ex" as "0.05e" (scientific notation)
-        // Convert to multiplication form to avoid parsing issues
-        fix_ex_dimension(&raw)
-    };
+    let val = convert_dimension(value);
     if name.contains("parskip") {
         state.par_skip = Some(val);
     } else if name.contains("parindent") {
@@ -5665,25 +5660,6 @@ fn apply_length_setting_state(state: &mut ConversionState, target: &str, value:
     }
 }
 
-/// Fix dimension values with 'ex' unit to avoid Typst parser issues.
-/// Typst interprets "1ex" as "1e" (scientific notation start) + "x".
-/// Convert 'ex' to 'em' with approximate ratio (1ex ≈ 0.43em).
-fn fix_ex_dimension(dim: &str) -> String {
-    let dim = dim.trim();
-    if dim.ends_with("ex") {
-        let num_part = dim.trim_end_matches("ex").trim();
-        if let Ok(val) = num_part.parse::<f64>() {
-            // Convert ex to em (1ex ≈ 0.43em based on typical x-height ratio)
-            let em_val = val * 0.43;
-            // Format with reasonable precision
-            let formatted = format!("{:.4}", em_val);
-            let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
-            return format!("{}em", formatted);
-        }
-    }
-    dim.to_string()
-}
-
 fn apply_fancy_head_state(state: &mut ConversionState, opt: &str, content: &str) {
     let text = super::utils::convert_caption_text(content);
     if opt.trim().is_empty() {
@@ -5801,6 +5777,62 @@ fn capture_hypersetup_hints(state: &mut ConversionState, input: &str) {
     }
 }
 
+/// Defines the `todo` function review notes are set with.
+const TODO_HELPER: &str =
+    "#let todo(body) = highlight(fill: yellow.lighten(40%))[*TODO:* #body]\n\n";
+
+/// The text of a `% TODO` or `% FIXME` comment, without its marker.
+fn review_comment(comment: &str) -> Option<&str> {
+    let text = comment.trim_start_matches('%').trim();
+    let rest = text
+        .strip_prefix("TODO")
+        .or_else(|| text.strip_prefix("FIXME"))?;
+    if rest.starts_with(|c: char| c.is_alphanumeric()) {
+        return None;
+    }
+    Some(rest.trim_start_matches(':').trim())
+}
+
+/// Collect `\newcolumntype{Y}[n]{spec}` declarations, so tables using the
+/// letter count and align their columns by what it stands for.
+fn capture_column_types(state: &mut ConversionState, input: &str) {
+    let stripped = strip_latex_comments(input);
+    let mut pos = 0usize;
+    while let Some(idx) = stripped[pos..].find("\\newcolumntype") {
+        let start = pos + idx + "\\newcolumntype".len();
+        pos = start;
+        let rest = stripped[start..].trim_start();
+        let name_start = stripped.len() - rest.len();
+        // The letter is usually braced, but may stand alone.
+        let (name, next) = match rest.chars().next() {
+            Some('{') => extract_braced_arg_at(&stripped, name_start),
+            Some(c) if c.is_ascii_alphabetic() => {
+                (Some(c.to_string()), Some(name_start + c.len_utf8()))
+            }
+            _ => continue,
+        };
+        let (Some(name), Some(next)) = (name, next) else {
+            continue;
+        };
+        let mut letters = name.trim().chars();
+        let (Some(letter), None) = (letters.next(), letters.next()) else {
+            continue;
+        };
+        let (arg_count, next) = extract_bracket_arg_at(&stripped, next);
+        let Some(next) = next else {
+            continue;
+        };
+        let (spec, end) = extract_braced_arg_at(&stripped, next);
+        if let Some(spec) = spec {
+            let arg_count = arg_count
+                .and_then(|count| count.trim().parse().ok())
+                .unwrap_or(0);
+            state.column_types.insert(letter, (arg_count, spec.trim().to_string()));
+        }
+        pos = end.unwrap_or(start);
+    }
+}
+
 fn capture_color_defs(state: &mut ConversionState, input: &str) {
     let stripped = strip_latex_comments(input);
     let mut pos = 0usize;
@@ -5846,3 +5878,17 @@ fn capture_color_defs(state: &mut ConversionState, input: &str) {
         pos = next2.unwrap_or(start + 1);
     }
 }
+
+/// The environments, formulas and commands of a parsed document, which the
+/// fidelity score is measured against so that a dropped environment weighs
+/// more than a single token would.
+fn structure_nodes(tree: &SyntaxNode) -> usize {
+    tree.descendants()
+        .filter(|node| {
+            matches!(
+                node.kind(),
+                SyntaxKind::ItemEnv | SyntaxKind::ItemFormula | SyntaxKind::ItemCmd
+            )
+        })
+        .count()
+}
@@ -8,7 +8,7 @@ use super::token::{TexToken, TokenList};
 use super::utils;
 use super::ArgumentErrorType;
 use super::EngineWarning;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 /// Errors that can occur during macro argument parsing
 #[derive(Debug, Clone)]
@@ -225,6 +225,9 @@ impl ExpansionState {
 pub struct ExpansionContext {
     /// Current token count during expansion (for limit checking).
     pub current_token_count: usize,
+    /// Conditionals left unexpanded and not yet closed, whose `\else` and
+    /// `\fi` are kept with them.
+    pub kept_conditionals: usize,
     /// Collected structured warnings during expansion.
     pub structured_warnings: Vec<EngineWarning>,
 }
@@ -238,6 +241,7 @@ impl ExpansionContext {
     /// Reset the context for a new expansion run.
     pub fn reset(&mut self) {
         self.current_token_count = 0;
+        self.kept_conditionals = 0;
         // Note: warnings are NOT reset here - they accumulate across runs.
         // Use `take_warnings()` to collect and clear them.
     }
@@ -577,7 +581,7 @@ impl Engine {
         depth: usize,
     ) -> Option<TokenList>
     where
-        I: Iterator<Item = TexToken>,
+        I: Iterator<Item = TexToken> + Clone,
     {
         match token {
             TexToken::ControlSeq(ref name) if name == "expandafter" => {
@@ -1080,13 +1084,38 @@ impl Engine {
     // ========================================================================
 
     /// Handle special macros that require hardcoded logic (conditionals, etc.)
+    /// A conditional that cannot be decided is left unexpanded, and so are
+    /// the `\else` and `\fi` that belong to it.
     fn handle_special_macro<I>(
+        &mut self,
+        name: &str,
+        iter: &mut std::iter::Peekable<I>,
+    ) -> Option<TokenList>
+    where
+        I: Iterator<Item = TexToken> + Clone,
+    {
+        match name {
+            "else" if self.context.kept_conditionals > 0 => return None,
+            "fi" if self.context.kept_conditionals > 0 => {
+                self.context.kept_conditionals -= 1;
+                return None;
+            }
+            _ => {}
+        }
+        let expanded = self.expand_special_macro(name, iter);
+        if expanded.is_none() && self.is_conditional(name) {
+            self.context.kept_conditionals += 1;
+        }
+        expanded
+    }
+
+    fn expand_special_macro<I>(
         &self,
         name: &str,
         iter: &mut std::iter::Peekable<I>,
     ) -> Option<TokenList>
     where
-        I: Iterator<Item = TexToken>,
+        I: Iterator<Item = TexToken> + Clone,
     {
         match name {
             "xspace" => {
@@ -1103,6 +1132,15 @@ impl Engine {
             "iffalse" => Some(self.handle_if_conditional(false, iter)),
             "ifmmode" => Some(self.handle_if_conditional(self.config.math_mode, iter)),
             "ifx" => self.handle_ifx(iter),
+            "ifdefined" => self.handle_ifdefined(iter),
+            "ifcsname" => self.handle_ifcsname(iter),
+            "ifnum" => self.handle_ifnum(iter),
+            "ifodd" => {
+                let mut lookahead = iter.clone();
+                let odd = read_integer(&mut lookahead)? % 2 != 0;
+                *iter = lookahead;
+                Some(self.handle_if_conditional(odd, iter))
+            }
             "ifstrequal" => self.handle_ifstrequal(iter),
             "else" | "fi" => Some(TokenList::new()),
             _ => None,
@@ -1122,8 +1160,7 @@ impl Engine {
         for token in iter.by_ref() {
             match &token {
                 TexToken::ControlSeq(cmd) => match cmd.as_str() {
-                    "iftrue" | "iffalse" | "ifmmode" | "ifstrequal" | "ifx" | "if" | "ifnum"
-                    | "ifdim" | "ifcat" | "ifvoid" | "ifhbox" | "ifvbox" | "ifinner" | "ifcase" => {
+                    cmd if self.is_conditional(cmd) => {
                         depth += 1;
                         if in_else {
                             false_branch.push(token);
@@ -1177,6 +1214,102 @@ impl Engine {
         }
     }
 
+    /// Whether `name` opens a conditional that a `\fi` closes: a TeX
+    /// conditional or one made with `\newif`. `\ifstrequal` and similar
+    /// take their branches as arguments instead.
+    fn is_conditional(&self, name: &str) -> bool {
+        matches!(
+            name,
+            "iftrue"
+                | "iffalse"
+                | "ifmmode"
+                | "ifx"
+                | "if"
+                | "ifnum"
+                | "ifodd"
+                | "ifdim"
+                | "ifcat"
+                | "ifvoid"
+                | "ifhbox"
+                | "ifvbox"
+                | "ifhmode"
+                | "ifvmode"
+                | "ifinner"
+                | "ifcase"
+                | "ifeof"
+                | "ifdefined"
+                | "ifcsname"
+        ) || self.state.db.get(name).is_some_and(|def| {
+            matches!(
+                def.body.as_slice(),
+                [TexToken::ControlSeq(body)] if body == "iftrue" || body == "iffalse"
+            )
+        })
+    }
+
+    /// Handle \ifdefined\name for macros defined in the document. The
+    /// engine only knows those, so a test of any other name, which may be a
+    /// LaTeX or package command, is left unexpanded.
+    fn handle_ifdefined<I>(&self, iter: &mut std::iter::Peekable<I>) -> Option<TokenList>
+    where
+        I: Iterator<Item = TexToken> + Clone,
+    {
+        let mut lookahead = iter.clone();
+        utils::skip_spaces(&mut lookahead);
+        match lookahead.next()? {
+            TexToken::ControlSeq(name) if !self.state.db.is_defined(&name) => return None,
+            _ => {}
+        }
+        *iter = lookahead;
+        Some(self.handle_if_conditional(true, iter))
+    }
+
+    /// Handle \ifcsname name\endcsname for macros defined in the document,
+    /// leaving a test of any other name unexpanded as \ifdefined does. The
+    /// name is taken as written, without expanding macros in it.
+    fn handle_ifcsname<I>(&self, iter: &mut std::iter::Peekable<I>) -> Option<TokenList>
+    where
+        I: Iterator<Item = TexToken> + Clone,
+    {
+        let mut lookahead = iter.clone();
+        let mut name = String::new();
+        loop {
+            match lookahead.next()? {
+                TexToken::ControlSeq(cs) if cs == "endcsname" => break,
+                TexToken::Char(c) => name.push(c),
+                TexToken::Space => name.push(' '),
+                _ => {}
+            }
+        }
+        if !self.state.db.is_defined(&name) {
+            return None;
+        }
+        *iter = lookahead;
+        Some(self.handle_if_conditional(true, iter))
+    }
+
+    /// Handle \ifnum with literal numbers, e.g. `\ifnum 2>1`. Counters and
+    /// other registers are not known, so an \ifnum with anything but two
+    /// numbers and a relation is left unexpanded.
+    fn handle_ifnum<I>(&self, iter: &mut std::iter::Peekable<I>) -> Option<TokenList>
+    where
+        I: Iterator<Item = TexToken> + Clone,
+    {
+        let mut lookahead = iter.clone();
+        let left = read_integer(&mut lookahead)?;
+        utils::skip_spaces(&mut lookahead);
+        let relation = lookahead.next()?;
+        let right = read_integer(&mut lookahead)?;
+        let holds = match relation {
+            TexToken::Char('<') => left < right,
+            TexToken::Char('=') => left == right,
+            TexToken::Char('>') => left > right,
+            _ => return None,
+        };
+        *iter = lookahead;
+        Some(self.handle_if_conditional(holds, iter))
+    }
+
     /// Handle \ifstrequal{str1}{str2}{true}{false}
     fn handle_ifstrequal<I>(&self, iter: &mut std::iter::Peekable<I>) -> Option<TokenList>
     where
@@ -1555,6 +1688,11 @@ impl Engine {
     }
 
     /// Read tokens until delimiter is found (respecting brace depth)
+    ///
+    /// As in TeX, a delimiter may be several tokens long and only matches
+    /// outside braces; when a partial match fails, only its first token
+    /// joins the argument and matching resumes at the next one. An argument
+    /// that is a single braced group loses its outer braces.
     fn read_delimited_argument<I>(
         &self,
         iter: &mut std::iter::Peekable<I>,
@@ -1565,6 +1703,8 @@ impl Engine {
         I: Iterator<Item = TexToken>,
     {
         let mut arg_content = Vec::new();
+        // Tokens read ahead while matching the delimiter, not yet placed.
+        let mut pending: VecDeque<TexToken> = VecDeque::new();
         let mut depth: i32 = 0;
         let mut count = 0;
         let max_arg_tokens = 10000; // Safety limit
@@ -1576,70 +1716,43 @@ impl Engine {
 
             // At depth 0, try to match delimiter
             if depth == 0 && !delimiter.is_empty() {
-                // Check if first delimiter token matches
-                if let Some(first_delim) = delimiter.first() {
-                    if let Some(next) = iter.peek() {
-                        if next == first_delim {
-                            // Potential match - try to match entire delimiter
-                            let mut match_buffer = Vec::new();
-                            let mut matched = true;
-
-                            for expected in delimiter {
-                                if let Some(tok) = iter.next() {
-                                    match_buffer.push(tok.clone());
-                                    if tok != *expected {
-                                        matched = false;
-                                        break;
-                                    }
-                                } else {
-                                    matched = false;
-                                    break;
-                                }
-                            }
-
-                            if matched {
-                                // Full match - delimiter found
-                                consumed.extend(match_buffer);
-                                return Ok(arg_content);
-                            } else {
-                                // Partial match - these tokens are part of argument
-                                for tok in match_buffer {
-                                    consumed.push(tok.clone());
-                                    match &tok {
-                                        TexToken::BeginGroup => depth += 1,
-                                        TexToken::EndGroup => depth = (depth - 1).max(0),
-                                        _ => {}
-                                    }
-                                    arg_content.push(tok);
-                                }
-                                count += 1;
-                                continue;
-                            }
-                        }
+                let mut matched = 0;
+                while matched < delimiter.len() {
+                    if matched == pending.len() {
+                        let Some(tok) = iter.next() else {
+                            break;
+                        };
+                        consumed.push(tok.clone());
+                        pending.push_back(tok);
                     }
+                    if pending[matched] != delimiter[matched] {
+                        break;
+                    }
+                    matched += 1;
+                }
+                if matched == delimiter.len() {
+                    return Ok(strip_outer_group(arg_content));
                 }
             }
 
             // Normal token reading
-            if let Some(token) = iter.next() {
-                consumed.push(token.clone());
-                match &token {
-                    TexToken::BeginGroup => {
-                        depth += 1;
-                        arg_content.push(token);
+            let token = match pending.pop_front() {
+                Some(token) => token,
+                None => match iter.next() {
+                    Some(token) => {
+                        consumed.push(token.clone());
+                        token
                     }
-                    TexToken::EndGroup => {
-                        depth = (depth - 1).max(0);
-                        arg_content.push(token);
-                    }
-                    _ => {
-                        arg_content.push(token);
-                    }
-                }
-                count += 1;
-            } else {
-                return Err(MacroError::RunawayArgument);
+                    None => return Err(MacroError::RunawayArgument),
+                },
+            };
+            match &token {
+                TexToken::BeginGroup => depth += 1,
+                TexToken::EndGroup => depth = (depth - 1).max(0),
+                _ => {}
             }
+            arg_content.push(token);
+            count += 1;
         }
     }
 
@@ -1678,6 +1791,50 @@ impl Default for Engine {
     }
 }
 
+/// Read an integer written out in digits, with an optional minus sign.
+/// A space after the last digit is consumed, as TeX does.
+fn read_integer<I>(iter: &mut std::iter::Peekable<I>) -> Option<i64>
+where
+    I: Iterator<Item = TexToken>,
+{
+    utils::skip_spaces(iter);
+    let negative = iter.next_if_eq(&TexToken::Char('-')).is_some();
+    let mut digits = String::new();
+    while let Some(TexToken::Char(c)) = iter.next_if(|t| matches!(t, TexToken::Char('0'..='9'))) {
+        digits.push(c);
+    }
+    iter.next_if_eq(&TexToken::Space);
+    let number: i64 = digits.parse().ok()?;
+    Some(if negative { -number } else { number })
+}
+
+/// `tokens` without its outer braces when it is a single braced group.
+fn strip_outer_group(tokens: Vec<TexToken>) -> Vec<TexToken> {
+    if tokens.first() != Some(&TexToken::BeginGroup) {
+        return tokens;
+    }
+    let mut depth = 0;
+    for (idx, token) in tokens.iter().enumerate() {
+        match token {
+            TexToken::BeginGroup => depth += 1,
+            TexToken::EndGroup => {
+                depth -= 1;
+                if depth == 0 {
+                    if idx + 1 != tokens.len() {
+                        return tokens;
+                    }
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return tokens;
+    }
+    tokens[1..tokens.len() - 1].to_vec()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2337,6 +2494,120 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_multi_token_delimiter_backtracks() {
+        let mut engine = Engine::new();
+        // The failed partial match "a" + "a" must not swallow the real delimiter
+        let input = tokenize(r"\def\foo#1ab{<#1>} \foo xaab rest");
+        let output = engine.process(input);
+        let result = detokenize(&output);
+        assert!(result.contains("<xa>"), "Expected <xa> in: {}", result);
+        assert!(result.contains("rest"), "Expected rest in: {}", result);
+        assert!(engine.take_structured_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_control_sequence_delimiter() {
+        let mut engine = Engine::new();
+        let input = tokenize(r"\def\pair#1\and#2\end{(#1, #2)} \pair x\and {y\and z}\end");
+        let output = engine.process(input);
+        let result = detokenize(&output);
+        assert!(
+            result.contains(r"(x, y\and z)"),
+            "Expected (x, y\\and z) in: {}",
+            result
+        );
+        assert!(engine.take_structured_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_delimited_group_loses_braces() {
+        let mut engine = Engine::new();
+        let input = tokenize(r"\def\foo#1;{<#1>} \foo{a;b}; \foo{a}{b};");
+        let output = engine.process(input);
+        let result = detokenize(&output);
+        assert!(result.contains("<a;b>"), "Expected <a;b> in: {}", result);
+        assert!(result.contains("<{a}{b}>"), "Expected <{{a}}{{b}}> in: {}", result);
+    }
+
+    #[test]
+    fn test_delimited_macro_with_ifmmode_body() {
+        let mut engine = Engine::new();
+        let input =
+            tokenize(r"\def\v#1;{\ifmmode\mathbf{#1}\else\textbf{#1}\fi} \v x; and $\v y;$");
+        let output = engine.process(input);
+        let result = detokenize(&output);
+        assert!(result.contains(r"\textbf{x}"), "Expected \\textbf{{x}} in: {}", result);
+        assert!(result.contains(r"$\mathbf{y}$"), "Expected $\\mathbf{{y}}$ in: {}", result);
+        assert!(engine.take_structured_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_skipped_branch_with_newif_conditional() {
+        let mut engine = Engine::new();
+        // The \ifdraft in the skipped branch must not close the outer \iffalse
+        let input = tokenize(r"\newif\ifdraft \iffalse A\ifdraft B\else C\fi D\else E\fi F");
+        let output = engine.process(input);
+        let result = detokenize(&output);
+        assert!(result.contains("E"), "Expected E in: {}", result);
+        assert!(result.contains("F"), "Expected F in: {}", result);
+        for skipped in ["A", "B", "C", "D"] {
+            assert!(!result.contains(skipped), "Unexpected {} in: {}", skipped, result);
+        }
+    }
+
+    #[test]
+    fn test_ifdefined_and_ifcsname() {
+        let mut engine = Engine::new();
+        let input = tokenize(concat!(
+            r"\def\foo{x}\ifdefined\foo YES\else NO\fi ",
+            r"\ifdefined\bar YES2\else NO2\fi \ifcsname foo\endcsname CS\fi",
+            r"\ifcsname chapter\endcsname CH\fi",
+        ));
+        let output = engine.process(input);
+        let result = detokenize(&output);
+        assert!(result.contains("YES"), "Expected YES in: {}", result);
+        assert!(result.contains("CS"), "Expected CS in: {}", result);
+        assert!(!result.contains("NO "), "Unexpected NO in: {}", result);
+        // Built-in commands are not known to the engine: the tests stay.
+        assert!(
+            result.contains(r"\ifdefined \bar YES2\else NO2\fi"),
+            "Expected \\ifdefined kept in: {}",
+            result
+        );
+        assert!(
+            result.contains(r"\ifcsname chapter\endcsname CH\fi"),
+            "Expected \\ifcsname kept in: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_ifnum_and_ifodd_with_literals() {
+        let mut engine = Engine::new();
+        let input = tokenize(r"\ifnum 3>2 GT\else LE\fi \ifnum -1=1 EQ\else NE\fi \ifodd 7 ODD\fi");
+        let output = engine.process(input);
+        let result = detokenize(&output);
+        assert!(result.contains("GT"), "Expected GT in: {}", result);
+        assert!(result.contains("NE"), "Expected NE in: {}", result);
+        assert!(result.contains("ODD"), "Expected ODD in: {}", result);
+        assert!(!result.contains("LE") && !result.contains("EQ"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_ifnum_and_ifodd_with_counters_are_kept() {
+        let mut engine = Engine::new();
+        let input = tokenize(r"\ifnum 1<\value{x} A\else B\fi \ifodd\value{page} C\fi");
+        let output = engine.process(input);
+        let result = detokenize(&output);
+        for kept in [
+            r"\ifnum1<\value{x} A\else B\fi",
+            r"\ifodd \value{page} C\fi",
+        ] {
+            assert!(result.contains(kept), "Expected {} in: {}", kept, result);
+        }
+    }
+
     // =====================================================================
     // Robustness Tests - DeferredParam, makeatletter, ifx
     // =====================================================================
@@ -4,13 +4,15 @@
 
 use mitex_parser::syntax::{CmdItem, EnvItem, SyntaxElement, SyntaxKind, SyntaxNode};
 use rowan::ast::AstNode;
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::time::Instant;
 
 use super::context::{ConversionMode, EnvironmentContext, LatexConverter, TemplateKind};
 use super::table::{parse_with_grid_parser, CellAlign};
 use super::utils::{
-    convert_caption_text, escape_typst_string, sanitize_label, strip_label_from_text,
+    convert_caption_text, convert_dimension, escape_typst_string, sanitize_label,
+    strip_label_from_text,
 };
 use crate::data::constants::{CodeBlockOptions, TheoremStyle, LANGUAGE_MAP, THEOREM_TYPES};
 use crate::features::images::{render_image_expr, ImageAttributes};
@@ -59,7 +61,7 @@ pub fn convert_environment(conv: &mut LatexConverter, elem: SyntaxElement, outpu
         }
 
         // Tabular environment
-        "tabular" | "tabularx" | "longtable" | "longtab" | "longtabu" | "array" => {
+        "tabular" | "tabularx" | "tabulary" | "longtable" | "longtab" | "longtabu" | "array" => {
             convert_tabular(conv, &node, output);
         }
 
@@ -420,8 +422,12 @@ pub fn convert_environment(conv: &mut LatexConverter, elem: SyntaxElement, outpu
             output.push_str("\n]\n");
         }
 
+        "subequations" => {
+            convert_subequations(conv, &node, output);
+        }
+
         // Wrapper environments that should keep inner content
-        "subequations" | "savenotes" => {
+        "savenotes" => {
             output.push('\n');
             conv.visit_env_content(&node, output);
             output.push('\n');
@@ -913,7 +919,7 @@ fn convert_tabular(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut St
 
     // Get column specification from the environment's first required argument
     let col_spec = get_tabular_col_spec(node).unwrap_or_default();
-    let columns = parse_column_spec(&col_spec);
+    let columns = parse_column_spec(&col_spec, &conv.state.column_types);
 
     // Convert column specs to CellAlign
     let alignments: Vec<CellAlign> = columns
@@ -1055,6 +1061,57 @@ fn convert_equation(
     conv.state.pop_env();
 }
 
+/// Convert a subequations group. Its equations are numbered after the
+/// group, as (3a) and (3b), and a `\label` on the group itself is set on a
+/// metadata element that references to it are resolved against.
+fn convert_subequations(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut String) {
+    let label = node
+        .children()
+        .filter_map(CmdItem::cast)
+        .find(|cmd| cmd.name_tok().is_some_and(|name| name.text() == "\\label"))
+        .and_then(|cmd| conv.get_required_arg(&cmd, 0))
+        .map(|label| sanitize_label(&label));
+
+    // Typst numbers an align block once, where LaTeX numbers each row.
+    for child in node.children().filter_map(EnvItem::cast) {
+        let name = child
+            .name_tok()
+            .map(|t| t.text().trim().to_string())
+            .unwrap_or_default();
+        let rows = child
+            .syntax()
+            .descendants_with_tokens()
+            .any(|t| t.kind() == SyntaxKind::ItemNewLine);
+        if matches!(name.as_str(), "align" | "gather" | "eqnarray") && rows {
+            conv.record_loss(
+                LossKind::UnsupportedFeature,
+                Some("subequations".to_string()),
+                format!("the rows of {} share one sub-number", name),
+                Some(child.syntax().text().to_string()),
+                Some("math".to_string()),
+            );
+        }
+    }
+
+    conv.state.push_env(EnvironmentContext::Subequations);
+    let mut body = String::new();
+    conv.visit_env_content(node, &mut body);
+    conv.state.pop_env();
+
+    output.push_str("\n#counter(math.equation).step()\n");
+    if let Some(label) = label.filter(|label| !label.is_empty()) {
+        let _ = writeln!(output, "#metadata(\"subequations\") <{}>", label);
+    }
+    output.push_str("#context {\n");
+    output.push_str("  let parent = counter(math.equation).get().first()\n");
+    output.push_str(
+        "  set math.equation(numbering: (..n) => numbering(\"(1a)\", parent, ..n.pos()))\n",
+    );
+    output.push_str("  counter(math.equation).update(0)\n  [\n");
+    output.push_str(body.trim());
+    output.push_str("\n  ]\n  counter(math.equation).update(parent)\n}\n");
+}
+
 /// Convert an align environment
 fn convert_align(
     conv: &mut LatexConverter,
@@ -1554,8 +1611,10 @@ fn convert_gameproof(conv: &mut LatexConverter, node: &SyntaxNode, output: &mut
 // =============================================================================
 
 /// Get the column specification from a tabular environment
-/// The col spec is in the first curly arg after the env name: \begin{tabular}{lccc}
+/// The col spec is the last curly arg after the env name: \begin{tabular}{lccc},
+/// or \begin{tabularx}{\linewidth}{lX} where the width comes first
 fn get_tabular_col_spec(node: &SyntaxNode) -> Option<String> {
+    let mut spec = None;
     // Look for ItemBegin, then find the column specification argument
     for child in node.children() {
         if child.kind() == SyntaxKind::ItemBegin {
@@ -1602,35 +1661,134 @@ fn get_tabular_col_spec(node: &SyntaxNode) -> Option<String> {
                         }
                         let trimmed = content.trim().to_string();
                         if !trimmed.is_empty() {
-                            return Some(trimmed);
+                            spec = Some(trimmed);
                         }
                     }
                 }
             }
         }
     }
-    None
+    spec
 }
 
 /// Parse column specification from LaTeX format (e.g., "l|ccc" -> ["l", "c", "c", "c"])
-fn parse_column_spec(spec: &str) -> Vec<String> {
+/// Letters declared with \newcolumntype are expanded, and a paragraph column
+/// takes the alignment its >{\centering} prefix gives it
+fn parse_column_spec(spec: &str, types: &HashMap<char, (usize, String)>) -> Vec<String> {
+    let mut columns = column_spec_items(spec, types, 0);
+    if columns.is_empty() {
+        columns.push("l".to_string());
+    }
+    columns
+}
+
+/// How deep \newcolumntype definitions are followed into one another
+const MAX_COLUMN_TYPE_DEPTH: usize = 8;
+
+fn column_spec_items(
+    spec: &str,
+    types: &HashMap<char, (usize, String)>,
+    depth: usize,
+) -> Vec<String> {
     let mut columns = Vec::new();
     let chars: Vec<char> = spec.chars().collect();
     let mut i = 0usize;
+    // Alignment from the >{..} in front of the next column
+    let mut pending: Option<&str> = None;
 
     while i < chars.len() {
         let c = chars[i];
         match c {
+            _ if types.contains_key(&c) && depth < MAX_COLUMN_TYPE_DEPTH => {
+                let (arg_count, definition) = &types[&c];
+                i += 1;
+                let mut expanded = definition.clone();
+                for n in 1..=*arg_count {
+                    i = skip_ws(&chars, i);
+                    let arg = if i < chars.len() && chars[i] == '{' {
+                        let (arg, next) = parse_braced_content(&chars, i)
+                            .unwrap_or_else(|| (String::new(), chars.len()));
+                        i = next;
+                        arg
+                    } else if i < chars.len() {
+                        i += 1;
+                        chars[i - 1].to_string()
+                    } else {
+                        String::new()
+                    };
+                    expanded = expanded.replace(&format!("#{}", n), &arg);
+                }
+                let mut inner_cols = column_spec_items(&expanded, types, depth + 1);
+                if let (Some(align), Some(first)) = (pending.take(), inner_cols.first_mut()) {
+                    *first = align.to_string();
+                }
+                columns.extend(inner_cols);
+            }
             'l' | 'c' | 'r' => {
+                pending = None;
                 columns.push(c.to_string());
                 i += 1;
             }
-            'p' | 'm' | 'b' | 'X' | 'S' => {
+            // tabulary's L, C, R and J
+            'L' | 'C' | 'R' | 'J' => {
+                let align = if c == 'J' {
+                    'l'
+                } else {
+                    c.to_ascii_lowercase()
+                };
+                pending = None;
+                columns.push(align.to_string());
+                i += 1;
+            }
+            'p' | 'm' | 'b' => {
                 i += 1;
                 i = skip_ws(&chars, i);
                 if i < chars.len() && chars[i] == '{' {
                     i = skip_braced_group(&chars, i);
                 }
+                columns.push(pending.take().unwrap_or("l").to_string());
+            }
+            // X and S take their options in brackets
+            'X' | 'S' => {
+                i += 1;
+                i = skip_ws(&chars, i);
+                if i < chars.len() && chars[i] == '[' {
+                    i = skip_bracket_group(&chars, i);
+                }
+                columns.push(pending.take().unwrap_or("l").to_string());
+            }
+            // w{align}{width} and W{align}{width}
+            'w' | 'W' => {
+                i += 1;
+                i = skip_ws(&chars, i);
+                let mut align = "l";
+                if i < chars.len() && chars[i] == '{' {
+                    if let Some((align_str, next)) = parse_braced_content(&chars, i) {
+                        align = match align_str.trim() {
+                            "c" => "c",
+                            "r" => "r",
+                            _ => "l",
+                        };
+                        i = next;
+                    }
+                }
+                i = skip_ws(&chars, i);
+                if i < chars.len() && chars[i] == '{' {
+                    i = skip_braced_group(&chars, i);
+                }
+                pending = None;
+                columns.push(align.to_string());
+            }
+            // dcolumn's D{sep}{out sep}{digits}
+            'D' => {
+                i += 1;
+                for _ in 0..3 {
+                    i = skip_ws(&chars, i);
+                    if i < chars.len() && chars[i] == '{' {
+                        i = skip_braced_group(&chars, i);
+                    }
+                }
+                pending = None;
                 columns.push("l".to_string());
             }
             '*' => {
@@ -1648,7 +1806,7 @@ fn parse_column_spec(spec: &str) -> Vec<String> {
                 i = skip_ws(&chars, i);
                 if i < chars.len() && chars[i] == '{' {
                     if let Some((spec_str, next)) = parse_braced_content(&chars, i) {
-                        let inner_cols = parse_column_spec(&spec_str);
+                        let inner_cols = column_spec_items(&spec_str, types, depth);
                         for _ in 0..count {
                             columns.extend(inner_cols.clone());
                         }
@@ -1658,20 +1816,38 @@ fn parse_column_spec(spec: &str) -> Vec<String> {
                     }
                 }
             }
-            '>' | '<' => {
+            '>' => {
                 i += 1;
                 i = skip_ws(&chars, i);
                 if i < chars.len() && chars[i] == '{' {
-                    i = skip_braced_group(&chars, i);
+                    if let Some((prefix, next)) = parse_braced_content(&chars, i) {
+                        pending = prefix_alignment(&prefix);
+                        i = next;
+                    } else {
+                        i = chars.len();
+                    }
                 }
             }
-            '@' | '!' => {
+            '<' | '@' | '!' => {
                 i += 1;
                 i = skip_ws(&chars, i);
                 if i < chars.len() && chars[i] == '{' {
                     i = skip_braced_group(&chars, i);
                 }
             }
+            // Arguments of a type not known here, which are not columns
+            '{' => {
+                i = skip_braced_group(&chars, i);
+            }
+            '[' => {
+                i = skip_bracket_group(&chars, i);
+            }
+            '\\' => {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                    i += 1;
+                }
+            }
             '|' => {
                 i += 1;
             }
@@ -1684,7 +1860,7 @@ fn parse_column_spec(spec: &str) -> Vec<String> {
                 if i < chars.len() && chars[i] == '{' {
                     i = skip_braced_group(&chars, i);
                 }
-                columns.push("l".to_string());
+                columns.push(pending.take().unwrap_or("l").to_string());
             }
             _ => {
                 i += 1;
@@ -1692,11 +1868,28 @@ fn parse_column_spec(spec: &str) -> Vec<String> {
         }
     }
 
-    if columns.is_empty() {
-        columns.push("l".to_string());
+    columns
+}
+
+/// The alignment a >{..} column prefix sets, as in >{\centering\arraybackslash}
+fn prefix_alignment(prefix: &str) -> Option<&'static str> {
+    let prefix = prefix.to_lowercase();
+    if prefix.contains("\\centering") {
+        Some("c")
+    } else if prefix.contains("\\raggedleft") {
+        Some("r")
+    } else if prefix.contains("\\raggedright") {
+        Some("l")
+    } else {
+        None
     }
+}
 
-    columns
+fn skip_bracket_group(chars: &[char], start: usize) -> usize {
+    chars[start..]
+        .iter()
+        .position(|&c| c == ']')
+        .map_or(chars.len(), |offset| start + offset + 1)
 }
 
 fn skip_ws(chars: &[char], mut i: usize) -> usize {
@@ -1756,22 +1949,27 @@ fn parse_braced_content(chars: &[char], start: usize) -> Option<(String, usize)>
 #[cfg(test)]
 mod tests {
     use super::parse_column_spec;
+    use std::collections::HashMap;
+
+    fn parse(spec: &str) -> Vec<String> {
+        parse_column_spec(spec, &HashMap::new())
+    }
 
     #[test]
     fn test_parse_column_spec_with_modifiers() {
-        let cols = parse_column_spec(r">{\raggedright\arraybackslash}p{2cm}c");
+        let cols = parse(r">{\raggedright\arraybackslash}p{2cm}c");
         assert_eq!(cols, vec!["l".to_string(), "c".to_string()]);
     }
 
     #[test]
     fn test_parse_column_spec_with_repeat_and_custom() {
-        let cols = parse_column_spec(r"l|*{2}{>{\centering}m{1cm}}r");
+        let cols = parse(r"l|*{2}{>{\centering}m{1cm}}r");
         assert_eq!(
             cols,
             vec![
                 "l".to_string(),
-                "l".to_string(),
-                "l".to_string(),
+                "c".to_string(),
+                "c".to_string(),
                 "r".to_string()
             ]
         );
@@ -1779,33 +1977,30 @@ mod tests {
 
     #[test]
     fn test_parse_column_spec_custom_types() {
-        let cols = parse_column_spec(r"S D{.}{.}{-1} c");
+        let cols = parse(r"S D{.}{.}{-1} c");
         assert_eq!(
             cols,
             vec!["l".to_string(), "l".to_string(), "c".to_string()]
         );
     }
-}
 
-/// Convert a LaTeX dimension to Typst
-fn convert_dimension(dim: &str) -> String {
-    let dim = dim.trim();
-
-    if dim.contains("\\linewidth") || dim.contains("\\textwidth") {
-        if let Some(mult) = dim
-            .strip_suffix("\\linewidth")
-            .or(dim.strip_suffix("\\textwidth"))
-        {
-            let mult = mult.trim();
-            if mult.is_empty() || mult == "1" {
-                return "100%".to_string();
-            }
-            if let Ok(f) = mult.parse::<f32>() {
-                return format!("{}%", (f * 100.0) as i32);
-            }
-        }
-        return "100%".to_string();
+    #[test]
+    fn test_parse_column_spec_inter_column_material() {
+        let cols = parse(r"@{}l!{\vrule width 1pt}S[table-format=2.1]@{\hspace{2em}}r@{}");
+        assert_eq!(
+            cols,
+            vec!["l".to_string(), "l".to_string(), "r".to_string()]
+        );
+        assert_eq!(parse(r"*{3}{c}|w{r}{2cm}").len(), 4);
     }
 
-    dim.to_string()
+    #[test]
+    fn test_parse_column_spec_newcolumntype() {
+        let mut types = HashMap::new();
+        types.insert('Y', (0, r">{\centering\arraybackslash}X".to_string()));
+        types.insert('P', (1, r">{\raggedleft\arraybackslash}p{#1}".to_string()));
+        types.insert('Z', (0, "YY".to_string()));
+        let cols = parse_column_spec(r"lY*{2}{P{2cm}}Z", &types);
+        assert_eq!(cols, vec!["l", "c", "r", "r", "c", "c"]);
+    }
 }
@@ -22,11 +22,11 @@ use mitex_spec::CommandSpecItem;
 
 use super::context::{
     CitationMode, ConversionMode, EnvironmentContext, LatexConverter, MacroDef, PendingHeading,
-    PendingOperator,
+    PendingOperator, ReviewNotes,
 };
 use super::utils::{
-    convert_caption_text, escape_typst_string, escape_typst_text, sanitize_citation_key,
-    sanitize_label, to_roman_numeral,
+    convert_caption_text, convert_dimension, escape_typst_string, escape_typst_text,
+    sanitize_citation_key, sanitize_label, to_roman_numeral,
 };
 use crate::features::images::{render_image_expr, ImageAttributes};
 use crate::utils::loss::{LossKind, LOSS_MARKER_PREFIX};
@@ -357,6 +357,30 @@ fn write_inline_raw(output: &mut String, content: &str, lang: Option<&str>) {
     let _ = write!(output, "#raw(\"{}\")", escaped);
 }
 
+/// Set a review note, such as a `\todo` or a `% TODO` comment, as the
+/// `review_notes` option asks. `raw` is the source of the note and `content`
+/// the note converted to Typst.
+pub(crate) fn write_review_note(
+    conv: &mut LatexConverter,
+    raw: &str,
+    content: &str,
+    output: &mut String,
+) {
+    match conv.state.options.review_notes {
+        ReviewNotes::Inline => {}
+        ReviewNotes::Comments => {
+            let text = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+            // Typst block comments nest, so neither may appear in the text.
+            let text = text.replace("/*", "/ *").replace("*/", "* /");
+            let _ = write!(output, "/* TODO: {} */", text);
+        }
+        ReviewNotes::Helper => {
+            conv.state.uses_todo_helper = true;
+            let _ = write!(output, "#todo[{}]", content.trim());
+        }
+    }
+}
+
 /// Protect content that contains commas by wrapping in `{}`.
 ///
 /// In Typst function calls like `sqrt(content)`, a comma inside `content`
@@ -1055,9 +1079,12 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         "tablefootmark" => {
             output.push_str("#footnote[]");
         }
-        "marginnote" => {
+        "marginnote" | "marginpar" => {
             if let Some(content) = conv.convert_required_arg(&cmd, 0) {
-                if !content.trim().is_empty() {
+                if conv.state.options.review_notes != ReviewNotes::Inline {
+                    let raw = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+                    write_review_note(conv, &raw, &content, output);
+                } else if base_name == "marginnote" && !content.trim().is_empty() {
                     let _ = write!(output, "#footnote[{}]", content.trim());
                 }
             }
@@ -1667,9 +1694,13 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         "label" => {
             // Skip label output if we're inside equation/align environments
             // because those environments handle labels at the end of the math block
+            // A subequations group sets its own label, too.
             if conv.state.is_inside(&EnvironmentContext::Equation)
                 || conv.state.is_inside(&EnvironmentContext::Align)
-                || matches!(conv.state.current_env(), EnvironmentContext::Theorem(_))
+                || matches!(
+                    conv.state.current_env(),
+                    EnvironmentContext::Theorem(_) | EnvironmentContext::Subequations
+                )
             {
                 return;
             }
@@ -1725,7 +1756,17 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         "numberthis" => {
             // Common in align* to force equation numbers; treat as no-op.
         }
-        "ref" | "autoref" | "cref" | "Cref" | "cref*" | "Cref*" | "crefrange" | "Crefrange"
+        "crefrange" | "Crefrange" => {
+            let from = sanitize_label(conv.get_required_arg(&cmd, 0).unwrap_or_default().trim());
+            let to = sanitize_label(conv.get_required_arg(&cmd, 1).unwrap_or_default().trim());
+            if !from.is_empty() {
+                let _ = write!(output, "__TYLAX_REF__{}__", from);
+            }
+            if !to.is_empty() {
+                let _ = write!(output, "–__TYLAX_REF__{}__", to);
+            }
+        }
+        "ref" | "autoref" | "cref" | "Cref" | "cref*" | "Cref*"
         | "secref" | "figref" | "Figref" | "tabref" | "thmref" | "lemref" | "propref"
         | "appref" | "Appref" => {
             let labels = conv.get_required_arg(&cmd, 0).unwrap_or_default();
@@ -2189,7 +2230,12 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         }
         "todo" => {
             let content = conv.convert_required_arg(&cmd, 0).unwrap_or_default();
-            let _ = write!(output, "#text(fill: red)[{}]", content);
+            if conv.state.options.review_notes != ReviewNotes::Inline {
+                let raw = conv.get_required_arg(&cmd, 0).unwrap_or_default();
+                write_review_note(conv, &raw, &content, output);
+            } else {
+                let _ = write!(output, "#text(fill: red)[{}]", content);
+            }
         }
         "doccmd" | "doccmddef" | "doccmdnoindex" => {
             let name = conv.get_required_arg(&cmd, 0).unwrap_or_default();
@@ -4786,7 +4832,7 @@ pub fn convert_command(conv: &mut LatexConverter, elem: SyntaxElement, output: &
         | "goodbreak" | "penalty" | "kern" | "hss" | "looseness" | "xspace"
         | "interlinepenalty" | "midsloppy" | "raggedbottom" | "doublespace"
         // Margin and page setup
-        | "marginpar" | "marginparpush" | "reversemarginpar" | "normalmarginpar"
+        | "marginparpush" | "reversemarginpar" | "normalmarginpar"
         // Misc invisible commands (excluding already handled: protect)
         | "expandafter" | "global" | "long" | "outer" | "inner"
         | "noexpand" | "csname" | "endcsname" | "string" | "number" 
@@ -5662,103 +5708,6 @@ fn expand_user_macro(conv: &mut LatexConverter, cmd: &CmdItem, macro_def: &Macro
     output
 }
 
-/// Convert a LaTeX dimension to Typst
-fn convert_dimension(dim: &str) -> String {
-    let dim = dim.trim();
-
-    // Handle LaTeX internal dimension macros
-    // \p@ = 1pt, \z@ = 0pt
-    // Strip trailing \par which can appear after \vskip
-    let dim = dim
-        .replace("\\p@", "pt")
-        .replace("\\z@", "0pt")
-        .replace("\\@plus", " ")
-        .replace("\\@minus", " ")
-        .replace("\\par", "");
-    let dim = dim.trim();
-
-    if let Some(rest) = dim.strip_prefix("\\stretch") {
-        let rest = rest.trim();
-        if let Some(arg) = rest.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
-            let value = arg.trim();
-            if !value.is_empty() {
-                return format!("{}fr", value);
-            }
-        }
-        return "1fr".to_string();
-    }
-
-    if dim == "\\fill" || dim == "\\hfill" || dim == "\\vfill" {
-        return "1fr".to_string();
-    }
-
-    // Handle \linewidth, \textwidth, etc.
-    if dim.contains("\\linewidth") || dim.contains("\\textwidth") || dim.contains("\\columnwidth") {
-        // Extract multiplier if present
-        if let Some(mult) = dim.strip_suffix("\\linewidth") {
-            let mult = mult.trim();
-            if mult.is_empty() || mult == "1" {
-                return "100%".to_string();
-            }
-            if let Ok(f) = mult.parse::<f32>() {
-                return format!("{}%", (f * 100.0) as i32);
-            }
-        }
-        if let Some(mult) = dim.strip_suffix("\\textwidth") {
-            let mult = mult.trim();
-            if mult.is_empty() || mult == "1" {
-                return "100%".to_string();
-            }
-            if let Ok(f) = mult.parse::<f32>() {
-                return format!("{}%", (f * 100.0) as i32);
-            }
-        }
-        return "100%".to_string();
-    }
-
-    // Handle standard units
-    let dim = dim
-        .replace("\\fill", "1fr")
-        .replace("\\hfill", "1fr");
-
-    if dim.ends_with("pc") {
-        let number = dim.trim_end_matches("pc").trim();
-        if let Ok(value) = number.parse::<f32>() {
-            let pts = value * 12.0;
-            let mut out = format!("{:.4}", pts);
-            while out.contains('.') && out.ends_with('0') {
-                out.pop();
-            }
-            if out.ends_with('.') {
-                out.pop();
-            }
-            return format!("{out}pt");
-        }
-    }
-
-    // Already has a unit
-    if dim.ends_with("pt")
-        || dim.ends_with("em")
-        || dim.ends_with("ex")
-        || dim.ends_with("mm")
-        || dim.ends_with("cm")
-        || dim.ends_with("in")
-        || dim.ends_with("pc")
-        || dim.ends_with("bp")
-        || dim.ends_with("%")
-        || dim.ends_with("fr")
-    {
-        return dim;
-    }
-
-    // Just a number, assume pt
-    if dim.parse::<f32>().is_ok() {
-        return format!("{}pt", dim);
-    }
-
-    dim
-}
-
 /// Apply a text accent to a character
 fn apply_text_accent(content: &str, accent: char) -> String {
     let c = content.chars().next().unwrap_or(' ');
@@ -5889,8 +5838,14 @@ fn apply_cedilla(content: &str) -> String {
 
 /// Convert section heading with proper level
 fn convert_section(conv: &mut LatexConverter, cmd: &CmdItem, level: u8, output: &mut String) {
-    let title = conv
-        .convert_required_arg(cmd, 0)
+    // Titles with inline math go through the caption converter, which
+    // keeps subscripts and math commands inside `$...$` on the heading line.
+    let math_title = conv
+        .get_required_arg(cmd, 0)
+        .filter(|raw| (raw.contains('$') || raw.contains("\\(")) && !raw.contains("\\label"))
+        .map(|raw| convert_caption_text(&raw.replace("\\(", "$").replace("\\)", "$")));
+    let title = math_title
+        .or_else(|| conv.convert_required_arg(cmd, 0))
         .or_else(|| conv.get_required_arg(cmd, 0))
         .map(|t| t.trim().to_string())
         .filter(|t| !t.is_empty());
@@ -6020,19 +5975,20 @@ fn apply_geometry_options(conv: &mut LatexConverter, options: &str) {
         if let Some((key, value)) = opt.split_once('=') {
             let key = key.trim();
             let value = value.trim();
+            let length = convert_dimension(value);
             match key {
-                "margin" => conv.state.page_margin.all = Some(value.to_string()),
-                "left" => conv.state.page_margin.left = Some(value.to_string()),
-                "right" => conv.state.page_margin.right = Some(value.to_string()),
-                "top" => conv.state.page_margin.top = Some(value.to_string()),
-                "bottom" => conv.state.page_margin.bottom = Some(value.to_string()),
+                "margin" => conv.state.page_margin.all = Some(length),
+                "left" => conv.state.page_margin.left = Some(length),
+                "right" => conv.state.page_margin.right = Some(length),
+                "top" => conv.state.page_margin.top = Some(length),
+                "bottom" => conv.state.page_margin.bottom = Some(length),
                 "hmargin" => {
-                    conv.state.page_margin.left = Some(value.to_string());
-                    conv.state.page_margin.right = Some(value.to_string());
+                    conv.state.page_margin.left = Some(length.clone());
+                    conv.state.page_margin.right = Some(length);
                 }
                 "vmargin" => {
-                    conv.state.page_margin.top = Some(value.to_string());
-                    conv.state.page_margin.bottom = Some(value.to_string());
+                    conv.state.page_margin.top = Some(length.clone());
+                    conv.state.page_margin.bottom = Some(length);
                 }
                 "paper" => {
                     conv.state.page_paper = Some(value.to_string());
@@ -41,7 +41,7 @@ pub mod utils;
 // Re-export main types for convenience
 pub use context::{
     ConversionMode, ConversionState, EnvironmentContext, L2TOptions, LatexConverter, MacroDef,
-    MERGED_SPEC,
+    ReviewNotes, MERGED_SPEC,
 };
 
 // =============================================================================
@@ -6,6 +6,7 @@ use crate::data::symbols::GREEK_LETTERS;
 use mitex_parser::syntax::{SyntaxElement, SyntaxKind, SyntaxNode};
 use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
+use tylax_units::Length;
 
 // =============================================================================
 // Text Processing Utilities
@@ -2386,16 +2387,21 @@ pub fn resolve_reference_markers(input: &str) -> String {
     const PAGEREF_MARKER: &str = "__TYLAX_PAGEREF__";
 
     let labels = collect_emitted_labels(input);
+    let groups = collect_subequation_groups(input);
 
     let mut out = replace_marker(input, REF_MARKER, |label| {
-        if labels.contains(label) {
+        if groups.contains(label) {
+            group_reference(label, "1")
+        } else if labels.contains(label) {
             format!("@{}", label)
         } else {
             format!("\\@{}", label)
         }
     });
     out = replace_marker(&out, EQREF_MARKER, |label| {
-        if labels.contains(label) {
+        if groups.contains(label) {
+            group_reference(label, "(1)")
+        } else if labels.contains(label) {
             format!("(@{})", label)
         } else {
             format!("(\\@{})", label)
@@ -2411,6 +2417,28 @@ pub fn resolve_reference_markers(input: &str) -> String {
     out
 }
 
+/// Labels of subequations groups, which are set on metadata and so cannot
+/// be referenced with `@`.
+fn collect_subequation_groups(input: &str) -> HashSet<String> {
+    const GROUP_MARKER: &str = "#metadata(\"subequations\") <";
+    input
+        .match_indices(GROUP_MARKER)
+        .filter_map(|(idx, _)| {
+            let rest = &input[idx + GROUP_MARKER.len()..];
+            rest.find('>').map(|end| rest[..end].to_string())
+        })
+        .collect()
+}
+
+/// A link to a subequations group showing the number the group was given,
+/// set with `pattern`.
+fn group_reference(label: &str, pattern: &str) -> String {
+    format!(
+        "#link(<{0}>, context numbering(\"{1}\", ..counter(math.equation).at(<{0}>)))",
+        label, pattern
+    )
+}
+
 /// Attach standalone label lines to the previous non-empty line.
 pub fn attach_orphan_labels(input: &str) -> String {
     let mut lines: Vec<String> = Vec::new();
@@ -3100,6 +3128,18 @@ pub fn extract_curly_inner_content(node: &SyntaxNode) -> String {
     content
 }
 
+// =============================================================================
+// Lengths
+// =============================================================================
+
+/// Convert a LaTeX dimension to Typst; what is not a length, such as
+/// `2\parindent`, is kept as written
+pub fn convert_dimension(dim: &str) -> String {
+    // A \vskip takes the \par that ends it along
+    let dim = dim.trim().trim_end_matches("\\par").trim();
+    Length::parse_latex(dim).map_or_else(|| dim.to_string(), |length| length.to_typst())
+}
+
 // =============================================================================
 // Caption Text Conversion
 // =============================================================================
@@ -3215,8 +3255,9 @@ pub fn convert_caption_text(text: &str) -> String {
                     result.push(']');
                 }
             } else {
+                // Padding inside `$...$` would make the math display-style.
                 result.push('$');
-                result.push_str(&converted);
+                result.push_str(trimmed);
                 result.push('$');
             }
         } else if ch == '\\' {
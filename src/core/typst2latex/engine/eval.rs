@@ -262,48 +262,63 @@ impl MiniEval {
     /// This recursively traverses the content tree and applies matching rules.
     /// Rules are applied in reverse priority order (later rules take precedence).
     pub fn apply_show_rules(&mut self, content: Vec<ContentNode>) -> EvalResult<Vec<ContentNode>> {
+        let rules = self.show_rules.clone();
+        self.apply_rules(&rules, content)
+    }
+
+    /// Apply `rules` to `content`; the result of a transform is not revisited.
+    fn apply_rules(
+        &mut self,
+        rules: &[ShowRule],
+        content: Vec<ContentNode>,
+    ) -> EvalResult<Vec<ContentNode>> {
         let mut result = Vec::new();
 
         for node in content {
             // Find the first matching rule (by highest priority)
-            let matching_rule = self
-                .show_rules
+            let matching_rule = rules
                 .iter()
                 .rev()
                 .find(|rule| rule.selector.matches(&node))
                 .cloned();
 
             if let Some(rule) = matching_rule {
-                // The transform function receives the matched content as "it"
-                // For MVP, we pass the node as a Value
-                let node_value = Value::Content(vec![node.clone()]);
-
-                // Create a scope with "it" bound to the matched content
-                self.scopes.enter();
-                self.scopes.define("it".to_string(), node_value);
-
-                // Call the closure with no explicit args (it uses "it" from scope)
-                let body_result = self.call_closure_inner(&rule.transform);
-
-                self.scopes.exit();
-
-                // Convert result to content
-                match body_result {
-                    Ok(Value::Content(nodes)) => {
-                        result.extend(nodes);
-                    }
-                    Ok(other) => {
-                        // Convert other values to text content
-                        result.push(ContentNode::Text(other.display()));
+                // Text selectors only replace the matched part of a text node
+                if let ContentNode::Text(text) = &node {
+                    if let Some(parts) = split_text_matches(&rule.selector, text) {
+                        for (matched, part) in parts {
+                            if matched {
+                                let replaced =
+                                    self.transform_node(&rule, ContentNode::Text(part.clone()));
+                                result.extend(
+                                    replaced.unwrap_or_else(|| vec![ContentNode::Text(part)]),
+                                );
+                            } else {
+                                result.push(ContentNode::Text(part));
+                            }
+                        }
+                        continue;
                     }
-                    Err(_) => {
-                        // On error, keep the original node
-                        result.push(node);
+                }
+                match self.transform_node(&rule, node.clone()) {
+                    // A replaced heading or block stays separated from its
+                    // surroundings even when the transform returns inline content
+                    Some(nodes) if is_block_node(&node) => {
+                        if !nodes.first().is_some_and(is_block_node) {
+                            push_block_break(&mut result);
+                        }
+                        result.extend(nodes);
+                        if !result.last().is_some_and(is_block_node) {
+                            result.push(ContentNode::Parbreak);
+                        }
                     }
+                    Some(nodes) => result.extend(nodes),
+                    // On error, keep the original node
+                    None => result.push(node),
                 }
             } else {
                 // No matching rule, recursively process children
-                let processed = self.process_content_children(node)?;
+                let processed = self.process_content_children(rules, node)?;
                 result.push(processed);
             }
         }
@@ -311,6 +326,41 @@ impl MiniEval {
         Ok(result)
     }
 
+    /// Run a rule's transform on one matched node, or `None` if it fails.
+    fn transform_node(&mut self, rule: &ShowRule, node: ContentNode) -> Option<Vec<ContentNode>> {
+        // The transform function receives the matched content as "it"
+        let node_value = Value::Content(vec![node]);
+        let transform = &rule.transform;
+        let body_result = if transform.body_source.is_empty() {
+            // Element functions such as `underline` wrap the match
+            let name = transform
+                .name
+                .as_deref()
+                .and_then(|name| name.strip_prefix("<builtin:"))
+                .and_then(|name| name.strip_suffix('>'))?;
+            let wrapper = self.create_builtin_wrapper(name);
+            self.apply_closure(&wrapper, vec![node_value])
+        } else if transform.params.is_empty() {
+            // Constant transforms see the matched content as "it"
+            self.scopes.enter();
+            self.scopes.define("it".to_string(), node_value);
+            let value = self.call_closure_inner(transform);
+            self.scopes.exit();
+            value
+        } else {
+            self.apply_closure(transform, vec![node_value])
+        };
+
+        // Convert result to content
+        match body_result {
+            Ok(Value::Content(nodes)) => Some(nodes),
+            Ok(Value::None) => Some(Vec::new()),
+            // Convert other values to text content
+            Ok(other) => Some(vec![ContentNode::Text(other.display())]),
+            Err(_) => None,
+        }
+    }
+
     /// Call a closure's body without binding parameters (assumes scope is set up).
     fn call_closure_inner(&mut self, closure: &Closure) -> EvalResult<Value> {
         // Parse and evaluate the body
@@ -332,29 +382,33 @@ impl MiniEval {
     }
 
     /// Recursively process children of a content node.
-    fn process_content_children(&mut self, node: ContentNode) -> EvalResult<ContentNode> {
+    fn process_content_children(
+        &mut self,
+        rules: &[ShowRule],
+        node: ContentNode,
+    ) -> EvalResult<ContentNode> {
         match node {
             ContentNode::Strong(children) => {
-                let processed = self.apply_show_rules(children)?;
+                let processed = self.apply_rules(rules, children)?;
                 Ok(ContentNode::Strong(processed))
             }
             ContentNode::Emph(children) => {
-                let processed = self.apply_show_rules(children)?;
+                let processed = self.apply_rules(rules, children)?;
                 Ok(ContentNode::Emph(processed))
             }
             ContentNode::Heading { level, content } => {
-                let processed = self.apply_show_rules(content)?;
+                let processed = self.apply_rules(rules, content)?;
                 Ok(ContentNode::Heading {
                     level,
                     content: processed,
                 })
             }
             ContentNode::ListItem(children) => {
-                let processed = self.apply_show_rules(children)?;
+                let processed = self.apply_rules(rules, children)?;
                 Ok(ContentNode::ListItem(processed))
             }
             ContentNode::EnumItem { number, content } => {
-                let processed = self.apply_show_rules(content)?;
+                let processed = self.apply_rules(rules, content)?;
                 Ok(ContentNode::EnumItem {
                     number,
                     content: processed,
@@ -365,11 +419,102 @@ impl MiniEval {
         }
     }
 
-    /// Evaluate markup (the top-level content).
-    pub fn eval_markup(&mut self, markup: ast::Markup) -> EvalResult<Value> {
+    /// Evaluate a `#show` rule into a rule that can be applied to the content
+    /// after it. Returns `None` for forms that stay in the output as source:
+    /// show-set rules, everything rules (`#show: ...`) and unknown selectors.
+    fn eval_show_rule(&mut self, show: ast::ShowRule) -> EvalResult<Option<ShowRule>> {
+        let Some(selector_expr) = show.selector() else {
+            return Ok(None);
+        };
+        let transform_expr = show.transform();
+        if matches!(transform_expr, ast::Expr::SetRule(_)) {
+            return Ok(None);
+        }
+        let Some(selector) = self.eval_show_selector(selector_expr)? else {
+            return Ok(None);
+        };
+
+        let function = match transform_expr {
+            ast::Expr::Closure(_) | ast::Expr::Ident(_) | ast::Expr::FieldAccess(_) => {
+                match self.eval_expr(transform_expr)? {
+                    Value::Func(func) => Some(func),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        // Any other expression is a replacement evaluated per match
+        let transform = function.unwrap_or_else(|| {
+            Arc::new(Closure {
+                name: None,
+                params: Vec::new(),
+                defaults: Vec::new(),
+                sink: None,
+                body_source: transform_expr.to_untyped().clone().into_text().to_string(),
+                captures: IndexMap::new(),
+            })
+        });
+        Ok(Some(ShowRule::new(
+            selector,
+            transform,
+            self.show_rules.len(),
+        )))
+    }
+
+    /// Evaluate the selector of a show rule.
+    fn eval_show_selector(&mut self, expr: ast::Expr) -> EvalResult<Option<Selector>> {
+        // `heading.where(level: 1)` would otherwise evaluate to a passthrough call
+        if let ast::Expr::FuncCall(call) = expr {
+            if let ast::Expr::FieldAccess(access) = call.callee() {
+                if let ast::Expr::Ident(ident) = access.target() {
+                    if access.field().get() == "where" {
+                        let (_, named) = self.eval_args(call.args())?;
+                        return Ok(Some(Selector::element_with_filters(
+                            ident.get().as_str(),
+                            named.into_iter().collect(),
+                        )));
+                    }
+                }
+            }
+        }
+        let selector = match self.eval_expr(expr)? {
+            Value::Selector(selector) => Some(selector),
+            Value::Str(text) if !text.is_empty() => Some(Selector::String(text)),
+            Value::Regex(pattern) => Some(Selector::Regex(pattern)),
+            Value::Label(label) => Some(Selector::Label(label)),
+            Value::Func(func) => func
+                .name
+                .as_deref()
+                .and_then(|name| name.strip_prefix("<builtin:"))
+                .and_then(|name| name.strip_suffix('>'))
+                .map(Selector::element),
+            _ => None,
+        };
+        Ok(selector)
+    }
+
+    /// Evaluate a sequence of markup or code expressions. A show rule applies
+    /// to everything after it in the same sequence.
+    fn eval_sequence<'a>(
+        &mut self,
+        mut exprs: impl Iterator<Item = ast::Expr<'a>>,
+    ) -> EvalResult<Value> {
         let mut output = Value::None;
 
-        for expr in markup.exprs() {
+        while let Some(expr) = exprs.next() {
+            if let ast::Expr::ShowRule(show) = expr {
+                if let Some(rule) = self.eval_show_rule(show)? {
+                    let rest = self.eval_sequence(exprs)?;
+                    let content = match rest {
+                        Value::Content(nodes) => nodes,
+                        Value::None => Vec::new(),
+                        other => vec![ContentNode::Text(other.display())],
+                    };
+                    let transformed = self.apply_rules(&[rule], content)?;
+                    return ops::join(output, Value::Content(transformed));
+                }
+            }
+
             let value = self.eval_expr(expr)?;
             output = ops::join(output, value)?;
 
@@ -381,6 +526,11 @@ impl MiniEval {
         Ok(output)
     }
 
+    /// Evaluate markup (the top-level content).
+    pub fn eval_markup(&mut self, markup: ast::Markup) -> EvalResult<Value> {
+        self.eval_sequence(markup.exprs())
+    }
+
     /// Evaluate an expression.
     /// Evaluate an expression.
     ///
@@ -986,18 +1136,7 @@ impl MiniEval {
 
     /// Evaluate code (a sequence of expressions).
     fn eval_code(&mut self, code: ast::Code) -> EvalResult<Value> {
-        let mut output = Value::None;
-
-        for expr in code.exprs() {
-            let value = self.eval_expr(expr)?;
-            output = ops::join(output, value)?;
-
-            if self.flow.is_some() {
-                break;
-            }
-        }
-
-        Ok(output)
+        self.eval_sequence(code.exprs())
     }
 
     /// Evaluate a content block.
@@ -1791,7 +1930,16 @@ impl MiniEval {
                 .get(field)
                 .cloned()
                 .ok_or(EvalError::key_not_found(field.to_string())),
-            Value::Content(_) => {
+            Value::Content(nodes) => {
+                // Fields of a single element, e.g. `it.body` inside a show rule
+                let mut elements = nodes
+                    .iter()
+                    .filter(|node| !matches!(node, ContentNode::Space));
+                if let (Some(node), None) = (elements.next(), elements.next()) {
+                    if let Some(value) = node.fields().get(field) {
+                        return Ok(value.clone());
+                    }
+                }
                 // Content field access - preserve as raw
                 let source = access.to_untyped().text().to_string();
                 Ok(Value::Content(vec![ContentNode::RawSource(source)]))
@@ -2253,11 +2401,69 @@ pub fn expand_macros(source: &str) -> EvalResult<String> {
     Ok(result.output)
 }
 
+/// Whether a content node renders as its own block in Typst source.
+fn is_block_node(node: &ContentNode) -> bool {
+    matches!(
+        node,
+        ContentNode::Heading { .. }
+            | ContentNode::ListItem(_)
+            | ContentNode::EnumItem { .. }
+            | ContentNode::Parbreak
+            | ContentNode::Raw { block: true, .. }
+            | ContentNode::Math { block: true, .. }
+    )
+}
+
+/// End the current paragraph before block-level content is appended.
+fn push_block_break(result: &mut Vec<ContentNode>) {
+    while matches!(result.last(), Some(ContentNode::Space)) {
+        result.pop();
+    }
+    if !result.is_empty() && !result.last().is_some_and(is_block_node) {
+        result.push(ContentNode::Parbreak);
+    }
+}
+
+/// Split `text` into unmatched and matched parts for a string or regex
+/// selector. Returns `None` for other selectors or when nothing matches.
+fn split_text_matches(selector: &Selector, text: &str) -> Option<Vec<(bool, String)>> {
+    let ranges: Vec<(usize, usize)> = match selector {
+        Selector::String(needle) if !needle.is_empty() => text
+            .match_indices(needle.as_str())
+            .map(|(start, found)| (start, start + found.len()))
+            .collect(),
+        Selector::Regex(pattern) => pattern
+            .0
+            .find_iter(text)
+            .filter(|found| !found.is_empty())
+            .map(|found| (found.start(), found.end()))
+            .collect(),
+        _ => return None,
+    };
+    if ranges.is_empty() {
+        return None;
+    }
+    let mut parts = Vec::new();
+    let mut last = 0;
+    for (start, end) in ranges {
+        if start > last {
+            parts.push((false, text[last..start].to_string()));
+        }
+        parts.push((true, text[start..end].to_string()));
+        last = end;
+    }
+    if last < text.len() {
+        parts.push((false, text[last..].to_string()));
+    }
+    Some(parts)
+}
+
 /// Normalize whitespace in content nodes.
 ///
 /// This function:
 /// 1. Removes consecutive Space nodes (keeps only one)
 /// 2. Removes Space nodes before ListItem/EnumItem (they should start at column 0)
+/// 3. Removes Space nodes right after a Parbreak
 ///
 /// This prevents indentation accumulation in loops like `#for x in arr [- #x]`
 fn normalize_content_whitespace(nodes: Vec<ContentNode>) -> Vec<ContentNode> {
@@ -2267,8 +2473,8 @@ fn normalize_content_whitespace(nodes: Vec<ContentNode>) -> Vec<ContentNode> {
     for node in nodes {
         match &node {
             ContentNode::Space => {
-                // Skip consecutive spaces
-                if !prev_was_space {
+                // Skip consecutive spaces and spaces opening a paragraph
+                if !prev_was_space && !matches!(result.last(), Some(ContentNode::Parbreak)) {
                     result.push(node);
                     prev_was_space = true;
                 }
@@ -3280,4 +3486,50 @@ sum(1..10)=#sum-to(10)
         assert!(result.contains("1"), "Should have 1: {}", result);
         assert!(result.contains("3"), "Should have 3: {}", result);
     }
+
+    #[test]
+    fn test_show_rule_transforms_following_headings() {
+        let code = "= Before\n#show heading.where(level: 1): it => [*#it.body*]\n= Title\n== Sub\n";
+        let result = expand_macros(code).unwrap();
+        assert!(
+            result.contains("= Before"),
+            "Earlier heading kept: {}",
+            result
+        );
+        assert!(
+            result.contains("*Title*\n\n"),
+            "Heading transformed: {}",
+            result
+        );
+        assert!(result.contains("== Sub"), "Level 2 untouched: {}", result);
+    }
+
+    #[test]
+    fn test_show_rule_string_and_element_functions() {
+        let result = expand_macros("#show \"TODO\": [*todo*]\nFix TODO now.").unwrap();
+        assert_eq!(result, "Fix *todo* now.");
+
+        let result = expand_macros("#show link: underline\nSee https://typst.app.").unwrap();
+        assert!(
+            result.contains("#underline([https://typst.app])"),
+            "Link wrapped: {}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_show_rule_scoped_to_code_block() {
+        let code = "#let conf(doc) = { show strong: it => emph(it.body); doc }\n#conf[a *b*] *c*";
+        let result = expand_macros(code).unwrap();
+        assert!(
+            result.contains("#emph([b])"),
+            "Rule applied inside: {}",
+            result
+        );
+        assert!(
+            result.contains("*c*"),
+            "Rule not applied outside: {}",
+            result
+        );
+    }
 }
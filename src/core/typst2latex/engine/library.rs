@@ -730,10 +730,12 @@ fn builtin_csv(
         return Err(EvalError::other("Custom CSV delimiters not yet supported"));
     }
 
-    let has_header = if let Some(row_type) = named.get("row-type") {
-        row_type.as_str()? == "dictionary"
-    } else {
-        true // default is dictionary which implies header? Typst default is `dictionary`.
+    // Typst reads rows as arrays unless `row-type: dictionary` asks for
+    // dictionaries keyed by the header row.
+    let has_header = match named.get("row-type") {
+        Some(Value::Str(name)) => name == "dictionary",
+        Some(Value::Func(func)) => func.name.as_deref() == Some("<builtin:dictionary>"),
+        _ => false,
     };
 
     data::parse_csv(&content, has_header)
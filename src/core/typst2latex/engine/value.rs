@@ -32,12 +32,17 @@ pub enum LengthUnit {
 impl LengthUnit {
     /// Convert a value in this unit to points.
     pub fn to_pt(&self, value: f64) -> Option<f64> {
+        // Em is context-dependent, and has no font size here.
+        tylax_units::Length::new(value, self.unit()).to_pt(0.0)
+    }
+
+    fn unit(&self) -> tylax_units::Unit {
         match self {
-            LengthUnit::Pt => Some(value),
-            LengthUnit::Mm => Some(value * 2.835),
-            LengthUnit::Cm => Some(value * 28.35),
-            LengthUnit::In => Some(value * 72.0),
-            LengthUnit::Em => None, // Em is context-dependent
+            LengthUnit::Pt => tylax_units::Unit::Pt,
+            LengthUnit::Mm => tylax_units::Unit::Mm,
+            LengthUnit::Cm => tylax_units::Unit::Cm,
+            LengthUnit::In => tylax_units::Unit::In,
+            LengthUnit::Em => tylax_units::Unit::Em,
         }
     }
 
@@ -458,6 +463,8 @@ impl Selector {
                     ("list", ContentNode::ListItem(_)) => true,
                     ("enum", ContentNode::EnumItem { .. }) => true,
                     ("text", ContentNode::Text(_)) => true,
+                    // Bare URLs are kept as source but still count as links
+                    ("link", ContentNode::RawSource(source)) => is_bare_link(source),
                     // Element variant matches by name
                     (elem_name, ContentNode::Element { name: n, .. }) if elem_name == n => true,
                     // FuncCall matches by function name
@@ -507,38 +514,7 @@ impl Selector {
 
     /// Extract fields from a content node for filter matching.
     fn get_node_fields(&self, node: &ContentNode) -> IndexMap<String, Value> {
-        match node {
-            ContentNode::Element { fields, .. } => fields.clone(),
-            ContentNode::Heading { level, content } => {
-                let mut fields = IndexMap::new();
-                fields.insert("level".to_string(), Value::Int(*level as i64));
-                fields.insert("body".to_string(), Value::Content(content.clone()));
-                fields
-            }
-            ContentNode::Raw { text, lang, block } => {
-                let mut fields = IndexMap::new();
-                fields.insert("text".to_string(), Value::Str(text.clone()));
-                if let Some(l) = lang {
-                    fields.insert("lang".to_string(), Value::Str(l.clone()));
-                }
-                fields.insert("block".to_string(), Value::Bool(*block));
-                fields
-            }
-            ContentNode::Math { content, block } => {
-                let mut fields = IndexMap::new();
-                fields.insert("body".to_string(), Value::Str(content.clone()));
-                fields.insert("block".to_string(), Value::Bool(*block));
-                fields
-            }
-            ContentNode::EnumItem { number, .. } => {
-                let mut fields = IndexMap::new();
-                if let Some(n) = number {
-                    fields.insert("number".to_string(), Value::Int(*n));
-                }
-                fields
-            }
-            _ => IndexMap::new(),
-        }
+        node.fields()
     }
 
     /// Create an element selector from a string.
@@ -1266,6 +1242,12 @@ pub enum Arg {
 // Show Rules
 // ============================================================================
 
+/// Whether raw source is an auto-detected URL such as `https://typst.app`.
+fn is_bare_link(source: &str) -> bool {
+    (source.starts_with("http://") || source.starts_with("https://"))
+        && !source.contains(char::is_whitespace)
+}
+
 /// A show rule that transforms matched content.
 #[derive(Debug, Clone)]
 pub struct ShowRule {
@@ -1289,6 +1271,92 @@ impl ShowRule {
 }
 
 impl ContentNode {
+    /// Fields of this node as seen by show rules, e.g. `it.body` or `it.level`.
+    pub fn fields(&self) -> IndexMap<String, Value> {
+        match self {
+            ContentNode::Element { fields, .. } => fields.clone(),
+            ContentNode::Heading { level, content } => {
+                let mut fields = IndexMap::new();
+                fields.insert("level".to_string(), Value::Int(*level as i64));
+                fields.insert("body".to_string(), Value::Content(content.clone()));
+                fields
+            }
+            ContentNode::Raw { text, lang, block } => {
+                let mut fields = IndexMap::new();
+                fields.insert("text".to_string(), Value::Str(text.clone()));
+                if let Some(l) = lang {
+                    fields.insert("lang".to_string(), Value::Str(l.clone()));
+                }
+                fields.insert("block".to_string(), Value::Bool(*block));
+                fields
+            }
+            ContentNode::Math { content, block } => {
+                let mut fields = IndexMap::new();
+                fields.insert("body".to_string(), Value::Str(content.clone()));
+                fields.insert("block".to_string(), Value::Bool(*block));
+                fields
+            }
+            ContentNode::Strong(content)
+            | ContentNode::Emph(content)
+            | ContentNode::ListItem(content) => {
+                let mut fields = IndexMap::new();
+                fields.insert("body".to_string(), Value::Content(content.clone()));
+                fields
+            }
+            ContentNode::EnumItem { number, content } => {
+                let mut fields = IndexMap::new();
+                if let Some(n) = number {
+                    fields.insert("number".to_string(), Value::Int(*n));
+                }
+                fields.insert("body".to_string(), Value::Content(content.clone()));
+                fields
+            }
+            ContentNode::Text(text) => {
+                let mut fields = IndexMap::new();
+                fields.insert("text".to_string(), Value::Str(text.clone()));
+                fields
+            }
+            ContentNode::FuncCall { name, args } => {
+                let mut fields = IndexMap::new();
+                let positional: Vec<&Value> = args
+                    .iter()
+                    .filter_map(|arg| match arg {
+                        Arg::Pos(value) => Some(value),
+                        _ => None,
+                    })
+                    .collect();
+                if name == "link" {
+                    if let Some(dest) = positional.first() {
+                        fields.insert("dest".to_string(), (*dest).clone());
+                    }
+                }
+                if let Some(body) = positional
+                    .iter()
+                    .rev()
+                    .find(|value| matches!(value, Value::Content(_)))
+                {
+                    fields.insert("body".to_string(), (*body).clone());
+                }
+                for arg in args {
+                    if let Arg::Named(key, value) = arg {
+                        fields.insert(key.clone(), value.clone());
+                    }
+                }
+                fields
+            }
+            ContentNode::RawSource(source) if is_bare_link(source) => {
+                let mut fields = IndexMap::new();
+                fields.insert("dest".to_string(), Value::Str(source.clone()));
+                fields.insert(
+                    "body".to_string(),
+                    Value::Content(vec![ContentNode::Text(source.clone())]),
+                );
+                fields
+            }
+            _ => IndexMap::new(),
+        }
+    }
+
     /// Convert this content node back to Typst source code.
     pub fn to_typst(&self) -> String {
         match self {
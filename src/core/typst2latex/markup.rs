@@ -8,12 +8,13 @@ use super::table::{LatexCell, LatexCellAlign, LatexHLine, LatexTableGenerator};
 use super::utils::{
     count_heading_markers, escape_latex_text, extract_length_value, get_raw_text_with_lang,
     get_simple_text, get_string_content, is_color_name, is_display_math, is_string_or_content,
-    typst_color_to_latex, FuncArgs,
+    is_typst_color, typst_color_to_latex, FuncArgs,
 };
 use crate::data::typst_compat::{
     get_heading_command, is_math_func_in_markup, MarkupHandler, TYPST_MARKUP_HANDLERS,
 };
 use crate::tikz::{convert_cetz_to_tikz, is_cetz_code};
+use tylax_units::Length;
 use typst_syntax::{SyntaxKind, SyntaxNode};
 
 /// Languages supported by the listings package (case-insensitive check)
@@ -1166,14 +1167,12 @@ fn convert_text_func(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
                 SyntaxKind::FuncCall => {
                     // Could be a color function like blue.lighten(50%)
                     let func_text = get_simple_text(child);
-                    if func_text.contains('.') {
-                        let base_color = func_text.split('.').next().unwrap_or("");
-                        if is_color_name(base_color) {
-                            color = Some(base_color.to_string());
-                        }
+                    if is_typst_color(&func_text) {
+                        color = Some(func_text);
+                    } else {
+                        // Or it could be content (like a nested function)
+                        content_nodes.push(child);
                     }
-                    // Or it could be content (like a nested function)
-                    content_nodes.push(child);
                 }
                 _ => {
                     // Check for positional content (not comma/paren)
@@ -1195,7 +1194,7 @@ fn convert_text_func(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
     // Apply color first (outermost wrapper)
     if let Some(c) = &color {
         let latex_color = typst_color_to_latex(c);
-        ctx.push(&format!("\\textcolor{{{}}}", latex_color));
+        ctx.push(&format!("\\textcolor{}", latex_color));
         ctx.push("{");
         suffix_count += 1;
     }
@@ -1357,14 +1356,14 @@ fn convert_rect_func(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
             // Optionally wrap in minipage if width specified
             if let Some(w) = width {
                 let latex_width = convert_dimension_to_latex(w);
-                ctx.push(&format!("\\colorbox{{{}}}", color));
+                ctx.push(&format!("\\colorbox{}", color));
                 ctx.push(&format!("{{\\begin{{minipage}}{{{}}}", latex_width));
                 for node in &content_nodes {
                     convert_markup_node(node, ctx);
                 }
                 ctx.push("\\end{minipage}}");
             } else {
-                ctx.push(&format!("\\colorbox{{{}}}", color));
+                ctx.push(&format!("\\colorbox{}", color));
                 ctx.push("{");
                 for node in &content_nodes {
                     convert_markup_node(node, ctx);
@@ -1378,12 +1377,12 @@ fn convert_rect_func(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
                 .map(convert_dimension_to_latex)
                 .unwrap_or_else(|| "\\linewidth".to_string());
             ctx.push(&format!(
-                "{{\\color{{{}}}\\rule{{{}}}{{{}}}}}",
+                "{{\\color{}\\rule{{{}}}{{{}}}}}",
                 color, latex_width, latex_height
             ));
         } else {
             // Has fill but no height and no content - just colorbox with empty
-            ctx.push(&format!("\\colorbox{{{}}}{{}}", color));
+            ctx.push(&format!("\\colorbox{}{{}}", color));
         }
     } else if let Some(h) = height {
         // No fill, but has height - black rule
@@ -1439,15 +1438,10 @@ fn convert_image_to_latex(children: &[&SyntaxNode], ctx: &mut ConvertContext) {
 /// Convert Typst dimension to LaTeX dimension
 fn convert_dimension_to_latex(dim: &str) -> String {
     let dim = dim.trim();
-    if dim.ends_with('%') {
-        // Convert percentage to \textwidth
-        let percent = dim.trim_end_matches('%');
-        if let Ok(p) = percent.parse::<f64>() {
-            return format!("{:.2}\\textwidth", p / 100.0);
-        }
-    }
-    // Pass through other dimensions (pt, cm, mm, in, em)
-    dim.to_string()
+    Length::parse_typst(dim).map_or_else(
+        || dim.to_string(),
+        |length| length.to_latex_relative_to("\\textwidth"),
+    )
 }
 
 // ============================================================================
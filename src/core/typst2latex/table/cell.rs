@@ -4,7 +4,7 @@ use typst_syntax::SyntaxNode;
 
 use crate::core::typst2latex::context::{ConvertContext, EnvironmentContext};
 use crate::core::typst2latex::markup::convert_markup_node;
-use crate::core::typst2latex::utils::{get_simple_text, is_color_name, typst_color_to_latex};
+use crate::core::typst2latex::utils::{get_simple_text, is_typst_color, typst_color_to_latex};
 
 /// LaTeX cell alignment options
 #[allow(dead_code)]
@@ -143,26 +143,7 @@ impl LatexCell {
                                             // Store the complete color expression for proper conversion
                                             // Examples: "blue", "blue.lighten(80%)", "rgb(255, 0, 0)"
                                             let value_trimmed = value.trim();
-
-                                            // Check if it starts with a known color name
-                                            if is_color_name(value_trimmed) {
-                                                fill = Some(value_trimmed.to_string());
-                                            } else if value_trimmed.contains('.') {
-                                                // Color with method call: blue.lighten(80%)
-                                                let base = value_trimmed
-                                                    .split('.')
-                                                    .next()
-                                                    .unwrap_or("")
-                                                    .trim();
-                                                if is_color_name(base) {
-                                                    // Store the FULL expression, not just base
-                                                    fill = Some(value_trimmed.to_string());
-                                                }
-                                            } else if value_trimmed.starts_with("rgb")
-                                                || value_trimmed.starts_with("luma")
-                                                || value_trimmed.starts_with("cmyk")
-                                            {
-                                                // Store as-is for potential future handling
+                                            if is_typst_color(value_trimmed) {
                                                 fill = Some(value_trimmed.to_string());
                                             }
                                         }
@@ -235,7 +216,7 @@ impl LatexCell {
         // Add cell color if present
         if let Some(ref color) = self.fill {
             let latex_color = typst_color_to_latex(color);
-            prefix.push_str(&format!("\\cellcolor{{{}}} ", latex_color));
+            prefix.push_str(&format!("\\cellcolor{} ", latex_color));
         }
 
         let content = format!("{}{}", prefix, content_str);
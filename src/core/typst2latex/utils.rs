@@ -2,9 +2,10 @@
 //!
 //! Helper functions for text escaping, content extraction, etc.
 
-use crate::data::colors::TYPST_TO_LATEX_COLORS;
 use lazy_static::lazy_static;
 use std::collections::HashMap;
+use tylax_color::Color;
+use tylax_units::{Length, Unit};
 use typst_syntax::{SyntaxKind, SyntaxNode};
 
 lazy_static! {
@@ -207,92 +208,29 @@ pub fn escape_latex_text(text: &str) -> String {
         .replace('^', "\\textasciicircum{}")
 }
 
-/// Check if a string is a known color name
+/// Check if a string is a color name Typst or xcolor knows
 pub fn is_color_name(s: &str) -> bool {
-    matches!(
-        s.to_lowercase().as_str(),
-        "black"
-            | "white"
-            | "red"
-            | "green"
-            | "blue"
-            | "yellow"
-            | "cyan"
-            | "magenta"
-            | "orange"
-            | "purple"
-            | "pink"
-            | "brown"
-            | "gray"
-            | "grey"
-            | "lime"
-            | "olive"
-            | "navy"
-            | "teal"
-            | "aqua"
-            | "maroon"
-            | "silver"
-            | "fuchsia"
-    )
+    Color::from_name(s).is_some()
 }
 
-/// Convert Typst color to LaTeX color name (returns owned String for complex expressions)
-pub fn typst_color_to_latex(color: &str) -> String {
-    let color = color.trim();
-
-    // Handle color expressions like "purple.lighten(80%)" or "red.darken(20%)"
-    if color.contains('.') {
-        let parts: Vec<&str> = color.split('.').collect();
-        if let Some(base_color) = parts.first() {
-            let base = simple_color_to_latex(base_color.trim());
-
-            // Check for lighten/darken
-            if parts.len() > 1 {
-                let method = parts[1];
-                if method.starts_with("lighten(") {
-                    // Extract percentage: lighten(80%) -> 80
-                    if let Some(pct) = extract_percentage(method) {
-                        // LaTeX xcolor: color!percentage!white for lighten
-                        let remaining = 100 - pct;
-                        return format!("{}!{}!white", base, remaining);
-                    }
-                } else if method.starts_with("darken(") {
-                    // Extract percentage: darken(20%) -> 20
-                    if let Some(pct) = extract_percentage(method) {
-                        // LaTeX xcolor: color!percentage!black for darken
-                        let remaining = 100 - pct;
-                        return format!("{}!{}!black", base, remaining);
-                    }
-                } else if method.starts_with("transparentize(") || method.starts_with("opacify(") {
-                    // Transparency - just return base color (LaTeX doesn't handle this well in tables)
-                    return base.to_string();
-                }
-            }
-            return base.to_string();
-        }
-    }
-
-    simple_color_to_latex(color).to_string()
+/// Check if a Typst expression is a color, such as `blue.lighten(80%)` or
+/// `rgb(255, 0, 0)`, rather than a variable of the document
+pub fn is_typst_color(expr: &str) -> bool {
+    Color::parse_typst(expr).is_some_and(|color| !matches!(color, Color::Custom(_)))
 }
 
-/// Extract percentage from method call like "lighten(80%)" -> 80
-fn extract_percentage(method: &str) -> Option<i32> {
-    // Find content between ( and )
-    let start = method.find('(')?;
-    let end = method.find(')')?;
-    let content = &method[start + 1..end];
-    // Remove % sign and parse
-    let num_str = content.trim().trim_end_matches('%');
-    num_str.parse::<i32>().ok()
-}
-
-/// Simple color name to LaTeX mapping (returns &'static str)
-fn simple_color_to_latex(color: &str) -> &'static str {
-    // Data-driven mapping from centralized configuration
-    if let Some(tex) = TYPST_TO_LATEX_COLORS.get(color.to_lowercase().as_str()) {
-        return tex;
+/// Convert a Typst color to the argument `\textcolor`, `\colorbox` and
+/// `\cellcolor` take, with the xcolor model where it is given by value
+///
+/// Handles expressions like "purple.lighten(80%)" (`{purple!20!white}`) and
+/// `rgb("#ff4136")` (`[HTML]{FF4136}`); unknown colors fall back to black.
+pub fn typst_color_to_latex(color: &str) -> String {
+    let color = Color::parse_typst(color).filter(|color| !matches!(color, Color::Custom(_)));
+    match color.map(|color| color.to_latex()) {
+        Some((Some(model), value)) => format!("[{}]{{{}}}", model, value),
+        Some((None, expression)) => format!("{{{}}}", expression),
+        None => "{black}".to_string(),
     }
-    "black" // Default fallback
 }
 
 /// Check if a node kind represents string or content
@@ -460,29 +398,15 @@ pub fn extract_length_value(text: &str) -> Option<String> {
         .trim_start_matches('(')
         .trim_end_matches(')')
         .trim();
-
-    // Common LaTeX-compatible units
-    let units = ["em", "ex", "pt", "pc", "in", "cm", "mm", "bp", "sp"];
-
-    for unit in units {
-        if text.ends_with(unit) {
-            let num_part = text.trim_end_matches(unit).trim();
-            // Verify it's a valid number
-            if num_part.parse::<f64>().is_ok() {
-                return Some(format!("{}{}", num_part, unit));
-            }
-        }
+    // A bare number is no length here, and a share of the space left over
+    // has no LaTeX length.
+    if !text.ends_with(|c: char| c.is_ascii_alphabetic() || c == '%') {
+        return None;
     }
-
-    // Handle percentage -> \textwidth
-    if text.ends_with('%') {
-        let num_part = text.trim_end_matches('%').trim();
-        if let Ok(percent) = num_part.parse::<f64>() {
-            return Some(format!("{:.2}\\textwidth", percent / 100.0));
-        }
-    }
-
-    None
+    Length::parse_typst(text)
+        .or_else(|| Length::parse_latex(text))
+        .filter(|length| length.unit != Unit::Fr)
+        .map(|length| length.to_latex_relative_to("\\textwidth"))
 }
 
 // ============================================================================
@@ -16,7 +16,7 @@ pub mod symbols;
 pub mod typst_compat;
 
 // Re-export commonly used items
-pub use colors::{convert_color_commands, parse_color_expression, NAMED_COLORS};
+pub use colors::{convert_color_commands, parse_color_expression};
 pub use constants::{
     AcronymDef, CodeBlockOptions, GlossaryDef, TheoremInfo, TheoremStyle, LANGUAGE_MAP,
     THEOREM_TYPES,
@@ -26,6 +26,8 @@
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::fmt::Write;
+use tylax_color::Color;
+use tylax_units::{Length, Unit};
 
 lazy_static! {
     // Coordinate patterns (used in Coordinate::parse)
@@ -1011,106 +1013,16 @@ fn parse_relative_position(input: &str) -> Option<RelativePosition> {
 
 /// Check if a string is a known TikZ/LaTeX color name
 fn is_color_name(s: &str) -> bool {
-    matches!(
-        s,
-        "red"
-            | "green"
-            | "blue"
-            | "yellow"
-            | "cyan"
-            | "magenta"
-            | "black"
-            | "white"
-            | "gray"
-            | "grey"
-            | "orange"
-            | "purple"
-            | "brown"
-            | "pink"
-            | "lime"
-            | "olive"
-            | "teal"
-            | "violet"
-            | "darkgray"
-            | "lightgray"
-            | "darkblue"
-            | "darkred"
-            | "darkgreen"
-    )
+    Color::from_name(s).is_some()
 }
 
 /// Convert TikZ color to CeTZ/Typst color
-/// Handles various TikZ color mixing syntaxes:
-/// - "green!20" = 20% green + 80% white → green.lighten(80%)
-/// - "green!60!black" = 60% green + 40% black → color.mix((green, 60%), (black, 40%))
-/// - "red!50!blue!30!white" = nested mixing (processed left to right)
+/// Handles xcolor's mixing syntax, e.g. "green!20" (20% green, 80% white) or
+/// "green!60!black"; colors the document defines keep their name
 fn convert_color(color: &str) -> String {
-    // Handle TikZ color mixing syntax
-    if color.contains('!') {
-        let parts: Vec<&str> = color.split('!').collect();
-
-        // Three-part syntax: color1!percent!color2 (e.g., green!60!black)
-        if parts.len() == 3 {
-            let color1 = parts[0].trim();
-            let color2 = parts[2].trim();
-            if let Ok(pct1) = parts[1].trim().parse::<f64>() {
-                let pct2 = 100.0 - pct1;
-                // Typst color.mix syntax
-                return format!(
-                    "color.mix(({}, {:.0}%), ({}, {:.0}%))",
-                    normalize_color_name(color1),
-                    pct1,
-                    normalize_color_name(color2),
-                    pct2
-                );
-            }
-        }
-        // Two-part syntax: color!percent (e.g., green!20 = 20% green + 80% white)
-        else if parts.len() == 2 {
-            let base_color = parts[0].trim();
-            if let Ok(percentage) = parts[1].trim().parse::<f64>() {
-                // TikZ green!20 = 20% green, 80% white → CeTZ green.lighten(80%)
-                let lighten_pct = 100.0 - percentage;
-                return format!(
-                    "{}.lighten({:.0}%)",
-                    normalize_color_name(base_color),
-                    lighten_pct
-                );
-            }
-        }
-        // More complex mixing (4+ parts): process iteratively
-        else if parts.len() > 3 {
-            // For complex cases like red!50!blue!30!white, use nested mix
-            // This is a simplified approach - full support would need recursive mixing
-            let color1 = parts[0].trim();
-            if let Ok(pct1) = parts[1].trim().parse::<f64>() {
-                // Take first three parts and recurse for the rest
-                let remaining = parts[2..].join("!");
-                let mixed_rest = convert_color(&remaining);
-                let pct2 = 100.0 - pct1;
-                return format!(
-                    "color.mix(({}, {:.0}%), ({}, {:.0}%))",
-                    normalize_color_name(color1),
-                    pct1,
-                    mixed_rest,
-                    pct2
-                );
-            }
-        }
-    }
-
-    normalize_color_name(color)
-}
-
-/// Normalize color name to Typst-compatible format
-fn normalize_color_name(color: &str) -> String {
-    match color.trim() {
-        "gray" | "grey" => "gray".to_string(),
-        "darkgray" | "darkgrey" => "luma(64)".to_string(),
-        "lightgray" | "lightgrey" => "luma(192)".to_string(),
-        c if c.starts_with('#') => format!("rgb(\"{}\")", c),
-        c => c.to_string(),
-    }
+    Color::parse_latex(color)
+        .map(|color| color.to_typst())
+        .unwrap_or_else(|| color.trim().to_string())
 }
 
 /// A parsed TikZ drawing command
@@ -1947,14 +1859,7 @@ fn convert_relative_position_to_cetz(rel_pos: &RelativePosition) -> String {
             let unit = d.trim_start_matches(|c: char| c.is_numeric() || c == '.' || c == '-');
             let num: f64 = num_str.parse().unwrap_or(1.0);
 
-            // Convert to approximate cm
-            match unit {
-                "cm" => num,
-                "mm" => num / 10.0,
-                "pt" => num / 28.35, // Approximate
-                "in" => num * 2.54,
-                _ => num,
-            }
+            convert_dimension_to_cm(num, unit)
         })
         .unwrap_or(1.0);
 
@@ -2609,27 +2514,21 @@ fn parse_dimension(val: &str) -> Option<f64> {
 
     // Extract unit and convert to pt
     let unit = trimmed.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
-    match unit.trim() {
-        "pt" | "" => Some(num),
-        "cm" => Some(num * 28.35),
-        "mm" => Some(num * 2.835),
-        "in" => Some(num * 72.0),
-        _ => Some(num),
-    }
+    let points = Unit::parse_latex(unit.trim())
+        .and_then(|unit| Length::new(num, unit).to_pt(DEFAULT_FONT_SIZE));
+    Some(points.unwrap_or(num))
 }
 
+/// Font size in points `em` and `ex` are taken of, as in a 10pt document
+const DEFAULT_FONT_SIZE: f64 = 10.0;
+
 /// Convert a dimension value with unit to cm (TikZ default unit)
-/// This is used for polar coordinates where the radius may have a unit suffix
+/// This is used for polar coordinates where the radius may have a unit suffix.
+/// A bare number or an unknown unit is taken as cm.
 fn convert_dimension_to_cm(value: f64, unit: &str) -> f64 {
-    match unit.trim().to_lowercase().as_str() {
-        "cm" | "" => value,    // cm is default, no conversion needed
-        "mm" => value / 10.0,  // 10mm = 1cm
-        "pt" => value / 28.35, // ~28.35pt = 1cm
-        "in" => value * 2.54,  // 1in = 2.54cm
-        "em" => value * 0.35,  // approximate: 1em ≈ 0.35cm (depends on font)
-        "ex" => value * 0.15,  // approximate: 1ex ≈ 0.15cm
-        _ => value,            // unknown unit, assume cm
-    }
+    Unit::parse_latex(&unit.trim().to_lowercase())
+        .and_then(|unit| Length::new(value, unit).to_unit(Unit::Cm, DEFAULT_FONT_SIZE))
+        .unwrap_or(value)
 }
 
 /// Parse a dimension string (e.g., "2pt", "1.5cm") and return value in cm
@@ -2648,22 +2547,12 @@ fn parse_dimension_to_cm(val: &str) -> Option<f64> {
 }
 
 /// Convert Typst color expressions to TikZ
+/// Handles color operations like "green.lighten(80%)" (`green!20!white`);
+/// colors xcolor can only give by value are kept as written
 fn convert_typst_color_to_tikz(color: &str) -> String {
-    // Handle Typst color operations like "green.lighten(80%)"
-    if color.contains(".lighten") {
-        // Extract base color and percentage
-        if let Some(base) = color.split('.').next() {
-            if let Some(pct_str) = color.split("lighten(").nth(1) {
-                if let Ok(pct) = pct_str.trim_end_matches([')', '%']).parse::<f64>() {
-                    // Convert to TikZ color mixing: green!20 means 20% green, 80% white
-                    let color_pct = 100.0 - pct;
-                    return format!("{}!{:.0}", base, color_pct);
-                }
-            }
-        }
-    }
-    // Return as-is for simple colors
-    color.to_string()
+    Color::parse_typst(color)
+        .and_then(|color| color.latex_expression())
+        .unwrap_or_else(|| color.to_string())
 }
 
 fn get_token_value(token: &CetzToken) -> Option<String> {
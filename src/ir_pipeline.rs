@@ -1,18 +1,42 @@
-//! IR-based Typst → LaTeX pipeline.
+//! IR-based pipelines: Typst → LaTeX, and LaTeX → Typst through the
+//! LaTeX frontend and the Typst backend.
 
-use tylax_ir::Document;
-use tylax_latex_backend::{render_document, LatexRenderOptions};
-use tylax_typst_frontend::typst_to_ir;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-use crate::preamble_hints::{
-    equation_numbering_enabled, extract_preamble_hints, is_two_column, parse_length_to_pt,
-    render_article_preamble,
+use tylax_ir::{
+    images_mut, partition, stats, Block, Budget, CancellationToken, ConversionLimits, Document,
+    Loss, Part, ReferenceIssue, Severity,
 };
+use tylax_latex_backend::{
+    included_image_path, label_map, push_content_packages, render_document,
+    render_document_with_map, split_rendered, BibDatabase, CitationPackage, DocumentTemplate,
+    EncodingMode, LatexRenderOptions, QuoteStyle, RenderedBlock, SplitDocument,
+};
+use tylax_latex_frontend::{latex_to_ir_with, LatexFrontendOptions};
+use tylax_typst_backend::{render_document as render_typst_document, TypstRenderOptions};
+use tylax_typst_frontend::{
+    typst_to_ir, typst_to_ir_with, IncludeResolver, IncludeStrategy, Spread, SpreadEvaluator,
+    TypstFrontendOptions,
+};
+use typst_syntax::{ast, parse, parse_code, SyntaxKind, SyntaxNode};
+
+use crate::core::latex2typst::utils::escape_typst_text;
+use crate::core::latex2typst::{latex_math_to_typst, MERGED_SPEC};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::core::typst2latex::engine::RealVfs;
+use crate::core::typst2latex::engine::{ContentNode, MiniEval, NoopVfs, Value, VirtualFileSystem};
+
+use crate::preamble_hints::{code_backend, extract_preamble_hints, render_article_preamble};
 use crate::template_adapters::aaai::maybe_convert_aaai;
 use crate::template_adapters::acm::maybe_convert_acm;
 use crate::template_adapters::ams::maybe_convert_ams;
 use crate::template_adapters::arxiv::maybe_convert_arxiv;
 use crate::template_adapters::book::maybe_convert_book;
+use crate::template_adapters::common::render_options;
 use crate::template_adapters::cvpr::maybe_convert_cvpr;
 use crate::template_adapters::elsevier::maybe_convert_elsevier;
 use crate::template_adapters::generic::maybe_convert_template_with;
@@ -25,182 +49,866 @@ use crate::template_adapters::lncs::maybe_convert_lncs;
 use crate::template_adapters::neurips::maybe_convert_neurips;
 use crate::template_adapters::newsletter::maybe_convert_newsletter;
 use crate::template_adapters::tmlr::maybe_convert_tmlr;
-use crate::utils::loss::{ConversionReport, LossRecord, LossReport, LOSS_MARKER_PREFIX};
+use crate::utils::assets::{collect_assets, AssetOptions, AssetReport};
+use crate::utils::error::TylaxResult;
+use crate::utils::files::FileResolver;
+use crate::utils::loss::{ConversionReport, LossRecord, LossReport, SourceSpan};
 
-fn build_loss_report(doc: &Document) -> LossReport {
+fn build_loss_report(doc: &Document, input: &str, source: &str, target: &str) -> LossReport {
     let mut records = Vec::new();
     for (idx, loss) in doc.losses.iter().enumerate() {
         let id = format!("L{:04}", idx + 1);
-        records.push(LossRecord::from_ir_loss(id, loss));
+        records.push(LossRecord::from_ir_loss(id, loss, input));
     }
-    LossReport::new("typst", "latex", records, Vec::new())
+    // The score is measured against the document's blocks and what was
+    // lost, so a dropped figure weighs as much as a paragraph rather than
+    // as one syntax token among thousands.
+    let lost = doc
+        .losses
+        .iter()
+        .filter(|loss| loss.severity != Severity::Info)
+        .count();
+    LossReport::new(source, target, records, Vec::new()).with_nodes(stats(doc).blocks + lost)
 }
 
-fn append_loss_markers(output: &mut String, report: &LossReport, full_document: bool) {
-    if report.losses.is_empty() {
-        return;
+/// Maps byte ranges of LaTeX produced by the IR pipeline back to the Typst
+/// source. Output from template adapters is not covered.
+#[derive(Debug, Clone, Default)]
+pub struct LatexSourceMap {
+    entries: Vec<(usize, usize, SourceSpan)>,
+}
+
+impl LatexSourceMap {
+    fn from_blocks(doc: &Document, blocks: &[RenderedBlock]) -> Self {
+        let entries = blocks
+            .iter()
+            .filter_map(|rendered| {
+                let span = doc.block_span(rendered.block)?;
+                Some((
+                    rendered.start,
+                    rendered.end,
+                    SourceSpan {
+                        start: span.start,
+                        end: span.end,
+                    },
+                ))
+            })
+            .collect();
+        Self { entries }
     }
-    let mut marker_block = String::new();
-    marker_block.push('\n');
-    marker_block.push_str("% Tylax conversion losses\n");
-    for loss in &report.losses {
-        let name = loss
-            .name
-            .as_ref()
-            .map(|n| format!(" kind={}", n))
-            .unwrap_or_default();
-        let line = format!(
-            "% {}{}{} message={}\n",
-            LOSS_MARKER_PREFIX,
-            loss.id,
-            name,
-            loss.message.replace('\n', " ")
-        );
-        marker_block.push_str(&line);
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
     }
-    if full_document {
-        if let Some(pos) = output.rfind("\\end{document}") {
-            output.insert_str(pos, &marker_block);
-            return;
+
+    /// Typst span of the block whose LaTeX contains byte `offset`.
+    pub fn lookup(&self, offset: usize) -> Option<SourceSpan> {
+        self.entries
+            .iter()
+            .find(|(start, end, _)| (*start..*end).contains(&offset))
+            .map(|(_, _, span)| *span)
+    }
+}
+
+/// Settings of the LaTeX the IR pipeline writes that the Typst source
+/// leaves open. The default writes what the source suggests.
+#[derive(Debug, Clone, Default)]
+pub struct LatexOutputOptions {
+    /// How text outside ASCII is written. By default it is kept as written
+    /// when the document sets a system font, which only XeLaTeX and
+    /// LuaLaTeX load, and transliterated for pdflatex otherwise.
+    pub encoding_mode: Option<EncodingMode>,
+    /// How smart quotes are set.
+    pub quote_style: QuoteStyle,
+    /// Set strong and emphasized spans that hold only math as
+    /// `\boldsymbol` and `\mathit` inside the formula.
+    pub math_emphasis: bool,
+    /// Deepest heading level the table of contents lists. `None` keeps the
+    /// class's own depth.
+    pub toc_depth: Option<u8>,
+    /// Set cross-references with cleveref's `\cref` and `\crefrange`,
+    /// which name the target themselves, and load the package.
+    pub cleveref: bool,
+    /// Set every table as a `longtable`, which breaks across pages.
+    pub long_tables: bool,
+    /// Set tables with more rows than this as `longtable`, unless the
+    /// layout has two columns.
+    pub long_table_rows: Option<usize>,
+    /// Set tables with `fr` columns as `tabularx`, whose `X` columns share
+    /// what the other columns leave of the line.
+    pub tabularx: bool,
+    /// Package that sets citations and the bibliography. Templates whose
+    /// class or style loads natbib keep natbib.
+    pub citation_package: CitationPackage,
+    /// Typeset the bibliography inline from the `.bib` files it names,
+    /// resolved against this directory, so the output needs no external
+    /// database. The usual `\bibliography` command is kept when none of
+    /// the files can be read.
+    pub inline_bibliography: Option<PathBuf>,
+    /// Directory the files named by `csv()`, `json()` and the other data
+    /// loaders are read from, for tables filled from data. Without it the
+    /// loaders read nothing and such tables are dropped.
+    pub data_root: Option<PathBuf>,
+    /// Template a full document is set in, with headings mapped to the
+    /// sectioning commands of its class, in place of the template adapter
+    /// for the template the source imports. `Article` is the article
+    /// preamble read from the source, as when no adapter applies.
+    pub template: Option<DocumentTemplate>,
+    /// Label prefixes replaced in the output, e.g. `("fig:", "fig-")`.
+    pub label_rewrites: Vec<(String, String)>,
+    /// Start a part at each heading of this level or above, which
+    /// [`LatexConversion::split`] cuts into a file of its own.
+    pub split_level: Option<u8>,
+    /// Copy or link the images of the document next to the output as this
+    /// says and point the output at the copies.
+    pub assets: Option<AssetOptions>,
+    /// Convert the `#include`s of the document as this says. Without it
+    /// the content of included files is dropped as a loss.
+    pub includes: Option<IncludeOptions>,
+}
+
+/// How the `#include`s of a document are converted.
+#[derive(Clone)]
+pub struct IncludeOptions {
+    /// Reads the included files by their path relative to the main file's
+    /// directory.
+    pub files: Arc<dyn FileResolver>,
+    pub strategy: IncludeStrategy,
+    /// Where the converted file is relative to the main file's directory,
+    /// for an included file converted on its own; `None` for the main
+    /// file.
+    pub path: Option<String>,
+}
+
+impl IncludeOptions {
+    pub fn new(files: Arc<dyn FileResolver>, strategy: IncludeStrategy) -> Self {
+        Self {
+            files,
+            strategy,
+            path: None,
         }
     }
-    output.push_str(&marker_block);
+}
+
+impl fmt::Debug for IncludeOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IncludeOptions")
+            .field("strategy", &self.strategy)
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A Typst document the IR pipeline converted to LaTeX, with what it
+/// found on the way.
+#[derive(Debug, Clone)]
+pub struct LatexConversion {
+    pub output: String,
+    /// What the frontend dropped, not marked in the output.
+    pub report: LossReport,
+    /// Each label of the document with the label the output sets in its
+    /// place.
+    pub labels: Vec<(String, String)>,
+    /// The images written next to the output and those left as they were,
+    /// empty unless [`LatexOutputOptions::assets`] is set.
+    pub assets: AssetReport,
+    /// The IR the output is rendered from, with the files it includes in
+    /// place and its images as they were collected.
+    pub document: Document,
+    blocks: Vec<RenderedBlock>,
+    parts: Vec<Part>,
+}
+
+impl LatexConversion {
+    /// Cut `output`, the output of the conversion with the loss markers
+    /// appended or not, into the main file and a file for each part the
+    /// split level starts. An output changed before the end of its body,
+    /// as by a repair, no longer matches the parts and is kept whole.
+    pub fn split(&self, output: &str) -> SplitDocument {
+        let body_end = self.blocks.last().map_or(0, |rendered| rendered.end);
+        if output.get(..body_end) != self.output.get(..body_end) {
+            return SplitDocument {
+                main: output.to_string(),
+                parts: Vec::new(),
+            };
+        }
+        split_rendered(output, &self.blocks, &self.parts)
+    }
 }
 
 /// Convert Typst to LaTeX using the IR pipeline.
 pub fn typst_to_latex_ir(input: &str, full_document: bool) -> String {
-    if full_document {
-        if let Some(rendered) = maybe_convert_ieee(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_acm(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_aaai(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_neurips(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_icml(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_iclr(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_cvpr(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_tmlr(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_jmlr(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_ams(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_lncs(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_elsevier(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_book(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_letter(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_newsletter(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_template_with(input) {
-            return rendered;
-        }
-        if let Some(rendered) = maybe_convert_arxiv(input) {
-            return rendered;
+    render_ir(input, full_document, &LatexOutputOptions::default()).0
+}
+
+/// Convert Typst to LaTeX using the IR pipeline as `options` says and
+/// report what the frontend dropped, without marking it in the output.
+pub fn typst_to_latex_ir_with_options(
+    input: &str,
+    full_document: bool,
+    options: &LatexOutputOptions,
+) -> (String, LossReport) {
+    let conversion = typst_to_latex_ir_conversion(input, full_document, options);
+    (conversion.output, conversion.report)
+}
+
+/// Record a loss for each image the output includes under the name of the
+/// file it is converted to, which exists only once converted.
+fn record_converted_images(doc: &mut Document) {
+    let mut seen = HashSet::new();
+    let losses: Vec<Loss> = images_mut(doc)
+        .into_iter()
+        .filter(|image| seen.insert(image.path.clone()))
+        .filter_map(|image| {
+            let included = included_image_path(&image.path)?;
+            let message = format!(
+                "{} is included as {}, which pdflatex reads",
+                image.path, included
+            );
+            Some(Loss::new("image", message).with_suggestion(
+                "convert the image by hand, or collect the images as assets, which converts them",
+            ))
+        })
+        .collect();
+    doc.losses.extend(losses);
+}
+
+/// Record a loss for each reference to a label the document does not
+/// define and each label it defines twice, which LaTeX sets as `??` or
+/// stops at.
+fn record_reference_issues(doc: &mut Document) {
+    let losses: Vec<Loss> = doc
+        .check_references()
+        .iter()
+        .map(ReferenceIssue::to_loss)
+        .collect();
+    doc.losses.extend(losses);
+}
+
+/// Record a loss when the caller asks for pdflatex output and the text has
+/// characters pdflatex cannot set, which the output keeps as written.
+fn record_unicode_only_text(doc: &mut Document, input: &str, options: &LatexOutputOptions) {
+    if options.encoding_mode != Some(EncodingMode::Transliterate) {
+        return;
+    }
+    if let Some(ch) = extract_preamble_hints(input).unicode_only_char {
+        let message = format!(
+            "pdflatex cannot set {:?} (U+{:04X}), which is kept as written",
+            ch, ch as u32
+        );
+        doc.losses.push(
+            Loss::new("encoding", message).with_suggestion(
+                "convert for XeLaTeX or LuaLaTeX, which set any script the font has",
+            ),
+        );
+    }
+}
+
+/// Convert Typst to LaTeX using the IR pipeline as `options` says, keeping
+/// the loss report, the label map and the parts to split the output into.
+pub fn typst_to_latex_ir_conversion(
+    input: &str,
+    full_document: bool,
+    options: &LatexOutputOptions,
+) -> LatexConversion {
+    let mut doc = typst_to_ir_with(
+        input,
+        &frontend_options(input, data_vfs(options), options.includes.as_ref()),
+    );
+    // Images that cannot be converted are losses of the report.
+    let assets = options
+        .assets
+        .as_ref()
+        .map(|assets| collect_assets(&mut doc, assets))
+        .unwrap_or_default();
+    record_converted_images(&mut doc);
+    record_reference_issues(&mut doc);
+    record_unicode_only_text(&mut doc, input, options);
+    let report = build_loss_report(&doc, input, "typst", "latex");
+    let labels = label_map(
+        &doc,
+        &LatexRenderOptions {
+            label_rewrites: options.label_rewrites.clone(),
+            ..LatexRenderOptions::default()
+        },
+    );
+    if let Some(output) = full_document
+        .then(|| convert_with_template_adapter(input, options))
+        .flatten()
+    {
+        return LatexConversion {
+            output,
+            report,
+            labels,
+            assets,
+            document: doc,
+            blocks: Vec::new(),
+            parts: Vec::new(),
+        };
+    }
+    let (output, blocks) = render_doc(&doc, input, full_document, options);
+    let parts = options
+        .split_level
+        .map(|level| partition(&doc, level))
+        .unwrap_or_default();
+    LatexConversion {
+        output,
+        report,
+        labels,
+        assets,
+        document: doc,
+        blocks,
+        parts,
+    }
+}
+
+/// Convert Typst to LaTeX using the IR pipeline, typesetting the bibliography
+/// inline from the `.bib` files it names so the output needs no external
+/// database. Relative paths are resolved against `bib_dir`; when none of the
+/// files can be read the usual `\bibliography` command is kept.
+pub fn typst_to_latex_ir_with_inline_bibliography(
+    input: &str,
+    full_document: bool,
+    bib_dir: &Path,
+) -> String {
+    let options = LatexOutputOptions {
+        inline_bibliography: Some(bib_dir.to_path_buf()),
+        ..LatexOutputOptions::default()
+    };
+    render_ir(input, full_document, &options).0
+}
+
+/// Convert Typst to LaTeX using the IR pipeline, keeping a map from the
+/// output back to the Typst source.
+pub fn typst_to_latex_ir_with_source_map(
+    input: &str,
+    full_document: bool,
+) -> (String, LatexSourceMap) {
+    render_ir(input, full_document, &LatexOutputOptions::default())
+}
+
+/// Bibliography files named by the document's bibliography blocks, as
+/// written in the source and resolved against `bib_dir`.
+fn bibliography_files(doc: &Document, bib_dir: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let mut files = Vec::new();
+    for block in &doc.blocks {
+        let Block::Bibliography { file, .. } = block else {
+            continue;
+        };
+        for name in file
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            let mut name = PathBuf::from(name);
+            if name.extension().is_none() {
+                name.set_extension("bib");
+            }
+            let path = bib_dir.join(&name);
+            files.push((name, path));
         }
     }
-    let doc: Document = typst_to_ir(input);
+    files
+}
+
+/// Whether `path` names a file below the directory it is taken relative
+/// to: no root, drive prefix or `..` component.
+fn is_relative_below(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+fn is_hayagriva(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yml") || ext.eq_ignore_ascii_case("yaml"))
+}
+
+/// Parse the `.bib` and Hayagriva `.yml` files referenced by the document.
+fn load_bibliography(doc: &Document, bib_dir: &Path) -> Option<BibDatabase> {
+    let mut db: Option<BibDatabase> = None;
+    for (_, path) in bibliography_files(doc, bib_dir) {
+        let Ok(source) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let parsed = if is_hayagriva(&path) {
+            BibDatabase::from_hayagriva(&source)
+        } else {
+            BibDatabase::parse(&source)
+        };
+        db.get_or_insert_with(BibDatabase::default).extend(parsed);
+    }
+    db
+}
+
+/// Convert the Hayagriva `.yml` bibliographies a Typst document names into
+/// BibTeX. Returns the `.bib` path, relative to `bib_dir`, that the LaTeX
+/// output's `\bibliography` command expects for each, with its contents.
+/// Names that are absolute or climb out of `bib_dir` with `..` are left
+/// out, so the paths can be joined to an output directory safely.
+pub fn hayagriva_bibliographies_to_bibtex(input: &str, bib_dir: &Path) -> Vec<(PathBuf, String)> {
+    let doc = typst_to_ir(input);
+    bibliography_files(&doc, bib_dir)
+        .into_iter()
+        .filter(|(name, path)| is_relative_below(name) && is_hayagriva(path))
+        .filter_map(|(name, path)| {
+            let source = fs::read_to_string(&path).ok()?;
+            let bibtex = BibDatabase::from_hayagriva(&source).to_bibtex();
+            Some((name.with_extension("bib"), bibtex))
+        })
+        .collect()
+}
+
+fn render_ir(
+    input: &str,
+    full_document: bool,
+    options: &LatexOutputOptions,
+) -> (String, LatexSourceMap) {
     if full_document {
-        let hints = extract_preamble_hints(input);
-        let base_font_size_pt = hints
-            .text_size
+        if let Some(rendered) = convert_with_template_adapter(input, options) {
+            return (rendered, LatexSourceMap::default());
+        }
+    }
+    let doc = typst_to_ir_with(
+        input,
+        &frontend_options(input, data_vfs(options), options.includes.as_ref()),
+    );
+    let (out, blocks) = render_doc(&doc, input, full_document, options);
+    let map = LatexSourceMap::from_blocks(&doc, &blocks);
+    (out, map)
+}
+
+/// A full document set by the template adapter for the template `input`
+/// imports, when one recognizes it and the caller names no template, label
+/// rewrites, split level or assets, which the adapters do not follow.
+fn convert_with_template_adapter(input: &str, options: &LatexOutputOptions) -> Option<String> {
+    if options.template.is_some()
+        || !options.label_rewrites.is_empty()
+        || options.split_level.is_some()
+        || options.assets.is_some()
+    {
+        return None;
+    }
+    maybe_convert_ieee(input, options)
+        .or_else(|| maybe_convert_acm(input, options))
+        .or_else(|| maybe_convert_aaai(input, options))
+        .or_else(|| maybe_convert_neurips(input, options))
+        .or_else(|| maybe_convert_icml(input, options))
+        .or_else(|| maybe_convert_iclr(input, options))
+        .or_else(|| maybe_convert_cvpr(input, options))
+        .or_else(|| maybe_convert_tmlr(input, options))
+        .or_else(|| maybe_convert_jmlr(input, options))
+        .or_else(|| maybe_convert_ams(input, options))
+        .or_else(|| maybe_convert_lncs(input, options))
+        .or_else(|| maybe_convert_elsevier(input, options))
+        .or_else(|| maybe_convert_book(input, options))
+        .or_else(|| maybe_convert_letter(input, options))
+        .or_else(|| maybe_convert_newsletter(input, options))
+        .or_else(|| maybe_convert_template_with(input, options))
+        .or_else(|| maybe_convert_arxiv(input, options))
+}
+
+/// Render a document the Typst frontend produced from `input`, reading the
+/// preamble settings from the source, and report where each top-level block
+/// landed in the output.
+fn render_doc(
+    doc: &Document,
+    input: &str,
+    full_document: bool,
+    output: &LatexOutputOptions,
+) -> (String, Vec<RenderedBlock>) {
+    let hints = extract_preamble_hints(input);
+    let options = LatexRenderOptions {
+        force_here: full_document,
+        code_backend: code_backend(&hints),
+        inline_bibliography: output
+            .inline_bibliography
             .as_deref()
-            .and_then(|size| parse_length_to_pt(size, "10pt"));
-        let preamble = render_article_preamble(&hints);
-        let number_equations = equation_numbering_enabled(&hints);
-        let cite_command = hints.cite_command.clone().or_else(|| {
-            if hints.uses_natbib {
-                Some("citep".to_string())
-            } else {
-                None
-            }
-        });
-        let body = render_document(
-            &doc,
+            .and_then(|dir| load_bibliography(doc, dir)),
+        label_rewrites: output.label_rewrites.clone(),
+        ..render_options(&hints, &doc.meta, output)
+    };
+    if !full_document {
+        return render_document_with_map(doc, options);
+    }
+    if let Some(template) = output
+        .template
+        .clone()
+        .filter(|template| *template != DocumentTemplate::Article)
+    {
+        return render_document_with_map(
+            doc,
             LatexRenderOptions {
-                full_document: false,
-                number_equations,
-                two_column: is_two_column(&hints),
-                inline_wide_tables: false,
-                force_here: true,
-                table_grid: false,
-                table_style: tylax_latex_backend::TableStyle::Plain,
-                table_caption_position: tylax_latex_backend::TableCaptionPosition::Bottom,
-                bibliography_style_default: hints.bibliography_style.clone(),
-                cite_command,
-                base_font_size_pt,
-                heading_numbering_none: hints.heading_numbering_none,
+                full_document: true,
+                template,
+                ..options
             },
         );
-        let mut out = String::new();
-        out.push_str(&preamble);
-        out.push_str("\\begin{document}\n\n");
-        if !body.trim().is_empty() {
-            out.push_str(&body);
-            out.push('\n');
-        }
-        out.push_str("\\end{document}\n");
-        return out;
     }
-    let hints = extract_preamble_hints(input);
-    let base_font_size_pt = hints
-        .text_size
-        .as_deref()
-        .and_then(|size| parse_length_to_pt(size, "10pt"));
-    let cite_command = hints.cite_command.clone().or_else(|| {
-        if hints.uses_natbib {
-            Some("citep".to_string())
-        } else {
-            None
-        }
-    });
-    render_document(
-        &doc,
+    // The preamble read from the source is the document's own, and the
+    // backend sets the front matter and the abstract after it as in any
+    // full document.
+    let mut preamble = render_article_preamble(&hints, &doc.meta, &options);
+    push_content_packages(&mut preamble, doc, &options);
+    render_document_with_map(
+        doc,
         LatexRenderOptions {
-            full_document: false,
-            number_equations: equation_numbering_enabled(&hints),
-            two_column: is_two_column(&hints),
-            inline_wide_tables: false,
-            force_here: false,
-            table_grid: false,
-            table_style: tylax_latex_backend::TableStyle::Plain,
-            table_caption_position: tylax_latex_backend::TableCaptionPosition::Bottom,
-            bibliography_style_default: hints.bibliography_style.clone(),
-            cite_command,
-            base_font_size_pt,
-            heading_numbering_none: hints.heading_numbering_none,
+            full_document: true,
+            template: DocumentTemplate::Custom { preamble },
+            ..options
         },
     )
 }
 
+/// Convert Typst to a full LaTeX document set in `template`. The backend
+/// writes the preamble for the template instead of a Typst template adapter,
+/// and the document's own set rules and front matter fill it in.
+pub fn typst_to_latex_ir_with_template(input: &str, template: DocumentTemplate) -> String {
+    let options = LatexOutputOptions {
+        template: Some(template),
+        ..LatexOutputOptions::default()
+    };
+    render_ir(input, true, &options).0
+}
+
+/// Convert Typst to LaTeX using the IR pipeline, with each part of the
+/// document that starts at a heading of `level` or above in a file of its
+/// own, which the main file `\input`s. A full document is set in `template`
+/// when one is given; template adapters are not applied.
+pub fn typst_to_latex_ir_split(
+    input: &str,
+    full_document: bool,
+    template: Option<DocumentTemplate>,
+    level: u8,
+) -> SplitDocument {
+    let options = LatexOutputOptions {
+        template,
+        split_level: Some(level),
+        ..LatexOutputOptions::default()
+    };
+    let conversion = typst_to_latex_ir_conversion(input, full_document, &options);
+    conversion.split(&conversion.output)
+}
+
+/// Convert Typst to LaTeX using the IR pipeline, copying or linking the
+/// images of the document next to the output as `assets` says and pointing
+/// the output at the copies. Template adapters are not applied.
+pub fn typst_to_latex_ir_with_assets(
+    input: &str,
+    full_document: bool,
+    assets: &AssetOptions,
+) -> (String, AssetReport) {
+    let options = LatexOutputOptions {
+        assets: Some(assets.clone()),
+        ..LatexOutputOptions::default()
+    };
+    let conversion = typst_to_latex_ir_conversion(input, full_document, &options);
+    (conversion.output, conversion.assets)
+}
+
 /// Convert Typst to LaTeX using the IR pipeline and return a loss report.
+/// The losses are also marked in the output, for repair tools to find.
 pub fn typst_to_latex_ir_with_report(input: &str, full_document: bool) -> ConversionReport {
-    let doc: Document = typst_to_ir(input);
-    let report = build_loss_report(&doc);
-    let mut out = typst_to_latex_ir(input, full_document);
-    append_loss_markers(&mut out, &report, full_document);
+    let (mut out, report) = typst_to_latex_ir_and_report(input, full_document);
+    report.append_markers(&mut out, full_document);
     ConversionReport::new(out, report)
 }
+
+/// Convert Typst to LaTeX as [`typst_to_latex_ir`] does and report what the
+/// frontend dropped, without marking it in the output.
+pub fn typst_to_latex_ir_and_report(input: &str, full_document: bool) -> (String, LossReport) {
+    typst_to_latex_ir_with_options(input, full_document, &LatexOutputOptions::default())
+}
+
+/// Convert Typst to LaTeX using the IR pipeline, failing with
+/// [`crate::ConversionError::LossyConversion`], wrapped in a [`crate::TylaxError`],
+/// when the frontend dropped content.
+/// Warnings and info losses do not fail the conversion.
+pub fn typst_to_latex_ir_strict(input: &str, full_document: bool) -> TylaxResult<String> {
+    let (out, report) = typst_to_latex_ir_and_report(input, full_document);
+    report.check_strict()?;
+    Ok(out)
+}
+
+/// Convert Typst to LaTeX using the IR pipeline within `limits`, stopping
+/// early once `cancel` is cancelled. What was converted before a limit is
+/// kept and the rest is reported as a `limit` loss. Template adapters are
+/// skipped, since they read the whole source on their own.
+pub fn typst_to_latex_ir_with_limits(
+    input: &str,
+    full_document: bool,
+    limits: &ConversionLimits,
+    cancel: Option<&CancellationToken>,
+) -> ConversionReport {
+    let options = TypstFrontendOptions {
+        limits: limits.clone(),
+        cancel: cancel.cloned(),
+        ..frontend_options(input, Arc::new(NoopVfs), None)
+    };
+    let mut doc = typst_to_ir_with(input, &options);
+    record_reference_issues(&mut doc);
+    let report = build_loss_report(&doc, input, "typst", "latex");
+    let (kept, _) = Budget::new(limits, None).truncate_input(input);
+    let (mut out, _) = render_doc(&doc, kept, full_document, &LatexOutputOptions::default());
+    report.append_markers(&mut out, full_document);
+    ConversionReport::new(out, report)
+}
+
+/// Convert Typst to LaTeX using the IR pipeline, filling data-driven tables
+/// such as `#table(..csv("data.csv").flatten())` from files read through
+/// `vfs`. Template adapters are not applied.
+pub fn typst_to_latex_ir_with_vfs(
+    input: &str,
+    full_document: bool,
+    vfs: Arc<dyn VirtualFileSystem>,
+) -> String {
+    let doc = typst_to_ir_with(input, &frontend_options(input, vfs, None));
+    render_doc(&doc, input, full_document, &LatexOutputOptions::default()).0
+}
+
+/// Convert Typst to LaTeX using the IR pipeline with label prefixes
+/// replaced as `rewrites` says, e.g. `("fig:", "fig-")`, and return each
+/// label of the document with the label the output sets in its place.
+/// Template adapters are not applied.
+pub fn typst_to_latex_ir_with_label_rewrites(
+    input: &str,
+    full_document: bool,
+    rewrites: &[(String, String)],
+) -> (String, Vec<(String, String)>) {
+    let options = LatexOutputOptions {
+        label_rewrites: rewrites.to_vec(),
+        ..LatexOutputOptions::default()
+    };
+    let conversion = typst_to_latex_ir_conversion(input, full_document, &options);
+    (conversion.output, conversion.labels)
+}
+
+/// The file system the data loaders of a document read through: the
+/// directory `options` names, or none.
+fn data_vfs(options: &LatexOutputOptions) -> Arc<dyn VirtualFileSystem> {
+    match &options.data_root {
+        #[cfg(not(target_arch = "wasm32"))]
+        Some(root) => Arc::new(RealVfs::new(root)),
+        _ => Arc::new(NoopVfs),
+    }
+}
+
+/// Frontend options that evaluate the spread arguments of tables and grids
+/// with MiniEval, reading files through `vfs`, and convert `#include` as
+/// `includes` says.
+fn frontend_options(
+    input: &str,
+    vfs: Arc<dyn VirtualFileSystem>,
+    includes: Option<&IncludeOptions>,
+) -> TypstFrontendOptions {
+    let mut options = TypstFrontendOptions {
+        spread_evaluator: Some(spread_evaluator(input, vfs)),
+        ..TypstFrontendOptions::default()
+    };
+    if let Some(includes) = includes {
+        let files = includes.files.clone();
+        options.include_strategy = includes.strategy;
+        options.include_resolver =
+            Some(IncludeResolver::new(move |path| files.read_file(path).ok()));
+        options.include_path = includes.path.clone();
+    }
+    options
+}
+
+/// Evaluate spread arguments with MiniEval, after the top-level `#let`
+/// bindings and imports of `input` before the spread, so `..cells` can name
+/// an array the document builds. Each value of the spread array becomes the
+/// markup of one cell; strings and numbers are escaped so data is never
+/// read as markup.
+fn spread_evaluator(input: &str, vfs: Arc<dyn VirtualFileSystem>) -> SpreadEvaluator {
+    let definitions = Mutex::new(TopLevelDefinitions::new(input, vfs));
+    SpreadEvaluator::new(move |spread| {
+        let root = parse_code(spread.expr);
+        if root.erroneous() {
+            return None;
+        }
+        let expr = root.cast::<ast::Code>()?.exprs().next()?;
+        let mut definitions = definitions.lock().ok()?;
+        let value = definitions.before(spread)?.eval_expr(expr).ok()?;
+        match value {
+            Value::Array(values) => Some(values.iter().map(spread_markup).collect()),
+            Value::None => Some(Vec::new()),
+            _ => None,
+        }
+    })
+}
+
+/// The top-level `#let` bindings and imports of a document, run as far as
+/// the spreads evaluated need them. Spreads come in document order, so each
+/// definition runs once for the whole conversion.
+struct TopLevelDefinitions {
+    vfs: Arc<dyn VirtualFileSystem>,
+    definitions: Vec<(usize, SyntaxNode)>,
+    eval: MiniEval,
+    next: usize,
+}
+
+impl TopLevelDefinitions {
+    fn new(input: &str, vfs: Arc<dyn VirtualFileSystem>) -> Self {
+        let mut definitions = Vec::new();
+        let mut offset = 0;
+        for child in parse(input).children() {
+            if matches!(
+                child.kind(),
+                SyntaxKind::LetBinding | SyntaxKind::ModuleImport
+            ) {
+                definitions.push((offset, child.clone()));
+            }
+            offset += child.len();
+        }
+        Self {
+            eval: MiniEval::with_vfs(vfs.clone()),
+            vfs,
+            definitions,
+            next: 0,
+        }
+    }
+
+    /// The evaluator after the definitions before `spread`, or `None` once
+    /// the conversion's budget is spent. A definition that fails to
+    /// evaluate is skipped; a spread naming it then fails on its own.
+    fn before(&mut self, spread: &Spread<'_>) -> Option<&mut MiniEval> {
+        if self.next > 0 && self.definitions[self.next - 1].0 >= spread.offset {
+            // A spread before the last one evaluated: run them again.
+            self.eval = MiniEval::with_vfs(self.vfs.clone());
+            self.next = 0;
+        }
+        while let Some((offset, node)) = self.definitions.get(self.next) {
+            if *offset >= spread.offset || spread.exhausted() {
+                break;
+            }
+            if let Some(expr) = node.cast::<ast::Expr>() {
+                let _ = self.eval.eval_expr(expr);
+            }
+            self.next += 1;
+        }
+        (!spread.exhausted()).then_some(&mut self.eval)
+    }
+}
+
+fn spread_markup(value: &Value) -> String {
+    match value {
+        Value::Content(nodes) => nodes.iter().map(ContentNode::to_typst).collect(),
+        other => escape_typst_text(&other.display()),
+    }
+}
+
+/// Serialize an IR document as JSON, as written by `tylax ir dump`.
+pub fn ir_to_json(doc: &Document) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(doc)
+}
+
+/// Serialize labels and the labels they became as a JSON object, as
+/// written by `--label-map`.
+pub fn label_map_to_json(labels: &[(String, String)]) -> serde_json::Result<String> {
+    let map: serde_json::Map<String, serde_json::Value> = labels
+        .iter()
+        .map(|(label, output)| (label.clone(), serde_json::Value::String(output.clone())))
+        .collect();
+    serde_json::to_string_pretty(&map)
+}
+
+/// Read an IR document from JSON. `losses` and `spans` may be omitted.
+pub fn ir_from_json(json: &str) -> serde_json::Result<Document> {
+    serde_json::from_str(json)
+}
+
+/// Render an IR document to LaTeX with the backend's default options. No
+/// source is available, so preamble hints and template adapters do not apply.
+pub fn ir_to_latex(doc: &Document, full_document: bool) -> String {
+    ir_to_latex_with_template(doc, full_document, DocumentTemplate::Article)
+}
+
+/// Render an IR document as LaTeX, a full document being set in `template`.
+/// Headings follow the template in either case, e.g. as chapters in a book.
+pub fn ir_to_latex_with_template(
+    doc: &Document,
+    full_document: bool,
+    template: DocumentTemplate,
+) -> String {
+    render_document(
+        doc,
+        LatexRenderOptions {
+            full_document,
+            template,
+            ..LatexRenderOptions::default()
+        },
+    )
+}
+
+/// Render an IR document as Typst.
+pub fn ir_to_typst(doc: &Document, full_document: bool) -> String {
+    render_typst_document(doc, TypstRenderOptions { full_document })
+}
+
+/// Convert LaTeX to Typst through the shared IR, as the other direction
+/// goes: the LaTeX frontend lowers the document and the Typst backend
+/// renders it.
+pub fn latex_to_typst_ir(input: &str, full_document: bool) -> String {
+    ir_to_typst(&latex_to_ir(input), full_document)
+}
+
+/// Convert LaTeX to Typst as [`latex_to_typst_ir`] does and report what the
+/// frontend dropped.
+pub fn latex_to_typst_ir_with_report(input: &str, full_document: bool) -> ConversionReport {
+    let (doc, report) = latex_to_ir_with_report(input);
+    ConversionReport::new(ir_to_typst(&doc, full_document), report)
+}
+
+/// Parse LaTeX into the shared IR. Math is converted to Typst syntax so the
+/// document matches what the Typst frontend produces.
+pub fn latex_to_ir(input: &str) -> Document {
+    latex_to_ir_with_environments(input, &[])
+}
+
+/// [`latex_to_ir`], also keeping the environments named in `environments`,
+/// such as a class's `problem`, as environment blocks like the theorem-like
+/// ones.
+pub fn latex_to_ir_with_environments(input: &str, environments: &[String]) -> Document {
+    let options = LatexFrontendOptions {
+        spec: Some(MERGED_SPEC.clone()),
+        math: Some(latex_math_to_typst),
+        environments: environments.to_vec(),
+        ..LatexFrontendOptions::default()
+    };
+    latex_to_ir_with(input, &options)
+}
+
+/// Parse LaTeX into the shared IR and report what the frontend dropped.
+pub fn latex_to_ir_with_report(input: &str) -> (Document, LossReport) {
+    let doc = latex_to_ir(input);
+    let report = build_loss_report(&doc, input, "latex", "typst");
+    (doc, report)
+}
+
+/// Parse LaTeX into the shared IR within `limits`, stopping early once
+/// `cancel` is cancelled. Content past a limit is reported as a `limit` loss.
+pub fn latex_to_ir_with_limits(
+    input: &str,
+    limits: &ConversionLimits,
+    cancel: Option<&CancellationToken>,
+) -> (Document, LossReport) {
+    let options = LatexFrontendOptions {
+        spec: Some(MERGED_SPEC.clone()),
+        math: Some(latex_math_to_typst),
+        limits: limits.clone(),
+        cancel: cancel.cloned(),
+        ..LatexFrontendOptions::default()
+    };
+    let doc = latex_to_ir_with(input, &options);
+    let report = build_loss_report(&doc, input, "latex", "typst");
+    (doc, report)
+}
+
+/// Parse LaTeX into the shared IR, failing with
+/// [`crate::ConversionError::LossyConversion`], wrapped in a [`crate::TylaxError`],
+/// when the frontend dropped content.
+pub fn latex_to_ir_strict(input: &str) -> TylaxResult<Document> {
+    let (doc, report) = latex_to_ir_with_report(input);
+    report.check_strict()?;
+    Ok(doc)
+}
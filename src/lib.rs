@@ -71,6 +71,9 @@ pub mod ir_pipeline;
 mod preamble_hints;
 mod template_adapters;
 
+/// Round-trip checks through the other language
+pub mod roundtrip;
+
 // Re-export core conversion functions
 pub use core::typst2latex;
 pub use core::typst2latex::T2LOptions;
@@ -85,7 +88,7 @@ pub use core::latex2typst::{
     latex_math_to_typst_with_diagnostics, latex_math_to_typst_with_eval,
     latex_math_to_typst_with_report, latex_to_typst_with_diagnostics, latex_to_typst_with_eval,
     latex_to_typst_with_report, ConversionMode, ConversionResult as L2TConversionResult,
-    ConversionState, EnvironmentContext, L2TOptions, LatexConverter, WarningKind,
+    ConversionState, EnvironmentContext, L2TOptions, LatexConverter, ReviewNotes, WarningKind,
 };
 
 // Re-export data modules
@@ -110,14 +113,43 @@ pub use data::symbols;
 pub use utils::diagnostics;
 pub use utils::error::{
     CliDiagnostic, ConversionError, ConversionOutput, ConversionResult, ConversionWarning,
-    DiagnosticSeverity,
+    DiagnosticSeverity, TylaxError, TylaxResult,
 };
+pub use utils::assets::{AssetMode, AssetOptions, AssetReport, SvgTarget};
+pub use utils::table_export::{export_tables, TableExportOptions, TableExportReport, TableFormat};
 pub use utils::files;
-pub use utils::loss::{ConversionReport, LossKind, LossRecord, LossReport, LOSS_MARKER_PREFIX};
+pub use utils::loss::{
+    ConversionReport, FidelityScore, LossKind, LossRecord, LossReport, LOSS_MARKER_PREFIX,
+};
 
 // Re-export IR pipeline function
 pub use ir_pipeline::typst_to_latex_ir;
-pub use ir_pipeline::typst_to_latex_ir_with_report;
+pub use ir_pipeline::{typst_to_latex_ir_with_options, LatexOutputOptions};
+pub use ir_pipeline::{typst_to_latex_ir_conversion, LatexConversion};
+pub use ir_pipeline::{
+    hayagriva_bibliographies_to_bibtex, typst_to_latex_ir_with_inline_bibliography,
+};
+pub use ir_pipeline::{typst_to_latex_ir_and_report, typst_to_latex_ir_with_report};
+pub use ir_pipeline::{
+    latex_to_ir, latex_to_ir_strict, latex_to_ir_with_environments, latex_to_ir_with_limits,
+    latex_to_ir_with_report,
+};
+pub use ir_pipeline::{ir_to_typst, latex_to_typst_ir, latex_to_typst_ir_with_report};
+pub use ir_pipeline::typst_to_latex_ir_with_limits;
+pub use ir_pipeline::typst_to_latex_ir_with_vfs;
+pub use ir_pipeline::IncludeOptions;
+pub use ir_pipeline::typst_to_latex_ir_strict;
+pub use ir_pipeline::{typst_to_latex_ir_split, typst_to_latex_ir_with_template};
+pub use ir_pipeline::typst_to_latex_ir_with_assets;
+pub use ir_pipeline::{label_map_to_json, typst_to_latex_ir_with_label_rewrites};
+pub use ir_pipeline::{ir_from_json, ir_to_json, ir_to_latex, ir_to_latex_with_template};
+pub use tylax_typst_frontend::{included_files, typst_to_ir, IncludeStrategy};
+pub use tylax_ir::{stats, DocStats, HeadingNode};
+pub use ir_pipeline::{typst_to_latex_ir_with_source_map, LatexSourceMap};
+pub use tylax_latex_backend::{
+    format_latex, CitationPackage, DocumentTemplate, EncodingMode, FormatOptions, QuoteStyle,
+    SplitDocument,
+};
 
 // Re-export main types and functions from eval (MiniEval) - now located in typst2latex
 pub use core::typst2latex::engine::{
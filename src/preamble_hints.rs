@@ -1,35 +1,42 @@
 use std::collections::BTreeMap;
 
 use typst_syntax::{parse, SyntaxKind, SyntaxNode};
+use tylax_ir::DocumentMeta;
+use tylax_latex_backend::{
+    class_options, push_meta_preamble, push_page_style, unicode_only_char, CitationPackage, CodeBackend,
+    DocumentTemplate, EncodingMode, LatexRenderOptions,
+};
+use tylax_color::Color;
+use tylax_units::Length;
 
 #[derive(Debug, Default, Clone)]
 pub struct PreambleHints {
-    pub paper: Option<String>,
-    pub margin: Margin,
-    pub text_size: Option<String>,
     pub font: Option<String>,
-    pub justify: Option<bool>,
     pub leading: Option<String>,
     pub first_line_indent: Option<String>,
-    pub columns: Option<usize>,
     pub bibliography_style: Option<String>,
     pub colors: BTreeMap<String, String>,
     pub heading_styles: BTreeMap<u8, HeadingStyle>,
     pub equation_numbering: Option<String>,
     pub heading_numbering_none: bool,
     pub uses_natbib: bool,
-    pub uses_amsthm: bool,
     pub has_headings: bool,
+    pub has_non_ascii_text: bool,
+    /// A character of the text pdflatex cannot set, as CJK.
+    pub unicode_only_char: Option<char>,
     pub cite_command: Option<String>,
+    pub code_block: Option<CodeBlockStyle>,
 }
 
-#[derive(Debug, Default, Clone)]
-pub struct Margin {
-    pub all: Option<String>,
-    pub left: Option<String>,
-    pub right: Option<String>,
-    pub top: Option<String>,
-    pub bottom: Option<String>,
+/// Block styling for raw code recognized from
+/// `#show raw.where(block: true): set block(...)`. Colors are LaTeX color
+/// specs; lengths are already converted to LaTeX units.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CodeBlockStyle {
+    pub fill: Option<String>,
+    pub inset: Option<String>,
+    pub stroke: Option<String>,
+    pub stroke_width: Option<String>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -51,7 +58,6 @@ pub fn extract_preamble_hints(input: &str) -> PreambleHints {
             SyntaxKind::SetRule => {
                 if let Some(name) = set_rule_name(&node) {
                     match name.as_str() {
-                        "page" => parse_page_set(&node, &mut hints),
                         "text" => parse_text_set(&node, &mut hints),
                         "par" => parse_par_set(&node, &mut hints),
                         "math.equation" => parse_math_equation_set(&node, &mut hints),
@@ -67,8 +73,15 @@ pub fn extract_preamble_hints(input: &str) -> PreambleHints {
             SyntaxKind::Heading => {
                 hints.has_headings = true;
             }
+            SyntaxKind::Text if !node.text().is_ascii() => {
+                hints.has_non_ascii_text = true;
+                hints.unicode_only_char = hints
+                    .unicode_only_char
+                    .or_else(|| unicode_only_char(node.text()));
+            }
             SyntaxKind::ShowRule => {
                 parse_heading_show_rule(&node, &mut hints);
+                parse_raw_show_rule(&node, &mut hints);
             }
             SyntaxKind::FuncCall => {
                 if let Some(name) = get_func_call_name(&node) {
@@ -78,9 +91,6 @@ pub fn extract_preamble_hints(input: &str) -> PreambleHints {
                     if name == "bibliography" {
                         parse_bibliography_call(&node, &mut hints);
                     }
-                    if is_theorem_like(&name) {
-                        hints.uses_amsthm = true;
-                    }
                 }
             }
             SyntaxKind::LetBinding => {
@@ -98,20 +108,19 @@ pub fn extract_preamble_hints(input: &str) -> PreambleHints {
     hints
 }
 
-pub fn render_article_preamble(hints: &PreambleHints) -> String {
+/// The preamble of an article set as `hints` and the document-wide
+/// settings of `meta` say, under the encoding and citation package of
+/// `options`. The page, font size, language, justification and heading
+/// numbering are the backend's to set, from `meta`.
+pub fn render_article_preamble(
+    hints: &PreambleHints,
+    meta: &DocumentMeta,
+    options: &LatexRenderOptions,
+) -> String {
+    let encoding = options.encoding_mode;
     let mut out = String::new();
 
-    let mut class_opts = Vec::new();
-    if let Some(size) = hints.text_size.as_deref() {
-        if let Some(opt) = map_text_size_option(size) {
-            class_opts.push(opt.to_string());
-        }
-    }
-    if let Some(paper) = hints.paper.as_deref() {
-        if let Some(opt) = map_paper_option(paper) {
-            class_opts.push(opt.to_string());
-        }
-    }
+    let class_opts = class_options(meta, &DocumentTemplate::Article);
 
     if class_opts.is_empty() {
         out.push_str("\\documentclass{article}\n");
@@ -122,9 +131,8 @@ pub fn render_article_preamble(hints: &PreambleHints) -> String {
         ));
     }
 
-    if let Some(geometry) = build_geometry_options(&hints.margin) {
-        out.push_str(&format!("\\usepackage[{}]{{geometry}}\n", geometry));
-    }
+    out.push_str(&render_encoding_packages(hints, encoding));
+    push_meta_preamble(&mut out, meta, &DocumentTemplate::Article);
 
     out.push_str("\\usepackage{amsmath,amssymb}\n");
     out.push_str("\\usepackage{graphicx}\n");
@@ -138,12 +146,10 @@ pub fn render_article_preamble(hints: &PreambleHints) -> String {
     out.push_str("\\usepackage{array}\n");
     out.push_str("\\usepackage{textcomp}\n");
     out.push_str("\\usepackage{float}\n");
-    if hints.uses_natbib {
+    // biblatex, which the content packages load, cannot go with natbib.
+    if hints.uses_natbib && options.citation_package == CitationPackage::Natbib {
         out.push_str("\\usepackage{natbib}\n");
     }
-    if hints.uses_amsthm {
-        out.push_str("\\usepackage{amsthm}\n");
-    }
 
     if !hints.heading_styles.is_empty() {
         out.push_str("\\usepackage{titlesec}\n");
@@ -175,6 +181,8 @@ pub fn render_article_preamble(hints: &PreambleHints) -> String {
     if let Some(font) = hints.font.as_deref() {
         if is_new_computer_modern(font) {
             out.push_str("\\usepackage{newcomputermodern}\n");
+        } else if encoding == EncodingMode::Unicode {
+            out.push_str(&format!("\\setmainfont{{{}}}\n", escape_latex(font)));
         } else {
             out.push_str("\\usepackage{iftex}\n");
             out.push_str("\\ifPDFTeX\n");
@@ -189,11 +197,7 @@ pub fn render_article_preamble(hints: &PreambleHints) -> String {
         out.push_str(&format!("\\numberwithin{{equation}}{{{}}}\n", within));
     }
 
-    if hints.uses_amsthm {
-        out.push_str(&render_amsthm_definitions(hints));
-    }
-
-    if let Some(stretch) = compute_line_stretch(hints) {
+    if let Some(stretch) = compute_line_stretch(hints, meta) {
         out.push_str("\\usepackage{setspace}\n");
         out.push_str(&format!("\\setstretch{{{:.2}}}\n", stretch));
     }
@@ -210,74 +214,13 @@ pub fn render_article_preamble(hints: &PreambleHints) -> String {
         out.push_str(&format!("\\setlength{{\\parindent}}{{{}}}\n", indent));
     }
 
-    if hints.justify == Some(false) {
-        out.push_str("\\AtBeginDocument{\\raggedright}\n");
-    }
+    push_page_style(&mut out, meta, options);
+    out.push_str(&render_code_block_setup(hints, code_backend(hints)));
 
     out.push_str("\\providecommand{\\textsubscript}[1]{$_{\\text{#1}}$}\n");
     out
 }
 
-pub fn render_amsthm_definitions(hints: &PreambleHints) -> String {
-    if !hints.uses_amsthm {
-        return String::new();
-    }
-    let mut out = String::new();
-    let within = theorem_number_within(hints);
-    let theorem_decl = match within.as_deref() {
-        Some(within) => format!("\\newtheorem{{theorem}}{{Theorem}}[{}]\n", within),
-        None => "\\newtheorem{theorem}{Theorem}\n".to_string(),
-    };
-    out.push_str("\\theoremstyle{plain}\n");
-    out.push_str(&theorem_decl);
-    out.push_str("\\newtheorem{lemma}[theorem]{Lemma}\n");
-    out.push_str("\\newtheorem{corollary}[theorem]{Corollary}\n");
-    out.push_str("\\newtheorem{proposition}[theorem]{Proposition}\n");
-    out.push_str("\\newtheorem{claim}[theorem]{Claim}\n");
-    out.push_str("\\newtheorem{axiom}[theorem]{Axiom}\n");
-    out.push_str("\\theoremstyle{definition}\n");
-    out.push_str("\\newtheorem{definition}[theorem]{Definition}\n");
-    out.push_str("\\newtheorem{example}[theorem]{Example}\n");
-    out.push_str("\\theoremstyle{remark}\n");
-    out.push_str("\\newtheorem{remark}[theorem]{Remark}\n");
-    out
-}
-
-fn parse_page_set(node: &SyntaxNode, hints: &mut PreambleHints) {
-    let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) else {
-        return;
-    };
-    for child in args.children() {
-        if child.kind() != SyntaxKind::Named {
-            continue;
-        }
-        let key = extract_named_key(&child).unwrap_or_default();
-        let Some(value) = extract_named_value_node(&child) else {
-            continue;
-        };
-        match key.as_str() {
-            "paper" => {
-                if let Some(text) = extract_literal_string(&value) {
-                    hints.paper = Some(text);
-                }
-            }
-            "margin" => {
-                if let Some(margin) = parse_margin_value(&value) {
-                    hints.margin = margin;
-                }
-            }
-            "columns" => {
-                if let Some(columns) = parse_usize_literal(&value) {
-                    if columns >= 1 {
-                        hints.columns = Some(columns);
-                    }
-                }
-            }
-            _ => {}
-        }
-    }
-}
-
 fn parse_text_set(node: &SyntaxNode, hints: &mut PreambleHints) {
     let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) else {
         return;
@@ -291,11 +234,6 @@ fn parse_text_set(node: &SyntaxNode, hints: &mut PreambleHints) {
             continue;
         };
         match key.as_str() {
-            "size" => {
-                if let Some(text) = extract_literal_string(&value) {
-                    hints.text_size = Some(text);
-                }
-            }
             "font" => {
                 if let Some(text) = extract_literal_string(&value) {
                     hints.font = Some(text);
@@ -319,9 +257,6 @@ fn parse_par_set(node: &SyntaxNode, hints: &mut PreambleHints) {
             continue;
         };
         match key.as_str() {
-            "justify" => {
-                hints.justify = parse_bool_literal(&value);
-            }
             "leading" => {
                 if let Some(text) = extract_literal_string(&value) {
                     hints.leading = Some(text);
@@ -444,7 +379,17 @@ fn parse_cite_set(node: &SyntaxNode, hints: &mut PreambleHints) {
         if matches!(key.as_str(), "style" | "form" | "mode") {
             if let Some(text) = extract_literal_string(&value) {
                 let lowered = text.trim().to_lowercase();
-                let cmd = if lowered.contains("author")
+                // Typst's own forms, as the backend sets them with natbib.
+                let form = match (key.as_str(), lowered.as_str()) {
+                    ("form", "prose") => Some("citet"),
+                    ("form", "full") => Some("citet*"),
+                    ("form", "author") => Some("citeauthor"),
+                    ("form", "year") => Some("citeyearpar"),
+                    _ => None,
+                };
+                let cmd = if let Some(form) = form {
+                    form
+                } else if lowered.contains("author")
                     || lowered.contains("text")
                     || lowered.contains("prose")
                 {
@@ -463,79 +408,9 @@ fn parse_cite_set(node: &SyntaxNode, hints: &mut PreambleHints) {
     }
 }
 
-fn parse_margin_value(node: &SyntaxNode) -> Option<Margin> {
-    match node.kind() {
-        SyntaxKind::Numeric | SyntaxKind::Str => {
-            let value = extract_literal_string(node)?;
-            return Some(Margin {
-                all: Some(value),
-                ..Margin::default()
-            });
-        }
-        SyntaxKind::Dict => {
-            let mut margin = Margin::default();
-            for child in node.children() {
-                if child.kind() != SyntaxKind::Named {
-                    continue;
-                }
-                let key = extract_named_key(&child).unwrap_or_default();
-                let Some(value) = extract_named_value_node(&child) else {
-                    continue;
-                };
-                let Some(text) = extract_literal_string(&value) else {
-                    continue;
-                };
-                match key.as_str() {
-                    "x" => {
-                        margin.left = Some(text.clone());
-                        margin.right = Some(text);
-                    }
-                    "y" => {
-                        margin.top = Some(text.clone());
-                        margin.bottom = Some(text);
-                    }
-                    "left" => margin.left = Some(text),
-                    "right" => margin.right = Some(text),
-                    "top" => margin.top = Some(text),
-                    "bottom" => margin.bottom = Some(text),
-                    _ => {}
-                }
-            }
-            return Some(margin);
-        }
-        _ => {}
-    }
-    None
-}
-
-fn build_geometry_options(margin: &Margin) -> Option<String> {
-    if let Some(all) = margin.all.as_deref() {
-        return Some(format!("margin={}", all));
-    }
-
-    let mut opts = Vec::new();
-    if let Some(left) = margin.left.as_deref() {
-        opts.push(format!("left={}", left));
-    }
-    if let Some(right) = margin.right.as_deref() {
-        opts.push(format!("right={}", right));
-    }
-    if let Some(top) = margin.top.as_deref() {
-        opts.push(format!("top={}", top));
-    }
-    if let Some(bottom) = margin.bottom.as_deref() {
-        opts.push(format!("bottom={}", bottom));
-    }
-    if opts.is_empty() {
-        None
-    } else {
-        Some(opts.join(","))
-    }
-}
-
-fn compute_line_stretch(hints: &PreambleHints) -> Option<f64> {
+fn compute_line_stretch(hints: &PreambleHints, meta: &DocumentMeta) -> Option<f64> {
     let leading = hints.leading.as_deref()?;
-    let size = hints.text_size.as_deref()?;
+    let size = meta.font_size.as_deref()?;
     let leading_pt = parse_length_to_pt(leading, size)?;
     let size_pt = parse_length_to_pt(size, size)?;
     if size_pt <= 0.0 {
@@ -565,7 +440,9 @@ pub fn equation_number_within(hints: &PreambleHints) -> Option<&'static str> {
     None
 }
 
-fn theorem_number_within(hints: &PreambleHints) -> Option<&'static str> {
+/// The counter theorem numbers reset with: that of equations, or sections
+/// in a document with headings.
+pub fn theorem_number_within(hints: &PreambleHints) -> Option<&'static str> {
     equation_number_within(hints).or_else(|| {
         if hints.has_headings {
             Some("section")
@@ -654,8 +531,208 @@ fn parse_heading_show_rule(node: &SyntaxNode, hints: &mut PreambleHints) {
     }
 }
 
+fn parse_raw_show_rule(node: &SyntaxNode, hints: &mut PreambleHints) {
+    if !matches!(show_rule_target(node), Some(ShowTarget::RawBlock)) {
+        return;
+    }
+    let mut style = hints.code_block.clone().unwrap_or_default();
+    walk_nodes(node, &mut |descendant| {
+        let args = match descendant.kind() {
+            SyntaxKind::SetRule if set_rule_name(descendant).as_deref() == Some("block") => {
+                descendant.children().find(|c| c.kind() == SyntaxKind::Args)
+            }
+            SyntaxKind::FuncCall
+                if matches!(
+                    get_func_call_name(descendant).as_deref(),
+                    Some("block") | Some("block.with")
+                ) =>
+            {
+                descendant.children().find(|c| c.kind() == SyntaxKind::Args)
+            }
+            _ => None,
+        };
+        let Some(args) = args else {
+            return;
+        };
+        for child in args.children() {
+            if child.kind() != SyntaxKind::Named {
+                continue;
+            }
+            let key = extract_named_key(child).unwrap_or_default();
+            let Some(value) = extract_named_value_node(child) else {
+                continue;
+            };
+            match key.as_str() {
+                "fill" => {
+                    if let Some(color) = code_color_spec(&value, hints) {
+                        style.fill = Some(color);
+                    }
+                }
+                "inset" => {
+                    if let Some(length) = first_length(&value) {
+                        style.inset = Some(length);
+                    }
+                }
+                "stroke" => {
+                    let mut color = None;
+                    walk_nodes(&value, &mut |part| {
+                        if color.is_none() {
+                            color = code_color_spec(part, hints);
+                        }
+                    });
+                    style.stroke = Some(color.unwrap_or_else(|| "black".to_string()));
+                    style.stroke_width = first_length(&value);
+                }
+                _ => {}
+            }
+        }
+    });
+    if style != CodeBlockStyle::default() {
+        hints.code_block = Some(style);
+    }
+}
+
+/// Resolve a Typst color expression to an xcolor spec: a color the
+/// document defines by its name, any other by its expression, or its value
+/// as `[HTML]{..}` where xcolor has no expression for it.
+fn code_color_spec(node: &SyntaxNode, hints: &PreambleHints) -> Option<String> {
+    if node.kind() == SyntaxKind::Ident && hints.colors.contains_key(node.text().as_str()) {
+        return Some(node.text().to_string());
+    }
+    let color = Color::parse_typst(&node_full_text(node))?;
+    if matches!(color, Color::Custom(_)) {
+        return None;
+    }
+    Some(match color.to_latex() {
+        (Some(model), value) => format!("[{}]{{{}}}", model, value),
+        (None, expression) => expression,
+    })
+}
+
+fn first_length(node: &SyntaxNode) -> Option<String> {
+    let mut found = None;
+    walk_nodes(node, &mut |part| {
+        if found.is_none() && part.kind() == SyntaxKind::Numeric {
+            let text = part.text().to_string();
+            if text.ends_with("pt") || text.ends_with("em") || text.ends_with("cm")
+                || text.ends_with("mm") || text.ends_with("in")
+            {
+                found = Some(text);
+            }
+        }
+    });
+    found
+}
+
+/// The code backend used for raw blocks: block theming needs `listings`,
+/// plain documents keep `verbatim`.
+/// How text outside ASCII is written when the caller does not say: kept as
+/// written when the document sets a system font, which only XeLaTeX and
+/// LuaLaTeX can load, or has text pdflatex cannot set, and transliterated
+/// for pdflatex otherwise.
+pub fn encoding_mode(hints: &PreambleHints) -> EncodingMode {
+    if hints.unicode_only_char.is_some() {
+        return EncodingMode::Unicode;
+    }
+    match hints.font.as_deref() {
+        Some(font) if !is_new_computer_modern(font) => EncodingMode::Unicode,
+        _ => EncodingMode::Transliterate,
+    }
+}
+
+/// The packages `encoding` needs: `fontspec` for Unicode engines, and T1
+/// font encoding when transliterated text has accented letters.
+pub fn render_encoding_packages(hints: &PreambleHints, encoding: EncodingMode) -> String {
+    match encoding {
+        EncodingMode::Unicode => "\\usepackage{fontspec}\n".to_string(),
+        EncodingMode::Transliterate if hints.has_non_ascii_text => {
+            "\\usepackage[T1]{fontenc}\n".to_string()
+        }
+        _ => String::new(),
+    }
+}
+
+pub fn code_backend(hints: &PreambleHints) -> CodeBackend {
+    if hints.code_block.is_some() {
+        CodeBackend::Listings
+    } else {
+        CodeBackend::Verbatim
+    }
+}
+
+/// Preamble lines configuring `backend` for the raw-block theme in `hints`.
+pub fn render_code_block_setup(hints: &PreambleHints, backend: CodeBackend) -> String {
+    let mut out = String::new();
+    let style = hints.code_block.clone().unwrap_or_default();
+    match backend {
+        CodeBackend::Verbatim => {}
+        CodeBackend::Listings => {
+            out.push_str("\\usepackage{listings}\n");
+            let mut opts = vec![
+                "basicstyle=\\ttfamily\\small".to_string(),
+                "columns=fullflexible".to_string(),
+                "keepspaces=true".to_string(),
+            ];
+            if let Some(fill) = style.fill.as_deref() {
+                out.push_str(&define_code_color("tylaxCodeBackground", fill));
+                opts.push("backgroundcolor=\\color{tylaxCodeBackground}".to_string());
+            }
+            if let Some(stroke) = style.stroke.as_deref() {
+                out.push_str(&define_code_color("tylaxCodeRule", stroke));
+                opts.push("frame=single".to_string());
+                opts.push("rulecolor=\\color{tylaxCodeRule}".to_string());
+                if let Some(width) = style.stroke_width.as_deref() {
+                    opts.push(format!("framerule={}", width));
+                }
+            } else if style.inset.is_some() {
+                // A zero-width frame makes `framesep` pad the background.
+                opts.push("frame=single".to_string());
+                opts.push("framerule=0pt".to_string());
+            }
+            if let Some(inset) = style.inset.as_deref() {
+                opts.push(format!("framesep={}", inset));
+                opts.push(format!("xleftmargin={}", inset));
+                opts.push(format!("xrightmargin={}", inset));
+            }
+            out.push_str(&format!("\\lstset{{{}}}\n", opts.join(", ")));
+        }
+        CodeBackend::Minted => {
+            out.push_str("\\usepackage{minted}\n");
+            let mut opts = Vec::new();
+            if let Some(fill) = style.fill.as_deref() {
+                out.push_str(&define_code_color("tylaxCodeBackground", fill));
+                opts.push("bgcolor=tylaxCodeBackground".to_string());
+            }
+            if let Some(stroke) = style.stroke.as_deref() {
+                out.push_str(&define_code_color("tylaxCodeRule", stroke));
+                opts.push("frame=single".to_string());
+                opts.push("rulecolor=tylaxCodeRule".to_string());
+                if let Some(width) = style.stroke_width.as_deref() {
+                    opts.push(format!("framerule={}", width));
+                }
+            }
+            if let Some(inset) = style.inset.as_deref() {
+                opts.push(format!("framesep={}", inset));
+            }
+            if !opts.is_empty() {
+                out.push_str(&format!("\\setminted{{{}}}\n", opts.join(", ")));
+            }
+        }
+        _ => {}
+    }
+    out
+}
+
+fn define_code_color(name: &str, spec: &str) -> String {
+    match spec.strip_prefix("[HTML]") {
+        Some(hex) => format!("\\definecolor{{{}}}{{HTML}}{}\n", name, hex),
+        None => format!("\\colorlet{{{}}}{{{}}}\n", name, spec),
+    }
+}
+
 enum ShowTarget {
     Heading(Vec<u8>),
+    RawBlock,
     Other,
 }
 
@@ -667,6 +744,9 @@ fn show_rule_target(node: &SyntaxNode) -> Option<ShowTarget> {
                 if child.text() == "heading" {
                     return Some(ShowTarget::Heading(vec![1, 2, 3, 4]));
                 }
+                if child.text() == "raw" {
+                    return Some(ShowTarget::RawBlock);
+                }
             }
             SyntaxKind::FuncCall => {
                 if let Some(name) = get_func_call_name(&child) {
@@ -679,6 +759,9 @@ fn show_rule_target(node: &SyntaxNode) -> Option<ShowTarget> {
                     if name == "heading" {
                         return Some(ShowTarget::Heading(vec![1, 2, 3, 4]));
                     }
+                    if name == "raw.where" && !raw_where_excludes_blocks(child) {
+                        return Some(ShowTarget::RawBlock);
+                    }
                 }
             }
             _ => {}
@@ -687,6 +770,20 @@ fn show_rule_target(node: &SyntaxNode) -> Option<ShowTarget> {
     Some(ShowTarget::Other)
 }
 
+/// `raw.where(block: false)` only selects inline code.
+fn raw_where_excludes_blocks(node: &SyntaxNode) -> bool {
+    let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) else {
+        return false;
+    };
+    args.children().any(|child| {
+        child.kind() == SyntaxKind::Named
+            && extract_named_key(child).as_deref() == Some("block")
+            && extract_named_value_node(child)
+                .and_then(|value| parse_bool_literal(&value))
+                == Some(false)
+    })
+}
+
 fn extract_level_from_args(node: &SyntaxNode) -> Option<u8> {
     let args = node.children().find(|c| c.kind() == SyntaxKind::Args)?;
     for child in args.children() {
@@ -805,61 +902,10 @@ fn is_italic_style(value: &str) -> bool {
 }
 
 pub fn parse_length_to_pt(value: &str, font_size: &str) -> Option<f64> {
-    let (num, unit) = split_number_unit(value)?;
-    if unit == "pt" {
-        return Some(num);
-    }
-    if unit == "em" {
-        let (base, base_unit) = split_number_unit(font_size)?;
-        if base_unit == "pt" {
-            return Some(num * base);
-        }
-    }
-    None
-}
-
-fn split_number_unit(value: &str) -> Option<(f64, String)> {
-    let trimmed = value.trim();
-    let mut number = String::new();
-    let mut unit = String::new();
-    for ch in trimmed.chars() {
-        if ch.is_ascii_digit() || ch == '.' || ch == '-' {
-            number.push(ch);
-        } else {
-            unit.push(ch);
-        }
-    }
-    if number.is_empty() {
-        return None;
-    }
-    let num = number.parse::<f64>().ok()?;
-    Some((num, unit.trim().to_string()))
-}
-
-fn map_text_size_option(value: &str) -> Option<&'static str> {
-    let (num, unit) = split_number_unit(value)?;
-    if unit != "pt" {
-        return None;
-    }
-    if (num - 10.0).abs() < 0.2 {
-        return Some("10pt");
-    }
-    if (num - 11.0).abs() < 0.2 {
-        return Some("11pt");
-    }
-    if (num - 12.0).abs() < 0.2 {
-        return Some("12pt");
-    }
-    None
-}
-
-fn map_paper_option(value: &str) -> Option<&'static str> {
-    let normalized = value.trim().trim_matches('"').to_lowercase();
-    match normalized.as_str() {
-        "us-letter" | "letter" | "letterpaper" => Some("letterpaper"),
-        "a4" | "a4paper" => Some("a4paper"),
-        _ => None,
-    }
+    let font_size = Length::parse_typst(font_size)
+        .and_then(|size| size.to_pt(0.0))
+        .unwrap_or(0.0);
+    Length::parse_typst(value)?.to_pt(font_size)
 }
 
 fn parse_color_let(node: &SyntaxNode) -> Option<(String, String)> {
@@ -888,88 +934,16 @@ fn parse_let_value(node: &SyntaxNode) -> Option<(String, SyntaxNode)> {
     Some((name?, value?))
 }
 
+/// The value of a color expression as six hex digits, as `\definecolor`
+/// takes it in the `HTML` model.
 fn extract_hex_color(node: &SyntaxNode) -> Option<String> {
-    match node.kind() {
-        SyntaxKind::Str => return extract_hex_from_text(&node.text()),
-        SyntaxKind::FuncCall => {
-            let name = get_func_call_name(node)?;
-            if name != "rgb" {
-                return None;
-            }
-            if let Some(hex) = node
-                .children()
-                .find(|c| c.kind() == SyntaxKind::Args)
-                .and_then(|args| {
-                    args.children()
-                        .find(|c| c.kind() == SyntaxKind::Str)
-                        .map(|s| extract_hex_from_text(&s.text()))
-                })
-                .flatten()
-            {
-                return Some(hex);
-            }
-
-            let mut nums = Vec::new();
-            if let Some(args) = node.children().find(|c| c.kind() == SyntaxKind::Args) {
-                for child in args.children() {
-                    if matches!(
-                        child.kind(),
-                        SyntaxKind::Numeric | SyntaxKind::Int | SyntaxKind::Float
-                    ) {
-                        if let Some(n) = parse_number(&child.text()) {
-                            nums.push(n);
-                        }
-                    }
-                }
-            }
-            if nums.len() == 3 {
-                let mut vals = [nums[0], nums[1], nums[2]];
-                if vals.iter().all(|v| *v <= 1.0) {
-                    for v in &mut vals {
-                        *v *= 255.0;
-                    }
-                }
-                let hex = format!(
-                    "{:02X}{:02X}{:02X}",
-                    clamp_byte(vals[0]),
-                    clamp_byte(vals[1]),
-                    clamp_byte(vals[2])
-                );
-                return Some(hex);
-            }
-        }
-        _ => {}
-    }
-    None
-}
-
-fn clamp_byte(value: f64) -> u8 {
-    if value.is_nan() {
-        return 0;
-    }
-    let v = value.round().max(0.0).min(255.0);
-    v as u8
-}
-
-fn extract_hex_from_text(text: &str) -> Option<String> {
-    let trimmed = text.trim().trim_matches('"');
-    if let Some(pos) = trimmed.find('#') {
-        let hex = &trimmed[pos + 1..];
-        let mut out = String::new();
-        for ch in hex.chars() {
-            if !ch.is_ascii_hexdigit() {
-                break;
-            }
-            if out.len() >= 6 {
-                break;
-            }
-            out.push(ch);
-        }
-        if out.len() == 3 || out.len() == 6 {
-            return Some(out.to_uppercase());
-        }
-    }
-    None
+    let text = node_full_text(node);
+    let color = match node.kind() {
+        SyntaxKind::Str => Color::parse_typst(&format!("rgb({})", text.trim()))?,
+        _ => Color::parse_typst(&text)?,
+    };
+    let [r, g, b] = color.to_rgb()?;
+    Some(format!("{:02X}{:02X}{:02X}", r, g, b))
 }
 
 fn parse_number(text: &str) -> Option<f64> {
@@ -1013,26 +987,6 @@ pub fn equation_numbering_enabled(hints: &PreambleHints) -> bool {
     false
 }
 
-pub fn is_two_column(hints: &PreambleHints) -> bool {
-    hints.columns.unwrap_or(1) >= 2
-}
-
-fn is_theorem_like(name: &str) -> bool {
-    matches!(
-        name,
-        "theorem"
-            | "lemma"
-            | "corollary"
-            | "proposition"
-            | "definition"
-            | "example"
-            | "remark"
-            | "proof"
-            | "claim"
-            | "axiom"
-    )
-}
-
 fn bibliography_style_needs_natbib(style: &str) -> bool {
     let lowered = style.trim().to_lowercase();
     lowered.contains("author")
@@ -1118,20 +1072,8 @@ fn parse_bool_literal(node: &SyntaxNode) -> Option<bool> {
     }
 }
 
-fn parse_usize_literal(node: &SyntaxNode) -> Option<usize> {
-    let raw = node.text().trim();
-    if raw.is_empty() {
-        return None;
-    }
-    if let Ok(val) = raw.parse::<usize>() {
-        return Some(val);
-    }
-    if let Ok(val) = raw.parse::<f64>() {
-        if val.is_finite() && val > 0.0 {
-            return Some(val.round().clamp(1.0, 99.0) as usize);
-        }
-    }
-    None
+fn node_full_text(node: &SyntaxNode) -> String {
+    node.clone().into_text().to_string()
 }
 
 fn is_new_computer_modern(value: &str) -> bool {
@@ -0,0 +1,212 @@
+//! Round trips through the other language: Typst → LaTeX → Typst and
+//! LaTeX → Typst → LaTeX. The document read back is compared with the
+//! original by its structure, so content a conversion drops or garbles
+//! shows up as a difference.
+
+use std::io;
+use std::path::Path;
+
+use tylax_ir::{stats, Block, DocStats, Document, HeadingNode};
+use tylax_typst_frontend::typst_to_ir;
+
+use crate::ir_pipeline::{latex_to_ir, typst_to_latex_ir};
+use crate::latex_document_to_typst;
+use crate::utils::compile_check::{
+    compile_latex, compile_typst, typst_available, CompileOutcome, TexEngine,
+};
+
+/// A document language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Latex,
+    Typst,
+}
+
+impl Language {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::Latex => "LaTeX",
+            Language::Typst => "Typst",
+        }
+    }
+
+    fn other(&self) -> Language {
+        match self {
+            Language::Latex => Language::Typst,
+            Language::Typst => Language::Latex,
+        }
+    }
+}
+
+/// Something that reads differently in the document converted back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// What differs, e.g. `equations` or `block 3`.
+    pub what: String,
+    pub original: String,
+    pub round_tripped: String,
+}
+
+/// A document taken to the other language and back.
+#[derive(Debug, Clone)]
+pub struct RoundTrip {
+    pub from: Language,
+    /// The document in the other language.
+    pub converted: String,
+    /// The document converted back to its own language.
+    pub back: String,
+    pub differences: Vec<Difference>,
+}
+
+impl RoundTrip {
+    /// Whether the document read back has the structure of the original.
+    pub fn is_faithful(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// Compile both ends of the trip: the converted document and the one
+    /// converted back, with the files they name read from `search_dir`,
+    /// the directory of the original. A language whose toolchain is not
+    /// installed is left out.
+    pub fn compile(
+        &self,
+        search_dir: Option<&Path>,
+    ) -> Vec<(Language, io::Result<CompileOutcome>)> {
+        [
+            (self.from.other(), &self.converted),
+            (self.from, &self.back),
+        ]
+        .into_iter()
+        .filter_map(|(language, source)| match language {
+            Language::Latex => {
+                let engine = TexEngine::detect()?;
+                Some((language, compile_latex(source, engine, search_dir)))
+            }
+            Language::Typst => {
+                typst_available().then(|| (language, compile_typst(source, search_dir)))
+            }
+        })
+        .collect()
+    }
+}
+
+/// Convert `source` to the other language and back, and compare the IR of
+/// the original with that of the document converted back.
+pub fn round_trip(source: &str, from: Language) -> RoundTrip {
+    let (converted, back, original, round_tripped) = match from {
+        Language::Typst => {
+            let converted = typst_to_latex_ir(source, true);
+            let back = latex_document_to_typst(&converted);
+            let round_tripped = typst_to_ir(&back);
+            (converted, back, typst_to_ir(source), round_tripped)
+        }
+        Language::Latex => {
+            let converted = latex_document_to_typst(source);
+            let back = typst_to_latex_ir(&converted, true);
+            let round_tripped = latex_to_ir(&back);
+            (converted, back, latex_to_ir(source), round_tripped)
+        }
+    };
+    RoundTrip {
+        from,
+        converted,
+        back,
+        differences: compare(&original, &round_tripped),
+    }
+}
+
+/// The structural differences between two documents: their counts, their
+/// headings and the kinds of their top-level blocks in order.
+pub fn compare(original: &Document, round_tripped: &Document) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    let (before, after) = (stats(original), stats(round_tripped));
+    let mut check = |what: &str, original: String, round_tripped: String| {
+        if original != round_tripped {
+            differences.push(Difference {
+                what: what.to_string(),
+                original,
+                round_tripped,
+            });
+        }
+    };
+    let counts = |stats: &DocStats| {
+        [
+            ("words", stats.words),
+            ("equations", stats.equations),
+            ("tables", stats.tables),
+            ("figures", stats.figures),
+        ]
+    };
+    for ((what, original), (_, round_tripped)) in counts(&before).into_iter().zip(counts(&after)) {
+        check(what, original.to_string(), round_tripped.to_string());
+    }
+    check(
+        "citations",
+        before.citations.join(", "),
+        after.citations.join(", "),
+    );
+    check(
+        "headings",
+        heading_outline(&before.headings),
+        heading_outline(&after.headings),
+    );
+    let (before, after) = (block_kinds(original), block_kinds(round_tripped));
+    if let Some(index) =
+        (0..before.len().max(after.len())).find(|&index| before.get(index) != after.get(index))
+    {
+        let kind = |kinds: &[&str]| kinds.get(index).unwrap_or(&"nothing").to_string();
+        check(&format!("block {}", index + 1), kind(&before), kind(&after));
+    }
+    differences
+}
+
+fn heading_outline(headings: &[HeadingNode]) -> String {
+    headings
+        .iter()
+        .map(|heading| {
+            if heading.children.is_empty() {
+                heading.title.clone()
+            } else {
+                format!("{} ({})", heading.title, heading_outline(&heading.children))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// The variant names of the top-level blocks, without empty paragraphs.
+fn block_kinds(doc: &Document) -> Vec<&'static str> {
+    doc.blocks
+        .iter()
+        .filter(|block| !matches!(block, Block::Paragraph(inlines) if inlines.is_empty()))
+        .map(block_kind)
+        .collect()
+}
+
+fn block_kind(block: &Block) -> &'static str {
+    match block {
+        Block::Paragraph(_) => "paragraph",
+        Block::Heading { .. } => "heading",
+        Block::List { .. } => "list",
+        Block::TermList(_) => "term list",
+        Block::MathBlock(_) => "equation",
+        Block::Table(_) => "table",
+        Block::Figure(_) => "figure",
+        Block::Environment(_) => "environment",
+        Block::Bibliography { .. } => "bibliography",
+        Block::Outline { .. } => "outline",
+        Block::Quote(_) => "quote",
+        Block::Align { .. } => "align",
+        Block::Box(_) => "box",
+        Block::Block(_) => "block",
+        Block::Columns(_) => "columns",
+        Block::Grid(_) => "grid",
+        Block::Algorithm(_) => "algorithm",
+        Block::Slide(_) => "slide",
+        Block::SideBySide(_) => "side by side",
+        Block::VSpace(_) => "vertical space",
+        Block::CodeBlock { .. } => "code",
+        Block::Unsupported { .. } => "unsupported",
+        _ => "other",
+    }
+}
@@ -1,15 +1,15 @@
-use tylax_latex_backend::{render_document, LatexRenderOptions};
+use tylax_latex_backend::{push_content_packages, render_document, LatexRenderOptions};
 use tylax_typst_frontend::typst_to_ir;
 use typst_syntax::parse;
 
+use crate::ir_pipeline::LatexOutputOptions;
 use crate::preamble_hints::{
-    equation_number_within, equation_numbering_enabled, extract_preamble_hints, parse_length_to_pt,
-    render_amsthm_definitions,
+    equation_number_within, extract_preamble_hints, render_encoding_packages,
 };
 use crate::template_adapters::common::{
     collect_let_bindings, escape_latex, extract_array_elements, extract_array_strings,
     extract_bibliography_path, extract_dict_entries, extract_named_args, extract_string_like,
-    find_show_rule_with_prefix, resolve_ident,
+    find_show_rule_with_prefix, natbib_render_options, resolve_ident,
 };
 
 #[derive(Debug, Clone)]
@@ -46,7 +46,7 @@ struct AcmMetadata {
     bibliography: Option<String>,
 }
 
-pub fn maybe_convert_acm(input: &str) -> Option<String> {
+pub fn maybe_convert_acm(input: &str, output: &LatexOutputOptions) -> Option<String> {
     let root = parse(input);
     let (show, _name) = find_show_rule_with_prefix(&root, "acm")?;
     let args = extract_named_args(&show);
@@ -54,37 +54,15 @@ pub fn maybe_convert_acm(input: &str) -> Option<String> {
 
     let meta = extract_acm_metadata(&args, &lets);
     let hints = extract_preamble_hints(input);
-    let base_font_size_pt = hints
-        .text_size
-        .as_deref()
-        .and_then(|size| parse_length_to_pt(size, "10pt"));
-
-    let cite_command = hints.cite_command.clone().or_else(|| {
-        if hints.uses_natbib {
-            Some("citep".to_string())
-        } else {
-            None
-        }
-    });
 
     let doc = typst_to_ir(input);
-    let body = render_document(
-        &doc,
-        LatexRenderOptions {
-            full_document: false,
-            number_equations: equation_numbering_enabled(&hints),
-            two_column: true, // ACM sigconf is two-column
-            inline_wide_tables: false,
-            force_here: false,
-            table_grid: false,
-            table_style: tylax_latex_backend::TableStyle::Plain,
-            table_caption_position: tylax_latex_backend::TableCaptionPosition::Top,
-            bibliography_style_default: Some("ACM-Reference-Format".to_string()),
-            cite_command,
-            base_font_size_pt,
-            heading_numbering_none: hints.heading_numbering_none,
-        },
-    );
+    let options = LatexRenderOptions {
+        two_column: true, // ACM sigconf is two-column
+        table_caption_position: tylax_latex_backend::TableCaptionPosition::Top,
+        bibliography_style_default: Some("ACM-Reference-Format".to_string()),
+        ..natbib_render_options(&hints, &doc.meta, output)
+    };
+    let body = render_document(&doc, options.clone());
 
     let format = meta.acm_format.as_deref().unwrap_or("sigconf");
     let mut out = String::new();
@@ -93,6 +71,7 @@ pub fn maybe_convert_acm(input: &str) -> Option<String> {
     out.push_str("\\PassOptionsToPackage{table}{xcolor}\n");
     // Document class with format
     out.push_str(&format!("\\documentclass[{}]{{acmart}}\n", format));
+    out.push_str(&render_encoding_packages(&hints, options.encoding_mode));
 
     // ACM-specific metadata
     if let Some(doi) = meta.doi.as_deref() {
@@ -134,10 +113,6 @@ pub fn maybe_convert_acm(input: &str) -> Option<String> {
     out.push_str("\\usepackage{bm}\n");
     out.push_str("\\usepackage{booktabs}\n");
 
-    if hints.uses_amsthm {
-        out.push_str("\\usepackage{amsthm}\n");
-        out.push_str(&render_amsthm_definitions(&hints));
-    }
     if let Some(within) = equation_number_within(&hints) {
         out.push_str(&format!("\\numberwithin{{equation}}{{{}}}\n", within));
     }
@@ -182,6 +157,7 @@ pub fn maybe_convert_acm(input: &str) -> Option<String> {
         }
     }
 
+    push_content_packages(&mut out, &doc, &options);
     out.push_str("\\begin{document}\n");
 
     // Abstract
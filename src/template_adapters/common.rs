@@ -1,14 +1,90 @@
 use std::collections::HashMap;
 
+use tylax_ir::DocumentMeta;
+use tylax_latex_backend::{CitationPackage, LatexRenderOptions, TheoremNumbering};
 use typst_syntax::{SyntaxKind, SyntaxNode};
 
+use crate::ir_pipeline::LatexOutputOptions;
+use crate::preamble_hints::{
+    encoding_mode, equation_numbering_enabled, parse_length_to_pt, theorem_number_within,
+    PreambleHints,
+};
+
+/// The options a document body is rendered with, as the set rules in
+/// `hints`, the document-wide settings in `meta` and the caller's `output`
+/// settings say. Adapters override what their template fixes, as the column
+/// count or the table style.
+pub fn render_options(
+    hints: &PreambleHints,
+    meta: &DocumentMeta,
+    output: &LatexOutputOptions,
+) -> LatexRenderOptions {
+    LatexRenderOptions {
+        number_equations: equation_numbering_enabled(hints),
+        two_column: meta.columns.unwrap_or(1) >= 2,
+        bibliography_style_default: hints.bibliography_style.clone(),
+        cite_command: match output.citation_package {
+            CitationPackage::Natbib => hints
+                .cite_command
+                .clone()
+                .or_else(|| hints.uses_natbib.then(|| "citep".to_string())),
+            // The hints name natbib's commands; biblatex has its own.
+            CitationPackage::Biblatex => match hints.cite_command.as_deref() {
+                Some("citet") => Some("textcite".to_string()),
+                Some("citet*") => Some("fullcite".to_string()),
+                Some("citeauthor") => Some("citeauthor".to_string()),
+                Some("citeyearpar") => Some("parencite*".to_string()),
+                _ => None,
+            },
+            _ => None,
+        },
+        base_font_size_pt: meta
+            .font_size
+            .as_deref()
+            .and_then(|size| parse_length_to_pt(size, "10pt")),
+        heading_numbering_none: hints.heading_numbering_none,
+        encoding_mode: output.encoding_mode.unwrap_or_else(|| encoding_mode(hints)),
+        quote_style: output.quote_style,
+        math_emphasis: output.math_emphasis,
+        toc_depth: output.toc_depth,
+        cleveref: output.cleveref,
+        long_tables: output.long_tables,
+        long_table_rows: output.long_table_rows,
+        tabularx: output.tabularx,
+        citation_package: output.citation_package,
+        theorem_numbering: TheoremNumbering {
+            within: theorem_number_within(hints).map(str::to_string),
+            ..TheoremNumbering::default()
+        },
+        ..LatexRenderOptions::default()
+    }
+}
+
+/// [`render_options`] for a template whose class or style loads natbib,
+/// which biblatex cannot go with: citations are set with natbib whichever
+/// package the caller asked for.
+pub fn natbib_render_options(
+    hints: &PreambleHints,
+    meta: &DocumentMeta,
+    output: &LatexOutputOptions,
+) -> LatexRenderOptions {
+    let output = LatexOutputOptions {
+        citation_package: CitationPackage::Natbib,
+        ..output.clone()
+    };
+    render_options(hints, meta, &output)
+}
+
 pub fn find_show_rule_with_prefix(root: &SyntaxNode, prefix: &str) -> Option<(SyntaxNode, String)> {
     let mut stack = vec![root.clone()];
     while let Some(node) = stack.pop() {
         if node.kind() == SyntaxKind::ShowRule {
             if let Some(func) = node.children().find(|c| c.kind() == SyntaxKind::FuncCall) {
                 if let Some(name) = func_call_name(&func) {
-                    if name.starts_with(prefix) && name.ends_with(".with") {
+                    if name.starts_with(prefix)
+                        && name.ends_with(".with")
+                        && !is_layout_function(&name)
+                    {
                         return Some((node, name));
                     }
                 }
@@ -21,6 +97,15 @@ pub fn find_show_rule_with_prefix(root: &SyntaxNode, prefix: &str) -> Option<(Sy
     None
 }
 
+/// Built-in functions a document is wrapped in for its layout, as in
+/// `#show: columns.with(2)`, which are no templates.
+fn is_layout_function(name: &str) -> bool {
+    matches!(
+        name.trim_end_matches(".with"),
+        "columns" | "align" | "pad" | "block" | "box" | "place"
+    )
+}
+
 pub fn collect_let_bindings(root: &SyntaxNode) -> HashMap<String, SyntaxNode> {
     let mut map = HashMap::new();
     let mut stack = vec![root.clone()];
@@ -1,13 +1,14 @@
-use tylax_latex_backend::{render_document, LatexRenderOptions};
+use tylax_latex_backend::{push_content_packages, render_document, LatexRenderOptions};
 use tylax_typst_frontend::typst_to_ir;
 use typst_syntax::{parse, SyntaxKind, SyntaxNode};
 
+use crate::ir_pipeline::LatexOutputOptions;
 use crate::preamble_hints::{
-    equation_number_within, equation_numbering_enabled, extract_preamble_hints,
-    parse_length_to_pt, render_amsthm_definitions,
+    equation_number_within, extract_preamble_hints, render_encoding_packages,
 };
 use crate::template_adapters::common::{
     extract_bibliography_path, extract_named_args, find_show_rule_with_prefix,
+    natbib_render_options,
 };
 
 #[derive(Debug, Clone)]
@@ -29,17 +30,13 @@ struct ElsevierMetadata {
     bibliography: Option<String>,
 }
 
-pub fn maybe_convert_elsevier(input: &str) -> Option<String> {
+pub fn maybe_convert_elsevier(input: &str, output: &LatexOutputOptions) -> Option<String> {
     let root = parse(input);
     // Match both "elsevier" and "elsearticle" (the @preview package name)
     let (show, _name) = find_show_rule_with_prefix(&root, "elsevier")
         .or_else(|| find_show_rule_with_prefix(&root, "elsearticle"))?;
     let args = extract_named_args(&show);
     let hints = extract_preamble_hints(input);
-    let base_font_size_pt = hints
-        .text_size
-        .as_deref()
-        .and_then(|size| parse_length_to_pt(size, "10pt"));
 
     let meta = extract_metadata(&args);
 
@@ -63,36 +60,25 @@ pub fn maybe_convert_elsevier(input: &str) -> Option<String> {
             Some("cite".to_string())
         }
     });
-    let body = render_document(
-        &doc,
-        LatexRenderOptions {
-            full_document: false,
-            number_equations: equation_numbering_enabled(&hints),
-            two_column,
-            inline_wide_tables: false,
-            force_here: false,
-            table_grid: false,
-            table_style: tylax_latex_backend::TableStyle::Booktabs,
-            table_caption_position: tylax_latex_backend::TableCaptionPosition::Top,
-            bibliography_style_default: Some("elsarticle-num".to_string()),
-            cite_command,
-            base_font_size_pt,
-            heading_numbering_none: hints.heading_numbering_none,
-        },
-    );
+    let options = LatexRenderOptions {
+        two_column,
+        table_style: tylax_latex_backend::TableStyle::Booktabs,
+        table_caption_position: tylax_latex_backend::TableCaptionPosition::Top,
+        bibliography_style_default: Some("elsarticle-num".to_string()),
+        cite_command,
+        ..natbib_render_options(&hints, &doc.meta, output)
+    };
+    let body = render_document(&doc, options.clone());
 
     let mut out = String::new();
     out.push_str(&format!("\\documentclass[{}]{{elsarticle}}\n", class_opts));
+    out.push_str(&render_encoding_packages(&hints, options.encoding_mode));
     out.push_str("\\usepackage{graphicx}\n");
     out.push_str("\\usepackage{amsmath,amssymb}\n");
     out.push_str("\\usepackage[table]{xcolor}\n");
     out.push_str("\\usepackage{booktabs}\n");
     out.push_str("\\usepackage{lineno}\n");
     out.push_str("\\usepackage{hyperref}\n");
-    if hints.uses_amsthm {
-        out.push_str("\\usepackage{amsthm}\n");
-        out.push_str(&render_amsthm_definitions(&hints));
-    }
     if let Some(within) = equation_number_within(&hints) {
         out.push_str(&format!("\\numberwithin{{equation}}{{{}}}\n", within));
     }
@@ -110,6 +96,7 @@ pub fn maybe_convert_elsevier(input: &str) -> Option<String> {
         out.push_str(&format!("\\journal{{{}}}\n", escape_latex(journal)));
     }
 
+    push_content_packages(&mut out, &doc, &options);
     out.push_str("\\begin{document}\n");
     out.push_str("\\begin{frontmatter}\n");
 
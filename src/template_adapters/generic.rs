@@ -1,17 +1,15 @@
-use tylax_latex_backend::{render_document, LatexRenderOptions};
+use tylax_latex_backend::{push_content_packages, render_document, LatexRenderOptions};
 use tylax_typst_frontend::typst_to_ir;
 use typst_syntax::{parse, SyntaxKind};
 
-use crate::preamble_hints::{
-    equation_numbering_enabled, extract_preamble_hints, is_two_column, parse_length_to_pt,
-    render_article_preamble,
-};
+use crate::ir_pipeline::LatexOutputOptions;
+use crate::preamble_hints::{extract_preamble_hints, render_article_preamble};
 use crate::template_adapters::common::{
     collect_let_bindings, escape_latex, extract_array_strings, extract_named_args,
-    extract_string_like, find_show_rule_with_prefix,
+    extract_string_like, find_show_rule_with_prefix, render_options,
 };
 
-pub fn maybe_convert_template_with(input: &str) -> Option<String> {
+pub fn maybe_convert_template_with(input: &str, output: &LatexOutputOptions) -> Option<String> {
     let root = parse(input);
     let (show_rule, _name) = find_show_rule_with_prefix(&root, "")?;
     let lets = collect_let_bindings(&root);
@@ -54,35 +52,12 @@ pub fn maybe_convert_template_with(input: &str) -> Option<String> {
 
     let doc = typst_to_ir(input);
     let hints = extract_preamble_hints(input);
-    let base_font_size_pt = hints
-        .text_size
-        .as_deref()
-        .and_then(|size| parse_length_to_pt(size, "10pt"));
-    let cite_command = hints.cite_command.clone().or_else(|| {
-        if hints.uses_natbib {
-            Some("citep".to_string())
-        } else {
-            None
-        }
-    });
-    let body = render_document(
-        &doc,
-        LatexRenderOptions {
-            full_document: false,
-            number_equations: equation_numbering_enabled(&hints),
-            two_column: is_two_column(&hints),
-            inline_wide_tables: false,
-            force_here: true,
-            table_grid: false,
-            table_style: tylax_latex_backend::TableStyle::Plain,
-            table_caption_position: tylax_latex_backend::TableCaptionPosition::Bottom,
-            bibliography_style_default: hints.bibliography_style.clone(),
-            cite_command,
-            base_font_size_pt,
-            heading_numbering_none: hints.heading_numbering_none,
-        },
-    );
-    let preamble = render_article_preamble(&hints);
+    let options = LatexRenderOptions {
+        force_here: true,
+        ..render_options(&hints, &doc.meta, output)
+    };
+    let body = render_document(&doc, options.clone());
+    let preamble = render_article_preamble(&hints, &doc.meta, &options);
 
     let mut out = String::new();
     out.push_str(&preamble);
@@ -111,6 +86,7 @@ pub fn maybe_convert_template_with(input: &str) -> Option<String> {
         }
     }
 
+    push_content_packages(&mut out, &doc, &options);
     out.push_str("\\begin{document}\n\n");
 
     if has_title || has_author || has_date || has_subtitle {
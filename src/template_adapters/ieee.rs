@@ -1,11 +1,14 @@
-use tylax_latex_backend::{render_document, LatexRenderOptions};
+use tylax_latex_backend::{
+    push_content_packages, render_document, CitationPackage, LatexRenderOptions,
+};
 use tylax_typst_frontend::typst_to_ir;
 use typst_syntax::{parse, SyntaxKind, SyntaxNode};
 
+use crate::ir_pipeline::LatexOutputOptions;
 use crate::preamble_hints::{
-    equation_number_within, equation_numbering_enabled, extract_preamble_hints,
-    parse_length_to_pt, render_amsthm_definitions,
+    equation_number_within, extract_preamble_hints, render_encoding_packages,
 };
+use crate::template_adapters::common::render_options;
 
 #[derive(Debug, Clone)]
 struct IeeeAuthor {
@@ -25,61 +28,36 @@ struct IeeeMetadata {
     bibliography: Option<String>,
 }
 
-pub fn maybe_convert_ieee(input: &str) -> Option<String> {
+pub fn maybe_convert_ieee(input: &str, output: &LatexOutputOptions) -> Option<String> {
     let root = parse(input);
     let show = find_ieee_show_rule(&root)?;
     let meta = extract_metadata(&show);
     let hints = extract_preamble_hints(input);
-    let base_font_size_pt = hints
-        .text_size
-        .as_deref()
-        .and_then(|size| parse_length_to_pt(size, "10pt"));
 
     // Convert body using IR pipeline (show/let/set are ignored by preprocessor).
     let doc = typst_to_ir(input);
-    let cite_command = hints.cite_command.clone().or_else(|| {
-        if hints.uses_natbib {
-            Some("citep".to_string())
-        } else {
-            None
-        }
-    });
-    let body = render_document(
-        &doc,
-        LatexRenderOptions {
-            full_document: false,
-            number_equations: equation_numbering_enabled(&hints),
-            // IEEE conference papers are always two-column
-            two_column: true,
-            inline_wide_tables: false,
-            force_here: false,
-            table_grid: false,
-            table_style: tylax_latex_backend::TableStyle::Plain,
-            table_caption_position: tylax_latex_backend::TableCaptionPosition::Top,
-            bibliography_style_default: hints.bibliography_style.clone(),
-            cite_command,
-            base_font_size_pt,
-            heading_numbering_none: hints.heading_numbering_none,
-        },
-    );
+    let options = LatexRenderOptions {
+        // IEEE conference papers are always two-column
+        two_column: true,
+        table_caption_position: tylax_latex_backend::TableCaptionPosition::Top,
+        ..render_options(&hints, &doc.meta, output)
+    };
+    let body = render_document(&doc, options.clone());
 
     let mut out = String::new();
     out.push_str("\\documentclass[conference]{IEEEtran}\n");
-    if hints.uses_natbib {
-        out.push_str("\\usepackage{natbib}\n");
-    } else {
-        out.push_str("\\usepackage{cite}\n");
+    out.push_str(&render_encoding_packages(&hints, options.encoding_mode));
+    // biblatex, which the content packages load, goes with neither.
+    match options.citation_package {
+        CitationPackage::Natbib if hints.uses_natbib => out.push_str("\\usepackage{natbib}\n"),
+        CitationPackage::Natbib => out.push_str("\\usepackage{cite}\n"),
+        _ => {}
     }
     out.push_str("\\usepackage{amsmath,amssymb,amsfonts}\n");
-    out.push_str("\\usepackage{algorithmic}\n");
     out.push_str("\\usepackage{graphicx}\n");
     out.push_str("\\usepackage{textcomp}\n");
     out.push_str("\\usepackage[table]{xcolor}\n");
     out.push_str("\\usepackage{booktabs}\n");
-    if hints.uses_amsthm {
-        out.push_str("\\usepackage{amsthm}\n");
-        out.push_str(&render_amsthm_definitions(&hints));
-    }
     if let Some(within) = equation_number_within(&hints) {
         out.push_str(&format!("\\numberwithin{{equation}}{{{}}}\n", within));
     }
@@ -99,6 +77,7 @@ pub fn maybe_convert_ieee(input: &str) -> Option<String> {
         out.push_str(&render_ieee_authors(&meta.authors));
         out.push_str("}\n");
     }
+    push_content_packages(&mut out, &doc, &options);
     out.push_str("\\begin{document}\n");
     if meta.title.is_some() || !meta.authors.is_empty() {
         out.push_str("\\maketitle\n");
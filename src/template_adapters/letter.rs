@@ -1,11 +1,14 @@
-use tylax_latex_backend::{render_document, LatexRenderOptions};
+use tylax_latex_backend::{
+    push_content_packages, render_document, CitationPackage, LatexRenderOptions,
+};
 use tylax_typst_frontend::typst_to_ir;
 use typst_syntax::{parse, SyntaxKind, SyntaxNode};
 
+use crate::ir_pipeline::LatexOutputOptions;
 use crate::preamble_hints::{
-    equation_number_within, equation_numbering_enabled, extract_preamble_hints, is_two_column,
-    parse_length_to_pt, render_amsthm_definitions,
+    equation_number_within, extract_preamble_hints, render_encoding_packages,
 };
+use crate::template_adapters::common::render_options;
 
 #[derive(Debug, Default)]
 struct LetterMeta {
@@ -16,57 +19,32 @@ struct LetterMeta {
     name: Option<String>,
 }
 
-pub fn maybe_convert_letter(input: &str) -> Option<String> {
+pub fn maybe_convert_letter(input: &str, output: &LatexOutputOptions) -> Option<String> {
     let root = parse(input);
     let show = find_show_with(&root, "letter.with")?;
     let meta = extract_meta(&show);
 
     let doc = typst_to_ir(input);
     let hints = extract_preamble_hints(input);
-    let base_font_size_pt = hints
-        .text_size
-        .as_deref()
-        .and_then(|size| parse_length_to_pt(size, "10pt"));
-    let cite_command = hints.cite_command.clone().or_else(|| {
-        if hints.uses_natbib {
-            Some("citep".to_string())
-        } else {
-            None
-        }
-    });
-    let body = render_document(
-        &doc,
-        LatexRenderOptions {
-            full_document: false,
-            number_equations: equation_numbering_enabled(&hints),
-            two_column: is_two_column(&hints),
-            inline_wide_tables: false,
-            force_here: true,
-            table_grid: false,
-            table_style: tylax_latex_backend::TableStyle::Plain,
-            table_caption_position: tylax_latex_backend::TableCaptionPosition::Bottom,
-            bibliography_style_default: hints.bibliography_style.clone(),
-            cite_command,
-            base_font_size_pt,
-            heading_numbering_none: hints.heading_numbering_none,
-        },
-    );
+    let options = LatexRenderOptions {
+        force_here: true,
+        ..render_options(&hints, &doc.meta, output)
+    };
+    let body = render_document(&doc, options.clone());
 
     let mut out = String::new();
     out.push_str("\\documentclass{letter}\n");
+    out.push_str(&render_encoding_packages(&hints, options.encoding_mode));
     out.push_str("\\usepackage{graphicx}\n");
     out.push_str("\\usepackage{float}\n");
     out.push_str("\\usepackage{hyperref}\n");
-    if hints.uses_amsthm {
-        out.push_str("\\usepackage{amsthm}\n");
-        out.push_str(&render_amsthm_definitions(&hints));
-    }
-    if hints.uses_natbib {
+    if hints.uses_natbib && options.citation_package == CitationPackage::Natbib {
         out.push_str("\\usepackage{natbib}\n");
     }
     if let Some(within) = equation_number_within(&hints) {
         out.push_str(&format!("\\numberwithin{{equation}}{{{}}}\n", within));
     }
+    push_content_packages(&mut out, &doc, &options);
     out.push_str("\\begin{document}\n");
     if let Some(sender) = meta.sender.as_deref() {
         out.push_str("\\address{");
@@ -1,13 +1,14 @@
-use tylax_latex_backend::{render_document, LatexRenderOptions};
+use tylax_latex_backend::{push_content_packages, render_document, LatexRenderOptions};
 use tylax_typst_frontend::typst_to_ir;
 use typst_syntax::{parse, SyntaxKind, SyntaxNode};
 
+use crate::ir_pipeline::LatexOutputOptions;
 use crate::preamble_hints::{
-    equation_number_within, equation_numbering_enabled, extract_preamble_hints,
-    parse_length_to_pt, render_amsthm_definitions,
+    equation_number_within, extract_preamble_hints, render_encoding_packages,
 };
 use crate::template_adapters::common::{
-    collect_let_bindings, extract_bibliography_path, extract_named_args, find_show_rule_with_prefix,
+    collect_let_bindings, extract_bibliography_path, extract_named_args,
+    find_show_rule_with_prefix, render_options,
 };
 
 #[derive(Debug, Clone)]
@@ -36,57 +37,33 @@ struct LncsMetadata {
     bibliography: Option<String>,
 }
 
-pub fn maybe_convert_lncs(input: &str) -> Option<String> {
+pub fn maybe_convert_lncs(input: &str, output: &LatexOutputOptions) -> Option<String> {
     let root = parse(input);
     let (show, _name) = find_show_rule_with_prefix(&root, "lncs")?;
     let args = extract_named_args(&show);
     let lets = collect_let_bindings(&root);
     let hints = extract_preamble_hints(input);
-    let base_font_size_pt = hints
-        .text_size
-        .as_deref()
-        .and_then(|size| parse_length_to_pt(size, "10pt"));
 
     let meta = extract_metadata(&args, &lets);
 
     // Convert body using IR pipeline
     let doc = typst_to_ir(input);
-    let cite_command = hints.cite_command.clone().or_else(|| {
-        if hints.uses_natbib {
-            Some("citep".to_string())
-        } else {
-            None
-        }
-    });
-    let body = render_document(
-        &doc,
-        LatexRenderOptions {
-            full_document: false,
-            number_equations: equation_numbering_enabled(&hints),
-            two_column: false,
-            inline_wide_tables: false,
-            force_here: false,
-            table_grid: false,
-            table_style: tylax_latex_backend::TableStyle::Booktabs,
-            table_caption_position: tylax_latex_backend::TableCaptionPosition::Top,
-            bibliography_style_default: hints.bibliography_style.clone(),
-            cite_command,
-            base_font_size_pt,
-            heading_numbering_none: hints.heading_numbering_none,
-        },
-    );
+    let options = LatexRenderOptions {
+        two_column: false,
+        table_style: tylax_latex_backend::TableStyle::Booktabs,
+        table_caption_position: tylax_latex_backend::TableCaptionPosition::Top,
+        ..render_options(&hints, &doc.meta, output)
+    };
+    let body = render_document(&doc, options.clone());
 
     let mut out = String::new();
     out.push_str("\\documentclass[runningheads]{llncs}\n");
+    out.push_str(&render_encoding_packages(&hints, options.encoding_mode));
     out.push_str("\\usepackage{graphicx}\n");
     out.push_str("\\usepackage{amsmath,amssymb}\n");
     out.push_str("\\usepackage[table]{xcolor}\n");
     out.push_str("\\usepackage{booktabs}\n");
     out.push_str("\\usepackage{hyperref}\n");
-    if hints.uses_amsthm {
-        out.push_str("\\usepackage{amsthm}\n");
-        out.push_str(&render_amsthm_definitions(&hints));
-    }
     if let Some(within) = equation_number_within(&hints) {
         out.push_str(&format!("\\numberwithin{{equation}}{{{}}}\n", within));
     }
@@ -99,6 +76,7 @@ pub fn maybe_convert_lncs(input: &str) -> Option<String> {
         ));
     }
 
+    push_content_packages(&mut out, &doc, &options);
     out.push_str("\\begin{document}\n");
 
     // Title
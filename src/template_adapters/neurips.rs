@@ -1,18 +1,18 @@
-use tylax_latex_backend::{render_document, LatexRenderOptions};
+use tylax_latex_backend::{push_content_packages, render_document, LatexRenderOptions};
 use tylax_typst_frontend::typst_to_ir;
 use typst_syntax::parse;
 
+use crate::ir_pipeline::LatexOutputOptions;
 use crate::preamble_hints::{
-    equation_number_within, equation_numbering_enabled, extract_preamble_hints, parse_length_to_pt,
-    render_amsthm_definitions,
+    equation_number_within, extract_preamble_hints, render_encoding_packages,
 };
 use crate::template_adapters::common::{
     escape_latex, extract_bibliography_path, extract_named_args, extract_option_bool,
-    extract_string_like, extract_year_from_name, find_show_rule_with_prefix,
+    extract_string_like, extract_year_from_name, find_show_rule_with_prefix, natbib_render_options,
     parse_authors_with_affls, render_authors_simple,
 };
 
-pub fn maybe_convert_neurips(input: &str) -> Option<String> {
+pub fn maybe_convert_neurips(input: &str, output: &LatexOutputOptions) -> Option<String> {
     let root = parse(input);
     let (show, name) = find_show_rule_with_prefix(&root, "neurips")?;
     let args = extract_named_args(&show);
@@ -40,39 +40,19 @@ pub fn maybe_convert_neurips(input: &str) -> Option<String> {
     let style_pkg = format!("neurips_{}", year);
 
     let hints = extract_preamble_hints(input);
-    let base_font_size_pt = hints
-        .text_size
-        .as_deref()
-        .and_then(|size| parse_length_to_pt(size, "10pt"));
-    let cite_command = hints.cite_command.clone().or_else(|| {
-        if hints.uses_natbib {
-            Some("citep".to_string())
-        } else {
-            None
-        }
-    });
 
     let doc = typst_to_ir(input);
-    let body = render_document(
-        &doc,
-        LatexRenderOptions {
-            full_document: false,
-            number_equations: equation_numbering_enabled(&hints),
-            two_column: true,
-            inline_wide_tables: false,
-            force_here: false,
-            table_grid: false,
-            table_style: tylax_latex_backend::TableStyle::Plain,
-            table_caption_position: tylax_latex_backend::TableCaptionPosition::Top,
-            bibliography_style_default: Some("plainnat".to_string()),
-            cite_command,
-            base_font_size_pt,
-            heading_numbering_none: hints.heading_numbering_none,
-        },
-    );
+    let options = LatexRenderOptions {
+        two_column: true,
+        table_caption_position: tylax_latex_backend::TableCaptionPosition::Top,
+        bibliography_style_default: Some("plainnat".to_string()),
+        ..natbib_render_options(&hints, &doc.meta, output)
+    };
+    let body = render_document(&doc, options.clone());
 
     let mut out = String::new();
     out.push_str("\\documentclass{article}\n");
+    out.push_str(&render_encoding_packages(&hints, options.encoding_mode));
     let pkg_line = match accepted {
         Some(true) => format!("\\usepackage[final]{{{}}}", style_pkg),
         None => format!("\\usepackage[preprint]{{{}}}", style_pkg),
@@ -94,10 +74,6 @@ pub fn maybe_convert_neurips(input: &str) -> Option<String> {
     out.push_str("\\def\\And{\\\\}\n");
     out.push_str("\\fi\n");
 
-    if hints.uses_amsthm {
-        out.push_str("\\usepackage{amsthm}\n");
-        out.push_str(&render_amsthm_definitions(&hints));
-    }
     if let Some(within) = equation_number_within(&hints) {
         out.push_str(&format!("\\numberwithin{{equation}}{{{}}}\n", within));
     }
@@ -117,6 +93,7 @@ pub fn maybe_convert_neurips(input: &str) -> Option<String> {
         out.push_str(&render_authors_simple(&authors, &affls, "\\And"));
     }
 
+    push_content_packages(&mut out, &doc, &options);
     out.push_str("\\begin{document}\n");
     if title.is_some() || !authors.is_empty() {
         out.push_str("\\maketitle\n");
@@ -0,0 +1,327 @@
+//! Copy the images of a converted document next to its output. Typst reads
+//! image paths relative to the document, LaTeX relative to the directory it
+//! runs in, so the paths of a `.tex` written elsewhere no longer resolve.
+//! The images are copied or linked into the output directory and the
+//! document is pointed at them. SVG and GIF, which pdflatex cannot read,
+//! are converted on the way when tylax is built with the `svg` and `gif`
+//! features: SVG to PDF or PNG, GIF to a PNG of its first frame. An image
+//! that cannot be converted is copied as it is and noted as a loss.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tylax_ir::{images_mut, Document, Loss};
+
+/// How an image reaches the output directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AssetMode {
+    #[default]
+    Copy,
+    /// A symbolic link to the original; a copy where links are not
+    /// supported.
+    Link,
+}
+
+/// What an SVG image is converted to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SvgTarget {
+    #[default]
+    Pdf,
+    Png,
+}
+
+impl SvgTarget {
+    fn extension(&self) -> &'static str {
+        match self {
+            SvgTarget::Pdf => "pdf",
+            SvgTarget::Png => "png",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetOptions {
+    /// The directory of the Typst document, which its image paths are
+    /// relative to. Paths starting with `/` are relative to it as well.
+    pub source_dir: PathBuf,
+    /// The directory the LaTeX output is written to.
+    pub out_dir: PathBuf,
+    pub mode: AssetMode,
+    pub svg: SvgTarget,
+}
+
+impl AssetOptions {
+    pub fn new(source_dir: impl Into<PathBuf>, out_dir: impl Into<PathBuf>) -> Self {
+        AssetOptions {
+            source_dir: source_dir.into(),
+            out_dir: out_dir.into(),
+            mode: AssetMode::default(),
+            svg: SvgTarget::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AssetReport {
+    /// Files written to the output directory, relative to it.
+    pub written: Vec<PathBuf>,
+    /// Image paths, as the document gives them, that name no file.
+    pub missing: Vec<String>,
+    /// Images left as they were, with the reason.
+    pub warnings: Vec<String>,
+}
+
+/// Copy or link the images of `doc` into the output directory and rewrite
+/// their paths to the copies. An image keeps its path relative to the
+/// document where that stays inside the output directory; one from outside
+/// the document's directory goes to `assets/`.
+pub fn collect_assets(doc: &mut Document, options: &AssetOptions) -> AssetReport {
+    let mut report = AssetReport::default();
+    // Where each file went, so an image shown twice is written once.
+    let mut placed: HashMap<PathBuf, String> = HashMap::new();
+    let mut taken: HashSet<PathBuf> = HashSet::new();
+    let mut losses = Vec::new();
+    for image in images_mut(doc) {
+        if image.path.contains("://") {
+            continue;
+        }
+        let source = options.source_dir.join(image.path.trim_start_matches('/'));
+        if let Some(path) = placed.get(&source) {
+            image.path = path.clone();
+            continue;
+        }
+        if !source.is_file() {
+            report.missing.push(image.path.clone());
+            continue;
+        }
+        let mut target = target_path(&image.path, &mut taken);
+        // The path the output includes, when not that of the file written.
+        let mut included = None;
+        let format = target
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .filter(|ext| ext == "svg" || ext == "gif");
+        let converted = format
+            .as_deref()
+            .map(|format| convert_image(&source, format, options.svg));
+        let result = match converted {
+            Some(Ok((bytes, extension))) => {
+                target.set_extension(extension);
+                write_file(&options.out_dir.join(&target), &bytes)
+            }
+            Some(Err(reason)) => {
+                // The original is copied to be converted by hand; the backend
+                // sets SVG as PDF and GIF as PNG, which pdflatex reads.
+                let fallback = if format.as_deref() == Some("svg") {
+                    "pdf"
+                } else {
+                    "png"
+                };
+                let path = slash_path(&target.with_extension(fallback));
+                let message = format!(
+                    "{} could not be converted ({}); the output includes {}",
+                    image.path, reason, path
+                );
+                included = Some(path);
+                report.warnings.push(message.clone());
+                losses.push(Loss::new("image", message).with_suggestion(
+                    "convert the image by hand, or build tylax with the `svg` and `gif` features",
+                ));
+                place_file(&source, &options.out_dir.join(&target), options.mode)
+            }
+            None => place_file(&source, &options.out_dir.join(&target), options.mode),
+        };
+        match result {
+            Ok(true) => report.written.push(target.clone()),
+            Ok(false) => {}
+            Err(err) => {
+                report.warnings.push(format!("{}: {}", image.path, err));
+                continue;
+            }
+        }
+        let path = included.unwrap_or_else(|| slash_path(&target));
+        placed.insert(source, path.clone());
+        image.path = path;
+    }
+    doc.losses.extend(losses);
+    report
+}
+
+/// The image at `source`, of `format` `svg` or `gif`, converted to a format
+/// pdflatex reads, with the extension of that format.
+fn convert_image(
+    source: &Path,
+    format: &str,
+    svg: SvgTarget,
+) -> Result<(Vec<u8>, &'static str), String> {
+    let bytes = fs::read(source).map_err(|err| err.to_string())?;
+    match format {
+        "svg" => convert_svg(&bytes, svg).map(|out| (out, svg.extension())),
+        _ => convert_gif(&bytes).map(|out| (out, "png")),
+    }
+}
+
+/// Where an image at `path` in the document goes, relative to the output
+/// directory, without reusing a path in `taken`.
+fn target_path(path: &str, taken: &mut HashSet<PathBuf>) -> PathBuf {
+    let mut parts: Vec<&str> = Vec::new();
+    let mut escapes = false;
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => escapes |= parts.pop().is_none(),
+            _ => parts.push(part),
+        }
+    }
+    let mut target: PathBuf = if escapes {
+        ["assets", parts.last().copied().unwrap_or("image")]
+            .iter()
+            .collect()
+    } else {
+        parts.iter().collect()
+    };
+    if taken.contains(&target) {
+        let stem = target
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let ext = target
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+        let base = target.clone();
+        target = (2..)
+            .map(|n| base.with_file_name(format!("{}-{}{}", stem, n, ext)))
+            .find(|candidate| !taken.contains(candidate))
+            .unwrap_or(base);
+    }
+    taken.insert(target.clone());
+    target
+}
+
+/// Put `source` at `dest`, and tell whether anything was written: nothing
+/// is when `dest` already is `source`.
+fn place_file(source: &Path, dest: &Path, mode: AssetMode) -> io::Result<bool> {
+    if let (Ok(a), Ok(b)) = (fs::canonicalize(source), fs::canonicalize(dest)) {
+        if a == b {
+            return Ok(false);
+        }
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::symlink_metadata(dest).is_ok() {
+        fs::remove_file(dest)?;
+    }
+    match mode {
+        #[cfg(unix)]
+        AssetMode::Link => std::os::unix::fs::symlink(fs::canonicalize(source)?, dest)?,
+        _ => {
+            fs::copy(source, dest)?;
+        }
+    }
+    Ok(true)
+}
+
+fn write_file(dest: &Path, bytes: &[u8]) -> io::Result<bool> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::symlink_metadata(dest).is_ok() {
+        fs::remove_file(dest)?;
+    }
+    fs::write(dest, bytes)?;
+    Ok(true)
+}
+
+fn slash_path(path: &Path) -> String {
+    path.iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The resolution SVG images are rendered at as PNG.
+#[cfg(feature = "svg")]
+const PNG_DPI: f32 = 300.0;
+
+#[cfg(feature = "svg")]
+fn convert_svg(svg: &[u8], to: SvgTarget) -> Result<Vec<u8>, String> {
+    use resvg::{tiny_skia, usvg};
+
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_data(svg, &options).map_err(|err| err.to_string())?;
+    match to {
+        SvgTarget::Pdf => svg2pdf::to_pdf(
+            &tree,
+            svg2pdf::ConversionOptions::default(),
+            svg2pdf::PageOptions::default(),
+        )
+        .map_err(|err| format!("{:?}", err)),
+        SvgTarget::Png => {
+            // SVG sizes are in CSS pixels, 96 to the inch.
+            let scale = PNG_DPI / 96.0;
+            let size = tree.size().to_int_size().scale_by(scale);
+            let mut pixmap = size
+                .and_then(|size| tiny_skia::Pixmap::new(size.width(), size.height()))
+                .ok_or_else(|| "the SVG has no area".to_string())?;
+            let transform = tiny_skia::Transform::from_scale(scale, scale);
+            resvg::render(&tree, transform, &mut pixmap.as_mut());
+            pixmap.encode_png().map_err(|err| format!("{:?}", err))
+        }
+    }
+}
+
+#[cfg(not(feature = "svg"))]
+fn convert_svg(_svg: &[u8], _to: SvgTarget) -> Result<Vec<u8>, String> {
+    Err("tylax was built without the `svg` feature".to_string())
+}
+
+/// The first frame of a GIF as a PNG.
+#[cfg(feature = "gif")]
+fn convert_gif(gif: &[u8]) -> Result<Vec<u8>, String> {
+    let frame = image::load_from_memory_with_format(gif, image::ImageFormat::Gif)
+        .map_err(|err| err.to_string())?;
+    let mut png = io::Cursor::new(Vec::new());
+    frame
+        .write_to(&mut png, image::ImageFormat::Png)
+        .map_err(|err| err.to_string())?;
+    Ok(png.into_inner())
+}
+
+#[cfg(not(feature = "gif"))]
+fn convert_gif(_gif: &[u8]) -> Result<Vec<u8>, String> {
+    Err("tylax was built without the `gif` feature".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets(paths: &[&str]) -> Vec<String> {
+        let mut taken = HashSet::new();
+        paths
+            .iter()
+            .map(|path| slash_path(&target_path(path, &mut taken)))
+            .collect()
+    }
+
+    #[test]
+    fn target_path_keeps_paths_inside_the_document_directory() {
+        assert_eq!(
+            targets(&["figs/plot.png", "/logo.png", "./figs/../a.jpg"]),
+            ["figs/plot.png", "logo.png", "a.jpg"]
+        );
+    }
+
+    #[test]
+    fn target_path_moves_outside_paths_to_assets() {
+        assert_eq!(
+            targets(&["../shared/logo.png", "../other/logo.png", "assets/logo.png"]),
+            ["assets/logo.png", "assets/logo-2.png", "assets/logo-3.png"]
+        );
+    }
+}
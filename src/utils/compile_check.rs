@@ -0,0 +1,474 @@
+//! Compile generated LaTeX with a local TeX engine, or Typst with the
+//! `typst` CLI, and collect its errors.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long a compiler may run before it is stopped.
+pub const COMPILE_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TexEngine {
+    Latexmk,
+    Tectonic,
+}
+
+impl TexEngine {
+    /// First supported engine found on `PATH`, preferring latexmk.
+    pub fn detect() -> Option<Self> {
+        [TexEngine::Latexmk, TexEngine::Tectonic]
+            .into_iter()
+            .find(|engine| find_executable(engine.name()).is_some())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TexEngine::Latexmk => "latexmk",
+            TexEngine::Tectonic => "tectonic",
+        }
+    }
+}
+
+/// An error reported by the TeX engine. `line` is 1-based in the compiled
+/// file and missing when the error points into another file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TexError {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompileOutcome {
+    pub success: bool,
+    pub errors: Vec<TexError>,
+}
+
+const MAIN_FILE: &str = "main.tex";
+
+/// Compile `source` in a scratch directory. `search_dir` is added to the TeX
+/// search path so relative `\includegraphics` and `\bibliography` paths
+/// resolve against the original document.
+pub fn compile_latex(
+    source: &str,
+    engine: TexEngine,
+    search_dir: Option<&Path>,
+) -> io::Result<CompileOutcome> {
+    let dir = scratch_dir()?;
+    fs::write(dir.join(MAIN_FILE), source)?;
+
+    let mut command = Command::new(engine.name());
+    match engine {
+        TexEngine::Latexmk => {
+            command.args([
+                "-pdf",
+                "-interaction=nonstopmode",
+                "-halt-on-error",
+                "-file-line-error",
+                MAIN_FILE,
+            ]);
+            if let Some(search_dir) = search_dir {
+                // The trailing separator keeps kpathsea's default search path.
+                let separator = if cfg!(windows) { ";" } else { ":" };
+                command.env(
+                    "TEXINPUTS",
+                    format!("{}{}", search_dir.display(), separator),
+                );
+            }
+        }
+        TexEngine::Tectonic => {
+            command.args(["--keep-logs", "--chatter", "minimal"]);
+            if let Some(search_dir) = search_dir {
+                command
+                    .arg("-Z")
+                    .arg(format!("search-path={}", search_dir.display()));
+            }
+            command.arg(MAIN_FILE);
+        }
+    }
+    command.current_dir(&dir);
+
+    let result = output_with_timeout(&mut command, COMPILE_TIMEOUT).map(|output| {
+        let log = fs::read_to_string(dir.join("main.log")).unwrap_or_else(|_| {
+            format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            )
+        });
+        let mut errors = parse_tex_log(&log);
+        if !output.status.success() && errors.is_empty() {
+            errors.push(TexError {
+                line: None,
+                message: format!("{} exited with {}", engine.name(), output.status),
+            });
+        }
+        CompileOutcome {
+            success: output.status.success() && errors.is_empty(),
+            errors,
+        }
+    });
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+const TYPST_MAIN_FILE: &str = "main.typ";
+
+/// Whether the `typst` CLI is on `PATH`.
+pub fn typst_available() -> bool {
+    find_executable("typst").is_some()
+}
+
+/// Compile Typst `source` with the `typst` CLI, in a scratch directory.
+/// Typst reads files relative to the file it compiles and only within its
+/// root, so with a `search_dir` the root is a scratch directory the entries
+/// of `search_dir` are mirrored into, for relative `image` and `include`
+/// paths to resolve against the original document. Nothing is written to
+/// `search_dir`.
+pub fn compile_typst(source: &str, search_dir: Option<&Path>) -> io::Result<CompileOutcome> {
+    let dir = scratch_dir()?;
+    let root = dir.join("root");
+    // The mirrored document may have a `main.typ` of its own, but no file
+    // named after the scratch directory.
+    let main_file = match search_dir {
+        Some(_) => format!(
+            "{}.typ",
+            dir.file_name().unwrap_or_default().to_string_lossy()
+        ),
+        None => TYPST_MAIN_FILE.to_string(),
+    };
+    let compiled = fs::create_dir(&root)
+        .and_then(|()| match search_dir {
+            Some(search_dir) => mirror_dir(search_dir, &root),
+            None => Ok(()),
+        })
+        .and_then(|()| run_typst(source, &root, &main_file, &dir.join("main.pdf")));
+    let _ = fs::remove_dir_all(&dir);
+    compiled
+}
+
+/// Compile `source` as `main_file` in `root`, into `pdf`.
+fn run_typst(source: &str, root: &Path, main_file: &str, pdf: &Path) -> io::Result<CompileOutcome> {
+    let main = root.join(main_file);
+    fs::write(&main, source)?;
+    let mut command = Command::new("typst");
+    command
+        .args(["compile", "--diagnostic-format", "short", "--root"])
+        .arg(root)
+        .arg(&main)
+        .arg(pdf)
+        .current_dir(root);
+    output_with_timeout(&mut command, COMPILE_TIMEOUT).map(|output| {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let mut errors = parse_typst_diagnostics(&stderr, main_file);
+        if !output.status.success() && errors.is_empty() {
+            errors.push(TexError {
+                line: None,
+                message: format!("typst exited with {}", output.status),
+            });
+        }
+        CompileOutcome {
+            success: output.status.success() && errors.is_empty(),
+            errors,
+        }
+    })
+}
+
+/// Make each entry of `source` appear in `dest`: linked where symbolic
+/// links are available, copied elsewhere.
+fn mirror_dir(source: &Path, dest: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target = dest.join(entry.file_name());
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(fs::canonicalize(entry.path())?, &target)?;
+        #[cfg(not(unix))]
+        if entry.file_type()?.is_dir() {
+            fs::create_dir(&target)?;
+            mirror_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Extract errors from `typst compile --diagnostic-format short` output,
+/// whose lines read `main.typ:3:5: error: message`. Only errors in
+/// `main_file` keep their line.
+fn parse_typst_diagnostics(output: &str, main_file: &str) -> Vec<TexError> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (location, message) = line.split_once(": error: ")?;
+            let mut parts = location.rsplitn(3, ':');
+            let _column = parts.next();
+            let line_no = parts.next().and_then(|line_no| line_no.parse().ok());
+            let is_main = parts
+                .next()
+                .and_then(|file| Path::new(file).file_name())
+                .is_some_and(|name| name == main_file);
+            Some(TexError {
+                line: line_no.filter(|_| is_main),
+                message: message.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Extract errors from a TeX log. Understands both `-file-line-error`
+/// output (`./main.tex:12: message`) and the classic `! message` followed
+/// by an `l.12` context line.
+pub fn parse_tex_log(log: &str) -> Vec<TexError> {
+    let mut errors = Vec::new();
+    let mut pending: Option<String> = None;
+    for line in log.lines() {
+        let line = line.trim_end();
+        if let Some((file, line_no, message)) = split_file_line_error(line) {
+            let is_main = Path::new(file).file_name().and_then(|name| name.to_str())
+                == Some(MAIN_FILE);
+            errors.push(TexError {
+                line: is_main.then_some(line_no),
+                message: message.to_string(),
+            });
+            pending = None;
+            continue;
+        }
+        if let Some(message) = line.strip_prefix("! ") {
+            if let Some(previous) = pending.take() {
+                errors.push(TexError {
+                    line: None,
+                    message: previous,
+                });
+            }
+            pending = Some(message.trim().to_string());
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("l.") {
+            let digits: String = rest.chars().take_while(|ch| ch.is_ascii_digit()).collect();
+            if let (Some(message), Ok(line_no)) = (pending.as_ref(), digits.parse::<usize>()) {
+                errors.push(TexError {
+                    line: Some(line_no),
+                    message: message.clone(),
+                });
+                pending = None;
+            }
+        }
+    }
+    if let Some(message) = pending {
+        errors.push(TexError {
+            line: None,
+            message,
+        });
+    }
+    errors
+}
+
+fn split_file_line_error(line: &str) -> Option<(&str, usize, &str)> {
+    let line = line.strip_prefix("error: ").unwrap_or(line);
+    let mut search = 0;
+    while let Some(pos) = line[search..].find(':') {
+        let colon = search + pos;
+        let file = &line[..colon];
+        if file.is_empty() || file.contains(char::is_whitespace) {
+            return None;
+        }
+        let rest = &line[colon + 1..];
+        if let Some((line_no, message)) = rest.split_once(':') {
+            if let Ok(line_no) = line_no.parse() {
+                return Some((file, line_no, message.trim()));
+            }
+        }
+        search = colon + 1;
+    }
+    None
+}
+
+/// Byte offset where 1-based `line` starts in `text`.
+pub fn line_start_offset(text: &str, line: usize) -> Option<usize> {
+    if line == 0 {
+        return None;
+    }
+    if line == 1 {
+        return Some(0);
+    }
+    text.match_indices('\n')
+        .nth(line - 2)
+        .map(|(idx, _)| idx + 1)
+        .filter(|offset| *offset <= text.len())
+}
+
+/// 1-based line and column of a byte offset in `text`.
+pub fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(text.len());
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+fn scratch_dir() -> io::Result<PathBuf> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    let dir = std::env::temp_dir().join(format!(
+        "tylax-compile-{}-{}",
+        std::process::id(),
+        nanos
+    ));
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn find_executable(name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths).find_map(|dir| {
+        let candidate = dir.join(name);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if cfg!(windows) {
+            let candidate = dir.join(format!("{}.exe", name));
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+        None
+    })
+}
+
+/// Run `command` to completion, killing it once `timeout` has passed.
+/// The command runs in a process group of its own, which is killed whole,
+/// so the engines latexmk starts stop with it.
+pub(crate) fn output_with_timeout(command: &mut Command, timeout: Duration) -> io::Result<Output> {
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(command, 0);
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Drain both pipes while waiting, so a chatty compiler never blocks on a
+    // full one.
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            kill_process_group(&mut child);
+            let _ = child.wait();
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("stopped after {} seconds", timeout.as_secs()),
+            ));
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+#[cfg(unix)]
+fn kill_process_group(child: &mut Child) {
+    // The group has the id of the child that leads it.
+    match libc::pid_t::try_from(child.id()) {
+        // SAFETY: kill takes no pointers; a group that has already exited
+        // makes it fail with ESRCH and nothing else.
+        Ok(group) => unsafe {
+            libc::kill(-group, libc::SIGKILL);
+        },
+        Err(_) => {
+            let _ = child.kill();
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(child: &mut Child) {
+    let _ = child.kill();
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_with_timeout_collects_output() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo out; echo err >&2"]);
+        let output = output_with_timeout(&mut command, Duration::from_secs(10)).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[test]
+    fn output_with_timeout_stops_slow_commands() {
+        let mut command = Command::new("sleep");
+        command.arg("10");
+        let err = output_with_timeout(&mut command, Duration::from_millis(100)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn mirror_dir_links_the_entries_without_writing_to_the_source() {
+        let source = scratch_dir().unwrap();
+        fs::create_dir(source.join("figures")).unwrap();
+        fs::write(source.join("figures").join("plot.png"), "png").unwrap();
+        fs::write(source.join("chapter.typ"), "= Chapter").unwrap();
+        let dest = scratch_dir().unwrap();
+        mirror_dir(&source, &dest).unwrap();
+        fs::write(dest.join("main.typ"), "#include \"chapter.typ\"").unwrap();
+        assert_eq!(
+            fs::read_to_string(dest.join("chapter.typ")).unwrap(),
+            "= Chapter"
+        );
+        assert_eq!(
+            fs::read_to_string(dest.join("figures").join("plot.png")).unwrap(),
+            "png"
+        );
+        fs::remove_dir_all(&dest).unwrap();
+        let mut left: Vec<_> = fs::read_dir(&source)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["chapter.typ", "figures"]);
+        assert!(source.join("figures").join("plot.png").exists());
+        let _ = fs::remove_dir_all(&source);
+    }
+
+    #[test]
+    fn output_with_timeout_stops_what_the_command_started() {
+        let marker = std::env::temp_dir().join(format!("tylax-timeout-{}", std::process::id()));
+        let _ = fs::remove_file(&marker);
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(format!("(sleep 1; touch '{}') & wait", marker.display()));
+        let err = output_with_timeout(&mut command, Duration::from_millis(100)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        thread::sleep(Duration::from_millis(1500));
+        assert!(!marker.exists());
+    }
+}
@@ -1,12 +1,21 @@
 //! Error handling for Tylax conversions
 //!
 //! This module provides a unified error type and result type for all
-//! conversion operations.
+//! conversion operations, and [`TylaxError`], which any failure of the
+//! library converts into.
 
 use std::fmt;
 
+use serde::Serialize;
+use tylax_ir::Loss as IrLoss;
+
+use crate::core::typst2latex::engine::{EvalError, VfsError};
+use crate::utils::files::FileResolveError;
+use crate::utils::loss::LossRecord;
+
 /// Conversion error type
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum ConversionError {
     /// Parse error - input could not be parsed
     ParseError {
@@ -25,6 +34,8 @@ pub enum ConversionError {
     IoError { message: String },
     /// Internal error
     InternalError { message: String },
+    /// Strict mode: the conversion dropped content (error-severity losses)
+    LossyConversion { losses: Vec<LossRecord> },
 }
 
 impl fmt::Display for ConversionError {
@@ -62,6 +73,14 @@ impl fmt::Display for ConversionError {
             ConversionError::InternalError { message } => {
                 write!(f, "Internal error: {}", message)
             }
+            ConversionError::LossyConversion { losses } => {
+                write!(f, "Conversion dropped content ({} loss(es))", losses.len())?;
+                if let Some(first) = losses.first() {
+                    let kind = first.name.as_deref().unwrap_or("loss");
+                    write!(f, ": [{}] {}", kind, first.message)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -79,6 +98,48 @@ impl From<std::io::Error> for ConversionError {
 /// Result type for conversion operations
 pub type ConversionResult<T> = Result<T, ConversionError>;
 
+/// Any failure of the library: a conversion, an evaluation of Typst code,
+/// a file that could not be read, or content a frontend dropped. Each error
+/// type of the library converts into it, so one `?` handles them all.
+#[derive(Debug, Clone, thiserror::Error)]
+#[non_exhaustive]
+pub enum TylaxError {
+    #[error(transparent)]
+    Conversion(#[from] ConversionError),
+    #[error("Evaluation error: {0}")]
+    Eval(#[from] EvalError),
+    #[error("File error: {0}")]
+    Vfs(#[from] VfsError),
+    #[error("File error: {0}")]
+    File(#[from] FileResolveError),
+    /// Losses of a frontend, as it reported them.
+    #[error("{}", dropped_content(.0))]
+    Losses(Vec<IrLoss>),
+}
+
+impl From<std::io::Error> for TylaxError {
+    fn from(err: std::io::Error) -> Self {
+        TylaxError::Conversion(err.into())
+    }
+}
+
+impl From<Vec<IrLoss>> for TylaxError {
+    fn from(losses: Vec<IrLoss>) -> Self {
+        TylaxError::Losses(losses)
+    }
+}
+
+fn dropped_content(losses: &[IrLoss]) -> String {
+    let mut out = format!("Conversion dropped content ({} loss(es))", losses.len());
+    if let Some(first) = losses.first() {
+        out.push_str(&format!(": [{}] {}", first.kind, first.message));
+    }
+    out
+}
+
+/// Result type for any operation of the library
+pub type TylaxResult<T> = Result<T, TylaxError>;
+
 /// Conversion warnings (non-fatal issues)
 #[derive(Debug, Clone)]
 pub struct ConversionWarning {
@@ -106,7 +167,9 @@ impl fmt::Display for ConversionWarning {
 // =============================================================================
 
 /// Severity level for CLI diagnostics (determines coloring and behavior).
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
 pub enum DiagnosticSeverity {
     /// Critical errors (red) - e.g., undefined variables, division by zero
     Error,
@@ -277,6 +340,32 @@ mod tests {
         assert!(msg.contains("Consider"));
     }
 
+    #[test]
+    fn test_tylax_error_wraps_every_error() {
+        let err: TylaxError = ConversionError::parse("unexpected token").into();
+        assert_eq!(err.to_string(), "Parse error: unexpected token");
+
+        let err: TylaxError = EvalError::undefined("x").into();
+        assert_eq!(err.to_string(), "Evaluation error: undefined variable: x");
+
+        let vfs: TylaxError = VfsError::NotFound("a.typ".to_string()).into();
+        assert_eq!(vfs.to_string(), "File error: file not found: a.typ");
+        let file: TylaxError = FileResolveError::NotFound("a.tex".to_string()).into();
+        assert_eq!(file.to_string(), "File error: file not found: a.tex");
+
+        let io: TylaxError = std::io::Error::other("disk full").into();
+        assert!(matches!(
+            io,
+            TylaxError::Conversion(ConversionError::IoError { .. })
+        ));
+
+        let losses: TylaxError = vec![IrLoss::new("table", "cell dropped")].into();
+        assert_eq!(
+            losses.to_string(),
+            "Conversion dropped content (1 loss(es)): [table] cell dropped"
+        );
+    }
+
     #[test]
     fn test_conversion_output() {
         let output = ConversionOutput::new("hello".to_string());
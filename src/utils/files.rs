@@ -43,9 +43,9 @@ pub enum FileResolveError {
 impl std::fmt::Display for FileResolveError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            FileResolveError::NotFound(path) => write!(f, "File not found: {}", path),
-            FileResolveError::ReadError(msg) => write!(f, "Read error: {}", msg),
-            FileResolveError::NotSupported(msg) => write!(f, "Not supported: {}", msg),
+            FileResolveError::NotFound(path) => write!(f, "file not found: {}", path),
+            FileResolveError::ReadError(msg) => write!(f, "read error: {}", msg),
+            FileResolveError::NotSupported(msg) => write!(f, "not supported: {}", msg),
         }
     }
 }
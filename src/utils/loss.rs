@@ -1,12 +1,17 @@
 //! Loss reporting for non-deterministic or unsupported conversions.
 
+use std::collections::BTreeMap;
+
 use serde::Serialize;
-use tylax_ir::Loss as IrLoss;
+use tylax_ir::{Loss as IrLoss, Severity as IrSeverity};
+
+use crate::utils::error::{ConversionError, DiagnosticSeverity, TylaxResult};
 
 pub const LOSS_MARKER_PREFIX: &str = "tylax:loss:";
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
 pub enum LossKind {
     UnknownCommand,
     UnknownEnvironment,
@@ -16,6 +21,19 @@ pub enum LossKind {
     Other,
 }
 
+impl LossKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LossKind::UnknownCommand => "unknown-command",
+            LossKind::UnknownEnvironment => "unknown-environment",
+            LossKind::ParseError => "parse-error",
+            LossKind::UnsupportedFeature => "unsupported-feature",
+            LossKind::MacroExpansion => "macro-expansion",
+            LossKind::Other => "other",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct LossRecord {
     pub id: String,
@@ -24,6 +42,21 @@ pub struct LossRecord {
     pub message: String,
     pub snippet: Option<String>,
     pub context: Option<String>,
+    /// Whether content was dropped (error), approximated (warning) or only
+    /// noted (info).
+    pub severity: DiagnosticSeverity,
+    /// Byte range of the construct in the source document, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<SourceSpan>,
+    /// How the source could be written so the construct converts, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SourceSpan {
+    pub start: usize,
+    pub end: usize,
 }
 
 impl LossRecord {
@@ -42,17 +75,48 @@ impl LossRecord {
             message: message.into(),
             snippet,
             context,
+            severity: DiagnosticSeverity::Warning,
+            span: None,
+            suggestion: None,
         }
     }
 
-    pub fn from_ir_loss(id: String, loss: &IrLoss) -> Self {
+    /// The kind the loss is counted under: the IR loss kind for losses from
+    /// the IR pipeline, the [`LossKind`] otherwise.
+    pub fn kind_name(&self) -> &str {
+        match (&self.kind, &self.name) {
+            (LossKind::Other, Some(name)) => name,
+            (kind, _) => kind.as_str(),
+        }
+    }
+
+    /// Build a record from an IR loss; `source` is the text the loss spans
+    /// refer to and supplies the snippet.
+    pub fn from_ir_loss(id: String, loss: &IrLoss, source: &str) -> Self {
+        let span = loss.span.map(|span| SourceSpan {
+            start: span.start,
+            end: span.end,
+        });
+        let snippet = loss
+            .span
+            .and_then(|span| source.get(span.start..span.end))
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty());
         Self {
             id,
             kind: LossKind::Other,
             name: Some(loss.kind.clone()),
             message: loss.message.clone(),
-            snippet: None,
+            snippet,
             context: None,
+            severity: match loss.severity {
+                IrSeverity::Info => DiagnosticSeverity::Info,
+                IrSeverity::Warning => DiagnosticSeverity::Warning,
+                IrSeverity::Error => DiagnosticSeverity::Error,
+                _ => DiagnosticSeverity::Warning,
+            },
+            span,
+            suggestion: loss.suggestion.clone(),
         }
     }
 }
@@ -63,6 +127,11 @@ pub struct LossReport {
     pub target_lang: String,
     pub losses: Vec<LossRecord>,
     pub warnings: Vec<String>,
+    /// Structural elements of the source, what the fidelity score is
+    /// measured against: the blocks and losses of an IR conversion, or the
+    /// environments, formulas and commands the LaTeX converter parsed. Zero
+    /// when they were not counted.
+    pub nodes: usize,
 }
 
 impl LossReport {
@@ -77,12 +146,109 @@ impl LossReport {
             target_lang: target_lang.into(),
             losses,
             warnings,
+            nodes: 0,
         }
     }
 
+    pub fn with_nodes(mut self, nodes: usize) -> Self {
+        self.nodes = nodes;
+        self
+    }
+
     pub fn is_empty(&self) -> bool {
         self.losses.is_empty() && self.warnings.is_empty()
     }
+
+    /// Losses of error severity: content missing from the output.
+    pub fn errors(&self) -> impl Iterator<Item = &LossRecord> {
+        self.losses
+            .iter()
+            .filter(|loss| loss.severity == DiagnosticSeverity::Error)
+    }
+
+    /// How faithfully the document was converted. Each warning or error loss
+    /// counts as one structural element that did not convert; info losses
+    /// are only notes and count under their kind alone.
+    pub fn score(&self) -> FidelityScore {
+        let mut by_kind = BTreeMap::new();
+        let mut lossy_nodes = 0;
+        for loss in &self.losses {
+            *by_kind.entry(loss.kind_name().to_string()).or_insert(0) += 1;
+            if loss.severity != DiagnosticSeverity::Info {
+                lossy_nodes += 1;
+            }
+        }
+        let nodes = self.nodes.max(lossy_nodes);
+        let percent = if nodes == 0 {
+            100.0
+        } else {
+            100.0 * (nodes - lossy_nodes) as f64 / nodes as f64
+        };
+        FidelityScore {
+            percent,
+            nodes,
+            lossy_nodes,
+            by_kind,
+        }
+    }
+
+    /// The report as `--loss-log` writes it: pretty JSON with its fidelity
+    /// score under `score`, for tools to read rather than recompute.
+    pub fn to_loss_log(&self) -> serde_json::Result<String> {
+        #[derive(Serialize)]
+        struct LossLog<'a> {
+            #[serde(flatten)]
+            report: &'a LossReport,
+            score: FidelityScore,
+        }
+        serde_json::to_string_pretty(&LossLog {
+            report: self,
+            score: self.score(),
+        })
+    }
+
+    /// Mark each loss in `output` with a comment, for repair tools to find;
+    /// before `\end{document}` in a full document.
+    pub fn append_markers(&self, output: &mut String, full_document: bool) {
+        if self.losses.is_empty() {
+            return;
+        }
+        let mut marker_block = String::new();
+        marker_block.push('\n');
+        marker_block.push_str("% Tylax conversion losses\n");
+        for loss in &self.losses {
+            let name = loss
+                .name
+                .as_ref()
+                .map(|n| format!(" kind={}", n))
+                .unwrap_or_default();
+            let line = format!(
+                "% {}{}{} message={}\n",
+                LOSS_MARKER_PREFIX,
+                loss.id,
+                name,
+                loss.message.replace('\n', " ")
+            );
+            marker_block.push_str(&line);
+        }
+        if full_document {
+            if let Some(pos) = output.rfind("\\end{document}") {
+                output.insert_str(pos, &marker_block);
+                return;
+            }
+        }
+        output.push_str(&marker_block);
+    }
+
+    /// Strict mode: fail when any loss dropped content.
+    pub fn check_strict(&self) -> TylaxResult<()> {
+        let losses: Vec<LossRecord> = self.errors().cloned().collect();
+        if losses.is_empty() {
+            Ok(())
+        } else {
+            Err(ConversionError::LossyConversion { losses }.into())
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -95,4 +261,29 @@ impl ConversionReport {
     pub fn new(content: String, report: LossReport) -> Self {
         Self { content, report }
     }
+
+    /// How faithfully the document was converted; see [`LossReport::score`].
+    pub fn score(&self) -> FidelityScore {
+        self.report.score()
+    }
+}
+
+/// The share of a document converted without loss, from
+/// [`LossReport::score`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FidelityScore {
+    /// Percentage of structural elements converted without loss.
+    pub percent: f64,
+    pub nodes: usize,
+    pub lossy_nodes: usize,
+    /// Number of losses of each kind.
+    pub by_kind: BTreeMap<String, usize>,
+}
+
+impl FidelityScore {
+    /// Whether the output needs a look by hand: some content was dropped or
+    /// approximated.
+    pub fn needs_review(&self) -> bool {
+        self.lossy_nodes > 0
+    }
 }
@@ -4,22 +4,32 @@
 //! - Diagnostics and error reporting
 //! - File resolution for multi-file documents
 //! - Error types and result types
+//! - Compile checks of generated LaTeX and Typst
+//! - Verification of conversions by compiling their output
+//! - Copying of image assets next to the output
+//! - Export of table data as CSV or TSV
 
+pub mod assets;
+pub mod compile_check;
 pub mod diagnostics;
 pub mod error;
 pub mod files;
 pub mod latex_analysis;
 pub mod loss;
 pub mod repair;
+pub mod table_export;
 pub mod typst_analysis;
+pub mod verify;
 
 // Re-export commonly used items
+pub use assets::{AssetMode, AssetOptions, AssetReport, SvgTarget};
 pub use diagnostics::{check_latex, format_diagnostics, Diagnostic, DiagnosticLevel};
 pub use error::{ConversionError, ConversionOutput, ConversionResult, ConversionWarning};
 pub use files::{FileResolveError, FileResolver, MemoryFileResolver, NoopFileResolver};
 pub use latex_analysis::{lint_source as lint_latex_source, LatexMetrics};
 pub use loss::{ConversionReport, LossKind, LossRecord, LossReport, LOSS_MARKER_PREFIX};
 pub use repair::AiRepairConfig;
+pub use table_export::{TableExportOptions, TableExportReport, TableFormat};
 pub use typst_analysis::{lint_source as lint_typst_source, TypstIssue, TypstMetrics};
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -0,0 +1,180 @@
+//! Export the tables of a document as CSV or tab-separated files, one per
+//! table, for reviewing the data of a conversion or for documents whose
+//! tables are regenerated from data rather than kept as markup.
+//!
+//! A file is named after the label of its table, `tab:results` giving
+//! `tab-results.csv`, or after its place among the tables of the document,
+//! `table-3.csv`, when the table has none. A cell spanning several columns
+//! or rows is written in the first of them; the others are left empty.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tylax_ir::{tables, Document, Table};
+
+/// How the cells of an exported table are separated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TableFormat {
+    /// Comma-separated, with fields quoted as RFC 4180 has them.
+    #[default]
+    Csv,
+    /// Tab-separated; tabs and line breaks in a cell become spaces.
+    Tsv,
+}
+
+impl TableFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            TableFormat::Csv => "csv",
+            TableFormat::Tsv => "tsv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableExportOptions {
+    /// The directory the files are written to.
+    pub out_dir: PathBuf,
+    pub format: TableFormat,
+}
+
+impl TableExportOptions {
+    pub fn new(out_dir: impl Into<PathBuf>) -> Self {
+        TableExportOptions {
+            out_dir: out_dir.into(),
+            format: TableFormat::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableExportReport {
+    /// Files written to the output directory, relative to it, one per
+    /// table in document order.
+    pub written: Vec<PathBuf>,
+}
+
+/// Write each table of `doc` to a file of its own in the output directory.
+pub fn export_tables(
+    doc: &Document,
+    options: &TableExportOptions,
+) -> io::Result<TableExportReport> {
+    let mut report = TableExportReport::default();
+    let mut taken = HashSet::new();
+    for (index, table) in tables(doc).into_iter().enumerate() {
+        let target = file_name(table.label, index + 1, options.format, &mut taken);
+        let dest = options.out_dir.join(&target);
+        write_file(&dest, &table_to_delimited(table.table, options.format))?;
+        report.written.push(target);
+    }
+    Ok(report)
+}
+
+/// The cells of `table` in `format`, a line per row.
+pub fn table_to_delimited(table: &Table, format: TableFormat) -> String {
+    let mut out = String::new();
+    for row in table.text_rows() {
+        let fields: Vec<String> = row.iter().map(|cell| field(cell, format)).collect();
+        out.push_str(&fields.join(match format {
+            TableFormat::Csv => ",",
+            TableFormat::Tsv => "\t",
+        }));
+        out.push('\n');
+    }
+    out
+}
+
+fn field(text: &str, format: TableFormat) -> String {
+    match format {
+        TableFormat::Csv if text.contains([',', '"', '\n', '\r']) => {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        }
+        TableFormat::Csv => text.to_string(),
+        TableFormat::Tsv => text.replace(['\t', '\n', '\r'], " "),
+    }
+}
+
+/// The file a table is written to: its label with the characters file
+/// systems dislike replaced, or its number, without reusing a name in
+/// `taken`.
+fn file_name(
+    label: Option<&str>,
+    number: usize,
+    format: TableFormat,
+    taken: &mut HashSet<PathBuf>,
+) -> PathBuf {
+    let stem = label
+        .map(|label| {
+            label
+                .chars()
+                .map(|ch| {
+                    if ch.is_alphanumeric() || matches!(ch, '-' | '_' | '.') {
+                        ch
+                    } else {
+                        '-'
+                    }
+                })
+                .collect::<String>()
+        })
+        .map(|stem| stem.trim_matches(['-', '.']).to_string())
+        .filter(|stem| !stem.is_empty())
+        .unwrap_or_else(|| format!("table-{}", number));
+    let ext = format.extension();
+    let mut target = PathBuf::from(format!("{}.{}", stem, ext));
+    if taken.contains(&target) {
+        target = (2..)
+            .map(|n| PathBuf::from(format!("{}-{}.{}", stem, n, ext)))
+            .find(|candidate| !taken.contains(candidate))
+            .unwrap_or(target);
+    }
+    taken.insert(target.clone());
+    target
+}
+
+fn write_file(dest: &Path, content: &str) -> io::Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(dest, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(labels: &[Option<&str>]) -> Vec<String> {
+        let mut taken = HashSet::new();
+        labels
+            .iter()
+            .enumerate()
+            .map(|(index, label)| {
+                let name = file_name(*label, index + 1, TableFormat::Csv, &mut taken);
+                name.to_string_lossy().into_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn file_names_follow_labels() {
+        assert_eq!(
+            names(&[Some("tab:results"), None, Some("tab:results"), Some("::")]),
+            [
+                "tab-results.csv",
+                "table-2.csv",
+                "tab-results-2.csv",
+                "table-4.csv"
+            ]
+        );
+    }
+
+    #[test]
+    fn fields_are_quoted_as_the_format_needs() {
+        assert_eq!(field("plain", TableFormat::Csv), "plain");
+        assert_eq!(field("1,5", TableFormat::Csv), "\"1,5\"");
+        assert_eq!(field("say \"hi\"", TableFormat::Csv), "\"say \"\"hi\"\"\"");
+        assert_eq!(field("a\tb", TableFormat::Tsv), "a b");
+        assert_eq!(field("1,5", TableFormat::Tsv), "1,5");
+    }
+}
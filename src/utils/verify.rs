@@ -0,0 +1,135 @@
+//! Verify a conversion by compiling its output: LaTeX with latexmk or
+//! tectonic, Typst with the `typst` CLI. Compilers run with a time limit,
+//! and their errors are traced back to the source document where the IR
+//! source map covers them.
+
+use std::path::Path;
+
+use crate::ir_pipeline::typst_to_latex_ir_with_source_map;
+use crate::utils::compile_check::{
+    compile_latex, compile_typst, line_column, line_start_offset, typst_available, CompileOutcome,
+    TexEngine,
+};
+
+/// Preamble wrapped around fragment output so it can be compiled on its own.
+const FRAGMENT_PREAMBLE: &str =
+    "\\documentclass{article}\n\\usepackage{amsmath,amssymb,graphicx,hyperref}\n\\begin{document}\n";
+
+/// A compile error of the converted output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    pub message: String,
+    /// 1-based line in the converted output, when the compiler names one.
+    pub output_line: Option<usize>,
+    /// 1-based line and column in the source document the output line was
+    /// converted from, when known.
+    pub source_location: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    Passed {
+        compiler: &'static str,
+    },
+    Failed {
+        compiler: &'static str,
+        errors: Vec<VerifyError>,
+    },
+    /// No compiler for the output is installed, or it could not be run.
+    Skipped {
+        reason: String,
+    },
+}
+
+/// Compile LaTeX converted from the Typst `source`. Fragments are wrapped
+/// in a minimal article; `search_dir` resolves relative paths such as
+/// images. Errors are traced back to `source` when `latex` is what the IR
+/// pipeline makes of it.
+pub fn verify_latex(
+    latex: &str,
+    source: &str,
+    full_document: bool,
+    search_dir: Option<&Path>,
+) -> Verification {
+    let Some(engine) = TexEngine::detect() else {
+        return Verification::Skipped {
+            reason: "no TeX engine found (install latexmk or tectonic)".to_string(),
+        };
+    };
+    let (document, prefix_lines) = if full_document {
+        (latex.to_string(), 0)
+    } else {
+        (
+            format!("{}{}\n\\end{{document}}\n", FRAGMENT_PREAMBLE, latex),
+            FRAGMENT_PREAMBLE.lines().count(),
+        )
+    };
+    let outcome = match compile_latex(&document, engine, search_dir) {
+        Ok(outcome) => outcome,
+        Err(err) => {
+            return Verification::Skipped {
+                reason: format!("failed to run {}: {}", engine.name(), err),
+            }
+        }
+    };
+    // The map only describes the output if the IR pipeline produced it as-is.
+    let (mapped, source_map) = typst_to_latex_ir_with_source_map(source, full_document);
+    let source_map = (mapped == latex).then_some(source_map);
+    verification(engine.name(), outcome, |line| {
+        let line = line.checked_sub(prefix_lines).filter(|line| *line > 0)?;
+        let span = source_map
+            .as_ref()
+            .and_then(|map| line_start_offset(latex, line).and_then(|offset| map.lookup(offset)));
+        Some((line, span.map(|span| line_column(source, span.start))))
+    })
+}
+
+/// Compile Typst converted from LaTeX, set as an equation when it is
+/// `math`; `search_dir` resolves relative paths such as images. This
+/// direction has no source map, so errors carry their output line only.
+pub fn verify_typst(typst: &str, math: bool, search_dir: Option<&Path>) -> Verification {
+    if !typst_available() {
+        return Verification::Skipped {
+            reason: "the typst CLI was not found".to_string(),
+        };
+    }
+    let document = if math {
+        format!("$ {} $\n", typst)
+    } else {
+        typst.to_string()
+    };
+    match compile_typst(&document, search_dir) {
+        Ok(outcome) => verification("typst", outcome, |line| Some((line, None))),
+        Err(err) => Verification::Skipped {
+            reason: format!("failed to run typst: {}", err),
+        },
+    }
+}
+
+/// `locate` maps a line of the compiled file to the output line and the
+/// source location, or `None` for lines outside the output.
+fn verification(
+    compiler: &'static str,
+    outcome: CompileOutcome,
+    locate: impl Fn(usize) -> Option<(usize, Option<(usize, usize)>)>,
+) -> Verification {
+    if outcome.success {
+        return Verification::Passed { compiler };
+    }
+    let errors = outcome
+        .errors
+        .into_iter()
+        .map(|error| {
+            let (output_line, source_location) = match error.line.and_then(&locate) {
+                Some((line, location)) => (Some(line), location),
+                None => (None, None),
+            };
+            VerifyError {
+                message: error.message,
+                output_line,
+                source_location,
+            }
+        })
+        .collect();
+    Verification::Failed { compiler, errors }
+}
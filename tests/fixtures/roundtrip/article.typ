@@ -0,0 +1,19 @@
+= Introduction
+
+We study the sum of two squares and how often it is prime.
+
+$ x^2 + y^2 = p $
+
+== Method
+
+The search covers every pair below a bound.
+
+- pairs with $x < y$
+- pairs with $x = y$
+
+== Results
+
++ the first count
++ the second count
+
+Most primes of the form $4k + 1$ appear early.
@@ -0,0 +1,28 @@
+#import "@preview/lovelace:0.3.0": *
+
+#figure(
+  kind: "algorithm",
+  supplement: [Algorithm],
+  pseudocode-list(booktabs: true)[
+    + *input:* integers $a$ and $b$
+    + *while* $b != 0$ *do*
+      + *if* $a > b$ *then*
+        + $a <- a - b$
+      + *else*
+        + $b <- b - a$
+      + *end*
+    + *end*
+    + *return* $a$
+  ],
+  caption: [Euclid's algorithm],
+) <alg:euclid>
+
+#import "@preview/algo:0.3.3": algo, i, d, comment
+
+#algo(title: "Fib", parameters: ("n",))[
+  if $n < 0$:#i\
+    return null#d\
+  if $n = 0$ or $n = 1$:#i #comment[base case]\
+    return $n$#d\
+  return $"Fib"(n-1) + "Fib"(n-2)$
+]
@@ -0,0 +1,9 @@
+#columns(3)[
+  Opening text.
+
+  #figure(image("wide.png", width: 100%), caption: [Overview])
+
+  Middle text.
+  #colbreak()
+  Closing text.
+]
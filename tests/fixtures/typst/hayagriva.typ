@@ -0,0 +1,3 @@
+The fifth volume @harry came out in 2003.
+
+#bibliography("hayagriva.yml")
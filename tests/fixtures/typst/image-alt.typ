@@ -0,0 +1,4 @@
+#figure(
+  image("fig.png", width: 80%, alt: "Bar chart of runtimes, 100% at most"),
+  caption: [Runtimes.]
+)
@@ -0,0 +1,3 @@
+Relativity follows from two postulates @einstein1905.
+
+#bibliography("inline_bib.bib", style: "ieee")
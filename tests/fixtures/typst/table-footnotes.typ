@@ -0,0 +1,7 @@
+#table(
+  columns: 2,
+  [Alpha#footnote[Measured twice.]], [Beta#footnote[Estimated.]],
+  [Gamma#footnote[Measured twice.]], [Delta],
+)
+
+After the table.#footnote[Outside.]
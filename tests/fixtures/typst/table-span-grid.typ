@@ -0,0 +1,8 @@
+= Table Span Grid
+
+#table(
+  columns: 3,
+  table.cell(colspan: 2, rowspan: 2)[A], [B],
+  [C],
+  [D], [E], [F],
+)
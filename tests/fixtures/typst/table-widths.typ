@@ -0,0 +1,5 @@
+#table(
+  columns: (auto, 1fr, 2fr),
+  [Name], [Summary], [Details],
+  [Alpha], [Short note.], [A longer description that needs room to wrap.],
+)
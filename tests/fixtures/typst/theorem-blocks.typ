@@ -0,0 +1,11 @@
+#theorem(name: "Bound")[
+  For all $n$ we have
+
+  $ sum_(i=1)^n i = n(n+1)/2 $
+
+  and the cases
+  - even,
+  - odd.
+
+  Hence the claim.
+]
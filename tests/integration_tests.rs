@@ -1,8 +1,9 @@
 //! Integration tests for Tylax full document conversion
 
 use tylax::{
-    convert_auto, convert_auto_document, detect_format, latex_document_to_typst, latex_to_typst,
-    typst_to_latex, typst_to_latex_with_options, T2LOptions,
+    convert_auto, convert_auto_document, detect_format, latex_document_to_typst,
+    latex_document_to_typst_with_options, latex_to_typst, typst_to_latex,
+    typst_to_latex_with_options, L2TOptions, ReviewNotes, T2LOptions,
 };
 
 // ============================================================================
@@ -341,6 +342,118 @@ The formula $E = mc^2$ is famous.
             result
         );
     }
+
+    #[test]
+    fn test_tabular_newcolumntype_and_inter_column_material() {
+        let latex = r#"
+\documentclass{article}
+\usepackage{array}
+\newcolumntype{Y}{>{\centering\arraybackslash}p{2cm}}
+\newcolumntype{R}[1]{>{\raggedleft\arraybackslash}p{#1}}
+\begin{document}
+\begin{tabular}{@{}lY!{\quad}R{3cm}*{2}{c}@{}}
+  a & b & c & d & e \\
+\end{tabular}
+\end{document}
+"#;
+
+        let result = latex_document_to_typst(latex);
+        assert!(
+            result.contains("columns: (auto, auto, auto, auto, auto)"),
+            "Expected five columns, got: {}",
+            result
+        );
+        assert!(
+            result.contains("align: (left, center, right, center, center)"),
+            "Expected column alignments, got: {}",
+            result
+        );
+    }
+
+    fn heading_line(body: &str, marker: &str) -> String {
+        let latex = format!(
+            "\\documentclass{{article}}\n\\begin{{document}}\n{}\n\\end{{document}}\n",
+            body
+        );
+        let result = latex_document_to_typst(&latex);
+        result
+            .lines()
+            .find(|line| line.starts_with(marker))
+            .unwrap_or_else(|| panic!("Expected `{}` heading, got: {}", marker, result))
+            .to_string()
+    }
+
+    #[test]
+    fn test_section_title_with_math_commands() {
+        let latex = r"\section{The $O(n\log n)$ bound}";
+        let line = heading_line(latex, "= ");
+        assert!(line.starts_with("= The $O"), "got: {}", line);
+        assert!(line.ends_with("$ bound"), "got: {}", line);
+        assert!(line.contains("log"), "got: {}", line);
+        assert!(!line.contains('\\'), "got: {}", line);
+        assert_eq!(line.matches('$').count(), 2, "got: {}", line);
+    }
+
+    #[test]
+    fn test_section_title_with_sub_and_superscripts() {
+        let latex = r"\subsection{Bounding \(x_i^2\) for $i \in I$}";
+        let line = heading_line(latex, "== ");
+        assert!(line.contains("$x_i^2$"), "got: {}", line);
+        assert!(line.contains("$i in I$"), "got: {}", line);
+        assert_eq!(line.matches('$').count(), 4, "got: {}", line);
+    }
+
+    #[test]
+    fn test_section_title_with_math_and_text_commands() {
+        let latex = r"\section{Bounds on $\alpha$ via \emph{duality}}";
+        let line = heading_line(latex, "= ");
+        assert!(line.contains("$alpha$"), "got: {}", line);
+        assert!(line.contains("#emph[duality]"), "got: {}", line);
+    }
+
+    const SUBEQUATIONS: &str = r"\documentclass{article}
+\begin{document}
+\begin{subequations}\label{eq:maxwell}
+\begin{equation}\nabla \cdot E = 0 \label{eq:gauss}\end{equation}
+\begin{equation}\nabla \cdot B = 0 \label{eq:magnetism}\end{equation}
+\end{subequations}
+By \eqref{eq:maxwell}, and \eqref{eq:gauss} in particular.
+\end{document}
+";
+
+    #[test]
+    fn test_subequations_are_sub_numbered() {
+        let result = latex_document_to_typst(SUBEQUATIONS);
+        assert!(result.contains("#counter(math.equation).step()"), "{}", result);
+        assert!(
+            result.contains("numbering(\"(1a)\", parent, ..n.pos())"),
+            "{}",
+            result
+        );
+        assert!(result.contains("counter(math.equation).update(parent)"), "{}", result);
+        assert!(result.contains("<eq-gauss>"), "{}", result);
+        assert!(result.contains("<eq-magnetism>"), "{}", result);
+        assert!(result.contains("(@eq-gauss)"), "{}", result);
+    }
+
+    #[test]
+    fn test_subequations_label_names_the_group() {
+        let result = latex_document_to_typst(SUBEQUATIONS);
+        assert!(
+            result.contains("#metadata(\"subequations\") <eq-maxwell>"),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains(concat!(
+                "#link(<eq-maxwell>, context numbering(\"(1)\", ",
+                "..counter(math.equation).at(<eq-maxwell>)))"
+            )),
+            "{}",
+            result
+        );
+        assert!(!result.contains("@eq-maxwell"), "{}", result);
+    }
 }
 
 // ============================================================================
@@ -644,6 +757,48 @@ mod options {
         assert!(result.contains("\\author{Author Name}"));
         assert!(result.contains("\\maketitle"));
     }
+
+    const REVIEWED: &str = concat!(
+        "\\documentclass{article}\n\\begin{document}\n",
+        "Text.\\todo{Cite the survey} More\\marginpar{check}.\n",
+        "% TODO: rewrite this paragraph\n",
+        "% an ordinary comment\n",
+        "\\end{document}\n"
+    );
+
+    fn with_review_notes(review_notes: ReviewNotes) -> String {
+        let opts = L2TOptions {
+            review_notes,
+            ..Default::default()
+        };
+        latex_document_to_typst_with_options(REVIEWED, &opts)
+    }
+
+    #[test]
+    fn test_l2t_review_notes_as_comments() {
+        let result = with_review_notes(ReviewNotes::Comments);
+        assert!(result.contains("/* TODO: Cite the survey */"), "{}", result);
+        assert!(result.contains("/* TODO: check */"), "{}", result);
+        assert!(result.contains("/* TODO: rewrite this paragraph */"), "{}", result);
+        assert!(!result.contains("ordinary comment"), "{}", result);
+        assert!(!result.contains("#let todo"), "{}", result);
+    }
+
+    #[test]
+    fn test_l2t_review_notes_with_helper() {
+        let result = with_review_notes(ReviewNotes::Helper);
+        assert!(result.contains("#let todo(body)"), "{}", result);
+        assert!(result.contains("#todo[Cite the survey]"), "{}", result);
+        assert!(result.contains("#todo[check]"), "{}", result);
+        assert!(result.contains("#todo[rewrite this paragraph]"), "{}", result);
+    }
+
+    #[test]
+    fn test_l2t_review_notes_inline_by_default() {
+        let result = latex_document_to_typst(REVIEWED);
+        assert!(result.contains("#text(fill: red)[Cite the survey]"), "{}", result);
+        assert!(!result.contains("TODO"), "{}", result);
+    }
 }
 
 // ============================================================================
@@ -1914,3 +2069,48 @@ mod t2l_lr_delimiters {
         );
     }
 }
+
+// ============================================================================
+// Compile Check Tests
+// ============================================================================
+
+mod compile_check {
+    use tylax::utils::compile_check::{line_column, line_start_offset, parse_tex_log, TexError};
+
+    #[test]
+    fn test_parse_file_line_errors() {
+        let log = "./main.tex:12: Undefined control sequence.\nl.12 \\foo\n/usr/share/texmf/article.cls:40: Bad option\n";
+        let errors = parse_tex_log(log);
+        assert_eq!(
+            errors,
+            vec![
+                TexError {
+                    line: Some(12),
+                    message: "Undefined control sequence.".to_string(),
+                },
+                TexError {
+                    line: None,
+                    message: "Bad option".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_classic_errors() {
+        let log = "! Missing $ inserted.\n<inserted text>\n                $\nl.7 a_b\n\n! Emergency stop.\n";
+        let errors = parse_tex_log(log);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].line, Some(7));
+        assert_eq!(errors[0].message, "Missing $ inserted.");
+        assert_eq!(errors[1].line, None);
+    }
+
+    #[test]
+    fn test_line_helpers() {
+        let text = "a\nbb\nc";
+        assert_eq!(line_start_offset(text, 3), Some(5));
+        assert_eq!(line_start_offset(text, 4), None);
+        assert_eq!(line_column(text, 4), (2, 3));
+    }
+}
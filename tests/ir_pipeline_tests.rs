@@ -1,7 +1,19 @@
 use std::fs;
 use std::path::Path;
-
-use tylax::typst_to_latex_ir;
+use std::sync::Arc;
+
+use tylax::engine::MemoryVfs;
+use tylax::files::{FileResolver, MemoryFileResolver};
+use tylax::utils::assets::collect_assets;
+use tylax::{
+    export_tables, hayagriva_bibliographies_to_bibtex, ir_from_json, ir_to_json, ir_to_latex,
+    label_map_to_json, typst_to_ir, typst_to_latex_ir, typst_to_latex_ir_conversion,
+    typst_to_latex_ir_split, typst_to_latex_ir_with_assets,
+    typst_to_latex_ir_with_inline_bibliography, typst_to_latex_ir_with_label_rewrites,
+    typst_to_latex_ir_with_options, typst_to_latex_ir_with_vfs, AssetOptions, CitationPackage,
+    DocumentTemplate, EncodingMode, IncludeOptions, IncludeStrategy, LatexOutputOptions,
+    QuoteStyle, TableExportOptions, TableFormat,
+};
 
 fn read_fixture(path: &str) -> String {
     fs::read_to_string(Path::new(path)).expect("fixture missing")
@@ -163,6 +175,143 @@ fn ir_pipeline_theorem_label() {
     assert_eq!(normalize(&output), normalize(&expected));
 }
 
+#[test]
+fn ir_pipeline_label_keeps_following_text() {
+    let input = "#table(columns: 2, [A], [B]) <tab:a> See @tab:a.";
+    let output = typst_to_latex_ir(input, false);
+    let table_end = output.find("\\end{table}").expect("table rendered");
+    assert!(output.contains("\\label{tab:a}"));
+    assert!(output[table_end..].contains("See Table~\\ref{tab:a}."));
+}
+
+#[test]
+fn ir_pipeline_lists_in_cells_and_captions() {
+    let input = "#figure(table(columns: 2, [Steps], [+ mix\n+ bake]), caption: [Notes:\n- fast\n- cheap])";
+    let output = typst_to_latex_ir(input, false);
+    assert!(output.contains("\\begin{minipage}[t]{0.45\\linewidth}\\begin{enumerate}"));
+    assert!(output.contains("\\item bake"));
+    assert!(output.contains("\\caption{Notes: fast; cheap}"));
+}
+
+#[test]
+fn ir_pipeline_long_cells_use_paragraph_columns() {
+    let input = "#table(columns: 3, [Name], [Description], [Qty], [Widget], [A small widget used for holding other widgets together in a reliable way], [3])";
+    let output = typst_to_latex_ir(input, false);
+    assert!(output.contains("\\begin{tabular}{|c|p{0.68\\linewidth}|c|}"));
+
+    let short = "#table(columns: 2, [A], [B])";
+    assert!(typst_to_latex_ir(short, false).contains("\\begin{tabular}{|c|c|}"));
+}
+
+#[test]
+fn ir_pipeline_raw_block_theme_uses_listings() {
+    let input = "#show raw.where(block: true): set block(fill: luma(240), inset: 8pt)\n\n#raw(\"let x = 1;\", block: true)";
+    let output = typst_to_latex_ir(input, true);
+    assert!(output.contains("\\usepackage{listings}"));
+    assert!(output.contains("\\definecolor{tylaxCodeBackground}{HTML}{F0F0F0}"));
+    assert!(output.contains("backgroundcolor=\\color{tylaxCodeBackground}"));
+    assert!(output.contains("framesep=8pt"));
+    assert!(output.contains("\\begin{lstlisting}\nlet x = 1;\n\\end{lstlisting}"));
+
+    let plain = typst_to_latex_ir("#raw(\"x\", block: true)", true);
+    assert!(plain.contains("\\begin{verbatim}"));
+    assert!(!plain.contains("listings"));
+}
+
+#[test]
+fn ir_pipeline_latex_frontend_shares_ir() {
+    let input = "\\section{Intro}\nLet $\\alpha + 1$ hold.\n";
+    let (doc, report) = tylax::latex_to_ir_with_report(input);
+    assert_eq!(doc.blocks.len(), 2);
+    let blocks = format!("{:?}", doc.blocks);
+    assert!(blocks.contains("Heading"));
+    // Math is converted to Typst syntax, matching the Typst frontend.
+    assert!(blocks.contains("Math(\"alpha"));
+    assert!(!blocks.contains("\\\\alpha"));
+    assert_eq!(report.source_lang, "latex");
+    assert_eq!(report.target_lang, "typst");
+}
+
+#[test]
+fn ir_pipeline_latex_to_typst_goes_through_the_ir() {
+    let input = "\\section{Intro}\\label{sec:intro}\nLet $\\alpha + 1$ hold, see \\ref{sec:intro}.\n";
+    assert_eq!(
+        tylax::latex_to_typst_ir(input, false),
+        "= Intro <sec:intro>\n\nLet $alpha + 1$ hold, see @sec:intro."
+    );
+    let report = tylax::latex_to_typst_ir_with_report(input, true);
+    assert!(report.content.starts_with("#set heading(numbering: \"1.\")\n\n= Intro"));
+    assert_eq!(report.report.target_lang, "typst");
+}
+
+#[test]
+fn ir_pipeline_latex_frontend_keeps_named_environments() {
+    let input = "\\begin{problem}\nSolve it.\n\\end{problem}\n";
+    let doc = tylax::latex_to_ir_with_environments(input, &["problem".to_string()]);
+    assert!(
+        format!("{:?}", doc.blocks)
+            .starts_with("[Environment(EnvironmentBlock { name: \"problem\""),
+        "{:?}",
+        doc.blocks
+    );
+    let doc = tylax::latex_to_ir(input);
+    assert!(!format!("{:?}", doc.blocks).contains("Environment("));
+}
+
+#[test]
+fn ir_pipeline_strict_fails_only_on_dropped_content() {
+    // An unsupported set rule is a warning: strict mode still converts.
+    let input = "#set figure(caption: none)\n\nText.";
+    let output = tylax::typst_to_latex_ir_strict(input, false).expect("warnings pass");
+    assert_eq!(output, typst_to_latex_ir(input, false));
+
+    let nested = format!("{}x{}", "#box[".repeat(800), "]".repeat(800));
+    match tylax::typst_to_latex_ir_strict(&nested, false) {
+        Err(tylax::TylaxError::Conversion(tylax::ConversionError::LossyConversion { losses })) => {
+            assert!(losses
+                .iter()
+                .all(|loss| loss.severity == tylax::DiagnosticSeverity::Error));
+            assert_eq!(losses[0].name.as_deref(), Some("nesting"));
+        }
+        other => panic!("expected a lossy conversion error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn ir_pipeline_scores_conversion_fidelity() {
+    let clean = tylax::typst_to_latex_ir_with_report("= Intro\n\nText.", false).score();
+    assert!(clean.nodes > 0);
+    assert_eq!(clean.percent, 100.0);
+    assert!(!clean.needs_review());
+
+    let input = "#set figure(caption: none)\n\n#outline(target: image)\n\nText.\n\nMore.";
+    let score = tylax::typst_to_latex_ir_with_report(input, false).score();
+    // The outline and the two paragraphs, and the two losses.
+    assert_eq!(score.nodes, 5);
+    assert_eq!(score.lossy_nodes, 2);
+    assert_eq!(score.by_kind.get("set-rule"), Some(&1));
+    assert_eq!(score.by_kind.get("outline"), Some(&1));
+    assert!(score.percent < 100.0 && score.percent > 50.0);
+    assert!(score.needs_review());
+
+    let (_, report) = tylax::typst_to_latex_ir_and_report(input, false);
+    let log: serde_json::Value = serde_json::from_str(&report.to_loss_log().unwrap()).unwrap();
+    assert_eq!(log["score"]["lossy_nodes"], 2);
+    assert_eq!(log["score"]["by_kind"]["outline"], 1);
+    assert_eq!(log["losses"].as_array().map(Vec::len), Some(2));
+}
+
+#[test]
+fn ir_pipeline_source_map_points_back_to_typst() {
+    let input = "= Intro\n\nFirst paragraph.\n\nSecond paragraph.\n";
+    let (output, map) = tylax::typst_to_latex_ir_with_source_map(input, true);
+    assert_eq!(output, typst_to_latex_ir(input, true));
+    let offset = output.find("Second paragraph.").expect("paragraph in output");
+    let span = map.lookup(offset).expect("mapped block");
+    assert_eq!(&input[span.start..span.end], "Second paragraph.");
+    assert!(map.lookup(0).is_none(), "preamble is not mapped");
+}
+
 #[test]
 fn ir_pipeline_theorem_preamble() {
     let input = "#theorem[Sample theorem.]";
@@ -196,6 +345,64 @@ fn ir_pipeline_image() {
     assert_eq!(normalize(&output), normalize(&expected));
 }
 
+#[test]
+fn ir_pipeline_image_alt_text() {
+    let input = read_fixture("tests/fixtures/typst/image-alt.typ");
+    let expected = read_fixture("tests/fixtures/latex/image-alt.tex");
+    let output = typst_to_latex_ir(&input, false);
+    assert_eq!(normalize(&output), normalize(&expected));
+}
+
+#[test]
+fn ir_pipeline_data_driven_table() {
+    let mut vfs = MemoryVfs::new();
+    vfs.add_text_file("scores.csv", "Ada,12\nGrace_H,9\n");
+    let input =
+        "#table(columns: 2, table.header([Name], [Score]), ..csv(\"scores.csv\").flatten())";
+    let output = typst_to_latex_ir_with_vfs(input, false, Arc::new(vfs));
+    assert!(output.contains("Ada & 12 \\\\\n"), "{output}");
+    assert!(output.contains("Grace\\_H & 9 \\\\\n"), "{output}");
+
+    // Without the file the header is all that is left.
+    let output = typst_to_latex_ir_with_vfs(input, false, Arc::new(MemoryVfs::new()));
+    assert!(!output.contains("Ada"), "{output}");
+}
+
+#[test]
+fn ir_pipeline_data_root_reads_tables_from_disk() {
+    let root = std::env::temp_dir().join(format!("tylax-data-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("scores.csv"), "Ada,12\n").unwrap();
+    let input = "#table(columns: 2, ..csv(\"scores.csv\").flatten())";
+    let options = LatexOutputOptions {
+        data_root: Some(root.clone()),
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options(input, false, &options);
+    let _ = fs::remove_dir_all(&root);
+    assert!(output.contains("Ada & 12 \\\\\n"), "{output}");
+}
+
+#[test]
+fn ir_pipeline_spreads_let_bound_cells() {
+    let input = "#let cells = range(4).map(n => str(n * 2))\n\n\
+                 #table(columns: 2, ..cells)\n\n#grid(columns: 2, ..cells)\n";
+    let output = typst_to_latex_ir(input, false);
+    assert_eq!(output.matches("0 & 2 \\\\\n").count(), 2, "{output}");
+    assert_eq!(output.matches("4 & 6").count(), 2, "{output}");
+}
+
+#[test]
+fn ir_pipeline_spreads_only_see_earlier_definitions() {
+    let input = "#let cells = ([a], [b])\n\n#table(columns: 2, ..cells)\n\n\
+                 #let cells = ([c], [d])\n\n#table(columns: 2, ..cells)\n\n\
+                 #table(columns: 1, ..later)\n\n#let later = ([Late],)\n";
+    let output = typst_to_latex_ir(input, false);
+    assert!(output.contains("a & b \\\\\n"), "{output}");
+    assert!(output.contains("c & d \\\\\n"), "{output}");
+    assert!(!output.contains("Late"), "{output}");
+}
+
 #[test]
 fn ir_pipeline_refs() {
     let input = read_fixture("tests/fixtures/typst/refs.typ");
@@ -204,6 +411,27 @@ fn ir_pipeline_refs() {
     assert_eq!(normalize(&output), normalize(&expected));
 }
 
+#[test]
+fn ir_pipeline_rewrites_label_prefixes_and_maps_them() {
+    let input = "= Intro <sec:intro>\n\nSee @fig:plot.\n\n\
+                 #figure(image(\"a.png\"), caption: [Plot]) <fig:plot>\n";
+    let rewrites = vec![("fig:".to_string(), "fig-".to_string())];
+    let (output, labels) = typst_to_latex_ir_with_label_rewrites(input, false, &rewrites);
+    assert!(output.contains("See Fig.~\\ref{fig-plot}."), "{output}");
+    assert!(output.contains("\\label{fig-plot}"), "{output}");
+    assert!(output.contains("\\label{sec:intro}"), "{output}");
+    assert_eq!(
+        labels,
+        vec![
+            ("fig:plot".to_string(), "fig-plot".to_string()),
+            ("sec:intro".to_string(), "sec:intro".to_string()),
+        ]
+    );
+    let json: serde_json::Value =
+        serde_json::from_str(&label_map_to_json(&labels).unwrap()).unwrap();
+    assert_eq!(json["fig:plot"], "fig-plot");
+}
+
 #[test]
 fn ir_pipeline_cite() {
     let input = read_fixture("tests/fixtures/typst/cite.typ");
@@ -305,20 +533,70 @@ fn ir_pipeline_preamble_hints() {
     let input = r##"
 #let primary = rgb("#1e40af")
 #set page(paper: "us-letter", margin: (x: 1in, y: 2in))
-#set text(font: "New Computer Modern", size: 11pt)
+#set text(font: "New Computer Modern", size: 11pt, lang: "de")
 #set par(justify: false, leading: 0.55em, first-line-indent: 1.5em)
 
 Hello world.
 "##;
     let output = typst_to_latex_ir(input, true);
     assert!(
-        output.contains("\\documentclass[11pt,letterpaper]{article}")
-            || output.contains("\\documentclass[letterpaper,11pt]{article}")
+        output.contains("\\documentclass[11pt]{article}"),
+        "{output}"
     );
-    assert!(output.contains("\\usepackage[left=1in,right=1in,top=2in,bottom=2in]{geometry}"));
+    assert!(output
+        .contains("\\usepackage[letterpaper,top=2in,bottom=2in,left=1in,right=1in]{geometry}"));
+    assert!(output.contains("\\usepackage[ngerman]{babel}"), "{output}");
     assert!(output.contains("\\setlength{\\parindent}{1.5em}"));
     assert!(output.contains("\\definecolor{primary}{HTML}{1E40AF}"));
-    assert!(output.contains("\\raggedright"));
+    assert!(
+        output.contains("\\usepackage[document]{ragged2e}"),
+        "{output}"
+    );
+}
+
+#[test]
+fn ir_pipeline_alternating_page_header() {
+    let input = r##"
+#set page(
+  header: context {
+    if calc.odd(here().page()) [#h(1fr) #hydra(1)] else [#hydra(2) #h(1fr) _Notes_]
+  },
+  footer: context [#h(1fr) #counter(page).display() #h(1fr)],
+)
+
+= Intro
+Hello.
+"##;
+    let output = typst_to_latex_ir(input, true);
+    assert!(output.contains("\\documentclass[twoside]{article}"));
+    assert!(output.contains("\\usepackage{fancyhdr}"));
+    assert!(output.contains("\\fancyhead[RO]{\\leftmark{}}"));
+    assert!(output.contains("\\fancyhead[LE]{\\rightmark{}}"));
+    assert!(output.contains("\\fancyhead[RE]{\\textit{Notes}}"));
+    assert!(output.contains("\\fancyfoot[C]{\\arabic{page}}"));
+    assert!(output.contains("\\renewcommand{\\sectionmark}[1]{\\markboth{#1}{}}"));
+}
+
+#[test]
+fn ir_pipeline_column_wrappers_set_two_columns() {
+    let input = "#show: doc => columns(2, doc)\n\n= Intro\nHello.\n";
+    let output = typst_to_latex_ir(input, true);
+    assert!(output.contains("\\documentclass[twocolumn]{article}"), "{}", output);
+    assert!(!output.contains("\\begin{multicols}"), "{}", output);
+
+    let output = typst_to_latex_ir("#show: columns.with(2, gutter: 1em)\n\nHello.\n", true);
+    assert!(output.contains("\\documentclass[twocolumn]{article}"), "{}", output);
+}
+
+#[test]
+fn ir_pipeline_page_header_unrecognized_keeps_default_style() {
+    let input = r##"
+#set page(header: context { let h = query(heading); h.first().body })
+
+Hello.
+"##;
+    let output = typst_to_latex_ir(input, true);
+    assert!(!output.contains("fancyhdr"));
 }
 
 #[test]
@@ -378,6 +656,7 @@ Hello world.
     assert!(output.contains("\\author{Jane Doe}"));
     assert!(output.contains("\\frontmatter"));
     assert!(output.contains("\\mainmatter"));
+    assert!(output.contains("\\chapter{Chapter}"));
 }
 
 #[test]
@@ -464,6 +743,14 @@ fn ir_pipeline_table_rowspan() {
     assert_eq!(normalize(&output), normalize(&expected));
 }
 
+#[test]
+fn ir_pipeline_table_span_grid() {
+    let input = read_fixture("tests/fixtures/typst/table-span-grid.typ");
+    let expected = read_fixture("tests/fixtures/latex/table-span-grid.tex");
+    let output = typst_to_latex_ir(&input, false);
+    assert_eq!(normalize(&output), normalize(&expected));
+}
+
 #[test]
 fn ir_pipeline_box() {
     let input = read_fixture("tests/fixtures/typst/box.typ");
@@ -488,6 +775,46 @@ fn ir_pipeline_columns() {
     assert_eq!(normalize(&output), normalize(&expected));
 }
 
+#[test]
+fn ir_pipeline_columns_split_around_figures() {
+    let input = read_fixture("tests/fixtures/typst/columns-span.typ");
+    let expected = read_fixture("tests/fixtures/latex/columns-span.tex");
+    let output = typst_to_latex_ir(&input, false);
+    assert_eq!(normalize(&output), normalize(&expected));
+}
+
+#[test]
+fn ir_pipeline_algorithm() {
+    let input = read_fixture("tests/fixtures/typst/algorithm.typ");
+    let expected = read_fixture("tests/fixtures/latex/algorithm.tex");
+    let output = typst_to_latex_ir(&input, false);
+    assert_eq!(normalize(&output), normalize(&expected));
+}
+
+#[test]
+fn ir_pipeline_theorem_body_blocks() {
+    let input = read_fixture("tests/fixtures/typst/theorem-blocks.typ");
+    let expected = read_fixture("tests/fixtures/latex/theorem-blocks.tex");
+    let output = typst_to_latex_ir(&input, false);
+    assert_eq!(normalize(&output), normalize(&expected));
+}
+
+#[test]
+fn ir_pipeline_table_footnotes() {
+    let input = read_fixture("tests/fixtures/typst/table-footnotes.typ");
+    let expected = read_fixture("tests/fixtures/latex/table-footnotes.tex");
+    let output = typst_to_latex_ir(&input, false);
+    assert_eq!(normalize(&output), normalize(&expected));
+}
+
+#[test]
+fn ir_pipeline_table_column_widths() {
+    let input = read_fixture("tests/fixtures/typst/table-widths.typ");
+    let expected = read_fixture("tests/fixtures/latex/table-widths.tex");
+    let output = typst_to_latex_ir(&input, false);
+    assert_eq!(normalize(&output), normalize(&expected));
+}
+
 #[test]
 fn ir_pipeline_grid() {
     let input = read_fixture("tests/fixtures/typst/grid.typ");
@@ -519,3 +846,653 @@ fn ir_pipeline_preprocess_logic() {
     let output = typst_to_latex_ir(&input, false);
     assert_eq!(normalize(&output), normalize(&expected));
 }
+
+#[test]
+fn ir_pipeline_inline_bibliography() {
+    let input = read_fixture("tests/fixtures/typst/inline_bib.typ");
+    let output = typst_to_latex_ir_with_inline_bibliography(
+        &input,
+        false,
+        Path::new("tests/fixtures/typst"),
+    );
+    assert!(!output.contains("\\bibliography{"));
+    assert!(output.contains("\\begin{thebibliography}{1}"));
+    assert!(output.contains(
+        "\\bibitem{einstein1905}\nA. Einstein, ``Zur Elektrodynamik bewegter K{\\\"o}rper,'' \
+         \\emph{Annalen der Physik}, vol.~17, no.~10, pp.~891--921, 1905.\n"
+    ));
+    assert!(!output.contains("uncited"));
+
+    let missing = typst_to_latex_ir_with_inline_bibliography(&input, false, Path::new("nowhere"));
+    assert!(missing.contains("\\bibliography{inline_bib}"));
+}
+
+#[test]
+fn ir_pipeline_hayagriva_bibliography() {
+    let input = read_fixture("tests/fixtures/typst/hayagriva.typ");
+    let dir = Path::new("tests/fixtures/typst");
+    assert!(typst_to_latex_ir(&input, false).contains("\\bibliography{hayagriva}"));
+
+    let converted = hayagriva_bibliographies_to_bibtex(&input, dir);
+    assert_eq!(converted.len(), 1);
+    assert_eq!(converted[0].0, Path::new("hayagriva.bib"));
+    assert!(converted[0]
+        .1
+        .starts_with("@book{harry,\n  author = {Rowling, J. K.},\n"));
+
+    let inline = typst_to_latex_ir_with_inline_bibliography(&input, false, dir);
+    assert!(inline.contains("\\bibitem{harry}\nJ. K. Rowling.\n"));
+
+    let escaping = "#bibliography(\"../typst/hayagriva.yml\")";
+    assert!(hayagriva_bibliographies_to_bibtex(escaping, dir).is_empty());
+}
+
+#[test]
+fn ir_pipeline_json_round_trip() {
+    let input = read_fixture("tests/fixtures/typst/table.typ");
+    let doc = typst_to_ir(&input);
+    let json = ir_to_json(&doc).expect("serializable IR");
+    let restored = ir_from_json(&json).expect("valid IR JSON");
+    assert_eq!(restored, doc);
+    assert_eq!(ir_to_latex(&restored, false), ir_to_latex(&doc, false));
+
+    let minimal = ir_from_json(r#"{"blocks": [{"Paragraph": [{"Text": "Hi"}]}]}"#).unwrap();
+    assert_eq!(ir_to_latex(&minimal, false).trim(), "Hi");
+    assert!(ir_from_json(r#"{"blocks": [{"Nope": 1}]}"#).is_err());
+}
+
+#[test]
+fn ir_pipeline_converts_included_files() {
+    let mut files = MemoryFileResolver::new();
+    files.add_file("chapters/intro.typ", "= Intro\nHello.");
+    let files: Arc<dyn FileResolver> = Arc::new(files);
+    let input = "Before.\n#include \"chapters/intro.typ\"";
+
+    let convert = |strategy| {
+        let options = LatexOutputOptions {
+            includes: Some(IncludeOptions::new(files.clone(), strategy)),
+            ..LatexOutputOptions::default()
+        };
+        typst_to_latex_ir_conversion(input, false, &options).output
+    };
+    let inline = convert(IncludeStrategy::Inline);
+    assert!(inline.contains("\\section{Intro}\n\nHello."), "{}", inline);
+
+    let input_only = convert(IncludeStrategy::Input);
+    assert!(input_only.contains("\\input{chapters/intro}"), "{}", input_only);
+    assert!(!input_only.contains("Hello."));
+
+    // Without include options, includes are dropped.
+    assert!(!typst_to_latex_ir(input, false).contains("Intro"));
+}
+
+#[test]
+fn ir_pipeline_splits_output_by_heading() {
+    let input = "Preface.\n\n= Intro <sec:intro>\nSee @sec:method.\n\n\
+        = Method <sec:method>\nBack to @sec:intro.";
+    let split = typst_to_latex_ir_split(input, true, None, 1);
+    let names: Vec<&str> = split.parts.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["01-intro", "02-method"]);
+    assert!(split.parts[0].1.contains("\\label{sec:intro}"), "{:?}", split.parts);
+    assert!(split.parts[0].1.contains("\\ref{sec:method}"), "{:?}", split.parts);
+    assert!(split.parts[1].1.contains("\\ref{sec:intro}"), "{:?}", split.parts);
+    assert!(split.main.contains("Preface."), "{}", split.main);
+    assert!(
+        split.main.contains("\\input{01-intro}\n\\input{02-method}\n\\end{document}"),
+        "{}",
+        split.main
+    );
+
+    let book = typst_to_latex_ir_split(input, true, Some(DocumentTemplate::Book), 1);
+    assert!(book.main.contains("\\documentclass"), "{}", book.main);
+    assert!(book.parts[0].1.starts_with("\\chapter{Intro}"), "{:?}", book.parts);
+}
+
+#[test]
+fn ir_pipeline_splits_the_output_after_the_other_options() {
+    let input = "#set figure(caption: none)\n\n= Intro <sec:intro>\nSee @sec:method.\n\n\
+        = Method <sec:method>\nBack to @sec:intro.";
+    let options = LatexOutputOptions {
+        label_rewrites: vec![("sec:".to_string(), "sec-".to_string())],
+        split_level: Some(1),
+        ..LatexOutputOptions::default()
+    };
+    let conversion = typst_to_latex_ir_conversion(input, true, &options);
+    let rewritten = ("sec:intro".to_string(), "sec-intro".to_string());
+    assert!(conversion.labels.contains(&rewritten));
+    let mut output = conversion.output.clone();
+    conversion.report.append_markers(&mut output, true);
+    let split = conversion.split(&output);
+    let (main, parts) = (&split.main, &split.parts);
+    assert_eq!(parts.len(), 2, "{main}");
+    assert!(parts[0].1.contains("\\label{sec-intro}"), "{parts:?}");
+    assert!(parts[1].1.contains("\\ref{sec-intro}"), "{parts:?}");
+    // The loss markers stay in the main file, before its end.
+    assert!(main.contains("% Tylax conversion losses"), "{main}");
+    assert!(main.ends_with("\\end{document}\n"), "{main}");
+
+    // An output changed in its body no longer matches the parts.
+    let edited = output.replacen("See", "Compare", 1);
+    let whole = conversion.split(&edited);
+    assert!(whole.parts.is_empty());
+    assert_eq!(whole.main, edited);
+}
+
+#[test]
+fn ir_pipeline_copies_images_next_to_the_output() {
+    let root = std::env::temp_dir().join(format!("tylax-assets-{}", std::process::id()));
+    let (source_dir, out_dir) = (root.join("doc"), root.join("out"));
+    fs::create_dir_all(source_dir.join("figs")).unwrap();
+    fs::create_dir_all(root.join("shared")).unwrap();
+    fs::write(source_dir.join("figs/plot.png"), b"png").unwrap();
+    fs::write(root.join("shared/logo.png"), b"logo").unwrap();
+
+    let input = "#figure(image(\"figs/plot.png\"), caption: [Plot])\n\n\
+        #figure(image(\"../shared/logo.png\"), caption: [Logo])\n\n\
+        #figure(image(\"missing.png\"), caption: [Missing])";
+    let (out, report) =
+        typst_to_latex_ir_with_assets(input, false, &AssetOptions::new(&source_dir, &out_dir));
+    assert!(out.contains("\\includegraphics{figs/plot.png}"), "{}", out);
+    assert!(out.contains("\\includegraphics{assets/logo.png}"), "{}", out);
+    assert_eq!(report.missing, ["missing.png"]);
+    assert_eq!(fs::read(out_dir.join("figs/plot.png")).unwrap(), b"png");
+    assert_eq!(fs::read(out_dir.join("assets/logo.png")).unwrap(), b"logo");
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn ir_pipeline_copies_images_with_the_other_output_options() {
+    let root = std::env::temp_dir().join(format!("tylax-assets-split-{}", std::process::id()));
+    let (source_dir, out_dir) = (root.join("doc"), root.join("out"));
+    fs::create_dir_all(&source_dir).unwrap();
+    fs::write(source_dir.join("plot.png"), b"png").unwrap();
+
+    let input = "= Intro\n\n#figure(image(\"plot.png\"), caption: [Plot]) <fig:plot>\n\n\
+        = Method\n\nSee @fig:plot.";
+    let options = LatexOutputOptions {
+        template: Some(DocumentTemplate::Report),
+        label_rewrites: vec![("fig:".to_string(), "fig-".to_string())],
+        split_level: Some(1),
+        assets: Some(AssetOptions::new(&source_dir, &out_dir)),
+        ..LatexOutputOptions::default()
+    };
+    let conversion = typst_to_latex_ir_conversion(input, true, &options);
+    assert_eq!(conversion.assets.written, [Path::new("plot.png")]);
+    let split = conversion.split(&conversion.output);
+    let (main, intro) = (&split.main, &split.parts[0].1);
+    assert!(intro.contains("\\includegraphics{plot.png}"), "{intro}");
+    assert!(intro.contains("\\label{fig-plot}"), "{intro}");
+    assert!(main.starts_with("\\documentclass{report}"), "{main}");
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn tables_are_exported_one_file_each() {
+    let out_dir = std::env::temp_dir().join(format!("tylax-tables-{}", std::process::id()));
+    let input = "#figure(table(columns: 2, [*Name*], [Score], [Smith, J.], [$1.5$], \
+        table.cell(colspan: 2)[Total]), caption: [Results]) <tab:results>\n\n\
+        #table(columns: 2, [a], [b])";
+    let doc = typst_to_ir(input);
+
+    let report = export_tables(&doc, &TableExportOptions::new(&out_dir)).unwrap();
+    assert_eq!(report.written, [Path::new("tab-results.csv"), Path::new("table-2.csv")]);
+    assert_eq!(
+        fs::read_to_string(out_dir.join("tab-results.csv")).unwrap(),
+        "Name,Score\n\"Smith, J.\",1.5\nTotal,\n"
+    );
+    let tsv = TableExportOptions {
+        format: TableFormat::Tsv,
+        ..TableExportOptions::new(&out_dir)
+    };
+    export_tables(&doc, &tsv).unwrap();
+    assert_eq!(fs::read_to_string(out_dir.join("table-2.tsv")).unwrap(), "a\tb\n");
+    let _ = fs::remove_dir_all(&out_dir);
+}
+
+#[test]
+fn images_that_cannot_be_converted_are_copied_and_noted() {
+    let root = std::env::temp_dir().join(format!("tylax-convert-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    // Neither decodes, whatever features tylax is built with.
+    fs::write(root.join("anim.gif"), b"GIF89a").unwrap();
+    fs::write(root.join("plot.svg"), b"<svg").unwrap();
+
+    let input = "#figure(image(\"anim.gif\"))\n\n#figure(image(\"plot.svg\"))";
+    let mut doc = typst_to_ir(input);
+    let out_dir = root.join("out");
+    let report = collect_assets(&mut doc, &AssetOptions::new(&root, &out_dir));
+    assert!(out_dir.join("anim.gif").is_file());
+    assert!(out_dir.join("plot.svg").is_file());
+    let messages: Vec<&str> = doc
+        .losses
+        .iter()
+        .filter(|loss| loss.kind == "image")
+        .map(|loss| loss.message.as_str())
+        .collect();
+    assert_eq!(messages.len(), 2, "{:?}", doc.losses);
+    assert!(messages[0].ends_with("the output includes anim.png"), "{:?}", messages);
+    assert!(messages[1].ends_with("the output includes plot.pdf"), "{:?}", messages);
+    assert_eq!(report.warnings.len(), 2);
+    let out = ir_to_latex(&doc, false);
+    assert!(out.contains("\\includegraphics{anim.png}"), "{}", out);
+    assert!(out.contains("\\includegraphics{plot.pdf}"), "{}", out);
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn ir_pipeline_notes_images_included_as_another_format() {
+    let input = "#image(\"plot.svg\")\n\n#image(\"plot.svg\")\n\n#image(\"photo.jpg\")";
+    let options = LatexOutputOptions::default();
+    let (output, report) = typst_to_latex_ir_with_options(input, false, &options);
+    assert!(output.contains("\\includegraphics{plot.pdf}"), "{output}");
+    let messages: Vec<&str> = report.losses.iter().map(|loss| &*loss.message).collect();
+    assert_eq!(
+        messages,
+        ["plot.svg is included as plot.pdf, which pdflatex reads"]
+    );
+}
+
+#[test]
+fn ir_pipeline_sets_math_in_cells_and_footnotes_inline() {
+    let input = "Text#footnote[$a &= b \\ c &= d$]\n\n\
+                 #table(columns: 2, [$ x \\ y $], [$x &= 1$],\n\
+                 [$mat(1, 2)$], [$a \\\\ b \\ c$])\n";
+    let out = ir_to_latex(&typst_to_ir(input), false);
+    // Rows stay apart, and a single row loses the alignment points that
+    // would end the cell.
+    assert!(
+        out.contains("\\footnote{$\\begin{aligned}a &= b \\\\ c &= d\\end{aligned}$}"),
+        "{out}"
+    );
+    assert!(
+        out.contains("$\\begin{gathered}x \\\\ y\\end{gathered}$"),
+        "{out}"
+    );
+    assert!(out.contains("$x = 1$"), "{out}");
+    assert!(
+        out.contains("$\\begin{pmatrix}1 & 2\\end{pmatrix}$"),
+        "{out}"
+    );
+    // An escaped backslash is a set difference, not a row break.
+    assert!(
+        out.contains("$\\begin{gathered}a \\setminus{} b \\\\ c\\end{gathered}$"),
+        "{out}"
+    );
+}
+
+#[test]
+fn ir_pipeline_reports_references_to_undefined_labels() {
+    let input = "= Intro <sec:intro>\n\nSee @sec:intro and @sec:missing.";
+    let (_, report) = typst_to_latex_ir_with_options(input, false, &LatexOutputOptions::default());
+    let loss = &report.losses[0];
+    assert_eq!(report.losses.len(), 1);
+    assert_eq!(loss.name.as_deref(), Some("reference"));
+    assert_eq!(loss.message, "reference to undefined label `sec:missing`");
+}
+
+#[test]
+fn ir_pipeline_loads_the_packages_the_content_needs() {
+    let subfigures = concat!(
+        "#figure(grid(columns: 2,\n",
+        "  figure(image(\"a.png\"), caption: [A]),\n",
+        "  figure(image(\"b.png\"), caption: [B]),\n",
+        "), caption: [Both])",
+    );
+    let output = typst_to_latex_ir(subfigures, true);
+    assert!(output.contains("\\usepackage{subcaption}\n"), "{}", output);
+    assert!(output.contains("\\begin{subfigure}"), "{}", output);
+
+    let wrapped = "#wrap-content(figure(image(\"a.png\"), caption: [A]), [Text beside it.])";
+    let output = typst_to_latex_ir(wrapped, true);
+    assert!(output.contains("\\usepackage{wrapfig}\n"), "{}", output);
+    assert!(output.contains("\\begin{wrapfigure}"), "{}", output);
+
+    let unnumbered = "#figure(image(\"a.png\"), caption: [A], numbering: none)";
+    let output = typst_to_latex_ir(unnumbered, true);
+    assert!(output.contains("\\usepackage{caption}\n"), "{}", output);
+    assert!(output.contains("\\caption*{A}"), "{}", output);
+
+    let styled = concat!(
+        "#figure(image(\"b.png\"),\n",
+        "  caption: figure.caption(position: top, separator: [ | ])[B])",
+    );
+    let output = typst_to_latex_ir(styled, true);
+    assert!(output.contains("\\usepackage{caption}\n"), "{}", output);
+    assert!(
+        output.contains(concat!(
+            "\\DeclareCaptionLabelSeparator{tylax}{ | }\n",
+            "\\captionsetup{position=top,labelsep=tylax}\n",
+            "\\caption{B}",
+        )),
+        "{}",
+        output
+    );
+
+    let units = concat!(
+        "#import \"@preview/unify:0.7.1\": qty\n",
+        "#import \"@preview/whalogen:0.2.0\": ce\n",
+        "#qty(\"3\", \"m\") of #ce(\"H2O\")",
+    );
+    let output = typst_to_latex_ir(units, true);
+    assert!(output.contains("\\usepackage{siunitx}\n"), "{}", output);
+    assert!(output.contains("\\usepackage[version=4]{mhchem}\n"), "{}", output);
+
+    let algorithm = concat!(
+        "#import \"@preview/algo:0.3.3\": algo, i, d, comment\n",
+        "#algo(title: \"Sum\", parameters: (\"xs\",))[\n",
+        "  output: the sum\n",
+        "  $s <- 0$ #comment[start empty]\n",
+        "]",
+    );
+    let output = typst_to_latex_ir(algorithm, true);
+    assert!(
+        output.contains("\\usepackage{algorithm}\n\\usepackage{algpseudocode}\n"),
+        "{}",
+        output
+    );
+    assert!(output.contains("\\Statex \\textbf{Output:} the sum\n"), "{}", output);
+    assert!(output.contains("0$ \\Comment{start empty}\n"), "{}", output);
+
+    let output = typst_to_latex_ir("#underline[kept] and #strike[struck]", true);
+    assert!(output.contains("\\usepackage[normalem]{ulem}\n"), "{}", output);
+    assert!(output.contains("\\uline{kept} and \\sout{struck}"), "{}", output);
+
+    let listed = concat!(
+        "#outline(target: figure.where(kind: raw))\n\n",
+        "#figure(raw(\"x = 1\", lang: \"py\", block: true), caption: [Setup]) <lst:setup>",
+    );
+    let output = typst_to_latex_ir(listed, true);
+    assert!(output.contains("\\usepackage{listings}\n"), "{}", output);
+    assert!(
+        output.contains("\\begin{lstlisting}[language={Python}, caption={Setup}, captionpos=b, label={lst:setup}]"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn ir_pipeline_encoding_follows_the_font_or_the_option() {
+    let input = "#set text(font: \"Libertinus Serif\")\nCafé";
+    // A system font needs XeLaTeX or LuaLaTeX, so the text is kept as written.
+    let output = typst_to_latex_ir(input, true);
+    assert!(output.contains("\\usepackage{fontspec}\n"), "{}", output);
+    assert!(output.contains("\\setmainfont{Libertinus Serif}\n"), "{}", output);
+    assert!(!output.contains("\\ifPDFTeX"), "{}", output);
+    assert!(output.contains("Café"), "{}", output);
+
+    let options = LatexOutputOptions {
+        encoding_mode: Some(EncodingMode::Transliterate),
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options(input, true, &options);
+    assert!(output.contains("\\usepackage[T1]{fontenc}\n"), "{}", output);
+    assert!(output.contains("\\ifPDFTeX\n"), "{}", output);
+    assert!(output.contains("Caf\\'{e}"), "{}", output);
+
+    let options = LatexOutputOptions {
+        encoding_mode: Some(EncodingMode::Unicode),
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options("Café", true, &options);
+    assert!(output.contains("\\usepackage{fontspec}\n"), "{}", output);
+    assert!(!output.contains("fontenc"), "{}", output);
+    assert!(output.contains("Café"), "{}", output);
+}
+
+#[test]
+fn ir_pipeline_text_pdflatex_cannot_set_needs_a_unicode_engine() {
+    let input = "Café in 東京";
+    // Without a choice from the caller the output is for a Unicode engine.
+    let (output, report) =
+        typst_to_latex_ir_with_options(input, true, &LatexOutputOptions::default());
+    assert!(output.contains("\\usepackage{fontspec}\n"), "{}", output);
+    assert!(output.contains("Café in 東京"), "{}", output);
+    assert!(report.losses.is_empty(), "{:?}", report.losses);
+
+    // pdflatex output keeps the script as written and reports it.
+    let options = LatexOutputOptions {
+        encoding_mode: Some(EncodingMode::Transliterate),
+        ..LatexOutputOptions::default()
+    };
+    let (output, report) = typst_to_latex_ir_with_options(input, true, &options);
+    assert!(output.contains("\\usepackage[T1]{fontenc}\n"), "{}", output);
+    assert!(output.contains("Caf\\'{e} in 東京"), "{}", output);
+    assert_eq!(report.losses.len(), 1);
+    assert_eq!(report.losses[0].name.as_deref(), Some("encoding"));
+    assert_eq!(
+        report.losses[0].message,
+        "pdflatex cannot set '東' (U+6771), which is kept as written"
+    );
+}
+
+#[test]
+fn template_adapters_load_the_packages_the_content_needs() {
+    let input = concat!(
+        "#import \"@preview/charged-ieee:0.1.0\": ieee\n",
+        "#show: ieee.with(title: [T])\n",
+        "#figure(grid(columns: 2,\n",
+        "  figure(image(\"a.png\"), caption: [A]),\n",
+        "  figure(image(\"b.png\"), caption: [B]),\n",
+        "), caption: [Both])\n",
+        "#algo(title: \"Euclid\")[if $b = 0$]\n",
+    );
+    let output = typst_to_latex_ir(input, true);
+    let preamble = &output[..output.find("\\begin{document}").expect("full document")];
+    assert!(preamble.contains("{IEEEtran}"), "{}", output);
+    assert!(preamble.contains("\\usepackage{subcaption}\n"), "{}", output);
+    assert!(preamble.contains("\\usepackage{algpseudocode}\n"), "{}", output);
+    // algorithmic defines the same environment as algpseudocode.
+    assert!(!preamble.contains("{algorithmic}"), "{}", output);
+}
+
+#[test]
+fn ir_pipeline_sets_quotes_as_asked() {
+    let options = LatexOutputOptions {
+        quote_style: QuoteStyle::Csquotes,
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options("A \"quoted\" word.", true, &options);
+    assert!(output.contains("\\usepackage{csquotes}\n"), "{}", output);
+    assert!(output.contains("A \\enquote{quoted} word."), "{}", output);
+
+    let options = LatexOutputOptions {
+        quote_style: QuoteStyle::Tex,
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options("A \"quoted\" word.", false, &options);
+    assert_eq!(output.trim(), "A ``quoted'' word.");
+}
+
+#[test]
+fn ir_pipeline_sets_emphasized_math_in_the_formula_when_asked() {
+    let input = "*$x^2$* and _$v$_";
+    let (output, _) = typst_to_latex_ir_with_options(input, false, &LatexOutputOptions::default());
+    assert_eq!(output.trim(), "\\textbf{$x^2$} and \\textit{$v$}");
+
+    let options = LatexOutputOptions {
+        math_emphasis: true,
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options(input, false, &options);
+    assert_eq!(output.trim(), "$\\boldsymbol{x^2}$ and $\\mathit{v}$");
+}
+
+#[test]
+fn ir_pipeline_sets_the_toc_depth_when_asked() {
+    let input = "#outline()\n= Intro\n== Setup";
+    let (output, _) = typst_to_latex_ir_with_options(input, true, &LatexOutputOptions::default());
+    assert!(!output.contains("tocdepth"), "{output}");
+
+    let options = LatexOutputOptions {
+        toc_depth: Some(1),
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options(input, true, &options);
+    assert!(
+        output.contains("\\setcounter{tocdepth}{1}\n\\tableofcontents"),
+        "{output}"
+    );
+}
+
+#[test]
+fn ir_pipeline_sets_cleveref_references_when_asked() {
+    let input = "= Intro <sec:intro>\nAs in @sec:intro, e.g. @sec:intro.";
+    let options = LatexOutputOptions {
+        cleveref: true,
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options(input, true, &options);
+    let cleveref = output.find("\\usepackage{cleveref}\n").expect(&output);
+    assert!(output
+        .find("\\usepackage{hyperref}")
+        .is_some_and(|hyperref| hyperref < cleveref));
+    assert!(
+        output.contains("As in \\cref{sec:intro}, e.g. \\cref{sec:intro}."),
+        "{}",
+        output
+    );
+
+    let (output, _) = typst_to_latex_ir_with_options(input, true, &LatexOutputOptions::default());
+    assert!(!output.contains("cleveref"), "{}", output);
+}
+
+#[test]
+fn ir_pipeline_sets_the_template_with_the_other_output_options() {
+    let input = "= Intro <sec:intro>\nAs in @sec:intro.";
+    let article = LatexOutputOptions {
+        template: Some(DocumentTemplate::Article),
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options(input, true, &article);
+    assert_eq!(output, typst_to_latex_ir(input, true));
+
+    let report = LatexOutputOptions {
+        template: Some(DocumentTemplate::Report),
+        cleveref: true,
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options(input, true, &report);
+    assert!(output.starts_with("\\documentclass{report}\n"), "{output}");
+    assert!(output.contains("\\chapter{Intro}"), "{output}");
+    assert!(output.contains("As in \\cref{sec:intro}."), "{output}");
+
+    // A template the caller names takes the place of the adapter.
+    let ieee = "#import \"@preview/charged-ieee:0.1.0\": ieee\n\
+        #show: ieee.with(title: [A Paper])\n\nHello.";
+    let (output, _) = typst_to_latex_ir_with_options(ieee, true, &article);
+    assert!(output.starts_with("\\documentclass{article}\n"), "{output}");
+}
+
+#[test]
+fn ir_pipeline_sets_ref_ranges_with_cleveref_when_asked() {
+    let input = "#figure(rect(), caption: [A]) <fig:a>\n#figure(rect(), caption: [C]) <fig:c>\nSee #refrange(<fig:a>, <fig:c>).";
+    let options = LatexOutputOptions {
+        cleveref: true,
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options(input, false, &options);
+    assert!(
+        output.contains("See \\crefrange{fig:a}{fig:c}."),
+        "{}",
+        output
+    );
+
+    let (output, _) = typst_to_latex_ir_with_options(input, false, &LatexOutputOptions::default());
+    assert!(
+        output.contains("See Fig.~\\ref{fig:a}--\\ref{fig:c}."),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn ir_pipeline_sets_long_tables_when_asked() {
+    let input = "#table(columns: 2, [a], [b], [c], [d], [e], [f])";
+    let options = LatexOutputOptions {
+        long_table_rows: Some(2),
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options(input, true, &options);
+    assert!(output.contains("\\usepackage{longtable}\n"), "{}", output);
+    assert!(output.contains("\\begin{longtable}"), "{}", output);
+
+    let (output, _) = typst_to_latex_ir_with_options(input, true, &LatexOutputOptions::default());
+    assert!(!output.contains("longtable"), "{}", output);
+}
+
+#[test]
+fn ir_pipeline_sets_fraction_columns_as_tabularx_when_asked() {
+    let input = "#table(columns: (1fr, 2cm), [a], [b])";
+    let options = LatexOutputOptions {
+        tabularx: true,
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options(input, true, &options);
+    assert!(output.contains("\\usepackage{tabularx}\n"), "{}", output);
+    assert!(
+        output.contains("\\begin{tabularx}{\\linewidth}{|X|p{2cm}|}"),
+        "{}",
+        output
+    );
+
+    let (output, _) = typst_to_latex_ir_with_options(input, true, &LatexOutputOptions::default());
+    assert!(
+        output.contains("\\begin{tabular}{|p{\\dimexpr 0.80\\linewidth-56.91pt\\relax}|p{2cm}|}"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn ir_pipeline_sets_citations_with_biblatex_when_asked() {
+    let input = "#set cite(form: \"prose\")\nAs @knuth shows.\n#bibliography(\"refs.bib\")";
+    let options = LatexOutputOptions {
+        citation_package: CitationPackage::Biblatex,
+        ..LatexOutputOptions::default()
+    };
+    let (output, _) = typst_to_latex_ir_with_options(input, true, &options);
+    assert!(
+        output.contains("\\usepackage[style=numeric]{biblatex}\n\\addbibresource{refs.bib}\n"),
+        "{}",
+        output
+    );
+    assert!(!output.contains("natbib"), "{}", output);
+    assert!(output.contains("As \\textcite{knuth} shows."), "{}", output);
+    assert!(output.contains("\\printbibliography"), "{}", output);
+
+    let (output, _) = typst_to_latex_ir_with_options(input, true, &LatexOutputOptions::default());
+    assert!(output.contains("\\usepackage{natbib}\n"), "{}", output);
+    assert!(output.contains("As \\citet{knuth} shows."), "{}", output);
+}
+
+#[test]
+fn ir_pipeline_sets_a_leading_abstract_section_as_the_abstract() {
+    let input = "= Abstract\nWe study it.\n= Intro\nText.";
+    let output = typst_to_latex_ir(input, true);
+    assert!(
+        output.contains("\\begin{abstract}\nWe study it.\n\\end{abstract}\n\n\\section{Intro}"),
+        "{}",
+        output
+    );
+
+    let body = typst_to_latex_ir(input, false);
+    assert!(!body.contains("\\begin{abstract}"), "{}", body);
+}
+
+#[test]
+fn ir_pipeline_sets_the_front_matter_of_a_template_closure() {
+    let input = concat!(
+        "#show: doc => conf(title: [A Study], authors: ((name: \"Ann\"),),\n",
+        "  abstract: [We study it.], doc)\n",
+        "Text.",
+    );
+    let output = typst_to_latex_ir(input, true);
+    assert!(
+        output.contains(concat!(
+            "\\begin{document}\n\n\\title{A Study}\n\\author{Ann}\n\\date{}\n\\maketitle\n\n",
+            "\\begin{abstract}\nWe study it.\n\\end{abstract}\n\nText.",
+        )),
+        "{}",
+        output
+    );
+}
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::Path;
+
+use proptest::prelude::*;
+use tylax::roundtrip::{compare, round_trip, Language};
+use tylax::typst_to_ir;
+
+#[test]
+fn compare_reports_structural_differences() {
+    let original = typst_to_ir("= Intro\n\nSome text.\n\n$ x^2 $\n");
+    assert!(compare(&original, &original.clone()).is_empty());
+
+    let dropped = typst_to_ir("= Intro\n\nSome text.\n");
+    let differences = compare(&original, &dropped);
+    let what: Vec<&str> = differences.iter().map(|d| d.what.as_str()).collect();
+    assert_eq!(what, ["equations", "block 3"]);
+    assert_eq!(differences[1].original, "equation");
+    assert_eq!(differences[1].round_tripped, "nothing");
+}
+
+#[test]
+fn round_trips_go_through_the_other_language() {
+    let trip = round_trip("= Intro\n\nSome text.\n", Language::Typst);
+    assert!(trip.converted.contains("\\section{Intro}"), "{}", trip.converted);
+    assert!(trip.back.contains("= Intro"), "{}", trip.back);
+
+    let trip = round_trip("\\section{Intro}\nSome text.\n", Language::Latex);
+    assert!(trip.converted.contains("= Intro"), "{}", trip.converted);
+    assert!(trip.back.contains("\\section{Intro}"), "{}", trip.back);
+}
+
+#[test]
+fn corpus_documents_survive_the_round_trip() {
+    let dir = Path::new("tests/fixtures/roundtrip");
+    let mut checked = 0;
+    for entry in fs::read_dir(dir).expect("read round-trip corpus") {
+        let path = entry.expect("read entry").path();
+        let language = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("typ") => Language::Typst,
+            Some("tex") => Language::Latex,
+            _ => continue,
+        };
+        let source = fs::read_to_string(&path).expect("read corpus document");
+        let trip = round_trip(&source, language);
+        assert!(
+            trip.is_faithful(),
+            "{}: {:?}",
+            path.display(),
+            trip.differences
+        );
+        checked += 1;
+    }
+    assert!(checked > 0, "no documents in {}", dir.display());
+}
+
+fn words(max: usize) -> impl Strategy<Value = String> {
+    prop::collection::vec("[a-z]{1,8}", 1..max).prop_map(|words| words.join(" "))
+}
+
+/// A top-level block of Typst markup the round trip should keep: a
+/// heading, a paragraph, an equation or a list.
+fn block() -> impl Strategy<Value = String> {
+    prop_oneof![
+        (1..=3usize, words(4)).prop_map(|(level, title)| "=".repeat(level) + " " + &title),
+        words(12).prop_map(|text| format!("{}.", text)),
+        ("[a-z]", "[a-z]").prop_map(|(a, b)| format!("$ {}^2 + {} $", a, b)),
+        prop::collection::vec(words(4), 1..4).prop_map(|items| {
+            items
+                .iter()
+                .map(|item| format!("- {}", item))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn generated_documents_survive_the_round_trip(blocks in prop::collection::vec(block(), 1..8)) {
+        // Lists a blank line parts are one list in Typst.
+        let adjacent_lists = blocks
+            .windows(2)
+            .any(|pair| pair.iter().all(|block| block.starts_with("- ")));
+        prop_assume!(!adjacent_lists);
+        let source = format!("{}\n", blocks.join("\n\n"));
+        let trip = round_trip(&source, Language::Typst);
+        prop_assert!(trip.is_faithful(), "{}\n{:?}", source, trip.differences);
+    }
+}
@@ -0,0 +1,333 @@
+#!/usr/bin/env python3
+"""
+Convert public Typst example documents to LaTeX and track their fidelity
+over time.
+
+Each run fetches the repositories named in the gallery list at the commit
+the list pins, converts every entrypoint with the IR pipeline, stores the outputs and loss logs under a
+run directory, appends the scores to history.jsonl and rewrites
+dashboard.md with the latest scores next to those of the previous run.
+With --check the run fails when a document converts worse than before.
+--pin writes the commit each repository is at into the list, for entries
+that pin none yet.
+"""
+
+from __future__ import annotations
+
+import argparse
+import json
+import os
+import subprocess
+from datetime import datetime, timezone
+from pathlib import Path
+
+
+UNPINNED = "-"
+
+
+def read_list(path: str) -> list[dict]:
+    entries = []
+    with open(path, "r", encoding="utf-8") as f:
+        for line in f:
+            line = line.strip()
+            if not line or line.startswith("#"):
+                continue
+            parts = line.split()
+            if len(parts) != 4:
+                raise SystemExit(f"expected `name repository commit entrypoint`: {line}")
+            name, repo, commit, entrypoint = parts
+            entries.append(
+                {
+                    "name": name,
+                    "repo": repo,
+                    "commit": None if commit == UNPINNED else commit,
+                    "entrypoint": entrypoint,
+                }
+            )
+    return entries
+
+
+def pin_list(path: str, commits: dict[str, str | None]) -> None:
+    """Write the commit each repository was fetched at into the list, for
+    the entries that pin none."""
+    lines = []
+    with open(path, "r", encoding="utf-8") as f:
+        for line in f:
+            parts = line.split()
+            if (
+                len(parts) == 4
+                and not line.startswith("#")
+                and parts[2] == UNPINNED
+                and commits.get(parts[1])
+            ):
+                parts[2] = commits[parts[1]]
+                line = " ".join(parts) + "\n"
+            lines.append(line)
+    with open(path, "w", encoding="utf-8") as f:
+        f.writelines(lines)
+
+
+def repo_dir(cache_dir: str, repo: str) -> str:
+    name = repo.rstrip("/").removesuffix(".git")
+    owner, project = name.split("/")[-2:]
+    return os.path.join(cache_dir, f"{owner}-{project}")
+
+
+def fetch_repo(repo: str, dest: str, commit: str | None, offline: bool) -> str | None:
+    """Fetch `repo` into `dest` and check out `commit`, or the latest commit
+    when none is pinned; returns the commit checked out."""
+    if not os.path.isdir(os.path.join(dest, ".git")):
+        if offline:
+            return None
+        print(f"[clone] {repo} -> {dest}")
+        subprocess.run(["git", "init", "-q", dest], check=True)
+        subprocess.run(["git", "remote", "add", "origin", repo], cwd=dest, check=True)
+    if not offline:
+        proc = subprocess.run(
+            ["git", "fetch", "--depth", "1", "-q", "origin", commit or "HEAD"], cwd=dest
+        )
+        if proc.returncode != 0:
+            print(f"[warn] {dest} could not be updated; using it as is.")
+        else:
+            subprocess.run(["git", "checkout", "-q", "--detach", "FETCH_HEAD"], cwd=dest)
+    elif commit:
+        subprocess.run(["git", "checkout", "-q", "--detach", commit], cwd=dest)
+    proc = subprocess.run(
+        ["git", "rev-parse", "HEAD"], cwd=dest, stdout=subprocess.PIPE, text=True
+    )
+    return proc.stdout.strip() or None
+
+
+def tylax_commit() -> str | None:
+    proc = subprocess.run(
+        ["git", "rev-parse", "--short", "HEAD"],
+        stdout=subprocess.PIPE,
+        stderr=subprocess.DEVNULL,
+        text=True,
+    )
+    return proc.stdout.strip() or None
+
+
+def score(loss_log: str) -> dict:
+    """The fidelity score t2l wrote into the loss log."""
+    try:
+        with open(loss_log, "r", encoding="utf-8") as f:
+            score = json.load(f)["score"]
+    except (OSError, ValueError, KeyError):
+        return {"percent": None, "nodes": 0, "lossy_nodes": 0, "by_kind": {}}
+    return {
+        "percent": round(score["percent"], 2),
+        "nodes": score["nodes"],
+        "lossy_nodes": score["lossy_nodes"],
+        "by_kind": score["by_kind"],
+    }
+
+
+def convert(entry: dict, source: str, run_dir: str, args) -> dict:
+    out_dir = os.path.join(run_dir, entry["name"])
+    os.makedirs(out_dir, exist_ok=True)
+    out_tex = os.path.join(out_dir, "out.tex")
+    loss_log = os.path.join(out_dir, "loss.json")
+    cmd = [
+        os.path.abspath(args.t2l_bin),
+        source,
+        "--full-document",
+        "--direction",
+        "t2l",
+        "--ir",
+        "--quiet",
+        "--output",
+        out_tex,
+        "--loss-log",
+        loss_log,
+    ]
+    if args.verify:
+        cmd.append("--verify")
+    with open(os.path.join(out_dir, "run.log"), "w", encoding="utf-8") as log:
+        log.write("$ " + " ".join(cmd) + "\n")
+        try:
+            proc = subprocess.run(
+                cmd,
+                cwd=os.path.dirname(source),
+                stdout=subprocess.DEVNULL,
+                stderr=subprocess.PIPE,
+                text=True,
+                timeout=args.timeout,
+            )
+            exit_code = proc.returncode
+            log.write(proc.stderr or "")
+        except subprocess.TimeoutExpired:
+            exit_code = 124
+            log.write("ERROR: timeout\n")
+        log.write(f"\n[exit={exit_code}]\n")
+    result = {"name": entry["name"], "exit_code": exit_code}
+    result.update(score(loss_log))
+    return result
+
+
+def read_history(path: str) -> list[dict]:
+    if not os.path.exists(path):
+        return []
+    with open(path, "r", encoding="utf-8") as f:
+        return [json.loads(line) for line in f if line.strip()]
+
+
+def regressions(previous: dict | None, current: dict) -> list[str]:
+    """Documents that convert worse than in the previous run."""
+    if previous is None:
+        return []
+    before = {doc["name"]: doc for doc in previous["documents"]}
+    found = []
+    for doc in current["documents"]:
+        old = before.get(doc["name"])
+        if old is None:
+            continue
+        if old["exit_code"] == 0 and doc["exit_code"] != 0:
+            found.append(f"{doc['name']}: conversion now fails (exit {doc['exit_code']})")
+        elif (
+            old["percent"] is not None
+            and doc["percent"] is not None
+            and doc["percent"] < old["percent"]
+        ):
+            found.append(
+                f"{doc['name']}: fidelity {old['percent']:.2f}% -> {doc['percent']:.2f}%"
+            )
+    return found
+
+
+def write_dashboard(path: str, history: list[dict]) -> None:
+    current = history[-1]
+    previous = history[-2] if len(history) > 1 else None
+    before = {doc["name"]: doc for doc in previous["documents"]} if previous else {}
+    lines = [
+        "# Typst gallery fidelity",
+        "",
+        f"Run {current['run']} at tylax {current['tylax'] or 'unknown'}"
+        + (f", compared with run {previous['run']}." if previous else "."),
+        "",
+        "| Document | Exit | Fidelity | Change | Lossy nodes | Top losses |",
+        "| --- | --- | --- | --- | --- | --- |",
+    ]
+    for doc in current["documents"]:
+        percent = doc["percent"]
+        old = before.get(doc["name"], {}).get("percent")
+        change = (
+            f"{percent - old:+.2f}" if percent is not None and old is not None else ""
+        )
+        top = sorted(doc["by_kind"].items(), key=lambda kv: (-kv[1], kv[0]))[:3]
+        lines.append(
+            "| {} | {} | {} | {} | {}/{} | {} |".format(
+                doc["name"],
+                doc["exit_code"],
+                "-" if percent is None else f"{percent:.2f}%",
+                change,
+                doc["lossy_nodes"],
+                doc["nodes"],
+                ", ".join(f"{kind} ({count})" for kind, count in top),
+            )
+        )
+    lines += ["", "## History", "", "| Run | tylax | Mean fidelity | Failures |"]
+    lines.append("| --- | --- | --- | --- |")
+    for run in reversed(history[-20:]):
+        scores = [d["percent"] for d in run["documents"] if d["percent"] is not None]
+        mean = f"{sum(scores) / len(scores):.2f}%" if scores else "-"
+        failures = sum(1 for d in run["documents"] if d["exit_code"] != 0)
+        lines.append(f"| {run['run']} | {run['tylax'] or '-'} | {mean} | {failures} |")
+    with open(path, "w", encoding="utf-8") as f:
+        f.write("\n".join(lines) + "\n")
+
+
+def main() -> int:
+    parser = argparse.ArgumentParser(
+        description="Convert the Typst gallery and track its fidelity."
+    )
+    parser.add_argument("--list", default="tools/typst_gallery_list.txt")
+    parser.add_argument("--cache-dir", default="target/typst-gallery/src")
+    parser.add_argument("--out-dir", default="target/typst-gallery")
+    parser.add_argument("--t2l-bin", default="target/release/t2l")
+    parser.add_argument("--timeout", type=int, default=120)
+    parser.add_argument(
+        "--only", default="", help="Comma-separated document names to convert."
+    )
+    parser.add_argument(
+        "--offline",
+        action="store_true",
+        help="Use the repositories already fetched without updating them.",
+    )
+    parser.add_argument(
+        "--pin",
+        action="store_true",
+        help="Write the commit of each repository into the list where it pins none.",
+    )
+    parser.add_argument(
+        "--verify",
+        action="store_true",
+        help="Also compile each output with latexmk or tectonic.",
+    )
+    parser.add_argument(
+        "--check",
+        action="store_true",
+        help="Exit with 1 when a document converts worse than in the last run.",
+    )
+    args = parser.parse_args()
+
+    if not os.path.exists(args.t2l_bin):
+        raise SystemExit(f"t2l binary not found: {args.t2l_bin}")
+
+    entries = read_list(args.list)
+    only = {name.strip() for name in args.only.split(",") if name.strip()}
+    if only:
+        entries = [e for e in entries if e["name"] in only]
+
+    os.makedirs(args.cache_dir, exist_ok=True)
+    stamp = datetime.now(timezone.utc).strftime("%Y%m%dT%H%M%SZ")
+    run_dir = os.path.abspath(os.path.join(args.out_dir, "runs", stamp))
+    os.makedirs(run_dir, exist_ok=True)
+
+    commits: dict[str, str | None] = {}
+    documents = []
+    for entry in entries:
+        dest = repo_dir(args.cache_dir, entry["repo"])
+        if entry["repo"] not in commits:
+            commits[entry["repo"]] = fetch_repo(
+                entry["repo"], dest, entry["commit"], args.offline
+            )
+        source = Path(dest, entry["entrypoint"]).resolve()
+        if not source.is_file():
+            print(f"[skip] {entry['name']}: {source} not found")
+            continue
+        result = convert(entry, str(source), run_dir, args)
+        result["commit"] = commits[entry["repo"]]
+        percent = result["percent"]
+        print(
+            f"[{entry['name']}] exit={result['exit_code']} fidelity="
+            + ("-" if percent is None else f"{percent:.2f}%")
+        )
+        documents.append(result)
+
+    if args.pin:
+        pin_list(args.list, commits)
+    unpinned = sorted({e["repo"] for e in entries if e["commit"] is None})
+    for repo in unpinned:
+        print(f"[warn] {repo} pins no commit; run with --pin to record it")
+
+    history_path = os.path.join(args.out_dir, "history.jsonl")
+    history = read_history(history_path)
+    run = {"run": stamp, "tylax": tylax_commit(), "documents": documents}
+    with open(os.path.join(run_dir, "summary.json"), "w", encoding="utf-8") as f:
+        json.dump(run, f, indent=2)
+    with open(history_path, "a", encoding="utf-8") as f:
+        f.write(json.dumps(run) + "\n")
+    history.append(run)
+    dashboard = os.path.join(args.out_dir, "dashboard.md")
+    write_dashboard(dashboard, history)
+    print(f"Dashboard written to {dashboard}")
+
+    found = regressions(history[-2] if len(history) > 1 else None, run)
+    for regression in found:
+        print(f"[regression] {regression}")
+    return 1 if args.check and found else 0
+
+
+if __name__ == "__main__":
+    raise SystemExit(main())